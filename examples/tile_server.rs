@@ -0,0 +1,145 @@
+//! A minimal HTTP server for the tiles of a (Cloud-Optimized) GeoTIFF.
+//!
+//! Reads go through a [`CachingReader`], so that repeated requests for the same tiles don't read
+//! them from storage again.
+//!
+//! ```sh
+//! cargo run --example tile_server --features tile-server -- path/or/https/url.tif 127.0.0.1:3000
+//! ```
+//!
+//! Endpoints:
+//!
+//! - `GET /info` lists the pyramid levels.
+//! - `GET /tiles/{level}/{x}/{y}` returns the decoded bytes of one tile of a level. The tile's
+//!   dimensions and samples per pixel are returned in the `X-Tile-Width`, `X-Tile-Height` and
+//!   `X-Samples-Per-Pixel` headers.
+
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use async_tiff::decoder::{DecodePool, DecoderRegistry};
+use async_tiff::error::AsyncTiffResult;
+use async_tiff::metadata::{PrefetchBuffer, TiffMetadataReader};
+use async_tiff::reader::{AsyncFileReader, CachingReader, ObjectReader, ReqwestReader};
+use async_tiff::{Pyramid, TIFF};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use object_store::local::LocalFileSystem;
+use tokio::net::TcpListener;
+
+struct AppState {
+    pyramid: Pyramid,
+    reader: Arc<dyn AsyncFileReader>,
+    decoder_registry: Arc<DecoderRegistry>,
+    pool: DecodePool,
+}
+
+fn open_reader(source: &str) -> Arc<dyn AsyncFileReader> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let url = source.parse().expect("invalid URL");
+        Arc::new(ReqwestReader::new(reqwest::Client::new(), url))
+    } else {
+        let path = std::path::absolute(source).expect("invalid path");
+        let store = Arc::new(LocalFileSystem::new());
+        let path = object_store::path::Path::from_absolute_path(path).expect("invalid path");
+        Arc::new(ObjectReader::new(store, path))
+    }
+}
+
+async fn open_pyramid(reader: &Arc<dyn AsyncFileReader>) -> AsyncTiffResult<Pyramid> {
+    let prefetch_reader = PrefetchBuffer::new(reader.clone(), 32 * 1024).await?;
+    let mut metadata_reader = TiffMetadataReader::try_open(&prefetch_reader).await?;
+    let ifds = metadata_reader.read_all_ifds(&prefetch_reader).await?;
+    Pyramid::try_new(TIFF::new(ifds))
+}
+
+async fn info(State(state): State<Arc<AppState>>) -> String {
+    let mut body = String::new();
+    for (level, pyramid_level) in state.pyramid.levels().iter().enumerate() {
+        let ifd = state.pyramid.ifd(level).unwrap();
+        let (dx, dy) = pyramid_level.decimation();
+        let _ = writeln!(
+            body,
+            "level {level}: ifd {}, {}x{} pixels, {:?} tiles, decimation {dx:.2}x{dy:.2}, mask: {}",
+            pyramid_level.ifd_index(),
+            ifd.image_width(),
+            ifd.image_height(),
+            ifd.tile_count(),
+            pyramid_level.mask_ifd_index().is_some(),
+        );
+    }
+    body
+}
+
+async fn tile(
+    State(state): State<Arc<AppState>>,
+    Path((level, x, y)): Path<(usize, usize, usize)>,
+) -> Response {
+    let Some(ifd) = state.pyramid.ifd(level) else {
+        return (StatusCode::NOT_FOUND, format!("No level {level}")).into_response();
+    };
+    let (Some(tile_width), Some(tile_height), Some((x_count, y_count))) =
+        (ifd.tile_width(), ifd.tile_height(), ifd.tile_count())
+    else {
+        return (StatusCode::BAD_REQUEST, "Level is not tiled").into_response();
+    };
+    if x >= x_count || y >= y_count {
+        return (StatusCode::NOT_FOUND, format!("No tile ({x}, {y})")).into_response();
+    }
+
+    let result = async {
+        let tile = ifd.fetch_tile(x, y, state.reader.as_ref()).await?;
+        state
+            .pool
+            .decode(tile, state.decoder_registry.clone())
+            .await
+    }
+    .await;
+    match result {
+        Ok(body) => (
+            [
+                ("content-type", "application/octet-stream".to_string()),
+                ("x-tile-width", tile_width.to_string()),
+                ("x-tile-height", tile_height.to_string()),
+                ("x-samples-per-pixel", ifd.samples_per_pixel().to_string()),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let source = args
+        .next()
+        .ok_or("usage: tile_server <path-or-url> [address]")?;
+    let addr: SocketAddr = args
+        .next()
+        .unwrap_or_else(|| "127.0.0.1:3000".to_string())
+        .parse()?;
+
+    let reader: Arc<dyn AsyncFileReader> = Arc::new(CachingReader::new(open_reader(&source)));
+    let pyramid = open_pyramid(&reader).await?;
+    let state = Arc::new(AppState {
+        pyramid,
+        reader,
+        decoder_registry: Arc::new(DecoderRegistry::default()),
+        pool: DecodePool::default(),
+    });
+
+    let app = Router::new()
+        .route("/info", get(info))
+        .route("/tiles/{level}/{x}/{y}", get(tile))
+        .with_state(state);
+    let listener = TcpListener::bind(addr).await?;
+    println!("Serving {source} on http://{addr}");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
@@ -4,6 +4,6 @@ from ._async_tiff import *
 from ._async_tiff import ___version
 
 if TYPE_CHECKING:
-    from . import store
+    from . import exceptions, store
 
 __version__: str = ___version()
@@ -67,6 +67,14 @@ class ImageFileDirectory:
     def y_resolution(self) -> float | None:
         """The number of pixels per ResolutionUnit in the ImageLength direction."""
 
+    @property
+    def x_resolution_rational(self) -> tuple[int, int] | None:
+        """The exact (numerator, denominator) of XResolution, as stored in the file."""
+
+    @property
+    def y_resolution_rational(self) -> tuple[int, int] | None:
+        """The exact (numerator, denominator) of YResolution, as stored in the file."""
+
     @property
     def planar_configuration(self) -> PlanarConfiguration: ...
     @property
@@ -76,7 +84,7 @@ class ImageFileDirectory:
     @property
     def date_time(self) -> str | None: ...
     @property
-    def artist(self) -> str | None: ...
+    def artist(self) -> list[str] | None: ...
     @property
     def host_computer(self) -> str | None: ...
     @property
@@ -96,7 +104,7 @@ class ImageFileDirectory:
     @property
     def jpeg_tables(self) -> bytes | None: ...
     @property
-    def copyright(self) -> str | None: ...
+    def copyright(self) -> list[str] | None: ...
     @property
     def geo_key_directory(self) -> GeoKeyDirectory | None: ...
     @property
@@ -105,3 +113,48 @@ class ImageFileDirectory:
     def model_tiepoint(self) -> list[float] | None: ...
     @property
     def other_tags(self) -> dict[int, Value]: ...
+    def tile_index_for(
+        self,
+        x: float,
+        y: float,
+        *,
+        crs: bool = False,
+        transform: tuple[float, float, float, float, float, float] | None = None,
+    ) -> tuple[int, int] | None:
+        """The `(x, y)` index of the tile containing a point.
+
+        Args:
+            x: The column, or the x coordinate if `crs` is set.
+            y: The row, or the y coordinate if `crs` is set.
+            crs: Whether `x` and `y` are coordinates in the image's CRS rather than
+                pixel coordinates.
+            transform: The `(a, b, c, d, e, f)` affine transform of this IFD, used when
+                `crs` is set. Defaults to the transform from this IFD's ModelPixelScale
+                and ModelTiepoint tags, which overviews usually lack.
+
+        Returns:
+            The tile index, or `None` if the point is outside the image or this IFD is
+            not tiled.
+        """
+    def tile_bounds(
+        self,
+        x: int,
+        y: int,
+        *,
+        transform: tuple[float, float, float, float, float, float] | None = None,
+    ) -> tuple[float, float, float, float] | None:
+        """The `(minx, miny, maxx, maxy)` extent of a tile in the image's CRS.
+
+        Edge tiles are clipped to the image.
+
+        Args:
+            x: The column index of the tile.
+            y: The row index of the tile.
+            transform: The `(a, b, c, d, e, f)` affine transform of this IFD. Defaults to
+                the transform from this IFD's ModelPixelScale and ModelTiepoint tags,
+                which overviews usually lack.
+
+        Returns:
+            The tile extent, or `None` if the tile is outside the image or this IFD is
+            not tiled.
+        """
@@ -1,13 +1,22 @@
-from typing import Protocol
+from typing import Literal, Protocol
 from ._tile import Tile
 from ._ifd import ImageFileDirectory
 from .store import ObjectStore
 
 # Fix exports
-from obspec._get import GetRangeAsync, GetRangesAsync
+from obspec._get import GetAsync, GetRangeAsync, GetRangesAsync
 
-class ObspecInput(GetRangeAsync, GetRangesAsync, Protocol):
-    """Supported obspec input to reader."""
+class ObspecRangesInput(GetRangeAsync, GetRangesAsync, Protocol):
+    """Obspec backend supporting range requests."""
+
+class ObspecGetInput(GetAsync, Protocol):
+    """Minimal obspec backend only supporting `get_async`.
+
+    Each byte range is requested separately through the `range` option.
+    """
+
+ObspecInput = ObspecRangesInput | ObspecGetInput
+"""Supported obspec input to reader."""
 
 class TIFF:
     @classmethod
@@ -17,6 +26,9 @@ class TIFF:
         *,
         store: ObjectStore | ObspecInput,
         prefetch: int = 32768,
+        cache: Literal["lru"] | None = None,
+        cache_size: int = 67108864,
+        parse_mode: Literal["strict", "lenient"] = "strict",
     ) -> TIFF:
         """Open a new TIFF.
 
@@ -24,11 +36,61 @@ class TIFF:
             path: The path within the store to read from.
             store: The backend to use for data fetching.
             prefetch: The number of initial bytes to read up front.
+            cache: The cache to keep fetched bytes in. With `"lru"`, bytes are fetched
+                and cached in aligned blocks of 64 KiB, so that repeated reads of the same
+                tiles are served from memory, evicting the least recently used blocks once
+                `cache_size` is exceeded. Defaults to no caching.
+            cache_size: The maximum number of bytes to keep in the cache.
+            parse_mode: How to handle malformed metadata. With `"strict"`, any invalid
+                tag raises a [`TiffFormatError`][async_tiff.exceptions.TiffFormatError].
+                With `"lenient"`, tags that cannot be read are skipped, and IFDs are read
+                up to the first one that cannot be read.
 
         Returns:
             A TIFF instance.
         """
     @property
+    def crs(self) -> str | None:
+        """The CRS of the full-resolution image as an `"EPSG:<code>"` string.
+
+        This is `None` if the GeoKeyDirectory has no projected or geographic type.
+        """
+    @property
+    def transform(self) -> tuple[float, float, float, float, float, float] | None:
+        """The affine transform `(a, b, c, d, e, f)` of the full-resolution image.
+
+        This maps pixel `(col, row)` to `(a * col + b * row + c, d * col + e * row + f)`
+        and is computed from the ModelPixelScale and ModelTiepoint tags.
+        """
+    @property
+    def bounds(self) -> tuple[float, float, float, float] | None:
+        """The `(minx, miny, maxx, maxy)` extent of the full-resolution image in its CRS."""
+    @property
+    def nodata(self) -> float | None:
+        """The nodata value of the full-resolution image from the GDAL_NODATA tag."""
+    @property
+    def is_cog(self) -> bool:
+        """Whether this file passes the checks of [`validate_cog`][async_tiff.TIFF.validate_cog]."""
+    def validate_cog(self) -> list[str]:
+        """Check the structure a Cloud-Optimized GeoTIFF reader relies on.
+
+        This checks that every IFD is tiled, that the IFDs are written before the image
+        data if the file has GDAL structural metadata, and that the overviews agree with
+        the full-resolution image.
+
+        Returns:
+            A description of each problem found; empty for a valid COG.
+        """
+    @property
+    def ghost_metadata(self) -> dict[str, str] | None:
+        """The GDAL structural metadata following the file header, if any.
+
+        For example `{"LAYOUT": "IFDS_BEFORE_DATA", "BLOCK_ORDER": "ROW_MAJOR"}`.
+        """
+    @property
+    def overview_indices(self) -> list[int]:
+        """The indices of the IFDs holding overviews of the full-resolution image."""
+    @property
     def ifds(self) -> list[ImageFileDirectory]:
         """Access the underlying IFDs of this TIFF.
 
@@ -30,6 +30,7 @@ class PhotometricInterpretation(IntEnum):
     CMYK = 5
     YCbCr = 6
     CIELab = 8
+    ICCLab = 9
 
 
 class PlanarConfiguration(IntEnum):
@@ -0,0 +1,17 @@
+class AsyncTiffException(Exception):
+    """The base class for errors raised while reading a TIFF."""
+
+class TiffFormatError(AsyncTiffException):
+    """The file is malformed, e.g. an invalid tag, a corrupt tile or data ending early."""
+
+class UnsupportedError(AsyncTiffException):
+    """The file uses a feature that is not supported, such as an unknown compression."""
+
+class ReadError(AsyncTiffException):
+    """Fetching data from the store failed."""
+
+class FileChangedError(ReadError):
+    """The file was modified since it was opened.
+
+    Reopen the file to read it again.
+    """
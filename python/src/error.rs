@@ -0,0 +1,63 @@
+use async_tiff::error::AsyncTiffError;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    async_tiff.exceptions,
+    AsyncTiffException,
+    PyException,
+    "The base class for errors raised while reading a TIFF."
+);
+create_exception!(
+    async_tiff.exceptions,
+    TiffFormatError,
+    AsyncTiffException,
+    "The file is malformed, e.g. an invalid tag, a corrupt tile or data ending early."
+);
+create_exception!(
+    async_tiff.exceptions,
+    UnsupportedError,
+    AsyncTiffException,
+    "The file uses a feature that is not supported, such as an unknown compression."
+);
+create_exception!(
+    async_tiff.exceptions,
+    ReadError,
+    AsyncTiffException,
+    "Fetching data from the store failed."
+);
+create_exception!(
+    async_tiff.exceptions,
+    FileChangedError,
+    ReadError,
+    "The file was modified since it was opened."
+);
+
+/// Add the exception classes to the `exceptions` submodule registered on `m`.
+pub(crate) fn register_exceptions(m: &Bound<PyModule>) -> PyResult<()> {
+    let py = m.py();
+    let exceptions = m.getattr("exceptions")?.downcast_into::<PyModule>()?;
+    exceptions.add("AsyncTiffException", py.get_type::<AsyncTiffException>())?;
+    exceptions.add("TiffFormatError", py.get_type::<TiffFormatError>())?;
+    exceptions.add("UnsupportedError", py.get_type::<UnsupportedError>())?;
+    exceptions.add("ReadError", py.get_type::<ReadError>())?;
+    exceptions.add("FileChangedError", py.get_type::<FileChangedError>())?;
+    Ok(())
+}
+
+/// Convert an [`AsyncTiffError`] to the most specific Python exception class.
+pub(crate) fn to_py_err(err: AsyncTiffError) -> PyErr {
+    let message = err.to_string();
+    if err.is_file_changed() {
+        FileChangedError::new_err(message)
+    } else if err.is_unsupported() {
+        UnsupportedError::new_err(message)
+    } else if err.is_format_error() {
+        TiffFormatError::new_err(message)
+    } else if err.is_io_error() {
+        ReadError::new_err(message)
+    } else {
+        AsyncTiffException::new_err(message)
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
+use async_tiff::geo::AffineTransform;
 use async_tiff::ImageFileDirectory;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
 use crate::enums::{
@@ -15,6 +17,10 @@ pub(crate) struct PyImageFileDirectory(ImageFileDirectory);
 
 #[pymethods]
 impl PyImageFileDirectory {
+    fn __repr__(&self) -> String {
+        format!("ImageFileDirectory({})", self.0)
+    }
+
     #[getter]
     pub fn new_subfile_type(&self) -> Option<u32> {
         self.0.new_subfile_type()
@@ -109,6 +115,18 @@ impl PyImageFileDirectory {
         self.0.y_resolution()
     }
 
+    /// The exact (numerator, denominator) of XResolution, as stored in the file.
+    #[getter]
+    pub fn x_resolution_rational(&self) -> Option<(u32, u32)> {
+        self.0.x_resolution_rational()
+    }
+
+    /// The exact (numerator, denominator) of YResolution, as stored in the file.
+    #[getter]
+    pub fn y_resolution_rational(&self) -> Option<(u32, u32)> {
+        self.0.y_resolution_rational()
+    }
+
     /// How the components of each pixel are stored.
     ///
     /// The specification defines these values:
@@ -150,7 +168,7 @@ impl PyImageFileDirectory {
     }
 
     #[getter]
-    pub fn artist(&self) -> Option<&str> {
+    pub fn artist(&self) -> Option<&[String]> {
         self.0.artist()
     }
 
@@ -198,7 +216,7 @@ impl PyImageFileDirectory {
     }
 
     #[getter]
-    pub fn copyright(&self) -> Option<&str> {
+    pub fn copyright(&self) -> Option<&[String]> {
         self.0.copyright()
     }
 
@@ -227,6 +245,47 @@ impl PyImageFileDirectory {
             .map(|(key, val)| (key.to_u16(), val.clone().into()));
         HashMap::from_iter(iter)
     }
+
+    #[pyo3(signature = (x, y, *, crs=false, transform=None))]
+    pub fn tile_index_for(
+        &self,
+        x: f64,
+        y: f64,
+        crs: bool,
+        transform: Option<[f64; 6]>,
+    ) -> PyResult<Option<(usize, usize)>> {
+        if crs {
+            let transform = self.transform_or_default(transform)?;
+            Ok(self.0.tile_index_for_point(x, y, &transform))
+        } else {
+            Ok(self.0.tile_index_for_pixel(x, y))
+        }
+    }
+
+    #[pyo3(signature = (x, y, *, transform=None))]
+    pub fn tile_bounds(
+        &self,
+        x: usize,
+        y: usize,
+        transform: Option<[f64; 6]>,
+    ) -> PyResult<Option<(f64, f64, f64, f64)>> {
+        let transform = self.transform_or_default(transform)?;
+        Ok(self.0.tile_bounds(x, y, &transform))
+    }
+}
+
+impl PyImageFileDirectory {
+    /// The given `(a, b, c, d, e, f)` transform, falling back to the one stored in this IFD.
+    fn transform_or_default(&self, transform: Option<[f64; 6]>) -> PyResult<AffineTransform> {
+        match transform {
+            Some([a, b, c, d, e, f]) => Ok(AffineTransform::new(a, b, c, d, e, f)),
+            None => AffineTransform::from_ifd(&self.0).ok_or_else(|| {
+                PyValueError::new_err(
+                    "IFD has no ModelPixelScale and ModelTiepoint tags, pass a transform",
+                )
+            }),
+        }
+    }
 }
 
 impl From<ImageFileDirectory> for PyImageFileDirectory {
@@ -2,6 +2,7 @@
 
 mod decoder;
 mod enums;
+mod error;
 mod geo;
 mod ifd;
 mod reader;
@@ -58,6 +59,7 @@ fn _async_tiff(py: Python, m: &Bound<PyModule>) -> PyResult<()> {
 
     pyo3_object_store::register_store_module(py, m, "async_tiff", "store")?;
     pyo3_object_store::register_exceptions_module(py, m, "async_tiff", "exceptions")?;
+    crate::error::register_exceptions(m)?;
 
     Ok(())
 }
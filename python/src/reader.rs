@@ -4,7 +4,7 @@ use std::sync::Arc;
 use async_tiff::error::{AsyncTiffError, AsyncTiffResult};
 use async_tiff::reader::{AsyncFileReader, ObjectReader};
 use bytes::Bytes;
-use futures::future::BoxFuture;
+use futures::future::{try_join_all, BoxFuture};
 use futures::FutureExt;
 use pyo3::exceptions::PyTypeError;
 use pyo3::intern;
@@ -31,24 +31,36 @@ impl StoreInput {
     }
 }
 
-/// A Python backend for making requests that conforms to the GetRangeAsync and GetRangesAsync
-/// protocols defined by obspec.
+/// A Python backend for making requests that conforms to the obspec protocols.
+///
+/// Backends implementing GetRangeAsync and GetRangesAsync are used directly. Minimal backends
+/// that only implement GetAsync are supported by requesting each range through its `range`
+/// option.
 /// https://developmentseed.org/obspec/latest/api/get/#obspec.GetRangeAsync
 /// https://developmentseed.org/obspec/latest/api/get/#obspec.GetRangesAsync
+/// https://developmentseed.org/obspec/latest/api/get/#obspec.GetAsync
 #[derive(Debug)]
-pub(crate) struct ObspecBackend(PyObject);
+pub(crate) enum ObspecBackend {
+    /// A backend implementing GetRangeAsync and GetRangesAsync.
+    Ranges(PyObject),
+    /// A backend only implementing GetAsync.
+    Get(PyObject),
+}
 
 impl ObspecBackend {
     async fn get_range(&self, path: &str, range: Range<u64>) -> PyResult<PyBytes> {
+        let backend = match self {
+            Self::Ranges(backend) => backend,
+            Self::Get(backend) => return Self::get(backend, path, range).await,
+        };
         let future = Python::with_gil(|py| {
             let kwargs = PyDict::new(py);
             kwargs.set_item(intern!(py, "path"), path)?;
             kwargs.set_item(intern!(py, "start"), range.start)?;
             kwargs.set_item(intern!(py, "end"), range.end)?;
 
-            let coroutine = self
-                .0
-                .call_method(py, intern!(py, "get_range"), (), Some(&kwargs))?;
+            let coroutine =
+                backend.call_method(py, intern!(py, "get_range_async"), (), Some(&kwargs))?;
             into_future(coroutine.bind(py).clone())
         })?;
         let result = future.await?;
@@ -56,6 +68,15 @@ impl ObspecBackend {
     }
 
     async fn get_ranges(&self, path: &str, ranges: &[Range<u64>]) -> PyResult<Vec<PyBytes>> {
+        let backend = match self {
+            Self::Ranges(backend) => backend,
+            Self::Get(backend) => {
+                let futures = ranges
+                    .iter()
+                    .map(|range| Self::get(backend, path, range.clone()));
+                return try_join_all(futures).await;
+            }
+        };
         let starts = ranges.iter().map(|r| r.start).collect::<Vec<_>>();
         let ends = ranges.iter().map(|r| r.end).collect::<Vec<_>>();
 
@@ -65,15 +86,36 @@ impl ObspecBackend {
             kwargs.set_item(intern!(py, "starts"), starts)?;
             kwargs.set_item(intern!(py, "ends"), ends)?;
 
-            let coroutine = self
-                .0
-                .call_method(py, intern!(py, "get_range"), (), Some(&kwargs))?;
+            let coroutine =
+                backend.call_method(py, intern!(py, "get_ranges_async"), (), Some(&kwargs))?;
             into_future(coroutine.bind(py).clone())
         })?;
         let result = future.await?;
         Python::with_gil(|py| result.extract(py))
     }
 
+    /// Request `range` through `get_async` and buffer the response.
+    async fn get(backend: &PyObject, path: &str, range: Range<u64>) -> PyResult<PyBytes> {
+        let future = Python::with_gil(|py| {
+            let options = PyDict::new(py);
+            options.set_item(intern!(py, "range"), (range.start, range.end))?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item(intern!(py, "options"), options)?;
+
+            let coroutine =
+                backend.call_method(py, intern!(py, "get_async"), (path,), Some(&kwargs))?;
+            into_future(coroutine.bind(py).clone())
+        })?;
+        let get_result = future.await?;
+
+        let future = Python::with_gil(|py| {
+            let coroutine = get_result.call_method0(py, intern!(py, "buffer_async"))?;
+            into_future(coroutine.bind(py).clone())
+        })?;
+        let buffer = future.await?;
+        Python::with_gil(|py| buffer.extract(py))
+    }
+
     async fn get_range_wrapper(&self, path: &str, range: Range<u64>) -> AsyncTiffResult<Bytes> {
         let result = self
             .get_range(path, range)
@@ -101,9 +143,11 @@ impl<'py> FromPyObject<'py> for ObspecBackend {
         if ob.hasattr(intern!(py, "get_range_async"))?
             && ob.hasattr(intern!(py, "get_ranges_async"))?
         {
-            Ok(Self(ob.clone().unbind()))
+            Ok(Self::Ranges(ob.clone().unbind()))
+        } else if ob.hasattr(intern!(py, "get_async"))? {
+            Ok(Self::Get(ob.clone().unbind()))
         } else {
-            Err(PyTypeError::new_err("Expected obspec-compatible class with `get_range_async` and `get_ranges_async` method."))
+            Err(PyTypeError::new_err("Expected obspec-compatible class with `get_range_async` and `get_ranges_async` methods, or a `get_async` method."))
         }
     }
 }
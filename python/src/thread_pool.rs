@@ -1,48 +1,45 @@
-use std::sync::Arc;
-
+use async_tiff::decoder::DecodePool;
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
 use pyo3::sync::GILOnceCell;
-use rayon::{ThreadPool, ThreadPoolBuilder};
 
-static DEFAULT_POOL: GILOnceCell<Arc<ThreadPool>> = GILOnceCell::new();
+static DEFAULT_POOL: GILOnceCell<DecodePool> = GILOnceCell::new();
 
-pub fn get_default_pool(py: Python<'_>) -> PyResult<Arc<ThreadPool>> {
-    let runtime = DEFAULT_POOL.get_or_try_init(py, || {
-        let pool = ThreadPoolBuilder::new().build().map_err(|err| {
-            PyValueError::new_err(format!("Could not create rayon threadpool. {err}"))
-        })?;
-        Ok::<_, PyErr>(Arc::new(pool))
+pub fn get_default_pool(py: Python<'_>) -> PyResult<DecodePool> {
+    let pool = DEFAULT_POOL.get_or_try_init(py, || {
+        let num_threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        DecodePool::new(num_threads).map_err(|err| {
+            PyValueError::new_err(format!("Could not create decode thread pool. {err}"))
+        })
     })?;
-    Ok(runtime.clone())
+    Ok(pool.clone())
 }
 
 #[pyclass(name = "ThreadPool", frozen, module = "async_tiff")]
-pub(crate) struct PyThreadPool(Arc<ThreadPool>);
+pub(crate) struct PyThreadPool(DecodePool);
 
 #[pymethods]
 impl PyThreadPool {
     #[new]
     fn new(num_threads: usize) -> PyResult<Self> {
-        let pool = ThreadPoolBuilder::new()
-            .num_threads(num_threads)
-            .build()
-            .map_err(|err| {
-                PyValueError::new_err(format!("Could not create rayon threadpool. {err}"))
-            })?;
-        Ok(Self(Arc::new(pool)))
+        let pool = DecodePool::new(num_threads).map_err(|err| {
+            PyValueError::new_err(format!("Could not create decode thread pool. {err}"))
+        })?;
+        Ok(Self(pool))
     }
 }
 
 impl PyThreadPool {
-    pub(crate) fn inner(&self) -> &Arc<ThreadPool> {
+    pub(crate) fn inner(&self) -> &DecodePool {
         &self.0
     }
 }
 
-impl AsRef<ThreadPool> for PyThreadPool {
-    fn as_ref(&self) -> &ThreadPool {
+impl AsRef<DecodePool> for PyThreadPool {
+    fn as_ref(&self) -> &DecodePool {
         &self.0
     }
 }
@@ -1,13 +1,16 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use async_tiff::metadata::{PrefetchBuffer, TiffMetadataReader};
-use async_tiff::reader::AsyncFileReader;
-use async_tiff::TIFF;
-use pyo3::exceptions::{PyFileNotFoundError, PyIndexError, PyTypeError};
+use async_tiff::geo::AffineTransform;
+use async_tiff::metadata::{GhostMetadata, PrefetchBuffer, TiffMetadataReader};
+use async_tiff::reader::{AsyncFileReader, CachingReader};
+use async_tiff::{ImageFileDirectory, TIFF};
+use pyo3::exceptions::{PyFileNotFoundError, PyIndexError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::PyType;
 use pyo3_async_runtimes::tokio::future_into_py;
 
+use crate::error::to_py_err;
 use crate::reader::StoreInput;
 use crate::tile::PyTile;
 use crate::PyImageFileDirectory;
@@ -16,36 +19,163 @@ use crate::PyImageFileDirectory;
 pub(crate) struct PyTIFF {
     tiff: TIFF,
     reader: Arc<dyn AsyncFileReader>,
+    ghost_metadata: Option<GhostMetadata>,
+}
+
+impl PyTIFF {
+    /// The first IFD that is neither an overview nor a mask.
+    fn full_resolution_ifd(&self) -> Option<&ImageFileDirectory> {
+        self.tiff
+            .ifds()
+            .iter()
+            .find(|ifd| ifd.new_subfile_type().unwrap_or(0) & 0b101 == 0)
+    }
+
+    fn affine_transform(&self) -> Option<AffineTransform> {
+        AffineTransform::from_ifd(self.full_resolution_ifd()?)
+    }
+
+    /// Structural problems preventing efficient Cloud-Optimized GeoTIFF access.
+    fn cog_problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        for (index, ifd) in self.tiff.ifds().iter().enumerate() {
+            if ifd.tile_width().is_none() {
+                problems.push(format!("IFD {index} is not tiled"));
+            }
+        }
+        if let Some(ghost) = &self.ghost_metadata {
+            if !ghost.ifds_before_data() {
+                problems.push("IFDs are not written before the image data".to_string());
+            }
+        }
+        let report = self.tiff.check_overviews();
+        problems.extend(report.issues().iter().map(|issue| issue.to_string()));
+        problems
+    }
 }
 
 #[pymethods]
 impl PyTIFF {
     #[classmethod]
-    #[pyo3(signature = (path, *, store, prefetch=32768))]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (path, *, store, prefetch=32768, cache=None, cache_size=67108864, parse_mode="strict"))]
     fn open<'py>(
         _cls: &'py Bound<PyType>,
         py: Python<'py>,
         path: String,
         store: StoreInput,
         prefetch: u64,
+        cache: Option<String>,
+        cache_size: usize,
+        parse_mode: &str,
     ) -> PyResult<Bound<'py, PyAny>> {
+        let lenient = match parse_mode {
+            "strict" => false,
+            "lenient" => true,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown parse_mode {other:?}, expected \"strict\" or \"lenient\""
+                )))
+            }
+        };
         let reader = store.into_async_file_reader(path);
+        let reader: Arc<dyn AsyncFileReader> = match cache.as_deref() {
+            None => reader,
+            Some("lru") => Arc::new(CachingReader::new(reader).with_capacity(cache_size as u64)),
+            Some(other) => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown cache {other:?}, expected \"lru\" or None"
+                )))
+            }
+        };
 
         let cog_reader = future_into_py(py, async move {
             let metadata_fetch = PrefetchBuffer::new(reader.clone(), prefetch)
                 .await
                 .map_err(|err| PyFileNotFoundError::new_err(err.to_string()))?;
-            let mut metadata_reader = TiffMetadataReader::try_open(&metadata_fetch).await.unwrap();
+            let mut metadata_reader = TiffMetadataReader::try_open(&metadata_fetch)
+                .await
+                .map_err(to_py_err)?
+                .with_lenient(lenient);
+            let ghost_metadata = metadata_reader
+                .read_ghost_metadata(&metadata_fetch)
+                .await
+                .map_err(to_py_err)?;
             let ifds = metadata_reader
                 .read_all_ifds(&metadata_fetch)
                 .await
-                .unwrap();
+                .map_err(to_py_err)?;
             let tiff = TIFF::new(ifds);
-            Ok(PyTIFF { tiff, reader })
+            Ok(PyTIFF {
+                tiff,
+                reader,
+                ghost_metadata,
+            })
         })?;
         Ok(cog_reader)
     }
 
+    #[getter]
+    fn crs(&self) -> Option<String> {
+        let epsg = self
+            .full_resolution_ifd()?
+            .geo_key_directory()?
+            .epsg_code()?;
+        Some(format!("EPSG:{epsg}"))
+    }
+
+    #[getter]
+    fn transform(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        let t = self.affine_transform()?;
+        Some((t.a(), t.b(), t.c(), t.d(), t.e(), t.f()))
+    }
+
+    #[getter]
+    fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        let ifd = self.full_resolution_ifd()?;
+        let transform = self.affine_transform()?;
+        let (width, height) = (ifd.image_width() as f64, ifd.image_height() as f64);
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .map(|(x, y)| transform.apply(x, y));
+        let xs = corners.map(|(x, _)| x);
+        let ys = corners.map(|(_, y)| y);
+        Some((
+            xs.into_iter().fold(f64::INFINITY, f64::min),
+            ys.into_iter().fold(f64::INFINITY, f64::min),
+            xs.into_iter().fold(f64::NEG_INFINITY, f64::max),
+            ys.into_iter().fold(f64::NEG_INFINITY, f64::max),
+        ))
+    }
+
+    #[getter]
+    fn nodata(&self) -> Option<f64> {
+        self.full_resolution_ifd()?.nodata()
+    }
+
+    #[getter]
+    fn is_cog(&self) -> bool {
+        self.cog_problems().is_empty()
+    }
+
+    fn validate_cog(&self) -> Vec<String> {
+        self.cog_problems()
+    }
+
+    #[getter]
+    fn ghost_metadata(&self) -> Option<HashMap<String, String>> {
+        self.ghost_metadata.as_ref().map(|ghost| {
+            ghost
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        })
+    }
+
+    #[getter]
+    fn overview_indices(&self) -> Vec<usize> {
+        self.tiff.check_overviews().overview_indices().to_vec()
+    }
+
     #[getter]
     fn ifds(&self) -> Vec<PyImageFileDirectory> {
         let ifds = self.tiff.ifds();
@@ -72,7 +202,7 @@ impl PyTIFF {
             let tile = ifd
                 .fetch_tile(x, y, reader.as_ref())
                 .await
-                .map_err(|err| PyTypeError::new_err(err.to_string()))?;
+                .map_err(to_py_err)?;
 
             Ok(PyTile::new(tile))
         })
@@ -98,7 +228,7 @@ impl PyTIFF {
             let tiles = ifd
                 .fetch_tiles(&x, &y, reader.as_ref())
                 .await
-                .map_err(|err| PyTypeError::new_err(err.to_string()))?;
+                .map_err(to_py_err)?;
             let py_tiles = tiles.into_iter().map(PyTile::new).collect::<Vec<_>>();
             Ok(py_tiles)
         })
@@ -3,10 +3,10 @@ use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3_async_runtimes::tokio::future_into_py;
 use pyo3_bytes::PyBytes;
-use tokio_rayon::AsyncThreadPool;
 
 use crate::decoder::get_default_decoder_registry;
 use crate::enums::PyCompressionMethod;
+use crate::error::to_py_err;
 use crate::thread_pool::{get_default_pool, PyThreadPool};
 use crate::PyDecoderRegistry;
 
@@ -71,9 +71,9 @@ impl PyTile {
 
         let result = future_into_py(py, async move {
             let decoded_bytes = pool
-                .spawn_async(move || tile.decode(&decoder_registry))
+                .decode(tile, decoder_registry)
                 .await
-                .unwrap();
+                .map_err(to_py_err)?;
             Ok(PyBytes::new(decoded_bytes))
         })?;
         Ok(result.unbind())
@@ -34,3 +34,40 @@ async def test_cog_missing_file():
     store = LocalStore()
     with pytest.raises(FileNotFoundError):
         await TIFF.open(path="imaginary_file.tif", store=store)
+
+
+async def test_cog_validation_s3():
+    """
+    Ensure that a Sentinel-2 Cloud-Optimized GeoTIFF passes COG validation.
+    """
+    path = "sentinel-s2-l2a-cogs/12/S/UF/2022/6/S2B_12SUF_20220609_0_L2A/B04.tif"
+    store = S3Store("sentinel-cogs", region="us-west-2", skip_signature=True)
+    tiff = await TIFF.open(path=path, store=store)
+
+    assert tiff.validate_cog() == []
+    assert tiff.is_cog
+    assert tiff.overview_indices == [1, 2, 3, 4]
+    assert tiff.ghost_metadata == {
+        "LAYOUT": "IFDS_BEFORE_DATA",
+        "BLOCK_ORDER": "ROW_MAJOR",
+        "BLOCK_LEADER": "SIZE_AS_UINT4",
+        "BLOCK_TRAILER": "LAST_4_BYTES_REPEATED",
+        "KNOWN_INCOMPATIBLE_EDITION": "NO",
+    }
+
+
+async def test_cog_geo_properties_s3():
+    """
+    Ensure the GeoTIFF convenience properties are computed from the full-resolution IFD.
+    """
+    path = "sentinel-s2-l2a-cogs/12/S/UF/2022/6/S2B_12SUF_20220609_0_L2A/B04.tif"
+    store = S3Store("sentinel-cogs", region="us-west-2", skip_signature=True)
+    tiff = await TIFF.open(path=path, store=store)
+
+    assert tiff.crs == "EPSG:32612"
+    a, b, c, d, e, f = tiff.transform
+    assert (a, b, d, e) == (10.0, 0.0, 0.0, -10.0)
+    minx, miny, maxx, maxy = tiff.bounds
+    assert minx == c and maxy == f
+    assert maxx - minx == 10.0 * tiff.ifds[0].image_width
+    assert tiff.nodata == 0.0
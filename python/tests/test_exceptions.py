@@ -6,11 +6,13 @@ import pytest
 from async_tiff.store import HTTPStore
 
 from async_tiff import TIFF
+from async_tiff.exceptions import AsyncTiffException
 
 
-async def test_raise_typeerror_fetch_tile_striped_tiff():
+async def test_raise_fetch_tile_striped_tiff():
     """
-    Ensure that a TypeError is raised when trying to fetch a tile from a striped TIFF.
+    Ensure that an AsyncTiffException is raised when trying to fetch a tile from a
+    striped TIFF.
     """
     store = HTTPStore(url="https://github.com/")
     path = "OSGeo/gdal/raw/refs/tags/v3.11.0/autotest/gdrivers/data/gtiff/int8.tif"
@@ -18,5 +20,5 @@ async def test_raise_typeerror_fetch_tile_striped_tiff():
     tiff = await TIFF.open(path=path, store=store)
     assert len(tiff.ifds) >= 1
 
-    with pytest.raises(TypeError):
+    with pytest.raises(AsyncTiffException):
         await tiff.fetch_tile(0, 0, 0)
@@ -0,0 +1,162 @@
+//! Print the structure of a TIFF file, similar to `tiffinfo`.
+//!
+//! ```sh
+//! cargo run --features cli -- path/to/file.tif
+//! cargo run --features cli -- https://example.com/file.tif
+//! cargo run --features cli -- s3://bucket/key.tif
+//! ```
+//!
+//! `s3://` URLs are read anonymously over HTTPS, so they must point to publicly readable objects.
+
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use async_tiff::error::AsyncTiffResult;
+use async_tiff::geo::AffineTransform;
+use async_tiff::metadata::{PrefetchBuffer, TiffMetadataReader};
+use async_tiff::reader::{AsyncFileReader, ObjectReader, ReqwestReader};
+use async_tiff::{ImageFileDirectory, TIFF};
+use object_store::local::LocalFileSystem;
+
+fn open_reader(source: &str) -> Result<Arc<dyn AsyncFileReader>, String> {
+    let url = if let Some(rest) = source.strip_prefix("s3://") {
+        let (bucket, key) = rest
+            .split_once('/')
+            .ok_or_else(|| format!("Invalid S3 URL: {source}"))?;
+        Some(format!("https://{bucket}.s3.amazonaws.com/{key}"))
+    } else if source.starts_with("http://") || source.starts_with("https://") {
+        Some(source.to_string())
+    } else {
+        None
+    };
+
+    if let Some(url) = url {
+        let url = url.parse().map_err(|err| format!("Invalid URL: {err}"))?;
+        Ok(Arc::new(ReqwestReader::new(reqwest::Client::new(), url)))
+    } else {
+        let path = std::path::absolute(source).map_err(|err| err.to_string())?;
+        let path =
+            object_store::path::Path::from_absolute_path(path).map_err(|err| err.to_string())?;
+        Ok(Arc::new(ObjectReader::new(
+            Arc::new(LocalFileSystem::new()),
+            path,
+        )))
+    }
+}
+
+async fn open_tiff(reader: &Arc<dyn AsyncFileReader>) -> AsyncTiffResult<TIFF> {
+    let prefetch_reader = PrefetchBuffer::new(reader.clone(), 32 * 1024).await?;
+    let mut metadata_reader = TiffMetadataReader::try_open(&prefetch_reader).await?;
+    let ifds = metadata_reader.read_all_ifds(&prefetch_reader).await?;
+    Ok(TIFF::new(ifds))
+}
+
+fn print_ifd(index: usize, ifd: &ImageFileDirectory) {
+    let kind = match ifd.new_subfile_type() {
+        Some(t) if t & 4 != 0 => "mask",
+        Some(t) if t & 1 != 0 => "overview",
+        _ => "image",
+    };
+    println!("IFD {index} ({kind})");
+    println!("  Size: {}x{}", ifd.image_width(), ifd.image_height());
+    println!(
+        "  Samples: {} x {:?} bits, {:?}",
+        ifd.samples_per_pixel(),
+        ifd.bits_per_sample(),
+        ifd.sample_format()
+    );
+    println!("  Photometric: {:?}", ifd.photometric_interpretation());
+    println!("  Compression: {:?}", ifd.compression());
+    if let Some(predictor) = ifd.predictor() {
+        println!("  Predictor: {predictor:?}");
+    }
+    println!("  Planar configuration: {:?}", ifd.planar_configuration());
+
+    if let (Some(tile_width), Some(tile_height)) = (ifd.tile_width(), ifd.tile_height()) {
+        let (x_count, y_count) = ifd.tile_count().unwrap_or_default();
+        println!("  Tiles: {tile_width}x{tile_height}, {x_count}x{y_count} grid");
+    } else if let Some(offsets) = ifd.strip_offsets() {
+        let rows = ifd.strip_height().unwrap_or_default();
+        println!("  Strips: {} of {rows} rows", offsets.len());
+    }
+    for warning in ifd.warnings() {
+        println!("  Warning: {warning:?}");
+    }
+    if let Some(nodata) = ifd.nodata() {
+        println!("  Nodata: {nodata}");
+    }
+
+    if let Some(geo_keys) = ifd.geo_key_directory() {
+        if let Some(epsg) = geo_keys.epsg_code() {
+            println!("  EPSG: {epsg}");
+        }
+        if let Some(citation) = &geo_keys.citation {
+            println!("  Citation: {citation}");
+        }
+    }
+    if let Some(transform) = AffineTransform::from_ifd(ifd) {
+        println!(
+            "  Transform: [{}, {}, {}, {}, {}, {}]",
+            transform.a(),
+            transform.b(),
+            transform.c(),
+            transform.d(),
+            transform.e(),
+            transform.f()
+        );
+    }
+}
+
+/// Print structural checks a Cloud-Optimized GeoTIFF reader relies on, returning whether all of
+/// them passed.
+fn print_cog_checks(tiff: &TIFF) -> bool {
+    let mut problems = vec![];
+    for (index, ifd) in tiff.ifds().iter().enumerate() {
+        if ifd.tile_width().is_none() {
+            problems.push(format!("IFD {index} is not tiled"));
+        }
+    }
+    let report = tiff.check_overviews();
+    problems.extend(report.issues().iter().map(|issue| issue.to_string()));
+
+    println!("COG checks");
+    println!("  Overviews: {}", report.overview_indices().len());
+    if problems.is_empty() {
+        println!("  OK");
+    }
+    for problem in &problems {
+        println!("  - {problem}");
+    }
+    problems.is_empty()
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let Some(source) = std::env::args().nth(1) else {
+        eprintln!("usage: async-tiff <path-or-url>");
+        return ExitCode::FAILURE;
+    };
+    let reader = match open_reader(&source) {
+        Ok(reader) => reader,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let tiff = match open_tiff(&reader).await {
+        Ok(tiff) => tiff,
+        Err(err) => {
+            eprintln!("Failed to read {source}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for (index, ifd) in tiff.ifds().iter().enumerate() {
+        print_ifd(index, ifd);
+    }
+    if print_cog_checks(&tiff) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(2)
+    }
+}
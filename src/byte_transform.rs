@@ -0,0 +1,35 @@
+//! Transformation of compressed chunk bytes before decoding.
+
+use std::fmt::Debug;
+
+use bytes::Bytes;
+
+use crate::error::AsyncTiffResult;
+
+/// A transformation of the compressed bytes of each tile or strip, applied after fetching and
+/// before decoding.
+///
+/// This allows reading files whose image data is obfuscated or encrypted, without changes to the
+/// decoders. Attach it to an IFD with
+/// [`ImageFileDirectory::with_byte_transform`](crate::ImageFileDirectory::with_byte_transform).
+///
+/// ```
+/// # use async_tiff::ByteTransform;
+/// # use async_tiff::error::AsyncTiffResult;
+/// # use bytes::Bytes;
+/// /// Chunks XOR'd with a repeating key, starting at the chunk's offset in the file.
+/// #[derive(Debug)]
+/// struct Xor(Vec<u8>);
+///
+/// impl ByteTransform for Xor {
+///     fn transform(&self, _x: usize, _y: usize, offset: u64, bytes: Bytes) -> AsyncTiffResult<Bytes> {
+///         let key = self.0.iter().cycle().skip(offset as usize % self.0.len());
+///         Ok(bytes.iter().zip(key).map(|(b, k)| b ^ k).collect())
+///     }
+/// }
+/// ```
+pub trait ByteTransform: Debug + Send + Sync {
+    /// Transform the `bytes` fetched for the tile or strip at column `x` and row `y`, which start
+    /// at byte `offset` of the file.
+    fn transform(&self, x: usize, y: usize, offset: u64, bytes: Bytes) -> AsyncTiffResult<Bytes>;
+}
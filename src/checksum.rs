@@ -0,0 +1,199 @@
+//! CRC-32 checksums of written tiles and files, and their validation when reading tiles.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use bytes::Bytes;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::tiff::tags::Tag;
+use crate::{ByteTransform, ImageFileDirectory};
+
+/// The CRC-32 checksum of the stored bytes of a tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileChecksum {
+    offset: u64,
+    byte_count: u64,
+    crc32: u32,
+}
+
+impl TileChecksum {
+    /// Create the checksum of the `byte_count` bytes of a tile starting at `offset`.
+    pub fn new(offset: u64, byte_count: u64, crc32: u32) -> Self {
+        Self {
+            offset,
+            byte_count,
+            crc32,
+        }
+    }
+
+    /// The offset of the tile in the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The number of stored, compressed bytes of the tile.
+    pub fn byte_count(&self) -> u64 {
+        self.byte_count
+    }
+
+    /// The CRC-32 (IEEE) checksum of the stored bytes of the tile.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+}
+
+/// Checksums of a written file, as returned by
+/// [`CogWriter::write_with_checksums`](crate::CogWriter::write_with_checksums).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChecksums {
+    size: u64,
+    crc32: u32,
+    tiles: Vec<Vec<TileChecksum>>,
+}
+
+impl FileChecksums {
+    pub(crate) fn new(size: u64, crc32: u32, tiles: Vec<Vec<TileChecksum>>) -> Self {
+        Self { size, crc32, tiles }
+    }
+
+    /// The size of the file in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The CRC-32 (IEEE) checksum of the whole file.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// The checksums of the tiles of each IFD, in the order of
+    /// [`ImageFileDirectory::chunk_index`].
+    pub fn tiles(&self) -> &[Vec<TileChecksum>] {
+        &self.tiles
+    }
+
+    /// A validator of the tiles of the IFD at `index`, to attach with
+    /// [`ImageFileDirectory::with_byte_transform`].
+    pub fn validator(&self, index: usize) -> Option<ChecksumValidator> {
+        Some(ChecksumValidator::new(self.tiles.get(index)?))
+    }
+
+    /// Serialize the checksums as JSON, to store as a sidecar next to the file.
+    ///
+    /// ```json
+    /// {"algorithm":"crc32","size":1234,"crc32":305419896,"ifds":[{"tiles":[{"offset":512,"byte_count":100,"crc32":2882400001}]}]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = format!(
+            r#"{{"algorithm":"crc32","size":{},"crc32":{},"ifds":["#,
+            self.size, self.crc32
+        );
+        for (i, tiles) in self.tiles.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(r#"{"tiles":["#);
+            for (j, tile) in tiles.iter().enumerate() {
+                if j > 0 {
+                    json.push(',');
+                }
+                // Writing to a String can't fail
+                let _ = write!(
+                    json,
+                    r#"{{"offset":{},"byte_count":{},"crc32":{}}}"#,
+                    tile.offset, tile.byte_count, tile.crc32
+                );
+            }
+            json.push_str("]}");
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// A [`ByteTransform`] that checks the fetched bytes of each tile against its CRC-32 checksum,
+/// failing with [`AsyncTiffError::ChecksumMismatch`] if they differ.
+///
+/// Tiles without a known checksum are passed through unchecked. The checksums can come from the
+/// [`FileChecksums`] returned when writing, or from a tag embedded with
+/// [`CogWriter::with_checksum_tag`](crate::CogWriter::with_checksum_tag).
+#[derive(Debug, Clone, Default)]
+pub struct ChecksumValidator {
+    checksums: HashMap<u64, u32>,
+}
+
+impl ChecksumValidator {
+    /// Create a validator of the tiles with the given checksums.
+    pub fn new(tiles: &[TileChecksum]) -> Self {
+        Self {
+            checksums: tiles.iter().map(|tile| (tile.offset, tile.crc32)).collect(),
+        }
+    }
+
+    /// Create a validator from the checksums stored in `tag` of `ifd`, one per tile or strip.
+    ///
+    /// Returns `None` if the tag is absent or doesn't hold a checksum for every tile or strip.
+    /// Fails if the tile offsets of `ifd` are
+    /// [lazy](ImageFileDirectory::has_lazy_tile_offsets) and weren't
+    /// [loaded](ImageFileDirectory::load_tile_offsets).
+    pub fn from_ifd(ifd: &ImageFileDirectory, tag: Tag) -> AsyncTiffResult<Option<Self>> {
+        let Some(checksums) = ifd
+            .other_tags()
+            .get(&tag)
+            .and_then(|value| value.clone().into_u32_vec().ok())
+        else {
+            return Ok(None);
+        };
+        let Some(ranges) = ifd.chunk_byte_ranges()? else {
+            return Ok(None);
+        };
+        if ranges.len() != checksums.len() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            checksums: ranges
+                .into_iter()
+                .map(|range| range.start)
+                .zip(checksums)
+                .collect(),
+        }))
+    }
+}
+
+impl ByteTransform for ChecksumValidator {
+    fn transform(&self, _x: usize, _y: usize, offset: u64, bytes: Bytes) -> AsyncTiffResult<Bytes> {
+        if let Some(&expected) = self.checksums.get(&offset) {
+            let actual = crc32fast::hash(&bytes);
+            if actual != expected {
+                return Err(AsyncTiffError::ChecksumMismatch {
+                    offset,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checksums_to_json() {
+        let checksums = FileChecksums::new(
+            100,
+            7,
+            vec![
+                vec![TileChecksum::new(60, 20, 1), TileChecksum::new(80, 20, 2)],
+                vec![],
+            ],
+        );
+        assert_eq!(
+            checksums.to_json(),
+            r#"{"algorithm":"crc32","size":100,"crc32":7,"ifds":[{"tiles":[{"offset":60,"byte_count":20,"crc32":1},{"offset":80,"byte_count":20,"crc32":2}]},{"tiles":[]}]}"#
+        );
+    }
+}
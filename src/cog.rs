@@ -16,9 +16,29 @@ impl TIFF {
     pub fn ifds(&self) -> &[ImageFileDirectory] {
         &self.ifds
     }
+
+    /// Find the internal transparency mask for the IFD at `index`.
+    ///
+    /// This is the first [mask IFD](ImageFileDirectory::is_mask) with the same dimensions as the
+    /// image, as written by GDAL with `GDAL_TIFF_INTERNAL_MASK`.
+    pub fn mask_ifd(&self, index: usize) -> Option<&ImageFileDirectory> {
+        Some(&self.ifds[self.mask_ifd_index(index)?])
+    }
+
+    /// Find the index of the internal transparency mask for the IFD at `index`.
+    ///
+    /// See [`mask_ifd`](Self::mask_ifd).
+    pub fn mask_ifd_index(&self, index: usize) -> Option<usize> {
+        let ifd = self.ifds.get(index)?;
+        self.ifds.iter().position(|mask| {
+            mask.is_mask()
+                && mask.image_width == ifd.image_width
+                && mask.image_height == ifd.image_height
+        })
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "object_store"))]
 mod test {
     use std::io::BufReader;
     use std::sync::Arc;
@@ -0,0 +1,1705 @@
+//! Writing of Cloud-Optimized GeoTIFFs.
+
+use std::collections::HashMap;
+use std::pin::pin;
+use std::sync::Arc;
+
+use bytes::{Buf, Bytes};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+
+use crate::checksum::{FileChecksums, TileChecksum};
+use crate::decoder::{DecodePool, DecoderRegistry, TileInfo};
+use crate::encoder::EncoderRegistry;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::ifd::ImageFileDirectory;
+use crate::ifd_builder::validate_color;
+use crate::metadata::TiffMetadataReader;
+use crate::overview_builder::{OverviewResampling, Raster};
+use crate::predictor::{hpredict_nsamp, predict_float};
+use crate::reader::{AsyncFileReader, Endianness};
+use crate::statistics::{gdal_metadata, BandStatistics, StatisticsBuilder};
+use crate::tiff::tags::{
+    CompressionMethod, ExtraSample, PhotometricInterpretation, PlanarConfiguration, Predictor,
+    SampleFormat, Tag, Type,
+};
+use crate::tiff::{TiffError, TiffUnsupportedError, Value};
+use crate::writer::AsyncFileWriter;
+use crate::{IfdBuilder, Window};
+
+/// The structural metadata GDAL writes after the header of a COG, which readers such as GDAL
+/// use to recognize the layout without scanning the file.
+const GHOST_METADATA: &str = "LAYOUT=IFDS_BEFORE_DATA\nBLOCK_ORDER=ROW_MAJOR\nBLOCK_LEADER=NONE\nBLOCK_TRAILER=NONE\nKNOWN_INCOMPATIBLE_EDITION=NO\n";
+
+/// The maximum number of tiles of an image compressed to choose its predictor.
+const PREDICTOR_SAMPLE_TILES: usize = 8;
+
+/// NewSubfileType flag of reduced-resolution images.
+const REDUCED_RESOLUTION: u32 = 1;
+
+/// An image to write with [`CogWriter`]: an IFD describing it, and the decoded data of each of
+/// its tiles.
+///
+/// The tiles are in the order of [`ImageFileDirectory::chunk_index`], and each holds the
+/// native-endian samples of a full tile as returned by [`Tile::decode`](crate::Tile::decode),
+/// including any padding past the edges of the image. The tile offsets and byte counts of the
+/// IFD are ignored, as they are determined when writing.
+#[derive(Debug, Clone)]
+pub struct CogImage {
+    ifd: ImageFileDirectory,
+    tiles: Vec<Bytes>,
+    /// Whether the tiles are already compressed.
+    encoded: bool,
+    statistics: Option<Vec<BandStatistics>>,
+}
+
+impl CogImage {
+    /// Create an image from an IFD, e.g. built with [`IfdBuilder`](crate::IfdBuilder), and its
+    /// decoded tiles.
+    pub fn new(ifd: ImageFileDirectory, tiles: Vec<Bytes>) -> Self {
+        Self {
+            ifd,
+            tiles,
+            encoded: false,
+            statistics: None,
+        }
+    }
+
+    /// Create an image from an IFD and the stored bytes of each of its tiles, which are written
+    /// verbatim, e.g. to copy tiles from another file without re-encoding them.
+    ///
+    /// The tiles must be compressed as described by the IFD, with multi-byte samples in
+    /// little-endian order.
+    pub fn from_encoded(ifd: ImageFileDirectory, tiles: Vec<Bytes>) -> Self {
+        Self {
+            ifd,
+            tiles,
+            encoded: true,
+            statistics: None,
+        }
+    }
+
+    /// Embed precomputed statistics of each band in the GDAL_METADATA tag of this image, instead
+    /// of computing them with [`CogWriter::with_statistics`].
+    pub fn with_statistics(mut self, statistics: Vec<BandStatistics>) -> Self {
+        self.statistics = Some(statistics);
+        self
+    }
+
+    /// The precomputed statistics of each band, if set.
+    pub fn statistics(&self) -> Option<&[BandStatistics]> {
+        self.statistics.as_deref()
+    }
+
+    /// The IFD describing this image.
+    pub fn ifd(&self) -> &ImageFileDirectory {
+        &self.ifd
+    }
+
+    /// The data of each tile, decoded unless created with [`from_encoded`](Self::from_encoded).
+    pub fn tiles(&self) -> &[Bytes] {
+        &self.tiles
+    }
+
+    /// Check the tiles against the IFD, and return the predictor to apply to them: `predictor` if
+    /// set, and otherwise the predictor of the IFD or one chosen by
+    /// [`select_predictor`](Self::select_predictor).
+    fn prepare(
+        &self,
+        predictor: Option<Predictor>,
+        encoders: &EncoderRegistry,
+    ) -> AsyncTiffResult<Predictor> {
+        self.validate()?;
+        if self.encoded {
+            return Ok(self.ifd.predictor.unwrap_or(Predictor::None));
+        }
+        match predictor.or(self.ifd.predictor.filter(|p| *p != Predictor::None)) {
+            Some(predictor) if self.supports_predictor(predictor) => Ok(predictor),
+            Some(predictor) => Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedPredictor(predictor),
+            )
+            .into()),
+            None => self.select_predictor(encoders),
+        }
+    }
+
+    /// Check the tiles against the IFD.
+    fn validate(&self) -> AsyncTiffResult<()> {
+        let ifd = &self.ifd;
+        let (Some((x_count, y_count)), Some(tile_height)) = (ifd.tile_count(), ifd.tile_height)
+        else {
+            return Err(AsyncTiffError::General(
+                "Cloud-Optimized GeoTIFFs must be tiled".to_string(),
+            ));
+        };
+        if !ifd.bits_per_sample.windows(2).all(|w| w[0] == w[1]) {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::InconsistentBitsPerSample(
+                    ifd.bits_per_sample.iter().map(|b| *b as u8).collect(),
+                ),
+            )
+            .into());
+        }
+        validate_color(
+            ifd.photometric_interpretation,
+            ifd.samples_per_pixel,
+            (
+                ifd.sample_format
+                    .first()
+                    .copied()
+                    .unwrap_or(SampleFormat::Uint),
+                self.bits_per_sample(),
+            ),
+            ifd.extra_samples.as_deref(),
+            ifd.color_map.as_deref(),
+        )
+        .map_err(AsyncTiffError::General)?;
+        let planes = match ifd.planar_configuration {
+            PlanarConfiguration::Chunky => 1,
+            PlanarConfiguration::Planar => ifd.samples_per_pixel as usize,
+        };
+        let num_tiles = x_count * y_count * planes;
+        if self.tiles.len() != num_tiles {
+            return Err(AsyncTiffError::General(format!(
+                "Expected {num_tiles} tiles, got {}",
+                self.tiles.len()
+            )));
+        }
+        let tile_bytes = ifd.chunk_row_bytes() * tile_height as usize;
+        match self.tiles.iter().find(|tile| tile.len() != tile_bytes) {
+            Some(tile) if !self.encoded => Err(AsyncTiffError::General(format!(
+                "Expected tiles of {tile_bytes} bytes, got {}",
+                tile.len()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Compute the statistics and histogram of each band from the valid area of the decoded
+    /// tiles, ignoring nodata and NaN samples.
+    ///
+    /// Pass them to [`with_statistics`](Self::with_statistics) to embed them, and write the
+    /// histograms to a sidecar with [`BandStatistics::to_aux_xml`]. Fails for images created with
+    /// [`from_encoded`](Self::from_encoded).
+    pub fn compute_statistics(&self) -> AsyncTiffResult<Vec<BandStatistics>> {
+        self.validate()?;
+        if self.encoded {
+            return Err(AsyncTiffError::General(
+                "Cannot compute statistics of encoded tiles".to_string(),
+            ));
+        }
+        let ifd = &self.ifd;
+        let bits_per_sample = self.bits_per_sample();
+        if !matches!(bits_per_sample, 8 | 16 | 32 | 64) {
+            return Err(AsyncTiffError::General(format!(
+                "Cannot compute statistics of {bits_per_sample}-bit samples"
+            )));
+        }
+        let bytes_per_sample = bits_per_sample as usize / 8;
+        let sample_format = ifd.sample_format.first().copied();
+        let mut builder = StatisticsBuilder::new(
+            ifd.samples_per_pixel as usize,
+            sample_format.unwrap_or(SampleFormat::Uint),
+            ifd.nodata(),
+        );
+
+        let (x_count, y_count) = ifd.tile_count().unwrap_or_default();
+        let tile_width = ifd.tile_width.unwrap_or_default() as usize;
+        let tile_height = ifd.tile_height.unwrap_or_default() as usize;
+        let planar = ifd.planar_configuration == PlanarConfiguration::Planar;
+        let pixel_bytes = ifd.chunk_row_bytes() / tile_width.max(1);
+        for histograms in [false, true] {
+            for (index, tile) in self.tiles.iter().enumerate() {
+                let (x, y) = (index % x_count, index / x_count % y_count);
+                let band = planar.then_some(index / (x_count * y_count));
+                let columns = tile_width.min(ifd.image_width as usize - x * tile_width);
+                let rows = tile_height.min(ifd.image_height as usize - y * tile_height);
+                for row in tile.chunks(tile_width * pixel_bytes).take(rows) {
+                    let row = &row[..columns * pixel_bytes];
+                    if histograms {
+                        builder.add_to_histograms(row, band, bytes_per_sample);
+                    } else {
+                        builder.add(row, band, bytes_per_sample);
+                    }
+                }
+            }
+        }
+        builder.finish()
+    }
+
+    /// Compress the tile at `index` with `predictor`, as returned by [`prepare`](Self::prepare).
+    fn encode_tile(
+        &self,
+        index: usize,
+        predictor: Predictor,
+        encoders: &EncoderRegistry,
+    ) -> AsyncTiffResult<Bytes> {
+        let tile = &self.tiles[index];
+        if self.encoded {
+            return Ok(tile.clone());
+        }
+        self.compress(tile, predictor, encoders)
+    }
+
+    /// Apply `predictor` to a decoded tile and compress it with the encoder registered for the
+    /// compression of the IFD.
+    fn compress(
+        &self,
+        tile: &Bytes,
+        predictor: Predictor,
+        encoders: &EncoderRegistry,
+    ) -> AsyncTiffResult<Bytes> {
+        encoders.encode_tile(
+            self.ifd.compression,
+            self.predict(tile, predictor),
+            self.ifd.photometric_interpretation,
+            &self.tile_info(),
+        )
+    }
+
+    /// The layout of each tile as passed to encoders.
+    fn tile_info(&self) -> TileInfo {
+        let samples = match self.ifd.planar_configuration {
+            PlanarConfiguration::Chunky => self.ifd.samples_per_pixel as u32,
+            PlanarConfiguration::Planar => 1,
+        };
+        TileInfo::new(
+            self.ifd.tile_width.unwrap_or_default(),
+            self.ifd.tile_height.unwrap_or_default(),
+            self.bits_per_sample() as u32 * samples,
+        )
+    }
+
+    fn bits_per_sample(&self) -> u16 {
+        self.ifd.bits_per_sample.first().copied().unwrap_or(8)
+    }
+
+    fn supports_predictor(&self, predictor: Predictor) -> bool {
+        let bits_per_sample = self.bits_per_sample();
+        match predictor {
+            Predictor::None => true,
+            Predictor::Horizontal => matches!(bits_per_sample, 8 | 16 | 32 | 64),
+            Predictor::FloatingPoint => {
+                self.ifd.sample_format.first() == Some(&SampleFormat::IEEEFP)
+                    && matches!(bits_per_sample, 16 | 32 | 64)
+            }
+            Predictor::Unknown(_) => false,
+        }
+    }
+
+    /// Choose the predictor giving the smallest compressed size of a sample of the tiles: no
+    /// predictor or horizontal differencing for integer samples, and no predictor or floating
+    /// point prediction for floating point samples, as GDAL recommends. Uncompressed and
+    /// JPEG-compressed tiles use no predictor.
+    fn select_predictor(&self, encoders: &EncoderRegistry) -> AsyncTiffResult<Predictor> {
+        if matches!(
+            self.ifd.compression,
+            CompressionMethod::None | CompressionMethod::JPEG | CompressionMethod::ModernJPEG
+        ) {
+            return Ok(Predictor::None);
+        }
+        let candidate = match self.ifd.sample_format.first() {
+            Some(SampleFormat::IEEEFP) => Predictor::FloatingPoint,
+            _ => Predictor::Horizontal,
+        };
+        if !self.supports_predictor(candidate) {
+            return Ok(Predictor::None);
+        }
+
+        let step = self.tiles.len().div_ceil(PREDICTOR_SAMPLE_TILES).max(1);
+        let compressed_size = |predictor| {
+            self.tiles.iter().step_by(step).try_fold(0, |size, tile| {
+                let compressed = self.compress(tile, predictor, encoders)?;
+                AsyncTiffResult::Ok(size + compressed.len())
+            })
+        };
+        if compressed_size(candidate)? < compressed_size(Predictor::None)? {
+            Ok(candidate)
+        } else {
+            Ok(Predictor::None)
+        }
+    }
+
+    /// Apply `predictor` to a decoded tile, returning its little-endian data.
+    fn predict(&self, tile: &Bytes, predictor: Predictor) -> Bytes {
+        let bits_per_sample = self.bits_per_sample();
+        let row_bytes = self.ifd.chunk_row_bytes();
+        let samples = match self.ifd.planar_configuration {
+            PlanarConfiguration::Chunky => self.ifd.samples_per_pixel as usize,
+            PlanarConfiguration::Planar => 1,
+        };
+        match predictor {
+            Predictor::None => to_little_endian(tile.clone(), bits_per_sample),
+            Predictor::Horizontal => {
+                let mut data = tile.to_vec();
+                for row in data.chunks_mut(row_bytes) {
+                    hpredict_nsamp(row, bits_per_sample, samples);
+                }
+                to_little_endian(data.into(), bits_per_sample)
+            }
+            // The byte planes are big-endian regardless of the byte order of the file
+            Predictor::FloatingPoint => {
+                let mut data = vec![0; tile.len()];
+                for (input, output) in tile.chunks(row_bytes).zip(data.chunks_mut(row_bytes)) {
+                    predict_float(input, output, bits_per_sample, samples);
+                }
+                data.into()
+            }
+            Predictor::Unknown(_) => {
+                unreachable!("Unsupported predictors are rejected by prepare. Please file a bug.")
+            }
+        }
+    }
+}
+
+/// A writer of Cloud-Optimized GeoTIFFs.
+///
+/// The file is laid out as GDAL's COG driver does: the header, then the IFDs of the full
+/// resolution image and of each overview, and then the tile data, from the smallest overview to
+/// the full resolution image. As the IFDs come first, every tile is encoded before anything is
+/// written, and the file is then streamed sequentially to an [`AsyncFileWriter`].
+///
+/// Tiles are compressed as set by the IFD of each image, with the [encoders](Self::with_encoders)
+/// registered for it, after applying a predictor chosen as described in
+/// [`with_predictor`](Self::with_predictor).
+///
+/// The file is written as little-endian BigTIFF if [forced](Self::with_bigtiff) or if it would
+/// exceed 4 GiB, and as classic TIFF otherwise.
+///
+/// ```
+/// # tokio_test::block_on(async {
+/// # use async_tiff::{CogImage, CogWriter, IfdBuilder};
+/// # use async_tiff::writer::MemoryWriter;
+/// # use bytes::Bytes;
+/// let ifd = IfdBuilder::new(32, 16).with_tiling(16, 16).build().unwrap();
+/// let tiles = vec![Bytes::from(vec![0u8; 256]); 2];
+///
+/// let mut writer = MemoryWriter::new();
+/// let size = CogWriter::new()
+///     .write(vec![CogImage::new(ifd, tiles)], &mut writer)
+///     .await
+///     .unwrap();
+/// assert_eq!(size, writer.data().len() as u64);
+/// # })
+/// ```
+#[derive(Debug, Clone)]
+pub struct CogWriter {
+    bigtiff: bool,
+    part_size: Option<usize>,
+    checksum_tag: Option<Tag>,
+    resampling: OverviewResampling,
+    predictor: Option<Predictor>,
+    encoders: Arc<EncoderRegistry>,
+    pool: Option<DecodePool>,
+    encode_concurrency: usize,
+    statistics: bool,
+}
+
+impl Default for CogWriter {
+    fn default() -> Self {
+        Self {
+            bigtiff: false,
+            part_size: None,
+            checksum_tag: None,
+            resampling: OverviewResampling::default(),
+            predictor: None,
+            encoders: Arc::new(EncoderRegistry::default()),
+            pool: None,
+            encode_concurrency: 8,
+            statistics: false,
+        }
+    }
+}
+
+impl CogWriter {
+    /// Create a writer with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Always write BigTIFF files, even if they would fit in classic TIFF.
+    pub fn with_bigtiff(mut self, bigtiff: bool) -> Self {
+        self.bigtiff = bigtiff;
+        self
+    }
+
+    /// Whether BigTIFF files are always written.
+    pub fn bigtiff(&self) -> bool {
+        self.bigtiff
+    }
+
+    /// Pass the file to the [`AsyncFileWriter`] in parts of `part_size` bytes, apart from the
+    /// last.
+    ///
+    /// Set this to the part size of a multipart upload, e.g. of a
+    /// [`MultipartWriter`](crate::writer::MultipartWriter), so that each write is uploaded as a
+    /// single part without being copied into a buffer. By default, the header and IFDs are
+    /// passed as one write and then each tile as its own.
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = Some(part_size.max(1));
+        self
+    }
+
+    /// The size of the parts the file is passed to the writer in, if set.
+    pub fn part_size(&self) -> Option<usize> {
+        self.part_size
+    }
+
+    /// Store the CRC-32 checksum of each tile in `tag` of its IFD, e.g. a private tag in the
+    /// range 65000 to 65535, as a LONG per tile in the order of
+    /// [`ImageFileDirectory::chunk_index`].
+    ///
+    /// Readers can then validate tiles with
+    /// [`ChecksumValidator::from_ifd`](crate::ChecksumValidator::from_ifd). The checksum of the
+    /// whole file can't be stored in the file itself, see
+    /// [`write_with_checksums`](Self::write_with_checksums).
+    pub fn with_checksum_tag(mut self, tag: Tag) -> Self {
+        self.checksum_tag = Some(tag);
+        self
+    }
+
+    /// The tag the checksum of each tile is stored in, if set.
+    pub fn checksum_tag(&self) -> Option<Tag> {
+        self.checksum_tag
+    }
+
+    /// Apply `predictor` to the tiles of every image, instead of choosing one per image.
+    ///
+    /// By default, images whose IFD sets horizontal or floating point prediction use it, and
+    /// otherwise the predictor giving the smallest size on a sample of the tiles is chosen based
+    /// on the sample format.
+    pub fn with_predictor(mut self, predictor: Predictor) -> Self {
+        self.predictor = Some(predictor);
+        self
+    }
+
+    /// The predictor applied to every image, if set.
+    pub fn predictor(&self) -> Option<Predictor> {
+        self.predictor
+    }
+
+    /// Set the registry used to compress tiles. Defaults to [`EncoderRegistry::default`].
+    pub fn with_encoders(mut self, encoders: Arc<EncoderRegistry>) -> Self {
+        self.encoders = encoders;
+        self
+    }
+
+    /// The registry used to compress tiles.
+    pub fn encoders(&self) -> &EncoderRegistry {
+        &self.encoders
+    }
+
+    /// Encode tiles on `pool` instead of on the polling thread.
+    ///
+    /// The pool can be shared with readers, e.g. with [`Pipeline`](crate::pipeline::Pipeline).
+    /// As the IFDs precede the tile data, every tile is still encoded before anything is written,
+    /// but the async runtime stays free to make progress on other I/O meanwhile.
+    pub fn with_pool(mut self, pool: DecodePool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// The pool tiles are encoded on, if set.
+    pub fn pool(&self) -> Option<&DecodePool> {
+        self.pool.as_ref()
+    }
+
+    /// Set how many tiles may be queued for encoding on the [pool](Self::with_pool) at once.
+    /// Defaults to 8.
+    pub fn with_encode_concurrency(mut self, encode_concurrency: usize) -> Self {
+        self.encode_concurrency = encode_concurrency.max(1);
+        self
+    }
+
+    /// How many tiles may be queued for encoding at once.
+    pub fn encode_concurrency(&self) -> usize {
+        self.encode_concurrency
+    }
+
+    /// Compute the minimum, maximum, mean, standard deviation and valid percentage of each band of
+    /// the full resolution image, ignoring nodata and NaN samples, and embed them in its
+    /// GDAL_METADATA tag, which GDAL and QGIS use for default rendering.
+    ///
+    /// Images with [precomputed statistics](CogImage::with_statistics) embed those instead, and
+    /// the full resolution image is skipped if it was created with [`CogImage::from_encoded`].
+    /// Existing GDAL_METADATA items are kept.
+    pub fn with_statistics(mut self, statistics: bool) -> Self {
+        self.statistics = statistics;
+        self
+    }
+
+    /// Whether the statistics of the full resolution image are computed.
+    pub fn statistics(&self) -> bool {
+        self.statistics
+    }
+
+    /// Set the resampling method of the overviews generated by
+    /// [`inject_overviews`](Self::inject_overviews). Defaults to
+    /// [`OverviewResampling::Average`].
+    pub fn with_resampling(mut self, resampling: OverviewResampling) -> Self {
+        self.resampling = resampling;
+        self
+    }
+
+    /// The resampling method of generated overviews.
+    pub fn resampling(&self) -> OverviewResampling {
+        self.resampling
+    }
+
+    /// Write the full resolution image of the TIFF read by `reader` to `writer` with new
+    /// overviews, e.g. to add overviews to an existing COG.
+    ///
+    /// The stored tiles of the full resolution image are copied verbatim, without being
+    /// re-encoded, and its tags are kept. The image is also decoded with `registry` and halved
+    /// with the [resampling](Self::with_resampling) method until it fits in a single tile, each
+    /// level becoming an overview with the same tiling. Palette images are always resampled with
+    /// [`OverviewResampling::Nearest`]. Overviews keep the compression of the full resolution
+    /// image, which fails if no [encoder](Self::with_encoders) is registered for it. Any other
+    /// IFDs of the file, such as existing overviews and masks, are dropped.
+    ///
+    /// As the overviews are written before the full resolution image, the whole image is decoded
+    /// in memory. Big-endian files are only supported with single-byte samples.
+    ///
+    /// Returns the size of the new file in bytes.
+    pub async fn inject_overviews<R: AsyncFileReader, W: AsyncFileWriter + ?Sized>(
+        &self,
+        reader: &R,
+        registry: &DecoderRegistry,
+        writer: &mut W,
+    ) -> AsyncTiffResult<u64> {
+        let mut metadata = TiffMetadataReader::try_open(reader).await?;
+        let ifd = metadata
+            .read_next_ifd(reader)
+            .await?
+            .ok_or(AsyncTiffError::General("TIFF has no IFDs".to_string()))?;
+        let (Some(tile_width), Some(tile_height), Some(offsets)) =
+            (ifd.tile_width, ifd.tile_height, &ifd.tile_offsets)
+        else {
+            return Err(AsyncTiffError::General(
+                "Cloud-Optimized GeoTIFFs must be tiled".to_string(),
+            ));
+        };
+        if matches!(ifd.endianness, Endianness::BigEndian)
+            && ifd.bits_per_sample.iter().any(|b| *b > 8)
+        {
+            return Err(AsyncTiffError::General(
+                "Cannot copy the tiles of a big-endian file with multi-byte samples".to_string(),
+            ));
+        }
+
+        let ranges = (0..offsets.len())
+            .map(|i| ifd.chunk_byte_range(i))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(AsyncTiffError::General(
+                "Missing tile byte counts".to_string(),
+            ))?;
+        let tiles = reader.get_byte_ranges(ranges).await?;
+
+        let window = Window::new(0, 0, ifd.image_width, ifd.image_height);
+        let mut raster = Raster::from_window(ifd.read_window(window, reader, registry).await?);
+        let palette = ifd.photometric_interpretation == PhotometricInterpretation::RGBPalette;
+        let resampling = if palette {
+            OverviewResampling::Nearest
+        } else {
+            self.resampling
+        };
+        let nodata = ifd.nodata();
+
+        let mut images = vec![CogImage::from_encoded(ifd.clone(), tiles)];
+        while raster.width() > tile_width as usize || raster.height() > tile_height as usize {
+            raster = raster.halve(resampling, nodata)?;
+            let overview = overview_ifd(&ifd, &raster, registry, &self.encoders)?;
+            let tiles = raster.tiles(tile_width as usize, tile_height as usize);
+            images.push(CogImage::new(overview, tiles));
+        }
+        self.write(images, writer).await
+    }
+
+    /// Encode `images` and write them to `writer` as a COG, finishing the writer.
+    ///
+    /// The first image is the full resolution image, and the others are its overviews, from
+    /// largest to smallest. Overviews are marked as reduced-resolution images unless their IFD
+    /// sets NewSubfileType. Tags whose values point to other IFDs, such as SubIFDs, are dropped.
+    ///
+    /// As the IFDs before the tiles hold their sizes, tiles are encoded twice: once to lay out
+    /// the file and once as they are written, so that at most
+    /// [`encode_concurrency`](Self::encode_concurrency) encoded tiles are held in memory.
+    ///
+    /// Returns the size of the file in bytes.
+    pub async fn write<W: AsyncFileWriter + ?Sized>(
+        &self,
+        images: Vec<CogImage>,
+        writer: &mut W,
+    ) -> AsyncTiffResult<u64> {
+        let (file_size, _) = self.write_inner(images, writer, false).await?;
+        Ok(file_size)
+    }
+
+    /// Like [`write`](Self::write), also computing the CRC-32 checksums of each tile and of the
+    /// whole file.
+    ///
+    /// Store them as a sidecar with [`FileChecksums::to_json`], and validate tiles when reading
+    /// with [`FileChecksums::validator`].
+    pub async fn write_with_checksums<W: AsyncFileWriter + ?Sized>(
+        &self,
+        images: Vec<CogImage>,
+        writer: &mut W,
+    ) -> AsyncTiffResult<FileChecksums> {
+        let (_, checksums) = self.write_inner(images, writer, true).await?;
+        Ok(checksums.expect("checksums are computed"))
+    }
+
+    async fn write_inner<W: AsyncFileWriter + ?Sized>(
+        &self,
+        images: Vec<CogImage>,
+        writer: &mut W,
+        checksums: bool,
+    ) -> AsyncTiffResult<(u64, Option<FileChecksums>)> {
+        if images.is_empty() {
+            return Err(AsyncTiffError::General("No images to write".to_string()));
+        }
+        let images = images.into_iter().map(Arc::new).collect::<Vec<_>>();
+        let predictors = stream::iter(images.iter().cloned())
+            .map(|image| {
+                let predictor = self.predictor;
+                let encoders = self.encoders.clone();
+                self.run(move || image.prepare(predictor, &encoders))
+            })
+            .buffered(self.encode_concurrency)
+            .try_collect::<Vec<_>>()
+            .await?;
+        // The IFDs, which precede the tiles, hold the size of every tile: encode them once to lay
+        // out the file, keeping only their sizes and checksums, and again as they are written.
+        let crcs = checksums || self.checksum_tag.is_some();
+        let mut sizes = self
+            .encode_tiles(&images, &predictors)
+            .map_ok(|tile| (tile.len() as u64, crcs.then(|| crc32fast::hash(&tile))))
+            .try_collect::<Vec<_>>()
+            .await?
+            .into_iter();
+        let mut byte_counts = vec![vec![]; images.len()];
+        let mut tile_crcs = crcs.then(|| vec![vec![]; images.len()]);
+        for (i, image) in images.iter().enumerate().rev() {
+            for (byte_count, crc) in sizes.by_ref().take(image.tiles.len()) {
+                byte_counts[i].push(byte_count);
+                if let (Some(tile_crcs), Some(crc)) = (&mut tile_crcs, crc) {
+                    tile_crcs[i].push(crc);
+                }
+            }
+        }
+
+        let first = images[0].clone();
+        let computed = if self.statistics && first.statistics.is_none() && !first.encoded {
+            Some(self.run(move || first.compute_statistics()).await?)
+        } else {
+            None
+        };
+
+        let mut tags = images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| image_tags(&image.ifd, i > 0))
+            .collect::<AsyncTiffResult<Vec<_>>>()?;
+        for (i, (tags, image)) in tags.iter_mut().zip(&images).enumerate() {
+            let computed = computed.as_deref().filter(|_| i == 0);
+            if let Some(statistics) = image.statistics.as_deref().or(computed) {
+                let xml = gdal_metadata(statistics, image.ifd.gdal_metadata());
+                tags.insert(Tag::GdalMetadata, Value::Ascii(xml));
+            }
+        }
+        for (tags, &predictor) in tags.iter_mut().zip(&predictors) {
+            if predictor == Predictor::None {
+                tags.remove(&Tag::Predictor);
+            } else {
+                tags.insert(Tag::Predictor, Value::Short(predictor.to_u16()));
+            }
+        }
+        if let (Some(tag), Some(tile_crcs)) = (self.checksum_tag, &tile_crcs) {
+            for (tags, crcs) in tags.iter_mut().zip(tile_crcs) {
+                let crcs = crcs.iter().copied().map(Value::Unsigned).collect();
+                tags.insert(tag, Value::List(crcs));
+            }
+        }
+
+        let mut layout = Layout::new(&tags, &byte_counts, self.bigtiff)?;
+        if !self.bigtiff && layout.file_size > u32::MAX as u64 {
+            layout = Layout::new(&tags, &byte_counts, true)?;
+        }
+
+        let mut sink = PartSink::new(writer, self.part_size, checksums);
+        sink.write(layout.metadata).await?;
+        let mut byte_counts = byte_counts.into_iter().rev().flatten();
+        let mut tiles = pin!(self.encode_tiles(&images, &predictors));
+        while let Some(tile) = tiles.try_next().await? {
+            if byte_counts.next() != Some(tile.len() as u64) {
+                return Err(AsyncTiffError::General(
+                    "Encoding a tile again gave a different number of bytes".to_string(),
+                ));
+            }
+            sink.write(tile).await?;
+        }
+        let file_crc = sink.finish().await?;
+
+        let checksums = file_crc.zip(tile_crcs).map(|(file_crc, tile_crcs)| {
+            let tiles = layout
+                .tile_locations
+                .iter()
+                .zip(tile_crcs)
+                .map(|((offsets, byte_counts), crcs)| {
+                    (offsets.iter().zip(byte_counts).zip(crcs))
+                        .map(|((offset, byte_count), crc)| {
+                            TileChecksum::new(*offset, *byte_count, crc)
+                        })
+                        .collect()
+                })
+                .collect();
+            FileChecksums::new(layout.file_size, file_crc, tiles)
+        });
+        Ok((layout.file_size, checksums))
+    }
+
+    /// Encode the tiles of `images` in the order they are stored, from the last image to the
+    /// first, encoding up to [`encode_concurrency`](Self::encode_concurrency) tiles at once.
+    fn encode_tiles<'a>(
+        &'a self,
+        images: &'a [Arc<CogImage>],
+        predictors: &'a [Predictor],
+    ) -> impl Stream<Item = AsyncTiffResult<Bytes>> + 'a {
+        let jobs = images
+            .iter()
+            .zip(predictors)
+            .rev()
+            .flat_map(|(image, predictor)| {
+                (0..image.tiles.len()).map(move |index| (image.clone(), *predictor, index))
+            });
+        stream::iter(jobs)
+            .map(|(image, predictor, index)| {
+                let encoders = self.encoders.clone();
+                self.run(move || image.encode_tile(index, predictor, &encoders))
+            })
+            .buffered(self.encode_concurrency)
+    }
+
+    /// Run an encoding job on the pool, if set.
+    async fn run<R, F>(&self, job: F) -> AsyncTiffResult<R>
+    where
+        F: FnOnce() -> AsyncTiffResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        match &self.pool {
+            Some(pool) => pool.spawn(job).await?,
+            None => job(),
+        }
+    }
+}
+
+/// The IFD of an overview of `ifd`, holding the decoded samples of `raster`, compressed like
+/// `ifd`.
+fn overview_ifd(
+    ifd: &ImageFileDirectory,
+    raster: &Raster,
+    registry: &DecoderRegistry,
+    encoders: &EncoderRegistry,
+) -> AsyncTiffResult<ImageFileDirectory> {
+    if !encoders.as_ref().contains_key(&ifd.compression) {
+        return Err(TiffError::UnsupportedError(
+            TiffUnsupportedError::UnsupportedCompressionMethod(ifd.compression),
+        )
+        .into());
+    }
+    let samples_per_pixel = raster.samples_per_pixel() as u16;
+    // Decoding converts some color spaces, such as JPEG-compressed YCbCr, to RGB
+    let photometric_interpretation = match ifd.photometric_interpretation {
+        PhotometricInterpretation::YCbCr
+            if matches!(
+                ifd.compression,
+                CompressionMethod::JPEG | CompressionMethod::ModernJPEG
+            ) =>
+        {
+            PhotometricInterpretation::RGB
+        }
+        photometric
+            if registry
+                .photometric_registry()
+                .as_ref()
+                .contains_key(&photometric) =>
+        {
+            if samples_per_pixel >= 3 {
+                PhotometricInterpretation::RGB
+            } else {
+                PhotometricInterpretation::BlackIsZero
+            }
+        }
+        photometric => photometric,
+    };
+    let mut builder = IfdBuilder::new(raster.width() as u32, raster.height() as u32)
+        .with_data_type(raster.sample_format(), raster.bits_per_sample())
+        .with_samples_per_pixel(samples_per_pixel)
+        .with_photometric_interpretation(photometric_interpretation)
+        .with_tiling(
+            ifd.tile_width.unwrap_or_default(),
+            ifd.tile_height.unwrap_or_default(),
+        )
+        .with_compression(ifd.compression);
+    if let Some(extra_samples) = &ifd.extra_samples {
+        let extra_samples = extra_samples
+            .iter()
+            .map(|v| ExtraSample::from_u16(*v).unwrap_or(ExtraSample::Unspecified))
+            .collect();
+        builder = builder.with_extra_samples(extra_samples);
+    }
+    if let Some(color_map) = ifd
+        .color_map
+        .as_ref()
+        .filter(|_| photometric_interpretation == PhotometricInterpretation::RGBPalette)
+    {
+        builder = builder.with_color_map(color_map.to_vec());
+    }
+    if let Some(nodata) = ifd.other_tags.get(&Tag::GdalNodata) {
+        builder = builder.with_tag(Tag::GdalNodata, nodata.clone());
+    }
+    builder.build()
+}
+
+/// The tags of an image to write, without its tile locations.
+fn image_tags(ifd: &ImageFileDirectory, overview: bool) -> AsyncTiffResult<HashMap<Tag, Value>> {
+    let mut tags = ifd.to_tags()?;
+    for tag in [
+        Tag::StripOffsets,
+        Tag::StripByteCounts,
+        Tag::RowsPerStrip,
+        Tag::TileOffsets,
+        Tag::TileByteCounts,
+    ] {
+        tags.remove(&tag);
+    }
+    tags.retain(|_, value| !is_ifd_pointer(value));
+    if overview && ifd.new_subfile_type.is_none() {
+        tags.insert(Tag::NewSubfileType, Value::Unsigned(REDUCED_RESOLUTION));
+    }
+    Ok(tags)
+}
+
+fn is_ifd_pointer(value: &Value) -> bool {
+    match value {
+        Value::Ifd(_) | Value::IfdBig(_) => true,
+        Value::List(values) => values.iter().any(is_ifd_pointer),
+        _ => false,
+    }
+}
+
+/// The placement of the IFDs and tiles of a file.
+struct Layout {
+    /// The header, ghost metadata and IFDs.
+    metadata: Bytes,
+    /// The offsets and byte counts of the tiles of each image.
+    tile_locations: Vec<(Vec<u64>, Vec<u64>)>,
+    file_size: u64,
+}
+
+impl Layout {
+    /// Lay out images with `tags` and tiles of `byte_counts`.
+    fn new(
+        tags: &[HashMap<Tag, Value>],
+        byte_counts: &[Vec<u64>],
+        bigtiff: bool,
+    ) -> AsyncTiffResult<Self> {
+        let header_len = if bigtiff { 16 } else { 8 };
+        let ghost = format!(
+            "GDAL_STRUCTURAL_METADATA_SIZE={:06} bytes\n{GHOST_METADATA}",
+            GHOST_METADATA.len()
+        );
+
+        // The size of an IFD doesn't depend on the offsets it holds, so lay them out with
+        // placeholder tile locations first.
+        let mut ifd_offsets = Vec::with_capacity(tags.len());
+        let mut offset = header_len + ghost.len() as u64;
+        for (tags, image_byte_counts) in tags.iter().zip(byte_counts) {
+            offset += offset % 2;
+            ifd_offsets.push(offset);
+            let placeholder = vec![0; image_byte_counts.len()];
+            let ifd =
+                IfdEncoder::new(bigtiff).encode(tags, (&placeholder, &placeholder), offset, 0)?;
+            offset += ifd.len() as u64;
+        }
+
+        // Tile data follows, from the last image to the first
+        let mut tile_locations = vec![(vec![], vec![]); byte_counts.len()];
+        for (image_byte_counts, (offsets, location_byte_counts)) in
+            byte_counts.iter().zip(tile_locations.iter_mut()).rev()
+        {
+            for &byte_count in image_byte_counts {
+                offsets.push(offset);
+                location_byte_counts.push(byte_count);
+                offset += byte_count;
+            }
+        }
+        let file_size = offset;
+
+        let mut metadata = Vec::with_capacity(ifd_offsets.last().copied().unwrap_or(0) as usize);
+        metadata.extend_from_slice(b"II");
+        if bigtiff {
+            metadata.extend_from_slice(&43u16.to_le_bytes());
+            metadata.extend_from_slice(&8u16.to_le_bytes());
+            metadata.extend_from_slice(&0u16.to_le_bytes());
+            metadata.extend_from_slice(&ifd_offsets[0].to_le_bytes());
+        } else {
+            metadata.extend_from_slice(&42u16.to_le_bytes());
+            metadata.extend_from_slice(&(ifd_offsets[0] as u32).to_le_bytes());
+        }
+        metadata.extend_from_slice(ghost.as_bytes());
+        for (i, (tags, (offsets, byte_counts))) in tags.iter().zip(&tile_locations).enumerate() {
+            metadata.resize(ifd_offsets[i] as usize, 0);
+            let next = ifd_offsets.get(i + 1).copied().unwrap_or(0);
+            let ifd = IfdEncoder::new(bigtiff).encode(
+                tags,
+                (offsets, byte_counts),
+                ifd_offsets[i],
+                next,
+            )?;
+            metadata.extend_from_slice(&ifd);
+        }
+
+        Ok(Self {
+            metadata: metadata.into(),
+            tile_locations,
+            file_size,
+        })
+    }
+}
+
+/// Serializes IFDs as little-endian classic TIFF or BigTIFF.
+struct IfdEncoder {
+    bigtiff: bool,
+}
+
+impl IfdEncoder {
+    fn new(bigtiff: bool) -> Self {
+        Self { bigtiff }
+    }
+
+    /// Encode an IFD starting at `offset` of the file, followed by the values that don't fit in
+    /// its entries.
+    fn encode(
+        &self,
+        tags: &HashMap<Tag, Value>,
+        (tile_offsets, tile_byte_counts): (&[u64], &[u64]),
+        offset: u64,
+        next_ifd: u64,
+    ) -> AsyncTiffResult<Vec<u8>> {
+        let long = |v: u64| {
+            if self.bigtiff {
+                Value::UnsignedBig(v)
+            } else {
+                Value::Unsigned(v as u32)
+            }
+        };
+        let longs = |values: &[u64]| Value::List(values.iter().map(|v| long(*v)).collect());
+        let mut entries = tags.iter().collect::<Vec<_>>();
+        let locations = [
+            (Tag::TileOffsets, longs(tile_offsets)),
+            (Tag::TileByteCounts, longs(tile_byte_counts)),
+        ];
+        entries.extend(locations.iter().map(|(tag, value)| (tag, value)));
+        entries.sort_by_key(|(tag, _)| tag.to_u16());
+
+        let (count_len, entry_len, inline_len) = if self.bigtiff { (8, 20, 8) } else { (2, 12, 4) };
+        let entries_end = offset + count_len + entries.len() as u64 * entry_len + inline_len;
+        let mut ifd = Vec::with_capacity((entries_end - offset) as usize);
+        let mut values = vec![];
+        self.put_uint(&mut ifd, entries.len() as u64, count_len as usize);
+        for (tag, value) in entries {
+            let (field_type, count, data) = self.encode_value(*tag, value)?;
+            ifd.extend_from_slice(&tag.to_u16().to_le_bytes());
+            ifd.extend_from_slice(&field_type.to_u16().to_le_bytes());
+            self.put_uint(&mut ifd, count, inline_len as usize);
+            if data.len() <= inline_len as usize {
+                let start = ifd.len();
+                ifd.extend_from_slice(&data);
+                ifd.resize(start + inline_len as usize, 0);
+            } else {
+                values.resize(values.len() + values.len() % 2, 0);
+                self.put_uint(
+                    &mut ifd,
+                    entries_end + values.len() as u64,
+                    inline_len as usize,
+                );
+                values.extend_from_slice(&data);
+            }
+        }
+        self.put_uint(&mut ifd, next_ifd, inline_len as usize);
+        ifd.extend_from_slice(&values);
+        Ok(ifd)
+    }
+
+    fn put_uint(&self, buf: &mut Vec<u8>, value: u64, len: usize) {
+        buf.extend_from_slice(&value.to_le_bytes()[..len]);
+    }
+
+    /// The type, count and little-endian bytes of a tag value.
+    fn encode_value(&self, tag: Tag, value: &Value) -> AsyncTiffResult<(Type, u64, Vec<u8>)> {
+        let values = match value {
+            Value::List(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        let mut field_type = None;
+        let mut count = 0;
+        let mut data = vec![];
+        for value in values {
+            let (value_type, value_count) = self.encode_scalar(value, &mut data)?;
+            if field_type.is_some_and(|t| t != value_type) {
+                return Err(AsyncTiffError::General(format!(
+                    "Mixed value types in tag {tag:?}"
+                )));
+            }
+            field_type = Some(value_type);
+            count += value_count;
+        }
+        let field_type = match (tag, field_type) {
+            (Tag::JPEGTables, _) => Type::UNDEFINED,
+            (_, Some(field_type)) => field_type,
+            (_, None) => Type::BYTE,
+        };
+        Ok((field_type, count, data))
+    }
+
+    fn encode_scalar(&self, value: &Value, data: &mut Vec<u8>) -> AsyncTiffResult<(Type, u64)> {
+        let overflow = || AsyncTiffError::from(TiffError::IntSizeError);
+        let field_type = match *value {
+            Value::Byte(v) => {
+                data.push(v);
+                Type::BYTE
+            }
+            Value::SignedByte(v) => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::SBYTE
+            }
+            Value::Short(v) => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::SHORT
+            }
+            Value::SignedShort(v) => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::SSHORT
+            }
+            Value::Unsigned(v) => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::LONG
+            }
+            Value::Signed(v) => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::SLONG
+            }
+            Value::UnsignedBig(v) if self.bigtiff => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::LONG8
+            }
+            Value::UnsignedBig(v) => {
+                let v = u32::try_from(v).map_err(|_| overflow())?;
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::LONG
+            }
+            Value::SignedBig(v) if self.bigtiff => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::SLONG8
+            }
+            Value::SignedBig(v) => {
+                let v = i32::try_from(v).map_err(|_| overflow())?;
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::SLONG
+            }
+            Value::Float(v) => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::FLOAT
+            }
+            Value::Double(v) => {
+                data.extend_from_slice(&v.to_le_bytes());
+                Type::DOUBLE
+            }
+            Value::Rational(n, d) => {
+                data.extend_from_slice(&n.to_le_bytes());
+                data.extend_from_slice(&d.to_le_bytes());
+                Type::RATIONAL
+            }
+            Value::RationalBig(n, d) => {
+                let n = u32::try_from(n).map_err(|_| overflow())?;
+                let d = u32::try_from(d).map_err(|_| overflow())?;
+                data.extend_from_slice(&n.to_le_bytes());
+                data.extend_from_slice(&d.to_le_bytes());
+                Type::RATIONAL
+            }
+            Value::SRational(n, d) => {
+                data.extend_from_slice(&n.to_le_bytes());
+                data.extend_from_slice(&d.to_le_bytes());
+                Type::SRATIONAL
+            }
+            Value::SRationalBig(n, d) => {
+                let n = i32::try_from(n).map_err(|_| overflow())?;
+                let d = i32::try_from(d).map_err(|_| overflow())?;
+                data.extend_from_slice(&n.to_le_bytes());
+                data.extend_from_slice(&d.to_le_bytes());
+                Type::SRATIONAL
+            }
+            Value::Ascii(ref s) => {
+                data.extend_from_slice(s.as_bytes());
+                data.push(0);
+                return Ok((Type::ASCII, s.len() as u64 + 1));
+            }
+            Value::Ifd(_) | Value::IfdBig(_) | Value::List(_) => {
+                return Err(AsyncTiffError::General(format!(
+                    "Cannot write tag value {value:?}"
+                )))
+            }
+        };
+        Ok((field_type, 1))
+    }
+}
+
+/// Convert native-endian samples to little-endian.
+fn to_little_endian(data: Bytes, bits_per_sample: u16) -> Bytes {
+    let size = bits_per_sample as usize / 8;
+    if cfg!(target_endian = "little") || !matches!(size, 2 | 4 | 8) {
+        return data;
+    }
+    let mut data = data.to_vec();
+    for sample in data.chunks_exact_mut(size) {
+        sample.reverse();
+    }
+    data.into()
+}
+
+/// Passes a file to an [`AsyncFileWriter`], optionally in parts of a fixed size, and optionally
+/// computing its checksum.
+struct PartSink<'a, W: AsyncFileWriter + ?Sized> {
+    writer: &'a mut W,
+    part_size: Option<usize>,
+    buffer: Vec<u8>,
+    hasher: Option<crc32fast::Hasher>,
+}
+
+impl<'a, W: AsyncFileWriter + ?Sized> PartSink<'a, W> {
+    fn new(writer: &'a mut W, part_size: Option<usize>, checksum: bool) -> Self {
+        Self {
+            writer,
+            part_size,
+            buffer: vec![],
+            hasher: checksum.then(crc32fast::Hasher::new),
+        }
+    }
+
+    async fn write(&mut self, mut data: Bytes) -> AsyncTiffResult<()> {
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&data);
+        }
+        let Some(part_size) = self.part_size else {
+            return self.writer.write(data).await;
+        };
+        if !self.buffer.is_empty() {
+            let len = (part_size - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..len]);
+            data.advance(len);
+            if self.buffer.len() < part_size {
+                return Ok(());
+            }
+            let part = std::mem::take(&mut self.buffer);
+            self.writer.write(part.into()).await?;
+        }
+        while data.len() >= part_size {
+            self.writer.write(data.split_to(part_size)).await?;
+        }
+        self.buffer.extend_from_slice(&data);
+        Ok(())
+    }
+
+    /// Flush and finish the writer, returning the checksum of the file if computed.
+    async fn finish(self) -> AsyncTiffResult<Option<u32>> {
+        if !self.buffer.is_empty() {
+            self.writer.write(self.buffer.into()).await?;
+        }
+        self.writer.finish().await?;
+        Ok(self.hasher.map(crc32fast::Hasher::finalize))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use futures::future::BoxFuture;
+
+    use super::*;
+    use crate::reader::MemoryReader;
+    use crate::writer::MemoryWriter;
+    use crate::ChecksumValidator;
+
+    /// Records the size of each write.
+    #[derive(Debug, Default)]
+    struct RecordingWriter {
+        inner: MemoryWriter,
+        writes: Vec<usize>,
+    }
+
+    impl AsyncFileWriter for RecordingWriter {
+        fn write(&mut self, data: Bytes) -> BoxFuture<'_, AsyncTiffResult<()>> {
+            self.writes.push(data.len());
+            self.inner.write(data)
+        }
+
+        fn finish(&mut self) -> BoxFuture<'_, AsyncTiffResult<()>> {
+            self.inner.finish()
+        }
+    }
+
+    fn images(compression: CompressionMethod) -> Vec<CogImage> {
+        let image = |size: u32, value: u16| {
+            let ifd = IfdBuilder::new(size, size)
+                .with_data_type(SampleFormat::Uint, 16)
+                .with_tiling(16, 16)
+                .with_compression(compression)
+                .with_model_pixel_scale([10.0, 10.0, 0.0])
+                .with_tag(Tag::Software, Value::Ascii("async-tiff".to_string()))
+                .build()
+                .unwrap();
+            let (x_count, y_count) = ifd.tile_count().unwrap();
+            let tiles = (0..x_count * y_count)
+                .map(|i| {
+                    let tile = (0..256u16).flat_map(|v| (v + value * i as u16).to_ne_bytes());
+                    Bytes::from(tile.collect::<Vec<_>>())
+                })
+                .collect();
+            CogImage::new(ifd, tiles)
+        };
+        vec![image(40, 1), image(20, 2), image(10, 3)]
+    }
+
+    async fn read_back(file: Bytes, images: &[CogImage]) {
+        let reader = MemoryReader::new(file);
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        let ghost = metadata
+            .read_ghost_metadata(&reader)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ghost.get("LAYOUT"), Some("IFDS_BEFORE_DATA"));
+        let ifds = metadata.read_all_ifds(&reader).await.unwrap();
+        assert_eq!(ifds.len(), images.len());
+
+        let registry = DecoderRegistry::default();
+        for (i, (ifd, image)) in ifds.iter().zip(images).enumerate() {
+            assert_eq!(ifd.image_width, image.ifd.image_width);
+            assert_eq!(ifd.compression, image.ifd.compression);
+            assert_eq!(ifd.software(), Some("async-tiff"));
+            assert_eq!(ifd.model_pixel_scale(), Some(&[10.0, 10.0, 0.0][..]));
+            assert_eq!(ifd.new_subfile_type, (i > 0).then_some(REDUCED_RESOLUTION));
+            let (x_count, y_count) = ifd.tile_count().unwrap();
+            for y in 0..y_count {
+                for x in 0..x_count {
+                    let tile = ifd.fetch_tile(x, y, &reader).await.unwrap();
+                    let decoded = tile.decode(&registry).unwrap();
+                    assert_eq!(decoded, image.tiles[y * x_count + x]);
+                }
+            }
+        }
+
+        // Tile data is ordered from the smallest overview to the full resolution image
+        let first_offset = |ifd: &ImageFileDirectory| ifd.tile_offsets().unwrap()[0];
+        assert!(first_offset(&ifds[2]) < first_offset(&ifds[1]));
+        assert!(first_offset(&ifds[1]) < first_offset(&ifds[0]));
+    }
+
+    #[tokio::test]
+    async fn test_write_cog() {
+        for compression in [
+            CompressionMethod::None,
+            CompressionMethod::Deflate,
+            CompressionMethod::LZW,
+            #[cfg(feature = "zstd")]
+            CompressionMethod::ZSTD,
+        ] {
+            for bigtiff in [false, true] {
+                let images = images(compression);
+                let mut writer = MemoryWriter::new();
+                let size = CogWriter::new()
+                    .with_bigtiff(bigtiff)
+                    .write(images.clone(), &mut writer)
+                    .await
+                    .unwrap();
+                let file = writer.into_inner();
+                assert_eq!(size, file.len() as u64);
+                assert_eq!(file[2], if bigtiff { 43 } else { 42 });
+                read_back(file, &images).await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_cog_part_size() {
+        let images = images(CompressionMethod::None);
+        let mut unaligned = MemoryWriter::new();
+        CogWriter::new()
+            .write(images.clone(), &mut unaligned)
+            .await
+            .unwrap();
+
+        let mut writer = RecordingWriter::default();
+        let size = CogWriter::new()
+            .with_part_size(1000)
+            .write(images, &mut writer)
+            .await
+            .unwrap();
+        let (last, parts) = writer.writes.split_last().unwrap();
+        assert!(parts.iter().all(|len| *len == 1000));
+        assert_eq!(size, (parts.len() * 1000 + last) as u64);
+        assert_eq!(writer.inner.data(), unaligned.data());
+    }
+
+    #[tokio::test]
+    async fn test_write_cog_pool() {
+        let images = images(CompressionMethod::Deflate);
+        let mut serial = MemoryWriter::new();
+        CogWriter::new()
+            .write(images.clone(), &mut serial)
+            .await
+            .unwrap();
+
+        let writer = CogWriter::new()
+            .with_pool(DecodePool::new(2).unwrap())
+            .with_encode_concurrency(3);
+        assert_eq!(writer.encode_concurrency(), 3);
+        let mut pooled = MemoryWriter::new();
+        writer.write(images.clone(), &mut pooled).await.unwrap();
+        assert_eq!(pooled.data(), serial.data());
+        read_back(pooled.into_inner(), &images).await;
+    }
+
+    #[tokio::test]
+    async fn test_write_cog_statistics() {
+        let mut images = images(CompressionMethod::Deflate);
+        let statistics = images[0].compute_statistics().unwrap();
+        assert_eq!((statistics[0].min(), statistics[0].max()), (0.0, 259.0));
+        assert_eq!(statistics[0].valid_percent(), 100.0);
+        let histogram = statistics[0].histogram().unwrap();
+        assert_eq!(histogram.counts().iter().sum::<u64>(), 40 * 40);
+
+        let precomputed = BandStatistics::new(1.0, 2.0, 1.5, 0.5, 100.0);
+        images[2] = images[2].clone().with_statistics(vec![precomputed]);
+        let mut writer = MemoryWriter::new();
+        CogWriter::new()
+            .with_statistics(true)
+            .write(images, &mut writer)
+            .await
+            .unwrap();
+
+        let reader = MemoryReader::new(writer.into_inner());
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        let ifds = metadata.read_all_ifds(&reader).await.unwrap();
+        let xml = ifds[0].gdal_metadata().unwrap();
+        assert!(xml.contains(r#"<Item name="STATISTICS_MAXIMUM" sample="0">259</Item>"#));
+        assert!(xml.contains(&format!(
+            r#"<Item name="STATISTICS_MEAN" sample="0">{}</Item>"#,
+            statistics[0].mean()
+        )));
+        assert_eq!(ifds[1].gdal_metadata(), None);
+        let xml = ifds[2].gdal_metadata().unwrap();
+        assert!(xml.contains(r#"<Item name="STATISTICS_MEAN" sample="0">1.5</Item>"#));
+    }
+
+    #[tokio::test]
+    async fn test_write_cog_color() {
+        let color_map = (0..768).map(|v| v * 85).collect::<Vec<u16>>();
+        let palette = IfdBuilder::new(16, 16)
+            .with_tiling(16, 16)
+            .with_photometric_interpretation(PhotometricInterpretation::RGBPalette)
+            .with_color_map(color_map.clone())
+            .build()
+            .unwrap();
+        let rgba = IfdBuilder::new(16, 16)
+            .with_samples_per_pixel(4)
+            .with_extra_samples(vec![ExtraSample::AssociatedAlpha])
+            .with_tiling(16, 16)
+            .build()
+            .unwrap();
+        for (ifd, tile_bytes) in [(palette, 256), (rgba, 1024)] {
+            let image = CogImage::new(ifd.clone(), vec![Bytes::from(vec![1; tile_bytes])]);
+            let mut writer = MemoryWriter::new();
+            CogWriter::new()
+                .write(vec![image], &mut writer)
+                .await
+                .unwrap();
+            let reader = MemoryReader::new(writer.into_inner());
+            let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+            let written = metadata.read_next_ifd(&reader).await.unwrap().unwrap();
+            assert_eq!(
+                written.photometric_interpretation(),
+                ifd.photometric_interpretation()
+            );
+            assert_eq!(written.extra_samples(), ifd.extra_samples());
+            assert_eq!(written.color_map.as_deref(), ifd.color_map.as_deref());
+        }
+
+        // ExtraSamples must account for every sample beyond the color space
+        let mut ifd = IfdBuilder::new(16, 16)
+            .with_samples_per_pixel(4)
+            .with_tiling(16, 16)
+            .build()
+            .unwrap();
+        ifd.extra_samples = None;
+        ifd.photometric_interpretation = PhotometricInterpretation::CMYK;
+        let image = CogImage::new(ifd.clone(), vec![Bytes::from(vec![0; 1024])]);
+        CogWriter::new()
+            .write(vec![image], &mut MemoryWriter::new())
+            .await
+            .unwrap();
+        ifd.extra_samples = Some(vec![0]);
+        let image = CogImage::new(ifd, vec![Bytes::from(vec![0; 1024])]);
+        let err = CogWriter::new()
+            .write(vec![image], &mut MemoryWriter::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("extra samples"));
+    }
+
+    #[tokio::test]
+    async fn test_write_cog_checksums() {
+        let images = images(CompressionMethod::Deflate);
+        let tag = Tag::Unknown(65000);
+        let mut writer = MemoryWriter::new();
+        let checksums = CogWriter::new()
+            .with_checksum_tag(tag)
+            .write_with_checksums(images.clone(), &mut writer)
+            .await
+            .unwrap();
+        let file = writer.into_inner();
+        assert_eq!(checksums.size(), file.len() as u64);
+        assert_eq!(checksums.crc32(), crc32fast::hash(&file));
+        assert_eq!(checksums.tiles().len(), 3);
+        assert_eq!(checksums.tiles()[0].len(), 9);
+
+        let reader = MemoryReader::new(file.clone());
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        let ifds = metadata.read_all_ifds(&reader).await.unwrap();
+        for (i, ifd) in ifds.into_iter().enumerate() {
+            let from_tag = ChecksumValidator::from_ifd(&ifd, tag).unwrap().unwrap();
+            let from_sidecar = checksums.validator(i).unwrap();
+            for validator in [from_tag, from_sidecar] {
+                let ifd = ifd.clone().with_byte_transform(Arc::new(validator));
+                ifd.fetch_tile(0, 0, &reader).await.unwrap();
+            }
+        }
+
+        // Corrupt the last tile of the full resolution image
+        let last = checksums.tiles()[0][8];
+        let mut corrupt = file.to_vec();
+        corrupt[last.offset() as usize] ^= 0xFF;
+        let reader = MemoryReader::new(corrupt);
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        let ifd = metadata.read_next_ifd(&reader).await.unwrap().unwrap();
+        let ifd = ifd.with_byte_transform(Arc::new(checksums.validator(0).unwrap()));
+        ifd.fetch_tile(1, 1, &reader).await.unwrap();
+        let err = ifd.fetch_tile(2, 2, &reader).await.unwrap_err();
+        assert!(err.is_format_error());
+        assert!(matches!(
+            err,
+            AsyncTiffError::ChecksumMismatch { offset, expected, .. }
+                if offset == last.offset() && expected == last.crc32()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_write_cog_predictor() {
+        let registry = &DecoderRegistry::default();
+        let write = |writer: CogWriter, images: Vec<CogImage>| async move {
+            let mut file = MemoryWriter::new();
+            writer.write(images, &mut file).await?;
+            let reader = MemoryReader::new(file.into_inner());
+            let mut metadata = TiffMetadataReader::try_open(&reader).await?;
+            let ifd = metadata.read_next_ifd(&reader).await?.unwrap();
+            let tile = ifd.fetch_tile(1, 1, &reader).await?;
+            AsyncTiffResult::Ok((ifd.predictor(), tile.decode(registry)?))
+        };
+
+        // Smooth integer samples are smaller with horizontal differencing
+        let deflate = images(CompressionMethod::Deflate);
+        let (predictor, tile) = write(CogWriter::new(), deflate.clone()).await.unwrap();
+        assert_eq!(predictor, Some(Predictor::Horizontal));
+        assert_eq!(tile, deflate[0].tiles()[4]);
+        let (predictor, tile) = write(
+            CogWriter::new().with_predictor(Predictor::None),
+            deflate.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(predictor, None);
+        assert_eq!(tile, deflate[0].tiles()[4]);
+        let (predictor, _) = write(CogWriter::new(), images(CompressionMethod::None))
+            .await
+            .unwrap();
+        assert_eq!(predictor, None);
+
+        // And floating point samples with floating point prediction
+        let ifd = IfdBuilder::new(32, 32)
+            .with_data_type(SampleFormat::IEEEFP, 32)
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::LZW)
+            .build()
+            .unwrap();
+        let tiles = (0..4)
+            .map(|i| {
+                let tile =
+                    (0..256).flat_map(|v| (1000.0 + (v + i * 256) as f32 * 0.25).to_ne_bytes());
+                Bytes::from(tile.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        let image = CogImage::new(ifd, tiles.clone());
+        let (predictor, tile) = write(CogWriter::new(), vec![image.clone()]).await.unwrap();
+        assert_eq!(predictor, Some(Predictor::FloatingPoint));
+        assert_eq!(tile, tiles[3]);
+        let (predictor, tile) = write(
+            CogWriter::new().with_predictor(Predictor::Horizontal),
+            vec![image],
+        )
+        .await
+        .unwrap();
+        assert_eq!(predictor, Some(Predictor::Horizontal));
+        assert_eq!(tile, tiles[3]);
+
+        // The floating point predictor requires floating point samples
+        let err = write(
+            CogWriter::new().with_predictor(Predictor::FloatingPoint),
+            deflate,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.is_unsupported());
+    }
+
+    #[tokio::test]
+    async fn test_inject_overviews() {
+        let mut images = images(CompressionMethod::Deflate);
+        images.truncate(1);
+        let mut source = MemoryWriter::new();
+        CogWriter::new().write(images, &mut source).await.unwrap();
+        let source = MemoryReader::new(source.into_inner());
+
+        let registry = DecoderRegistry::default();
+        let mut writer = MemoryWriter::new();
+        let size = CogWriter::new()
+            .inject_overviews(&source, &registry, &mut writer)
+            .await
+            .unwrap();
+        let file = writer.into_inner();
+        assert_eq!(size, file.len() as u64);
+
+        let reader = MemoryReader::new(file);
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        let ifds = metadata.read_all_ifds(&reader).await.unwrap();
+        let widths = ifds.iter().map(|ifd| ifd.image_width).collect::<Vec<_>>();
+        assert_eq!(widths, [40, 20, 10]);
+        assert_eq!(ifds[0].software(), Some("async-tiff"));
+        assert_eq!(ifds[1].new_subfile_type, Some(REDUCED_RESOLUTION));
+        assert_eq!(ifds[1].compression, CompressionMethod::Deflate);
+
+        // Full resolution tiles are copied without being re-encoded
+        let mut source_metadata = TiffMetadataReader::try_open(&source).await.unwrap();
+        let source_ifd = source_metadata
+            .read_next_ifd(&source)
+            .await
+            .unwrap()
+            .unwrap();
+        for i in 0..9 {
+            let range = ifds[0].chunk_byte_range(i).unwrap();
+            let source_range = source_ifd.chunk_byte_range(i).unwrap();
+            assert_eq!(
+                reader.get_bytes(range).await.unwrap(),
+                source.get_bytes(source_range).await.unwrap()
+            );
+        }
+
+        // The first pixel of the first overview averages the samples 0, 1, 16 and 17
+        let tile = ifds[1].fetch_tile(0, 0, &reader).await.unwrap();
+        let decoded = tile.decode(&registry).unwrap();
+        assert_eq!(decoded[..2], 9u16.to_ne_bytes());
+    }
+
+    #[cfg(feature = "jpeg-encoder")]
+    #[tokio::test]
+    async fn test_write_cog_jpeg() {
+        let ifd = IfdBuilder::new(32, 32)
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::ModernJPEG)
+            .build()
+            .unwrap();
+        let tiles = (0..4)
+            .map(|i| Bytes::from((0..256).map(|v| (v + i * 16) as u8).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let mut writer = MemoryWriter::new();
+        CogWriter::new()
+            .write(vec![CogImage::new(ifd, tiles.clone())], &mut writer)
+            .await
+            .unwrap();
+
+        let reader = MemoryReader::new(writer.into_inner());
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        let ifd = metadata.read_next_ifd(&reader).await.unwrap().unwrap();
+        assert_eq!(ifd.compression, CompressionMethod::ModernJPEG);
+        assert_eq!(ifd.predictor(), None);
+        let tile = ifd.fetch_tile(1, 1, &reader).await.unwrap();
+        let decoded = tile.decode(&DecoderRegistry::default()).unwrap();
+        assert!(decoded
+            .iter()
+            .zip(&tiles[3])
+            .all(|(a, b)| a.abs_diff(*b) <= 8));
+    }
+
+    #[tokio::test]
+    async fn test_inject_overviews_unsupported() {
+        let mut images = images(CompressionMethod::Deflate);
+        images.truncate(1);
+        let mut source = MemoryWriter::new();
+        CogWriter::new().write(images, &mut source).await.unwrap();
+        let source = MemoryReader::new(source.into_inner());
+
+        // Overviews keep the compression of the source, rather than falling back to another
+        let err = CogWriter::new()
+            .with_encoders(Arc::new(EncoderRegistry::new()))
+            .inject_overviews(
+                &source,
+                &DecoderRegistry::default(),
+                &mut MemoryWriter::new(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.is_unsupported());
+    }
+
+    #[tokio::test]
+    async fn test_write_cog_invalid() {
+        let write = |images| async move {
+            CogWriter::new()
+                .write(images, &mut MemoryWriter::new())
+                .await
+                .unwrap_err()
+        };
+        let ifd = IfdBuilder::new(16, 16).build().unwrap();
+        assert!(write(vec![CogImage::new(ifd, vec![])])
+            .await
+            .to_string()
+            .contains("tiled"));
+
+        let ifd = IfdBuilder::new(16, 16).with_tiling(16, 16).build().unwrap();
+        let tiles = vec![Bytes::from(vec![0; 255])];
+        assert!(write(vec![CogImage::new(ifd, tiles)])
+            .await
+            .to_string()
+            .contains("256"));
+
+        let ifd = IfdBuilder::new(16, 16)
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::PackBits)
+            .build()
+            .unwrap();
+        let tiles = vec![Bytes::from(vec![0; 256])];
+        assert!(write(vec![CogImage::new(ifd, tiles)])
+            .await
+            .is_unsupported());
+    }
+}
@@ -0,0 +1,68 @@
+//! Parsing of TIFF date/time strings.
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// Parse a TIFF date/time string into a [`NaiveDateTime`].
+///
+/// The TIFF and EXIF specs require the format `"YYYY:MM:DD HH:MM:SS"`, but writers frequently
+/// deviate from it. This parser is lenient and accepts:
+///
+/// - any non-digit separator, e.g. `"YYYY-MM-DD HH:MM:SS"`, `"YYYY/MM/DD HH:MM:SS"` or
+///   `"YYYY-MM-DDTHH:MM:SS"`,
+/// - a missing time or missing seconds, which default to zero,
+/// - trailing NUL bytes, whitespace, fractional seconds or timezone offsets, which are ignored.
+///
+/// Returns `None` for blank or all-zero placeholder values and for out-of-range fields.
+pub(crate) fn parse_date_time(s: &str) -> Option<NaiveDateTime> {
+    let mut fields = s
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|field| !field.is_empty())
+        .map(|field| field.parse::<u32>().ok());
+
+    let year = fields.next()??;
+    let month = fields.next()??;
+    let day = fields.next()??;
+    let hour = fields.next().flatten().unwrap_or(0);
+    let minute = fields.next().flatten().unwrap_or(0);
+    let second = fields.next().flatten().unwrap_or(0);
+
+    NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, month, day)?
+        .and_hms_opt(hour, minute, second)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dt(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, s)
+    }
+
+    #[test]
+    fn test_parse_date_time() {
+        let expected = dt(2024, 3, 29, 14, 52, 50);
+        assert_eq!(parse_date_time("2024:03:29 14:52:50"), expected);
+        assert_eq!(parse_date_time("2024:03:29 14:52:50\0"), expected);
+        assert_eq!(parse_date_time("2024-03-29 14:52:50"), expected);
+        assert_eq!(parse_date_time("2024/03/29 14:52:50"), expected);
+        assert_eq!(parse_date_time("2024-03-29T14:52:50"), expected);
+        assert_eq!(parse_date_time(" 2024:03:29 14:52:50 "), expected);
+        assert_eq!(parse_date_time("2024:03:29 14:52:50.123"), expected);
+        assert_eq!(parse_date_time("2024:03:29"), dt(2024, 3, 29, 0, 0, 0));
+        assert_eq!(
+            parse_date_time("2024:03:29 14:52"),
+            dt(2024, 3, 29, 14, 52, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_date_time_invalid() {
+        assert_eq!(parse_date_time(""), None);
+        assert_eq!(parse_date_time("    :  :     :  :  "), None);
+        assert_eq!(parse_date_time("0000:00:00 00:00:00"), None);
+        assert_eq!(parse_date_time("2024:13:29 14:52:50"), None);
+        assert_eq!(parse_date_time("2024:03:29 25:52:50"), None);
+    }
+}
@@ -0,0 +1,197 @@
+use zerocopy::{FromBytes, IntoBytes};
+
+use crate::decoder::DecodingResult;
+
+/// Bytes aligned for every sample type, so that they can be viewed as samples of any type
+/// without copying.
+///
+/// Plain byte buffers such as [`Bytes`](bytes::Bytes) or `Vec<u8>` only guarantee an alignment
+/// of one, so reinterpreting them as, say, `f64` samples is only possible by copying. This buffer
+/// is backed by 8-byte words instead, which suits handing decoded samples to libraries that
+/// expect typed, aligned memory, like Arrow or numpy, and reusing one buffer for samples of
+/// different types.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlignedBuffer {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    /// Create a buffer of `len` zero bytes.
+    pub fn zeroed(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(8)],
+            len,
+        }
+    }
+
+    /// Create a buffer holding a copy of `bytes`.
+    pub fn copy_from_slice(bytes: &[u8]) -> Self {
+        let mut buffer = Self::zeroed(bytes.len());
+        buffer.as_bytes_mut().copy_from_slice(bytes);
+        buffer
+    }
+
+    /// The length of the buffer in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the buffer holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Resize the buffer to `len` bytes, filling any new bytes with zeros.
+    ///
+    /// The allocation is kept when shrinking, so that the buffer can be reused.
+    pub fn resize(&mut self, len: usize) {
+        // Bytes past the length are kept zeroed, so that growing exposes zeros.
+        if len < self.len {
+            self.as_bytes_mut()[len..].fill(0);
+        }
+        self.words.resize(len.div_ceil(8), 0);
+        self.len = len;
+    }
+
+    /// The bytes of the buffer.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.words.as_bytes()[..self.len]
+    }
+
+    /// The bytes of the buffer, mutably.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.words.as_mut_bytes()[..self.len]
+    }
+
+    /// View the bytes as native-endian samples of type `T`.
+    ///
+    /// Returns `None` if the length isn't a multiple of the size of `T`.
+    pub fn as_slice<T: AlignedSample>(&self) -> Option<&[T]> {
+        T::cast(self.as_bytes())
+    }
+
+    /// View the bytes as native-endian samples of type `T`, mutably.
+    ///
+    /// Returns `None` if the length isn't a multiple of the size of `T`.
+    pub fn as_mut_slice<T: AlignedSample>(&mut self) -> Option<&mut [T]> {
+        T::cast_mut(self.as_bytes_mut())
+    }
+}
+
+/// A primitive sample type that an [`AlignedBuffer`] can be viewed as.
+///
+/// This is implemented for the integer and floating point primitives. It is sealed and can't be
+/// implemented outside of this crate. [`F16`](crate::decoder::F16) samples can be viewed as their
+/// `u16` bits.
+pub trait AlignedSample: private::Sealed + Copy + Send + Sync + 'static {
+    /// View `bytes` as samples, if their length is a multiple of the sample size.
+    fn cast(bytes: &[u8]) -> Option<&[Self]>;
+
+    /// View `bytes` as samples mutably, if their length is a multiple of the sample size.
+    fn cast_mut(bytes: &mut [u8]) -> Option<&mut [Self]>;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_aligned_sample {
+    ($($ty:ty),*) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl AlignedSample for $ty {
+                fn cast(bytes: &[u8]) -> Option<&[Self]> {
+                    <[$ty]>::ref_from_bytes(bytes).ok()
+                }
+
+                fn cast_mut(bytes: &mut [u8]) -> Option<&mut [Self]> {
+                    <[$ty]>::mut_from_bytes(bytes).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_aligned_sample!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl DecodingResult {
+    /// Copy the native-endian bytes of the samples into an [`AlignedBuffer`].
+    pub fn to_aligned(&self) -> AlignedBuffer {
+        let mut buffer = AlignedBuffer::zeroed(self.len() * self.bytes_per_sample());
+        match self {
+            Self::U8(data) => buffer.as_bytes_mut().copy_from_slice(data),
+            Self::U16(data) => copy_into(&mut buffer, data),
+            Self::U32(data) => copy_into(&mut buffer, data),
+            Self::U64(data) => copy_into(&mut buffer, data),
+            Self::I8(data) => copy_into(&mut buffer, data),
+            Self::I16(data) => copy_into(&mut buffer, data),
+            Self::I32(data) => copy_into(&mut buffer, data),
+            Self::I64(data) => copy_into(&mut buffer, data),
+            Self::F16(data) => {
+                let bits = data.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+                copy_into(&mut buffer, &bits)
+            }
+            Self::F32(data) => copy_into(&mut buffer, data),
+            Self::F64(data) => copy_into(&mut buffer, data),
+        }
+        buffer
+    }
+
+    /// The size in bytes of each sample.
+    fn bytes_per_sample(&self) -> usize {
+        match self {
+            Self::U8(_) | Self::I8(_) => 1,
+            Self::U16(_) | Self::I16(_) | Self::F16(_) => 2,
+            Self::U32(_) | Self::I32(_) | Self::F32(_) => 4,
+            Self::U64(_) | Self::I64(_) | Self::F64(_) => 8,
+        }
+    }
+}
+
+fn copy_into<T: AlignedSample>(buffer: &mut AlignedBuffer, samples: &[T]) {
+    buffer
+        .as_mut_slice::<T>()
+        .expect("buffer is sized for the samples")
+        .copy_from_slice(samples);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::F16;
+
+    #[test]
+    fn test_aligned_views() {
+        let mut buffer = AlignedBuffer::zeroed(12);
+        buffer
+            .as_mut_slice::<f32>()
+            .unwrap()
+            .copy_from_slice(&[1.0, 2.0, 3.0]);
+        assert_eq!(buffer.as_slice::<f32>(), Some(&[1.0, 2.0, 3.0][..]));
+        assert_eq!(buffer.as_slice::<u32>().unwrap()[0], 1.0f32.to_bits());
+        assert_eq!(buffer.as_bytes()[..4], 1.0f32.to_ne_bytes());
+        // 12 bytes can't hold whole f64 samples
+        assert_eq!(buffer.as_slice::<f64>(), None);
+
+        // Reusing the buffer with a different type and size
+        buffer.resize(16);
+        assert_eq!(buffer.as_slice::<f64>().map(<[f64]>::len), Some(2));
+        assert_eq!(buffer.as_bytes()[12..], [0; 4]);
+        buffer.resize(3);
+        buffer.resize(8);
+        assert_eq!(buffer.as_bytes()[3..], [0; 5]);
+    }
+
+    #[test]
+    fn test_to_aligned() {
+        let result = DecodingResult::from(vec![1u16, 2, 3]);
+        let buffer = result.to_aligned();
+        assert_eq!(buffer.len(), 6);
+        assert_eq!(buffer.as_slice::<u16>(), Some(&[1, 2, 3][..]));
+
+        let result = DecodingResult::from(vec![F16::from_bits(0x3c00)]);
+        assert_eq!(result.to_aligned().as_slice::<u16>(), Some(&[0x3c00][..]));
+    }
+}
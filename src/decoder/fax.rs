@@ -0,0 +1,442 @@
+//! Decompression of the bilevel CCITT fax codes: the modified Huffman run lengths of T.4 (TIFF
+//! compression 2), T.4 Group 3 with one or two-dimensional coding (compression 3), and T.6 Group 4
+//! (compression 4).
+//!
+//! Rows are decoded into packed bits, most significant bit first, with each row padded to a whole
+//! byte. Like libtiff, white runs are decoded to zeros and black runs to ones whatever the
+//! PhotometricInterpretation, which only tells how the bits are displayed. The uncompressed mode
+//! extension of the codes is not supported.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+/// T4Options: rows may be coded two-dimensionally relative to the previous row.
+const T4_2D_CODING: u32 = 1;
+/// T4Options and T6Options: rows may use the uncompressed mode extension.
+const UNCOMPRESSED_MODE: u32 = 2;
+
+/// The length in bits of the longest run length code.
+const MAX_CODE_BITS: u32 = 13;
+/// The end-of-line code is 11 zero bits followed by a one.
+const EOL_ZEROS: u32 = 11;
+
+/// The code and its length of each white run of 0 to 63 pixels.
+#[rustfmt::skip]
+const WHITE_TERMINATING: [(u16, u8); 64] = [
+    (0b00110101, 8), (0b000111, 6), (0b0111, 4), (0b1000, 4), (0b1011, 4), (0b1100, 4),
+    (0b1110, 4), (0b1111, 4), (0b10011, 5), (0b10100, 5), (0b00111, 5), (0b01000, 5),
+    (0b001000, 6), (0b000011, 6), (0b110100, 6), (0b110101, 6), (0b101010, 6), (0b101011, 6),
+    (0b0100111, 7), (0b0001100, 7), (0b0001000, 7), (0b0010111, 7), (0b0000011, 7),
+    (0b0000100, 7), (0b0101000, 7), (0b0101011, 7), (0b0010011, 7), (0b0100100, 7),
+    (0b0011000, 7), (0b00000010, 8), (0b00000011, 8), (0b00011010, 8), (0b00011011, 8),
+    (0b00010010, 8), (0b00010011, 8), (0b00010100, 8), (0b00010101, 8), (0b00010110, 8),
+    (0b00010111, 8), (0b00101000, 8), (0b00101001, 8), (0b00101010, 8), (0b00101011, 8),
+    (0b00101100, 8), (0b00101101, 8), (0b00000100, 8), (0b00000101, 8), (0b00001010, 8),
+    (0b00001011, 8), (0b01010010, 8), (0b01010011, 8), (0b01010100, 8), (0b01010101, 8),
+    (0b00100100, 8), (0b00100101, 8), (0b01011000, 8), (0b01011001, 8), (0b01011010, 8),
+    (0b01011011, 8), (0b01001010, 8), (0b01001011, 8), (0b00110010, 8), (0b00110011, 8),
+    (0b00110100, 8),
+];
+
+/// The code and its length of each white run of 64 to 1728 pixels, in steps of 64.
+#[rustfmt::skip]
+const WHITE_MAKEUP: [(u16, u8); 27] = [
+    (0b11011, 5), (0b10010, 5), (0b010111, 6), (0b0110111, 7), (0b00110110, 8),
+    (0b00110111, 8), (0b01100100, 8), (0b01100101, 8), (0b01101000, 8), (0b01100111, 8),
+    (0b011001100, 9), (0b011001101, 9), (0b011010010, 9), (0b011010011, 9), (0b011010100, 9),
+    (0b011010101, 9), (0b011010110, 9), (0b011010111, 9), (0b011011000, 9), (0b011011001, 9),
+    (0b011011010, 9), (0b011011011, 9), (0b010011000, 9), (0b010011001, 9), (0b010011010, 9),
+    (0b011000, 6), (0b010011011, 9),
+];
+
+/// The code and its length of each black run of 0 to 63 pixels.
+#[rustfmt::skip]
+const BLACK_TERMINATING: [(u16, u8); 64] = [
+    (0b0000110111, 10), (0b010, 3), (0b11, 2), (0b10, 2), (0b011, 3), (0b0011, 4),
+    (0b0010, 4), (0b00011, 5), (0b000101, 6), (0b000100, 6), (0b0000100, 7), (0b0000101, 7),
+    (0b0000111, 7), (0b00000100, 8), (0b00000111, 8), (0b000011000, 9), (0b0000010111, 10),
+    (0b0000011000, 10), (0b0000001000, 10), (0b00001100111, 11), (0b00001101000, 11),
+    (0b00001101100, 11), (0b00000110111, 11), (0b00000101000, 11), (0b00000010111, 11),
+    (0b00000011000, 11), (0b000011001010, 12), (0b000011001011, 12), (0b000011001100, 12),
+    (0b000011001101, 12), (0b000001101000, 12), (0b000001101001, 12), (0b000001101010, 12),
+    (0b000001101011, 12), (0b000011010010, 12), (0b000011010011, 12), (0b000011010100, 12),
+    (0b000011010101, 12), (0b000011010110, 12), (0b000011010111, 12), (0b000001101100, 12),
+    (0b000001101101, 12), (0b000011011010, 12), (0b000011011011, 12), (0b000001010100, 12),
+    (0b000001010101, 12), (0b000001010110, 12), (0b000001010111, 12), (0b000001100100, 12),
+    (0b000001100101, 12), (0b000001010010, 12), (0b000001010011, 12), (0b000000100100, 12),
+    (0b000000110111, 12), (0b000000111000, 12), (0b000000100111, 12), (0b000000101000, 12),
+    (0b000001011000, 12), (0b000001011001, 12), (0b000000101011, 12), (0b000000101100, 12),
+    (0b000001011010, 12), (0b000001100110, 12), (0b000001100111, 12),
+];
+
+/// The code and its length of each black run of 64 to 1728 pixels, in steps of 64.
+#[rustfmt::skip]
+const BLACK_MAKEUP: [(u16, u8); 27] = [
+    (0b0000001111, 10), (0b000011001000, 12), (0b000011001001, 12), (0b000001011011, 12),
+    (0b000000110011, 12), (0b000000110100, 12), (0b000000110101, 12), (0b0000001101100, 13),
+    (0b0000001101101, 13), (0b0000001001010, 13), (0b0000001001011, 13), (0b0000001001100, 13),
+    (0b0000001001101, 13), (0b0000001110010, 13), (0b0000001110011, 13), (0b0000001110100, 13),
+    (0b0000001110101, 13), (0b0000001110110, 13), (0b0000001110111, 13), (0b0000001010010, 13),
+    (0b0000001010011, 13), (0b0000001010100, 13), (0b0000001010101, 13), (0b0000001011010, 13),
+    (0b0000001011011, 13), (0b0000001100100, 13), (0b0000001100101, 13),
+];
+
+/// The code and its length of each run of 1792 to 2560 pixels of either color, in steps of 64.
+#[rustfmt::skip]
+const EXTENDED_MAKEUP: [(u16, u8); 13] = [
+    (0b00000001000, 11), (0b00000001100, 11), (0b00000001101, 11), (0b000000010010, 12),
+    (0b000000010011, 12), (0b000000010100, 12), (0b000000010101, 12), (0b000000010110, 12),
+    (0b000000010111, 12), (0b000000011100, 12), (0b000000011101, 12), (0b000000011110, 12),
+    (0b000000011111, 12),
+];
+
+/// How the rows of a chunk are coded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FaxCoding {
+    /// Modified Huffman run lengths, with each row starting on a byte boundary and no end-of-line
+    /// codes.
+    Huffman,
+    /// T.4 Group 3, with the T4Options of the image.
+    Group3(u32),
+    /// T.6 Group 4, with the T6Options of the image.
+    Group4(u32),
+}
+
+/// An error decoding fax data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FaxError(&'static str);
+
+impl fmt::Display for FaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+type Result<T> = std::result::Result<T, FaxError>;
+
+/// Decode up to `height` rows of `width` pixels from `data`.
+///
+/// Decoding stops early at the end of the data or at the end-of-block code of Group 4 data,
+/// returning the rows decoded so far.
+pub(crate) fn decode(data: &[u8], width: u32, height: u32, coding: FaxCoding) -> Result<Vec<u8>> {
+    let options = match coding {
+        FaxCoding::Huffman => 0,
+        FaxCoding::Group3(options) | FaxCoding::Group4(options) => options,
+    };
+    if options & UNCOMPRESSED_MODE != 0 {
+        return Err(FaxError("Uncompressed mode is not supported"));
+    }
+
+    let row_bytes = (width as usize).div_ceil(8);
+    let mut output = Vec::with_capacity(row_bytes * height as usize);
+    let mut input = BitReader::new(data);
+    // The changing elements of the previous row; the row above the first is all white.
+    let mut reference = vec![];
+    let mut changes = vec![];
+    for _ in 0..height {
+        if input.is_empty() {
+            break;
+        }
+        changes.clear();
+        let two_dimensional = match coding {
+            FaxCoding::Huffman => false,
+            FaxCoding::Group3(options) => {
+                input.skip_eol();
+                // Two-dimensional coding tags each row with whether it is coded in one dimension
+                options & T4_2D_CODING != 0 && input.bit()? == 0
+            }
+            FaxCoding::Group4(_) => true,
+        };
+        if two_dimensional {
+            if !decode_row_2d(&mut input, width, &reference, &mut changes)? {
+                break;
+            }
+        } else {
+            decode_row_1d(&mut input, width, &mut changes)?;
+        }
+        if coding == FaxCoding::Huffman {
+            input.align();
+        }
+
+        let start = output.len();
+        output.resize(start + row_bytes, 0);
+        fill_row(&mut output[start..], &changes, width);
+        std::mem::swap(&mut reference, &mut changes);
+    }
+    Ok(output)
+}
+
+/// Decode a row of alternating white and black runs into the positions at which the color
+/// changes.
+fn decode_row_1d(input: &mut BitReader, width: u32, changes: &mut Vec<u32>) -> Result<()> {
+    let mut position = 0;
+    let mut black = false;
+    while position < width {
+        position = position.saturating_add(input.run(black)?).min(width);
+        changes.push(position);
+        black = !black;
+    }
+    Ok(())
+}
+
+/// Decode a row coded relative to the changing elements of the row above, following the naming
+/// of T.4 section 4.2.1.3.
+///
+/// Returns `false` without decoding anything at an end-of-line code, which ends Group 4 data.
+fn decode_row_2d(
+    input: &mut BitReader,
+    width: u32,
+    reference: &[u32],
+    changes: &mut Vec<u32>,
+) -> Result<bool> {
+    // a0 starts on an imaginary white pixel before the first one of the row.
+    let mut a0 = None::<u32>;
+    let mut black = false;
+    let mut index = 0;
+    loop {
+        let start = a0.unwrap_or(0);
+        if a0.is_some() && start >= width {
+            return Ok(true);
+        }
+
+        // b1 is the first change in the row above to the right of a0 and to the opposite color
+        // of a0, and b2 the change after it. Changes to black are at even indices.
+        while index > 0 && a0.is_none_or(|a0| reference[index - 1] > a0) {
+            index -= 1;
+        }
+        while index < reference.len()
+            && (a0.is_some_and(|a0| reference[index] <= a0) || (index % 2 == 1) != black)
+        {
+            index += 1;
+        }
+        let b1 = reference.get(index).copied().unwrap_or(width);
+        let b2 = reference.get(index + 1).copied().unwrap_or(width);
+
+        match input.mode()? {
+            Mode::Pass => a0 = Some(b2),
+            Mode::Horizontal => {
+                let a1 = start.saturating_add(input.run(black)?).min(width);
+                let a2 = a1.saturating_add(input.run(!black)?).min(width);
+                changes.extend([a1, a2]);
+                a0 = Some(a2);
+            }
+            Mode::Vertical(offset) => {
+                let a1 = b1
+                    .checked_add_signed(offset)
+                    .filter(|a1| *a1 >= start && *a1 <= width)
+                    .ok_or(FaxError("Vertical mode code out of range"))?;
+                changes.push(a1);
+                a0 = Some(a1);
+                black = !black;
+            }
+            Mode::EndOfLine if a0.is_none() => return Ok(false),
+            Mode::EndOfLine => return Err(FaxError("Unexpected end of line")),
+        }
+    }
+}
+
+/// Set the bits of the black pixels of a row, given the positions at which its color changes.
+fn fill_row(row: &mut [u8], changes: &[u32], width: u32) {
+    let mut start = 0;
+    for (i, &end) in changes.iter().enumerate() {
+        let end = end.min(width) as usize;
+        if i % 2 == 1 {
+            for pixel in start..end {
+                row[pixel / 8] |= 0x80 >> (pixel % 8);
+            }
+        }
+        start = end.max(start);
+    }
+}
+
+/// A two-dimensional coding mode.
+enum Mode {
+    Pass,
+    Horizontal,
+    /// The offset of a1 from b1.
+    Vertical(i32),
+    EndOfLine,
+}
+
+/// The run length and code length of each 13-bit prefix, for white and black runs.
+struct RunTables {
+    white: Vec<(u16, u8)>,
+    black: Vec<(u16, u8)>,
+}
+
+impl RunTables {
+    fn get() -> &'static Self {
+        static TABLES: OnceLock<RunTables> = OnceLock::new();
+        TABLES.get_or_init(|| Self {
+            white: Self::build(&WHITE_TERMINATING, &WHITE_MAKEUP),
+            black: Self::build(&BLACK_TERMINATING, &BLACK_MAKEUP),
+        })
+    }
+
+    fn build(terminating: &[(u16, u8)], makeup: &[(u16, u8)]) -> Vec<(u16, u8)> {
+        let mut table = vec![(0, 0); 1 << MAX_CODE_BITS];
+        let makeup = makeup.iter().chain(&EXTENDED_MAKEUP).enumerate();
+        let makeup = makeup.map(|(i, code)| ((i + 1) * 64, code));
+        for (run, &(code, len)) in terminating.iter().enumerate().chain(makeup) {
+            let shift = MAX_CODE_BITS - len as u32;
+            let first = (code as usize) << shift;
+            table[first..first + (1 << shift)].fill((run as u16, len));
+        }
+        table
+    }
+}
+
+/// Reads the bits of fax data, most significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len() * 8
+    }
+
+    /// The next `count` bits, padded with zeros past the end of the data.
+    fn peek(&self, count: u32) -> u32 {
+        let mut value = 0;
+        for i in 0..count as usize {
+            let position = self.position + i;
+            let bit = self
+                .data
+                .get(position / 8)
+                .map_or(0, |byte| (byte >> (7 - position % 8)) & 1);
+            value = value << 1 | bit as u32;
+        }
+        value
+    }
+
+    fn consume(&mut self, count: u32) -> Result<()> {
+        self.position += count as usize;
+        if self.position > self.data.len() * 8 {
+            return Err(FaxError("Unexpected end of data"));
+        }
+        Ok(())
+    }
+
+    fn bit(&mut self) -> Result<u32> {
+        let bit = self.peek(1);
+        self.consume(1)?;
+        Ok(bit)
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.next_multiple_of(8);
+    }
+
+    /// Skip an end-of-line code and the fill bits before it, if present.
+    fn skip_eol(&mut self) {
+        if self.peek(EOL_ZEROS) != 0 {
+            return;
+        }
+        while !self.is_empty() && self.peek(1) == 0 {
+            self.position += 1;
+        }
+        self.position += 1;
+    }
+
+    /// Read a run length of the given color, made of any makeup codes and a terminating code.
+    fn run(&mut self, black: bool) -> Result<u32> {
+        let tables = RunTables::get();
+        let table = if black { &tables.black } else { &tables.white };
+        let mut total = 0u32;
+        loop {
+            let (run, len) = table[self.peek(MAX_CODE_BITS) as usize];
+            if len == 0 {
+                return Err(FaxError("Invalid run length code"));
+            }
+            self.consume(len as u32)?;
+            total = total.saturating_add(run as u32);
+            if run < 64 {
+                return Ok(total);
+            }
+        }
+    }
+
+    fn mode(&mut self) -> Result<Mode> {
+        let bits = self.peek(7);
+        let (mode, len) = match bits {
+            0b1000000..=0b1111111 => (Mode::Vertical(0), 1),
+            0b0110000..=0b0111111 => (Mode::Vertical(1), 3),
+            0b0100000..=0b0101111 => (Mode::Vertical(-1), 3),
+            0b0010000..=0b0011111 => (Mode::Horizontal, 3),
+            0b0001000..=0b0001111 => (Mode::Pass, 4),
+            0b0000110 | 0b0000111 => (Mode::Vertical(2), 6),
+            0b0000100 | 0b0000101 => (Mode::Vertical(-2), 6),
+            0b0000011 => (Mode::Vertical(3), 7),
+            0b0000010 => (Mode::Vertical(-3), 7),
+            0b0000001 => return Err(FaxError("Uncompressed mode is not supported")),
+            _ if self.peek(EOL_ZEROS + 1) == 1 => (Mode::EndOfLine, EOL_ZEROS + 1),
+            _ => return Err(FaxError("Invalid mode code")),
+        };
+        self.consume(len)?;
+        Ok(mode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Pack a string of code bits into bytes, padding the last one with zeros.
+    fn bits(code: &str) -> Vec<u8> {
+        let code = code.replace(' ', "");
+        code.as_bytes()
+            .chunks(8)
+            .map(|chunk| {
+                let byte = chunk.iter().fold(0, |byte, bit| byte << 1 | (bit - b'0'));
+                byte << (8 - chunk.len())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_huffman() {
+        // 3 white, 2 black, 5 white; then 10 black after a white run of 0
+        let data = [bits("1000 11 1100"), bits("00110101 0000100")].concat();
+        let rows = decode(&data, 10, 2, FaxCoding::Huffman).unwrap();
+        assert_eq!(rows, [0b0001_1000, 0b0000_0000, 0b1111_1111, 0b1100_0000]);
+    }
+
+    #[test]
+    fn test_decode_makeup_codes() {
+        // 1800 white with the extended makeup code of 1792, then 200 black
+        let data = bits("00000001000 10011 000011001001 000101");
+        let rows = decode(&data, 2000, 1, FaxCoding::Group3(0)).unwrap();
+        assert_eq!(rows.len(), 250);
+        assert!(rows[..225].iter().all(|byte| *byte == 0));
+        assert!(rows[225..].iter().all(|byte| *byte == 0xFF));
+    }
+
+    #[test]
+    fn test_decode_group3_2d() {
+        // EOL and a 1D row of 2 white, 3 black, 3 white; then EOL and a 2D row: V0 to start the
+        // black run at 2, VR1 to end it at 6, and V0 to reach the end.
+        let data = bits("000000000001 1 0111 10 1000 000000000001 0 1 011 1");
+        let rows = decode(&data, 8, 2, FaxCoding::Group3(T4_2D_CODING)).unwrap();
+        assert_eq!(rows, [0b0011_1000, 0b0011_1100]);
+    }
+
+    #[test]
+    fn test_decode_group4() {
+        // Horizontal mode for 1 white and 2 black, then V0 to reach the end. The second row
+        // passes the black run of the first, then the end-of-block code follows.
+        let data = bits("001 000111 11 1  0001 1  000000000001 000000000001");
+        let rows = decode(&data, 8, 3, FaxCoding::Group4(0)).unwrap();
+        assert_eq!(rows, [0b0110_0000, 0]);
+
+        let err = decode(&bits("0000001"), 8, 1, FaxCoding::Group4(0)).unwrap_err();
+        assert_eq!(err, FaxError("Uncompressed mode is not supported"));
+        assert!(decode(&bits("0000011"), 8, 1, FaxCoding::Group4(0)).is_err());
+        assert!(decode(&[0], 8, 1, FaxCoding::Group4(UNCOMPRESSED_MODE)).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A 16-bit IEEE 754 floating point sample, as written by GDAL for `Float16` rasters.
+///
+/// Rust has no stable half-precision type, so this stores the raw bits and converts to and from
+/// [`f32`] for arithmetic. Comparisons follow IEEE 754 semantics, like those of [`f32`].
+#[derive(Clone, Copy, Default)]
+pub struct F16(u16);
+
+impl F16 {
+    /// Reinterpret raw IEEE 754 binary16 bits as a sample.
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    /// The raw IEEE 754 binary16 bits of this sample.
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Convert an [`f32`] to the nearest half-precision value, rounding ties to even.
+    ///
+    /// Values too large to represent become infinite.
+    pub fn from_f32(value: f32) -> Self {
+        let x = value.to_bits();
+        let sign = (x & 0x8000_0000) >> 16;
+        let exp = x & 0x7F80_0000;
+        let man = x & 0x007F_FFFF;
+
+        // Infinity and NaN, keeping NaNs quiet
+        if exp == 0x7F80_0000 {
+            let nan_bit = if man == 0 { 0 } else { 0x0200 };
+            return Self((sign | 0x7C00 | nan_bit | (man >> 13)) as u16);
+        }
+
+        let half_exp = ((exp >> 23) as i32) - 127 + 15;
+        if half_exp >= 0x1F {
+            return Self((sign | 0x7C00) as u16);
+        }
+        if half_exp <= 0 {
+            // Subnormal, or too small and flushed to zero
+            if 14 - half_exp > 24 {
+                return Self(sign as u16);
+            }
+            let man = man | 0x0080_0000;
+            let mut half_man = man >> (14 - half_exp);
+            let round_bit = 1 << (13 - half_exp);
+            if man & round_bit != 0 && man & (3 * round_bit - 1) != 0 {
+                half_man += 1;
+            }
+            return Self((sign | half_man) as u16);
+        }
+
+        let bits = sign | ((half_exp as u32) << 10) | (man >> 13);
+        let round_bit = 0x0000_1000;
+        if man & round_bit != 0 && man & (3 * round_bit - 1) != 0 {
+            // Carrying into the exponent correctly rounds up to the next power of two
+            Self((bits + 1) as u16)
+        } else {
+            Self(bits as u16)
+        }
+    }
+
+    /// Convert this sample to an [`f32`], which represents every half-precision value exactly.
+    pub fn to_f32(self) -> f32 {
+        let sign = ((self.0 & 0x8000) as u32) << 16;
+        let exp = ((self.0 >> 10) & 0x1F) as u32;
+        let man = (self.0 & 0x03FF) as u32;
+        match exp {
+            0 if man == 0 => f32::from_bits(sign),
+            0 => {
+                let value = man as f32 * f32::from_bits(0x3380_0000); // 2^-24
+                if sign == 0 {
+                    value
+                } else {
+                    -value
+                }
+            }
+            0x1F => f32::from_bits(sign | 0x7F80_0000 | (man << 13)),
+            _ => f32::from_bits(sign | ((exp + 112) << 23) | (man << 13)),
+        }
+    }
+
+    /// Convert this sample to an [`f64`].
+    pub fn to_f64(self) -> f64 {
+        self.to_f32() as f64
+    }
+
+    /// Returns `true` if this sample is NaN.
+    pub fn is_nan(self) -> bool {
+        self.0 & 0x7C00 == 0x7C00 && self.0 & 0x03FF != 0
+    }
+}
+
+impl From<F16> for f32 {
+    fn from(value: F16) -> Self {
+        value.to_f32()
+    }
+}
+
+impl From<F16> for f64 {
+    fn from(value: F16) -> Self {
+        value.to_f64()
+    }
+}
+
+impl PartialEq for F16 {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_f32() == other.to_f32()
+    }
+}
+
+impl PartialOrd for F16 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.to_f32().partial_cmp(&other.to_f32())
+    }
+}
+
+impl fmt::Debug for F16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+
+impl fmt::Display for F16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f32(), f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_f16_conversion() {
+        for (bits, value) in [
+            (0x0000, 0.0),
+            (0x8000, -0.0),
+            (0x3C00, 1.0),
+            (0xC000, -2.0),
+            (0x3555, 0.333_251_95),
+            (0x7BFF, 65504.0),
+            (0x0001, 5.960_464_5e-8),
+            (0x0400, 6.103_515_6e-5),
+            (0x7C00, f32::INFINITY),
+        ] {
+            let sample = F16::from_bits(bits);
+            assert_eq!(sample.to_f32(), value, "{bits:#06x}");
+            assert_eq!(F16::from_f32(value).to_bits(), bits, "{value}");
+        }
+
+        // Ties round to even, and overflow becomes infinite
+        assert_eq!(F16::from_f32(1.0 + 2f32.powi(-11)).to_bits(), 0x3C00);
+        assert_eq!(F16::from_f32(1.0 + 3.0 * 2f32.powi(-11)).to_bits(), 0x3C02);
+        assert_eq!(F16::from_f32(65520.0).to_bits(), 0x7C00);
+
+        let nan = F16::from_f32(f32::NAN);
+        assert!(nan.is_nan() && nan.to_f32().is_nan());
+        assert_ne!(nan, nan);
+        assert_eq!(F16::from_bits(0x8000), F16::from_bits(0x0000));
+        assert!(F16::from_bits(0x3C00) < F16::from_bits(0x4000));
+    }
+}
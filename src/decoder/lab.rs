@@ -0,0 +1,186 @@
+use bytes::Bytes;
+
+use crate::decoder::PhotometricConverter;
+use crate::error::AsyncTiffResult;
+use crate::tiff::tags::SampleFormat;
+use crate::tiff::{TiffError, TiffUnsupportedError};
+
+/// The D50 reference white of the ICC profile connection space.
+const WHITE_POINT: [f64; 3] = [0.96422, 1.0, 0.82521];
+
+/// Linear sRGB from D50 XYZ, Bradford-adapted.
+const XYZ_TO_RGB: [[f64; 3]; 3] = [
+    [3.1338561, -1.6168667, -0.4906146],
+    [-0.9787684, 1.9161415, 0.0334540],
+    [0.0719453, -0.2289914, 1.4052427],
+];
+
+/// Conversion of CIE L\*a\*b\* samples to sRGB, following section 23 of the TIFF 6.0
+/// specification and the TIFF Technical Note on ICCLab.
+///
+/// The first three samples of each pixel are converted, keeping their bit depth, and any extra
+/// samples such as alpha are left as is. Images with only the L\* sample become grayscale.
+/// 8-bit and 16-bit samples are supported.
+///
+/// This is registered for [`CIELab`] and [`ICCLab`] in the default
+/// [`PhotometricRegistry`](super::PhotometricRegistry).
+///
+/// [`CIELab`]: crate::tiff::tags::PhotometricInterpretation::CIELab
+/// [`ICCLab`]: crate::tiff::tags::PhotometricInterpretation::ICCLab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LabConverter {
+    signed_ab: bool,
+}
+
+impl LabConverter {
+    /// The converter for CIELab, whose a\* and b\* samples are signed.
+    pub fn cielab() -> Self {
+        Self { signed_ab: true }
+    }
+
+    /// The converter for ICCLab, whose a\* and b\* samples are unsigned with an offset.
+    pub fn icclab() -> Self {
+        Self { signed_ab: false }
+    }
+
+    /// Convert L\* in `0..=100` and a\*, b\* to sRGB in `0..=1`.
+    fn to_rgb(l: f64, a: f64, b: f64) -> [f64; 3] {
+        let fy = (l + 16.0) / 116.0;
+        let fx = fy + a / 500.0;
+        let fz = fy - b / 200.0;
+        let finv = |t: f64| {
+            if t > 6.0 / 29.0 {
+                t * t * t
+            } else {
+                3.0 * (6.0f64 / 29.0).powi(2) * (t - 4.0 / 29.0)
+            }
+        };
+        let xyz = [
+            WHITE_POINT[0] * finv(fx),
+            WHITE_POINT[1] * finv(fy),
+            WHITE_POINT[2] * finv(fz),
+        ];
+        XYZ_TO_RGB.map(|row| {
+            let linear = row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2];
+            gamma(linear.clamp(0.0, 1.0))
+        })
+    }
+}
+
+impl PhotometricConverter for LabConverter {
+    fn convert(
+        &self,
+        buffer: Bytes,
+        samples_per_pixel: u16,
+        bits_per_sample: u16,
+        sample_format: SampleFormat,
+    ) -> AsyncTiffResult<Bytes> {
+        if sample_format == SampleFormat::IEEEFP || !matches!(bits_per_sample, 8 | 16) {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedBitsPerChannel(bits_per_sample as u8),
+            )
+            .into());
+        }
+        let samples = samples_per_pixel.max(1) as usize;
+        let bytes_per_sample = bits_per_sample as usize / 8;
+        let max = ((1u32 << bits_per_sample) - 1) as f64;
+
+        let read = |pixel: &[u8], i: usize| -> u16 {
+            match bytes_per_sample {
+                1 => pixel[i] as u16,
+                _ => u16::from_ne_bytes([pixel[2 * i], pixel[2 * i + 1]]),
+            }
+        };
+        // a* and b* are stored in units of 1 for 8-bit samples and 1/256 for 16-bit samples.
+        let chroma = |raw: u16| -> f64 {
+            match (self.signed_ab, bytes_per_sample) {
+                (true, 1) => raw as u8 as i8 as f64,
+                (true, _) => raw as i16 as f64 / 256.0,
+                (false, 1) => raw as f64 - 128.0,
+                (false, _) => raw as f64 / 256.0 - 128.0,
+            }
+        };
+
+        let mut out = buffer.to_vec();
+        for pixel in out.chunks_exact_mut(samples * bytes_per_sample) {
+            let l = read(pixel, 0) as f64 * 100.0 / max;
+            let rgb = if samples >= 3 {
+                Self::to_rgb(l, chroma(read(pixel, 1)), chroma(read(pixel, 2)))
+            } else {
+                Self::to_rgb(l, 0.0, 0.0)
+            };
+            for (i, value) in rgb.iter().take(samples.min(3)).enumerate() {
+                let value = (value * max).round();
+                match bytes_per_sample {
+                    1 => pixel[i] = value as u8,
+                    _ => pixel[2 * i..2 * i + 2].copy_from_slice(&(value as u16).to_ne_bytes()),
+                }
+            }
+        }
+        Ok(out.into())
+    }
+}
+
+/// The sRGB transfer function.
+fn gamma(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_convert_lab_u8() {
+        let convert = |converter: LabConverter, data: &'static [u8], samples: u16| {
+            converter
+                .convert(Bytes::from_static(data), samples, 8, SampleFormat::Uint)
+                .unwrap()
+        };
+
+        // White, black and mid gray keep their extra samples
+        let cielab = LabConverter::cielab();
+        assert_eq!(
+            convert(cielab, &[255, 0, 0, 7, 0, 0, 0, 9], 4).as_ref(),
+            [255, 255, 255, 7, 0, 0, 0, 9]
+        );
+        let gray = convert(cielab, &[128, 0, 0], 3);
+        assert!(gray.iter().all(|v| *v == gray[0]));
+
+        // ICCLab encodes neutral a* and b* as 128
+        assert_eq!(
+            convert(LabConverter::icclab(), &[255, 128, 128], 3).as_ref(),
+            [255, 255, 255]
+        );
+
+        // Positive a* is red, negative b* is blue
+        let red = convert(cielab, &[138, 80, 67], 3);
+        assert!(red[0] > 200 && red[1] < 100 && red[2] < 100);
+        let blue = convert(cielab, &[82, 200, 150], 3);
+        assert!(blue[2] > blue[0] && blue[2] > blue[1]);
+
+        // L* only
+        assert_eq!(convert(cielab, &[255, 0], 1).as_ref(), [255, 0]);
+    }
+
+    #[test]
+    fn test_convert_lab_u16() {
+        let data: Vec<u8> = [u16::MAX, 0, 0]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        let converted = LabConverter::cielab()
+            .convert(data.into(), 3, 16, SampleFormat::Uint)
+            .unwrap();
+        assert_eq!(converted.as_ref(), [255; 6]);
+
+        assert!(LabConverter::cielab()
+            .convert(Bytes::new(), 3, 32, SampleFormat::IEEEFP)
+            .unwrap_err()
+            .is_unsupported());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+
+/// Limits on the memory used to decode image data, to bound the worst case of a request.
+///
+/// Attach limits to a [`DecoderRegistry`](super::DecoderRegistry) with
+/// [`with_limits`](super::DecoderRegistry::with_limits). Every tile decoded with that registry
+/// and every window read with it is then checked before its buffers are allocated; exceeding a
+/// limit fails with [`AsyncTiffError::LimitExceeded`].
+///
+/// By default, there are no limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    max_tile_pixels: u64,
+    max_output_bytes: u64,
+}
+
+impl DecodeLimits {
+    /// No limits at all.
+    pub fn unlimited() -> Self {
+        Self {
+            max_tile_pixels: u64::MAX,
+            max_output_bytes: u64::MAX,
+        }
+    }
+
+    /// Set the maximum number of pixels of a single tile or strip, including padding.
+    pub fn with_max_tile_pixels(mut self, max_tile_pixels: u64) -> Self {
+        self.max_tile_pixels = max_tile_pixels;
+        self
+    }
+
+    /// Set the maximum number of bytes returned by a single call, such as
+    /// [`Tile::decode_typed`](crate::Tile::decode_typed) or
+    /// [`ImageFileDirectory::read_window`](crate::ImageFileDirectory::read_window).
+    pub fn with_max_output_bytes(mut self, max_output_bytes: u64) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// The maximum number of pixels of a single tile or strip.
+    pub fn max_tile_pixels(&self) -> u64 {
+        self.max_tile_pixels
+    }
+
+    /// The maximum number of bytes returned by a single call.
+    pub fn max_output_bytes(&self) -> u64 {
+        self.max_output_bytes
+    }
+
+    pub(crate) fn check_tile_pixels(&self, pixels: u64) -> AsyncTiffResult<()> {
+        if pixels > self.max_tile_pixels {
+            return Err(AsyncTiffError::LimitExceeded {
+                what: "Tile pixel count",
+                size: pixels,
+                limit: self.max_tile_pixels,
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_output_bytes(&self, bytes: u64) -> AsyncTiffResult<()> {
+        if bytes > self.max_output_bytes {
+            return Err(AsyncTiffError::LimitExceeded {
+                what: "Output size",
+                size: bytes,
+                limit: self.max_output_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
@@ -0,0 +1,622 @@
+//! Decoders for different TIFF compression methods.
+
+mod aligned;
+mod fax;
+mod half;
+mod lab;
+mod limits;
+mod photometric;
+mod pool;
+mod result;
+mod stats;
+mod streaming;
+mod uniform;
+mod ycbcr;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{Cursor, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use bytes::Bytes;
+use flate2::bufread::ZlibDecoder;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::reader::Endianness;
+use crate::tiff::tags::{CompressionMethod, PhotometricInterpretation};
+use crate::tiff::{TiffError, TiffUnsupportedError};
+use crate::tile::EdgeTiles;
+
+pub use aligned::{AlignedBuffer, AlignedSample};
+pub use half::F16;
+pub use lab::LabConverter;
+pub use limits::DecodeLimits;
+pub use photometric::{PhotometricConverter, PhotometricRegistry};
+pub use pool::DecodePool;
+pub use result::{DecodingResult, DecodingView, Sample, SampleView};
+pub use stats::{DecodeStats, StageStats};
+pub(crate) use streaming::StreamingDecompressor;
+pub(crate) use uniform::{is_uniform, UniformTileCache};
+pub(crate) use ycbcr::YCbCrConversion;
+
+/// A registry of decoders.
+///
+/// This allows end users to register their own decoders, for custom compression methods, or
+/// override the default decoder implementations.
+#[derive(Debug)]
+pub struct DecoderRegistry {
+    decoders: HashMap<CompressionMethod, Box<dyn Decoder>>,
+    stats: Option<Arc<DecodeStats>>,
+    uniform_tiles: UniformTileCache,
+    lenient_float_predictor: bool,
+    edge_tiles: EdgeTiles,
+    limits: DecodeLimits,
+    photometric: PhotometricRegistry,
+}
+
+impl DecoderRegistry {
+    /// Create a new decoder registry with no decoders registered
+    pub fn new() -> Self {
+        Self {
+            decoders: HashMap::new(),
+            stats: None,
+            uniform_tiles: UniformTileCache::default(),
+            lenient_float_predictor: false,
+            edge_tiles: EdgeTiles::default(),
+            limits: DecodeLimits::default(),
+            photometric: PhotometricRegistry::new(),
+        }
+    }
+
+    /// Collect decode performance counters into `stats` for every tile decoded with this
+    /// registry.
+    pub fn with_stats(mut self, stats: Arc<DecodeStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// The performance counters attached to this registry, if any.
+    pub fn stats(&self) -> Option<&Arc<DecodeStats>> {
+        self.stats.as_ref()
+    }
+
+    /// Reverse horizontal differencing (Predictor=2) on floating point data byte by byte, as GDAL
+    /// does.
+    ///
+    /// Horizontal differencing isn't defined for floating point data, but some writers apply it
+    /// to each byte of the sample anyway instead of using the floating point predictor. By
+    /// default such data is differenced as integers of the same width, which produces garbage for
+    /// these files.
+    pub fn with_lenient_float_predictor(mut self, lenient: bool) -> Self {
+        self.lenient_float_predictor = lenient;
+        self
+    }
+
+    /// Whether horizontal differencing on floating point data is reversed byte by byte.
+    pub fn lenient_float_predictor(&self) -> bool {
+        self.lenient_float_predictor
+    }
+
+    /// Select whether tiles on the right and bottom edges of the image keep their padding when
+    /// decoded. Defaults to [`EdgeTiles::Padded`].
+    pub fn with_edge_tiles(mut self, edge_tiles: EdgeTiles) -> Self {
+        self.edge_tiles = edge_tiles;
+        self
+    }
+
+    /// The layout of decoded edge tiles.
+    pub fn edge_tiles(&self) -> EdgeTiles {
+        self.edge_tiles
+    }
+
+    /// Fail with [`AsyncTiffError::LimitExceeded`] rather than decode tiles or windows beyond
+    /// `limits`.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The limits checked when decoding.
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+
+    /// Convert decoded tiles of the photometric interpretations in `photometric` with their
+    /// registered converters.
+    pub fn with_photometric_registry(mut self, photometric: PhotometricRegistry) -> Self {
+        self.photometric = photometric;
+        self
+    }
+
+    /// The converters applied to decoded tiles.
+    pub fn photometric_registry(&self) -> &PhotometricRegistry {
+        &self.photometric
+    }
+
+    pub(crate) fn uniform_tiles(&self) -> &UniformTileCache {
+        &self.uniform_tiles
+    }
+}
+
+impl AsRef<HashMap<CompressionMethod, Box<dyn Decoder>>> for DecoderRegistry {
+    fn as_ref(&self) -> &HashMap<CompressionMethod, Box<dyn Decoder>> {
+        &self.decoders
+    }
+}
+
+impl AsMut<HashMap<CompressionMethod, Box<dyn Decoder>>> for DecoderRegistry {
+    fn as_mut(&mut self) -> &mut HashMap<CompressionMethod, Box<dyn Decoder>> {
+        &mut self.decoders
+    }
+}
+
+impl Default for DecoderRegistry {
+    fn default() -> Self {
+        let mut registry = HashMap::with_capacity(10);
+        registry.insert(CompressionMethod::None, Box::new(UncompressedDecoder) as _);
+        registry.insert(CompressionMethod::Deflate, Box::new(DeflateDecoder) as _);
+        registry.insert(CompressionMethod::OldDeflate, Box::new(DeflateDecoder) as _);
+        registry.insert(CompressionMethod::LZW, Box::new(LZWDecoder) as _);
+        registry.insert(CompressionMethod::ModernJPEG, Box::new(JPEGDecoder) as _);
+        #[cfg(feature = "zstd")]
+        registry.insert(CompressionMethod::ZSTD, Box::new(ZstdDecoder) as _);
+        registry.insert(CompressionMethod::Huffman, Box::new(HuffmanDecoder) as _);
+        registry.insert(CompressionMethod::Fax3, Box::new(Fax3Decoder) as _);
+        registry.insert(CompressionMethod::Fax4, Box::new(Fax4Decoder) as _);
+        #[cfg(feature = "lerc")]
+        registry.insert(
+            CompressionMethod::LERC,
+            Box::new(crate::lerc::LercDecoder) as _,
+        );
+        Self {
+            decoders: registry,
+            stats: None,
+            uniform_tiles: UniformTileCache::default(),
+            lenient_float_predictor: false,
+            edge_tiles: EdgeTiles::default(),
+            limits: DecodeLimits::default(),
+            photometric: PhotometricRegistry::default(),
+        }
+    }
+}
+
+/// A handle for cooperatively abandoning tile decoding.
+///
+/// Decoding checks this token between stages and between rows of predictor reversal, returning
+/// [`AsyncTiffError::Cancelled`] as soon as the token has been cancelled or its deadline has
+/// passed. This lets a tile server stop spending CPU on requests whose clients have disconnected.
+///
+/// Clones share the same cancellation flag, so a token can be handed to a decode task while the
+/// original is kept to call [`cancel`][Self::cancel].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Create a new token that is not cancelled and has no deadline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a deadline after which decoding will be abandoned.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// The deadline of this token, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Cancel this token and all of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if this token was cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Return an error if this token was cancelled or its deadline has passed.
+    pub(crate) fn check(&self) -> AsyncTiffResult<()> {
+        if self.is_cancelled() {
+            Err(AsyncTiffError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A trait to decode a TIFF tile.
+pub trait Decoder: Debug + Send + Sync {
+    /// Decode a TIFF tile.
+    fn decode_tile(
+        &self,
+        buffer: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+        jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes>;
+
+    /// Decode a TIFF tile described by `info`.
+    ///
+    /// This is what tiles are decoded with. Most compressed data records its own size, so by
+    /// default this calls [`decode_tile`](Self::decode_tile); decoders of data that doesn't, like
+    /// the rows of bits of CCITT fax codes, override it instead.
+    fn decode_tile_with_info(
+        &self,
+        buffer: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+        jpeg_tables: Option<&[u8]>,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        let _ = info;
+        self.decode_tile(buffer, photometric_interpretation, jpeg_tables)
+    }
+}
+
+/// The layout and coding options of a tile, as passed to
+/// [`Decoder::decode_tile_with_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileInfo {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) bits_per_pixel: u32,
+    pub(crate) fax_options: u32,
+    pub(crate) endianness: Endianness,
+}
+
+impl TileInfo {
+    /// Describe a tile of `width` by `height` pixels of `bits_per_pixel` bits each, e.g. to
+    /// encode it with an [`Encoder`](crate::encoder::Encoder).
+    pub fn new(width: u32, height: u32, bits_per_pixel: u32) -> Self {
+        Self {
+            width,
+            height,
+            bits_per_pixel,
+            fax_options: 0,
+            endianness: Endianness::LittleEndian,
+        }
+    }
+
+    /// Set the T4Options or T6Options of a CCITT fax tile.
+    pub fn with_fax_options(mut self, fax_options: u32) -> Self {
+        self.fax_options = fax_options;
+        self
+    }
+
+    /// Set the byte order of the file, in which decoders that produce multi-byte samples
+    /// themselves must write them. Defaults to little endian.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// The width of the tile in pixels, including any padding columns.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The number of rows of the tile, including any padding rows.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of bits of each pixel of the tile, i.e. of all of its samples for chunky images
+    /// and of a single sample for planar images.
+    pub fn bits_per_pixel(&self) -> u32 {
+        self.bits_per_pixel
+    }
+
+    /// The T4Options of CCITT Group 3 images or the T6Options of Group 4 images, or 0 if absent.
+    pub fn fax_options(&self) -> u32 {
+        self.fax_options
+    }
+
+    /// The byte order of the file.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// The number of bytes of the decoded tile, with every row padded to a whole byte.
+    #[cfg(any(feature = "zstd", feature = "lerc"))]
+    pub(crate) fn decoded_bytes(&self) -> u64 {
+        (self.width as u64 * self.bits_per_pixel as u64).div_ceil(8) * self.height as u64
+    }
+}
+
+/// A decoder for the Deflate compression method.
+#[derive(Debug, Clone)]
+pub struct DeflateDecoder;
+
+impl Decoder for DeflateDecoder {
+    fn decode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        let mut decoder = ZlibDecoder::new(Cursor::new(buffer));
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf)?;
+        Ok(buf.into())
+    }
+}
+
+/// A decoder for the JPEG compression method.
+#[derive(Debug, Clone)]
+pub struct JPEGDecoder;
+
+impl Decoder for JPEGDecoder {
+    fn decode_tile(
+        &self,
+        buffer: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+        jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        decode_modern_jpeg(buffer, photometric_interpretation, jpeg_tables)
+    }
+}
+
+/// A decoder for the LZW compression method.
+#[derive(Debug, Clone)]
+pub struct LZWDecoder;
+
+impl Decoder for LZWDecoder {
+    fn decode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        // https://github.com/image-rs/image-tiff/blob/90ae5b8e54356a35e266fb24e969aafbcb26e990/src/decoder/stream.rs#L147
+        let mut decoder = weezl::decode::Decoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8);
+        let decoded = decoder
+            .decode(&buffer)
+            .map_err(|err| AsyncTiffError::General(format!("Failed to decode LZW data: {err}")))?;
+        Ok(decoded.into())
+    }
+}
+
+/// A decoder for uncompressed data.
+#[derive(Debug, Clone)]
+pub struct UncompressedDecoder;
+
+impl Decoder for UncompressedDecoder {
+    fn decode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        Ok(buffer)
+    }
+}
+
+/// A decoder for the ZSTD compression method, using the `zstd` crate.
+///
+/// As with the other decoders, the horizontal and floating point predictors that usually
+/// accompany ZSTD are reversed after decompression. When the tile layout is known, decompression
+/// stops at the size of the decoded tile, so that a small payload can't expand without bound.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone)]
+pub struct ZstdDecoder;
+
+#[cfg(feature = "zstd")]
+impl ZstdDecoder {
+    fn decompress(buffer: Bytes, limit: u64) -> AsyncTiffResult<Bytes> {
+        let decoder = zstd::stream::read::Decoder::with_buffer(Cursor::new(buffer))
+            .map_err(|err| AsyncTiffError::General(format!("Failed to decode ZSTD data: {err}")))?;
+        let mut buf = Vec::new();
+        decoder
+            .take(limit)
+            .read_to_end(&mut buf)
+            .map_err(|err| AsyncTiffError::General(format!("Failed to decode ZSTD data: {err}")))?;
+        Ok(buf.into())
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Decoder for ZstdDecoder {
+    fn decode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        Self::decompress(buffer, u64::MAX)
+    }
+
+    fn decode_tile_with_info(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        Self::decompress(buffer, info.decoded_bytes())
+    }
+}
+
+/// A decoder for the modified Huffman run length encoding of CCITT T.4, without end-of-line codes.
+#[derive(Debug, Clone)]
+pub struct HuffmanDecoder;
+
+impl Decoder for HuffmanDecoder {
+    fn decode_tile(
+        &self,
+        _buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        Err(fax_needs_info())
+    }
+
+    fn decode_tile_with_info(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        decode_fax(&buffer, info, fax::FaxCoding::Huffman)
+    }
+}
+
+/// A decoder for the CCITT T.4 Group 3 fax compression method, with one or two-dimensional coding
+/// as set by the T4Options of the image.
+#[derive(Debug, Clone)]
+pub struct Fax3Decoder;
+
+impl Decoder for Fax3Decoder {
+    fn decode_tile(
+        &self,
+        _buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        Err(fax_needs_info())
+    }
+
+    fn decode_tile_with_info(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        decode_fax(&buffer, info, fax::FaxCoding::Group3(info.fax_options))
+    }
+}
+
+/// A decoder for the CCITT T.6 Group 4 fax compression method.
+#[derive(Debug, Clone)]
+pub struct Fax4Decoder;
+
+impl Decoder for Fax4Decoder {
+    fn decode_tile(
+        &self,
+        _buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        Err(fax_needs_info())
+    }
+
+    fn decode_tile_with_info(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        decode_fax(&buffer, info, fax::FaxCoding::Group4(info.fax_options))
+    }
+}
+
+fn fax_needs_info() -> AsyncTiffError {
+    AsyncTiffError::General("CCITT fax data can't be decoded without the tile size".to_string())
+}
+
+/// Decode the rows of a bilevel tile into packed bits.
+fn decode_fax(buffer: &[u8], info: &TileInfo, coding: fax::FaxCoding) -> AsyncTiffResult<Bytes> {
+    if info.bits_per_pixel != 1 {
+        return Err(AsyncTiffError::General(format!(
+            "CCITT fax compression requires 1-bit pixels, not {}-bit",
+            info.bits_per_pixel
+        )));
+    }
+    let decoded = fax::decode(buffer, info.width, info.height, coding).map_err(|err| {
+        AsyncTiffError::General(format!("Failed to decode CCITT fax data: {err}"))
+    })?;
+    Ok(decoded.into())
+}
+
+// https://github.com/image-rs/image-tiff/blob/3bfb43e83e31b0da476832067ada68a82b378b7b/src/decoder/image.rs#L389-L450
+fn decode_modern_jpeg(
+    buf: Bytes,
+    photometric_interpretation: PhotometricInterpretation,
+    jpeg_tables: Option<&[u8]>,
+) -> AsyncTiffResult<Bytes> {
+    // Construct new jpeg_reader wrapping a SmartReader.
+    //
+    // JPEG compression in TIFF allows saving quantization and/or huffman tables in one central
+    // location. These `jpeg_tables` are simply prepended to the remaining jpeg image data. Because
+    // these `jpeg_tables` start with a `SOI` (HEX: `0xFFD8`) or __start of image__ marker which is
+    // also at the beginning of the remaining JPEG image data and would confuse the JPEG renderer,
+    // one of these has to be taken off. In this case the first two bytes of the remaining JPEG
+    // data is removed because it follows `jpeg_tables`. Similary, `jpeg_tables` ends with a `EOI`
+    // (HEX: `0xFFD9`) or __end of image__ marker, this has to be removed as well (last two bytes
+    // of `jpeg_tables`).
+    let reader = Cursor::new(buf);
+
+    let jpeg_reader = match jpeg_tables {
+        Some(jpeg_tables) => {
+            let mut reader = reader;
+            reader.read_exact(&mut [0; 2])?;
+
+            Box::new(Cursor::new(&jpeg_tables[..jpeg_tables.len() - 2]).chain(reader))
+                as Box<dyn Read>
+        }
+        None => Box::new(reader),
+    };
+
+    let mut decoder = jpeg::Decoder::new(jpeg_reader);
+
+    match photometric_interpretation {
+        PhotometricInterpretation::RGB => decoder.set_color_transform(jpeg::ColorTransform::RGB),
+        PhotometricInterpretation::WhiteIsZero
+        | PhotometricInterpretation::BlackIsZero
+        | PhotometricInterpretation::TransparencyMask => {
+            decoder.set_color_transform(jpeg::ColorTransform::None)
+        }
+        PhotometricInterpretation::CMYK => decoder.set_color_transform(jpeg::ColorTransform::CMYK),
+        PhotometricInterpretation::YCbCr => {
+            decoder.set_color_transform(jpeg::ColorTransform::YCbCr)
+        }
+        photometric_interpretation => {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedInterpretation(photometric_interpretation),
+            )
+            .into());
+        }
+    }
+
+    let data = decoder.decode()?;
+    Ok(data.into())
+}
+
+#[cfg(all(test, feature = "zstd"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zstd_limit() {
+        let data = vec![7; 1 << 20];
+        let encoded = Bytes::from(zstd::bulk::compress(&data, 3).unwrap());
+        let photometric = PhotometricInterpretation::BlackIsZero;
+        let decoded = ZstdDecoder
+            .decode_tile(encoded.clone(), photometric, None)
+            .unwrap();
+        assert_eq!(decoded.len(), data.len());
+
+        // Decompression stops at the size of the tile
+        let info = TileInfo::new(15, 4, 12);
+        let decoded = ZstdDecoder
+            .decode_tile_with_info(encoded, photometric, None, &info)
+            .unwrap();
+        assert_eq!(decoded.len(), 92);
+
+        assert!(ZstdDecoder
+            .decode_tile(Bytes::from_static(b"not zstd"), photometric, None)
+            .is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use bytes::Bytes;
+
+use crate::decoder::LabConverter;
+use crate::error::AsyncTiffResult;
+use crate::tiff::tags::{PhotometricInterpretation, SampleFormat};
+
+/// A trait to convert decoded samples of a color space to a canonical representation, such as
+/// RGB.
+pub trait PhotometricConverter: Debug + Send + Sync {
+    /// Convert the samples of a decoded tile.
+    ///
+    /// `buffer` holds pixel-interleaved, native-endian samples, after decompression and predictor
+    /// reversal. The result must keep the number, bit depth and format of the samples, so that
+    /// the decoded tile keeps the layout described by its IFD.
+    fn convert(
+        &self,
+        buffer: Bytes,
+        samples_per_pixel: u16,
+        bits_per_sample: u16,
+        sample_format: SampleFormat,
+    ) -> AsyncTiffResult<Bytes>;
+}
+
+/// A registry of [`PhotometricConverter`]s, keyed by the photometric interpretation they convert.
+///
+/// Attach it to a [`DecoderRegistry`](super::DecoderRegistry) with
+/// [`with_photometric_registry`](super::DecoderRegistry::with_photometric_registry). Tiles whose
+/// photometric interpretation has no converter are returned as decoded.
+///
+/// The default registry converts [`CIELab`](PhotometricInterpretation::CIELab) and
+/// [`ICCLab`](PhotometricInterpretation::ICCLab) to RGB with [`LabConverter`].
+#[derive(Debug)]
+pub struct PhotometricRegistry {
+    converters: HashMap<PhotometricInterpretation, Box<dyn PhotometricConverter>>,
+}
+
+impl PhotometricRegistry {
+    /// Create a new registry with no converters registered.
+    pub fn new() -> Self {
+        Self {
+            converters: HashMap::new(),
+        }
+    }
+}
+
+impl Default for PhotometricRegistry {
+    fn default() -> Self {
+        let mut converters = HashMap::with_capacity(2);
+        converters.insert(
+            PhotometricInterpretation::CIELab,
+            Box::new(LabConverter::cielab()) as _,
+        );
+        converters.insert(
+            PhotometricInterpretation::ICCLab,
+            Box::new(LabConverter::icclab()) as _,
+        );
+        Self { converters }
+    }
+}
+
+impl AsRef<HashMap<PhotometricInterpretation, Box<dyn PhotometricConverter>>>
+    for PhotometricRegistry
+{
+    fn as_ref(&self) -> &HashMap<PhotometricInterpretation, Box<dyn PhotometricConverter>> {
+        &self.converters
+    }
+}
+
+impl AsMut<HashMap<PhotometricInterpretation, Box<dyn PhotometricConverter>>>
+    for PhotometricRegistry
+{
+    fn as_mut(&mut self) -> &mut HashMap<PhotometricInterpretation, Box<dyn PhotometricConverter>> {
+        &mut self.converters
+    }
+}
@@ -0,0 +1,227 @@
+use std::any::Any;
+use std::fmt::Debug;
+use std::num::NonZeroUsize;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use bytes::Bytes;
+use futures::channel::oneshot;
+use futures::future::{join_all, try_join_all};
+
+use crate::decoder::DecoderRegistry;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::tile::Tile;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A pool of threads dedicated to CPU-bound tile decoding.
+///
+/// Decoding (decompression and predictor reversal) is synchronous and can take a significant
+/// amount of CPU time. Running it directly on an async runtime blocks that runtime's worker
+/// threads, so this pool offloads the work to its own threads and exposes the results as futures.
+/// The same pool can encode tiles when writing, see
+/// [`CogWriter::with_pool`](crate::CogWriter::with_pool).
+///
+/// Cloning a `DecodePool` is cheap and clones share the same threads. The threads are shut down
+/// once the last clone is dropped, after finishing any queued work.
+#[derive(Debug, Clone)]
+pub struct DecodePool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    sender: Mutex<Option<Sender<Job>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Debug for PoolInner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolInner")
+            .field("num_threads", &self.workers.len())
+            .finish()
+    }
+}
+
+impl DecodePool {
+    /// Create a new pool with `num_threads` worker threads.
+    pub fn new(num_threads: usize) -> AsyncTiffResult<Self> {
+        if num_threads == 0 {
+            return Err(AsyncTiffError::General(
+                "DecodePool requires at least one thread".to_string(),
+            ));
+        }
+
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..num_threads)
+            .map(|idx| {
+                let receiver = receiver.clone();
+                std::thread::Builder::new()
+                    .name(format!("async-tiff-decode-{idx}"))
+                    .spawn(move || worker_loop(&receiver))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                sender: Mutex::new(Some(sender)),
+                workers,
+            }),
+        })
+    }
+
+    /// The number of worker threads in this pool.
+    pub fn num_threads(&self) -> usize {
+        self.inner.workers.len()
+    }
+
+    /// Run a closure on the pool, returning its result asynchronously.
+    ///
+    /// A panic in the closure is caught and returned as an error, leaving the worker thread
+    /// running.
+    pub async fn spawn<F, R>(&self, f: F) -> AsyncTiffResult<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(f)).map_err(panic_error);
+            // The receiver may have been dropped if the caller is no longer interested.
+            let _ = tx.send(result);
+        });
+
+        self.inner
+            .sender
+            .lock()
+            .unwrap()
+            .as_ref()
+            .ok_or_else(|| AsyncTiffError::General("DecodePool has been shut down".to_string()))?
+            .send(job)
+            .map_err(|_| AsyncTiffError::General("DecodePool has been shut down".to_string()))?;
+
+        rx.await
+            .map_err(|_| AsyncTiffError::General("DecodePool has been shut down".to_string()))?
+    }
+
+    /// Decode a single tile on the pool.
+    pub async fn decode(
+        &self,
+        tile: Tile,
+        decoder_registry: Arc<DecoderRegistry>,
+    ) -> AsyncTiffResult<Bytes> {
+        self.spawn(move || tile.decode(&decoder_registry)).await?
+    }
+
+    /// Decode many tiles concurrently on the pool.
+    ///
+    /// The output is in the same order as the input tiles.
+    pub async fn decode_tiles(
+        &self,
+        tiles: Vec<Tile>,
+        decoder_registry: Arc<DecoderRegistry>,
+    ) -> AsyncTiffResult<Vec<Bytes>> {
+        try_join_all(
+            tiles
+                .into_iter()
+                .map(|tile| self.decode(tile, decoder_registry.clone())),
+        )
+        .await
+    }
+
+    /// Decode many tiles concurrently on the pool, with a separate result per tile.
+    ///
+    /// Unlike [`decode_tiles`](Self::decode_tiles), a tile that fails to decode doesn't abort the
+    /// others. The output is in the same order as the input tiles.
+    pub async fn decode_tiles_isolated(
+        &self,
+        tiles: Vec<Tile>,
+        decoder_registry: Arc<DecoderRegistry>,
+    ) -> Vec<AsyncTiffResult<Bytes>> {
+        join_all(
+            tiles
+                .into_iter()
+                .map(|tile| self.decode(tile, decoder_registry.clone())),
+        )
+        .await
+    }
+}
+
+impl Default for DecodePool {
+    /// Create a pool with one thread per available CPU.
+    fn default() -> Self {
+        let num_threads = std::thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1);
+        Self::new(num_threads).expect("failed to spawn decode threads")
+    }
+}
+
+impl Drop for PoolInner {
+    fn drop(&mut self) {
+        // Closing the channel makes each worker exit once the queue is drained.
+        self.sender.lock().unwrap().take();
+        for worker in self.workers.drain(..) {
+            // A worker can't join itself, e.g. if the last handle was dropped inside a task.
+            if worker.thread().id() != std::thread::current().id() {
+                let _ = worker.join();
+            }
+        }
+    }
+}
+
+/// The error returned for a job that panicked with `payload`.
+fn panic_error(payload: Box<dyn Any + Send>) -> AsyncTiffError {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    AsyncTiffError::General(format!("Decode task panicked: {message}"))
+}
+
+fn worker_loop(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        job();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawn() {
+        let pool = DecodePool::new(2).unwrap();
+        assert_eq!(pool.num_threads(), 2);
+        let results = try_join_all((0..8).map(|i| pool.spawn(move || i * 2)))
+            .await
+            .unwrap();
+        assert_eq!(results, vec![0, 2, 4, 6, 8, 10, 12, 14]);
+    }
+
+    #[tokio::test]
+    async fn test_panic() {
+        let pool = DecodePool::new(1).unwrap();
+        for _ in 0..2 {
+            let err = pool
+                .spawn(|| -> u8 { panic!("corrupt tile") })
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("corrupt tile"), "{err}");
+        }
+        // The only worker survived the panics
+        assert_eq!(pool.spawn(|| 42).await.unwrap(), 42);
+    }
+
+    #[test]
+    fn test_zero_threads() {
+        assert!(DecodePool::new(0).is_err());
+    }
+}
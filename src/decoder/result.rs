@@ -0,0 +1,524 @@
+use std::slice::ChunksExact;
+
+use bytes::Bytes;
+
+use crate::decoder::{DecodeLimits, F16};
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::predictor::PredictorInfo;
+use crate::tiff::tags::SampleFormat;
+
+/// The decoded samples of a tile or strip, typed by their sample format and bit depth.
+///
+/// Samples are in native byte order and in the order they were decoded: pixel-interleaved for
+/// chunky images, and a single band per chunk for planar images. Samples narrower than a byte,
+/// such as those of bilevel images, are expanded to one byte each in [`DecodingResult::U8`].
+///
+/// 8-bit samples are held in [`Bytes`], so that they can share the buffer of the decoded tile:
+/// for uncompressed tiles without a predictor, this is the buffer fetched from the file, and no
+/// copy of the samples is made at all.
+///
+/// This is returned by [`Tile::decode_typed`](crate::Tile::decode_typed).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodingResult {
+    /// Unsigned 8-bit samples, or samples narrower than a byte expanded to one byte each.
+    U8(Bytes),
+    /// Unsigned 16-bit samples.
+    U16(Vec<u16>),
+    /// Unsigned 32-bit samples.
+    U32(Vec<u32>),
+    /// Unsigned 64-bit samples.
+    U64(Vec<u64>),
+    /// Signed 8-bit samples.
+    I8(Vec<i8>),
+    /// Signed 16-bit samples.
+    I16(Vec<i16>),
+    /// Signed 32-bit samples.
+    I32(Vec<i32>),
+    /// Signed 64-bit samples.
+    I64(Vec<i64>),
+    /// 16-bit floating point samples.
+    F16(Vec<F16>),
+    /// 32-bit floating point samples.
+    F32(Vec<f32>),
+    /// 64-bit floating point samples.
+    F64(Vec<f64>),
+}
+
+/// Apply an expression to the data of every variant of a [`DecodingResult`]-shaped enum, wrapping
+/// the result in the same variant of `$out`.
+macro_rules! map_variants {
+    ($value:expr, $out:ident, |$data:ident| $body:expr) => {
+        match $value {
+            DecodingResult::U8($data) => $out::U8($body),
+            DecodingResult::U16($data) => $out::U16($body),
+            DecodingResult::U32($data) => $out::U32($body),
+            DecodingResult::U64($data) => $out::U64($body),
+            DecodingResult::I8($data) => $out::I8($body),
+            DecodingResult::I16($data) => $out::I16($body),
+            DecodingResult::I32($data) => $out::I32($body),
+            DecodingResult::I64($data) => $out::I64($body),
+            DecodingResult::F16($data) => $out::F16($body),
+            DecodingResult::F32($data) => $out::F32($body),
+            DecodingResult::F64($data) => $out::F64($body),
+        }
+    };
+}
+
+impl DecodingResult {
+    /// Interpret native-endian decoded bytes as the samples described by `predictor_info`, in
+    /// rows of `columns` pixels, checking the chunk and the output against `limits`.
+    pub(crate) fn from_predictor_info(
+        data: Bytes,
+        predictor_info: &PredictorInfo,
+        columns: u32,
+        limits: &DecodeLimits,
+    ) -> AsyncTiffResult<Self> {
+        limits.check_tile_pixels(predictor_info.padded_chunk_pixels())?;
+        let bits_per_sample = predictor_info.bits_per_sample();
+        if bits_per_sample < 8 {
+            let row_samples = columns as usize * predictor_info.chunk_samples_per_pixel();
+            let rows = data.len() / (row_samples * bits_per_sample as usize).div_ceil(8).max(1);
+            limits.check_output_bytes((rows * row_samples) as u64)?;
+            return Ok(Self::U8(
+                unpack_samples(&data, row_samples, bits_per_sample).into(),
+            ));
+        }
+        limits.check_output_bytes(data.len() as u64)?;
+        // Unsigned bytes need no conversion, so share the buffer rather than copying it.
+        if predictor_info.bits_per_sample() <= 8
+            && !matches!(
+                predictor_info.sample_format(),
+                SampleFormat::Int | SampleFormat::IEEEFP
+            )
+        {
+            return Ok(Self::U8(data));
+        }
+        Self::from_bytes(
+            &data,
+            predictor_info.sample_format(),
+            predictor_info.bits_per_sample(),
+        )
+    }
+
+    /// Interpret native-endian bytes as samples of the given format and bit depth.
+    pub(crate) fn from_bytes(
+        data: &[u8],
+        sample_format: SampleFormat,
+        bits_per_sample: u16,
+    ) -> AsyncTiffResult<Self> {
+        fn collect<const N: usize, T>(data: &[u8], f: fn([u8; N]) -> T) -> Vec<T> {
+            data.chunks_exact(N)
+                .map(|chunk| f(chunk.try_into().unwrap()))
+                .collect()
+        }
+
+        let result = match (sample_format, bits_per_sample) {
+            (SampleFormat::Int, 8) => Self::I8(collect(data, i8::from_ne_bytes)),
+            (SampleFormat::Int, 16) => Self::I16(collect(data, i16::from_ne_bytes)),
+            (SampleFormat::Int, 32) => Self::I32(collect(data, i32::from_ne_bytes)),
+            (SampleFormat::Int, 64) => Self::I64(collect(data, i64::from_ne_bytes)),
+            (SampleFormat::IEEEFP, 16) => Self::F16(collect(data, |bytes| {
+                F16::from_bits(u16::from_ne_bytes(bytes))
+            })),
+            (SampleFormat::IEEEFP, 32) => Self::F32(collect(data, f32::from_ne_bytes)),
+            (SampleFormat::IEEEFP, 64) => Self::F64(collect(data, f64::from_ne_bytes)),
+            (SampleFormat::IEEEFP, bits) => {
+                return Err(AsyncTiffError::General(format!(
+                    "Unsupported {bits}-bit floating point samples"
+                )))
+            }
+            (_, bits) if bits <= 8 => Self::U8(Bytes::copy_from_slice(data)),
+            (_, 16) => Self::U16(collect(data, u16::from_ne_bytes)),
+            (_, 32) => Self::U32(collect(data, u32::from_ne_bytes)),
+            (_, 64) => Self::U64(collect(data, u64::from_ne_bytes)),
+            (_, bits) => {
+                return Err(AsyncTiffError::General(format!(
+                    "Unsupported {bits}-bit samples"
+                )))
+            }
+        };
+        Ok(result)
+    }
+
+    /// The number of samples.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U8(data) => data.len(),
+            Self::U16(data) => data.len(),
+            Self::U32(data) => data.len(),
+            Self::U64(data) => data.len(),
+            Self::I8(data) => data.len(),
+            Self::I16(data) => data.len(),
+            Self::I32(data) => data.len(),
+            Self::I64(data) => data.len(),
+            Self::F16(data) => data.len(),
+            Self::F32(data) => data.len(),
+            Self::F64(data) => data.len(),
+        }
+    }
+
+    /// Returns `true` if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// View the samples as `rows` rows of `cols` pixels with `bands` interleaved samples each.
+    ///
+    /// Any samples past the first `rows * cols * bands`, such as the padding rows of an edge
+    /// tile, are excluded from the view. Returns an error if there are fewer samples than that.
+    pub fn view(
+        &self,
+        rows: usize,
+        cols: usize,
+        bands: usize,
+    ) -> AsyncTiffResult<DecodingView<'_>> {
+        Ok(map_variants!(self, DecodingView, |data| SampleView::new(
+            data, rows, cols, bands
+        )?))
+    }
+
+    /// Split pixel-interleaved samples with `bands` samples per pixel into one result per band.
+    ///
+    /// Returns an error if `bands` is zero or the number of samples isn't a multiple of it.
+    pub fn split_bands(&self, bands: usize) -> AsyncTiffResult<Vec<DecodingResult>> {
+        if bands == 0 || !self.len().is_multiple_of(bands) {
+            return Err(AsyncTiffError::General(format!(
+                "Cannot split {} samples into {bands} bands",
+                self.len()
+            )));
+        }
+        Ok((0..bands)
+            .map(|band| {
+                map_variants!(self, DecodingResult, |data| data
+                    .iter()
+                    .skip(band)
+                    .step_by(bands)
+                    .copied()
+                    .collect())
+            })
+            .collect())
+    }
+}
+
+/// A sample type that a [`DecodingResult`] can hold.
+///
+/// This is implemented for every primitive type with a [`DecodingResult`] variant, so that
+/// generic code can access decoded samples with
+/// [`as_slice`](DecodingResult::as_slice) and [`try_into_vec`](DecodingResult::try_into_vec). It
+/// is sealed and can't be implemented outside of this crate.
+pub trait Sample: private::Sealed + Copy + Send + Sync + 'static {
+    /// The samples of `result`, if it holds samples of this type.
+    fn slice_of(result: &DecodingResult) -> Option<&[Self]>;
+
+    /// The samples of `result`, or `result` itself if it doesn't hold samples of this type.
+    fn vec_of(result: DecodingResult) -> Result<Vec<Self>, DecodingResult>;
+
+    /// Wrap `samples` in the matching [`DecodingResult`] variant.
+    fn into_result(samples: Vec<Self>) -> DecodingResult;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_sample {
+    ($($ty:ty => $variant:ident),*) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl Sample for $ty {
+                fn slice_of(result: &DecodingResult) -> Option<&[Self]> {
+                    match result {
+                        DecodingResult::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+
+                fn vec_of(result: DecodingResult) -> Result<Vec<Self>, DecodingResult> {
+                    match result {
+                        DecodingResult::$variant(data) => Ok(data.into()),
+                        result => Err(result),
+                    }
+                }
+
+                fn into_result(samples: Vec<Self>) -> DecodingResult {
+                    DecodingResult::$variant(samples.into())
+                }
+            }
+
+            impl From<Vec<$ty>> for DecodingResult {
+                fn from(samples: Vec<$ty>) -> Self {
+                    DecodingResult::$variant(samples.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_sample!(
+    u8 => U8, u16 => U16, u32 => U32, u64 => U64,
+    i8 => I8, i16 => I16, i32 => I32, i64 => I64,
+    F16 => F16, f32 => F32, f64 => F64
+);
+
+impl DecodingResult {
+    /// The samples, if they are of type `T`.
+    ///
+    /// ```
+    /// # use async_tiff::decoder::DecodingResult;
+    /// let result = DecodingResult::from(vec![1.0f32, 2.0]);
+    /// assert_eq!(result.as_slice::<f32>(), Some(&[1.0, 2.0][..]));
+    /// assert_eq!(result.as_slice::<u16>(), None);
+    /// ```
+    pub fn as_slice<T: Sample>(&self) -> Option<&[T]> {
+        T::slice_of(self)
+    }
+
+    /// Take the samples if they are of type `T`, or return `self` unchanged otherwise.
+    ///
+    /// 8-bit samples are copied if their buffer is shared, e.g. with the fetched tile.
+    pub fn try_into_vec<T: Sample>(self) -> Result<Vec<T>, Self> {
+        T::vec_of(self)
+    }
+
+    /// View the samples as rows of pixels, as [`view`](Self::view), if they are of type `T`.
+    ///
+    /// Returns an error if the samples are of a different type or there are too few of them.
+    pub fn view_as<T: Sample>(
+        &self,
+        rows: usize,
+        cols: usize,
+        bands: usize,
+    ) -> AsyncTiffResult<SampleView<'_, T>> {
+        let data = self.as_slice::<T>().ok_or_else(|| {
+            AsyncTiffError::General(format!(
+                "Decoded samples are not of type {}",
+                std::any::type_name::<T>()
+            ))
+        })?;
+        SampleView::new(data, rows, cols, bands)
+    }
+}
+
+/// A typed view of a [`DecodingResult`] as rows of pixels.
+///
+/// This is returned by [`DecodingResult::view`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodingView<'a> {
+    /// A view of [`DecodingResult::U8`].
+    U8(SampleView<'a, u8>),
+    /// A view of [`DecodingResult::U16`].
+    U16(SampleView<'a, u16>),
+    /// A view of [`DecodingResult::U32`].
+    U32(SampleView<'a, u32>),
+    /// A view of [`DecodingResult::U64`].
+    U64(SampleView<'a, u64>),
+    /// A view of [`DecodingResult::I8`].
+    I8(SampleView<'a, i8>),
+    /// A view of [`DecodingResult::I16`].
+    I16(SampleView<'a, i16>),
+    /// A view of [`DecodingResult::I32`].
+    I32(SampleView<'a, i32>),
+    /// A view of [`DecodingResult::I64`].
+    I64(SampleView<'a, i64>),
+    /// A view of [`DecodingResult::F16`].
+    F16(SampleView<'a, F16>),
+    /// A view of [`DecodingResult::F32`].
+    F32(SampleView<'a, f32>),
+    /// A view of [`DecodingResult::F64`].
+    F64(SampleView<'a, f64>),
+}
+
+/// Pixel-interleaved samples of type `T`, viewed as `rows` rows of `cols` pixels with `bands`
+/// samples each.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleView<'a, T> {
+    data: &'a [T],
+    rows: usize,
+    cols: usize,
+    bands: usize,
+}
+
+impl<'a, T: Copy> SampleView<'a, T> {
+    /// View the first `rows * cols * bands` samples of `data`.
+    fn new(data: &'a [T], rows: usize, cols: usize, bands: usize) -> AsyncTiffResult<Self> {
+        let required = rows * cols * bands;
+        if data.len() < required {
+            return Err(AsyncTiffError::General(format!(
+                "Cannot view {} samples as {rows}x{cols}x{bands}",
+                data.len()
+            )));
+        }
+        Ok(Self {
+            data: &data[..required],
+            rows,
+            cols,
+            bands,
+        })
+    }
+
+    /// The number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of pixels in each row.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The number of samples in each pixel.
+    pub fn bands(&self) -> usize {
+        self.bands
+    }
+
+    /// All samples in the view, in row-major order.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.data
+    }
+
+    /// The samples of row `row`, or `None` if it is out of bounds.
+    pub fn row(&self, row: usize) -> Option<&'a [T]> {
+        let row_len = self.cols * self.bands;
+        (row < self.rows).then(|| &self.data[row * row_len..(row + 1) * row_len])
+    }
+
+    /// Iterate over the samples of each row.
+    pub fn iter_rows(&self) -> ChunksExact<'a, T> {
+        // chunks_exact panics on a zero chunk size, and an empty view has no rows to yield.
+        self.data.chunks_exact((self.cols * self.bands).max(1))
+    }
+
+    /// The samples of the pixel at `row` and `col`, or `None` if it is out of bounds.
+    pub fn pixel(&self, row: usize, col: usize) -> Option<&'a [T]> {
+        let start = (col < self.cols).then_some(col * self.bands)?;
+        Some(&self.row(row)?[start..start + self.bands])
+    }
+
+    /// The sample of `band` of the pixel at `row` and `col`, or `None` if it is out of bounds.
+    pub fn get(&self, row: usize, col: usize, band: usize) -> Option<T> {
+        self.pixel(row, col)?.get(band).copied()
+    }
+
+    /// Iterate over the samples of `band`, in row-major order.
+    pub fn iter_band(&self, band: usize) -> impl Iterator<Item = T> + 'a {
+        let bands = self.bands.max(1);
+        let skip = if band < self.bands {
+            band
+        } else {
+            self.data.len()
+        };
+        self.data.iter().skip(skip).step_by(bands).copied()
+    }
+}
+
+/// Expand samples of `bits_per_sample` bits, packed most significant bit first into rows of
+/// `row_samples` samples that are each padded to a whole byte, to one byte per sample.
+fn unpack_samples(data: &[u8], row_samples: usize, bits_per_sample: u16) -> Vec<u8> {
+    let bits = bits_per_sample as usize;
+    let row_bytes = (row_samples * bits).div_ceil(8);
+    if row_bytes == 0 {
+        return vec![];
+    }
+    let mask = (1u16 << bits) - 1;
+    let mut samples = Vec::with_capacity(data.len() / row_bytes * row_samples);
+    for row in data.chunks_exact(row_bytes) {
+        for bit in (0..row_samples).map(|i| i * bits) {
+            // A sample may straddle two bytes
+            let next = row.get(bit / 8 + 1).copied().unwrap_or(0);
+            let pair = u16::from_be_bytes([row[bit / 8], next]);
+            samples.push((pair >> (16 - bits - bit % 8) & mask) as u8);
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_view() {
+        // 2 rows of 3 RGB pixels, followed by a padding row
+        let result = DecodingResult::U16((0..27).collect());
+        let DecodingView::U16(view) = result.view(2, 3, 3).unwrap() else {
+            panic!("Expected a u16 view");
+        };
+        assert_eq!(view.row(1), Some(&[9, 10, 11, 12, 13, 14, 15, 16, 17][..]));
+        assert_eq!(view.row(2), None);
+        assert_eq!(view.iter_rows().count(), 2);
+        assert_eq!(view.pixel(1, 2), Some(&[15, 16, 17][..]));
+        assert_eq!(view.pixel(1, 3), None);
+        assert_eq!(view.get(0, 1, 2), Some(5));
+        assert_eq!(view.get(0, 1, 3), None);
+        assert_eq!(view.iter_band(1).collect::<Vec<_>>(), [1, 4, 7, 10, 13, 16]);
+        assert_eq!(view.iter_band(3).count(), 0);
+
+        assert!(result.view(3, 3, 4).is_err());
+    }
+
+    #[test]
+    fn test_unpack_samples() {
+        // 2 rows of 10 1-bit samples, each padded to 2 bytes
+        let data = [0b1010_0000, 0b0100_0000, 0b0000_0000, 0b1111_1111];
+        assert_eq!(
+            unpack_samples(&data, 10, 1),
+            [1, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1]
+        );
+        // 3-bit samples straddle bytes
+        let data = [0b0010_1001, 0b1100_0000];
+        assert_eq!(unpack_samples(&data, 4, 3), [1, 2, 3, 4]);
+        assert_eq!(unpack_samples(&[0b0001_1011], 4, 2), [0, 1, 2, 3]);
+        assert!(unpack_samples(&data, 0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_generic_access() {
+        fn sum<T: Sample + Into<f64>>(result: &DecodingResult) -> Option<f64> {
+            Some(result.as_slice::<T>()?.iter().map(|v| (*v).into()).sum())
+        }
+
+        let result = DecodingResult::from(vec![1i16, -2, 3, 4]);
+        assert_eq!(sum::<i16>(&result), Some(6.0));
+        assert_eq!(sum::<u16>(&result), None);
+
+        let view = result.view_as::<i16>(2, 1, 2).unwrap();
+        assert_eq!(view.row(1), Some(&[3, 4][..]));
+        assert!(result.view_as::<f32>(2, 1, 2).is_err());
+
+        let result = result.try_into_vec::<u8>().unwrap_err();
+        assert_eq!(result.try_into_vec::<i16>().unwrap(), [1, -2, 3, 4]);
+    }
+
+    #[test]
+    fn test_split_bands() {
+        let result = DecodingResult::F32(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(
+            result.split_bands(2).unwrap(),
+            [
+                DecodingResult::F32(vec![1.0, 3.0, 5.0]),
+                DecodingResult::F32(vec![2.0, 4.0, 6.0]),
+            ]
+        );
+        assert!(result.split_bands(4).is_err());
+        assert!(result.split_bands(0).is_err());
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let data = 258u16.to_ne_bytes().repeat(2);
+        assert_eq!(
+            DecodingResult::from_bytes(&data, SampleFormat::Uint, 16).unwrap(),
+            DecodingResult::U16(vec![258, 258])
+        );
+        assert_eq!(
+            DecodingResult::from_bytes(&data, SampleFormat::Int, 8).unwrap(),
+            DecodingResult::I8(data.iter().map(|b| *b as i8).collect())
+        );
+        assert_eq!(
+            DecodingResult::from_bytes(&0x3C00u16.to_ne_bytes(), SampleFormat::IEEEFP, 16).unwrap(),
+            DecodingResult::F16(vec![F16::from_f32(1.0)])
+        );
+        assert!(DecodingResult::from_bytes(&data, SampleFormat::IEEEFP, 24).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::tiff::tags::{CompressionMethod, Predictor};
+
+/// Aggregated counters for a single decoding stage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageStats {
+    /// The number of tiles processed by this stage.
+    pub count: u64,
+    /// The total number of bytes passed into this stage.
+    pub input_bytes: u64,
+    /// The total number of bytes produced by this stage.
+    pub output_bytes: u64,
+    /// The total wall-clock time spent in this stage.
+    pub duration: Duration,
+}
+
+impl StageStats {
+    /// The average output throughput of this stage, in bytes per second.
+    ///
+    /// Returns `None` if no time has been recorded.
+    pub fn throughput(&self) -> Option<f64> {
+        let secs = self.duration.as_secs_f64();
+        (secs > 0.0).then(|| self.output_bytes as f64 / secs)
+    }
+
+    fn record(&mut self, input_bytes: usize, output_bytes: usize, duration: Duration) {
+        self.count += 1;
+        self.input_bytes += input_bytes as u64;
+        self.output_bytes += output_bytes as u64;
+        self.duration += duration;
+    }
+}
+
+/// Performance counters collected while decoding tiles.
+///
+/// Attach this to a [`DecoderRegistry`][super::DecoderRegistry] with
+/// [`with_stats`][super::DecoderRegistry::with_stats]. Every tile decoded through that registry,
+/// including tiles decoded on a [`DecodePool`][super::DecodePool], then records the time and bytes
+/// spent in decompression (keyed by compression method) and predictor reversal (keyed by
+/// predictor). This makes it possible to see whether e.g. JPEG, deflate or the floating point
+/// predictor dominates a workload.
+#[derive(Debug, Default)]
+pub struct DecodeStats {
+    decompression: Mutex<HashMap<CompressionMethod, StageStats>>,
+    predictor: Mutex<HashMap<Predictor, StageStats>>,
+}
+
+impl DecodeStats {
+    /// Create a new, empty set of counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A snapshot of the decompression counters, per compression method.
+    pub fn decompression(&self) -> HashMap<CompressionMethod, StageStats> {
+        self.decompression.lock().unwrap().clone()
+    }
+
+    /// A snapshot of the predictor reversal counters, per predictor.
+    pub fn predictor(&self) -> HashMap<Predictor, StageStats> {
+        self.predictor.lock().unwrap().clone()
+    }
+
+    /// Reset all counters to zero.
+    pub fn reset(&self) {
+        self.decompression.lock().unwrap().clear();
+        self.predictor.lock().unwrap().clear();
+    }
+
+    pub(crate) fn record_decompression(
+        &self,
+        method: CompressionMethod,
+        input_bytes: usize,
+        output_bytes: usize,
+        duration: Duration,
+    ) {
+        self.decompression
+            .lock()
+            .unwrap()
+            .entry(method)
+            .or_default()
+            .record(input_bytes, output_bytes, duration);
+    }
+
+    pub(crate) fn record_predictor(
+        &self,
+        predictor: Predictor,
+        input_bytes: usize,
+        output_bytes: usize,
+        duration: Duration,
+    ) {
+        self.predictor
+            .lock()
+            .unwrap()
+            .entry(predictor)
+            .or_default()
+            .record(input_bytes, output_bytes, duration);
+    }
+}
@@ -0,0 +1,115 @@
+use flate2::{Decompress, FlushDecompress, Status};
+use weezl::LzwStatus;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::tiff::tags::CompressionMethod;
+
+/// An incremental decompressor, for decoding a chunk without holding all of its decompressed
+/// bytes in memory at once.
+///
+/// Compressed input is pushed in pieces of any size, and decompressed output is produced up to a
+/// caller-chosen limit, so memory use is bounded by the limit rather than by the chunk size.
+pub(crate) enum StreamingDecompressor {
+    None,
+    Deflate(Box<Decompress>),
+    Lzw(Box<weezl::decode::Decoder>),
+}
+
+impl StreamingDecompressor {
+    /// The incremental decompressor for `compression`, or `None` if that method can only be
+    /// decompressed all at once.
+    pub(crate) fn new(compression: CompressionMethod) -> Option<Self> {
+        match compression {
+            CompressionMethod::None => Some(Self::None),
+            CompressionMethod::Deflate | CompressionMethod::OldDeflate => {
+                Some(Self::Deflate(Box::new(Decompress::new(true))))
+            }
+            CompressionMethod::LZW => Some(Self::Lzw(Box::new(
+                weezl::decode::Decoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8),
+            ))),
+            _ => None,
+        }
+    }
+
+    /// Decompress from `input`, appending to `output` until it holds `limit` bytes.
+    ///
+    /// Returns the number of input bytes consumed, and whether the end of the compressed stream
+    /// was reached.
+    pub(crate) fn decompress(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        limit: usize,
+    ) -> AsyncTiffResult<(usize, bool)> {
+        let space = limit.saturating_sub(output.len());
+        match self {
+            Self::None => {
+                let len = space.min(input.len());
+                output.extend_from_slice(&input[..len]);
+                Ok((len, false))
+            }
+            Self::Deflate(decompress) => {
+                let start = output.len();
+                output.resize(start + space, 0);
+                let (total_in, total_out) = (decompress.total_in(), decompress.total_out());
+                let status = decompress
+                    .decompress(input, &mut output[start..], FlushDecompress::None)
+                    .map_err(|err| AsyncTiffError::External(Box::new(err)));
+                output.truncate(start + (decompress.total_out() - total_out) as usize);
+                let consumed = (decompress.total_in() - total_in) as usize;
+                Ok((consumed, status? == Status::StreamEnd))
+            }
+            Self::Lzw(decoder) => {
+                let start = output.len();
+                output.resize(start + space, 0);
+                let result = decoder.decode_bytes(input, &mut output[start..]);
+                output.truncate(start + result.consumed_out);
+                let status = result.status.map_err(|err| {
+                    AsyncTiffError::General(format!("Failed to decode LZW data: {err}"))
+                })?;
+                Ok((result.consumed_in, matches!(status, LzwStatus::Done)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn test_streaming_decompression() {
+        let data = (0..10_000u32).map(|v| (v % 251) as u8).collect::<Vec<_>>();
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(&data).unwrap();
+        let deflated = encoder.finish().unwrap();
+        let lzw = weezl::encode::Encoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8)
+            .encode(&data)
+            .unwrap();
+
+        for (compression, compressed) in [
+            (CompressionMethod::None, &data),
+            (CompressionMethod::Deflate, &deflated),
+            (CompressionMethod::LZW, &lzw),
+        ] {
+            let mut decompressor = StreamingDecompressor::new(compression).unwrap();
+            let mut decoded = vec![];
+            let mut input = &compressed[..];
+            // Feed input in small pieces, and take at most 1000 bytes of output at a time.
+            while decoded.len() < data.len() {
+                let mut chunk = vec![];
+                let piece = &input[..input.len().min(100)];
+                let (consumed, _) = decompressor.decompress(piece, &mut chunk, 1000).unwrap();
+                assert!(chunk.len() <= 1000);
+                input = &input[consumed..];
+                decoded.extend(chunk);
+            }
+            assert_eq!(decoded, data);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use bytes::Bytes;
+
+use crate::decoder::TileInfo;
+use crate::tiff::tags::{CompressionMethod, PhotometricInterpretation};
+
+/// Compressed payloads longer than this are never considered for the cache.
+///
+/// Uniform tiles compress extremely well, so their payloads are tiny compared to the tile size.
+const MAX_PAYLOAD_LEN: usize = 1024;
+
+/// The maximum number of distinct uniform payloads to remember.
+const CAPACITY: usize = 64;
+
+type Key = (
+    CompressionMethod,
+    PhotometricInterpretation,
+    TileInfo,
+    Bytes,
+);
+
+/// A cache of decompressed tiles whose bytes are all identical.
+///
+/// Encoders produce byte-identical payloads for identical tiles, so e.g. every empty tile of a
+/// mostly-empty mask band compresses to the same short deflate stream. The first such payload is
+/// decompressed normally; later ones are recognized by their compressed bytes and served from this
+/// cache without being decompressed again. Because [`Bytes`] is reference-counted, the cached
+/// buffer is shared rather than copied.
+///
+/// Entries are keyed on the [`TileInfo`] as well, since decoders such as the CCITT fax ones
+/// decode the same payload differently for tiles of different sizes or options.
+#[derive(Debug, Default)]
+pub(crate) struct UniformTileCache(Mutex<HashMap<Key, Bytes>>);
+
+impl UniformTileCache {
+    pub(crate) fn get(
+        &self,
+        compression_method: CompressionMethod,
+        photometric_interpretation: PhotometricInterpretation,
+        info: &TileInfo,
+        compressed_bytes: &Bytes,
+    ) -> Option<Bytes> {
+        if compressed_bytes.len() > MAX_PAYLOAD_LEN {
+            return None;
+        }
+        let key = (
+            compression_method,
+            photometric_interpretation,
+            *info,
+            compressed_bytes.clone(),
+        );
+        self.0.lock().unwrap().get(&key).cloned()
+    }
+
+    /// Remember `decoded` for `compressed_bytes` if it is a uniform buffer.
+    pub(crate) fn insert(
+        &self,
+        compression_method: CompressionMethod,
+        photometric_interpretation: PhotometricInterpretation,
+        info: &TileInfo,
+        compressed_bytes: &Bytes,
+        decoded: &Bytes,
+    ) {
+        if compressed_bytes.len() > MAX_PAYLOAD_LEN || !is_uniform(decoded) {
+            return;
+        }
+        let mut cache = self.0.lock().unwrap();
+        if cache.len() >= CAPACITY {
+            return;
+        }
+        // Copy the key so that the cache doesn't keep the tile's (possibly larger) parent
+        // allocation alive.
+        let key = (
+            compression_method,
+            photometric_interpretation,
+            *info,
+            Bytes::copy_from_slice(compressed_bytes),
+        );
+        cache.insert(key, decoded.clone());
+    }
+}
+
+/// Returns `true` if every byte of `buf` has the same value.
+pub(crate) fn is_uniform(buf: &[u8]) -> bool {
+    match buf.first() {
+        Some(first) => buf.iter().all(|b| b == first),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uniform_tile_cache() {
+        let cache = UniformTileCache::default();
+        let method = CompressionMethod::Deflate;
+        let pi = PhotometricInterpretation::BlackIsZero;
+        let info = TileInfo::new(16, 16, 8);
+        let payload = Bytes::from_static(&[1, 2, 3]);
+
+        cache.insert(method, pi, &info, &payload, &Bytes::from_static(&[0, 1, 0]));
+        assert_eq!(cache.get(method, pi, &info, &payload), None);
+
+        let zeros = Bytes::from(vec![0; 256]);
+        cache.insert(method, pi, &info, &payload, &zeros);
+        assert_eq!(cache.get(method, pi, &info, &payload), Some(zeros));
+        assert_eq!(cache.get(CompressionMethod::LZW, pi, &info, &payload), None);
+        let wide = TileInfo::new(32, 16, 8);
+        assert_eq!(cache.get(method, pi, &wide, &payload), None);
+    }
+}
@@ -0,0 +1,95 @@
+use bytes::Bytes;
+
+use crate::ifd::ImageFileDirectory;
+use crate::tiff::tags::{CompressionMethod, PhotometricInterpretation, PlanarConfiguration};
+
+/// The YCbCrCoefficients default: the CCIR Recommendation 601-1 luma coefficients.
+pub(crate) const DEFAULT_COEFFICIENTS: [f64; 3] = [0.299, 0.587, 0.114];
+
+/// The ReferenceBlackWhite default for YCbCr images: full-range luma and chroma.
+pub(crate) const DEFAULT_REFERENCE_BLACK_WHITE: [f64; 6] = [0.0, 255.0, 128.0, 255.0, 128.0, 255.0];
+
+/// Conversion of JPEG-compressed YCbCr samples to RGB using an image's own YCbCrCoefficients and
+/// ReferenceBlackWhite.
+///
+/// JPEG decoders convert YCbCr to RGB with the default coefficients and full-range samples, which
+/// shifts the colors of images written with anything else. For those images, the raw YCbCr
+/// samples are decoded instead and converted with this, following section 21 of the TIFF 6.0
+/// specification.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct YCbCrConversion {
+    coefficients: [f64; 3],
+    reference_black_white: [f64; 6],
+}
+
+impl YCbCrConversion {
+    /// The conversion for `ifd`, or `None` if the decoder's default conversion is already correct
+    /// or doesn't apply.
+    pub(crate) fn from_ifd(ifd: &ImageFileDirectory) -> Option<Self> {
+        if ifd.compression != CompressionMethod::ModernJPEG
+            || ifd.photometric_interpretation != PhotometricInterpretation::YCbCr
+            || ifd.samples_per_pixel != 3
+            || ifd.planar_configuration != PlanarConfiguration::Chunky
+            || ifd.bits_per_sample.iter().any(|bits| *bits != 8)
+        {
+            return None;
+        }
+        let conversion = Self {
+            coefficients: ifd.ycbcr_coefficients.unwrap_or(DEFAULT_COEFFICIENTS),
+            reference_black_white: ifd
+                .reference_black_white
+                .unwrap_or(DEFAULT_REFERENCE_BLACK_WHITE),
+        };
+        (!conversion.is_default()).then_some(conversion)
+    }
+
+    fn is_default(&self) -> bool {
+        let close = |a: &[f64], b: &[f64]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6);
+        close(&self.coefficients, &DEFAULT_COEFFICIENTS)
+            && close(&self.reference_black_white, &DEFAULT_REFERENCE_BLACK_WHITE)
+    }
+
+    /// Convert interleaved 8-bit YCbCr samples to RGB.
+    pub(crate) fn convert(&self, data: Bytes) -> Bytes {
+        let [luma_red, luma_green, luma_blue] = self.coefficients;
+        let [y_black, y_white, cb_black, cb_white, cr_black, cr_white] = self.reference_black_white;
+        let to_u8 = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+
+        let mut out = data.to_vec();
+        for pixel in out.chunks_exact_mut(3) {
+            let y = (pixel[0] as f64 - y_black) * 255.0 / (y_white - y_black);
+            let cb = (pixel[1] as f64 - cb_black) * 127.0 / (cb_white - cb_black);
+            let cr = (pixel[2] as f64 - cr_black) * 127.0 / (cr_white - cr_black);
+
+            let red = cr * (2.0 - 2.0 * luma_red) + y;
+            let blue = cb * (2.0 - 2.0 * luma_blue) + y;
+            let green = (y - luma_blue * blue - luma_red * red) / luma_green;
+            pixel.copy_from_slice(&[to_u8(red), to_u8(green), to_u8(blue)]);
+        }
+        out.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ycbcr_conversion() {
+        let rec709 = YCbCrConversion {
+            coefficients: [0.2126, 0.7152, 0.0722],
+            reference_black_white: DEFAULT_REFERENCE_BLACK_WHITE,
+        };
+        assert!(!rec709.is_default());
+        let data = Bytes::from_static(&[128, 128, 128, 100, 128, 200]);
+        assert_eq!(rec709.convert(data).as_ref(), [128, 128, 128, 213, 66, 100]);
+
+        // Studio-range luma and chroma
+        let studio = YCbCrConversion {
+            coefficients: DEFAULT_COEFFICIENTS,
+            reference_black_white: [16.0, 235.0, 128.0, 240.0, 128.0, 240.0],
+        };
+        let data = Bytes::from_static(&[16, 128, 128, 235, 128, 128]);
+        assert_eq!(studio.convert(data).as_ref(), [0, 0, 0, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,375 @@
+//! Encoders for different TIFF compression methods.
+//!
+//! These are the inverse of the [decoders](crate::decoder), so tiles can be decoded, modified and
+//! compressed again, or recompressed with a different method.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::Write;
+
+use bytes::Bytes;
+use flate2::write::ZlibEncoder;
+
+use crate::decoder::TileInfo;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::tiff::tags::{CompressionMethod, PhotometricInterpretation};
+use crate::tiff::{TiffError, TiffUnsupportedError};
+
+/// A registry of encoders.
+///
+/// This mirrors [`DecoderRegistry`](crate::decoder::DecoderRegistry): end users can register
+/// their own encoders, for custom compression methods, or override the default encoder
+/// implementations.
+#[derive(Debug)]
+pub struct EncoderRegistry(HashMap<CompressionMethod, Box<dyn Encoder>>);
+
+impl EncoderRegistry {
+    /// Create a new encoder registry with no encoders registered
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    /// Encode a tile with the encoder registered for `compression_method`.
+    pub fn encode_tile(
+        &self,
+        compression_method: CompressionMethod,
+        buffer: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        let encoder = self
+            .0
+            .get(&compression_method)
+            .ok_or(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedCompressionMethod(compression_method),
+            ))?;
+        encoder.encode_tile(buffer, photometric_interpretation, info)
+    }
+}
+
+impl AsRef<HashMap<CompressionMethod, Box<dyn Encoder>>> for EncoderRegistry {
+    fn as_ref(&self) -> &HashMap<CompressionMethod, Box<dyn Encoder>> {
+        &self.0
+    }
+}
+
+impl AsMut<HashMap<CompressionMethod, Box<dyn Encoder>>> for EncoderRegistry {
+    fn as_mut(&mut self) -> &mut HashMap<CompressionMethod, Box<dyn Encoder>> {
+        &mut self.0
+    }
+}
+
+impl Default for EncoderRegistry {
+    fn default() -> Self {
+        let mut registry = HashMap::with_capacity(6);
+        registry.insert(CompressionMethod::None, Box::new(UncompressedEncoder) as _);
+        registry.insert(CompressionMethod::Deflate, Box::new(DeflateEncoder) as _);
+        registry.insert(CompressionMethod::OldDeflate, Box::new(DeflateEncoder) as _);
+        registry.insert(CompressionMethod::LZW, Box::new(LZWEncoder) as _);
+        #[cfg(feature = "jpeg-encoder")]
+        registry.insert(
+            CompressionMethod::ModernJPEG,
+            Box::new(JPEGEncoder::default()) as _,
+        );
+        #[cfg(feature = "zstd")]
+        registry.insert(
+            CompressionMethod::ZSTD,
+            Box::new(ZstdEncoder::default()) as _,
+        );
+        Self(registry)
+    }
+}
+
+/// A trait to encode a TIFF tile.
+pub trait Encoder: Debug + Send + Sync {
+    /// Encode a TIFF tile described by `info`.
+    ///
+    /// Like the output of [`Decoder::decode_tile`](crate::decoder::Decoder::decode_tile),
+    /// `buffer` holds all rows of the tile, including any padding, in the byte order of the file
+    /// and with any predictor already applied.
+    fn encode_tile(
+        &self,
+        buffer: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes>;
+}
+
+/// An encoder for the Deflate compression method.
+#[derive(Debug, Clone)]
+pub struct DeflateEncoder;
+
+impl Encoder for DeflateEncoder {
+    fn encode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&buffer)?;
+        Ok(encoder.finish()?.into())
+    }
+}
+
+/// An encoder for the JPEG compression method, using the `jpeg-encoder` crate.
+///
+/// Tiles of 8-bit samples are encoded as baseline JPEG images without chroma subsampling:
+/// grayscale for a single sample, and three samples as RGB, or converted from RGB to YCbCr for the
+/// YCbCr photometric interpretation. The images include their quantization and Huffman tables, so
+/// no JPEGTables tag is needed.
+#[cfg(feature = "jpeg-encoder")]
+#[derive(Debug, Clone)]
+pub struct JPEGEncoder {
+    quality: u8,
+}
+
+#[cfg(feature = "jpeg-encoder")]
+impl JPEGEncoder {
+    /// Create an encoder with the quality of the IJG library, from 1 to 100.
+    pub fn new(quality: u8) -> Self {
+        Self {
+            quality: quality.clamp(1, 100),
+        }
+    }
+
+    /// The quality of encoded tiles.
+    pub fn quality(&self) -> u8 {
+        self.quality
+    }
+}
+
+#[cfg(feature = "jpeg-encoder")]
+impl Default for JPEGEncoder {
+    /// An encoder with a quality of 75, the default of libjpeg and GDAL.
+    fn default() -> Self {
+        Self::new(75)
+    }
+}
+
+#[cfg(feature = "jpeg-encoder")]
+impl Encoder for JPEGEncoder {
+    fn encode_tile(
+        &self,
+        buffer: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        use jpeg_encoder::{ColorType, QuantizationTableType, SamplingFactor};
+
+        // RGB components are coded as they are, which the encoder does for YCbCr input
+        let color_type = match (info.bits_per_pixel(), photometric_interpretation) {
+            (8, _) => ColorType::Luma,
+            (24, PhotometricInterpretation::RGB) => ColorType::Ycbcr,
+            (24, PhotometricInterpretation::YCbCr) => ColorType::Rgb,
+            (24, photometric_interpretation) => {
+                return Err(TiffError::UnsupportedError(
+                    TiffUnsupportedError::UnsupportedInterpretation(photometric_interpretation),
+                )
+                .into())
+            }
+            (bits, _) => {
+                return Err(AsyncTiffError::General(format!(
+                    "JPEG encoding requires one or three 8-bit samples per pixel, not {bits} bits"
+                )))
+            }
+        };
+        let (Ok(width), Ok(height)) = (u16::try_from(info.width()), u16::try_from(info.height()))
+        else {
+            return Err(AsyncTiffError::General(format!(
+                "JPEG images can't be larger than 65535x65535 pixels, not {}x{}",
+                info.width(),
+                info.height()
+            )));
+        };
+        let expected = width as usize * height as usize * info.bits_per_pixel() as usize / 8;
+        if width == 0 || height == 0 || buffer.len() < expected {
+            return Err(AsyncTiffError::General(format!(
+                "Expected {expected} bytes for a {width}x{height} JPEG tile, got {}",
+                buffer.len()
+            )));
+        }
+
+        let mut encoded = vec![];
+        let mut encoder = jpeg_encoder::Encoder::new(&mut encoded, self.quality);
+        encoder.set_sampling_factor(SamplingFactor::R_4_4_4);
+        if photometric_interpretation == PhotometricInterpretation::RGB {
+            // Quantize every RGB component as luminance, rather than as chrominance
+            encoder.set_quantization_tables(
+                QuantizationTableType::Default,
+                QuantizationTableType::Custom(Box::new(LUMINANCE_QUANTIZATION)),
+            );
+        }
+        encoder
+            .encode(&buffer[..expected], width, height, color_type)
+            .map_err(|err| AsyncTiffError::General(format!("Failed to encode JPEG data: {err}")))?;
+        Ok(encoded.into())
+    }
+}
+
+/// The luminance quantization table of ITU T.81 Annex K.1, in natural order.
+#[cfg(feature = "jpeg-encoder")]
+const LUMINANCE_QUANTIZATION: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+/// An encoder for the LZW compression method.
+#[derive(Debug, Clone)]
+pub struct LZWEncoder;
+
+impl Encoder for LZWEncoder {
+    fn encode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        let mut encoder = weezl::encode::Encoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8);
+        let encoded = encoder
+            .encode(&buffer)
+            .map_err(|err| AsyncTiffError::General(format!("Failed to encode LZW data: {err}")))?;
+        Ok(encoded.into())
+    }
+}
+
+/// An encoder for uncompressed data.
+#[derive(Debug, Clone)]
+pub struct UncompressedEncoder;
+
+impl Encoder for UncompressedEncoder {
+    fn encode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        Ok(buffer)
+    }
+}
+
+/// An encoder for the ZSTD compression method, using the `zstd` crate.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone)]
+pub struct ZstdEncoder {
+    level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdEncoder {
+    /// Create an encoder with the compression level of libzstd, from 1 to 22.
+    pub fn new(level: i32) -> Self {
+        Self {
+            level: level.clamp(1, 22),
+        }
+    }
+
+    /// The compression level of encoded tiles.
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Default for ZstdEncoder {
+    /// An encoder with a compression level of 9, the default of GDAL.
+    fn default() -> Self {
+        Self::new(9)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Encoder for ZstdEncoder {
+    fn encode_tile(
+        &self,
+        buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        Ok(zstd::bulk::compress(&buffer, self.level)?.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoder::DecoderRegistry;
+
+    #[test]
+    fn test_round_trip() {
+        let encoders = EncoderRegistry::default();
+        let decoders = DecoderRegistry::default();
+        let info = TileInfo::new(16, 8, 8);
+        let data = Bytes::from((0..128).map(|i| (i % 16 * 4) as u8).collect::<Vec<_>>());
+        let photometric = PhotometricInterpretation::BlackIsZero;
+        for method in [
+            CompressionMethod::None,
+            CompressionMethod::Deflate,
+            CompressionMethod::LZW,
+            #[cfg(feature = "zstd")]
+            CompressionMethod::ZSTD,
+        ] {
+            let encoded = encoders
+                .encode_tile(method, data.clone(), photometric, &info)
+                .unwrap();
+            let decoded = decoders.as_ref()[&method]
+                .decode_tile_with_info(encoded, photometric, None, &info)
+                .unwrap();
+            assert_eq!(decoded, data, "{method:?}");
+        }
+
+        assert!(encoders
+            .encode_tile(CompressionMethod::PackBits, data, photometric, &info)
+            .is_err());
+    }
+
+    #[cfg(feature = "jpeg-encoder")]
+    #[test]
+    fn test_jpeg() {
+        let encoders = EncoderRegistry::default();
+        let decoders = DecoderRegistry::default();
+        let info = TileInfo::new(16, 8, 8);
+        let data = Bytes::from((0..128).map(|i| (i % 16 * 4) as u8).collect::<Vec<_>>());
+        let photometric = PhotometricInterpretation::BlackIsZero;
+        let encoded = encoders
+            .encode_tile(
+                CompressionMethod::ModernJPEG,
+                data.clone(),
+                photometric,
+                &info,
+            )
+            .unwrap();
+        let decoded = decoders.as_ref()[&CompressionMethod::ModernJPEG]
+            .decode_tile(encoded, photometric, None)
+            .unwrap();
+        assert_eq!(decoded.len(), data.len());
+        assert!(decoded.iter().zip(&data).all(|(a, b)| a.abs_diff(*b) <= 8));
+
+        let rgb = TileInfo::new(16, 8, 24);
+        let data = Bytes::from(
+            (0..384)
+                .map(|i| [200, 100, 30][i % 3] + (i / 3 % 16) as u8)
+                .collect::<Vec<_>>(),
+        );
+        let photometric = PhotometricInterpretation::RGB;
+        let encoded = encoders
+            .encode_tile(
+                CompressionMethod::ModernJPEG,
+                data.clone(),
+                photometric,
+                &rgb,
+            )
+            .unwrap();
+        let decoded = decoders.as_ref()[&CompressionMethod::ModernJPEG]
+            .decode_tile(encoded, photometric, None)
+            .unwrap();
+        assert_eq!(decoded.len(), data.len());
+        assert!(decoded.iter().zip(&data).all(|(a, b)| a.abs_diff(*b) <= 8));
+
+        let rgb16 = TileInfo::new(16, 8, 48);
+        assert!(encoders
+            .encode_tile(CompressionMethod::ModernJPEG, data, photometric, &rgb16)
+            .is_err());
+    }
+}
@@ -15,6 +15,68 @@ pub enum AsyncTiffError {
     #[error("General error: {0}")]
     General(String),
 
+    /// Decoding was abandoned because its [`CancellationToken`] was cancelled or its deadline
+    /// passed.
+    ///
+    /// [`CancellationToken`]: crate::decoder::CancellationToken
+    #[error("Decoding was cancelled")]
+    Cancelled,
+
+    /// A tile decompressed to fewer bytes than its rows require, e.g. because its compressed
+    /// data is corrupt or truncated.
+    #[error(
+        "Tile ({x}, {y}) decompressed to {actual_bytes} bytes, expected at least {required_bytes}"
+    )]
+    UnexpectedCompressedData {
+        /// The column index of the tile.
+        x: usize,
+        /// The row index of the tile.
+        y: usize,
+        /// The number of decompressed bytes.
+        actual_bytes: usize,
+        /// The number of bytes required by the tile's rows within the image.
+        required_bytes: usize,
+    },
+
+    /// The byte range of a tile extends past the end of the file, e.g. because an upload was
+    /// truncated.
+    #[error("Tile ({x}, {y}) ends at byte {end}, past the end of the {file_size}-byte file")]
+    TruncatedTile {
+        /// The column index of the tile.
+        x: usize,
+        /// The row index of the tile.
+        y: usize,
+        /// The end of the tile's byte range.
+        end: u64,
+        /// The size of the file.
+        file_size: u64,
+    },
+
+    /// Reading or decoding would exceed a configured limit, such as
+    /// [`MetadataLimits`](crate::metadata::MetadataLimits) or
+    /// [`DecodeLimits`](crate::decoder::DecodeLimits).
+    #[error("{what} of {size} exceeds the limit of {limit}")]
+    LimitExceeded {
+        /// What exceeded the limit, e.g. "Tag value size".
+        what: &'static str,
+        /// The requested size in bytes, or count of pixels.
+        size: u64,
+        /// The configured limit.
+        limit: u64,
+    },
+
+    /// The stored bytes of a tile don't match its checksum, e.g. because the file was corrupted
+    /// after it was written.
+    #[error("Tile at byte {offset} has CRC-32 {actual:#010x}, expected {expected:#010x}")]
+    ChecksumMismatch {
+        /// The offset of the tile in the file.
+        offset: u64,
+        /// The checksum recorded when the file was written.
+        expected: u32,
+        /// The checksum of the fetched bytes.
+        actual: u32,
+    },
+
     /// Tile index error
     #[error("Tile index out of bounds: {0}, {1}")]
     TileIndexError(u32, u32),
@@ -46,5 +108,240 @@ pub enum AsyncTiffError {
     External(Box<dyn std::error::Error + Send + Sync>),
 }
 
+impl AsyncTiffError {
+    /// Whether the file is malformed, e.g. an invalid tag, a corrupt tile or data ending early.
+    pub fn is_format_error(&self) -> bool {
+        match self {
+            Self::EndOfFile(..)
+            | Self::UnexpectedCompressedData { .. }
+            | Self::TruncatedTile { .. }
+            | Self::ChecksumMismatch { .. }
+            | Self::JPEGDecodingError(_) => true,
+            Self::InternalTIFFError(err) => matches!(
+                err,
+                crate::tiff::TiffError::FormatError(_) | crate::tiff::TiffError::IntSizeError
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether the file uses a feature that is not supported, such as a compression method
+    /// without a registered decoder.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            Self::InternalTIFFError(crate::tiff::TiffError::UnsupportedError(_))
+        )
+    }
+
+    /// Whether the error came from fetching data rather than from the file's contents.
+    pub fn is_io_error(&self) -> bool {
+        match self {
+            Self::IOError(_) | Self::External(_) => true,
+            Self::InternalTIFFError(err) => matches!(err, crate::tiff::TiffError::IoError(_)),
+            #[cfg(feature = "object_store")]
+            Self::ObjectStore(_) => true,
+            #[cfg(feature = "reqwest")]
+            Self::ReqwestError(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the file was modified since it was opened, as reported by a failed precondition
+    /// on a conditional request.
+    pub fn is_file_changed(&self) -> bool {
+        match self {
+            #[cfg(feature = "object_store")]
+            Self::ObjectStore(err) => matches!(
+                err,
+                object_store::Error::Precondition { .. } | object_store::Error::NotModified { .. }
+            ),
+            #[cfg(feature = "reqwest")]
+            Self::ReqwestError(err) => {
+                err.status() == Some(reqwest::StatusCode::PRECONDITION_FAILED)
+            }
+            _ => false,
+        }
+    }
+    /// Whether the file or object doesn't exist.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            Self::IOError(err) | Self::InternalTIFFError(crate::tiff::TiffError::IoError(err)) => {
+                err.kind() == std::io::ErrorKind::NotFound
+            }
+            #[cfg(feature = "object_store")]
+            Self::ObjectStore(err) => matches!(err, object_store::Error::NotFound { .. }),
+            #[cfg(feature = "reqwest")]
+            Self::ReqwestError(err) => err.status() == Some(reqwest::StatusCode::NOT_FOUND),
+            _ => false,
+        }
+    }
+
+    /// Whether the error is likely transient, such as a timeout, a dropped connection or a
+    /// throttled or failed request to the server, so that the same call may succeed if retried.
+    ///
+    /// Errors from the file's contents are never retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::IOError(err) | Self::InternalTIFFError(crate::tiff::TiffError::IoError(err)) => {
+                is_transient(err)
+            }
+            #[cfg(feature = "object_store")]
+            Self::ObjectStore(object_store::Error::Generic { source, .. }) => {
+                source_is_transient(source.as_ref())
+            }
+            #[cfg(feature = "reqwest")]
+            Self::ReqwestError(err) => is_transient_reqwest(err),
+            Self::External(err) => source_is_transient(err.as_ref()),
+            _ => false,
+        }
+    }
+
+    /// The [`std::io::ErrorKind`] that best describes this error.
+    fn io_error_kind(&self) -> std::io::ErrorKind {
+        use std::io::ErrorKind;
+
+        if self.is_not_found() {
+            ErrorKind::NotFound
+        } else if self.is_unsupported() {
+            ErrorKind::Unsupported
+        } else if matches!(self, Self::EndOfFile(..) | Self::TruncatedTile { .. }) {
+            ErrorKind::UnexpectedEof
+        } else if self.is_format_error() {
+            ErrorKind::InvalidData
+        } else if self.is_retryable() {
+            ErrorKind::TimedOut
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+/// Convert to an I/O error, e.g. to surface errors through [`std::io::Read`] implementations.
+///
+/// I/O errors are unwrapped. Other errors are wrapped with the closest [`std::io::ErrorKind`],
+/// so that [`std::io::Error::kind`] can be matched on instead of the message.
+impl From<AsyncTiffError> for std::io::Error {
+    fn from(err: AsyncTiffError) -> Self {
+        match err {
+            AsyncTiffError::IOError(err)
+            | AsyncTiffError::InternalTIFFError(crate::tiff::TiffError::IoError(err)) => err,
+            err => std::io::Error::new(err.io_error_kind(), err),
+        }
+    }
+}
+
+fn is_transient(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    matches!(
+        err.kind(),
+        ErrorKind::TimedOut
+            | ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+    )
+}
+
+#[cfg(feature = "reqwest")]
+fn is_transient_reqwest(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => {
+            status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        }
+        None => err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+    }
+}
+
+/// Whether `err` or one of its sources is a transient I/O or HTTP error.
+fn source_is_transient(err: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(err) = err.downcast_ref::<std::io::Error>() {
+        // The error wrapped by an I/O error isn't returned as its source
+        if is_transient(err) {
+            return true;
+        }
+        if let Some(inner) = err.get_ref() {
+            return source_is_transient(inner);
+        }
+    }
+    #[cfg(feature = "reqwest")]
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        if is_transient_reqwest(err) {
+            return true;
+        }
+    }
+    err.source().is_some_and(source_is_transient)
+}
+
 /// Crate-specific result type.
 pub type AsyncTiffResult<T> = std::result::Result<T, AsyncTiffError>;
+
+#[cfg(test)]
+mod test {
+    use std::io::{Error, ErrorKind};
+
+    use super::*;
+    use crate::tiff::TiffError;
+
+    #[test]
+    fn test_error_classification() {
+        let err = AsyncTiffError::from(Error::from(ErrorKind::NotFound));
+        assert!(err.is_not_found());
+        assert!(!err.is_retryable());
+        let err = AsyncTiffError::InternalTIFFError(TiffError::IoError(Error::from(
+            ErrorKind::ConnectionReset,
+        )));
+        assert!(err.is_retryable());
+        assert!(!err.is_not_found());
+
+        // Transient errors are found among the sources of external errors
+        let err =
+            AsyncTiffError::External(Box::new(Error::other(Error::from(ErrorKind::TimedOut))));
+        assert!(err.is_retryable());
+        let err = AsyncTiffError::External(Box::new(Error::other("invalid credentials")));
+        assert!(!err.is_retryable());
+
+        let err = AsyncTiffError::ChecksumMismatch {
+            offset: 0,
+            expected: 1,
+            actual: 2,
+        };
+        assert!(!err.is_retryable());
+        assert!(!err.is_not_found());
+    }
+
+    #[cfg(feature = "object_store")]
+    #[test]
+    fn test_object_store_classification() {
+        let err = AsyncTiffError::from(object_store::Error::NotFound {
+            path: "image.tif".to_string(),
+            source: "missing".into(),
+        });
+        assert!(err.is_not_found());
+        let err = AsyncTiffError::from(object_store::Error::Generic {
+            store: "test",
+            source: Box::new(Error::from(ErrorKind::ConnectionAborted)),
+        });
+        assert!(err.is_retryable());
+        assert_eq!(Error::from(err).kind(), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_into_io_error() {
+        let err = Error::from(AsyncTiffError::from(Error::from(ErrorKind::BrokenPipe)));
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        assert!(err.get_ref().is_none());
+
+        let err = Error::from(AsyncTiffError::EndOfFile(10, 4));
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert!(err
+            .get_ref()
+            .is_some_and(|inner| inner.downcast_ref::<AsyncTiffError>().is_some()));
+        let err = Error::from(AsyncTiffError::TileIndexError(3, 4));
+        assert_eq!(err.kind(), ErrorKind::Other);
+    }
+}
@@ -0,0 +1,193 @@
+//! Estimates of the I/O and memory a read takes, before fetching any data.
+
+use std::ops::Range;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::ifd::ImageFileDirectory;
+use crate::window::{sequential_requests, ReadWindowOptions, Window};
+
+/// The expected cost of a read, as returned by [`ImageFileDirectory::estimate_read`] and
+/// [`ImageFileDirectory::estimate_tiles_read`].
+///
+/// This is computed from the tile offsets and byte counts alone, so services can enforce quotas
+/// or pick a coarser overview before issuing any I/O.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadEstimate {
+    chunk_count: usize,
+    request_count: usize,
+    compressed_bytes: u64,
+    decoded_bytes: u64,
+}
+
+impl ReadEstimate {
+    /// The number of tiles fetched and decoded, counting the tiles of each sample plane of a
+    /// planar image separately.
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_count
+    }
+
+    /// The number of byte ranges requested from the reader.
+    ///
+    /// Readers that merge nearby ranges, like an
+    /// [`ObjectReader`](crate::reader::ObjectReader) with a coalesce gap, may issue fewer
+    /// requests.
+    pub fn request_count(&self) -> usize {
+        self.request_count
+    }
+
+    /// The number of compressed bytes requested, including the gaps between tiles that are
+    /// fetched with a single request.
+    pub fn compressed_bytes(&self) -> u64 {
+        self.compressed_bytes
+    }
+
+    /// The number of bytes of the decoded tiles, including the padding of edge tiles.
+    pub fn decoded_bytes(&self) -> u64 {
+        self.decoded_bytes
+    }
+}
+
+impl std::iter::Sum for ReadEstimate {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, estimate| Self {
+            chunk_count: total.chunk_count + estimate.chunk_count,
+            request_count: total.request_count + estimate.request_count,
+            compressed_bytes: total.compressed_bytes + estimate.compressed_bytes,
+            decoded_bytes: total.decoded_bytes + estimate.decoded_bytes,
+        })
+    }
+}
+
+impl ImageFileDirectory {
+    /// Estimate the cost of reading `window` with
+    /// [`read_window_with_options`](Self::read_window_with_options).
+    pub fn estimate_read(
+        &self,
+        window: Window,
+        options: &ReadWindowOptions,
+    ) -> AsyncTiffResult<ReadEstimate> {
+        self.check_window_bounds(window)?;
+        let (_, byte_ranges) = self.window_chunks(window)?;
+        let requests = options
+            .sequential_reads()
+            .and_then(|max| sequential_requests(&byte_ranges, max));
+        Ok(self.read_estimate(&byte_ranges, requests.as_deref()))
+    }
+
+    /// Estimate the cost of fetching the tiles located at `x` column and `y` row with
+    /// [`fetch_tiles`](Self::fetch_tiles) and decoding them.
+    pub fn estimate_tiles_read(&self, x: &[usize], y: &[usize]) -> AsyncTiffResult<ReadEstimate> {
+        assert_eq!(x.len(), y.len(), "x and y should have same len");
+        if self.tile_count().is_none() {
+            return Err(AsyncTiffError::General("Not a tiled TIFF".to_string()));
+        }
+        let byte_ranges = x
+            .iter()
+            .zip(y)
+            .map(|(&x, &y)| {
+                self.chunk_index(x, y, 0)
+                    .and_then(|index| self.chunk_byte_range(index))
+                    .ok_or(AsyncTiffError::TileIndexError(x as u32, y as u32))
+            })
+            .collect::<AsyncTiffResult<Vec<_>>>()?;
+        Ok(self.read_estimate(&byte_ranges, None))
+    }
+
+    /// The estimate for fetching the chunks at `byte_ranges`, with `requests` if they are merged
+    /// into fewer requests.
+    fn read_estimate(
+        &self,
+        byte_ranges: &[Range<u64>],
+        requests: Option<&[Range<u64>]>,
+    ) -> ReadEstimate {
+        let requests = requests.unwrap_or(byte_ranges);
+        let chunk_height = self.tile_height().unwrap_or(self.image_height()) as u64;
+        let chunk_bytes = self.chunk_row_bytes() as u64 * chunk_height;
+        ReadEstimate {
+            chunk_count: byte_ranges.len(),
+            request_count: requests.len(),
+            compressed_bytes: requests.iter().map(|range| range.end - range.start).sum(),
+            decoded_bytes: chunk_bytes * byte_ranges.len() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::decoder::DecoderRegistry;
+    use crate::test_util::{read_ifds, write_cog, CountingReader};
+    use crate::tiff::tags::{CompressionMethod, SampleFormat};
+    use crate::{CogImage, IfdBuilder};
+
+    /// A 100x70 image of 32x32 tiles with 3 bands.
+    async fn test_file() -> (CountingReader, ImageFileDirectory) {
+        let ifd = IfdBuilder::new(100, 70)
+            .with_data_type(SampleFormat::Uint, 8)
+            .with_samples_per_pixel(3)
+            .with_tiling(32, 32)
+            .with_compression(CompressionMethod::None)
+            .build()
+            .unwrap();
+        let tiles = vec![Bytes::from(vec![7; 32 * 32 * 3]); 12];
+        let file = write_cog(vec![CogImage::from_encoded(ifd, tiles)]).await;
+        let reader = CountingReader::new(file);
+        let ifd = read_ifds(&reader).await.remove(0);
+        reader.take_counts();
+        (reader, ifd)
+    }
+
+    #[tokio::test]
+    async fn test_estimate_read() {
+        let (reader, ifd) = test_file().await;
+        let registry = DecoderRegistry::default();
+        let tile_bytes = 32 * 32 * 3;
+
+        // Intersects the first two tiles of the first two tile rows
+        let window = Window::new(10, 20, 50, 30);
+        let estimate = ifd.estimate_read(window, &Default::default()).unwrap();
+        assert_eq!(estimate.chunk_count(), 4);
+        assert_eq!(estimate.decoded_bytes(), 4 * tile_bytes);
+        ifd.read_window(window, &reader, &registry).await.unwrap();
+        let counts = (estimate.request_count(), estimate.compressed_bytes());
+        assert_eq!(counts, (4, 4 * tile_bytes));
+        assert_eq!(reader.take_counts(), counts);
+
+        // Sequential reads merge the two tile rows, including the two tiles between them
+        let options = ReadWindowOptions::new().with_sequential_reads(u64::MAX);
+        let estimate = ifd.estimate_read(window, &options).unwrap();
+        ifd.read_window_with_options(window, &options, &reader, &registry)
+            .await
+            .unwrap();
+        let counts = (estimate.request_count(), estimate.compressed_bytes());
+        assert_eq!(counts, (1, 6 * tile_bytes));
+        assert_eq!(reader.take_counts(), counts);
+
+        let empty = ifd
+            .estimate_read(Window::new(0, 0, 0, 0), &Default::default())
+            .unwrap();
+        assert_eq!(empty, ReadEstimate::default());
+        assert!(ifd
+            .estimate_read(Window::new(90, 0, 20, 10), &Default::default())
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_tiles_read() {
+        let (reader, ifd) = test_file().await;
+        let estimate = ifd.estimate_tiles_read(&[0, 3], &[2, 2]).unwrap();
+        assert_eq!(estimate.chunk_count(), 2);
+        assert_eq!(estimate.decoded_bytes(), 2 * 32 * 32 * 3);
+
+        ifd.fetch_tiles(&[0, 3], &[2, 2], &reader).await.unwrap();
+        let counts = (estimate.request_count(), estimate.compressed_bytes());
+        assert_eq!(reader.take_counts(), counts);
+
+        assert!(matches!(
+            ifd.estimate_tiles_read(&[4], &[0]),
+            Err(AsyncTiffError::TileIndexError(4, 0))
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+//! Plugins that parse tags without a dedicated field on [`ImageFileDirectory`].
+//!
+//! [`ImageFileDirectory`]: crate::ImageFileDirectory
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::tiff::tags::Tag;
+use crate::tiff::Value;
+
+/// A plugin that parses a set of tags into its own typed representation, e.g. EXIF or
+/// vendor-specific metadata.
+///
+/// Register a plugin with an [`ExtraTagsRegistry`]. Each IFD read with the registry gets its own
+/// clone of the plugin, which is passed the values of its [`tags`](Self::tags) found in that
+/// IFD. These tags are then not included in
+/// [`other_tags`](crate::ImageFileDirectory::other_tags), and the processed plugin is available
+/// from [`ImageFileDirectory::extra`](crate::ImageFileDirectory::extra).
+///
+/// ```
+/// # use async_tiff::error::AsyncTiffResult;
+/// # use async_tiff::extra_tags::{ExtraTags, ExtraTagsRegistry};
+/// # use async_tiff::tags::Tag;
+/// # use async_tiff::tags::Value;
+/// #[derive(Debug, Clone, Default)]
+/// struct GdalMetadata {
+///     xml: Option<String>,
+/// }
+///
+/// impl ExtraTags for GdalMetadata {
+///     fn tags(&self) -> &'static [Tag] {
+///         &[Tag::GdalMetadata]
+///     }
+///
+///     fn process_tag(&mut self, _tag: Tag, value: Value) -> AsyncTiffResult<()> {
+///         self.xml = Some(value.into_string()?);
+///         Ok(())
+///     }
+/// }
+///
+/// let mut registry = ExtraTagsRegistry::new();
+/// registry.register(GdalMetadata::default()).unwrap();
+/// assert_eq!(registry.len(), 1);
+/// assert!(registry.get::<GdalMetadata>().is_some());
+/// ```
+pub trait ExtraTags: ExtraTagsBlankets + Any + Debug + Send + Sync {
+    /// The tags processed by this plugin.
+    fn tags(&self) -> &'static [Tag];
+
+    /// Process the value of one of [`tags`](Self::tags) read from an IFD.
+    fn process_tag(&mut self, tag: Tag, value: Value) -> AsyncTiffResult<()>;
+}
+
+/// Object-safe helpers implemented for every [`ExtraTags`] plugin that is [`Clone`].
+pub trait ExtraTagsBlankets {
+    /// Clone this plugin into a new box.
+    fn clone_box(&self) -> Box<dyn ExtraTags>;
+
+    /// Access this plugin as [`Any`], to downcast it to its concrete type.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: ExtraTags + Clone> ExtraTagsBlankets for T {
+    fn clone_box(&self) -> Box<dyn ExtraTags> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn ExtraTags> {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_box()
+    }
+}
+
+/// A set of [`ExtraTags`] plugins, each handling distinct tags.
+#[derive(Debug, Clone, Default)]
+pub struct ExtraTagsRegistry {
+    plugins: Vec<Box<dyn ExtraTags>>,
+    /// The index into `plugins` of the plugin handling each tag.
+    by_tag: HashMap<Tag, usize>,
+}
+
+impl ExtraTagsRegistry {
+    /// Create a new registry with no plugins.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `plugin` for its tags.
+    ///
+    /// Returns an error if one of its tags is already handled by another plugin.
+    pub fn register(&mut self, plugin: impl ExtraTags) -> AsyncTiffResult<()> {
+        if let Some(tag) = plugin.tags().iter().find(|t| self.by_tag.contains_key(t)) {
+            return Err(AsyncTiffError::General(format!(
+                "Tag {tag:?} is already registered"
+            )));
+        }
+        let index = self.plugins.len();
+        self.by_tag
+            .extend(plugin.tags().iter().map(|tag| (*tag, index)));
+        self.plugins.push(Box::new(plugin));
+        Ok(())
+    }
+
+    /// Remove the plugin handling `tag`, along with all of its other tags.
+    pub fn remove(&mut self, tag: Tag) -> Option<Box<dyn ExtraTags>> {
+        let index = *self.by_tag.get(&tag)?;
+        let plugin = self.plugins.remove(index);
+        self.by_tag = self
+            .plugins
+            .iter()
+            .enumerate()
+            .flat_map(|(index, plugin)| plugin.tags().iter().map(move |tag| (*tag, index)))
+            .collect();
+        Some(plugin)
+    }
+
+    /// The registered plugin of type `T`, if any.
+    pub fn get<T: ExtraTags>(&self) -> Option<&T> {
+        self.plugins
+            .iter()
+            .find_map(|plugin| plugin.as_any().downcast_ref::<T>())
+    }
+
+    /// The plugin handling `tag`, if any.
+    pub fn get_by_tag(&self, tag: Tag) -> Option<&dyn ExtraTags> {
+        let index = *self.by_tag.get(&tag)?;
+        Some(self.plugins[index].as_ref())
+    }
+
+    /// Iterate over the registered plugins in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn ExtraTags> {
+        self.plugins.iter().map(|plugin| plugin.as_ref())
+    }
+
+    /// The number of registered plugins.
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    /// Returns `true` if no plugins are registered.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Pass `value` to the plugin handling `tag`.
+    ///
+    /// Returns the value back if no plugin handles `tag`.
+    pub(crate) fn process_tag(&mut self, tag: Tag, value: Value) -> AsyncTiffResult<Option<Value>> {
+        match self.by_tag.get(&tag) {
+            Some(index) => {
+                self.plugins[*index].process_tag(tag, value)?;
+                Ok(None)
+            }
+            None => Ok(Some(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, Default)]
+    struct Copyright(Vec<String>);
+
+    impl ExtraTags for Copyright {
+        fn tags(&self) -> &'static [Tag] {
+            &[Tag::Copyright, Tag::Artist]
+        }
+
+        fn process_tag(&mut self, _tag: Tag, value: Value) -> AsyncTiffResult<()> {
+            self.0.push(value.into_string()?);
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Software;
+
+    impl ExtraTags for Software {
+        fn tags(&self) -> &'static [Tag] {
+            &[Tag::Software]
+        }
+
+        fn process_tag(&mut self, _tag: Tag, _value: Value) -> AsyncTiffResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = ExtraTagsRegistry::new();
+        registry.register(Copyright::default()).unwrap();
+        registry.register(Software).unwrap();
+        assert!(registry.register(Software).is_err());
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.iter().map(|p| p.tags().len()).collect::<Vec<_>>(),
+            [2, 1]
+        );
+
+        let value = Value::Ascii("me".to_string());
+        assert!(registry.process_tag(Tag::Artist, value).unwrap().is_none());
+        assert_eq!(registry.get::<Copyright>().unwrap().0, ["me"]);
+        let value = Value::Short(1);
+        assert!(registry
+            .process_tag(Tag::Orientation, value)
+            .unwrap()
+            .is_some());
+
+        assert!(registry.remove(Tag::Artist).is_some());
+        assert!(registry.get_by_tag(Tag::Copyright).is_none());
+        assert!(registry.get_by_tag(Tag::Software).is_some());
+        assert!(registry.get::<Copyright>().is_none());
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_from_tags_with_extra_tags() {
+        let mut registry = ExtraTagsRegistry::new();
+        registry.register(Copyright::default()).unwrap();
+        let tags = HashMap::from([
+            (Tag::ImageWidth, Value::Unsigned(16)),
+            (Tag::ImageLength, Value::Unsigned(16)),
+            (Tag::PhotometricInterpretation, Value::Short(1)),
+            (Tag::Artist, Value::Ascii("me".to_string())),
+            (Tag::Unknown(65000), Value::Short(1)),
+        ]);
+        let ifd = crate::ImageFileDirectory::from_tags_with_extra_tags(
+            tags,
+            &registry,
+            crate::reader::Endianness::LittleEndian,
+        )
+        .unwrap();
+        assert_eq!(ifd.artist(), None);
+        assert!(ifd.other_tags().contains_key(&Tag::Unknown(65000)));
+        assert_eq!(ifd.extra::<Copyright>().unwrap().0, ["me"]);
+        assert!(ifd.extra::<Software>().is_none());
+        // The registry's own plugin is left untouched
+        assert!(registry.get::<Copyright>().unwrap().0.is_empty());
+    }
+}
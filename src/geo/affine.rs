@@ -1,7 +1,7 @@
 use crate::ImageFileDirectory;
 
 /// Affine transformation values.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AffineTransform(f64, f64, f64, f64, f64, f64);
 
 impl AffineTransform {
@@ -40,6 +40,34 @@ impl AffineTransform {
         self.5
     }
 
+    /// Apply this transform to the point `(x, y)`.
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.0 * x + self.1 * y + self.2,
+            self.3 * x + self.4 * y + self.5,
+        )
+    }
+
+    /// The inverse of this transform, or `None` if it is degenerate.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.0 * self.4 - self.1 * self.3;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let a = self.4 / det;
+        let b = -self.1 / det;
+        let d = -self.3 / det;
+        let e = self.0 / det;
+        Some(Self::new(
+            a,
+            b,
+            -(a * self.2 + b * self.5),
+            d,
+            e,
+            -(d * self.2 + e * self.5),
+        ))
+    }
+
     /// Construct a new Affine Transform from the IFD
     pub fn from_ifd(ifd: &ImageFileDirectory) -> Option<Self> {
         if let (Some(model_pixel_scale), Some(model_tiepoint)) =
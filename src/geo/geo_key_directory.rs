@@ -2,9 +2,11 @@
 #![allow(missing_docs)]
 
 use std::collections::HashMap;
+use std::fmt;
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+use crate::tiff::tags::Tag;
 use crate::tiff::Value;
 use crate::tiff::{TiffError, TiffResult};
 
@@ -69,7 +71,7 @@ pub(crate) enum GeoKeyTag {
 /// Metadata defined by the GeoTIFF standard.
 ///
 /// <http://docs.opengeospatial.org/is/19-008r4/19-008r4.html#_requirements_class_geokeydirectorytag>
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct GeoKeyDirectory {
     pub model_type: Option<u16>,
     pub raster_type: Option<u16>,
@@ -298,6 +300,96 @@ impl GeoKeyDirectory {
         })
     }
 
+    /// Encode this directory as the `GeoKeyDirectoryTag`, `GeoDoubleParamsTag` and
+    /// `GeoAsciiParamsTag` TIFF tags.
+    pub(crate) fn to_tags(&self) -> HashMap<Tag, Value> {
+        let mut writer = GeoKeyWriter::default();
+
+        // Keys must be written in ascending order of their id.
+        writer.short(GeoKeyTag::ModelType, self.model_type);
+        writer.short(GeoKeyTag::RasterType, self.raster_type);
+        writer.ascii(GeoKeyTag::Citation, self.citation.as_deref());
+
+        writer.short(GeoKeyTag::GeographicType, self.geographic_type);
+        writer.ascii(GeoKeyTag::GeogCitation, self.geog_citation.as_deref());
+        writer.short(GeoKeyTag::GeogGeodeticDatum, self.geog_geodetic_datum);
+        writer.short(GeoKeyTag::GeogPrimeMeridian, self.geog_prime_meridian);
+        writer.short(GeoKeyTag::GeogLinearUnits, self.geog_linear_units);
+        writer.double(GeoKeyTag::GeogLinearUnitSize, self.geog_linear_unit_size);
+        writer.short(GeoKeyTag::GeogAngularUnits, self.geog_angular_units);
+        writer.double(GeoKeyTag::GeogAngularUnitSize, self.geog_angular_unit_size);
+        writer.short(GeoKeyTag::GeogEllipsoid, self.geog_ellipsoid);
+        writer.double(GeoKeyTag::GeogSemiMajorAxis, self.geog_semi_major_axis);
+        writer.double(GeoKeyTag::GeogSemiMinorAxis, self.geog_semi_minor_axis);
+        writer.double(GeoKeyTag::GeogInvFlattening, self.geog_inv_flattening);
+        writer.short(GeoKeyTag::GeogAzimuthUnits, self.geog_azimuth_units);
+        writer.double(
+            GeoKeyTag::GeogPrimeMeridianLong,
+            self.geog_prime_meridian_long,
+        );
+
+        writer.short(GeoKeyTag::ProjectedType, self.projected_type);
+        writer.ascii(GeoKeyTag::ProjCitation, self.proj_citation.as_deref());
+        writer.short(GeoKeyTag::Projection, self.projection);
+        writer.short(GeoKeyTag::ProjCoordTrans, self.proj_coord_trans);
+        writer.short(GeoKeyTag::ProjLinearUnits, self.proj_linear_units);
+        writer.double(GeoKeyTag::ProjLinearUnitSize, self.proj_linear_unit_size);
+        writer.double(GeoKeyTag::ProjStdParallel1, self.proj_std_parallel1);
+        writer.double(GeoKeyTag::ProjStdParallel2, self.proj_std_parallel2);
+        writer.double(GeoKeyTag::ProjNatOriginLong, self.proj_nat_origin_long);
+        writer.double(GeoKeyTag::ProjNatOriginLat, self.proj_nat_origin_lat);
+        writer.double(GeoKeyTag::ProjFalseEasting, self.proj_false_easting);
+        writer.double(GeoKeyTag::ProjFalseNorthing, self.proj_false_northing);
+        writer.double(GeoKeyTag::ProjFalseOriginLong, self.proj_false_origin_long);
+        writer.double(GeoKeyTag::ProjFalseOriginLat, self.proj_false_origin_lat);
+        writer.double(
+            GeoKeyTag::ProjFalseOriginEasting,
+            self.proj_false_origin_easting,
+        );
+        writer.double(
+            GeoKeyTag::ProjFalseOriginNorthing,
+            self.proj_false_origin_northing,
+        );
+        writer.double(GeoKeyTag::ProjCenterLong, self.proj_center_long);
+        writer.double(GeoKeyTag::ProjCenterLat, self.proj_center_lat);
+        writer.double(GeoKeyTag::ProjCenterEasting, self.proj_center_easting);
+        writer.double(GeoKeyTag::ProjCenterNorthing, self.proj_center_northing);
+        writer.double(
+            GeoKeyTag::ProjScaleAtNatOrigin,
+            self.proj_scale_at_nat_origin,
+        );
+        writer.double(GeoKeyTag::ProjScaleAtCenter, self.proj_scale_at_center);
+        writer.double(GeoKeyTag::ProjAzimuthAngle, self.proj_azimuth_angle);
+        writer.double(
+            GeoKeyTag::ProjStraightVertPoleLong,
+            self.proj_straight_vert_pole_long,
+        );
+
+        writer.short(GeoKeyTag::Vertical, self.vertical);
+        writer.ascii(
+            GeoKeyTag::VerticalCitation,
+            self.vertical_citation.as_deref(),
+        );
+        writer.short(GeoKeyTag::VerticalDatum, self.vertical_datum);
+        writer.short(GeoKeyTag::VerticalUnits, self.vertical_units);
+
+        writer.finish()
+    }
+
+    /// The heap memory held by the citation strings, in bytes.
+    pub(crate) fn heap_size(&self) -> usize {
+        [
+            &self.citation,
+            &self.geog_citation,
+            &self.proj_citation,
+            &self.vertical_citation,
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::capacity)
+        .sum()
+    }
+
     /// Return the EPSG code representing the crs of the image
     ///
     /// This will return either [`GeoKeyDirectory::projected_type`] or
@@ -310,3 +402,91 @@ impl GeoKeyDirectory {
         }
     }
 }
+
+/// A one-line summary of the CRS, e.g. `EPSG:32612 "WGS 84 / UTM zone 12N", projected,
+/// PixelIsArea`.
+impl fmt::Display for GeoKeyDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.epsg_code() {
+            Some(epsg) => write!(f, "EPSG:{epsg}")?,
+            None => write!(f, "user-defined CRS")?,
+        }
+        if let Some(citation) = self
+            .citation
+            .as_ref()
+            .or(self.proj_citation.as_ref())
+            .or(self.geog_citation.as_ref())
+        {
+            write!(f, " {citation:?}")?;
+        }
+        match self.model_type {
+            Some(1) => write!(f, ", projected")?,
+            Some(2) => write!(f, ", geographic")?,
+            Some(3) => write!(f, ", geocentric")?,
+            _ => {}
+        }
+        match self.raster_type {
+            Some(1) => write!(f, ", PixelIsArea")?,
+            Some(2) => write!(f, ", PixelIsPoint")?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates GeoKey entries and the parameter values they point to.
+#[derive(Default)]
+struct GeoKeyWriter {
+    keys: Vec<[u16; 4]>,
+    double_params: Vec<f64>,
+    ascii_params: String,
+}
+
+impl GeoKeyWriter {
+    fn short(&mut self, tag: GeoKeyTag, value: Option<u16>) {
+        if let Some(value) = value {
+            self.keys.push([tag.into(), 0, 1, value]);
+        }
+    }
+
+    fn double(&mut self, tag: GeoKeyTag, value: Option<f64>) {
+        if let Some(value) = value {
+            let location = Tag::GeoDoubleParamsTag.to_u16();
+            let offset = self.double_params.len() as u16;
+            self.keys.push([tag.into(), location, 1, offset]);
+            self.double_params.push(value);
+        }
+    }
+
+    fn ascii(&mut self, tag: GeoKeyTag, value: Option<&str>) {
+        if let Some(value) = value {
+            // Each string is terminated by a `|`, which is included in its count.
+            let location = Tag::GeoAsciiParamsTag.to_u16();
+            let offset = self.ascii_params.len() as u16;
+            let count = value.len() as u16 + 1;
+            self.keys.push([tag.into(), location, count, offset]);
+            self.ascii_params.push_str(value);
+            self.ascii_params.push('|');
+        }
+    }
+
+    fn finish(self) -> HashMap<Tag, Value> {
+        let mut directory = vec![1, 1, 0, self.keys.len() as u16];
+        directory.extend(self.keys.into_iter().flatten());
+
+        let mut tags = HashMap::from([(
+            Tag::GeoKeyDirectoryTag,
+            Value::List(directory.into_iter().map(Value::Short).collect()),
+        )]);
+        if !self.double_params.is_empty() {
+            tags.insert(
+                Tag::GeoDoubleParamsTag,
+                Value::List(self.double_params.into_iter().map(Value::Double).collect()),
+            );
+        }
+        if !self.ascii_params.is_empty() {
+            tags.insert(Tag::GeoAsciiParamsTag, Value::Ascii(self.ascii_params));
+        }
+        tags
+    }
+}
@@ -3,7 +3,9 @@
 mod affine;
 mod geo_key_directory;
 mod partial_reads;
+mod raw_geo_keys;
 
 pub use affine::AffineTransform;
 pub use geo_key_directory::GeoKeyDirectory;
 pub(crate) use geo_key_directory::GeoKeyTag;
+pub use raw_geo_keys::{GeoKeyEntry, RawGeoKeys};
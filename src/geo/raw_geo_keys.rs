@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use crate::tiff::tags::Tag;
+use crate::tiff::Value;
+
+/// A GeoKey as stored in the GeoKeyDirectoryTag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeoKeyEntry {
+    /// The id of the key.
+    pub key_id: u16,
+    /// The tag holding the value, or 0 if the value is stored in the entry itself.
+    pub tag_location: u16,
+    /// The number of values.
+    pub count: u16,
+    /// The value itself if `tag_location` is 0, and otherwise the index of the first value in
+    /// the tag at `tag_location`.
+    pub value_offset: u16,
+}
+
+/// The GeoKeys of an image as stored in its GeoKeyDirectoryTag, GeoDoubleParamsTag and
+/// GeoAsciiParamsTag.
+///
+/// Unlike [`GeoKeyDirectory`](super::GeoKeyDirectory), this holds every key, including those the
+/// typed directory doesn't model, and is written back exactly as it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawGeoKeys {
+    version: [u16; 3],
+    entries: Vec<GeoKeyEntry>,
+    double_params: Option<Vec<f64>>,
+    ascii_params: Option<String>,
+}
+
+impl RawGeoKeys {
+    /// Create the keys of a directory of the given `version`, with the values of its
+    /// GeoDoubleParamsTag and GeoAsciiParamsTag.
+    pub fn new(
+        version: [u16; 3],
+        entries: Vec<GeoKeyEntry>,
+        double_params: Option<Vec<f64>>,
+        ascii_params: Option<String>,
+    ) -> Self {
+        Self {
+            version,
+            entries,
+            double_params,
+            ascii_params,
+        }
+    }
+
+    /// The KeyDirectoryVersion, KeyRevision and MinorRevision of the directory.
+    pub fn version(&self) -> [u16; 3] {
+        self.version
+    }
+
+    /// The entries of the directory, in the order they are stored.
+    pub fn entries(&self) -> &[GeoKeyEntry] {
+        &self.entries
+    }
+
+    /// The entry of the key `key_id`, if present.
+    pub fn entry(&self, key_id: u16) -> Option<&GeoKeyEntry> {
+        self.entries.iter().find(|entry| entry.key_id == key_id)
+    }
+
+    /// The values of the GeoDoubleParamsTag, if present.
+    pub fn double_params(&self) -> Option<&[f64]> {
+        self.double_params.as_deref()
+    }
+
+    /// The value of the GeoAsciiParamsTag, if present.
+    pub fn ascii_params(&self) -> Option<&str> {
+        self.ascii_params.as_deref()
+    }
+
+    /// The value of `entry`: a short if it's stored in the entry itself, a string from the
+    /// GeoAsciiParamsTag without its `|` terminator, or a double or list of doubles from the
+    /// GeoDoubleParamsTag.
+    ///
+    /// Returns `None` if the value is stored in another tag or lies outside its tag.
+    pub fn value(&self, entry: &GeoKeyEntry) -> Option<Value> {
+        self.try_value(entry).ok().flatten()
+    }
+
+    /// The value of `entry`, as [`value`](Self::value), or a description of why it lies outside
+    /// its tag.
+    pub(crate) fn try_value(&self, entry: &GeoKeyEntry) -> Result<Option<Value>, String> {
+        let range = entry.value_offset as usize..entry.value_offset as usize + entry.count as usize;
+        if entry.tag_location == 0 {
+            return Ok(Some(Value::Short(entry.value_offset)));
+        }
+        match Tag::from_u16_exhaustive(entry.tag_location) {
+            Tag::GeoAsciiParamsTag => {
+                let ascii_params = self
+                    .ascii_params()
+                    .ok_or("GeoAsciiParamsTag is missing".to_string())?;
+                let mut s = ascii_params.get(range.clone()).ok_or(format!(
+                    "Characters {range:?} are out of bounds of the {} characters of \
+                     GeoAsciiParamsTag",
+                    ascii_params.len()
+                ))?;
+
+                // It seems that this string subslice might always include the final |
+                // character?
+                if s.ends_with('|') {
+                    s = &s[0..s.len() - 1];
+                }
+                Ok(Some(Value::Ascii(s.to_string())))
+            }
+            Tag::GeoDoubleParamsTag => {
+                let double_params = self
+                    .double_params()
+                    .ok_or("GeoDoubleParamsTag is missing".to_string())?;
+                let values = double_params.get(range.clone()).ok_or(format!(
+                    "Values {range:?} are out of bounds of the {} values of GeoDoubleParamsTag",
+                    double_params.len()
+                ))?;
+                Ok(Some(match values {
+                    [value] => Value::Double(*value),
+                    values => Value::List(values.iter().copied().map(Value::Double).collect()),
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Encode these keys as the `GeoKeyDirectoryTag`, `GeoDoubleParamsTag` and
+    /// `GeoAsciiParamsTag` TIFF tags they were read from.
+    pub(crate) fn to_tags(&self) -> HashMap<Tag, Value> {
+        let mut directory = self.version.to_vec();
+        directory.push(self.entries.len() as u16);
+        for entry in &self.entries {
+            directory.extend([
+                entry.key_id,
+                entry.tag_location,
+                entry.count,
+                entry.value_offset,
+            ]);
+        }
+
+        let mut tags = HashMap::from([(
+            Tag::GeoKeyDirectoryTag,
+            Value::List(directory.into_iter().map(Value::Short).collect()),
+        )]);
+        if let Some(double_params) = &self.double_params {
+            tags.insert(
+                Tag::GeoDoubleParamsTag,
+                Value::List(double_params.iter().copied().map(Value::Double).collect()),
+            );
+        }
+        if let Some(ascii_params) = &self.ascii_params {
+            tags.insert(Tag::GeoAsciiParamsTag, Value::Ascii(ascii_params.clone()));
+        }
+        tags
+    }
+
+    pub(crate) fn heap_size(&self) -> usize {
+        std::mem::size_of_val(self.entries.as_slice())
+            + self.double_params.as_ref().map_or(0, |d| d.len() * 8)
+            + self.ascii_params.as_ref().map_or(0, String::len)
+    }
+}
@@ -0,0 +1,271 @@
+//! Compare decoding a file with this crate against the upstream [`tiff`] crate.
+//!
+//! The TIFF parsing in this crate was vendored from `tiff` and has since diverged from it, so
+//! decoding both ways and diffing the output is a quick way to tell whether a problem file
+//! exposes a bug in either decoder.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use ::tiff::decoder::{Decoder, DecodingResult, Limits};
+use bytes::Bytes;
+
+use crate::decoder::DecoderRegistry;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::metadata::TiffMetadataReader;
+use crate::reader::{AsyncFileReader, MemoryReader};
+use crate::tiff::tags::PlanarConfiguration;
+use crate::{ImageFileDirectory, Window};
+
+/// How the output of the two decoders compares for one IFD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GoldenOutcome {
+    /// Both decoders produced the same samples.
+    Match,
+    /// Both decoders succeeded, but produced different samples.
+    Mismatch {
+        /// The number of bytes decoded by this crate.
+        len: usize,
+        /// The number of bytes decoded by the `tiff` crate.
+        upstream_len: usize,
+        /// The first byte that differs, or the length of the shorter output if it is a prefix of
+        /// the longer one.
+        first_difference: usize,
+        /// The number of differing bytes within the length of the shorter output.
+        differing_bytes: usize,
+    },
+    /// Only this crate failed to decode the image.
+    Failed(String),
+    /// Only the `tiff` crate failed to decode the image.
+    UpstreamFailed(String),
+    /// Both decoders failed.
+    BothFailed {
+        /// The error of this crate.
+        error: String,
+        /// The error of the `tiff` crate.
+        upstream_error: String,
+    },
+}
+
+/// The result of [`compare_with_upstream`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoldenReport {
+    ifds: Vec<GoldenOutcome>,
+}
+
+impl GoldenReport {
+    /// The outcome for each IFD, in file order.
+    pub fn ifds(&self) -> &[GoldenOutcome] {
+        &self.ifds
+    }
+
+    /// Whether both decoders produced the same samples for every IFD.
+    pub fn is_match(&self) -> bool {
+        self.ifds
+            .iter()
+            .all(|outcome| *outcome == GoldenOutcome::Match)
+    }
+
+    /// The index and outcome of each IFD whose outputs differ or failed to decode either way.
+    pub fn divergent(&self) -> impl Iterator<Item = (usize, &GoldenOutcome)> {
+        self.ifds
+            .iter()
+            .enumerate()
+            .filter(|(_, outcome)| **outcome != GoldenOutcome::Match)
+    }
+}
+
+/// Decode every IFD of the local file at `path` with this crate and with the `tiff` crate, and
+/// compare the native-endian samples of the whole images.
+///
+/// The file is read into memory at once, as this is meant for debugging problem files. The
+/// samples of each pixel are compared interleaved, as returned by
+/// [`read_window`](ImageFileDirectory::read_window), regardless of the planar configuration.
+/// Note that the `tiff` crate only decodes the first plane of images with planar configuration,
+/// so they are reported as mismatches.
+///
+/// Returns an error only if the file can't be read or its IFDs can't be parsed by this crate;
+/// decoding errors of either side are reported per IFD.
+pub async fn compare_with_upstream(
+    path: impl AsRef<Path>,
+    decoder_registry: &DecoderRegistry,
+) -> AsyncTiffResult<GoldenReport> {
+    let file = Bytes::from(std::fs::read(path)?);
+    let reader = MemoryReader::new(file.clone());
+    let mut metadata = TiffMetadataReader::try_open(&reader).await?;
+    let ifds = metadata.read_all_ifds(&reader).await?;
+
+    let mut upstream = Decoder::new(Cursor::new(file.as_ref()))
+        .map(|decoder| decoder.with_limits(Limits::unlimited()))
+        .map_err(|err| err.to_string());
+    let mut outcomes = vec![];
+    for (index, ifd) in ifds.iter().enumerate() {
+        let ours = decode_image(ifd, &reader, decoder_registry)
+            .await
+            .map_err(|err| err.to_string());
+        let theirs = match &mut upstream {
+            Ok(decoder) => decoder
+                .seek_to_image(index)
+                .and_then(|_| decoder.read_image())
+                .map(upstream_bytes)
+                .map_err(|err| err.to_string()),
+            Err(err) => Err(err.clone()),
+        };
+        outcomes.push(compare(ours, theirs));
+    }
+    Ok(GoldenReport { ifds: outcomes })
+}
+
+fn compare(ours: Result<Vec<u8>, String>, theirs: Result<Vec<u8>, String>) -> GoldenOutcome {
+    match (ours, theirs) {
+        (Ok(ours), Ok(theirs)) if ours == theirs => GoldenOutcome::Match,
+        (Ok(ours), Ok(theirs)) => {
+            let common = ours.len().min(theirs.len());
+            let differs = |(a, b): (&u8, &u8)| a != b;
+            GoldenOutcome::Mismatch {
+                len: ours.len(),
+                upstream_len: theirs.len(),
+                first_difference: ours.iter().zip(&theirs).position(differs).unwrap_or(common),
+                differing_bytes: ours
+                    .iter()
+                    .zip(&theirs)
+                    .filter(|&pair| differs(pair))
+                    .count(),
+            }
+        }
+        (Err(error), Ok(_)) => GoldenOutcome::Failed(error),
+        (Ok(_), Err(upstream_error)) => GoldenOutcome::UpstreamFailed(upstream_error),
+        (Err(error), Err(upstream_error)) => GoldenOutcome::BothFailed {
+            error,
+            upstream_error,
+        },
+    }
+}
+
+/// Decode the whole image of `ifd`, with the samples of each pixel interleaved.
+async fn decode_image(
+    ifd: &ImageFileDirectory,
+    reader: &dyn AsyncFileReader,
+    decoder_registry: &DecoderRegistry,
+) -> AsyncTiffResult<Vec<u8>> {
+    if ifd.tile_count().is_some() {
+        let window = Window::new(0, 0, ifd.image_width(), ifd.image_height());
+        let data = ifd.read_window(window, reader, decoder_registry).await?;
+        return Ok(data.into_data());
+    }
+
+    let strip_count = ifd
+        .strip_count()
+        .ok_or_else(|| AsyncTiffError::General("Image has no tiles or strips".to_string()))?;
+    let indices = (0..strip_count).collect::<Vec<_>>();
+    let strips = ifd.fetch_strips(&indices, reader).await?;
+    let planes = match ifd.planar_configuration() {
+        PlanarConfiguration::Chunky => 1,
+        PlanarConfiguration::Planar => ifd.samples_per_pixel() as usize,
+    };
+    let strips_per_plane = strip_count / planes;
+    let plane_bytes = ifd.chunk_row_bytes() * ifd.image_height() as usize;
+    let mut decoded = vec![];
+    for (i, strip) in strips.into_iter().enumerate() {
+        decoded.extend_from_slice(&strip.decode(decoder_registry)?);
+        // The last strip of a plane may be padded to the full strip height.
+        if (i + 1) % strips_per_plane == 0 {
+            decoded.truncate(plane_bytes * (i + 1) / strips_per_plane);
+        }
+    }
+    if planes == 1 {
+        return Ok(decoded);
+    }
+
+    let bytes_per_sample = (ifd.bits_per_sample()[0] as usize).div_ceil(8);
+    let mut interleaved = vec![0; decoded.len()];
+    for (plane, samples) in decoded.chunks_exact(plane_bytes).enumerate() {
+        for (pixel, sample) in samples.chunks_exact(bytes_per_sample).enumerate() {
+            let start = (pixel * planes + plane) * bytes_per_sample;
+            interleaved[start..start + bytes_per_sample].copy_from_slice(sample);
+        }
+    }
+    Ok(interleaved)
+}
+
+/// The native-endian bytes of the samples decoded by the `tiff` crate.
+fn upstream_bytes(result: DecodingResult) -> Vec<u8> {
+    fn ne_bytes<T, const N: usize>(values: Vec<T>, f: impl Fn(T) -> [u8; N]) -> Vec<u8> {
+        values.into_iter().flat_map(f).collect()
+    }
+    match result {
+        DecodingResult::U8(values) => values,
+        DecodingResult::U16(values) => ne_bytes(values, u16::to_ne_bytes),
+        DecodingResult::U32(values) => ne_bytes(values, u32::to_ne_bytes),
+        DecodingResult::U64(values) => ne_bytes(values, u64::to_ne_bytes),
+        DecodingResult::F32(values) => ne_bytes(values, f32::to_ne_bytes),
+        DecodingResult::F64(values) => ne_bytes(values, f64::to_ne_bytes),
+        DecodingResult::I8(values) => ne_bytes(values, i8::to_ne_bytes),
+        DecodingResult::I16(values) => ne_bytes(values, i16::to_ne_bytes),
+        DecodingResult::I32(values) => ne_bytes(values, i32::to_ne_bytes),
+        DecodingResult::I64(values) => ne_bytes(values, i64::to_ne_bytes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_IMAGE_DIR: &str = "tests/image_tiff/images/";
+
+    #[tokio::test]
+    async fn test_compare_with_upstream() {
+        let registry = DecoderRegistry::default();
+        for filename in ["tiled-rgb-u8.tif", "predictor-3-gray-f32.tif"] {
+            let report = compare_with_upstream(format!("{TEST_IMAGE_DIR}{filename}"), &registry)
+                .await
+                .unwrap();
+            assert!(!report.ifds().is_empty());
+            assert!(report.is_match(), "{filename}: {report:?}");
+        }
+
+        // The `tiff` crate only decodes the first plane of planar images
+        let path = format!("{TEST_IMAGE_DIR}planar-rgb-u8.tif");
+        let report = compare_with_upstream(path, &registry).await.unwrap();
+        let divergent = report.divergent().collect::<Vec<_>>();
+        assert!(matches!(
+            divergent[..],
+            [(0, GoldenOutcome::Mismatch { len, upstream_len, .. })] if *len == upstream_len * 3
+        ));
+
+        assert!(compare_with_upstream("missing.tif", &registry)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(
+            compare(Ok(vec![1, 2, 3]), Ok(vec![1, 2, 3])),
+            GoldenOutcome::Match
+        );
+        assert_eq!(
+            compare(Ok(vec![1, 2, 3, 4]), Ok(vec![1, 0, 3, 0, 5])),
+            GoldenOutcome::Mismatch {
+                len: 4,
+                upstream_len: 5,
+                first_difference: 1,
+                differing_bytes: 2,
+            }
+        );
+        assert_eq!(
+            compare(Ok(vec![1, 2]), Ok(vec![1, 2, 3])),
+            GoldenOutcome::Mismatch {
+                len: 2,
+                upstream_len: 3,
+                first_difference: 2,
+                differing_bytes: 0,
+            }
+        );
+        assert_eq!(
+            compare(Err("ours".to_string()), Ok(vec![])),
+            GoldenOutcome::Failed("ours".to_string())
+        );
+    }
+}
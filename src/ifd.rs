@@ -1,19 +1,27 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
+use futures::future::join_all;
 use num_enum::TryFromPrimitive;
 
+use crate::byte_transform::ByteTransform;
+use crate::decoder::YCbCrConversion;
 use crate::error::{AsyncTiffError, AsyncTiffResult};
-use crate::geo::{GeoKeyDirectory, GeoKeyTag};
+use crate::extra_tags::{ExtraTags, ExtraTagsRegistry};
+use crate::geo::{AffineTransform, GeoKeyDirectory, GeoKeyEntry, GeoKeyTag, RawGeoKeys};
+use crate::lazy_offsets::LazyTileOffsets;
 use crate::predictor::PredictorInfo;
 use crate::reader::{AsyncFileReader, Endianness};
 use crate::tiff::tags::{
-    CompressionMethod, PhotometricInterpretation, PlanarConfiguration, Predictor, ResolutionUnit,
-    SampleFormat, Tag,
+    CompressionMethod, FillOrder, PhotometricInterpretation, PlanarConfiguration, Predictor,
+    ResolutionUnit, SampleFormat, Tag,
 };
-use crate::tiff::{TiffError, Value};
-use crate::tile::Tile;
+use crate::tiff::{TiffError, TiffFormatError, TiffResult, Value};
+use crate::tile::{FetchInfo, RawTile, Tile, TilePayload, Truncated, TruncatedTiles};
 
 const DOCUMENT_NAME: u16 = 269;
 
@@ -38,11 +46,13 @@ pub struct ImageFileDirectory {
 
     pub(crate) photometric_interpretation: PhotometricInterpretation,
 
+    pub(crate) fill_order: FillOrder,
+
     pub(crate) document_name: Option<String>,
 
     pub(crate) image_description: Option<String>,
 
-    pub(crate) strip_offsets: Option<Vec<u64>>,
+    pub(crate) strip_offsets: Option<Arc<[u64]>>,
 
     pub(crate) orientation: Option<u16>,
 
@@ -55,16 +65,18 @@ pub struct ImageFileDirectory {
 
     pub(crate) rows_per_strip: Option<u32>,
 
-    pub(crate) strip_byte_counts: Option<Vec<u64>>,
+    pub(crate) strip_byte_counts: Option<Arc<[u64]>>,
 
     pub(crate) min_sample_value: Option<Vec<u16>>,
     pub(crate) max_sample_value: Option<Vec<u16>>,
 
-    /// The number of pixels per ResolutionUnit in the ImageWidth direction.
-    pub(crate) x_resolution: Option<f64>,
+    /// The number of pixels per ResolutionUnit in the ImageWidth direction, as the exact
+    /// (numerator, denominator) pair stored in the file.
+    pub(crate) x_resolution: Option<(u32, u32)>,
 
-    /// The number of pixels per ResolutionUnit in the ImageLength direction.
-    pub(crate) y_resolution: Option<f64>,
+    /// The number of pixels per ResolutionUnit in the ImageLength direction, as the exact
+    /// (numerator, denominator) pair stored in the file.
+    pub(crate) y_resolution: Option<(u32, u32)>,
 
     /// How the components of each pixel are stored.
     ///
@@ -93,7 +105,15 @@ pub struct ImageFileDirectory {
     /// space character between the date and the time. The length of the string, including the
     /// terminating NUL, is 20 bytes.
     pub(crate) date_time: Option<String>,
-    pub(crate) artist: Option<String>,
+
+    /// The DateTimeOriginal tag of the EXIF IFD that this IFD points to.
+    pub(crate) date_time_original: Option<String>,
+
+    /// Person(s) who created the image.
+    ///
+    /// Multiple artists are stored as NUL-separated strings in a single tag.
+    pub(crate) artist: Option<Vec<String>>,
+
     pub(crate) host_computer: Option<String>,
 
     pub(crate) predictor: Option<Predictor>,
@@ -117,13 +137,16 @@ pub struct ImageFileDirectory {
     /// In Specification Supplement 1, support was added for ColorMaps containing other then RGB
     /// values. This scheme includes the Indexed tag, with value 1, and a PhotometricInterpretation
     /// different from PaletteColor then next denotes the colorspace of the ColorMap entries.
-    pub(crate) color_map: Option<Vec<u16>>,
+    pub(crate) color_map: Option<Arc<[u16]>>,
 
     pub(crate) tile_width: Option<u32>,
     pub(crate) tile_height: Option<u32>,
 
-    pub(crate) tile_offsets: Option<Vec<u64>>,
-    pub(crate) tile_byte_counts: Option<Vec<u64>>,
+    pub(crate) tile_offsets: Option<Arc<[u64]>>,
+    pub(crate) tile_byte_counts: Option<Arc<[u64]>>,
+
+    /// The location of the tile offsets and byte counts, if they weren't read with the IFD.
+    pub(crate) lazy_tile_offsets: Option<Arc<LazyTileOffsets>>,
 
     pub(crate) extra_samples: Option<Vec<u16>>,
 
@@ -131,10 +154,25 @@ pub struct ImageFileDirectory {
 
     pub(crate) jpeg_tables: Option<Bytes>,
 
-    pub(crate) copyright: Option<String>,
+    /// The JPEG process used to produce old-style JPEG compressed data.
+    pub(crate) jpeg_proc: Option<u16>,
+
+    /// The transformation from RGB to YCbCr image data, as the luma coefficients of red, green
+    /// and blue.
+    pub(crate) ycbcr_coefficients: Option<[f64; 3]>,
+
+    /// The headroom and footroom of each component, as black and white pairs.
+    pub(crate) reference_black_white: Option<[f64; 6]>,
+
+    /// Copyright notice.
+    ///
+    /// This may contain two NUL-separated strings: the photographer copyright followed by the
+    /// editor copyright.
+    pub(crate) copyright: Option<Vec<String>>,
 
     // Geospatial tags
     pub(crate) geo_key_directory: Option<GeoKeyDirectory>,
+    pub(crate) raw_geo_keys: Option<RawGeoKeys>,
     pub(crate) model_pixel_scale: Option<Vec<f64>>,
     pub(crate) model_tiepoint: Option<Vec<f64>>,
 
@@ -142,20 +180,130 @@ pub struct ImageFileDirectory {
     // no_data
     // gdal_metadata
     pub(crate) other_tags: HashMap<Tag, Value>,
+
+    /// Problems that were worked around while parsing this IFD.
+    pub(crate) warnings: Vec<IfdWarning>,
+
+    /// Applied to the compressed bytes of each fetched tile or strip.
+    pub(crate) byte_transform: Option<Arc<dyn ByteTransform>>,
+
+    /// Plugins that processed the tags they handle.
+    pub(crate) extra_tags: ExtraTagsRegistry,
+
+    /// The bytes of the file holding this IFD's entries and out-of-line tag values, if it was
+    /// read from a file.
+    pub(crate) metadata_range: Option<Range<u64>>,
+
+    /// Whether fetched tiles and strips record their [`FetchInfo`].
+    pub(crate) record_fetch_info: bool,
+}
+
+/// A deviation from the TIFF specification that was tolerated while parsing an IFD.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum IfdWarning {
+    /// A stripped image had a missing or zero RowsPerStrip, so it is treated as a single strip
+    /// spanning the whole image.
+    RowsPerStripDefaulted {
+        /// The RowsPerStrip value found in the file, if any.
+        found: Option<u32>,
+    },
+    /// A stripped image had no StripOffsets tag, so its data cannot be located.
+    MissingStripOffsets,
+    /// A stripped image had no StripByteCounts tag.
+    MissingStripByteCounts,
+    /// The GeoKeyDirectoryTag was malformed, so the image has no GeoKeys.
+    ///
+    /// This is only tolerated by lenient reads, e.g. with
+    /// [`TiffMetadataReader::with_lenient`](crate::metadata::TiffMetadataReader::with_lenient).
+    InvalidGeoKeyDirectory {
+        /// Why the directory couldn't be read.
+        reason: String,
+    },
+    /// A GeoKey couldn't be read, e.g. because its value lies outside the GeoAsciiParamsTag or
+    /// GeoDoubleParamsTag, so it was skipped.
+    ///
+    /// This is only tolerated by lenient reads.
+    InvalidGeoKey {
+        /// The id of the key.
+        key: u16,
+        /// Why the key couldn't be read.
+        reason: String,
+    },
 }
 
 impl ImageFileDirectory {
-    /// Create a new ImageFileDirectory from tag data
+    /// Create a new ImageFileDirectory from tag data.
+    ///
+    /// This is how IFDs read from a file are constructed, but it can equally be used with a tag
+    /// map assembled by hand, e.g. to build synthetic or patched IFDs for tests. `endianness` is
+    /// the byte order of the image data that tiles of this IFD will be decoded from.
+    ///
+    /// `ImageWidth`, `ImageLength` and `PhotometricInterpretation` are required. Other tags
+    /// take their default value from the TIFF specification when missing, e.g. one 1-bit sample
+    /// per pixel and no compression. Tags without a dedicated field are kept and are available
+    /// from [`other_tags`](Self::other_tags).
+    ///
+    /// Returns an error if a required tag is missing or a tag has a value of the wrong type.
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use async_tiff::ImageFileDirectory;
+    /// # use async_tiff::reader::Endianness;
+    /// # use async_tiff::tags::Tag;
+    /// # use async_tiff::tags::Value;
+    /// let tags = HashMap::from([
+    ///     (Tag::ImageWidth, Value::Unsigned(512)),
+    ///     (Tag::ImageLength, Value::Unsigned(512)),
+    ///     (Tag::BitsPerSample, Value::Short(8)),
+    ///     (Tag::PhotometricInterpretation, Value::Short(1)),
+    ///     (Tag::TileWidth, Value::Short(256)),
+    ///     (Tag::TileLength, Value::Short(256)),
+    ///     (Tag::TileOffsets, Value::List(vec![Value::Unsigned(0); 4])),
+    ///     (Tag::TileByteCounts, Value::List(vec![Value::Unsigned(65536); 4])),
+    /// ]);
+    /// let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+    /// assert_eq!(ifd.tile_count(), Some((2, 2)));
+    /// assert_eq!(ifd.samples_per_pixel(), 1);
+    /// ```
     pub fn from_tags(
         tag_data: HashMap<Tag, Value>,
         endianness: Endianness,
     ) -> AsyncTiffResult<Self> {
+        Self::from_tags_with_extra_tags(tag_data, &ExtraTagsRegistry::default(), endianness)
+    }
+
+    /// Create a new ImageFileDirectory from tag data, passing the tags handled by the plugins of
+    /// `extra_tags` to a clone of each plugin instead of [`other_tags`](Self::other_tags).
+    ///
+    /// The clones are available from [`extra_tags`](Self::extra_tags). See
+    /// [`from_tags`](Self::from_tags) for how the other tags are handled.
+    pub fn from_tags_with_extra_tags(
+        tag_data: HashMap<Tag, Value>,
+        extra_tags: &ExtraTagsRegistry,
+        endianness: Endianness,
+    ) -> AsyncTiffResult<Self> {
+        Self::from_tags_lenient(tag_data, extra_tags, endianness, false)
+    }
+
+    /// Create a new ImageFileDirectory from tag data, as
+    /// [`from_tags_with_extra_tags`](Self::from_tags_with_extra_tags) does.
+    ///
+    /// If `lenient`, malformed GeoKeys are skipped with an [`IfdWarning`] instead of failing.
+    pub(crate) fn from_tags_lenient(
+        tag_data: HashMap<Tag, Value>,
+        extra_tags: &ExtraTagsRegistry,
+        endianness: Endianness,
+        lenient: bool,
+    ) -> AsyncTiffResult<Self> {
+        let mut extra_tags = extra_tags.clone();
         let mut new_subfile_type = None;
         let mut image_width = None;
         let mut image_height = None;
         let mut bits_per_sample = None;
         let mut compression = None;
         let mut photometric_interpretation = None;
+        let mut fill_order = None;
         let mut document_name = None;
         let mut image_description = None;
         let mut strip_offsets = None;
@@ -182,6 +330,9 @@ impl ImageFileDirectory {
         let mut extra_samples = None;
         let mut sample_format = None;
         let mut jpeg_tables = None;
+        let mut jpeg_proc = None;
+        let mut ycbcr_coefficients = None;
+        let mut reference_black_white = None;
         let mut copyright = None;
         let mut geo_key_directory_data = None;
         let mut model_pixel_scale = None;
@@ -195,6 +346,9 @@ impl ImageFileDirectory {
 
         // }
         tag_data.into_iter().try_for_each(|(tag, value)| {
+            let Some(value) = extra_tags.process_tag(tag, value)? else {
+                return Ok(());
+            };
             match tag {
                 Tag::NewSubfileType => new_subfile_type = Some(value.into_u32()?),
                 Tag::ImageWidth => image_width = Some(value.into_u32()?),
@@ -204,25 +358,21 @@ impl ImageFileDirectory {
                     compression = Some(CompressionMethod::from_u16_exhaustive(value.into_u16()?))
                 }
                 Tag::PhotometricInterpretation => {
-                    photometric_interpretation =
-                        PhotometricInterpretation::from_u16(value.into_u16()?)
+                    photometric_interpretation = Some(
+                        PhotometricInterpretation::from_u16_exhaustive(value.into_u16()?),
+                    )
                 }
                 Tag::ImageDescription => image_description = Some(value.into_string()?),
-                Tag::StripOffsets => strip_offsets = Some(value.into_u64_vec()?),
+                Tag::StripOffsets => strip_offsets = Some(value.into_u64_vec()?.into()),
+                Tag::FillOrder => fill_order = FillOrder::from_u16(value.into_u16()?),
                 Tag::Orientation => orientation = Some(value.into_u16()?),
                 Tag::SamplesPerPixel => samples_per_pixel = Some(value.into_u16()?),
                 Tag::RowsPerStrip => rows_per_strip = Some(value.into_u32()?),
-                Tag::StripByteCounts => strip_byte_counts = Some(value.into_u64_vec()?),
+                Tag::StripByteCounts => strip_byte_counts = Some(value.into_u64_vec()?.into()),
                 Tag::MinSampleValue => min_sample_value = Some(value.into_u16_vec()?),
                 Tag::MaxSampleValue => max_sample_value = Some(value.into_u16_vec()?),
-                Tag::XResolution => match value {
-                    Value::Rational(n, d) => x_resolution = Some(n as f64 / d as f64),
-                    _ => unreachable!("Expected rational type for XResolution."),
-                },
-                Tag::YResolution => match value {
-                    Value::Rational(n, d) => y_resolution = Some(n as f64 / d as f64),
-                    _ => unreachable!("Expected rational type for YResolution."),
-                },
+                Tag::XResolution => x_resolution = Some(resolution_from_value(tag, value)?),
+                Tag::YResolution => y_resolution = Some(resolution_from_value(tag, value)?),
                 Tag::PlanarConfiguration => {
                     planar_configuration = PlanarConfiguration::from_u16(value.into_u16()?)
                 }
@@ -231,14 +381,16 @@ impl ImageFileDirectory {
                 }
                 Tag::Software => software = Some(value.into_string()?),
                 Tag::DateTime => date_time = Some(value.into_string()?),
-                Tag::Artist => artist = Some(value.into_string()?),
+                Tag::Artist => artist = Some(value.into_string_vec()?),
                 Tag::HostComputer => host_computer = Some(value.into_string()?),
-                Tag::Predictor => predictor = Predictor::from_u16(value.into_u16()?),
-                Tag::ColorMap => color_map = Some(value.into_u16_vec()?),
+                Tag::Predictor => {
+                    predictor = Some(Predictor::from_u16_exhaustive(value.into_u16()?))
+                }
+                Tag::ColorMap => color_map = Some(value.into_u16_vec()?.into()),
                 Tag::TileWidth => tile_width = Some(value.into_u32()?),
                 Tag::TileLength => tile_height = Some(value.into_u32()?),
-                Tag::TileOffsets => tile_offsets = Some(value.into_u64_vec()?),
-                Tag::TileByteCounts => tile_byte_counts = Some(value.into_u64_vec()?),
+                Tag::TileOffsets => tile_offsets = Some(value.into_u64_vec()?.into()),
+                Tag::TileByteCounts => tile_byte_counts = Some(value.into_u64_vec()?.into()),
                 Tag::ExtraSamples => extra_samples = Some(value.into_u16_vec()?),
                 Tag::SampleFormat => {
                     let values = value.into_u16_vec()?;
@@ -250,7 +402,14 @@ impl ImageFileDirectory {
                     );
                 }
                 Tag::JPEGTables => jpeg_tables = Some(value.into_u8_vec()?.into()),
-                Tag::Copyright => copyright = Some(value.into_string()?),
+                Tag::JPEGProc => jpeg_proc = Some(value.into_u16()?),
+                Tag::YCbCrCoefficients => {
+                    ycbcr_coefficients = Some(rationals_from_value(tag, value)?)
+                }
+                Tag::ReferenceBlackWhite => {
+                    reference_black_white = Some(rationals_from_value(tag, value)?)
+                }
+                Tag::Copyright => copyright = Some(value.into_string_vec()?),
 
                 // Geospatial tags
                 // http://geotiff.maptools.org/spec/geotiff2.4.html
@@ -266,85 +425,35 @@ impl ImageFileDirectory {
                     other_tags.insert(tag, value);
                 }
             };
-            Ok::<_, TiffError>(())
+            Ok::<_, AsyncTiffError>(())
         })?;
 
-        let mut geo_key_directory = None;
-
         // We need to actually parse the GeoKeyDirectory after parsing all other tags because the
         // GeoKeyDirectory relies on `GeoAsciiParamsTag` having been parsed.
-        if let Some(data) = geo_key_directory_data {
-            let mut chunks = data.chunks(4);
-
-            let header = chunks
-                .next()
-                .expect("If the geo key directory exists, a header should exist.");
-            let key_directory_version = header[0];
-            assert_eq!(key_directory_version, 1);
-
-            let key_revision = header[1];
-            assert_eq!(key_revision, 1);
-
-            let _key_minor_revision = header[2];
-            let number_of_keys = header[3];
-
-            let mut tags = HashMap::with_capacity(number_of_keys as usize);
-            for _ in 0..number_of_keys {
-                let chunk = chunks
-                    .next()
-                    .expect("There should be a chunk for each key.");
-
-                let key_id = chunk[0];
-                let tag_name =
-                    GeoKeyTag::try_from_primitive(key_id).expect("Unknown GeoKeyTag id: {key_id}");
-
-                let tag_location = chunk[1];
-                let count = chunk[2];
-                let value_offset = chunk[3];
-
-                if tag_location == 0 {
-                    tags.insert(tag_name, Value::Short(value_offset));
-                } else if Tag::from_u16_exhaustive(tag_location) == Tag::GeoAsciiParamsTag {
-                    // If the tag_location points to the value of Tag::GeoAsciiParamsTag, then we
-                    // need to extract a subslice from GeoAsciiParamsTag
-
-                    let geo_ascii_params = geo_ascii_params
-                        .as_ref()
-                        .expect("GeoAsciiParamsTag exists but geo_ascii_params does not.");
-                    let value_offset = value_offset as usize;
-                    let mut s = &geo_ascii_params[value_offset..value_offset + count as usize];
-
-                    // It seems that this string subslice might always include the final |
-                    // character?
-                    if s.ends_with('|') {
-                        s = &s[0..s.len() - 1];
-                    }
-
-                    tags.insert(tag_name, Value::Ascii(s.to_string()));
-                } else if Tag::from_u16_exhaustive(tag_location) == Tag::GeoDoubleParamsTag {
-                    // If the tag_location points to the value of Tag::GeoDoubleParamsTag, then we
-                    // need to extract a subslice from GeoDoubleParamsTag
-
-                    let geo_double_params = geo_double_params
-                        .as_ref()
-                        .expect("GeoDoubleParamsTag exists but geo_double_params does not.");
-                    let value_offset = value_offset as usize;
-                    let value = if count == 1 {
-                        Value::Double(geo_double_params[value_offset])
-                    } else {
-                        let x = geo_double_params[value_offset..value_offset + count as usize]
-                            .iter()
-                            .map(|val| Value::Double(*val))
-                            .collect();
-                        Value::List(x)
-                    };
-                    tags.insert(tag_name, value);
-                }
-            }
-            geo_key_directory = Some(GeoKeyDirectory::from_tags(tags)?);
-        }
+        let mut warnings = vec![];
+        let (geo_key_directory, raw_geo_keys) = match geo_key_directory_data {
+            Some(data) => parse_geo_keys(
+                &data,
+                geo_ascii_params,
+                geo_double_params,
+                lenient,
+                &mut warnings,
+            )?,
+            None => (None, None),
+        };
 
-        let samples_per_pixel = samples_per_pixel.expect("samples_per_pixel not found");
+        let required = |tag: Tag| {
+            AsyncTiffError::from(TiffError::FormatError(
+                TiffFormatError::RequiredTagNotFound(tag),
+            ))
+        };
+        let image_width = image_width.ok_or_else(|| required(Tag::ImageWidth))?;
+        let image_height = image_height.ok_or_else(|| required(Tag::ImageLength))?;
+        let photometric_interpretation =
+            photometric_interpretation.ok_or_else(|| required(Tag::PhotometricInterpretation))?;
+        // Defaults to one sample per pixel
+        // https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/samplesperpixel.html
+        let samples_per_pixel = samples_per_pixel.unwrap_or(1);
         let planar_configuration = if let Some(planar_configuration) = planar_configuration {
             planar_configuration
         } else if samples_per_pixel == 1 {
@@ -354,17 +463,38 @@ impl ImageFileDirectory {
         } else {
             PlanarConfiguration::Chunky
         };
+
+        if tile_width.is_none() {
+            if rows_per_strip.unwrap_or(0) == 0 {
+                // Per the spec a missing RowsPerStrip means the whole image is a single strip. Zero
+                // is invalid, but writers that emit it mean the same thing.
+                warnings.push(IfdWarning::RowsPerStripDefaulted {
+                    found: rows_per_strip,
+                });
+            }
+            if strip_offsets.is_none() {
+                warnings.push(IfdWarning::MissingStripOffsets);
+            }
+            if strip_byte_counts.is_none() {
+                warnings.push(IfdWarning::MissingStripByteCounts);
+            }
+        }
+
         Ok(Self {
             endianness,
             new_subfile_type,
-            image_width: image_width.expect("image_width not found"),
-            image_height: image_height.expect("image_height not found"),
-            bits_per_sample: bits_per_sample.expect("bits per sample not found"),
+            image_width,
+            image_height,
+            // Defaults to 1 bit per sample
+            // https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/bitspersample.html
+            bits_per_sample: bits_per_sample.unwrap_or(vec![1; samples_per_pixel as _]),
             // Defaults to no compression
             // https://web.archive.org/web/20240329145331/https://www.awaresystems.be/imaging/tiff/tifftags/compression.html
             compression: compression.unwrap_or(CompressionMethod::None),
-            photometric_interpretation: photometric_interpretation
-                .expect("photometric interpretation not found"),
+            photometric_interpretation,
+            // Defaults to the most significant bit first
+            // https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/fillorder.html
+            fill_order: fill_order.unwrap_or(FillOrder::MsbToLsb),
             document_name,
             image_description,
             strip_offsets,
@@ -380,6 +510,7 @@ impl ImageFileDirectory {
             resolution_unit,
             software,
             date_time,
+            date_time_original: None,
             artist,
             host_computer,
             predictor,
@@ -388,6 +519,7 @@ impl ImageFileDirectory {
             tile_height,
             tile_offsets,
             tile_byte_counts,
+            lazy_tile_offsets: None,
             extra_samples,
             // Uint8 is the default for SampleFormat
             // https://web.archive.org/web/20240329145340/https://www.awaresystems.be/imaging/tiff/tifftags/sampleformat.html
@@ -395,10 +527,19 @@ impl ImageFileDirectory {
                 .unwrap_or(vec![SampleFormat::Uint; samples_per_pixel as _]),
             copyright,
             jpeg_tables,
+            jpeg_proc,
+            ycbcr_coefficients,
+            reference_black_white,
             geo_key_directory,
+            raw_geo_keys,
             model_pixel_scale,
             model_tiepoint,
             other_tags,
+            warnings,
+            byte_transform: None,
+            extra_tags,
+            metadata_range: None,
+            record_fetch_info: false,
         })
     }
 
@@ -408,6 +549,13 @@ impl ImageFileDirectory {
         self.new_subfile_type
     }
 
+    /// Whether this IFD is a transparency mask for another image in the file.
+    ///
+    /// This checks bit 2 of [`new_subfile_type`][Self::new_subfile_type].
+    pub fn is_mask(&self) -> bool {
+        self.new_subfile_type.is_some_and(|t| t & 4 != 0)
+    }
+
     /// The number of columns in the image, i.e., the number of pixels per row.
     /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/imagewidth.html>
     pub fn image_width(&self) -> u32 {
@@ -438,6 +586,14 @@ impl ImageFileDirectory {
         self.photometric_interpretation
     }
 
+    /// The logical order of bits within a byte.
+    ///
+    /// When this is [`FillOrder::LsbToMsb`], the bits of each byte are reversed before decoding.
+    /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/fillorder.html>
+    pub fn fill_order(&self) -> FillOrder {
+        self.fill_order
+    }
+
     /// Document name.
     pub fn document_name(&self) -> Option<&str> {
         self.document_name.as_deref()
@@ -476,6 +632,20 @@ impl ImageFileDirectory {
         self.rows_per_strip
     }
 
+    /// The number of rows in each strip of a stripped image, or `None` if this image is tiled.
+    ///
+    /// Unlike [`rows_per_strip`][Self::rows_per_strip], this applies the specification's default
+    /// when the tag is missing, treats zero the same way, and is capped at the image height.
+    pub fn strip_height(&self) -> Option<u32> {
+        if self.tile_width.is_some() {
+            return None;
+        }
+        match self.rows_per_strip {
+            Some(rows) if rows > 0 => Some(rows.min(self.image_height)),
+            _ => Some(self.image_height),
+        }
+    }
+
     /// For each strip, the number of bytes in the strip after compression.
     /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/stripbytecounts.html>
     pub fn strip_byte_counts(&self) -> Option<&[u64]> {
@@ -495,14 +665,40 @@ impl ImageFileDirectory {
     }
 
     /// The number of pixels per ResolutionUnit in the ImageWidth direction.
+    ///
+    /// Returns `None` if the tag is absent or has a zero denominator. Use
+    /// [`x_resolution_rational`][Self::x_resolution_rational] to access the exact stored value.
+    ///
     /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/xresolution.html>
     pub fn x_resolution(&self) -> Option<f64> {
-        self.x_resolution
+        self.x_resolution.and_then(rational_to_f64)
     }
 
     /// The number of pixels per ResolutionUnit in the ImageLength direction.
+    ///
+    /// Returns `None` if the tag is absent or has a zero denominator. Use
+    /// [`y_resolution_rational`][Self::y_resolution_rational] to access the exact stored value.
+    ///
     /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/yresolution.html>
     pub fn y_resolution(&self) -> Option<f64> {
+        self.y_resolution.and_then(rational_to_f64)
+    }
+
+    /// The (numerator, denominator) of XResolution, exactly as stored in the file if it is a
+    /// RATIONAL.
+    ///
+    /// Values written by non-conforming writers are coerced: integers to `(value, 1)`, and FLOAT
+    /// or DOUBLE values approximately to `(round(value * 10000), 10000)`.
+    pub fn x_resolution_rational(&self) -> Option<(u32, u32)> {
+        self.x_resolution
+    }
+
+    /// The (numerator, denominator) of YResolution, exactly as stored in the file if it is a
+    /// RATIONAL.
+    ///
+    /// Values written by non-conforming writers are coerced: integers to `(value, 1)`, and FLOAT
+    /// or DOUBLE values approximately to `(round(value * 10000), 10000)`.
+    pub fn y_resolution_rational(&self) -> Option<(u32, u32)> {
         self.y_resolution
     }
 
@@ -549,9 +745,46 @@ impl ImageFileDirectory {
         self.date_time.as_deref()
     }
 
-    /// Person who created the image.
+    /// The parsed form of [`date_time`][Self::date_time].
+    ///
+    /// Parsing is lenient: common deviations from the `"YYYY:MM:DD HH:MM:SS"` format such as `-`
+    /// or `/` date separators, a `T` between date and time, or a missing time are accepted.
+    /// Returns `None` if the tag is absent or cannot be interpreted as a date.
+    #[cfg(feature = "chrono")]
+    pub fn date_time_parsed(&self) -> Option<chrono::NaiveDateTime> {
+        crate::date_time::parse_date_time(self.date_time()?)
+    }
+
+    /// The date and time when the original image data was generated, from the EXIF
+    /// DateTimeOriginal tag.
+    ///
+    /// The tag belongs in the EXIF IFD, which with the `chrono` feature is read through the
+    /// ExifIFD pointer of IFDs read with
+    /// [`TiffMetadataReader`](crate::metadata::TiffMetadataReader) or
+    /// [`ImageFileDirectoryReader`](crate::metadata::ImageFileDirectoryReader). Otherwise, or if
+    /// the EXIF IFD has no such tag, a copy in this IFD itself is returned.
+    ///
+    /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/privateifd/exif/datetimeoriginal.html>
+    pub fn date_time_original(&self) -> Option<&str> {
+        self.date_time_original
+            .as_deref()
+            .or_else(|| self.tag_str(Tag::DateTimeOriginal))
+    }
+
+    /// The parsed form of [`date_time_original`][Self::date_time_original].
+    ///
+    /// This uses the same lenient parsing as [`date_time_parsed`][Self::date_time_parsed].
+    #[cfg(feature = "chrono")]
+    pub fn date_time_original_parsed(&self) -> Option<chrono::NaiveDateTime> {
+        crate::date_time::parse_date_time(self.date_time_original()?)
+    }
+
+    /// Person(s) who created the image.
+    ///
+    /// When multiple artists are listed, each one is returned as a separate string.
+    ///
     /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/artist.html>
-    pub fn artist(&self) -> Option<&str> {
+    pub fn artist(&self) -> Option<&[String]> {
         self.artist.as_deref()
     }
 
@@ -582,12 +815,16 @@ impl ImageFileDirectory {
 
     /// For each tile, the byte offset of that tile, as compressed and stored on disk.
     /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/tileoffsets.html>
+    ///
+    /// This is `None` for [lazy tile offsets](Self::has_lazy_tile_offsets) until they are loaded.
     pub fn tile_offsets(&self) -> Option<&[u64]> {
         self.tile_offsets.as_deref()
     }
 
     /// For each tile, the number of (compressed) bytes in that tile.
     /// <https://web.archive.org/web/20240329145339/https://www.awaresystems.be/imaging/tiff/tifftags/tilebytecounts.html>
+    ///
+    /// This is `None` for [lazy tile offsets](Self::has_lazy_tile_offsets) until they are loaded.
     pub fn tile_byte_counts(&self) -> Option<&[u64]> {
         self.tile_byte_counts.as_deref()
     }
@@ -610,9 +847,41 @@ impl ImageFileDirectory {
         self.jpeg_tables.as_deref()
     }
 
+    /// The JPEG process used to produce old-style JPEG compressed data: 1 for baseline
+    /// sequential and 14 for lossless.
+    ///
+    /// This is only meaningful for the obsolete JPEG compression method, and is ignored when
+    /// decoding modern JPEG-in-TIFF data, which is self-describing.
+    /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/jpegproc.html>
+    pub fn jpeg_proc(&self) -> Option<u16> {
+        self.jpeg_proc
+    }
+
+    /// The luma coefficients of red, green and blue used to convert RGB to YCbCr.
+    ///
+    /// These are used to convert JPEG-compressed YCbCr tiles to RGB. When absent, the default of
+    /// `[0.299, 0.587, 0.114]` applies.
+    /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/ycbcrcoefficients.html>
+    pub fn ycbcr_coefficients(&self) -> Option<[f64; 3]> {
+        self.ycbcr_coefficients
+    }
+
+    /// The reference black and white point of each component, as `[black, white]` pairs.
+    ///
+    /// These are used to convert JPEG-compressed YCbCr tiles to RGB. When absent, the default for
+    /// YCbCr images is `[0, 255, 128, 255, 128, 255]`.
+    /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/referenceblackwhite.html>
+    pub fn reference_black_white(&self) -> Option<[f64; 6]> {
+        self.reference_black_white
+    }
+
     /// Copyright notice.
+    ///
+    /// The spec allows both a photographer and an editor copyright to be stored, in which case
+    /// both are returned in that order.
+    ///
     /// <https://web.archive.org/web/20240329145250/https://www.awaresystems.be/imaging/tiff/tifftags/copyright.html>
-    pub fn copyright(&self) -> Option<&str> {
+    pub fn copyright(&self) -> Option<&[String]> {
         self.copyright.as_deref()
     }
 
@@ -622,6 +891,12 @@ impl ImageFileDirectory {
         self.geo_key_directory.as_ref()
     }
 
+    /// Every GeoKey as stored in the GeoKeyDirectoryTag, including keys that
+    /// [`geo_key_directory`](Self::geo_key_directory) doesn't model.
+    pub fn raw_geo_keys(&self) -> Option<&RawGeoKeys> {
+        self.raw_geo_keys.as_ref()
+    }
+
     /// Used in interchangeable GeoTIFF files.
     /// <https://web.archive.org/web/20240329145238/https://www.awaresystems.be/imaging/tiff/tifftags/modelpixelscaletag.html>
     pub fn model_pixel_scale(&self) -> Option<&[f64]> {
@@ -634,11 +909,262 @@ impl ImageFileDirectory {
         self.model_tiepoint.as_deref()
     }
 
+    /// Deviations from the specification that were tolerated while parsing this IFD.
+    pub fn warnings(&self) -> &[IfdWarning] {
+        &self.warnings
+    }
+
+    /// Assemble the tags of this IFD, the inverse of [`from_tags`](Self::from_tags).
+    ///
+    /// Tags processed by [`extra_tags`](Self::extra_tags) plugins are not included, as plugins
+    /// don't keep their raw values.
+    pub(crate) fn to_tags(&self) -> TiffResult<HashMap<Tag, Value>> {
+        let shorts =
+            |values: &[u16]| Value::List(values.iter().copied().map(Value::Short).collect());
+        let longs =
+            |values: &[u64]| Value::List(values.iter().copied().map(Value::UnsignedBig).collect());
+        let doubles =
+            |values: &[f64]| Value::List(values.iter().copied().map(Value::Double).collect());
+        let rationals = |values: &[f64]| {
+            values
+                .iter()
+                .map(|v| float_to_rational(*v).map(|(n, d)| Value::Rational(n, d)))
+                .collect::<TiffResult<Vec<_>>>()
+                .map(Value::List)
+        };
+
+        let mut tags = self.other_tags.clone();
+        tags.extend([
+            (Tag::ImageWidth, Value::Unsigned(self.image_width)),
+            (Tag::ImageLength, Value::Unsigned(self.image_height)),
+            (Tag::BitsPerSample, shorts(&self.bits_per_sample)),
+            (Tag::Compression, Value::Short(self.compression.to_u16())),
+            (
+                Tag::PhotometricInterpretation,
+                Value::Short(self.photometric_interpretation.to_u16()),
+            ),
+            (Tag::SamplesPerPixel, Value::Short(self.samples_per_pixel)),
+            (
+                Tag::PlanarConfiguration,
+                Value::Short(self.planar_configuration.to_u16()),
+            ),
+            (
+                Tag::SampleFormat,
+                shorts(
+                    &self
+                        .sample_format
+                        .iter()
+                        .map(|f| f.to_u16())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+        ]);
+        if self.fill_order != FillOrder::MsbToLsb {
+            tags.insert(Tag::FillOrder, Value::Short(self.fill_order.to_u16()));
+        }
+
+        let u32_tags = [
+            (Tag::NewSubfileType, self.new_subfile_type),
+            (Tag::RowsPerStrip, self.rows_per_strip),
+            (Tag::TileWidth, self.tile_width),
+            (Tag::TileLength, self.tile_height),
+        ];
+        let u16_tags = [
+            (Tag::Orientation, self.orientation),
+            (
+                Tag::ResolutionUnit,
+                self.resolution_unit.map(|x| x.to_u16()),
+            ),
+            (Tag::Predictor, self.predictor.map(|x| x.to_u16())),
+            (Tag::JPEGProc, self.jpeg_proc),
+        ];
+        let str_tags = [
+            (Tag::Unknown(DOCUMENT_NAME), self.document_name.clone()),
+            (Tag::ImageDescription, self.image_description.clone()),
+            (Tag::Software, self.software.clone()),
+            (Tag::DateTime, self.date_time.clone()),
+            (Tag::HostComputer, self.host_computer.clone()),
+            (Tag::Artist, self.artist.as_ref().map(|v| v.join("\0"))),
+            (
+                Tag::Copyright,
+                self.copyright.as_ref().map(|v| v.join("\0")),
+            ),
+        ];
+        let u16_vec_tags = [
+            (Tag::MinSampleValue, self.min_sample_value.as_deref()),
+            (Tag::MaxSampleValue, self.max_sample_value.as_deref()),
+            (Tag::ColorMap, self.color_map.as_deref()),
+            (Tag::ExtraSamples, self.extra_samples.as_deref()),
+        ];
+        let u64_vec_tags = [
+            (Tag::StripOffsets, self.strip_offsets.as_deref()),
+            (Tag::StripByteCounts, self.strip_byte_counts.as_deref()),
+            (Tag::TileOffsets, self.tile_offsets.as_deref()),
+            (Tag::TileByteCounts, self.tile_byte_counts.as_deref()),
+        ];
+        let resolution_tags = [
+            (Tag::XResolution, self.x_resolution),
+            (Tag::YResolution, self.y_resolution),
+        ];
+        tags.extend(
+            u32_tags
+                .into_iter()
+                .filter_map(|(tag, v)| Some((tag, Value::Unsigned(v?)))),
+        );
+        tags.extend(
+            u16_tags
+                .into_iter()
+                .filter_map(|(tag, v)| Some((tag, Value::Short(v?)))),
+        );
+        tags.extend(
+            str_tags
+                .into_iter()
+                .filter_map(|(tag, v)| Some((tag, Value::Ascii(v?)))),
+        );
+        tags.extend(
+            u16_vec_tags
+                .into_iter()
+                .filter_map(|(tag, v)| Some((tag, shorts(v?)))),
+        );
+        tags.extend(
+            u64_vec_tags
+                .into_iter()
+                .filter_map(|(tag, v)| Some((tag, longs(v?)))),
+        );
+        tags.extend(
+            resolution_tags
+                .into_iter()
+                .filter_map(|(tag, v)| Some((tag, Value::Rational(v?.0, v?.1)))),
+        );
+
+        if let Some(jpeg_tables) = &self.jpeg_tables {
+            tags.insert(
+                Tag::JPEGTables,
+                Value::List(jpeg_tables.iter().copied().map(Value::Byte).collect()),
+            );
+        }
+        if let Some(coefficients) = &self.ycbcr_coefficients {
+            tags.insert(Tag::YCbCrCoefficients, rationals(coefficients)?);
+        }
+        if let Some(reference_black_white) = &self.reference_black_white {
+            tags.insert(Tag::ReferenceBlackWhite, rationals(reference_black_white)?);
+        }
+        if let Some(raw_geo_keys) = &self.raw_geo_keys {
+            tags.extend(raw_geo_keys.to_tags());
+        } else if let Some(geo_key_directory) = &self.geo_key_directory {
+            tags.extend(geo_key_directory.to_tags());
+        }
+        if let Some(model_pixel_scale) = &self.model_pixel_scale {
+            tags.insert(Tag::ModelPixelScaleTag, doubles(model_pixel_scale));
+        }
+        if let Some(model_tiepoint) = &self.model_tiepoint {
+            tags.insert(Tag::ModelTiepointTag, doubles(model_tiepoint));
+        }
+        Ok(tags)
+    }
+
+    /// The range of bytes of the file spanned by this IFD's entries, its next IFD offset and its
+    /// tag values stored outside of the entries.
+    ///
+    /// This is only known for IFDs read with [`TiffMetadataReader`](crate::metadata::TiffMetadataReader).
+    pub fn metadata_range(&self) -> Option<Range<u64>> {
+        self.metadata_range.clone()
+    }
+
     /// Tags for which the tiff crate doesn't have a hard-coded enum variant.
     pub fn other_tags(&self) -> &HashMap<Tag, Value> {
         &self.other_tags
     }
 
+    /// The plugins that processed the tags they handle in this IFD.
+    ///
+    /// See [`from_tags_with_extra_tags`](Self::from_tags_with_extra_tags).
+    pub fn extra_tags(&self) -> &ExtraTagsRegistry {
+        &self.extra_tags
+    }
+
+    /// The plugin of type `T` that processed its tags in this IFD, if one was registered.
+    ///
+    /// This is a shorthand for [`extra_tags().get::<T>()`](ExtraTagsRegistry::get).
+    pub fn extra<T: ExtraTags>(&self) -> Option<&T> {
+        self.extra_tags.get::<T>()
+    }
+
+    /// Read a single unsigned integer tag.
+    ///
+    /// This checks both the tags parsed into dedicated fields and [`other_tags`][Self::other_tags],
+    /// so it can be used for one-off vendor tags. Returns `None` if the tag is absent or its value
+    /// cannot be represented as a `u32`.
+    pub fn tag_u32(&self, tag: Tag) -> Option<u32> {
+        match tag {
+            Tag::NewSubfileType => self.new_subfile_type,
+            Tag::ImageWidth => Some(self.image_width),
+            Tag::ImageLength => Some(self.image_height),
+            Tag::Compression => Some(self.compression.to_u16().into()),
+            Tag::PhotometricInterpretation => Some(self.photometric_interpretation.to_u16().into()),
+            Tag::FillOrder => Some(self.fill_order.to_u16().into()),
+            Tag::Orientation => self.orientation.map(u32::from),
+            Tag::SamplesPerPixel => Some(self.samples_per_pixel.into()),
+            Tag::RowsPerStrip => self.rows_per_strip,
+            Tag::PlanarConfiguration => Some(self.planar_configuration.to_u16().into()),
+            Tag::ResolutionUnit => self.resolution_unit.map(|x| x.to_u16().into()),
+            Tag::Predictor => self.predictor.map(|x| x.to_u16().into()),
+            Tag::TileWidth => self.tile_width,
+            Tag::TileLength => self.tile_height,
+            Tag::JPEGProc => self.jpeg_proc.map(u32::from),
+            _ => self.other_tags.get(&tag)?.clone().into_u32().ok(),
+        }
+    }
+
+    /// Read a floating point array tag.
+    ///
+    /// This checks both the tags parsed into dedicated fields and [`other_tags`][Self::other_tags].
+    /// Returns `None` if the tag is absent or its value is not a list of doubles.
+    pub fn tag_f64_vec(&self, tag: Tag) -> Option<Vec<f64>> {
+        match tag {
+            Tag::XResolution => self.x_resolution().map(|x| vec![x]),
+            Tag::YResolution => self.y_resolution().map(|y| vec![y]),
+            Tag::ModelPixelScaleTag => self.model_pixel_scale.clone(),
+            Tag::ModelTiepointTag => self.model_tiepoint.clone(),
+            Tag::YCbCrCoefficients => self.ycbcr_coefficients.map(Vec::from),
+            Tag::ReferenceBlackWhite => self.reference_black_white.map(Vec::from),
+            _ => self.other_tags.get(&tag)?.clone().into_f64_vec().ok(),
+        }
+    }
+
+    /// Read an ASCII tag.
+    ///
+    /// This checks both the tags parsed into dedicated fields and [`other_tags`][Self::other_tags].
+    /// If the tag holds several NUL-separated strings, only the first is returned. Returns `None`
+    /// if the tag is absent or is not an ASCII tag.
+    pub fn tag_str(&self, tag: Tag) -> Option<&str> {
+        match tag {
+            Tag::Unknown(DOCUMENT_NAME) => self.document_name(),
+            Tag::ImageDescription => self.image_description(),
+            Tag::Software => self.software(),
+            Tag::DateTime => self.date_time(),
+            Tag::Artist => self.artist()?.first().map(String::as_str),
+            Tag::HostComputer => self.host_computer(),
+            Tag::Copyright => self.copyright()?.first().map(String::as_str),
+            _ => match self.other_tags.get(&tag)? {
+                Value::Ascii(s) => s.split('\0').next(),
+                _ => None,
+            },
+        }
+    }
+
+    /// The XML metadata from the GDAL_METADATA tag, if present, e.g. band statistics.
+    /// <https://gdal.org/en/stable/drivers/raster/gtiff.html#metadata>
+    pub fn gdal_metadata(&self) -> Option<&str> {
+        self.tag_str(Tag::GdalMetadata)
+    }
+
+    /// The nodata value from the GDAL_NODATA tag, if present and numeric.
+    /// <https://gdal.org/en/stable/drivers/raster/gtiff.html#nodata-value>
+    pub fn nodata(&self) -> Option<f64> {
+        self.tag_str(Tag::GdalNodata)?.trim().parse().ok()
+    }
+
     /// Construct colormap from colormap tag
     pub fn colormap(&self) -> Option<HashMap<usize, [u8; 3]>> {
         fn cmap_transform(val: u16) -> u8 {
@@ -672,16 +1198,138 @@ impl ImageFileDirectory {
         }
     }
 
-    fn get_tile_byte_range(&self, x: usize, y: usize) -> Option<Range<u64>> {
+    pub(crate) fn get_tile_byte_range(&self, x: usize, y: usize) -> Option<Range<u64>> {
+        let idx = self.tile_index(x, y)?;
+        if let Some(lazy) = &self.lazy_tile_offsets {
+            return lazy.get(idx);
+        }
         let tile_offsets = self.tile_offsets.as_deref()?;
         let tile_byte_counts = self.tile_byte_counts.as_deref()?;
-        let idx = (y * self.tile_count()?.0) + x;
-        let offset = tile_offsets[idx] as usize;
+        let offset = *tile_offsets.get(idx)? as usize;
         // TODO: aiocogeo has a -1 here, but I think that was in error
-        let byte_count = tile_byte_counts[idx] as usize;
+        let byte_count = *tile_byte_counts.get(idx)? as usize;
         Some(offset as _..(offset + byte_count) as _)
     }
 
+    /// The error of fetching a tile from an IFD without tiles, pointing stripped TIFFs to
+    /// [`fetch_strip`](Self::fetch_strip).
+    pub(crate) fn not_tiled_error(&self) -> AsyncTiffError {
+        if self.strip_count().is_some() {
+            AsyncTiffError::General(
+                "Not a tiled TIFF: fetch the strips of stripped TIFFs with fetch_strip".to_string(),
+            )
+        } else {
+            AsyncTiffError::General("Not a tiled TIFF".to_string())
+        }
+    }
+
+    /// The index into the tile offsets of the tile at column `x` and row `y`.
+    pub(crate) fn tile_index(&self, x: usize, y: usize) -> Option<usize> {
+        Some((y * self.tile_count()?.0) + x)
+    }
+
+    /// The byte range of the chunk at `index` into the tile or strip offsets.
+    pub(crate) fn chunk_byte_range(&self, index: usize) -> Option<Range<u64>> {
+        if let Some(lazy) = &self.lazy_tile_offsets {
+            return lazy.get(index);
+        }
+        let (offsets, byte_counts) = match (&self.tile_offsets, &self.tile_byte_counts) {
+            (Some(offsets), Some(byte_counts)) => (offsets, byte_counts),
+            _ => (
+                self.strip_offsets.as_ref()?,
+                self.strip_byte_counts.as_ref()?,
+            ),
+        };
+        let offset = *offsets.get(index)?;
+        Some(offset..offset + *byte_counts.get(index)?)
+    }
+
+    /// The byte range of every tile or strip, or `None` if the IFD has neither.
+    ///
+    /// Fails if the tile offsets are [lazy](Self::has_lazy_tile_offsets) and some of them weren't
+    /// loaded, as they can't be read here without a reader.
+    pub(crate) fn chunk_byte_ranges(&self) -> AsyncTiffResult<Option<Vec<Range<u64>>>> {
+        if let Some(lazy) = &self.lazy_tile_offsets {
+            return lazy.loaded_ranges().map(Some).ok_or_else(|| {
+                AsyncTiffError::General(
+                    "Tile offsets are read lazily: load them with load_tile_offsets first"
+                        .to_string(),
+                )
+            });
+        }
+        let (offsets, byte_counts) = match (&self.tile_offsets, &self.tile_byte_counts) {
+            (Some(offsets), Some(byte_counts)) => (offsets, byte_counts),
+            _ => match (&self.strip_offsets, &self.strip_byte_counts) {
+                (Some(offsets), Some(byte_counts)) => (offsets, byte_counts),
+                _ => return Ok(None),
+            },
+        };
+        Ok(Some(
+            offsets
+                .iter()
+                .zip(byte_counts.iter())
+                .map(|(&offset, &byte_count)| offset..offset.saturating_add(byte_count))
+                .collect(),
+        ))
+    }
+
+    /// Whether the tiles or strips are stored in the order of [`chunk_index`](Self::chunk_index),
+    /// each starting at or after the end of the previous one, as in Cloud Optimized GeoTIFFs.
+    ///
+    /// Sparse chunks without bytes are ignored. Many chunks of such images can be fetched with a
+    /// few linear requests; see
+    /// [`ReadWindowOptions::with_sequential_reads`](crate::ReadWindowOptions::with_sequential_reads).
+    ///
+    /// Fails if the tile offsets are [lazy](Self::has_lazy_tile_offsets) and weren't
+    /// [loaded](Self::load_tile_offsets).
+    pub fn has_sequential_chunks(&self) -> AsyncTiffResult<bool> {
+        let Some(ranges) = self.chunk_byte_ranges()? else {
+            return Ok(false);
+        };
+        let mut end = 0;
+        for range in ranges {
+            if range.is_empty() {
+                continue;
+            }
+            if range.start < end {
+                return Ok(false);
+            }
+            end = range.end;
+        }
+        Ok(true)
+    }
+
+    /// Apply `transform` to the compressed bytes of every tile or strip fetched from this IFD,
+    /// before they are decoded.
+    pub fn with_byte_transform(mut self, transform: Arc<dyn ByteTransform>) -> Self {
+        self.byte_transform = Some(transform);
+        self
+    }
+
+    /// The transform applied to fetched tiles and strips, if any.
+    pub fn byte_transform(&self) -> Option<&Arc<dyn ByteTransform>> {
+        self.byte_transform.as_ref()
+    }
+
+    /// Record the byte range and fetch duration of every tile or strip fetched from this IFD,
+    /// available with [`Tile::fetch_info`].
+    ///
+    /// This is off by default, as it reads the clock around every fetch.
+    pub fn with_fetch_info(mut self, record: bool) -> Self {
+        self.record_fetch_info = record;
+        self
+    }
+
+    /// Whether fetched tiles and strips record their [`FetchInfo`].
+    pub fn records_fetch_info(&self) -> bool {
+        self.record_fetch_info
+    }
+
+    /// Start timing a fetch, if fetch info is recorded.
+    fn start_fetch(&self) -> Option<Instant> {
+        self.record_fetch_info.then(Instant::now)
+    }
+
     /// Fetch the tile located at `x` column and `y` row using the provided reader.
     pub async fn fetch_tile(
         &self,
@@ -690,18 +1338,57 @@ impl ImageFileDirectory {
         reader: &dyn AsyncFileReader,
     ) -> AsyncTiffResult<Tile> {
         let range = self
-            .get_tile_byte_range(x, y)
-            .ok_or(AsyncTiffError::General("Not a tiled TIFF".to_string()))?;
-        let compressed_bytes = reader.get_bytes(range).await?;
-        Ok(Tile {
+            .fetch_tile_byte_range(x, y, reader)
+            .await?
+            .ok_or_else(|| self.not_tiled_error())?;
+        let offset = range.start;
+        let start = self.start_fetch();
+        let compressed_bytes = reader.get_bytes(range.clone()).await?;
+        let duration = start.map(|start| start.elapsed());
+        let mut tile = self.new_tile(x, y, offset, compressed_bytes)?;
+        tile.fetch_info = fetch_info(duration, range, 1);
+        Ok(tile)
+    }
+
+    /// Whether the compressed bytes of the tiles of this IFD can be served as images as is, as
+    /// fetched by [`fetch_tile_raw`](Self::fetch_tile_raw).
+    pub fn tile_payload(&self) -> TilePayload {
+        TilePayload::new(self.compression, self.jpeg_tables.as_deref())
+    }
+
+    /// Fetch the compressed bytes of the tile located at `x` column and `y` row, without the
+    /// information needed to decode it.
+    ///
+    /// This is meant for proxies that re-serve compressed tiles as they are, e.g. JPEG tiles as
+    /// HTTP responses. The [byte transform](Self::with_byte_transform), if any, is still applied.
+    pub async fn fetch_tile_raw(
+        &self,
+        x: usize,
+        y: usize,
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<RawTile> {
+        let (x_count, y_count) = self.tile_count().ok_or_else(|| self.not_tiled_error())?;
+        if x >= x_count || y >= y_count {
+            return Err(AsyncTiffError::TileIndexError(x as u32, y as u32));
+        }
+        let range = self
+            .fetch_tile_byte_range(x, y, reader)
+            .await?
+            .ok_or_else(|| self.not_tiled_error())?;
+        let start = self.start_fetch();
+        let compressed_bytes = reader.get_bytes(range.clone()).await?;
+        let duration = start.map(|start| start.elapsed());
+        let compressed_bytes = match &self.byte_transform {
+            Some(transform) => transform.transform(x, y, range.start, compressed_bytes)?,
+            None => compressed_bytes,
+        };
+        Ok(RawTile {
             x,
             y,
-            predictor: self.predictor.unwrap_or(Predictor::None),
-            predictor_info: PredictorInfo::from_ifd(self),
             compressed_bytes,
             compression_method: self.compression,
-            photometric_interpretation: self.photometric_interpretation,
             jpeg_tables: self.jpeg_tables.clone(),
+            fetch_info: fetch_info(duration, range, 1),
         })
     }
 
@@ -713,8 +1400,8 @@ impl ImageFileDirectory {
         reader: &dyn AsyncFileReader,
     ) -> AsyncTiffResult<Vec<Tile>> {
         assert_eq!(x.len(), y.len(), "x and y should have same len");
-
-        let predictor_info = PredictorInfo::from_ifd(self);
+        let indices = x.iter().zip(y).filter_map(|(&x, &y)| self.tile_index(x, y));
+        self.load_lazy_tile_offsets(indices, reader).await?;
 
         // 1: Get all the byte ranges for all tiles
         let byte_ranges = x
@@ -722,31 +1409,360 @@ impl ImageFileDirectory {
             .zip(y)
             .map(|(x, y)| {
                 self.get_tile_byte_range(*x, *y)
-                    .ok_or(AsyncTiffError::General("Not a tiled TIFF".to_string()))
+                    .ok_or_else(|| self.not_tiled_error())
             })
             .collect::<AsyncTiffResult<Vec<_>>>()?;
 
         // 2: Fetch using `get_byte_ranges`
-        let buffers = reader.get_byte_ranges(byte_ranges).await?;
+        let start = self.start_fetch();
+        let buffers = reader.get_byte_ranges(byte_ranges.clone()).await?;
+        let duration = start.map(|start| start.elapsed());
 
         // 3: Create tile objects
+        let batch_len = byte_ranges.len();
         let mut tiles = vec![];
-        for ((compressed_bytes, &x), &y) in buffers.into_iter().zip(x).zip(y) {
-            let tile = Tile {
-                x,
-                y,
-                predictor: self.predictor.unwrap_or(Predictor::None),
-                predictor_info,
-                compressed_bytes,
-                compression_method: self.compression,
-                photometric_interpretation: self.photometric_interpretation,
-                jpeg_tables: self.jpeg_tables.clone(),
+        for (((compressed_bytes, range), &x), &y) in
+            buffers.into_iter().zip(byte_ranges).zip(x).zip(y)
+        {
+            let mut tile = self.new_tile(x, y, range.start, compressed_bytes)?;
+            tile.fetch_info = fetch_info(duration, range, batch_len);
+            tiles.push(tile);
+        }
+        Ok(tiles)
+    }
+
+    /// Fetch the tiles located at `x` column and `y` row, with a separate result per tile.
+    ///
+    /// Unlike [`fetch_tiles`](Self::fetch_tiles), a tile that can't be fetched, e.g. because its
+    /// index is out of range or its byte range can't be read, doesn't fail the whole batch. The
+    /// tiles are first fetched together with
+    /// [`get_byte_ranges`](AsyncFileReader::get_byte_ranges); if that fails, each tile is
+    /// fetched on its own so that the error is attributed to the tiles that caused it.
+    ///
+    /// The output is in the same order as the input tiles.
+    pub async fn fetch_tiles_isolated(
+        &self,
+        x: &[usize],
+        y: &[usize],
+        reader: &dyn AsyncFileReader,
+    ) -> Vec<AsyncTiffResult<Tile>> {
+        assert_eq!(x.len(), y.len(), "x and y should have same len");
+        let indices = x.iter().zip(y).filter_map(|(&x, &y)| self.tile_index(x, y));
+        if let Err(err) = self.load_lazy_tile_offsets(indices, reader).await {
+            let message = format!("Failed to load tile offsets: {err}");
+            return x
+                .iter()
+                .map(|_| Err(AsyncTiffError::General(message.clone())))
+                .collect();
+        }
+
+        let byte_ranges = x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| {
+                let (x_count, y_count) = self.tile_count().ok_or_else(|| self.not_tiled_error())?;
+                if *x >= x_count || *y >= y_count {
+                    return Err(AsyncTiffError::TileIndexError(*x as u32, *y as u32));
+                }
+                self.get_tile_byte_range(*x, *y)
+                    .ok_or_else(|| self.not_tiled_error())
+            })
+            .collect::<Vec<_>>();
+
+        let valid = byte_ranges
+            .iter()
+            .filter_map(|range| range.as_ref().ok().cloned())
+            .collect::<Vec<_>>();
+        let batch_len = valid.len();
+        let start = self.start_fetch();
+        let mut buffers = match reader.get_byte_ranges(valid).await {
+            Ok(buffers) => buffers.into_iter().map(Ok).collect(),
+            Err(_) => {
+                join_all(byte_ranges.iter().filter_map(|range| {
+                    let range = range.as_ref().ok()?.clone();
+                    Some(reader.get_bytes(range))
+                }))
+                .await
+            }
+        }
+        .into_iter();
+        let duration = start.map(|start| start.elapsed());
+
+        byte_ranges
+            .into_iter()
+            .zip(x)
+            .zip(y)
+            .map(|((range, &x), &y)| {
+                let range = range?;
+                let compressed_bytes = buffers.next().unwrap_or_else(|| {
+                    Err(AsyncTiffError::General(
+                        "Reader returned too few byte ranges".to_string(),
+                    ))
+                })?;
+                let mut tile = self.new_tile(x, y, range.start, compressed_bytes)?;
+                tile.fetch_info = fetch_info(duration, range, batch_len);
+                Ok(tile)
+            })
+            .collect()
+    }
+
+    /// Fetch the tiles located at `x` column and `y` row, handling tiles whose byte range extends
+    /// past the end of the `file_size`-byte file according to `truncated`.
+    ///
+    /// Byte ranges are clamped to the end of the file, so that a truncated upload fails with
+    /// [`AsyncTiffError::TruncatedTile`] or decodes best-effort, rather than surfacing as an
+    /// opaque I/O error from the reader mid-batch.
+    pub async fn fetch_tiles_with_file_size(
+        &self,
+        x: &[usize],
+        y: &[usize],
+        reader: &dyn AsyncFileReader,
+        file_size: u64,
+        truncated: TruncatedTiles,
+    ) -> AsyncTiffResult<Vec<Tile>> {
+        assert_eq!(x.len(), y.len(), "x and y should have same len");
+        let indices = x.iter().zip(y).filter_map(|(&x, &y)| self.tile_index(x, y));
+        self.load_lazy_tile_offsets(indices, reader).await?;
+
+        let byte_ranges = x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| {
+                let range = self
+                    .get_tile_byte_range(*x, *y)
+                    .ok_or_else(|| self.not_tiled_error())?;
+                if range.end > file_size && truncated == TruncatedTiles::Error {
+                    return Err(AsyncTiffError::TruncatedTile {
+                        x: *x,
+                        y: *y,
+                        end: range.end,
+                        file_size,
+                    });
+                }
+                let is_truncated = range.end > file_size;
+                Ok((
+                    range.start.min(file_size)..range.end.min(file_size),
+                    is_truncated,
+                ))
+            })
+            .collect::<AsyncTiffResult<Vec<_>>>()?;
+
+        // Tiles starting past the end of the file have no bytes to fetch at all.
+        let non_empty = byte_ranges
+            .iter()
+            .filter(|(range, _)| !range.is_empty())
+            .map(|(range, _)| range.clone())
+            .collect::<Vec<_>>();
+        let batch_len = non_empty.len();
+        let start = self.start_fetch();
+        let mut buffers = reader.get_byte_ranges(non_empty).await?.into_iter();
+        let duration = start.map(|start| start.elapsed());
+
+        let truncation = match truncated {
+            TruncatedTiles::Error | TruncatedTiles::Partial => Truncated::Partial,
+            TruncatedTiles::FillNodata => Truncated::Fill(self.nodata().unwrap_or(0.0)),
+        };
+        let mut tiles = vec![];
+        for (((range, is_truncated), &x), &y) in byte_ranges.into_iter().zip(x).zip(y) {
+            let compressed_bytes = if range.is_empty() {
+                Bytes::new()
+            } else {
+                buffers.next().unwrap_or_default()
             };
+            let mut tile = self.new_tile(x, y, range.start, compressed_bytes)?;
+            if !range.is_empty() {
+                tile.fetch_info = fetch_info(duration, range, batch_len);
+            }
+            if is_truncated {
+                tile.truncated = Some(truncation);
+            }
             tiles.push(tile);
         }
         Ok(tiles)
     }
 
+    /// The number of strips in this IFD, or `None` if this is not a stripped TIFF.
+    ///
+    /// With [`PlanarConfiguration::Planar`], this counts the strips of every sample plane.
+    pub fn strip_count(&self) -> Option<usize> {
+        if self.tile_width.is_some() {
+            return None;
+        }
+        Some(self.strip_offsets.as_ref()?.len())
+    }
+
+    fn get_strip_byte_range(&self, index: usize) -> Option<Range<u64>> {
+        let offset = *self.strip_offsets.as_deref()?.get(index)?;
+        let byte_count = *self.strip_byte_counts.as_deref()?.get(index)?;
+        Some(offset..offset + byte_count)
+    }
+
+    /// Fetch the strip at `index` using the provided reader.
+    ///
+    /// The strip is returned as an image-width [`Tile`], whose [`y`](Tile::y) is the strip's row
+    /// within its sample plane.
+    pub async fn fetch_strip(
+        &self,
+        index: usize,
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<Tile> {
+        let range = self.strip_byte_range(index)?;
+        let offset = range.start;
+        let start = self.start_fetch();
+        let compressed_bytes = reader.get_bytes(range.clone()).await?;
+        let duration = start.map(|start| start.elapsed());
+        let mut tile = self.new_tile(0, self.strip_row(index), offset, compressed_bytes)?;
+        tile.fetch_info = fetch_info(duration, range, 1);
+        Ok(tile)
+    }
+
+    /// Fetch the strips at `indices` using the provided reader.
+    pub async fn fetch_strips(
+        &self,
+        indices: &[usize],
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<Vec<Tile>> {
+        let byte_ranges = indices
+            .iter()
+            .map(|index| self.strip_byte_range(*index))
+            .collect::<AsyncTiffResult<Vec<_>>>()?;
+        let batch_len = byte_ranges.len();
+        let start = self.start_fetch();
+        let buffers = reader.get_byte_ranges(byte_ranges.clone()).await?;
+        let duration = start.map(|start| start.elapsed());
+        buffers
+            .into_iter()
+            .zip(byte_ranges)
+            .zip(indices)
+            .map(|((compressed_bytes, range), index)| {
+                let row = self.strip_row(*index);
+                let mut tile = self.new_tile(0, row, range.start, compressed_bytes)?;
+                tile.fetch_info = fetch_info(duration, range, batch_len);
+                Ok(tile)
+            })
+            .collect()
+    }
+
+    fn strip_byte_range(&self, index: usize) -> AsyncTiffResult<Range<u64>> {
+        if self.strip_count().is_none() {
+            return Err(AsyncTiffError::General("Not a stripped TIFF".to_string()));
+        }
+        self.get_strip_byte_range(index)
+            .ok_or(AsyncTiffError::TileIndexError(
+                index as u32,
+                self.strip_count().unwrap_or_default() as u32,
+            ))
+    }
+
+    /// The row of the strip at `index` within its sample plane.
+    fn strip_row(&self, index: usize) -> usize {
+        let strip_height = self.strip_height().unwrap_or(self.image_height);
+        let strips_per_plane = self.image_height.div_ceil(strip_height).max(1) as usize;
+        index % strips_per_plane
+    }
+
+    pub(crate) fn new_tile(
+        &self,
+        x: usize,
+        y: usize,
+        offset: u64,
+        compressed_bytes: Bytes,
+    ) -> AsyncTiffResult<Tile> {
+        let compressed_bytes = match &self.byte_transform {
+            Some(transform) => transform.transform(x, y, offset, compressed_bytes)?,
+            None => compressed_bytes,
+        };
+        Ok(Tile {
+            x,
+            y,
+            predictor: self.predictor.unwrap_or(Predictor::None),
+            predictor_info: PredictorInfo::from_ifd(self),
+            compressed_bytes,
+            compression_method: self.compression,
+            photometric_interpretation: self.photometric_interpretation,
+            jpeg_tables: self.jpeg_tables.clone(),
+            ycbcr_conversion: YCbCrConversion::from_ifd(self),
+            fill_order: self.fill_order,
+            fax_options: self.fax_options(),
+            truncated: None,
+            fetch_info: None,
+        })
+    }
+
+    /// The T4Options of CCITT Group 3 images or the T6Options of Group 4 images, or 0 if absent.
+    fn fax_options(&self) -> u32 {
+        let tag = match self.compression {
+            CompressionMethod::Fax3 => Tag::T4Options,
+            CompressionMethod::Fax4 => Tag::T6Options,
+            _ => return 0,
+        };
+        self.tag_u32(tag).unwrap_or(0)
+    }
+
+    /// An estimate of the heap memory held by this IFD, in bytes.
+    ///
+    /// This covers the tag values, such as tile offsets and byte counts, the colormap and
+    /// [`other_tags`](Self::other_tags), but not the `size_of::<ImageFileDirectory>()` bytes of
+    /// the struct itself. Buffers shared between clones, like the JPEGTables and the tile
+    /// offsets, are counted in full by every IFD that references them, so this can be used to
+    /// enforce an upper bound on the memory of a metadata cache.
+    pub fn estimated_heap_size(&self) -> usize {
+        fn vec_size<T>(v: &[T]) -> usize {
+            std::mem::size_of_val(v)
+        }
+        fn opt_vec_size<T>(v: &Option<Vec<T>>) -> usize {
+            v.as_deref().map_or(0, vec_size)
+        }
+        fn opt_slice_size<T>(v: &Option<Arc<[T]>>) -> usize {
+            v.as_deref().map_or(0, vec_size)
+        }
+        fn opt_str_size(s: &Option<String>) -> usize {
+            s.as_ref().map_or(0, String::capacity)
+        }
+        fn opt_strings_size(v: &Option<Vec<String>>) -> usize {
+            v.as_deref().map_or(0, |v| {
+                vec_size(v) + v.iter().map(String::capacity).sum::<usize>()
+            })
+        }
+
+        let other_tags = self.other_tags.capacity()
+            * (std::mem::size_of::<Tag>() + std::mem::size_of::<Value>())
+            + self
+                .other_tags
+                .values()
+                .map(Value::heap_size)
+                .sum::<usize>();
+
+        vec_size(&self.bits_per_sample)
+            + vec_size(&self.sample_format)
+            + vec_size(&self.warnings)
+            + opt_str_size(&self.document_name)
+            + opt_str_size(&self.image_description)
+            + opt_str_size(&self.software)
+            + opt_str_size(&self.date_time)
+            + opt_str_size(&self.date_time_original)
+            + opt_str_size(&self.host_computer)
+            + opt_strings_size(&self.artist)
+            + opt_strings_size(&self.copyright)
+            + opt_slice_size(&self.strip_offsets)
+            + opt_slice_size(&self.strip_byte_counts)
+            + opt_vec_size(&self.min_sample_value)
+            + opt_vec_size(&self.max_sample_value)
+            + opt_slice_size(&self.color_map)
+            + opt_slice_size(&self.tile_offsets)
+            + opt_slice_size(&self.tile_byte_counts)
+            + opt_vec_size(&self.extra_samples)
+            + opt_vec_size(&self.model_pixel_scale)
+            + opt_vec_size(&self.model_tiepoint)
+            + self.jpeg_tables.as_ref().map_or(0, Bytes::len)
+            + self
+                .geo_key_directory
+                .as_ref()
+                .map_or(0, GeoKeyDirectory::heap_size)
+            + self.raw_geo_keys.as_ref().map_or(0, RawGeoKeys::heap_size)
+            + other_tags
+    }
+
     /// Return the number of x/y tiles in the IFD
     /// Returns `None` if this is not a tiled TIFF
     pub fn tile_count(&self) -> Option<(usize, usize)> {
@@ -754,4 +1770,727 @@ impl ImageFileDirectory {
         let y_count = (self.image_height as f64 / self.tile_height? as f64).ceil();
         Some((x_count as usize, y_count as usize))
     }
+
+    /// The index into the tile or strip offsets of the chunk at column `x` and row `y` of sample
+    /// plane `band`.
+    ///
+    /// Chunks are ordered row by row within each plane, and with
+    /// [`PlanarConfiguration::Planar`] all chunks of the first sample come before those of the
+    /// second, and so on. Strips are in column 0, and `band` must be 0 for chunky images.
+    /// Returns `None` if the position is out of range.
+    pub fn chunk_index(&self, x: usize, y: usize, band: usize) -> Option<usize> {
+        let (x_count, y_count) = match self.tile_count() {
+            Some(count) => count,
+            None => {
+                let strip_height = self.strip_height().unwrap_or(self.image_height).max(1);
+                (1, self.image_height.div_ceil(strip_height).max(1) as usize)
+            }
+        };
+        let planes = match self.planar_configuration {
+            PlanarConfiguration::Chunky => 1,
+            PlanarConfiguration::Planar => self.samples_per_pixel as usize,
+        };
+        if x >= x_count || y >= y_count || band >= planes {
+            return None;
+        }
+        Some((band * y_count + y) * x_count + x)
+    }
+
+    /// The number of samples per pixel within a single tile or strip.
+    ///
+    /// With [`PlanarConfiguration::Planar`], every tile or strip holds a single sample plane, so
+    /// this is 1 regardless of [`samples_per_pixel`](Self::samples_per_pixel).
+    pub fn chunk_samples_per_pixel(&self) -> u16 {
+        match self.planar_configuration {
+            PlanarConfiguration::Chunky => self.samples_per_pixel,
+            PlanarConfiguration::Planar => 1,
+        }
+    }
+
+    /// The number of bytes in each decoded row of a full tile or strip, including any padding
+    /// columns.
+    ///
+    /// With [`PlanarConfiguration::Planar`], this is the row stride of a single sample plane.
+    pub fn chunk_row_bytes(&self) -> usize {
+        let width = self.tile_width.unwrap_or(self.image_width) as usize;
+        let bits_per_sample = self.bits_per_sample.first().copied().unwrap_or(1) as usize;
+        (width * bits_per_sample * self.chunk_samples_per_pixel() as usize).div_ceil(8)
+    }
+
+    /// The `(x, y)` index of the tile containing the pixel at column `col` and row `row`.
+    ///
+    /// Returns `None` if this is not a tiled TIFF or the pixel is outside the image.
+    pub fn tile_index_for_pixel(&self, col: f64, row: f64) -> Option<(usize, usize)> {
+        if !(0.0..self.image_width as f64).contains(&col)
+            || !(0.0..self.image_height as f64).contains(&row)
+        {
+            return None;
+        }
+        let x = (col / self.tile_width? as f64).floor();
+        let y = (row / self.tile_height? as f64).floor();
+        Some((x as usize, y as usize))
+    }
+
+    /// The `(x, y)` index of the tile containing the point `(x, y)` in the coordinate reference
+    /// system of `transform`.
+    ///
+    /// Returns `None` if this is not a tiled TIFF, `transform` is not invertible or the point is
+    /// outside the image.
+    pub fn tile_index_for_point(
+        &self,
+        x: f64,
+        y: f64,
+        transform: &AffineTransform,
+    ) -> Option<(usize, usize)> {
+        let (col, row) = transform.inverse()?.apply(x, y);
+        self.tile_index_for_pixel(col, row)
+    }
+
+    /// The `(minx, miny, maxx, maxy)` extent of the tile at `(x, y)` in the coordinate reference
+    /// system of `transform`.
+    ///
+    /// Edge tiles are clipped to the image. Returns `None` if this is not a tiled TIFF or the tile
+    /// is outside the image.
+    pub fn tile_bounds(
+        &self,
+        x: usize,
+        y: usize,
+        transform: &AffineTransform,
+    ) -> Option<(f64, f64, f64, f64)> {
+        let (x_count, y_count) = self.tile_count()?;
+        if x >= x_count || y >= y_count {
+            return None;
+        }
+        let tile_width = self.tile_width? as f64;
+        let tile_height = self.tile_height? as f64;
+        let col_start = x as f64 * tile_width;
+        let row_start = y as f64 * tile_height;
+        let col_end = (col_start + tile_width).min(self.image_width as f64);
+        let row_end = (row_start + tile_height).min(self.image_height as f64);
+
+        let corners = [
+            (col_start, row_start),
+            (col_end, row_start),
+            (col_start, row_end),
+            (col_end, row_end),
+        ]
+        .map(|(col, row)| transform.apply(col, row));
+        let mut bounds = (
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        );
+        for (cx, cy) in corners {
+            bounds.0 = bounds.0.min(cx);
+            bounds.1 = bounds.1.min(cy);
+            bounds.2 = bounds.2.max(cx);
+            bounds.3 = bounds.3.max(cy);
+        }
+        Some(bounds)
+    }
+}
+
+/// A one-line summary of the image, e.g. `1024x1024 u16, 1 band, BlackIsZero, Deflate, tiled
+/// 256x256 (4x4), EPSG:32612`.
+impl fmt::Display for ImageFileDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{} ", self.image_width, self.image_height)?;
+        let bits = self.bits_per_sample.first().copied().unwrap_or(1);
+        let format = self.sample_format.first().copied();
+        let uniform = self.bits_per_sample.iter().all(|b| *b == bits)
+            && self.sample_format.iter().all(|s| Some(*s) == format);
+        match format {
+            _ if !uniform => write!(f, "mixed {:?} bits", self.bits_per_sample)?,
+            Some(SampleFormat::Int) => write!(f, "i{bits}")?,
+            Some(SampleFormat::IEEEFP) => write!(f, "f{bits}")?,
+            Some(SampleFormat::Uint) | None => write!(f, "u{bits}")?,
+            Some(other) => write!(f, "{other:?} {bits}-bit")?,
+        }
+        let bands = self.samples_per_pixel;
+        let plural = if bands == 1 { "" } else { "s" };
+        write!(
+            f,
+            ", {bands} band{plural}, {:?}, {:?}",
+            self.photometric_interpretation, self.compression
+        )?;
+
+        if let (Some(tile_width), Some(tile_height), Some((x_count, y_count))) =
+            (self.tile_width, self.tile_height, self.tile_count())
+        {
+            write!(
+                f,
+                ", tiled {tile_width}x{tile_height} ({x_count}x{y_count})"
+            )?;
+        } else if let Some(strip_offsets) = &self.strip_offsets {
+            write!(f, ", {} strips", strip_offsets.len())?;
+        }
+        if self.is_mask() {
+            write!(f, ", mask")?;
+        } else if self.new_subfile_type.is_some_and(|t| t & 1 != 0) {
+            write!(f, ", overview")?;
+        }
+        if let Some(epsg) = self.geo_key_directory.as_ref().and_then(|g| g.epsg_code()) {
+            write!(f, ", EPSG:{epsg}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse an XResolution or YResolution value into a (numerator, denominator) pair.
+///
+/// The spec requires RATIONAL, but some writers use an integer type instead. Floating point
+/// values are approximated with a denominator of 10000 so that they are not rejected outright.
+fn resolution_from_value(tag: Tag, value: Value) -> TiffResult<(u32, u32)> {
+    match value {
+        Value::Rational(n, d) => Ok((n, d)),
+        Value::RationalBig(n, d) => Ok((u32::try_from(n)?, u32::try_from(d)?)),
+        Value::Byte(_) | Value::Short(_) | Value::Unsigned(_) | Value::UnsignedBig(_) => {
+            Ok((value.into_u32()?, 1))
+        }
+        Value::Float(v) if v.is_finite() && v >= 0.0 => float_to_rational(v.into()),
+        Value::Double(v) if v.is_finite() && v >= 0.0 => float_to_rational(v),
+        _ => Err(TiffError::FormatError(
+            TiffFormatError::InvalidTagValueType(tag),
+        )),
+    }
+}
+
+/// Parse a fixed number of RATIONAL values, such as YCbCrCoefficients or ReferenceBlackWhite.
+fn rationals_from_value<const N: usize>(tag: Tag, value: Value) -> TiffResult<[f64; N]> {
+    let invalid = || TiffError::FormatError(TiffFormatError::InvalidTagValueType(tag));
+    let values = match value {
+        Value::List(values) => values,
+        value => vec![value],
+    };
+    let values = values
+        .into_iter()
+        .map(|value| match value {
+            Value::Rational(n, d) => Ok(n as f64 / d as f64),
+            Value::RationalBig(n, d) => Ok(n as f64 / d as f64),
+            Value::SRational(n, d) => Ok(n as f64 / d as f64),
+            Value::SRationalBig(n, d) => Ok(n as f64 / d as f64),
+            Value::Float(v) => Ok(v.into()),
+            Value::Double(v) => Ok(v),
+            value => Ok(value.into_u32()? as f64),
+        })
+        .collect::<TiffResult<Vec<_>>>()?;
+    values.try_into().map_err(|_| invalid())
+}
+
+fn float_to_rational(v: f64) -> TiffResult<(u32, u32)> {
+    const DENOMINATOR: u32 = 10_000;
+    let n = (v * DENOMINATOR as f64).round();
+    if n > u32::MAX as f64 {
+        Err(TiffError::IntSizeError)
+    } else {
+        Ok((n as u32, DENOMINATOR))
+    }
+}
+
+fn rational_to_f64((n, d): (u32, u32)) -> Option<f64> {
+    (d != 0).then(|| n as f64 / d as f64)
+}
+
+/// The [`FetchInfo`] of a tile read from `byte_range` by a fetch that took `duration`, if timed.
+fn fetch_info(
+    duration: Option<Duration>,
+    byte_range: Range<u64>,
+    batch_len: usize,
+) -> Option<FetchInfo> {
+    duration.map(|duration| FetchInfo {
+        byte_range,
+        duration,
+        batch_len,
+    })
+}
+
+/// Parse the GeoKeyDirectoryTag `data`, whose keys may refer to values in the GeoAsciiParamsTag
+/// and GeoDoubleParamsTag, into the typed directory and the raw keys.
+///
+/// Keys the typed directory doesn't model are only kept in the raw keys. Malformed keys fail, or
+/// if `lenient` are skipped with a warning.
+fn parse_geo_keys(
+    data: &[u16],
+    geo_ascii_params: Option<String>,
+    geo_double_params: Option<Vec<f64>>,
+    lenient: bool,
+    warnings: &mut Vec<IfdWarning>,
+) -> AsyncTiffResult<(Option<GeoKeyDirectory>, Option<RawGeoKeys>)> {
+    let invalid = |reason: String| {
+        AsyncTiffError::from(TiffError::FormatError(TiffFormatError::Format(reason)))
+    };
+    let header = match data {
+        [1, 1, _, number_of_keys, ..] => Ok(*number_of_keys as usize),
+        [version, revision, _, _, ..] => Err(format!(
+            "Unsupported GeoKeyDirectory version {version}.{revision}, expected 1.1"
+        )),
+        _ => Err(format!(
+            "GeoKeyDirectory of {} values is shorter than its header",
+            data.len()
+        )),
+    };
+    let number_of_keys = match header {
+        Ok(number_of_keys) => number_of_keys,
+        Err(reason) if lenient => {
+            warnings.push(IfdWarning::InvalidGeoKeyDirectory { reason });
+            return Ok((None, None));
+        }
+        Err(reason) => return Err(invalid(reason)),
+    };
+
+    let entries = data[4..].chunks_exact(4);
+    if entries.len() < number_of_keys {
+        let reason = format!(
+            "GeoKeyDirectory has {} keys, expected {number_of_keys}",
+            entries.len()
+        );
+        if !lenient {
+            return Err(invalid(reason));
+        }
+        warnings.push(IfdWarning::InvalidGeoKeyDirectory { reason });
+    }
+    let entries = entries
+        .take(number_of_keys)
+        .map(|entry| GeoKeyEntry {
+            key_id: entry[0],
+            tag_location: entry[1],
+            count: entry[2],
+            value_offset: entry[3],
+        })
+        .collect();
+    let raw_geo_keys = RawGeoKeys::new(
+        [data[0], data[1], data[2]],
+        entries,
+        geo_double_params,
+        geo_ascii_params,
+    );
+
+    let mut tags = HashMap::with_capacity(number_of_keys);
+    for entry in raw_geo_keys.entries() {
+        let Ok(tag) = GeoKeyTag::try_from_primitive(entry.key_id) else {
+            continue;
+        };
+        match raw_geo_keys.try_value(entry) {
+            Ok(Some(value)) => {
+                tags.insert(tag, value);
+            }
+            Ok(None) => {}
+            Err(reason) if lenient => warnings.push(IfdWarning::InvalidGeoKey {
+                key: entry.key_id,
+                reason,
+            }),
+            Err(reason) => return Err(invalid(format!("GeoKey {}: {reason}", entry.key_id))),
+        }
+    }
+    match GeoKeyDirectory::from_tags(tags) {
+        Ok(directory) => Ok((Some(directory), Some(raw_geo_keys))),
+        Err(err) if lenient => {
+            warnings.push(IfdWarning::InvalidGeoKeyDirectory {
+                reason: err.to_string(),
+            });
+            Ok((None, Some(raw_geo_keys)))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stripped_tags(rows_per_strip: Option<u32>) -> HashMap<Tag, Value> {
+        let mut tags = HashMap::from([
+            (Tag::ImageWidth, Value::Unsigned(100)),
+            (Tag::ImageLength, Value::Unsigned(50)),
+            (Tag::BitsPerSample, Value::Short(8)),
+            (Tag::PhotometricInterpretation, Value::Short(1)),
+            (Tag::SamplesPerPixel, Value::Short(1)),
+            (Tag::StripOffsets, Value::Unsigned(8)),
+            (Tag::StripByteCounts, Value::Unsigned(5000)),
+        ]);
+        if let Some(rows_per_strip) = rows_per_strip {
+            tags.insert(Tag::RowsPerStrip, Value::Unsigned(rows_per_strip));
+        }
+        tags
+    }
+
+    #[test]
+    fn test_from_tags_required() {
+        for tag in [
+            Tag::ImageWidth,
+            Tag::ImageLength,
+            Tag::PhotometricInterpretation,
+        ] {
+            let mut tags = stripped_tags(None);
+            tags.remove(&tag);
+            let err = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap_err();
+            assert!(matches!(
+                err,
+                AsyncTiffError::InternalTIFFError(TiffError::FormatError(
+                    TiffFormatError::RequiredTagNotFound(t)
+                )) if t == tag
+            ));
+        }
+
+        let mut tags = stripped_tags(None);
+        tags.remove(&Tag::BitsPerSample);
+        tags.remove(&Tag::SamplesPerPixel);
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.samples_per_pixel(), 1);
+        assert_eq!(ifd.bits_per_sample(), [1]);
+    }
+
+    #[test]
+    fn test_rows_per_strip_defaults() {
+        let ifd = ImageFileDirectory::from_tags(stripped_tags(Some(16)), Endianness::LittleEndian)
+            .unwrap();
+        assert_eq!(ifd.strip_height(), Some(16));
+        assert!(ifd.warnings().is_empty());
+
+        for rows_per_strip in [None, Some(0)] {
+            let tags = stripped_tags(rows_per_strip);
+            let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+            assert_eq!(ifd.rows_per_strip(), rows_per_strip);
+            assert_eq!(ifd.strip_height(), Some(50));
+            assert_eq!(
+                ifd.warnings(),
+                [IfdWarning::RowsPerStripDefaulted {
+                    found: rows_per_strip
+                }]
+            );
+            // This used to panic
+            PredictorInfo::from_ifd(&ifd);
+        }
+
+        let mut tags = stripped_tags(Some(u32::MAX));
+        tags.remove(&Tag::StripByteCounts);
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.strip_height(), Some(50));
+        assert_eq!(ifd.warnings(), [IfdWarning::MissingStripByteCounts]);
+    }
+
+    #[test]
+    fn test_chunk_row_bytes() {
+        let mut tags = stripped_tags(None);
+        tags.insert(Tag::SamplesPerPixel, Value::Short(3));
+        tags.insert(Tag::BitsPerSample, Value::Short(16));
+        let ifd = ImageFileDirectory::from_tags(tags.clone(), Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.chunk_samples_per_pixel(), 3);
+        assert_eq!(ifd.chunk_row_bytes(), 600);
+        assert_eq!(
+            ifd.chunk_row_bytes(),
+            PredictorInfo::from_ifd(&ifd).chunk_row_bytes()
+        );
+
+        tags.insert(Tag::PlanarConfiguration, Value::Short(2));
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.chunk_samples_per_pixel(), 1);
+        assert_eq!(ifd.chunk_row_bytes(), 200);
+        assert_eq!(
+            ifd.chunk_row_bytes(),
+            PredictorInfo::from_ifd(&ifd).chunk_row_bytes()
+        );
+    }
+
+    #[test]
+    fn test_fill_order() {
+        let ifd =
+            ImageFileDirectory::from_tags(stripped_tags(None), Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.fill_order(), FillOrder::MsbToLsb);
+
+        let mut tags = stripped_tags(None);
+        tags.insert(Tag::FillOrder, Value::Short(2));
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.fill_order(), FillOrder::LsbToMsb);
+        assert_eq!(ifd.tag_u32(Tag::FillOrder), Some(2));
+    }
+
+    #[test]
+    fn test_unknown_enum_values() {
+        let mut tags = stripped_tags(None);
+        tags.insert(Tag::Compression, Value::Short(60000));
+        tags.insert(Tag::PhotometricInterpretation, Value::Short(32844));
+        tags.insert(Tag::Predictor, Value::Short(34892));
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.compression(), CompressionMethod::Unknown(60000));
+        assert_eq!(
+            ifd.photometric_interpretation(),
+            PhotometricInterpretation::Unknown(32844)
+        );
+        assert_eq!(ifd.predictor(), Some(Predictor::Unknown(34892)));
+
+        // The values survive writing the IFD back out
+        let tags = ifd.to_tags().unwrap();
+        assert_eq!(tags[&Tag::Compression], Value::Short(60000));
+        assert_eq!(tags[&Tag::PhotometricInterpretation], Value::Short(32844));
+        assert_eq!(tags[&Tag::Predictor], Value::Short(34892));
+        let copy = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(copy.predictor(), ifd.predictor());
+    }
+
+    #[test]
+    fn test_geo_keys_out_of_bounds() {
+        let mut tags = stripped_tags(Some(50));
+        #[rustfmt::skip]
+        let keys = vec![
+            1, 1, 0, 4,
+            1024, 0, 1, 1,
+            1026, 34737, 10, 0,
+            2057, 34736, 1, 0,
+            2059, 34736, 1, 7,
+        ];
+        tags.insert(
+            Tag::GeoKeyDirectoryTag,
+            Value::List(keys.into_iter().map(Value::Short).collect()),
+        );
+        tags.insert(Tag::GeoAsciiParamsTag, Value::Ascii("WGS 84|".to_string()));
+        tags.insert(Tag::GeoDoubleParamsTag, Value::Double(6378137.0));
+
+        let err = ImageFileDirectory::from_tags(tags.clone(), Endianness::LittleEndian)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("GeoKey 1026"), "{err}");
+
+        let ifd = ImageFileDirectory::from_tags_lenient(
+            tags.clone(),
+            &Default::default(),
+            Endianness::LittleEndian,
+            true,
+        )
+        .unwrap();
+        let geo_keys = ifd.geo_key_directory().unwrap();
+        assert_eq!(geo_keys.model_type, Some(1));
+        assert_eq!(geo_keys.citation, None);
+        assert_eq!(geo_keys.geog_semi_major_axis, Some(6378137.0));
+        let skipped = ifd
+            .warnings()
+            .iter()
+            .map(|warning| match warning {
+                IfdWarning::InvalidGeoKey { key, .. } => *key,
+                warning => panic!("Unexpected {warning:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(skipped, [1026, 2059]);
+
+        // More keys than entries, and a header that is cut short
+        for keys in [vec![1, 1, 0, 2, 1024, 0, 1, 1], vec![1, 1]] {
+            let mut tags = tags.clone();
+            tags.insert(
+                Tag::GeoKeyDirectoryTag,
+                Value::List(keys.into_iter().map(Value::Short).collect()),
+            );
+            assert!(ImageFileDirectory::from_tags(tags.clone(), Endianness::LittleEndian).is_err());
+            let ifd = ImageFileDirectory::from_tags_lenient(
+                tags,
+                &Default::default(),
+                Endianness::LittleEndian,
+                true,
+            )
+            .unwrap();
+            assert!(matches!(
+                ifd.warnings(),
+                [IfdWarning::InvalidGeoKeyDirectory { .. }]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_raw_geo_keys() {
+        let mut tags = stripped_tags(Some(50));
+        // Includes a private key, which the typed directory doesn't model
+        #[rustfmt::skip]
+        let keys = vec![
+            1, 1, 0, 4,
+            1024, 0, 1, 1,
+            1026, 34737, 7, 0,
+            2057, 34736, 1, 0,
+            32768, 34736, 2, 0,
+        ];
+        let directory = Value::List(keys.into_iter().map(Value::Short).collect());
+        let ascii_params = Value::Ascii("WGS 84|".to_string());
+        let double_params = Value::List(vec![Value::Double(6378137.0), Value::Double(298.25)]);
+        tags.insert(Tag::GeoKeyDirectoryTag, directory.clone());
+        tags.insert(Tag::GeoAsciiParamsTag, ascii_params.clone());
+        tags.insert(Tag::GeoDoubleParamsTag, double_params.clone());
+
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(
+            ifd.geo_key_directory().unwrap().citation.as_deref(),
+            Some("WGS 84")
+        );
+        let raw = ifd.raw_geo_keys().unwrap();
+        assert_eq!(raw.version(), [1, 1, 0]);
+        assert_eq!(raw.entries().len(), 4);
+        let private = raw.entry(32768).unwrap();
+        assert_eq!(
+            *private,
+            GeoKeyEntry {
+                key_id: 32768,
+                tag_location: 34736,
+                count: 2,
+                value_offset: 0
+            }
+        );
+        assert_eq!(
+            raw.value(private),
+            Some(Value::List(vec![
+                Value::Double(6378137.0),
+                Value::Double(298.25)
+            ]))
+        );
+        assert_eq!(
+            raw.value(raw.entry(1026).unwrap()),
+            Some(Value::Ascii("WGS 84".to_string()))
+        );
+
+        let tags = ifd.to_tags().unwrap();
+        assert_eq!(tags[&Tag::GeoKeyDirectoryTag], directory);
+        assert_eq!(tags[&Tag::GeoAsciiParamsTag], ascii_params);
+        assert_eq!(tags[&Tag::GeoDoubleParamsTag], double_params);
+    }
+
+    #[test]
+    fn test_resolution_types() {
+        let resolution = |value| resolution_from_value(Tag::XResolution, value);
+        assert_eq!(resolution(Value::Rational(300, 2)).unwrap(), (300, 2));
+        assert_eq!(resolution(Value::Short(72)).unwrap(), (72, 1));
+        assert_eq!(resolution(Value::Float(72.5)).unwrap(), (725000, 10000));
+        assert_eq!(resolution(Value::Double(1.0 / 3.0)).unwrap(), (3333, 10000));
+        assert!(resolution(Value::Double(f64::NAN)).is_err());
+        assert!(resolution(Value::Double(-1.0)).is_err());
+        assert!(resolution(Value::Double(1e10)).is_err());
+        assert!(resolution(Value::Ascii("72".to_string())).is_err());
+
+        let mut tags = stripped_tags(Some(50));
+        tags.insert(Tag::XResolution, Value::Double(72.0));
+        tags.insert(Tag::YResolution, Value::Unsigned(96));
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.x_resolution(), Some(72.0));
+        assert_eq!(ifd.x_resolution_rational(), Some((720000, 10000)));
+        assert_eq!(ifd.y_resolution_rational(), Some((96, 1)));
+    }
+
+    #[test]
+    fn test_ycbcr_tags() {
+        let mut tags = stripped_tags(None);
+        tags.insert(Tag::JPEGProc, Value::Short(1));
+        tags.insert(
+            Tag::YCbCrCoefficients,
+            Value::List(vec![
+                Value::Rational(2126, 10000),
+                Value::Rational(7152, 10000),
+                Value::Rational(722, 10000),
+            ]),
+        );
+        tags.insert(
+            Tag::ReferenceBlackWhite,
+            Value::List(
+                [16, 235, 128, 240, 128, 240]
+                    .map(|v| Value::Rational(v, 1))
+                    .to_vec(),
+            ),
+        );
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.jpeg_proc(), Some(1));
+        assert_eq!(ifd.ycbcr_coefficients(), Some([0.2126, 0.7152, 0.0722]));
+        assert_eq!(
+            ifd.reference_black_white(),
+            Some([16.0, 235.0, 128.0, 240.0, 128.0, 240.0])
+        );
+        assert_eq!(ifd.tag_u32(Tag::JPEGProc), Some(1));
+        assert_eq!(
+            ifd.tag_f64_vec(Tag::YCbCrCoefficients),
+            Some(vec![0.2126, 0.7152, 0.0722])
+        );
+        assert_eq!(
+            ifd.tag_f64_vec(Tag::ReferenceBlackWhite),
+            Some(vec![16.0, 235.0, 128.0, 240.0, 128.0, 240.0])
+        );
+
+        let mut tags = stripped_tags(None);
+        tags.insert(Tag::YCbCrCoefficients, Value::Rational(1, 2));
+        assert!(ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).is_err());
+    }
+
+    #[test]
+    fn test_tile_lookup() {
+        let ifd = crate::IfdBuilder::new(100, 50)
+            .with_tiling(32, 32)
+            .build()
+            .unwrap();
+        assert_eq!(ifd.tile_index_for_pixel(0.0, 0.0), Some((0, 0)));
+        assert_eq!(ifd.tile_index_for_pixel(99.5, 49.0), Some((3, 1)));
+        assert_eq!(ifd.tile_index_for_pixel(100.0, 0.0), None);
+
+        let transform = AffineTransform::new(10.0, 0.0, 1000.0, 0.0, -10.0, 500.0);
+        assert_eq!(
+            ifd.tile_index_for_point(1325.0, 175.0, &transform),
+            Some((1, 1))
+        );
+        assert_eq!(
+            ifd.tile_bounds(3, 1, &transform),
+            Some((1960.0, 0.0, 2000.0, 180.0))
+        );
+        assert_eq!(ifd.tile_bounds(4, 0, &transform), None);
+    }
+
+    #[test]
+    fn test_estimated_heap_size() {
+        let mut tags = stripped_tags(None);
+        let base = ImageFileDirectory::from_tags(tags.clone(), Endianness::LittleEndian)
+            .unwrap()
+            .estimated_heap_size();
+
+        let offsets = Value::List((0..1000).map(Value::UnsignedBig).collect());
+        tags.insert(Tag::StripOffsets, offsets.clone());
+        tags.insert(Tag::StripByteCounts, offsets);
+        tags.insert(Tag::Software, Value::Ascii("x".repeat(100)));
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        assert!(ifd.estimated_heap_size() > base);
+        assert!(ifd.estimated_heap_size() >= 2 * 1000 * 8 + 100);
+    }
+
+    #[test]
+    fn test_clone_shares_offsets() {
+        let ifd =
+            ImageFileDirectory::from_tags(stripped_tags(None), Endianness::LittleEndian).unwrap();
+        let clone = ifd.clone();
+        assert!(std::ptr::eq(
+            ifd.strip_offsets().unwrap(),
+            clone.strip_offsets().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut geo_key_directory = GeoKeyDirectory {
+            model_type: Some(1),
+            raster_type: Some(1),
+            citation: Some("WGS 84 / UTM zone 12N".to_string()),
+            projected_type: Some(32612),
+            ..Default::default()
+        };
+        assert_eq!(
+            geo_key_directory.to_string(),
+            r#"EPSG:32612 "WGS 84 / UTM zone 12N", projected, PixelIsArea"#
+        );
+
+        let ifd = crate::IfdBuilder::new(1024, 1024)
+            .with_data_type(SampleFormat::Uint, 16)
+            .with_tiling(256, 256)
+            .with_compression(CompressionMethod::Deflate)
+            .with_geo_key_directory(geo_key_directory.clone())
+            .build()
+            .unwrap();
+        assert_eq!(
+            ifd.to_string(),
+            "1024x1024 u16, 1 band, BlackIsZero, Deflate, tiled 256x256 (4x4), EPSG:32612"
+        );
+
+        geo_key_directory.projected_type = None;
+        geo_key_directory.citation = None;
+        geo_key_directory.raster_type = None;
+        assert_eq!(geo_key_directory.to_string(), "user-defined CRS, projected");
+    }
 }
@@ -0,0 +1,566 @@
+use std::collections::HashMap;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::geo::{GeoKeyDirectory, RawGeoKeys};
+use crate::reader::Endianness;
+use crate::tiff::tags::{
+    CompressionMethod, ExtraSample, PhotometricInterpretation, PlanarConfiguration, Predictor,
+    SampleFormat, Tag,
+};
+use crate::tiff::Value;
+use crate::ImageFileDirectory;
+
+/// A builder for an [`ImageFileDirectory`] describing an image that isn't read from a file, e.g.
+/// an in-memory dataset for tests.
+///
+/// Setters can be called in any order, and are validated together by [`build`](Self::build),
+/// which assembles the tags and passes them to [`ImageFileDirectory::from_tags`].
+///
+/// ```
+/// # use async_tiff::IfdBuilder;
+/// # use async_tiff::tags::{CompressionMethod, SampleFormat};
+/// let ifd = IfdBuilder::new(512, 256)
+///     .with_data_type(SampleFormat::IEEEFP, 32)
+///     .with_tiling(256, 256)
+///     .with_compression(CompressionMethod::Deflate)
+///     .build()
+///     .unwrap();
+/// assert_eq!(ifd.tile_count(), Some((2, 1)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct IfdBuilder {
+    endianness: Endianness,
+    width: u32,
+    height: u32,
+    samples_per_pixel: u16,
+    sample_format: SampleFormat,
+    bits_per_sample: u16,
+    photometric_interpretation: Option<PhotometricInterpretation>,
+    extra_samples: Option<Vec<ExtraSample>>,
+    color_map: Option<Vec<u16>>,
+    planar_configuration: PlanarConfiguration,
+    tile_size: Option<(u32, u32)>,
+    rows_per_strip: Option<u32>,
+    compression: CompressionMethod,
+    predictor: Predictor,
+    chunk_locations: Option<(Vec<u64>, Vec<u64>)>,
+    geo_key_directory: Option<GeoKeyDirectory>,
+    raw_geo_keys: Option<RawGeoKeys>,
+    model_pixel_scale: Option<[f64; 3]>,
+    model_tiepoint: Option<[f64; 6]>,
+    other_tags: HashMap<Tag, Value>,
+}
+
+impl IfdBuilder {
+    /// Create a builder for a `width` by `height` image of single 8-bit unsigned samples, stored
+    /// as a single uncompressed strip.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            endianness: Endianness::LittleEndian,
+            width,
+            height,
+            samples_per_pixel: 1,
+            sample_format: SampleFormat::Uint,
+            bits_per_sample: 8,
+            photometric_interpretation: None,
+            extra_samples: None,
+            color_map: None,
+            planar_configuration: PlanarConfiguration::Chunky,
+            tile_size: None,
+            rows_per_strip: None,
+            compression: CompressionMethod::None,
+            predictor: Predictor::None,
+            chunk_locations: None,
+            geo_key_directory: None,
+            raw_geo_keys: None,
+            model_pixel_scale: None,
+            model_tiepoint: None,
+            other_tags: HashMap::new(),
+        }
+    }
+
+    /// Set the byte order of the image data.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Set the format and bit depth shared by every sample.
+    pub fn with_data_type(mut self, sample_format: SampleFormat, bits_per_sample: u16) -> Self {
+        self.sample_format = sample_format;
+        self.bits_per_sample = bits_per_sample;
+        self
+    }
+
+    /// Set the number of samples in each pixel.
+    pub fn with_samples_per_pixel(mut self, samples_per_pixel: u16) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Set the color space of the image data.
+    ///
+    /// Defaults to [`PhotometricInterpretation::RGB`] for three or more samples per pixel and
+    /// [`PhotometricInterpretation::BlackIsZero`] otherwise.
+    pub fn with_photometric_interpretation(
+        mut self,
+        photometric_interpretation: PhotometricInterpretation,
+    ) -> Self {
+        self.photometric_interpretation = Some(photometric_interpretation);
+        self
+    }
+
+    /// Set the meaning of each sample beyond those of the color space, e.g. an alpha channel.
+    ///
+    /// Defaults to [`ExtraSample::Unspecified`] for every extra sample.
+    pub fn with_extra_samples(mut self, extra_samples: Vec<ExtraSample>) -> Self {
+        self.extra_samples = Some(extra_samples);
+        self
+    }
+
+    /// Set the palette of a [`PhotometricInterpretation::RGBPalette`] image: the red values of
+    /// each of its `2^bits_per_sample` colors, followed by the green and then the blue values.
+    pub fn with_color_map(mut self, color_map: Vec<u16>) -> Self {
+        self.color_map = Some(color_map);
+        self
+    }
+
+    /// Set how the samples of each pixel are arranged.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+
+    /// Store the image in tiles of `tile_width` by `tile_height` pixels.
+    ///
+    /// Both must be multiples of 16, as required by the TIFF specification.
+    pub fn with_tiling(mut self, tile_width: u32, tile_height: u32) -> Self {
+        self.tile_size = Some((tile_width, tile_height));
+        self.rows_per_strip = None;
+        self
+    }
+
+    /// Store the image in strips of `rows_per_strip` rows.
+    pub fn with_strips(mut self, rows_per_strip: u32) -> Self {
+        self.rows_per_strip = Some(rows_per_strip);
+        self.tile_size = None;
+        self
+    }
+
+    /// Set the compression method of the image data.
+    pub fn with_compression(mut self, compression: CompressionMethod) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the predictor applied to the image data before compression.
+    pub fn with_predictor(mut self, predictor: Predictor) -> Self {
+        self.predictor = predictor;
+        self
+    }
+
+    /// Set the offsets and byte counts of every tile or strip, in the order they are indexed.
+    ///
+    /// Without these, every chunk is located at offset zero with a byte count of zero.
+    pub fn with_chunk_locations(mut self, offsets: Vec<u64>, byte_counts: Vec<u64>) -> Self {
+        self.chunk_locations = Some((offsets, byte_counts));
+        self
+    }
+
+    /// Set the GeoTIFF keys describing the image's coordinate reference system.
+    pub fn with_geo_key_directory(mut self, geo_key_directory: GeoKeyDirectory) -> Self {
+        self.geo_key_directory = Some(geo_key_directory);
+        self
+    }
+
+    /// Set the GeoTIFF keys exactly as they are to be stored, e.g. as read from another image's
+    /// [`raw_geo_keys`](ImageFileDirectory::raw_geo_keys).
+    ///
+    /// These take precedence over [`with_geo_key_directory`](Self::with_geo_key_directory).
+    pub fn with_raw_geo_keys(mut self, raw_geo_keys: RawGeoKeys) -> Self {
+        self.raw_geo_keys = Some(raw_geo_keys);
+        self
+    }
+
+    /// Set the size of a pixel in model space, as `[x, y, z]`.
+    pub fn with_model_pixel_scale(mut self, model_pixel_scale: [f64; 3]) -> Self {
+        self.model_pixel_scale = Some(model_pixel_scale);
+        self
+    }
+
+    /// Tie the raster point `(i, j, k)` to the model point `(x, y, z)`, as
+    /// `[i, j, k, x, y, z]`.
+    pub fn with_model_tiepoint(mut self, model_tiepoint: [f64; 6]) -> Self {
+        self.model_tiepoint = Some(model_tiepoint);
+        self
+    }
+
+    /// Set an additional tag, such as `GdalNodata`.
+    ///
+    /// Tags set by other methods of this builder take precedence.
+    pub fn with_tag(mut self, tag: Tag, value: Value) -> Self {
+        self.other_tags.insert(tag, value);
+        self
+    }
+
+    /// Validate the settings and create the [`ImageFileDirectory`].
+    pub fn build(self) -> AsyncTiffResult<ImageFileDirectory> {
+        let invalid = |msg: String| Err(AsyncTiffError::General(msg));
+        if self.width == 0 || self.height == 0 {
+            return invalid(format!(
+                "Invalid image dimensions {}x{}",
+                self.width, self.height
+            ));
+        }
+        if self.samples_per_pixel == 0 {
+            return invalid("Samples per pixel must be at least 1".to_string());
+        }
+        let valid_bits = match self.sample_format {
+            SampleFormat::IEEEFP => matches!(self.bits_per_sample, 16 | 32 | 64),
+            SampleFormat::Uint => matches!(self.bits_per_sample, 1..=16 | 32 | 64),
+            _ => matches!(self.bits_per_sample, 8 | 16 | 32 | 64),
+        };
+        if !valid_bits {
+            return invalid(format!(
+                "Invalid data type: {}-bit {:?} samples",
+                self.bits_per_sample, self.sample_format
+            ));
+        }
+        if let Some((tile_width, tile_height)) = self.tile_size {
+            if tile_width == 0
+                || tile_height == 0
+                || !tile_width.is_multiple_of(16)
+                || !tile_height.is_multiple_of(16)
+            {
+                return invalid(format!(
+                    "Invalid tile size {tile_width}x{tile_height}: must be non-zero multiples of 16"
+                ));
+            }
+        }
+        if self.rows_per_strip == Some(0) {
+            return invalid("Rows per strip must be at least 1".to_string());
+        }
+        if self.predictor == Predictor::FloatingPoint && self.sample_format != SampleFormat::IEEEFP
+        {
+            return invalid("The floating point predictor requires floating point samples".into());
+        }
+
+        let planes = match self.planar_configuration {
+            PlanarConfiguration::Chunky => 1,
+            PlanarConfiguration::Planar => self.samples_per_pixel as usize,
+        };
+        let chunks_per_plane = match self.tile_size {
+            Some((tile_width, tile_height)) => {
+                self.width.div_ceil(tile_width) as usize
+                    * self.height.div_ceil(tile_height) as usize
+            }
+            None => self
+                .height
+                .div_ceil(self.rows_per_strip.unwrap_or(self.height)) as usize,
+        };
+        let num_chunks = chunks_per_plane * planes;
+        let (offsets, byte_counts) = self
+            .chunk_locations
+            .unwrap_or_else(|| (vec![0; num_chunks], vec![0; num_chunks]));
+        if offsets.len() != num_chunks || byte_counts.len() != num_chunks {
+            return invalid(format!(
+                "Expected {num_chunks} chunk offsets and byte counts, got {} and {}",
+                offsets.len(),
+                byte_counts.len()
+            ));
+        }
+
+        let photometric_interpretation =
+            self.photometric_interpretation
+                .unwrap_or(if self.samples_per_pixel >= 3 {
+                    PhotometricInterpretation::RGB
+                } else {
+                    PhotometricInterpretation::BlackIsZero
+                });
+        let color_samples = color_samples(photometric_interpretation, self.samples_per_pixel);
+        let extra_samples = self.extra_samples.unwrap_or_else(|| {
+            let extra = self.samples_per_pixel.saturating_sub(color_samples);
+            vec![ExtraSample::Unspecified; extra as usize]
+        });
+        let extra_samples = extra_samples
+            .iter()
+            .map(ExtraSample::to_u16)
+            .collect::<Vec<_>>();
+        if let Err(msg) = validate_color(
+            photometric_interpretation,
+            self.samples_per_pixel,
+            (self.sample_format, self.bits_per_sample),
+            Some(&extra_samples),
+            self.color_map.as_deref(),
+        ) {
+            return invalid(msg);
+        }
+        let samples = self.samples_per_pixel as usize;
+        let shorts = |value: u16| Value::List(vec![Value::Short(value); samples]);
+        let longs =
+            |values: Vec<u64>| Value::List(values.into_iter().map(Value::UnsignedBig).collect());
+
+        let mut tags = self.other_tags;
+        tags.extend([
+            (Tag::ImageWidth, Value::Unsigned(self.width)),
+            (Tag::ImageLength, Value::Unsigned(self.height)),
+            (Tag::SamplesPerPixel, Value::Short(self.samples_per_pixel)),
+            (Tag::BitsPerSample, shorts(self.bits_per_sample)),
+            (Tag::SampleFormat, shorts(self.sample_format.to_u16())),
+            (
+                Tag::PhotometricInterpretation,
+                Value::Short(photometric_interpretation.to_u16()),
+            ),
+            (
+                Tag::PlanarConfiguration,
+                Value::Short(self.planar_configuration.to_u16()),
+            ),
+            (Tag::Compression, Value::Short(self.compression.to_u16())),
+            (Tag::Predictor, Value::Short(self.predictor.to_u16())),
+        ]);
+        if !extra_samples.is_empty() {
+            tags.insert(
+                Tag::ExtraSamples,
+                Value::List(extra_samples.into_iter().map(Value::Short).collect()),
+            );
+        }
+        if let Some(color_map) = self.color_map {
+            tags.insert(
+                Tag::ColorMap,
+                Value::List(color_map.into_iter().map(Value::Short).collect()),
+            );
+        }
+        match self.tile_size {
+            Some((tile_width, tile_height)) => tags.extend([
+                (Tag::TileWidth, Value::Unsigned(tile_width)),
+                (Tag::TileLength, Value::Unsigned(tile_height)),
+                (Tag::TileOffsets, longs(offsets)),
+                (Tag::TileByteCounts, longs(byte_counts)),
+            ]),
+            None => tags.extend([
+                (
+                    Tag::RowsPerStrip,
+                    Value::Unsigned(self.rows_per_strip.unwrap_or(self.height)),
+                ),
+                (Tag::StripOffsets, longs(offsets)),
+                (Tag::StripByteCounts, longs(byte_counts)),
+            ]),
+        }
+        if let Some(raw_geo_keys) = &self.raw_geo_keys {
+            tags.extend(raw_geo_keys.to_tags());
+        } else if let Some(geo_key_directory) = &self.geo_key_directory {
+            tags.extend(geo_key_directory.to_tags());
+        }
+        if let Some(model_pixel_scale) = self.model_pixel_scale {
+            tags.insert(
+                Tag::ModelPixelScaleTag,
+                Value::List(model_pixel_scale.map(Value::Double).to_vec()),
+            );
+        }
+        if let Some(model_tiepoint) = self.model_tiepoint {
+            tags.insert(
+                Tag::ModelTiepointTag,
+                Value::List(model_tiepoint.map(Value::Double).to_vec()),
+            );
+        }
+
+        ImageFileDirectory::from_tags(tags, self.endianness)
+    }
+}
+
+/// The number of samples of each pixel in a color space, before any extra samples.
+///
+/// Unknown color spaces are assumed to use all `samples_per_pixel` samples.
+fn color_samples(
+    photometric_interpretation: PhotometricInterpretation,
+    samples_per_pixel: u16,
+) -> u16 {
+    match photometric_interpretation {
+        PhotometricInterpretation::WhiteIsZero
+        | PhotometricInterpretation::BlackIsZero
+        | PhotometricInterpretation::RGBPalette
+        | PhotometricInterpretation::TransparencyMask => 1,
+        PhotometricInterpretation::RGB
+        | PhotometricInterpretation::YCbCr
+        | PhotometricInterpretation::CIELab
+        | PhotometricInterpretation::ICCLab => 3,
+        PhotometricInterpretation::CMYK => 4,
+        PhotometricInterpretation::Unknown(_) => samples_per_pixel,
+    }
+}
+
+/// Check that the color space, extra samples and color map of an image agree with its samples,
+/// returning a description of the first mismatch.
+pub(crate) fn validate_color(
+    photometric_interpretation: PhotometricInterpretation,
+    samples_per_pixel: u16,
+    (sample_format, bits_per_sample): (SampleFormat, u16),
+    extra_samples: Option<&[u16]>,
+    color_map: Option<&[u16]>,
+) -> Result<(), String> {
+    if let PhotometricInterpretation::Unknown(_) = photometric_interpretation {
+        // The samples of unknown color spaces can't be checked
+        return match color_map {
+            Some(_) => Err("Only palette images can have a color map".to_string()),
+            None => Ok(()),
+        };
+    }
+    let color_samples = color_samples(photometric_interpretation, samples_per_pixel);
+    if samples_per_pixel < color_samples {
+        return Err(format!(
+            "{photometric_interpretation:?} images need at least {color_samples} samples per \
+             pixel, got {samples_per_pixel}"
+        ));
+    }
+    let extra = (samples_per_pixel - color_samples) as usize;
+    if let Some(extra_samples) = extra_samples.filter(|samples| samples.len() != extra) {
+        return Err(format!(
+            "Expected {extra} extra samples for {samples_per_pixel} {photometric_interpretation:?} \
+             samples per pixel, got {}",
+            extra_samples.len()
+        ));
+    }
+
+    let palette = photometric_interpretation == PhotometricInterpretation::RGBPalette;
+    match color_map {
+        None if palette => Err("Palette images need a color map".to_string()),
+        Some(_) if !palette => Err("Only palette images can have a color map".to_string()),
+        Some(_) if sample_format != SampleFormat::Uint || bits_per_sample > 16 => Err(format!(
+            "Palette images need unsigned samples of at most 16 bits, got {bits_per_sample}-bit \
+             {sample_format:?} samples"
+        )),
+        Some(color_map) if color_map.len() != 3 << bits_per_sample => Err(format!(
+            "Expected a color map of {} values for {bits_per_sample}-bit samples, got {}",
+            3 << bits_per_sample,
+            color_map.len()
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_tiled() {
+        let ifd = IfdBuilder::new(100, 40)
+            .with_samples_per_pixel(3)
+            .with_data_type(SampleFormat::Int, 16)
+            .with_tiling(32, 32)
+            .with_compression(CompressionMethod::Deflate)
+            .with_predictor(Predictor::Horizontal)
+            .with_chunk_locations((0..8).collect(), vec![10; 8])
+            .build()
+            .unwrap();
+        assert_eq!(ifd.image_width(), 100);
+        assert_eq!(ifd.bits_per_sample(), [16, 16, 16]);
+        assert_eq!(ifd.sample_format(), [SampleFormat::Int; 3]);
+        assert_eq!(
+            ifd.photometric_interpretation(),
+            PhotometricInterpretation::RGB
+        );
+        assert_eq!(ifd.compression(), CompressionMethod::Deflate);
+        assert_eq!(ifd.predictor(), Some(Predictor::Horizontal));
+        assert_eq!(ifd.tile_count(), Some((4, 2)));
+        assert_eq!(ifd.tile_offsets(), Some(&[0, 1, 2, 3, 4, 5, 6, 7][..]));
+    }
+
+    #[test]
+    fn test_build_geo() {
+        let geo_keys = GeoKeyDirectory {
+            model_type: Some(1),
+            projected_type: Some(32633),
+            citation: Some("WGS 84 / UTM zone 33N".to_string()),
+            proj_linear_unit_size: Some(1.0),
+            ..Default::default()
+        };
+        let ifd = IfdBuilder::new(10, 10)
+            .with_geo_key_directory(geo_keys)
+            .with_model_pixel_scale([30.0, 30.0, 0.0])
+            .with_model_tiepoint([0.0, 0.0, 0.0, 500000.0, 4000000.0, 0.0])
+            .build()
+            .unwrap();
+        let geo_keys = ifd.geo_key_directory().unwrap();
+        assert_eq!(geo_keys.epsg_code(), Some(32633));
+        assert_eq!(geo_keys.citation.as_deref(), Some("WGS 84 / UTM zone 33N"));
+        assert_eq!(geo_keys.proj_linear_unit_size, Some(1.0));
+        assert_eq!(ifd.model_pixel_scale(), Some(&[30.0, 30.0, 0.0][..]));
+
+        let raw_geo_keys = ifd.raw_geo_keys().unwrap().clone();
+        let copy = IfdBuilder::new(10, 10)
+            .with_raw_geo_keys(raw_geo_keys.clone())
+            .build()
+            .unwrap();
+        assert_eq!(copy.raw_geo_keys(), Some(&raw_geo_keys));
+        let geo_keys = copy.geo_key_directory().unwrap();
+        assert_eq!(geo_keys.epsg_code(), Some(32633));
+    }
+
+    #[test]
+    fn test_build_color() {
+        let rgba = IfdBuilder::new(10, 10)
+            .with_samples_per_pixel(4)
+            .with_extra_samples(vec![ExtraSample::UnassociatedAlpha])
+            .build()
+            .unwrap();
+        assert_eq!(
+            rgba.photometric_interpretation(),
+            PhotometricInterpretation::RGB
+        );
+        assert_eq!(rgba.extra_samples(), Some(&[2][..]));
+
+        let gray = IfdBuilder::new(10, 10)
+            .with_samples_per_pixel(3)
+            .with_photometric_interpretation(PhotometricInterpretation::BlackIsZero)
+            .build()
+            .unwrap();
+        assert_eq!(gray.extra_samples(), Some(&[0, 0][..]));
+        assert_eq!(
+            IfdBuilder::new(10, 10).build().unwrap().extra_samples(),
+            None
+        );
+
+        let color_map = (0..768).collect::<Vec<u16>>();
+        let palette = IfdBuilder::new(10, 10)
+            .with_photometric_interpretation(PhotometricInterpretation::RGBPalette)
+            .with_color_map(color_map.clone())
+            .build()
+            .unwrap();
+        assert_eq!(palette.colormap().unwrap().len(), 256);
+
+        let invalid = [
+            IfdBuilder::new(10, 10)
+                .with_samples_per_pixel(2)
+                .with_photometric_interpretation(PhotometricInterpretation::RGB),
+            IfdBuilder::new(10, 10)
+                .with_samples_per_pixel(4)
+                .with_extra_samples(vec![ExtraSample::Unspecified; 2]),
+            IfdBuilder::new(10, 10)
+                .with_photometric_interpretation(PhotometricInterpretation::RGBPalette),
+            IfdBuilder::new(10, 10)
+                .with_photometric_interpretation(PhotometricInterpretation::RGBPalette)
+                .with_color_map(vec![0; 48]),
+            IfdBuilder::new(10, 10).with_color_map(color_map),
+        ];
+        for builder in invalid {
+            assert!(builder.build().is_err());
+        }
+    }
+
+    #[test]
+    fn test_build_invalid() {
+        assert!(IfdBuilder::new(0, 10).build().is_err());
+        assert!(IfdBuilder::new(10, 10).with_tiling(10, 16).build().is_err());
+        assert!(IfdBuilder::new(10, 10).with_strips(0).build().is_err());
+        assert!(IfdBuilder::new(10, 10)
+            .with_data_type(SampleFormat::IEEEFP, 8)
+            .build()
+            .is_err());
+        assert!(IfdBuilder::new(10, 10)
+            .with_strips(4)
+            .with_chunk_locations(vec![0; 2], vec![0; 2])
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//! Validation of JPEG table usage across the tiles of an IFD, and merging of the shared tables
+//! into the tiles.
+
+use bytes::Bytes;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::ifd::ImageFileDirectory;
+use crate::tiff::tags::CompressionMethod;
+use crate::tile::Tile;
+
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const SOS: u8 = 0xDA;
+const DQT: u8 = 0xDB;
+const DHT: u8 = 0xC4;
+
+/// A problem with the JPEG tables used by a tile.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum JpegTableIssue {
+    /// The shared JPEGTables of the IFD could not be parsed.
+    MalformedSharedTables,
+    /// The JPEG markers of the tile could not be parsed.
+    Malformed {
+        /// The column index of the tile.
+        x: usize,
+        /// The row index of the tile.
+        y: usize,
+    },
+    /// The tile's frame uses a quantization table that is neither in the shared JPEGTables nor
+    /// embedded in the tile.
+    MissingQuantizationTable {
+        /// The column index of the tile.
+        x: usize,
+        /// The row index of the tile.
+        y: usize,
+        /// The id of the missing table.
+        table_id: u8,
+    },
+    /// The tile embeds its own quantization or Huffman tables, which take precedence over the
+    /// shared JPEGTables of the IFD.
+    OverridesSharedTables {
+        /// The column index of the tile.
+        x: usize,
+        /// The row index of the tile.
+        y: usize,
+    },
+    /// The tile embeds different quantization tables than the first tile that embeds any.
+    InconsistentTables {
+        /// The column index of the tile.
+        x: usize,
+        /// The row index of the tile.
+        y: usize,
+    },
+}
+
+/// The result of [`ImageFileDirectory::check_jpeg_tables`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JpegTableReport {
+    tiles_checked: usize,
+    issues: Vec<JpegTableIssue>,
+}
+
+impl JpegTableReport {
+    /// The number of JPEG-compressed tiles that were checked.
+    pub fn tiles_checked(&self) -> usize {
+        self.tiles_checked
+    }
+
+    /// The problems found, in tile order.
+    pub fn issues(&self) -> &[JpegTableIssue] {
+        &self.issues
+    }
+
+    /// Returns `true` if no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl ImageFileDirectory {
+    /// Check that JPEG-compressed `tiles` fetched from this IFD use their tables consistently.
+    ///
+    /// Tiles that embed their own tables take precedence over the shared JPEGTables tag, so a
+    /// file mixing both decodes with whichever tables the tile carries, without any error. This
+    /// reports tiles that override the shared tables, tiles whose embedded quantization tables
+    /// differ from each other, and tiles referencing quantization tables that are not defined
+    /// anywhere. Tiles that are not JPEG-compressed are skipped.
+    pub fn check_jpeg_tables<'a>(
+        &self,
+        tiles: impl IntoIterator<Item = &'a Tile>,
+    ) -> JpegTableReport {
+        let mut report = JpegTableReport::default();
+        let shared = match self.jpeg_tables.as_deref().map(JpegSegments::parse) {
+            Some(Some(shared)) => Some(shared),
+            Some(None) => {
+                report.issues.push(JpegTableIssue::MalformedSharedTables);
+                None
+            }
+            None => None,
+        };
+
+        let mut first_embedded: Option<Vec<(u8, Vec<u8>)>> = None;
+        for tile in tiles {
+            if tile.compression_method != CompressionMethod::ModernJPEG {
+                continue;
+            }
+            report.tiles_checked += 1;
+            let (x, y) = (tile.x, tile.y);
+            let Some(segments) = JpegSegments::parse(&tile.compressed_bytes) else {
+                report.issues.push(JpegTableIssue::Malformed { x, y });
+                continue;
+            };
+
+            let embeds_tables = !segments.quantization.is_empty() || segments.huffman;
+            if embeds_tables && self.jpeg_tables.is_some() {
+                report
+                    .issues
+                    .push(JpegTableIssue::OverridesSharedTables { x, y });
+            }
+            if !segments.quantization.is_empty() {
+                match &first_embedded {
+                    Some(first) if *first != segments.quantization => {
+                        report
+                            .issues
+                            .push(JpegTableIssue::InconsistentTables { x, y });
+                    }
+                    Some(_) => {}
+                    None => first_embedded = Some(segments.quantization.clone()),
+                }
+            }
+
+            for &table_id in &segments.frame_quantization_ids {
+                let defined = segments.has_quantization(table_id)
+                    || shared
+                        .as_ref()
+                        .is_some_and(|shared| shared.has_quantization(table_id));
+                if !defined {
+                    report
+                        .issues
+                        .push(JpegTableIssue::MissingQuantizationTable { x, y, table_id });
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Merge the shared JPEGTables of an IFD into the JPEG stream of one of its tiles, making a
+/// self-contained JPEG that decodes without the IFD.
+///
+/// The JPEGTables are an abbreviated JPEG stream holding only table definitions, from an SOI to
+/// an EOI marker. The EOI of the tables and the SOI of the tile are dropped, so the tables come
+/// right before the tile's own segments. Tables the tile embeds itself follow the shared ones, so
+/// they still take precedence. No pixels are decoded or re-encoded.
+///
+/// This is what a tile whose [`payload`](crate::RawTile::payload) is
+/// [`JpegWithSharedTables`](crate::TilePayload::JpegWithSharedTables) needs to be served as an
+/// `image/jpeg`; see [`RawTile::image_bytes`](crate::RawTile::image_bytes).
+pub fn merge_jpeg_tables(jpeg_tables: &[u8], tile: &[u8]) -> AsyncTiffResult<Bytes> {
+    let tables = jpeg_tables
+        .strip_prefix(&[0xFF, SOI])
+        .ok_or_else(|| AsyncTiffError::General("JPEGTables do not start with SOI".to_string()))?;
+    let tables = tables.strip_suffix(&[0xFF, EOI]).unwrap_or(tables);
+    let tile = tile
+        .strip_prefix(&[0xFF, SOI])
+        .ok_or_else(|| AsyncTiffError::General("JPEG tile does not start with SOI".to_string()))?;
+
+    let mut merged = Vec::with_capacity(2 + tables.len() + tile.len());
+    merged.extend_from_slice(&[0xFF, SOI]);
+    merged.extend_from_slice(tables);
+    merged.extend_from_slice(tile);
+    Ok(merged.into())
+}
+
+/// The table definitions and frame header found before the scan data of a JPEG stream.
+#[derive(Debug, Default)]
+struct JpegSegments {
+    /// Quantization tables by id, with their contents.
+    quantization: Vec<(u8, Vec<u8>)>,
+    /// Whether any Huffman tables are defined.
+    huffman: bool,
+    /// The quantization table ids used by the frame's components.
+    frame_quantization_ids: Vec<u8>,
+}
+
+impl JpegSegments {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut segments = Self::default();
+        if data.get(..2)? != [0xFF, SOI] {
+            return None;
+        }
+        let mut pos = 2;
+        loop {
+            // Markers may be preceded by any number of fill bytes
+            while data.get(pos + 1) == Some(&0xFF) {
+                pos += 1;
+            }
+            if *data.get(pos)? != 0xFF {
+                return None;
+            }
+            let marker = *data.get(pos + 1)?;
+            if marker == EOI || marker == SOS {
+                return Some(segments);
+            }
+            let length = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+            let body = data.get(pos + 4..pos + 2 + length.max(2))?;
+            match marker {
+                DQT => segments.parse_quantization(body)?,
+                DHT => segments.huffman = true,
+                // Baseline, extended and progressive Huffman frames
+                0xC0..=0xC2 => segments.parse_frame(body)?,
+                _ => {}
+            }
+            pos += 2 + length;
+        }
+    }
+
+    fn parse_quantization(&mut self, mut body: &[u8]) -> Option<()> {
+        while let Some(&info) = body.first() {
+            let precision_bytes = if info >> 4 == 0 { 64 } else { 128 };
+            let table = body.get(1..1 + precision_bytes)?;
+            let id = info & 0x0F;
+            self.quantization.retain(|(existing, _)| *existing != id);
+            self.quantization.push((id, table.to_vec()));
+            body = &body[1 + precision_bytes..];
+        }
+        Some(())
+    }
+
+    fn parse_frame(&mut self, body: &[u8]) -> Option<()> {
+        // Precision, height, width, then three bytes per component: id, sampling, table id
+        let components = *body.get(5)? as usize;
+        for component in 0..components {
+            let table_id = *body.get(6 + component * 3 + 2)?;
+            if !self.frame_quantization_ids.contains(&table_id) {
+                self.frame_quantization_ids.push(table_id);
+            }
+        }
+        Some(())
+    }
+
+    fn has_quantization(&self, id: u8) -> bool {
+        self.quantization
+            .iter()
+            .any(|(existing, _)| *existing == id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::IfdBuilder;
+
+    fn segment(marker: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![0xFF, marker];
+        out.extend(((body.len() + 2) as u16).to_be_bytes());
+        out.extend(body);
+        out
+    }
+
+    fn dqt(id: u8, value: u8) -> Vec<u8> {
+        let mut body = vec![id];
+        body.extend([value; 64]);
+        segment(DQT, &body)
+    }
+
+    fn sof(table_ids: &[u8]) -> Vec<u8> {
+        let mut body = vec![8, 0, 16, 0, 16, table_ids.len() as u8];
+        for (i, id) in table_ids.iter().enumerate() {
+            body.extend([i as u8 + 1, 0x11, *id]);
+        }
+        segment(0xC0, &body)
+    }
+
+    fn jpeg(parts: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = vec![0xFF, SOI];
+        parts.iter().for_each(|part| out.extend(part));
+        out.extend(segment(SOS, &[0]));
+        out
+    }
+
+    #[test]
+    fn test_parse_segments() {
+        let data = jpeg(&[dqt(0, 1), dqt(1, 2), segment(DHT, &[0]), sof(&[0, 1, 1])]);
+        let segments = JpegSegments::parse(&data).unwrap();
+        assert!(segments.has_quantization(0) && segments.has_quantization(1));
+        assert!(segments.huffman);
+        assert_eq!(segments.frame_quantization_ids, vec![0, 1]);
+
+        assert!(JpegSegments::parse(&[0xFF, SOI, 0xFF, DQT, 0, 10]).is_none());
+        assert!(JpegSegments::parse(b"not a jpeg").is_none());
+    }
+
+    #[test]
+    fn test_merge_jpeg_tables() {
+        let mut tables = jpeg(&[dqt(0, 1)]);
+        tables.truncate(tables.len() - 5);
+        tables.extend([0xFF, EOI]);
+        let tile = jpeg(&[sof(&[0])]);
+        let merged = merge_jpeg_tables(&tables, &tile).unwrap();
+        assert_eq!(merged[..], jpeg(&[dqt(0, 1), sof(&[0])]));
+        let segments = JpegSegments::parse(&merged).unwrap();
+        assert!(segments.has_quantization(0));
+        assert_eq!(segments.frame_quantization_ids, vec![0]);
+
+        // Tables without a trailing EOI
+        let merged = merge_jpeg_tables(&tables[..tables.len() - 2], &tile).unwrap();
+        assert_eq!(merged[..], jpeg(&[dqt(0, 1), sof(&[0])]));
+
+        assert!(merge_jpeg_tables(&tables, &tile[2..]).is_err());
+        assert!(merge_jpeg_tables(&tables[2..], &tile).is_err());
+    }
+
+    #[test]
+    fn test_check_jpeg_tables() {
+        let mut ifd = IfdBuilder::new(32, 16)
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::ModernJPEG)
+            .build()
+            .unwrap();
+        let tile = |x, data: Vec<u8>| ifd.new_tile(x, 0, 0, Bytes::from(data)).unwrap();
+
+        // Tables embedded in every tile, without shared tables
+        let tiles = [
+            tile(0, jpeg(&[dqt(0, 1), sof(&[0])])),
+            tile(1, jpeg(&[dqt(0, 2), sof(&[0, 1])])),
+        ];
+        let report = ifd.check_jpeg_tables(&tiles);
+        assert_eq!(report.tiles_checked(), 2);
+        assert_eq!(
+            report.issues(),
+            [
+                JpegTableIssue::InconsistentTables { x: 1, y: 0 },
+                JpegTableIssue::MissingQuantizationTable {
+                    x: 1,
+                    y: 0,
+                    table_id: 1
+                },
+            ]
+        );
+
+        // Shared tables, with one tile overriding them
+        ifd.jpeg_tables = Some(Bytes::from(jpeg(&[dqt(0, 1), dqt(1, 1)])));
+        let tile = |x, data: Vec<u8>| ifd.new_tile(x, 0, 0, Bytes::from(data)).unwrap();
+        let tiles = [
+            tile(0, jpeg(&[sof(&[0, 1])])),
+            tile(1, jpeg(&[dqt(0, 3), sof(&[0, 1])])),
+        ];
+        let report = ifd.check_jpeg_tables(&tiles);
+        assert_eq!(
+            report.issues(),
+            [JpegTableIssue::OverridesSharedTables { x: 1, y: 0 }]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! A summary of where the metadata and image data of each IFD are stored in a file.
+
+use std::ops::Range;
+
+use crate::cog::TIFF;
+use crate::error::AsyncTiffResult;
+use crate::ifd::ImageFileDirectory;
+
+/// Where one IFD's metadata and tile or strip data are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IfdLayout {
+    ifd_index: usize,
+    metadata: Option<Range<u64>>,
+    data: Option<Range<u64>>,
+    chunk_count: usize,
+    data_bytes: u64,
+}
+
+impl IfdLayout {
+    /// The index of the IFD in the file.
+    pub fn ifd_index(&self) -> usize {
+        self.ifd_index
+    }
+
+    /// The range of bytes spanned by the IFD's entries and out-of-line tag values.
+    ///
+    /// See [`ImageFileDirectory::metadata_range`].
+    pub fn metadata(&self) -> Option<Range<u64>> {
+        self.metadata.clone()
+    }
+
+    /// The range of bytes from the start of the first tile or strip to the end of the last one,
+    /// or `None` if no tile or strip is stored.
+    pub fn data(&self) -> Option<Range<u64>> {
+        self.data.clone()
+    }
+
+    /// The number of tiles or strips stored, ignoring sparse ones with no bytes.
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_count
+    }
+
+    /// The total number of stored bytes of the tiles or strips.
+    pub fn data_bytes(&self) -> u64 {
+        self.data_bytes
+    }
+
+    /// Whether the tiles or strips are stored next to each other, without gaps between them.
+    ///
+    /// Readers can then fetch neighbouring tiles with a single request.
+    pub fn is_data_contiguous(&self) -> bool {
+        self.data
+            .as_ref()
+            .is_none_or(|data| data.end - data.start == self.data_bytes)
+    }
+}
+
+/// The result of [`TIFF::layout_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayoutReport {
+    ifds: Vec<IfdLayout>,
+    gaps: Vec<Range<u64>>,
+}
+
+impl LayoutReport {
+    /// The layout of each IFD, in file order.
+    pub fn ifds(&self) -> &[IfdLayout] {
+        &self.ifds
+    }
+
+    /// The ranges of bytes between the metadata and data extents of the IFDs that belong to
+    /// neither, in increasing order.
+    ///
+    /// The bytes before the first extent, which include the file header, are not counted. Gaps
+    /// inside the data extent of an IFD are not counted either; see
+    /// [`IfdLayout::is_data_contiguous`].
+    pub fn gaps(&self) -> &[Range<u64>] {
+        &self.gaps
+    }
+
+    /// Whether the metadata of every IFD precedes the data of all IFDs, so that the whole header
+    /// can be read with a single request as in a Cloud Optimized GeoTIFF.
+    ///
+    /// Returns `false` if the metadata range of an IFD is unknown.
+    pub fn metadata_before_data(&self) -> bool {
+        let metadata_end = self
+            .ifds
+            .iter()
+            .map(|ifd| ifd.metadata.as_ref().map(|metadata| metadata.end))
+            .try_fold(0, |end, metadata_end| Some(end.max(metadata_end?)));
+        let data_start = self
+            .ifds
+            .iter()
+            .filter_map(|ifd| ifd.data.as_ref().map(|data| data.start))
+            .min();
+        match (metadata_end, data_start) {
+            (Some(metadata_end), Some(data_start)) => metadata_end <= data_start,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// The end of the metadata of all IFDs, i.e. the number of bytes to prefetch to read the
+    /// whole header when [`metadata_before_data`](Self::metadata_before_data) holds.
+    pub fn metadata_end(&self) -> Option<u64> {
+        self.ifds
+            .iter()
+            .filter_map(|ifd| ifd.metadata.as_ref().map(|metadata| metadata.end))
+            .max()
+    }
+}
+
+impl TIFF {
+    /// Summarize where the metadata and tile or strip data of each IFD are stored, and the
+    /// unused bytes between them.
+    ///
+    /// This helps to tell why reading a file needs many requests: for example, IFDs scattered
+    /// through the file need a request each, and interleaved tiles of different IFDs need more
+    /// requests to read a window.
+    ///
+    /// Fails if the tile offsets of an IFD are
+    /// [lazy](ImageFileDirectory::has_lazy_tile_offsets) and weren't
+    /// [loaded](ImageFileDirectory::load_tile_offsets).
+    pub fn layout_report(&self) -> AsyncTiffResult<LayoutReport> {
+        let ifds = self
+            .ifds()
+            .iter()
+            .enumerate()
+            .map(|(ifd_index, ifd)| ifd_layout(ifd_index, ifd))
+            .collect::<AsyncTiffResult<Vec<_>>>()?;
+
+        let extents = ifds
+            .iter()
+            .flat_map(|ifd| [ifd.metadata.clone(), ifd.data.clone()])
+            .flatten()
+            .collect();
+        let gaps = gaps(extents);
+        Ok(LayoutReport { ifds, gaps })
+    }
+}
+
+/// The ranges not covered by any of `extents`, from the start of the first to the end of the last.
+fn gaps(mut extents: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    extents.sort_by_key(|extent| extent.start);
+    let mut gaps = vec![];
+    let mut end = extents.first().map_or(0, |extent| extent.end);
+    for extent in extents.iter().skip(1) {
+        if extent.start > end {
+            gaps.push(end..extent.start);
+        }
+        end = end.max(extent.end);
+    }
+    gaps
+}
+
+fn ifd_layout(ifd_index: usize, ifd: &ImageFileDirectory) -> AsyncTiffResult<IfdLayout> {
+    let chunks = ifd
+        .chunk_byte_ranges()?
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|chunk| !chunk.is_empty());
+
+    let mut data: Option<Range<u64>> = None;
+    let (mut chunk_count, mut data_bytes) = (0, 0);
+    for chunk in chunks {
+        data_bytes += chunk.end - chunk.start;
+        data = Some(match data {
+            Some(data) => data.start.min(chunk.start)..data.end.max(chunk.end),
+            None => chunk,
+        });
+        chunk_count += 1;
+    }
+    Ok(IfdLayout {
+        ifd_index,
+        metadata: ifd.metadata_range(),
+        data,
+        chunk_count,
+        data_bytes,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::metadata::TiffMetadataReader;
+    use crate::reader::MemoryReader;
+
+    async fn open(path: &str) -> TIFF {
+        let reader = MemoryReader::new(std::fs::read(path).unwrap());
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        TIFF::new(metadata.read_all_ifds(&reader).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_layout_report() {
+        let tiff = open("tests/image_tiff/images/tiled-jpeg-rgb-u8.tif").await;
+        let report = tiff.layout_report().unwrap();
+        assert_eq!(report.ifds().len(), tiff.ifds().len());
+
+        let ifd = &report.ifds()[0];
+        let metadata = ifd.metadata().unwrap();
+        let data = ifd.data().unwrap();
+        let offsets = tiff.ifds()[0].tile_offsets().unwrap();
+        assert_eq!(ifd.chunk_count(), offsets.len());
+        assert_eq!(
+            ifd.data_bytes(),
+            tiff.ifds()[0]
+                .tile_byte_counts()
+                .unwrap()
+                .iter()
+                .sum::<u64>()
+        );
+        assert_eq!(data.start, *offsets.iter().min().unwrap());
+        // Metadata and data don't overlap
+        assert!(metadata.end <= data.start || data.end <= metadata.start);
+        for gap in report.gaps() {
+            assert!(gap.start < gap.end);
+        }
+        assert_eq!(report.metadata_before_data(), metadata.end <= data.start);
+    }
+
+    #[test]
+    fn test_gaps() {
+        assert_eq!(gaps(vec![]), vec![]);
+        assert_eq!(
+            gaps(vec![300..400, 8..100, 50..120, 200..250]),
+            vec![120..200, 250..300]
+        );
+    }
+
+    #[test]
+    fn test_layout_ordering() {
+        let layout = |ifd_index, metadata: Range<u64>, data: Range<u64>, data_bytes| IfdLayout {
+            ifd_index,
+            metadata: Some(metadata),
+            data: Some(data),
+            chunk_count: 1,
+            data_bytes,
+        };
+        let ifds = vec![
+            layout(0, 8..100, 200..300, 100),
+            layout(1, 100..150, 300..400, 50),
+        ];
+        let mut report = LayoutReport { ifds, gaps: vec![] };
+        assert!(report.metadata_before_data());
+        assert_eq!(report.metadata_end(), Some(150));
+        assert!(report.ifds()[0].is_data_contiguous());
+        assert!(!report.ifds()[1].is_data_contiguous());
+
+        report.ifds[1].metadata = Some(400..450);
+        assert!(!report.metadata_before_data());
+        report.ifds[1].metadata = None;
+        assert!(!report.metadata_before_data());
+    }
+}
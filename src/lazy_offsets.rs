@@ -0,0 +1,462 @@
+//! Tile offsets and byte counts that are read from the file as they are needed.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::ifd::ImageFileDirectory;
+use crate::reader::{AsyncFileReader, EndianAwareReader, Endianness};
+use crate::tiff::tags::Type;
+
+/// The number of entries of each array fetched at once.
+const PAGE_ENTRIES: usize = 1024;
+
+/// The location in the file of an array of unsigned integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ArrayLocation {
+    pub(crate) offset: u64,
+    pub(crate) count: u64,
+    pub(crate) field_type: Type,
+}
+
+impl ArrayLocation {
+    /// The location of an array of `count` values of `field_type` at `offset`, if they are
+    /// unsigned integers that can hold tile offsets.
+    pub(crate) fn new(offset: u64, count: u64, field_type: Type) -> Option<Self> {
+        matches!(field_type, Type::SHORT | Type::LONG | Type::LONG8).then_some(Self {
+            offset,
+            count,
+            field_type,
+        })
+    }
+
+    fn value_size(&self) -> u64 {
+        match self.field_type {
+            Type::SHORT => 2,
+            Type::LONG => 4,
+            _ => 8,
+        }
+    }
+
+    /// The byte range of the entries in `entries`.
+    fn byte_range(&self, entries: &Range<usize>) -> AsyncTiffResult<Range<u64>> {
+        let size = self.value_size();
+        let start = (entries.start as u64)
+            .checked_mul(size)
+            .and_then(|start| self.offset.checked_add(start));
+        let end = (entries.end as u64)
+            .checked_mul(size)
+            .and_then(|end| self.offset.checked_add(end));
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(start..end),
+            _ => Err(AsyncTiffError::General(format!(
+                "Tile offsets at {} with {} entries overflow the file size",
+                self.offset, self.count
+            ))),
+        }
+    }
+
+    /// Parse the `count` entries in `bytes`, which must hold all of them.
+    fn parse(
+        &self,
+        bytes: bytes::Bytes,
+        count: usize,
+        endianness: Endianness,
+    ) -> AsyncTiffResult<Vec<u64>> {
+        let expected = count as u64 * self.value_size();
+        if (bytes.len() as u64) < expected {
+            return Err(AsyncTiffError::EndOfFile(expected, bytes.len() as u64));
+        }
+        let mut reader = EndianAwareReader::new(bytes, endianness);
+        (0..count)
+            .map(|_| match self.field_type {
+                Type::SHORT => reader.read_u16().map(u64::from),
+                Type::LONG => reader.read_u32().map(u64::from),
+                _ => reader.read_u64(),
+            })
+            .collect()
+    }
+}
+
+/// The TileOffsets and TileByteCounts of an IFD whose values weren't read with its entries.
+///
+/// Pages of entries are fetched as the tiles they locate are fetched, and kept for later fetches.
+/// Clones of the IFD share the pages.
+#[derive(Debug)]
+pub(crate) struct LazyTileOffsets {
+    offsets: ArrayLocation,
+    byte_counts: ArrayLocation,
+    endianness: Endianness,
+    /// The byte range of each tile of each loaded page.
+    pages: Mutex<HashMap<usize, Arc<[Range<u64>]>>>,
+}
+
+impl LazyTileOffsets {
+    pub(crate) fn new(
+        offsets: ArrayLocation,
+        byte_counts: ArrayLocation,
+        endianness: Endianness,
+    ) -> Self {
+        Self {
+            offsets,
+            byte_counts,
+            endianness,
+            pages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The number of tiles located by both arrays.
+    pub(crate) fn len(&self) -> usize {
+        self.offsets.count.min(self.byte_counts.count) as usize
+    }
+
+    /// The byte range of the tile at `index`, if its page was loaded.
+    pub(crate) fn get(&self, index: usize) -> Option<Range<u64>> {
+        if index >= self.len() {
+            return None;
+        }
+        let pages = self.pages.lock().unwrap();
+        pages
+            .get(&(index / PAGE_ENTRIES))
+            .and_then(|page| page.get(index % PAGE_ENTRIES).cloned())
+    }
+
+    /// The byte range of every tile, if all pages were loaded.
+    pub(crate) fn loaded_ranges(&self) -> Option<Vec<Range<u64>>> {
+        let pages = self.pages.lock().unwrap();
+        (0..self.len().div_ceil(PAGE_ENTRIES)).try_fold(
+            Vec::with_capacity(self.len()),
+            |mut ranges, page| {
+                ranges.extend_from_slice(pages.get(&page)?);
+                Some(ranges)
+            },
+        )
+    }
+
+    /// The number of pages loaded so far.
+    #[cfg(test)]
+    pub(crate) fn loaded_pages(&self) -> usize {
+        self.pages.lock().unwrap().len()
+    }
+
+    /// Fetch the pages holding the tiles at `indices` that weren't loaded yet.
+    ///
+    /// Indices out of range are ignored, so that looking them up fails as it does for tile
+    /// offsets read with the IFD.
+    pub(crate) async fn load(
+        &self,
+        indices: impl IntoIterator<Item = usize>,
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<()> {
+        let len = self.len();
+        let missing = {
+            let pages = self.pages.lock().unwrap();
+            indices
+                .into_iter()
+                .filter(|&index| index < len)
+                .map(|index| index / PAGE_ENTRIES)
+                .filter(|page| !pages.contains_key(page))
+                .collect::<BTreeSet<_>>()
+        };
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let entries = missing
+            .iter()
+            .map(|page| page * PAGE_ENTRIES..((page + 1) * PAGE_ENTRIES).min(len))
+            .collect::<Vec<_>>();
+        let mut byte_ranges = Vec::with_capacity(entries.len() * 2);
+        for entries in &entries {
+            byte_ranges.push(self.offsets.byte_range(entries)?);
+            byte_ranges.push(self.byte_counts.byte_range(entries)?);
+        }
+        let buffers = reader.get_byte_ranges(byte_ranges).await?;
+
+        let mut loaded = Vec::with_capacity(missing.len());
+        for ((page, entries), buffers) in missing
+            .into_iter()
+            .zip(entries)
+            .zip(buffers.chunks_exact(2))
+        {
+            let offsets = self
+                .offsets
+                .parse(buffers[0].clone(), entries.len(), self.endianness)?;
+            let byte_counts =
+                self.byte_counts
+                    .parse(buffers[1].clone(), entries.len(), self.endianness)?;
+            let ranges = offsets
+                .into_iter()
+                .zip(byte_counts)
+                .map(|(offset, byte_count)| offset..offset.saturating_add(byte_count))
+                .collect::<Arc<[_]>>();
+            loaded.push((page, ranges));
+        }
+        self.pages.lock().unwrap().extend(loaded);
+        Ok(())
+    }
+
+    /// The byte range of the tile at `index`, fetching just its two entries if its page wasn't
+    /// loaded.
+    ///
+    /// The entries aren't kept, so that a single tile is fetched with the least latency and
+    /// memory: pages are only worth loading for the many tiles of a window.
+    pub(crate) async fn fetch(
+        &self,
+        index: usize,
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<Option<Range<u64>>> {
+        if index >= self.len() {
+            return Ok(None);
+        }
+        if let Some(range) = self.get(index) {
+            return Ok(Some(range));
+        }
+        let entries = index..index + 1;
+        let byte_ranges = vec![
+            self.offsets.byte_range(&entries)?,
+            self.byte_counts.byte_range(&entries)?,
+        ];
+        let buffers = reader.get_byte_ranges(byte_ranges).await?;
+        let [offset, byte_count] = buffers.as_slice() else {
+            return Err(AsyncTiffError::General(
+                "Reader returned too few byte ranges".to_string(),
+            ));
+        };
+        let offset = self.offsets.parse(offset.clone(), 1, self.endianness)?;
+        let byte_count = self
+            .byte_counts
+            .parse(byte_count.clone(), 1, self.endianness)?;
+        Ok(offset
+            .first()
+            .zip(byte_count.first())
+            .map(|(&offset, &byte_count)| offset..offset.saturating_add(byte_count)))
+    }
+
+    /// Fetch both arrays in full.
+    async fn load_all(
+        &self,
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<(Vec<u64>, Vec<u64>)> {
+        let entries = 0..self.len();
+        let byte_ranges = vec![
+            self.offsets.byte_range(&entries)?,
+            self.byte_counts.byte_range(&entries)?,
+        ];
+        let mut buffers = reader.get_byte_ranges(byte_ranges).await?.into_iter();
+        let mut next = || {
+            buffers.next().ok_or(AsyncTiffError::General(
+                "Reader returned too few byte ranges".to_string(),
+            ))
+        };
+        let offsets = self
+            .offsets
+            .parse(next()?, entries.len(), self.endianness)?;
+        let byte_counts = self
+            .byte_counts
+            .parse(next()?, entries.len(), self.endianness)?;
+        Ok((offsets, byte_counts))
+    }
+}
+
+impl ImageFileDirectory {
+    /// Whether the tile offsets and byte counts of this IFD are read as tiles are fetched,
+    /// rather than with the IFD.
+    ///
+    /// See
+    /// [`TiffMetadataReader::with_lazy_tile_offsets`](crate::metadata::TiffMetadataReader::with_lazy_tile_offsets).
+    /// Until they are [loaded](Self::load_tile_offsets),
+    /// [`tile_offsets`](Self::tile_offsets) and [`tile_byte_counts`](Self::tile_byte_counts)
+    /// return `None`, and estimates only cover tiles fetched before.
+    pub fn has_lazy_tile_offsets(&self) -> bool {
+        self.lazy_tile_offsets.is_some()
+    }
+
+    /// Read the lazy tile offsets and byte counts of this IFD in full, so that they are
+    /// available from [`tile_offsets`](Self::tile_offsets) and
+    /// [`tile_byte_counts`](Self::tile_byte_counts).
+    ///
+    /// This does nothing if they were read with the IFD.
+    pub async fn load_tile_offsets(&mut self, reader: &dyn AsyncFileReader) -> AsyncTiffResult<()> {
+        if let Some(lazy) = &self.lazy_tile_offsets {
+            let (offsets, byte_counts) = lazy.load_all(reader).await?;
+            self.tile_offsets = Some(offsets.into());
+            self.tile_byte_counts = Some(byte_counts.into());
+            self.lazy_tile_offsets = None;
+        }
+        Ok(())
+    }
+
+    /// The byte range of the tile at column `x` and row `y`, fetching just its entries if the
+    /// tile offsets are lazy and its page wasn't loaded.
+    pub(crate) async fn fetch_tile_byte_range(
+        &self,
+        x: usize,
+        y: usize,
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<Option<Range<u64>>> {
+        match (&self.lazy_tile_offsets, self.tile_index(x, y)) {
+            (Some(lazy), Some(index)) => lazy.fetch(index, reader).await,
+            _ => Ok(self.get_tile_byte_range(x, y)),
+        }
+    }
+
+    /// Load the pages of lazy tile offsets holding the chunks at `indices`, if the tile offsets
+    /// are lazy.
+    pub(crate) async fn load_lazy_tile_offsets(
+        &self,
+        indices: impl IntoIterator<Item = usize>,
+        reader: &dyn AsyncFileReader,
+    ) -> AsyncTiffResult<()> {
+        match &self.lazy_tile_offsets {
+            Some(lazy) => lazy.load(indices, reader).await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::decoder::DecoderRegistry;
+    use crate::metadata::TiffMetadataReader;
+    use crate::test_util::{write_cog_with, CountingReader};
+    use crate::tiff::tags::{CompressionMethod, SampleFormat, Tag};
+    use crate::{ChecksumValidator, CogImage, CogWriter, IfdBuilder, Window, TIFF};
+
+    const CHECKSUM_TAG: Tag = Tag::Unknown(65000);
+
+    /// A BigTIFF of 8000x48 pixels in 1500 tiles of 16x16 pixels, each filled with its index.
+    async fn test_file() -> CountingReader {
+        let ifd = IfdBuilder::new(8000, 48)
+            .with_data_type(SampleFormat::Uint, 8)
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::None)
+            .build()
+            .unwrap();
+        let tiles = (0..1500)
+            .map(|i| Bytes::from(vec![i as u8; 256]))
+            .collect::<Vec<_>>();
+        let writer = CogWriter::new()
+            .with_bigtiff(true)
+            .with_checksum_tag(CHECKSUM_TAG);
+        let file = write_cog_with(&writer, vec![CogImage::from_encoded(ifd, tiles)]).await;
+        CountingReader::new(file)
+    }
+
+    async fn read_ifd(reader: &CountingReader, lazy: u64) -> ImageFileDirectory {
+        let mut metadata = TiffMetadataReader::try_open(reader)
+            .await
+            .unwrap()
+            .with_lazy_tile_offsets(lazy);
+        metadata.read_all_ifds(reader).await.unwrap().remove(0)
+    }
+
+    #[tokio::test]
+    async fn test_lazy_tile_offsets() {
+        let reader = test_file().await;
+        let eager = read_ifd(&reader, u64::MAX).await;
+        assert!(!eager.has_lazy_tile_offsets());
+        let mut ifd = read_ifd(&reader, 1000).await;
+        assert!(ifd.has_lazy_tile_offsets());
+        assert_eq!(ifd.tile_offsets(), None);
+        assert_eq!(ifd.tile_byte_counts(), None);
+
+        // Fetching a single tile only fetches its two entries
+        reader.take_counts();
+        let tile = ifd.fetch_tile(10, 2, &reader).await.unwrap();
+        assert_eq!(
+            tile.compressed_bytes(),
+            &Bytes::from(vec![1010_u16 as u8; 256])
+        );
+        let raw = ifd.fetch_tile_raw(10, 2, &reader).await.unwrap();
+        assert_eq!(raw.compressed_bytes(), tile.compressed_bytes());
+        assert_eq!(reader.requests(), 6);
+        let lazy = ifd.lazy_tile_offsets.clone().unwrap();
+        assert_eq!(lazy.loaded_pages(), 0);
+        assert!(ifd.fetch_tile(0, 3, &reader).await.is_err());
+
+        // Windows load the pages of all their tiles: the first and second page here
+        let registry = DecoderRegistry::default();
+        let window = Window::new(7984, 0, 16, 48);
+        let data = ifd.read_window(window, &reader, &registry).await.unwrap();
+        let expected = eager.read_window(window, &reader, &registry).await.unwrap();
+        assert_eq!(data.data(), expected.data());
+        assert_eq!(lazy.loaded_pages(), 2);
+
+        let tiles = ifd.fetch_tiles(&[1, 2], &[0, 0], &reader).await.unwrap();
+        reader.take_counts();
+        ifd.fetch_tiles(&[1, 2], &[0, 0], &reader).await.unwrap();
+        assert_eq!(reader.requests(), 2);
+        assert_eq!(tiles[1].compressed_bytes()[0], 2);
+
+        ifd.load_tile_offsets(&reader).await.unwrap();
+        assert!(!ifd.has_lazy_tile_offsets());
+        assert_eq!(ifd.tile_offsets(), eager.tile_offsets());
+        assert_eq!(ifd.tile_byte_counts(), eager.tile_byte_counts());
+    }
+
+    #[tokio::test]
+    async fn test_lazy_chunk_byte_ranges() {
+        let reader = test_file().await;
+        let eager = read_ifd(&reader, u64::MAX).await;
+        let ifd = read_ifd(&reader, 1000).await;
+        assert!(ifd.has_sequential_chunks().is_err());
+        assert!(ChecksumValidator::from_ifd(&ifd, CHECKSUM_TAG).is_err());
+        assert!(TIFF::new(vec![ifd.clone()]).layout_report().is_err());
+
+        // All pages are needed, not just those of the tiles fetched so far
+        ifd.load_lazy_tile_offsets([0], &reader).await.unwrap();
+        assert!(ifd.has_sequential_chunks().is_err());
+
+        ifd.load_lazy_tile_offsets([PAGE_ENTRIES], &reader)
+            .await
+            .unwrap();
+        assert!(ifd.has_lazy_tile_offsets());
+        assert_eq!(
+            ifd.chunk_byte_ranges().unwrap(),
+            eager.chunk_byte_ranges().unwrap()
+        );
+        assert!(ifd.has_sequential_chunks().unwrap());
+        assert_eq!(
+            TIFF::new(vec![ifd.clone()]).layout_report().unwrap(),
+            TIFF::new(vec![eager]).layout_report().unwrap()
+        );
+        let validator = ChecksumValidator::from_ifd(&ifd, CHECKSUM_TAG)
+            .unwrap()
+            .unwrap();
+        let ifd = ifd.with_byte_transform(Arc::new(validator));
+        let tile = ifd.fetch_tile(1499, 0, &reader).await.unwrap();
+        assert_eq!(tile.compressed_bytes()[0], 1499_u16 as u8);
+    }
+
+    #[tokio::test]
+    async fn test_short_tile_offsets() {
+        // Arrays that run past the end of the file
+        let reader = test_file().await;
+        let end = reader.file().len() as u64;
+        let lazy = LazyTileOffsets::new(
+            ArrayLocation::new(end - 4000, 1500, Type::LONG).unwrap(),
+            ArrayLocation::new(end - 6000, 1500, Type::LONG).unwrap(),
+            Endianness::LittleEndian,
+        );
+        assert!(matches!(
+            lazy.load([0], &reader).await,
+            Err(AsyncTiffError::EndOfFile(4096, 4000))
+        ));
+        assert_eq!(lazy.loaded_pages(), 0);
+        assert_eq!(lazy.get(0), None);
+        assert!(lazy.fetch(999, &reader).await.is_ok());
+        assert!(matches!(
+            lazy.fetch(1000, &reader).await,
+            Err(AsyncTiffError::EndOfFile(4, 0))
+        ));
+        assert!(matches!(
+            lazy.load_all(&reader).await,
+            Err(AsyncTiffError::EndOfFile(6000, 4000))
+        ));
+    }
+}
@@ -0,0 +1,545 @@
+//! LERC-compressed tiles, as written by Esri software and GDAL.
+//!
+//! LERC (Limited Error Raster Compression) stores each tile as a blob whose header records the
+//! maximum error the encoder was allowed per pixel. [`LercDecoder`] decodes the pixels of LERC1
+//! and LERC2 blobs with the `lerc-rs` crate, and is registered in the default
+//! [`DecoderRegistry`](crate::decoder::DecoderRegistry). This module also reads the headers, so
+//! that the precision of Esri COGs can be inspected.
+
+use bytes::Bytes;
+
+use crate::decoder::{Decoder, DeflateDecoder, TileInfo, ZstdDecoder};
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::ifd::ImageFileDirectory;
+use crate::reader::Endianness;
+use crate::tiff::tags::{CompressionMethod, PhotometricInterpretation, Tag};
+
+const LERC1_MAGIC: &[u8] = b"CntZImage ";
+const LERC2_MAGIC: &[u8] = b"Lerc2 ";
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// The private tag in which GDAL records the LERC version and additional compression.
+const LERC_PARAMETERS: Tag = Tag::Unknown(65000);
+
+/// The compression GDAL applies to LERC blobs on top of LERC itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LercAdditionalCompression {
+    /// The blobs are stored as is.
+    None,
+    /// The blobs are compressed with zlib.
+    Deflate,
+    /// The blobs are compressed with Zstandard.
+    Zstd,
+}
+
+/// The contents of the LercParameters tag written by GDAL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LercParameters {
+    version: u32,
+    additional_compression: LercAdditionalCompression,
+}
+
+impl LercParameters {
+    /// The version of LERC the tiles were written with.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The compression applied on top of LERC.
+    pub fn additional_compression(&self) -> LercAdditionalCompression {
+        self.additional_compression
+    }
+}
+
+/// The type of the values of a LERC blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LercDataType {
+    /// Signed 8-bit integers.
+    I8,
+    /// Unsigned 8-bit integers.
+    U8,
+    /// Signed 16-bit integers.
+    I16,
+    /// Unsigned 16-bit integers.
+    U16,
+    /// Signed 32-bit integers.
+    I32,
+    /// Unsigned 32-bit integers.
+    U32,
+    /// 32-bit floats.
+    F32,
+    /// 64-bit floats.
+    F64,
+}
+
+/// The header of the LERC blob of a tile, as read by [`ImageFileDirectory::lerc_info`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LercInfo {
+    lerc1: bool,
+    version: i32,
+    width: u32,
+    height: u32,
+    depth: u32,
+    valid_pixels: Option<u32>,
+    data_type: LercDataType,
+    max_z_error: f64,
+    z_range: Option<(f64, f64)>,
+}
+
+impl LercInfo {
+    /// Parse the header of an uncompressed LERC1 or LERC2 blob.
+    pub fn parse(blob: &[u8]) -> AsyncTiffResult<Self> {
+        if let Some(header) = blob.strip_prefix(LERC2_MAGIC) {
+            Self::parse_lerc2(header)
+        } else if let Some(header) = blob.strip_prefix(LERC1_MAGIC) {
+            Self::parse_lerc1(header)
+        } else {
+            Err(AsyncTiffError::General("Not a LERC blob".to_string()))
+        }
+    }
+
+    fn parse_lerc1(header: &[u8]) -> AsyncTiffResult<Self> {
+        let mut reader = HeaderReader(header);
+        let version = reader.i32()?;
+        let _image_type = reader.i32()?;
+        let height = reader.u32()?;
+        let width = reader.u32()?;
+        let max_z_error = reader.f64()?;
+        Ok(Self {
+            lerc1: true,
+            version,
+            width,
+            height,
+            depth: 1,
+            valid_pixels: None,
+            data_type: LercDataType::F32,
+            max_z_error,
+            z_range: None,
+        })
+    }
+
+    fn parse_lerc2(header: &[u8]) -> AsyncTiffResult<Self> {
+        let mut reader = HeaderReader(header);
+        let version = reader.i32()?;
+        if !(2..=6).contains(&version) {
+            return Err(AsyncTiffError::General(format!(
+                "Unsupported LERC2 version {version}"
+            )));
+        }
+        if version >= 3 {
+            let _checksum = reader.u32()?;
+        }
+        let height = reader.u32()?;
+        let width = reader.u32()?;
+        let depth = if version >= 4 { reader.u32()? } else { 1 };
+        let valid_pixels = reader.u32()?;
+        let _micro_block_size = reader.i32()?;
+        let _blob_size = reader.i32()?;
+        let data_type = match reader.i32()? {
+            0 => LercDataType::I8,
+            1 => LercDataType::U8,
+            2 => LercDataType::I16,
+            3 => LercDataType::U16,
+            4 => LercDataType::I32,
+            5 => LercDataType::U32,
+            6 => LercDataType::F32,
+            7 => LercDataType::F64,
+            data_type => {
+                return Err(AsyncTiffError::General(format!(
+                    "Invalid LERC data type {data_type}"
+                )))
+            }
+        };
+        if version >= 6 {
+            // The number of blobs that follow, and four flag bytes
+            reader.take(8)?;
+        }
+        let max_z_error = reader.f64()?;
+        let z_min = reader.f64()?;
+        let z_max = reader.f64()?;
+        Ok(Self {
+            lerc1: false,
+            version,
+            width,
+            height,
+            depth,
+            valid_pixels: Some(valid_pixels),
+            data_type,
+            max_z_error,
+            z_range: Some((z_min, z_max)),
+        })
+    }
+
+    /// Returns `true` if this is a LERC1 blob, rather than LERC2.
+    pub fn is_lerc1(&self) -> bool {
+        self.lerc1
+    }
+
+    /// The version of the blob's format.
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// The width of the blob in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the blob in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of values per pixel.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of valid pixels, or `None` for LERC1 blobs, which don't record it.
+    pub fn valid_pixels(&self) -> Option<u32> {
+        self.valid_pixels
+    }
+
+    /// The type of the values.
+    pub fn data_type(&self) -> LercDataType {
+        self.data_type
+    }
+
+    /// The maximum error of each decoded value, as allowed when encoding.
+    ///
+    /// For integer data, a maximum error of 0.5 means the data is stored losslessly.
+    pub fn max_z_error(&self) -> f64 {
+        self.max_z_error
+    }
+
+    /// The minimum and maximum valid value, or `None` for LERC1 blobs, which don't record them.
+    pub fn z_range(&self) -> Option<(f64, f64)> {
+        self.z_range
+    }
+}
+
+impl ImageFileDirectory {
+    /// The LERC version and additional compression from GDAL's LercParameters tag, if present.
+    pub fn lerc_parameters(&self) -> Option<LercParameters> {
+        let values = self
+            .other_tags()
+            .get(&LERC_PARAMETERS)?
+            .clone()
+            .into_u32_vec()
+            .ok()?;
+        let additional_compression = match values.get(1).copied().unwrap_or(0) {
+            0 => LercAdditionalCompression::None,
+            1 => LercAdditionalCompression::Deflate,
+            2 => LercAdditionalCompression::Zstd,
+            _ => return None,
+        };
+        Some(LercParameters {
+            version: *values.first()?,
+            additional_compression,
+        })
+    }
+
+    /// Read the LERC header of the compressed bytes of a tile of this IFD, including the maximum
+    /// error allowed when it was encoded.
+    ///
+    /// The additional compression recorded in [`lerc_parameters`](Self::lerc_parameters) is
+    /// undone first.
+    pub fn lerc_info(&self, compressed_bytes: &Bytes) -> AsyncTiffResult<LercInfo> {
+        if self.compression() != CompressionMethod::LERC {
+            return Err(AsyncTiffError::General(
+                "Not a LERC-compressed TIFF".to_string(),
+            ));
+        }
+        let additional_compression = self
+            .lerc_parameters()
+            .map_or(LercAdditionalCompression::None, |params| {
+                params.additional_compression
+            });
+        let decoder: &dyn Decoder = match additional_compression {
+            LercAdditionalCompression::None => return LercInfo::parse(compressed_bytes),
+            LercAdditionalCompression::Deflate => &DeflateDecoder,
+            LercAdditionalCompression::Zstd => &ZstdDecoder,
+        };
+        let blob = decoder.decode_tile(
+            compressed_bytes.clone(),
+            self.photometric_interpretation(),
+            None,
+        )?;
+        LercInfo::parse(&blob)
+    }
+}
+
+/// A decoder for the LERC compression method, using the `lerc-rs` crate.
+///
+/// Blobs that GDAL compressed further with Deflate or ZSTD are recognized by their magic bytes
+/// and decompressed first. Pixels that the blob's mask marks as invalid are decoded as NaN for
+/// floating point data and as 0 otherwise, as libtiff does.
+#[derive(Debug, Clone)]
+pub struct LercDecoder;
+
+impl Decoder for LercDecoder {
+    fn decode_tile(
+        &self,
+        _buffer: Bytes,
+        _photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+    ) -> AsyncTiffResult<Bytes> {
+        Err(AsyncTiffError::General(
+            "Decoding LERC requires the layout of the tile".to_string(),
+        ))
+    }
+
+    fn decode_tile_with_info(
+        &self,
+        buffer: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+        _jpeg_tables: Option<&[u8]>,
+        info: &TileInfo,
+    ) -> AsyncTiffResult<Bytes> {
+        let decoder: &dyn Decoder = if buffer.starts_with(ZSTD_MAGIC) {
+            &ZstdDecoder
+        } else if buffer.starts_with(LERC2_MAGIC) || buffer.starts_with(LERC1_MAGIC) {
+            return decode_lerc(&buffer, info);
+        } else {
+            &DeflateDecoder
+        };
+        let blob = decoder.decode_tile(buffer, photometric_interpretation, None)?;
+        decode_lerc(&blob, info)
+    }
+}
+
+/// Decode an uncompressed LERC blob into the samples of a tile, in the byte order of the file.
+fn decode_lerc(blob: &[u8], info: &TileInfo) -> AsyncTiffResult<Bytes> {
+    let lerc_error = |err| AsyncTiffError::General(format!("Failed to decode LERC data: {err}"));
+    // Check the header before decoding, so that a small blob can't claim a huge raster
+    let header = ::lerc::decode_info(blob).map_err(lerc_error)?;
+    let bits_per_pixel = header.depth as u64 * header.data_type.size() as u64 * 8;
+    if (header.width, header.height, header.bands) != (info.width(), info.height(), 1)
+        || bits_per_pixel != info.bits_per_pixel() as u64
+    {
+        return Err(AsyncTiffError::General(format!(
+            "LERC blob of {}x{} pixels of {bits_per_pixel} bits doesn't match a tile of {}x{} \
+             pixels of {} bits",
+            header.width,
+            header.height,
+            info.width(),
+            info.height(),
+            info.bits_per_pixel()
+        )));
+    }
+    let image = ::lerc::decode(blob).map_err(lerc_error)?;
+    let depth = image.depth as usize;
+    let mask = image.valid_masks.first();
+    let is_valid = |index: usize| mask.is_none_or(|mask| mask.is_valid(index / depth));
+
+    let mut decoded = Vec::with_capacity(info.decoded_bytes() as usize);
+    macro_rules! write_samples {
+        ($values:expr, $invalid:expr) => {
+            for (index, value) in $values.iter().enumerate() {
+                let value = if is_valid(index) { *value } else { $invalid };
+                match info.endianness() {
+                    Endianness::LittleEndian => decoded.extend(value.to_le_bytes()),
+                    Endianness::BigEndian => decoded.extend(value.to_be_bytes()),
+                }
+            }
+        };
+    }
+    match &image.data {
+        ::lerc::SampleData::I8(values) => write_samples!(values, 0),
+        ::lerc::SampleData::U8(values) => write_samples!(values, 0),
+        ::lerc::SampleData::I16(values) => write_samples!(values, 0),
+        ::lerc::SampleData::U16(values) => write_samples!(values, 0),
+        ::lerc::SampleData::I32(values) => write_samples!(values, 0),
+        ::lerc::SampleData::U32(values) => write_samples!(values, 0),
+        ::lerc::SampleData::F32(values) => write_samples!(values, f32::NAN),
+        ::lerc::SampleData::F64(values) => write_samples!(values, f64::NAN),
+    }
+    Ok(decoded.into())
+}
+
+/// Reads little-endian header fields.
+struct HeaderReader<'a>(&'a [u8]);
+
+impl HeaderReader<'_> {
+    fn take(&mut self, len: usize) -> AsyncTiffResult<&[u8]> {
+        if len > self.0.len() {
+            return Err(AsyncTiffError::General(
+                "LERC header is truncated".to_string(),
+            ));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn i32(&mut self) -> AsyncTiffResult<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> AsyncTiffResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> AsyncTiffResult<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    use flate2::write::ZlibEncoder;
+
+    use super::*;
+    use crate::reader::Endianness;
+    use crate::tiff::Value;
+
+    fn lerc2_header(version: i32) -> Vec<u8> {
+        let mut header = LERC2_MAGIC.to_vec();
+        header.extend(version.to_le_bytes());
+        if version >= 3 {
+            header.extend(0u32.to_le_bytes());
+        }
+        header.extend(256u32.to_le_bytes());
+        header.extend(512u32.to_le_bytes());
+        if version >= 4 {
+            header.extend(2u32.to_le_bytes());
+        }
+        for value in [1000, 8, 4096, 6] {
+            header.extend(i32::to_le_bytes(value));
+        }
+        if version >= 6 {
+            header.extend([0; 8]);
+        }
+        for value in [0.01, -5.0, 120.5] {
+            header.extend(f64::to_le_bytes(value));
+        }
+        header
+    }
+
+    #[test]
+    fn test_parse_lerc2() {
+        for version in 2..=6 {
+            let info = LercInfo::parse(&lerc2_header(version)).unwrap();
+            assert!(!info.is_lerc1());
+            assert_eq!(info.version(), version);
+            assert_eq!((info.width(), info.height()), (512, 256));
+            assert_eq!(info.depth(), if version >= 4 { 2 } else { 1 });
+            assert_eq!(info.valid_pixels(), Some(1000));
+            assert_eq!(info.data_type(), LercDataType::F32);
+            assert_eq!(info.max_z_error(), 0.01);
+            assert_eq!(info.z_range(), Some((-5.0, 120.5)));
+        }
+
+        let header = lerc2_header(4);
+        assert!(LercInfo::parse(&header[..header.len() - 1]).is_err());
+        assert!(LercInfo::parse(&lerc2_header(1)).is_err());
+        assert!(LercInfo::parse(b"not lerc").is_err());
+    }
+
+    #[test]
+    fn test_parse_lerc1() {
+        let mut header = LERC1_MAGIC.to_vec();
+        for value in [11, 8, 64, 32] {
+            header.extend(i32::to_le_bytes(value));
+        }
+        header.extend(0.5f64.to_le_bytes());
+        let info = LercInfo::parse(&header).unwrap();
+        assert!(info.is_lerc1());
+        assert_eq!((info.width(), info.height()), (32, 64));
+        assert_eq!(info.max_z_error(), 0.5);
+        assert_eq!(info.valid_pixels(), None);
+    }
+
+    #[test]
+    fn test_lerc_info() {
+        let mut tags = HashMap::from([
+            (Tag::ImageWidth, Value::Unsigned(512)),
+            (Tag::ImageLength, Value::Unsigned(256)),
+            (Tag::BitsPerSample, Value::Short(32)),
+            (Tag::PhotometricInterpretation, Value::Short(1)),
+            (
+                Tag::Compression,
+                Value::Short(CompressionMethod::LERC.to_u16()),
+            ),
+            (Tag::StripOffsets, Value::Unsigned(8)),
+            (Tag::StripByteCounts, Value::Unsigned(100)),
+        ]);
+        let ifd = ImageFileDirectory::from_tags(tags.clone(), Endianness::LittleEndian).unwrap();
+        assert_eq!(ifd.lerc_parameters(), None);
+        let blob = Bytes::from(lerc2_header(3));
+        assert_eq!(ifd.lerc_info(&blob).unwrap().version(), 3);
+
+        tags.insert(
+            LERC_PARAMETERS,
+            Value::List(vec![Value::Unsigned(4), Value::Unsigned(1)]),
+        );
+        let ifd = ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap();
+        let params = ifd.lerc_parameters().unwrap();
+        assert_eq!(params.version(), 4);
+        assert_eq!(
+            params.additional_compression(),
+            LercAdditionalCompression::Deflate
+        );
+        let mut encoder = ZlibEncoder::new(vec![], Default::default());
+        encoder.write_all(&blob).unwrap();
+        let compressed = Bytes::from(encoder.finish().unwrap());
+        assert_eq!(ifd.lerc_info(&compressed).unwrap().max_z_error(), 0.01);
+    }
+
+    #[test]
+    fn test_decode() {
+        use ::lerc::bitmask::BitMask;
+        use ::lerc::Precision;
+
+        let photometric = PhotometricInterpretation::BlackIsZero;
+        let values = (0..12).map(|i| i as f32 * 0.5).collect::<Vec<_>>();
+        let mut mask = BitMask::all_valid(12);
+        mask.set_invalid(5);
+        let blob = ::lerc::encode_slice_masked(4, 3, &values, &mask, Precision::Lossless).unwrap();
+        let info = TileInfo::new(4, 3, 32);
+        let decoded = LercDecoder
+            .decode_tile_with_info(Bytes::from(blob.clone()), photometric, None, &info)
+            .unwrap();
+        let decoded = decoded
+            .chunks(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        for (index, (decoded, value)) in decoded.iter().zip(&values).enumerate() {
+            if index == 5 {
+                assert!(decoded.is_nan());
+            } else {
+                assert_eq!(decoded, value);
+            }
+        }
+
+        // Samples are written in the byte order of the file, below any additional compression
+        let mut encoder = ZlibEncoder::new(vec![], Default::default());
+        encoder.write_all(&blob).unwrap();
+        let compressed = Bytes::from(encoder.finish().unwrap());
+        let big_endian = info.with_endianness(Endianness::BigEndian);
+        let decoded = LercDecoder
+            .decode_tile_with_info(compressed, photometric, None, &big_endian)
+            .unwrap();
+        assert_eq!(decoded[..8], [0, 0, 0, 0, 0x3f, 0, 0, 0]);
+        let compressed = Bytes::from(zstd::bulk::compress(&blob, 3).unwrap());
+        let decoded = LercDecoder
+            .decode_tile_with_info(compressed, photometric, None, &big_endian)
+            .unwrap();
+        assert_eq!(decoded.len(), 48);
+
+        let blob = Bytes::from(blob);
+        assert!(LercDecoder
+            .decode_tile(blob.clone(), photometric, None)
+            .is_err());
+        for info in [TileInfo::new(3, 4, 32), TileInfo::new(4, 3, 64)] {
+            assert!(LercDecoder
+                .decode_tile_with_info(blob.clone(), photometric, None, &info)
+                .is_err());
+        }
+    }
+}
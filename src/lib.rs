@@ -1,18 +1,79 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 
+mod byte_transform;
+mod checksum;
 pub mod reader;
 // TODO: maybe rename this mod
 mod cog;
+mod cog_writer;
+#[cfg(feature = "chrono")]
+mod date_time;
 pub mod decoder;
+pub mod encoder;
 pub mod error;
+mod estimate;
+pub mod extra_tags;
 pub mod geo;
+#[cfg(feature = "golden")]
+pub mod golden;
 mod ifd;
+mod ifd_builder;
+mod jpeg_tables;
+mod layout;
+mod lazy_offsets;
+#[cfg(feature = "lerc")]
+pub mod lerc;
 pub mod metadata;
+#[cfg(feature = "object_store")]
+mod open;
+mod overview;
+mod overview_builder;
+pub mod pipeline;
 pub mod predictor;
+mod probe;
+mod pyramid;
+mod stack;
+mod statistics;
+mod support;
+pub mod tags;
+#[cfg(test)]
+pub(crate) mod test_util;
+#[cfg(feature = "testgen")]
+pub mod testgen;
+mod thumbnail;
 pub mod tiff;
 mod tile;
+mod tile_stream;
+#[cfg(feature = "warp")]
+pub mod warp;
+mod window;
+pub mod writer;
 
+pub use byte_transform::ByteTransform;
+pub use checksum::{ChecksumValidator, FileChecksums, TileChecksum};
 pub use cog::TIFF;
-pub use ifd::ImageFileDirectory;
-pub use tile::Tile;
+pub use cog_writer::{CogImage, CogWriter};
+pub use estimate::ReadEstimate;
+pub use ifd::{IfdWarning, ImageFileDirectory};
+pub use ifd_builder::IfdBuilder;
+pub use jpeg_tables::{merge_jpeg_tables, JpegTableIssue, JpegTableReport};
+pub use layout::{IfdLayout, LayoutReport};
+#[cfg(feature = "object_store")]
+pub use open::{open_many, HeaderCache, OpenOptions};
+pub use overview::{OverviewIssue, OverviewReport};
+pub use overview_builder::OverviewResampling;
+#[cfg(feature = "object_store")]
+pub use probe::probe;
+pub use probe::ProbeInfo;
+pub use pyramid::{Pyramid, PyramidLevel};
+pub use stack::{Stack, StackData};
+pub use statistics::{BandStatistics, Histogram};
+pub use support::UnsupportedFeature;
+pub use thumbnail::Thumbnail;
+pub use tile::{
+    EdgeTiles, FetchInfo, RawTile, RowGroup, RowGroups, Tile, TilePayload, TruncatedTiles,
+};
+pub use window::{
+    MaskedWindowData, ReadWindowOptions, RowOrigin, SampleLayout, Window, WindowData,
+};
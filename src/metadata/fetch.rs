@@ -4,7 +4,7 @@ use bytes::Bytes;
 use futures::future::BoxFuture;
 use futures::FutureExt;
 
-use crate::error::AsyncTiffResult;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
 use crate::reader::{AsyncFileReader, EndianAwareReader, Endianness};
 
 /// A data source that can be used with [`TiffMetadataReader`] and [`ImageFileDirectoryReader`] to
@@ -60,6 +60,44 @@ impl<F: MetadataFetch> MetadataFetch for PrefetchBuffer<F> {
     }
 }
 
+/// Serves ranges within a block of the file fetched beforehand from memory, and fetches others.
+pub(crate) struct BlockFetch<'a, F: MetadataFetch> {
+    fetch: &'a F,
+    start: u64,
+    block: Bytes,
+}
+
+impl<'a, F: MetadataFetch> BlockFetch<'a, F> {
+    /// Fetch the bytes of `range` in one request.
+    pub(crate) async fn new(fetch: &'a F, range: Range<u64>) -> AsyncTiffResult<Self> {
+        let block = fetch.fetch(range.clone()).await?;
+        Ok(Self {
+            fetch,
+            start: range.start,
+            block,
+        })
+    }
+
+    /// The bytes of the block.
+    pub(crate) fn block(&self) -> &Bytes {
+        &self.block
+    }
+}
+
+impl<F: MetadataFetch> MetadataFetch for BlockFetch<'_, F> {
+    fn fetch(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        let end = self.start + self.block.len() as u64;
+        if self.start <= range.start && range.start <= range.end && range.end <= end {
+            let usize_range =
+                (range.start - self.start) as usize..(range.end - self.start) as usize;
+            let result = self.block.slice(usize_range);
+            async { Ok(result) }.boxed()
+        } else {
+            self.fetch.fetch(range)
+        }
+    }
+}
+
 pub(crate) struct MetadataCursor<'a, F: MetadataFetch> {
     fetch: &'a F,
     offset: u64,
@@ -92,39 +130,36 @@ impl<'a, F: MetadataFetch> MetadataCursor<'a, F> {
         self.offset = offset;
     }
 
+    /// The byte offset of the cursor.
+    pub(crate) fn position(&self) -> u64 {
+        self.offset
+    }
+
     /// Advance cursor position by a set amount
     pub(crate) fn advance(&mut self, amount: u64) {
-        self.offset += amount;
+        self.offset = self.offset.saturating_add(amount);
     }
 
     /// Read the given number of bytes, advancing the internal cursor state by the same amount.
     pub(crate) async fn read(&mut self, length: u64) -> AsyncTiffResult<EndianAwareReader> {
-        let range = self.offset as _..(self.offset + length) as _;
-        self.offset += length;
+        let end = self
+            .offset
+            .checked_add(length)
+            .ok_or(AsyncTiffError::General(format!(
+                "Cannot read {length} bytes at offset {}",
+                self.offset
+            )))?;
+        let range = self.offset..end;
+        self.offset = end;
         let bytes = self.fetch.fetch(range).await?;
         Ok(EndianAwareReader::new(bytes, self.endianness))
     }
 
-    /// Read a u8 from the cursor, advancing the internal state by 1 byte.
-    pub(crate) async fn read_u8(&mut self) -> AsyncTiffResult<u8> {
-        self.read(1).await?.read_u8()
-    }
-
-    /// Read a i8 from the cursor, advancing the internal state by 1 byte.
-    pub(crate) async fn read_i8(&mut self) -> AsyncTiffResult<i8> {
-        self.read(1).await?.read_i8()
-    }
-
     /// Read a u16 from the cursor, advancing the internal state by 2 bytes.
     pub(crate) async fn read_u16(&mut self) -> AsyncTiffResult<u16> {
         self.read(2).await?.read_u16()
     }
 
-    /// Read a i16 from the cursor, advancing the internal state by 2 bytes.
-    pub(crate) async fn read_i16(&mut self) -> AsyncTiffResult<i16> {
-        self.read(2).await?.read_i16()
-    }
-
     /// Read a u32 from the cursor, advancing the internal state by 4 bytes.
     pub(crate) async fn read_u32(&mut self) -> AsyncTiffResult<u32> {
         self.read(4).await?.read_u32()
@@ -145,10 +180,6 @@ impl<'a, F: MetadataFetch> MetadataCursor<'a, F> {
         self.read(8).await?.read_i64()
     }
 
-    pub(crate) async fn read_f32(&mut self) -> AsyncTiffResult<f32> {
-        self.read(4).await?.read_f32()
-    }
-
     pub(crate) async fn read_f64(&mut self) -> AsyncTiffResult<f64> {
         self.read(8).await?.read_f64()
     }
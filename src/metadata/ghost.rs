@@ -0,0 +1,99 @@
+//! GDAL "ghost" structural metadata.
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::metadata::MetadataFetch;
+
+const PREFIX: &str = "GDAL_STRUCTURAL_METADATA_SIZE=";
+/// The prefix, six digits for the size and the trailing `" bytes\n"`.
+const HEADER_LEN: u64 = PREFIX.len() as u64 + 13;
+
+/// The structural metadata GDAL writes between the TIFF header and the first IFD of a
+/// Cloud-Optimized GeoTIFF.
+///
+/// This is a list of `KEY=VALUE` lines, such as `LAYOUT=IFDS_BEFORE_DATA` or
+/// `BLOCK_ORDER=ROW_MAJOR`, describing how the file was laid out when it was written. Read it with
+/// [`TiffMetadataReader::read_ghost_metadata`](crate::metadata::TiffMetadataReader::read_ghost_metadata).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GhostMetadata {
+    entries: Vec<(String, String)>,
+}
+
+impl GhostMetadata {
+    /// Read the ghost metadata directly following a TIFF header of `header_len` bytes.
+    ///
+    /// Returns `None` if the file has no ghost metadata.
+    pub(crate) async fn read<F: MetadataFetch>(
+        fetch: &F,
+        header_len: u64,
+    ) -> AsyncTiffResult<Option<Self>> {
+        let header = fetch.fetch(header_len..header_len + HEADER_LEN).await?;
+        let Some(size) = std::str::from_utf8(&header)
+            .ok()
+            .and_then(|header| header.strip_prefix(PREFIX))
+            .and_then(|rest| rest.strip_suffix(" bytes\n"))
+        else {
+            return Ok(None);
+        };
+        let size: u64 = size.parse().map_err(|_| {
+            AsyncTiffError::General(format!("invalid ghost metadata size {size:?}"))
+        })?;
+
+        let start = header_len + HEADER_LEN;
+        let body = fetch.fetch(start..start + size).await?;
+        let body = std::str::from_utf8(&body).map_err(|err| {
+            AsyncTiffError::General(format!("ghost metadata is not valid UTF-8: {err}"))
+        })?;
+        Ok(Some(Self::parse(body)))
+    }
+
+    fn parse(body: &str) -> Self {
+        let entries = body
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        Self { entries }
+    }
+
+    /// Look up the value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Iterate over the `(key, value)` entries in file order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Whether all IFDs were written before the image data, as required by a Cloud-Optimized
+    /// GeoTIFF.
+    pub fn ifds_before_data(&self) -> bool {
+        self.get("LAYOUT") == Some("IFDS_BEFORE_DATA")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_ghost_metadata() {
+        let body = "LAYOUT=IFDS_BEFORE_DATA\nBLOCK_ORDER=ROW_MAJOR\n ";
+        let mut file = b"II*\0\0\0\0\0".to_vec();
+        file.extend(format!("{PREFIX}{:06} bytes\n{body}", body.len()).as_bytes());
+        let fetch = Bytes::from(file);
+
+        let ghost = GhostMetadata::read(&fetch, 8).await.unwrap().unwrap();
+        assert!(ghost.ifds_before_data());
+        assert_eq!(ghost.get("BLOCK_ORDER"), Some("ROW_MAJOR"));
+        assert_eq!(ghost.iter().count(), 2);
+
+        let fetch = Bytes::from(vec![0; 64]);
+        assert!(GhostMetadata::read(&fetch, 8).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,54 @@
+/// Limits on the memory used to read TIFF metadata, for reading untrusted files.
+///
+/// A forged tag count can otherwise make a reader allocate gigabytes for a single tag. Attach
+/// limits to a [`TiffMetadataReader`](super::TiffMetadataReader) with
+/// [`with_limits`](super::TiffMetadataReader::with_limits); exceeding any of them fails with
+/// [`AsyncTiffError::LimitExceeded`](crate::error::AsyncTiffError::LimitExceeded).
+///
+/// By default, there are no limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetadataLimits {
+    max_tag_value_bytes: u64,
+    max_metadata_bytes: u64,
+}
+
+impl MetadataLimits {
+    /// No limits at all.
+    pub fn unlimited() -> Self {
+        Self {
+            max_tag_value_bytes: u64::MAX,
+            max_metadata_bytes: u64::MAX,
+        }
+    }
+
+    /// Set the maximum size in bytes of a single tag value, as stored in the file.
+    ///
+    /// This is checked against the tag's count before its value is read.
+    pub fn with_max_tag_value_bytes(mut self, max_tag_value_bytes: u64) -> Self {
+        self.max_tag_value_bytes = max_tag_value_bytes;
+        self
+    }
+
+    /// Set the maximum memory in bytes held by the tag values of all IFDs read, such as tile
+    /// offsets and strings.
+    pub fn with_max_metadata_bytes(mut self, max_metadata_bytes: u64) -> Self {
+        self.max_metadata_bytes = max_metadata_bytes;
+        self
+    }
+
+    /// The maximum size in bytes of a single tag value, as stored in the file.
+    pub fn max_tag_value_bytes(&self) -> u64 {
+        self.max_tag_value_bytes
+    }
+
+    /// The maximum memory in bytes held by the tag values of all IFDs read.
+    pub fn max_metadata_bytes(&self) -> u64 {
+        self.max_metadata_bytes
+    }
+}
+
+impl Default for MetadataLimits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
@@ -5,6 +5,7 @@
 //! We can use [`TiffMetadataReader::read_all_ifds`] to read all IFDs up front:
 //!
 //! ```
+//! # #[cfg(feature = "object_store")]
 //! # tokio_test::block_on(async {
 //! use std::env::current_dir;
 //! use std::sync::Arc;
@@ -43,7 +44,7 @@
 //!     .read_all_ifds(&prefetch_reader)
 //!     .await
 //!     .unwrap();
-//! # })
+//! # });
 //! ```
 //!
 //!
@@ -59,7 +60,11 @@
 //!
 
 mod fetch;
+mod ghost;
+mod limits;
 mod reader;
 
 pub use fetch::{MetadataFetch, PrefetchBuffer};
-pub use reader::{ImageFileDirectoryReader, TiffMetadataReader};
+pub use ghost::GhostMetadata;
+pub use limits::MetadataLimits;
+pub use reader::{parse_ifd_entries, ImageFileDirectoryReader, TiffMetadataReader};
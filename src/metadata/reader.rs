@@ -1,11 +1,17 @@
 use std::collections::HashMap;
 use std::io::Read;
+use std::ops::Range;
+use std::sync::Arc;
 
 use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
 
 use crate::error::{AsyncTiffError, AsyncTiffResult};
-use crate::metadata::fetch::MetadataCursor;
-use crate::metadata::MetadataFetch;
+use crate::extra_tags::ExtraTagsRegistry;
+use crate::lazy_offsets::{ArrayLocation, LazyTileOffsets};
+use crate::metadata::fetch::{BlockFetch, MetadataCursor};
+use crate::metadata::{GhostMetadata, MetadataFetch, MetadataLimits};
 use crate::reader::Endianness;
 use crate::tiff::tags::{Tag, Type};
 use crate::tiff::{TiffError, TiffFormatError, Value};
@@ -24,6 +30,13 @@ pub struct TiffMetadataReader {
     endianness: Endianness,
     bigtiff: bool,
     next_ifd_offset: Option<u64>,
+    lenient: bool,
+    extra_tags: ExtraTagsRegistry,
+    limits: MetadataLimits,
+    /// The memory held by the tag values read so far, counted against `limits`.
+    metadata_bytes: u64,
+    /// The most tiles of an IFD whose tile offsets are read with it.
+    lazy_tile_offsets: u64,
 }
 
 impl TiffMetadataReader {
@@ -79,9 +92,52 @@ impl TiffMetadataReader {
             endianness,
             bigtiff,
             next_ifd_offset: Some(first_ifd_location),
+            lenient: false,
+            extra_tags: ExtraTagsRegistry::default(),
+            limits: MetadataLimits::default(),
+            metadata_bytes: 0,
+            lazy_tile_offsets: u64::MAX,
         })
     }
 
+    /// Set whether to tolerate malformed metadata instead of returning an error.
+    ///
+    /// In lenient mode, tags whose values cannot be read and malformed GeoKeys are skipped, the
+    /// latter with an [`IfdWarning`](crate::IfdWarning), and [`read_all_ifds`](Self::read_all_ifds) returns the IFDs read so far when a later IFD in the
+    /// chain cannot be read. Defaults to `false`.
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Pass the tags handled by the plugins of `extra_tags` to them when reading IFDs.
+    ///
+    /// See [`ImageFileDirectory::from_tags_with_extra_tags`].
+    pub fn with_extra_tags(mut self, extra_tags: ExtraTagsRegistry) -> Self {
+        self.extra_tags = extra_tags;
+        self
+    }
+
+    /// Fail with [`AsyncTiffError::LimitExceeded`] rather than read tag values beyond `limits`.
+    ///
+    /// The metadata memory limit applies to all IFDs read by this reader together.
+    pub fn with_limits(mut self, limits: MetadataLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Don't read the TileOffsets and TileByteCounts of IFDs with more than `max_tiles` tiles
+    /// up front, but only the parts locating the tiles that are fetched, as they are fetched.
+    ///
+    /// The offsets of huge images, such as BigTIFFs of millions of tiles, can take more memory and
+    /// time to read than the few tiles a reader needs. See
+    /// [`ImageFileDirectory::has_lazy_tile_offsets`]. Defaults to `u64::MAX`, i.e. always
+    /// reading them with the IFD.
+    pub fn with_lazy_tile_offsets(mut self, max_tiles: u64) -> Self {
+        self.lazy_tile_offsets = max_tiles;
+        self
+    }
+
     /// Returns the endianness of the file.
     pub fn endianness(&self) -> Endianness {
         self.endianness
@@ -92,6 +148,15 @@ impl TiffMetadataReader {
         self.bigtiff
     }
 
+    /// Read the [GDAL ghost metadata](GhostMetadata) following the file header, if any.
+    pub async fn read_ghost_metadata<F: MetadataFetch>(
+        &self,
+        fetch: &F,
+    ) -> AsyncTiffResult<Option<GhostMetadata>> {
+        let header_len = if self.bigtiff { 16 } else { 8 };
+        GhostMetadata::read(fetch, header_len).await
+    }
+
     /// Returns `true` if there are more IFDs to read.
     pub fn has_next_ifd(&self) -> bool {
         self.next_ifd_offset.is_some()
@@ -112,10 +177,26 @@ impl TiffMetadataReader {
         fetch: &F,
     ) -> AsyncTiffResult<Option<ImageFileDirectory>> {
         if let Some(ifd_start) = self.next_ifd_offset {
+            let remaining = self.limits.max_metadata_bytes() - self.metadata_bytes;
             let ifd_reader =
                 ImageFileDirectoryReader::open(fetch, ifd_start, self.bigtiff, self.endianness)
-                    .await?;
-            let ifd = ifd_reader.read(fetch).await?;
+                    .await?
+                    .with_limits(self.limits.with_max_metadata_bytes(remaining))
+                    .with_lazy_tile_offsets(self.lazy_tile_offsets);
+            let (tags, metadata_range, lazy_tile_offsets) = ifd_reader
+                .read_tags_with_range(fetch, self.lenient, &[])
+                .await?;
+            self.metadata_bytes += tags.values().map(|v| v.heap_size() as u64).sum::<u64>();
+            let mut ifd = ifd_reader
+                .finish_ifd(
+                    fetch,
+                    tags,
+                    lazy_tile_offsets,
+                    &self.extra_tags,
+                    self.lenient,
+                )
+                .await?;
+            ifd.metadata_range = Some(metadata_range);
             let next_ifd_offset = ifd_reader.finish(fetch).await?;
             self.next_ifd_offset = next_ifd_offset;
             Ok(Some(ifd))
@@ -130,8 +211,16 @@ impl TiffMetadataReader {
         fetch: &F,
     ) -> AsyncTiffResult<Vec<ImageFileDirectory>> {
         let mut ifds = vec![];
-        while let Some(ifd) = self.read_next_ifd(fetch).await? {
-            ifds.push(ifd);
+        loop {
+            match self.read_next_ifd(fetch).await {
+                Ok(Some(ifd)) => ifds.push(ifd),
+                Ok(None) => break,
+                Err(_) if self.lenient && !ifds.is_empty() => {
+                    self.next_ifd_offset = None;
+                    break;
+                }
+                Err(err) => return Err(err),
+            }
         }
         Ok(ifds)
     }
@@ -157,6 +246,9 @@ pub struct ImageFileDirectoryReader {
     ifd_entry_byte_size: u64,
     /// The number of bytes that the value for the number of tags takes up.
     tag_count_byte_size: u64,
+    limits: MetadataLimits,
+    /// The most tiles whose tile offsets are read with the IFD.
+    lazy_tile_offsets: u64,
 }
 
 impl ImageFileDirectoryReader {
@@ -194,9 +286,24 @@ impl ImageFileDirectoryReader {
             tag_count,
             tag_count_byte_size,
             ifd_start_offset,
+            limits: MetadataLimits::default(),
+            lazy_tile_offsets: u64::MAX,
         })
     }
 
+    /// Fail with [`AsyncTiffError::LimitExceeded`] rather than read tag values beyond `limits`.
+    pub fn with_limits(mut self, limits: MetadataLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Read the tile offsets of IFDs with more than `max_tiles` tiles lazily, as
+    /// [`TiffMetadataReader::with_lazy_tile_offsets`] does.
+    pub fn with_lazy_tile_offsets(mut self, max_tiles: u64) -> Self {
+        self.lazy_tile_offsets = max_tiles;
+        self
+    }
+
     /// Manually read the tag with the specified index.
     ///
     /// Panics if the tag index is out of range of the tag count.
@@ -209,32 +316,250 @@ impl ImageFileDirectoryReader {
         tag_idx: u64,
     ) -> AsyncTiffResult<(Tag, Value)> {
         assert!(tag_idx < self.tag_count);
-        let tag_offset =
-            self.ifd_start_offset + self.tag_count_byte_size + (self.ifd_entry_byte_size * tag_idx);
-        let (tag_name, tag_value) =
-            read_tag(fetch, tag_offset, self.endianness, self.bigtiff).await?;
+        let tag_offset = self.entry_offset(tag_idx);
+        let (tag_name, tag_value) = read_tag(
+            fetch,
+            tag_offset,
+            self.endianness,
+            self.bigtiff,
+            self.limits.max_tag_value_bytes(),
+        )
+        .await?;
         Ok((tag_name, tag_value))
     }
 
+    /// The byte offset of the entry with index `tag_idx`.
+    ///
+    /// This saturates rather than overflowing for a corrupt tag count, so that reading at the
+    /// offset fails instead.
+    fn entry_offset(&self, tag_idx: u64) -> u64 {
+        self.ifd_start_offset
+            .saturating_add(self.tag_count_byte_size)
+            .saturating_add(self.ifd_entry_byte_size.saturating_mul(tag_idx))
+    }
+
     /// Read all tags out of this IFD.
     ///
     /// Keep in mind that you'll still need to call [`finish`][Self::finish] to get the byte offset
     /// of the next IFD.
     pub async fn read<F: MetadataFetch>(&self, fetch: &F) -> AsyncTiffResult<ImageFileDirectory> {
-        let mut tags = HashMap::with_capacity(self.tag_count as usize);
-        for tag_idx in 0..self.tag_count {
-            let (tag, value) = self.read_tag(fetch, tag_idx).await?;
-            tags.insert(tag, value);
+        let (tags, _, lazy_tile_offsets) = self.read_tags_with_range(fetch, false, &[]).await?;
+        self.finish_ifd(fetch, tags, lazy_tile_offsets, &Default::default(), false)
+            .await
+    }
+
+    /// Read all tags out of this IFD, skipping tags whose values cannot be read.
+    ///
+    /// This still returns an error if a tag required by [`ImageFileDirectory::from_tags`] is
+    /// missing or invalid.
+    pub async fn read_lenient<F: MetadataFetch>(
+        &self,
+        fetch: &F,
+    ) -> AsyncTiffResult<ImageFileDirectory> {
+        let (tags, _, lazy_tile_offsets) = self.read_tags_with_range(fetch, true, &[]).await?;
+        self.finish_ifd(fetch, tags, lazy_tile_offsets, &Default::default(), true)
+            .await
+    }
+
+    /// Read all tags out of this IFD except `skip`, whose values are not fetched.
+    ///
+    /// This avoids fetching large values that aren't needed, such as the tile offsets and byte
+    /// counts when only the properties of the image are of interest. Skipping tags required by
+    /// [`ImageFileDirectory::from_tags`] fails.
+    pub async fn read_skipping<F: MetadataFetch>(
+        &self,
+        fetch: &F,
+        skip: &[Tag],
+    ) -> AsyncTiffResult<ImageFileDirectory> {
+        let (tags, _, lazy_tile_offsets) = self.read_tags_with_range(fetch, false, skip).await?;
+        self.finish_ifd(fetch, tags, lazy_tile_offsets, &Default::default(), false)
+            .await
+    }
+
+    /// Create the IFD from the `tags` read out of this IFD, and set the fields that aren't read
+    /// from its tags: the lazy tile offsets and the DateTimeOriginal of the EXIF IFD.
+    ///
+    /// Every method reading an IFD goes through this, so that they all return the same fields.
+    async fn finish_ifd<F: MetadataFetch>(
+        &self,
+        fetch: &F,
+        tags: HashMap<Tag, Value>,
+        lazy_tile_offsets: Option<LazyTileOffsets>,
+        extra_tags: &ExtraTagsRegistry,
+        lenient: bool,
+    ) -> AsyncTiffResult<ImageFileDirectory> {
+        let date_time_original = self.read_exif_date_time_original(fetch, &tags).await;
+        let mut ifd =
+            ImageFileDirectory::from_tags_lenient(tags, extra_tags, self.endianness, lenient)?;
+        ifd.lazy_tile_offsets = lazy_tile_offsets.map(Arc::new);
+        ifd.date_time_original = date_time_original;
+        Ok(ifd)
+    }
+
+    /// Read the DateTimeOriginal tag of the EXIF IFD that the ExifIFD pointer in `tags` points to.
+    ///
+    /// This is only done with the `chrono` feature, for IFDs with such a pointer. The entries of
+    /// the EXIF IFD are fetched in one request, and only the value of that tag is read. The EXIF
+    /// IFD is optional, so it being unreadable isn't an error, and this returns `None` instead.
+    async fn read_exif_date_time_original<F: MetadataFetch>(
+        &self,
+        fetch: &F,
+        tags: &HashMap<Tag, Value>,
+    ) -> Option<String> {
+        if !cfg!(feature = "chrono") {
+            return None;
+        }
+        let offset = tags.get(&Tag::ExifIfd)?.clone().into_u64().ok()?;
+        let exif = ImageFileDirectoryReader::open(fetch, offset, self.bigtiff, self.endianness)
+            .await
+            .ok()?
+            .with_limits(self.limits);
+        let tag_count = exif.tag_count.min(u16::MAX as u64);
+        let entries = BlockFetch::new(fetch, exif.entry_offset(0)..exif.entry_offset(tag_count))
+            .await
+            .ok()?;
+        let tag_idx = entries
+            .block()
+            .chunks_exact(exif.ifd_entry_byte_size as usize)
+            .position(|entry| {
+                let tag = match self.endianness {
+                    Endianness::LittleEndian => u16::from_le_bytes([entry[0], entry[1]]),
+                    Endianness::BigEndian => u16::from_be_bytes([entry[0], entry[1]]),
+                };
+                Tag::from_u16_exhaustive(tag) == Tag::DateTimeOriginal
+            })?;
+        let (_, value) = exif.read_tag(&entries, tag_idx as u64).await.ok()?;
+        value.into_string().ok()
+    }
+
+    /// Read the raw values of all tags, skipping unreadable ones if `lenient`.
+    async fn read_tags<F: MetadataFetch>(
+        &self,
+        fetch: &F,
+        lenient: bool,
+    ) -> AsyncTiffResult<HashMap<Tag, Value>> {
+        Ok(self.read_tags_with_range(fetch, lenient, &[]).await?.0)
+    }
+
+    /// Like [`read_tags`](Self::read_tags), also returning the range of bytes spanned by the
+    /// entries, the next IFD offset and the tag values stored outside of the entries, and the
+    /// tile offsets that are read lazily.
+    ///
+    /// The values of the tags in `skip` and of lazy tile offsets are not read, and don't count
+    /// towards the range.
+    async fn read_tags_with_range<F: MetadataFetch>(
+        &self,
+        fetch: &F,
+        lenient: bool,
+        skip: &[Tag],
+    ) -> AsyncTiffResult<(HashMap<Tag, Value>, Range<u64>, Option<LazyTileOffsets>)> {
+        let next_ifd_offset_size = if self.bigtiff { 8 } else { 4 };
+        let mut range = self.ifd_start_offset
+            ..self
+                .entry_offset(self.tag_count)
+                .saturating_add(next_ifd_offset_size);
+        // The tag count may be corrupt, so don't trust it for more than a u16 count of tags.
+        let mut tags = HashMap::with_capacity(self.tag_count.min(u16::MAX as u64) as usize);
+        let mut metadata_bytes = 0;
+        let mut lazy: HashMap<Tag, (u64, ArrayLocation)> = HashMap::new();
+        let mut tag_offsets = (0..self.tag_count).map(|tag_idx| self.entry_offset(tag_idx));
+        let mut eager = vec![];
+        loop {
+            let tag_offset = match tag_offsets.next() {
+                Some(tag_offset) => tag_offset,
+                // Tile offsets and byte counts are only lazy together
+                None if lazy.len() == 1 => lazy.drain().next().unwrap().1 .0,
+                None => break,
+            };
+            if !skip.is_empty() || self.lazy_tile_offsets != u64::MAX {
+                let mut cursor =
+                    MetadataCursor::new_with_offset(fetch, self.endianness, tag_offset);
+                let tag = Tag::from_u16_exhaustive(cursor.read_u16().await?);
+                if skip.contains(&tag) {
+                    continue;
+                }
+                if matches!(tag, Tag::TileOffsets | Tag::TileByteCounts)
+                    && !eager.contains(&tag_offset)
+                {
+                    if let Some(location) = self.lazy_location(&mut cursor).await? {
+                        lazy.insert(tag, (tag_offset, location));
+                        continue;
+                    }
+                }
+            }
+            eager.push(tag_offset);
+            let max_value_bytes = self.limits.max_tag_value_bytes();
+            match read_tag_with_range(
+                fetch,
+                tag_offset,
+                self.endianness,
+                self.bigtiff,
+                max_value_bytes,
+            )
+            .await
+            {
+                Ok((tag, value, value_range)) => {
+                    if let Some(value_range) = value_range {
+                        range.start = range.start.min(value_range.start);
+                        range.end = range.end.max(value_range.end);
+                    }
+                    metadata_bytes += value.heap_size() as u64;
+                    if metadata_bytes > self.limits.max_metadata_bytes() {
+                        return Err(AsyncTiffError::LimitExceeded {
+                            what: "Metadata size",
+                            size: metadata_bytes,
+                            limit: self.limits.max_metadata_bytes(),
+                        });
+                    }
+                    tags.insert(tag, value);
+                }
+                Err(_) if lenient => {}
+                Err(err) => return Err(err),
+            }
+        }
+        let lazy_tile_offsets = match (lazy.get(&Tag::TileOffsets), lazy.get(&Tag::TileByteCounts))
+        {
+            (Some((_, offsets)), Some((_, byte_counts))) => Some(LazyTileOffsets::new(
+                *offsets,
+                *byte_counts,
+                self.endianness,
+            )),
+            _ => None,
+        };
+        Ok((tags, range, lazy_tile_offsets))
+    }
+
+    /// The location of the value of the entry at `cursor`, just after its tag, if it is an
+    /// array of more than [`with_lazy_tile_offsets`](Self::with_lazy_tile_offsets) tile offsets
+    /// or byte counts stored outside of the entry.
+    async fn lazy_location<F: MetadataFetch>(
+        &self,
+        cursor: &mut MetadataCursor<'_, F>,
+    ) -> AsyncTiffResult<Option<ArrayLocation>> {
+        let Some(field_type) = Type::from_u16(cursor.read_u16().await?) else {
+            return Ok(None);
+        };
+        let (count, offset) = if self.bigtiff {
+            (cursor.read_u64().await?, cursor.read_u64().await?)
+        } else {
+            (
+                cursor.read_u32().await?.into(),
+                cursor.read_u32().await?.into(),
+            )
+        };
+        let inline_bytes = if self.bigtiff { 8 } else { 4 };
+        if count <= self.lazy_tile_offsets
+            || count.saturating_mul(type_size(field_type)) <= inline_bytes
+        {
+            return Ok(None);
         }
-        ImageFileDirectory::from_tags(tags, self.endianness)
+        Ok(ArrayLocation::new(offset, count, field_type))
     }
 
     /// Finish this reader, reading the byte offset of the next IFD
     pub async fn finish<F: MetadataFetch>(self, fetch: &F) -> AsyncTiffResult<Option<u64>> {
         // The byte offset for reading the next ifd
-        let next_ifd_byte_offset = self.ifd_start_offset
-            + self.tag_count_byte_size
-            + (self.ifd_entry_byte_size * self.tag_count);
+        let next_ifd_byte_offset = self.entry_offset(self.tag_count);
         let mut cursor =
             MetadataCursor::new_with_offset(fetch, self.endianness, next_ifd_byte_offset);
 
@@ -253,30 +578,122 @@ impl ImageFileDirectoryReader {
     }
 }
 
+/// Parse the entries of the IFD at byte `ifd_offset` of `bytes`, which holds the start of a TIFF
+/// file.
+///
+/// This is a synchronous, self-contained counterpart to [`ImageFileDirectoryReader`] for
+/// untrusted input, e.g. as a fuzzing target or to validate uploads before serving them. It never
+/// panics, and all allocations are bounded by the length of `bytes`: an entry or value count that
+/// extends past the end of the input, such as `count = u64::MAX`, is an error.
+///
+/// Pass the result to [`ImageFileDirectory::from_tags`] to interpret the entries.
+pub fn parse_ifd_entries(
+    bytes: &[u8],
+    ifd_offset: u64,
+    endianness: Endianness,
+    bigtiff: bool,
+) -> AsyncTiffResult<HashMap<Tag, Value>> {
+    let fetch = SliceFetch(Bytes::copy_from_slice(bytes));
+    async {
+        let reader =
+            ImageFileDirectoryReader::open(&fetch, ifd_offset, bigtiff, endianness).await?;
+        if reader.entry_offset(reader.tag_count) > bytes.len() as u64 {
+            return Err(AsyncTiffError::General(format!(
+                "IFD at offset {ifd_offset} with {} entries extends past the end of the input",
+                reader.tag_count
+            )));
+        }
+        reader.read_tags(&fetch, false).await
+    }
+    .now_or_never()
+    // Fetching from memory never waits, so this is unreachable.
+    .unwrap_or_else(|| {
+        Err(AsyncTiffError::General(
+            "Parsing did not complete".to_string(),
+        ))
+    })
+}
+
+/// A [`MetadataFetch`] over an in-memory buffer that fails, rather than panics, on out of bounds
+/// ranges.
+struct SliceFetch(Bytes);
+
+impl MetadataFetch for SliceFetch {
+    fn fetch(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        let len = self.0.len() as u64;
+        let result = if range.start <= range.end && range.end <= len {
+            Ok(self.0.slice(range.start as usize..range.end as usize))
+        } else {
+            Err(AsyncTiffError::General(format!(
+                "Range {range:?} is out of bounds for {len} bytes"
+            )))
+        };
+        async { result }.boxed()
+    }
+}
+
 /// Read a single tag from the cursor
 async fn read_tag<F: MetadataFetch>(
     fetch: &F,
     tag_offset: u64,
     endianness: Endianness,
     bigtiff: bool,
+    max_value_bytes: u64,
 ) -> AsyncTiffResult<(Tag, Value)> {
+    let (tag_name, tag_value, _) =
+        read_tag_with_range(fetch, tag_offset, endianness, bigtiff, max_value_bytes).await?;
+    Ok((tag_name, tag_value))
+}
+
+/// Like [`read_tag`], also returning the range of bytes of the value if it's stored outside of
+/// the entry.
+async fn read_tag_with_range<F: MetadataFetch>(
+    fetch: &F,
+    tag_offset: u64,
+    endianness: Endianness,
+    bigtiff: bool,
+    max_value_bytes: u64,
+) -> AsyncTiffResult<(Tag, Value, Option<Range<u64>>)> {
     let mut cursor = MetadataCursor::new_with_offset(fetch, endianness, tag_offset);
 
     let tag_name = Tag::from_u16_exhaustive(cursor.read_u16().await?);
 
     let tag_type_code = cursor.read_u16().await?;
-    let tag_type = Type::from_u16(tag_type_code).expect(
-        "Unknown tag type {tag_type_code}. TODO: we should skip entries with unknown tag types.",
-    );
+    let tag_type = Type::from_u16(tag_type_code).ok_or(TiffError::FormatError(
+        TiffFormatError::InvalidTagValueType(tag_name),
+    ))?;
     let count = if bigtiff {
         cursor.read_u64().await?
     } else {
         cursor.read_u32().await?.into()
     };
 
-    let tag_value = read_tag_value(&mut cursor, tag_type, count, bigtiff).await?;
+    let tag_value = read_tag_value(&mut cursor, tag_type, count, bigtiff, max_value_bytes).await?;
 
-    Ok((tag_name, tag_value))
+    // Values that fit in the entry leave the cursor inside it, others leave it at their end
+    let entry_end = tag_offset.saturating_add(if bigtiff { 20 } else { 12 });
+    let value_end = cursor.position();
+    let value_range = (value_end < tag_offset || value_end > entry_end).then(|| {
+        let value_byte_length = count.saturating_mul(type_size(tag_type));
+        value_end.saturating_sub(value_byte_length)..value_end
+    });
+
+    Ok((tag_name, tag_value, value_range))
+}
+
+/// The size in bytes of a single value of `tag_type`.
+fn type_size(tag_type: Type) -> u64 {
+    match tag_type {
+        Type::BYTE | Type::SBYTE | Type::ASCII | Type::UNDEFINED => 1,
+        Type::SHORT | Type::SSHORT => 2,
+        Type::LONG | Type::SLONG | Type::FLOAT | Type::IFD => 4,
+        Type::LONG8
+        | Type::SLONG8
+        | Type::DOUBLE
+        | Type::RATIONAL
+        | Type::SRATIONAL
+        | Type::IFD8 => 8,
+    }
 }
 
 /// Read a tag's value from the cursor
@@ -289,25 +706,23 @@ async fn read_tag_value<F: MetadataFetch>(
     tag_type: Type,
     count: u64,
     bigtiff: bool,
+    max_value_bytes: u64,
 ) -> AsyncTiffResult<Value> {
     // Case 1: there are no values so we can return immediately.
     if count == 0 {
         return Ok(Value::List(vec![]));
     }
 
-    let tag_size = match tag_type {
-        Type::BYTE | Type::SBYTE | Type::ASCII | Type::UNDEFINED => 1,
-        Type::SHORT | Type::SSHORT => 2,
-        Type::LONG | Type::SLONG | Type::FLOAT | Type::IFD => 4,
-        Type::LONG8
-        | Type::SLONG8
-        | Type::DOUBLE
-        | Type::RATIONAL
-        | Type::SRATIONAL
-        | Type::IFD8 => 8,
-    };
-
-    let value_byte_length = count.checked_mul(tag_size).unwrap();
+    let value_byte_length = count
+        .checked_mul(type_size(tag_type))
+        .ok_or(TiffError::FormatError(TiffFormatError::InvalidTag))?;
+    if value_byte_length > max_value_bytes {
+        return Err(AsyncTiffError::LimitExceeded {
+            what: "Tag value size",
+            size: value_byte_length,
+            limit: max_value_bytes,
+        });
+    }
 
     // Case 2: there is one value.
     if count == 1 {
@@ -331,7 +746,9 @@ async fn read_tag_value<F: MetadataFetch>(
                 | Type::LONG
                 | Type::SLONG
                 | Type::FLOAT
-                | Type::IFD => unreachable!(),
+                | Type::IFD => {
+                    return Err(TiffError::FormatError(TiffFormatError::InvalidTag).into())
+                }
             });
         }
 
@@ -350,11 +767,10 @@ async fn read_tag_value<F: MetadataFetch>(
             Type::SLONG => Value::Signed(data.read_i32()?),
             Type::FLOAT => Value::Float(data.read_f32()?),
             Type::ASCII => {
-                if data.as_ref()[0] == 0 {
+                if data.read_u8()? == 0 {
                     Value::Ascii("".to_string())
                 } else {
-                    panic!("Invalid tag");
-                    // return Err(TiffError::FormatError(TiffFormatError::InvalidTag));
+                    return Err(TiffError::FormatError(TiffFormatError::InvalidTag).into());
                 }
             }
             Type::LONG8 => {
@@ -406,22 +822,16 @@ async fn read_tag_value<F: MetadataFetch>(
 
         match tag_type {
             Type::BYTE | Type::UNDEFINED => {
-                return {
-                    Ok(Value::List(
-                        (0..count)
-                            .map(|_| Value::Byte(data.read_u8().unwrap()))
-                            .collect(),
-                    ))
-                };
+                return (0..count)
+                    .map(|_| Ok(Value::Byte(data.read_u8()?)))
+                    .collect::<AsyncTiffResult<_>>()
+                    .map(Value::List);
             }
             Type::SBYTE => {
-                return {
-                    Ok(Value::List(
-                        (0..count)
-                            .map(|_| Value::SignedByte(data.read_i8().unwrap()))
-                            .collect(),
-                    ))
-                }
+                return (0..count)
+                    .map(|_| Ok(Value::SignedByte(data.read_i8()?)))
+                    .collect::<AsyncTiffResult<_>>()
+                    .map(Value::List);
             }
             Type::ASCII => {
                 let mut buf = vec![0; count as usize];
@@ -429,11 +839,11 @@ async fn read_tag_value<F: MetadataFetch>(
                 if buf.is_ascii() && buf.ends_with(&[0]) {
                     let v = std::str::from_utf8(&buf)
                         .map_err(|err| AsyncTiffError::General(err.to_string()))?;
-                    let v = v.trim_matches(char::from(0));
+                    // Keep interior NULs, which separate multiple strings
+                    let v = v.trim_end_matches(char::from(0));
                     return Ok(Value::Ascii(v.into()));
                 } else {
-                    panic!("Invalid tag");
-                    // return Err(TiffError::FormatError(TiffFormatError::InvalidTag));
+                    return Err(TiffError::FormatError(TiffFormatError::InvalidTag).into());
                 }
             }
             Type::SHORT => {
@@ -498,121 +908,122 @@ async fn read_tag_value<F: MetadataFetch>(
     cursor.seek(offset);
 
     // Case 4: there is more than one value, and it doesn't fit in the offset field.
+    //
+    // Read the whole value at once, so that allocations are bounded by the number of bytes
+    // actually read rather than by `count`, which may be corrupt.
+    let mut data = cursor.read(value_byte_length).await?;
+    if (data.remaining() as u64) < value_byte_length {
+        return Err(TiffError::FormatError(TiffFormatError::InvalidTag).into());
+    }
     match tag_type {
         // TODO check if this could give wrong results
         // at a different endianess of file/computer.
         Type::BYTE | Type::UNDEFINED => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Byte(cursor.read_u8().await?))
+                v.push(Value::Byte(data.read_u8()?))
             }
             Ok(Value::List(v))
         }
         Type::SBYTE => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::SignedByte(cursor.read_i8().await?))
+                v.push(Value::SignedByte(data.read_i8()?))
             }
             Ok(Value::List(v))
         }
         Type::SHORT => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Short(cursor.read_u16().await?))
+                v.push(Value::Short(data.read_u16()?))
             }
             Ok(Value::List(v))
         }
         Type::SSHORT => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::SignedShort(cursor.read_i16().await?))
+                v.push(Value::SignedShort(data.read_i16()?))
             }
             Ok(Value::List(v))
         }
         Type::LONG => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Unsigned(cursor.read_u32().await?))
+                v.push(Value::Unsigned(data.read_u32()?))
             }
             Ok(Value::List(v))
         }
         Type::SLONG => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Signed(cursor.read_i32().await?))
+                v.push(Value::Signed(data.read_i32()?))
             }
             Ok(Value::List(v))
         }
         Type::FLOAT => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Float(cursor.read_f32().await?))
+                v.push(Value::Float(data.read_f32()?))
             }
             Ok(Value::List(v))
         }
         Type::DOUBLE => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Double(cursor.read_f64().await?))
+                v.push(Value::Double(data.read_f64()?))
             }
             Ok(Value::List(v))
         }
         Type::RATIONAL => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Rational(
-                    cursor.read_u32().await?,
-                    cursor.read_u32().await?,
-                ))
+                v.push(Value::Rational(data.read_u32()?, data.read_u32()?))
             }
             Ok(Value::List(v))
         }
         Type::SRATIONAL => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::SRational(
-                    cursor.read_i32().await?,
-                    cursor.read_i32().await?,
-                ))
+                v.push(Value::SRational(data.read_i32()?, data.read_i32()?))
             }
             Ok(Value::List(v))
         }
         Type::LONG8 => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::UnsignedBig(cursor.read_u64().await?))
+                v.push(Value::UnsignedBig(data.read_u64()?))
             }
             Ok(Value::List(v))
         }
         Type::SLONG8 => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::SignedBig(cursor.read_i64().await?))
+                v.push(Value::SignedBig(data.read_i64()?))
             }
             Ok(Value::List(v))
         }
         Type::IFD => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::Ifd(cursor.read_u32().await?))
+                v.push(Value::Ifd(data.read_u32()?))
             }
             Ok(Value::List(v))
         }
         Type::IFD8 => {
             let mut v = Vec::with_capacity(count as _);
             for _ in 0..count {
-                v.push(Value::IfdBig(cursor.read_u64().await?))
+                v.push(Value::IfdBig(data.read_u64()?))
             }
             Ok(Value::List(v))
         }
         Type::ASCII => {
-            let mut out = vec![0; count as _];
-            let mut buf = cursor.read(count).await?;
-            buf.read_exact(&mut out)?;
+            let (reader, _) = data.into_inner();
+            let mut out = reader.into_inner().to_vec();
 
-            // Strings may be null-terminated, so we trim anything downstream of the null byte
-            if let Some(first) = out.iter().position(|&b| b == 0) {
-                out.truncate(first);
+            // Strings are null-terminated, so we trim any trailing null bytes. Interior null bytes
+            // are kept because they separate multiple strings stored in one tag.
+            while out.last() == Some(&0) {
+                out.pop();
             }
             Ok(Value::Ascii(
                 String::from_utf8(out).map_err(|err| AsyncTiffError::General(err.to_string()))?,
@@ -624,7 +1035,11 @@ async fn read_tag_value<F: MetadataFetch>(
 #[cfg(test)]
 mod test {
     use crate::{
-        metadata::{reader::read_tag, MetadataFetch},
+        error::AsyncTiffError,
+        metadata::{
+            reader::{parse_ifd_entries, read_tag},
+            MetadataFetch, MetadataLimits, TiffMetadataReader,
+        },
         reader::Endianness,
         tiff::{tags::Tag, Value},
     };
@@ -677,7 +1092,7 @@ mod test {
         for (buf, byte_order, res) in cases {
                 let fetch = Bytes::copy_from_slice(&buf);
             assert_eq!(
-                read_tag(&fetch, 0, byte_order, false).await.unwrap(),
+                read_tag(&fetch, 0, byte_order, false, u64::MAX).await.unwrap(),
                 (Tag::from_u16_exhaustive(0x01_01),res)
             );
         }
@@ -732,7 +1147,7 @@ mod test {
         for (buf, byte_order, res) in cases {
             let fetch = Bytes::copy_from_slice(&buf);
             assert_eq!(
-                read_tag(&fetch, 0, byte_order, true).await.unwrap(),
+                read_tag(&fetch, 0, byte_order, true, u64::MAX).await.unwrap(),
                 (Tag::from_u16_exhaustive(0x0101), res)
             )
         }
@@ -769,7 +1184,7 @@ mod test {
             println!("testing {buf:?} to be {res:?}");
             let fetch = Bytes::copy_from_slice(&buf);
             assert_eq!(
-                read_tag(&fetch, 0, byte_order, false).await.unwrap(),
+                read_tag(&fetch, 0, byte_order, false, u64::MAX).await.unwrap(),
                 (Tag::from_u16_exhaustive(0x0101), res)
             )
         }
@@ -812,7 +1227,7 @@ mod test {
         for (buf, byte_order, res) in cases {
             let fetch = Bytes::copy_from_slice(&buf);
             assert_eq!(
-                read_tag(&fetch, 0, byte_order, true).await.unwrap(),
+                read_tag(&fetch, 0, byte_order, true, u64::MAX).await.unwrap(),
                 (Tag::from_u16_exhaustive(0x0101), res)
             )
         }
@@ -867,12 +1282,25 @@ mod test {
             println!("reading {buf:?} to be {res:?}");
             let fetch = Bytes::from_owner(buf);
             assert_eq!(
-                read_tag(&fetch, 0, byte_order, false).await.unwrap(),
+                read_tag(&fetch, 0, byte_order, false, u64::MAX).await.unwrap(),
                 (Tag::from_u16_exhaustive(0x0101), res)
             )
         }
     }
 
+    #[tokio::test]
+    #[rustfmt::skip]
+    async fn test_multi_string_ascii() {
+        //               type  count    offset 12
+        //               /\   /     \   /     \    a   \0  b   c  \0
+        let buf = vec![1,1, 2, 0, 5,0,0,0, 12, 0, 0, 0, 97, 0, 98, 99, 0];
+        let fetch = Bytes::from_owner(buf);
+        let (_, value) = read_tag(&fetch, 0, Endianness::LittleEndian, false, u64::MAX).await.unwrap();
+        assert_eq!(value, Value::Ascii("a\0bc".into()));
+        assert_eq!(value.clone().into_string().unwrap(), "a");
+        assert_eq!(value.into_string_vec().unwrap(), vec!["a".to_string(), "bc".to_string()]);
+    }
+
     #[tokio::test]
     #[rustfmt::skip]
     async fn test_notfits_big() {
@@ -920,7 +1348,175 @@ mod test {
         for (buf, byte_order, res) in cases {
             println!("reading {buf:?} to be {res:?}");
             let fetch = Bytes::from_owner(buf);
-            assert_eq!(read_tag(&fetch, 0, byte_order, true).await.unwrap(), (Tag::from_u16_exhaustive(0x0101), res))
+            assert_eq!(read_tag(&fetch, 0, byte_order, true, u64::MAX).await.unwrap(), (Tag::from_u16_exhaustive(0x0101), res))
         }
     }
+
+    /// A little-endian TIFF with two IFDs. The first has an entry with an unknown type, the
+    /// second is missing its required tags.
+    #[rustfmt::skip]
+    fn malformed_tiff() -> Bytes {
+        let mut file = vec![b'I', b'I', 42, 0, 8, 0, 0, 0];
+        // IFD at 8 with 4 entries, next IFD at 8 + 2 + 4 * 12 + 4 = 62
+        file.extend([4, 0]);
+        file.extend([0, 1, 4, 0, 1, 0, 0, 0, 16, 0, 0, 0]); // ImageWidth
+        file.extend([1, 1, 4, 0, 1, 0, 0, 0, 16, 0, 0, 0]); // ImageLength
+        file.extend([6, 1, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0]); // PhotometricInterpretation
+        file.extend([0, 2, 99, 0, 1, 0, 0, 0, 0, 0, 0, 0]); // unknown type 99
+        file.extend([62, 0, 0, 0]);
+        // IFD at 62 with no entries and no next IFD
+        file.extend([0, 0, 0, 0, 0, 0]);
+        Bytes::from(file)
+    }
+
+    #[test]
+    fn test_parse_ifd_entries() {
+        let file = malformed_tiff();
+        // Without the entry of unknown type, the first IFD parses
+        let mut valid = file.to_vec();
+        valid[8] = 3;
+        let tags = parse_ifd_entries(&valid, 8, Endianness::LittleEndian, false).unwrap();
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags[&Tag::ImageWidth], Value::Unsigned(16));
+        assert!(parse_ifd_entries(&file, 8, Endianness::LittleEndian, false).is_err());
+
+        // Counts far beyond the input
+        let mut huge_value = valid.clone();
+        huge_value[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_ifd_entries(&huge_value, 8, Endianness::LittleEndian, false).is_err());
+        let mut huge_bigtiff = vec![0; 32];
+        huge_bigtiff[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        huge_bigtiff[10..12].copy_from_slice(&[4, 0]);
+        huge_bigtiff[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(parse_ifd_entries(&huge_bigtiff, 0, Endianness::LittleEndian, true).is_err());
+        assert!(parse_ifd_entries(&valid, u64::MAX, Endianness::LittleEndian, false).is_err());
+
+        // Arbitrary input never panics
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for len in 0..512 {
+            let input = (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect::<Vec<_>>();
+            for bigtiff in [false, true] {
+                let _ = parse_ifd_entries(&input, 0, Endianness::BigEndian, bigtiff);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metadata_limits() {
+        let mut file = vec![b'I', b'I', 42, 0, 8, 0, 0, 0];
+        // IFD at 8 with 4 entries, description at 8 + 2 + 4 * 12 + 4 = 62
+        file.extend([4, 0]);
+        file.extend([0, 1, 4, 0, 1, 0, 0, 0, 16, 0, 0, 0]); // ImageWidth
+        file.extend([1, 1, 4, 0, 1, 0, 0, 0, 16, 0, 0, 0]); // ImageLength
+        file.extend([6, 1, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0]); // PhotometricInterpretation
+        file.extend([14, 1, 2, 0, 40, 0, 0, 0, 62, 0, 0, 0]); // ImageDescription
+        file.extend([0, 0, 0, 0]);
+        file.extend([b'a'; 39]);
+        file.push(0);
+        let fetch = Bytes::from(file);
+
+        let read = |limits| {
+            let fetch = fetch.clone();
+            async move {
+                TiffMetadataReader::try_open(&fetch)
+                    .await
+                    .unwrap()
+                    .with_limits(limits)
+                    .read_all_ifds(&fetch)
+                    .await
+            }
+        };
+        assert_eq!(read(MetadataLimits::default()).await.unwrap().len(), 1);
+        let limits = MetadataLimits::default().with_max_tag_value_bytes(40);
+        assert!(read(limits).await.is_ok());
+
+        let limits = MetadataLimits::default().with_max_tag_value_bytes(16);
+        let err = read(limits).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AsyncTiffError::LimitExceeded {
+                what: "Tag value size",
+                size: 40,
+                limit: 16
+            }
+        ));
+
+        let limits = MetadataLimits::default().with_max_metadata_bytes(16);
+        let err = read(limits).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AsyncTiffError::LimitExceeded {
+                what: "Metadata size",
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[tokio::test]
+    async fn test_exif_date_time_original() {
+        let mut file = vec![b'I', b'I', 42, 0, 8, 0, 0, 0];
+        // IFD at 8 with 4 entries, EXIF IFD at 8 + 2 + 4 * 12 + 4 = 62 with 2 entries, and its
+        // DateTimeOriginal at 62 + 2 + 2 * 12 + 4 = 92
+        file.extend([4, 0]);
+        file.extend([0, 1, 4, 0, 1, 0, 0, 0, 16, 0, 0, 0]); // ImageWidth
+        file.extend([1, 1, 4, 0, 1, 0, 0, 0, 16, 0, 0, 0]); // ImageLength
+        file.extend([6, 1, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0]); // PhotometricInterpretation
+        file.extend([0x69, 0x87, 4, 0, 1, 0, 0, 0, 62, 0, 0, 0]); // ExifIFD
+        file.extend([0, 0, 0, 0]);
+        file.extend([2, 0]);
+        file.extend([0x9a, 0x82, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0]); // ExposureTime
+        file.extend([0x03, 0x90, 2, 0, 20, 0, 0, 0, 92, 0, 0, 0]); // DateTimeOriginal
+        file.extend([0, 0, 0, 0]);
+        file.extend(b"2024:01:02 03:04:05\0");
+        let fetch = Bytes::from(file);
+
+        let mut reader = TiffMetadataReader::try_open(&fetch).await.unwrap();
+        let ifds = reader.read_all_ifds(&fetch).await.unwrap();
+        assert_eq!(ifds[0].date_time_original(), Some("2024:01:02 03:04:05"));
+
+        // Reading the tag takes a request for the entry count, the entries and the value
+        let count_fetches = |file: Vec<u8>| async move {
+            let fetch = crate::test_util::CountingReader::new(file);
+            crate::test_util::read_ifds(&fetch).await;
+            fetch.requests()
+        };
+        let mut without_exif = fetch.to_vec();
+        without_exif[46] = 0x68;
+        assert_eq!(
+            count_fetches(fetch.to_vec()).await,
+            count_fetches(without_exif).await + 3
+        );
+
+        // A pointer to an IFD without the tag
+        let mut file = fetch.to_vec();
+        file[54] = 8;
+        let fetch = Bytes::from(file);
+        let mut reader = TiffMetadataReader::try_open(&fetch).await.unwrap();
+        let ifds = reader.read_all_ifds(&fetch).await.unwrap();
+        assert_eq!(ifds[0].date_time_original(), None);
+    }
+
+    #[tokio::test]
+    async fn test_lenient_read_all_ifds() {
+        let fetch = malformed_tiff();
+        let mut reader = TiffMetadataReader::try_open(&fetch).await.unwrap();
+        assert!(reader.read_all_ifds(&fetch).await.is_err());
+
+        let mut reader = TiffMetadataReader::try_open(&fetch)
+            .await
+            .unwrap()
+            .with_lenient(true);
+        let ifds = reader.read_all_ifds(&fetch).await.unwrap();
+        assert_eq!(ifds.len(), 1);
+        assert_eq!(ifds[0].image_width(), 16);
+        assert!(!reader.has_next_ifd());
+    }
 }
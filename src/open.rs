@@ -0,0 +1,239 @@
+//! Opening many TIFFs from an object store at once.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::stream::{self, StreamExt};
+use object_store::path::Path;
+use object_store::ObjectStore;
+
+use crate::error::AsyncTiffResult;
+use crate::metadata::{MetadataLimits, PrefetchBuffer, TiffMetadataReader};
+use crate::reader::ObjectReader;
+use crate::TIFF;
+
+/// A cache of the parsed headers of opened files, keyed by path.
+///
+/// Clones share the same cache, so a single cache can be reused across calls to [`open_many`]
+/// to avoid re-reading the headers of files that were already opened.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderCache {
+    headers: Arc<Mutex<HashMap<Path, TIFF>>>,
+}
+
+impl HeaderCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached header of the file at `path`, if any.
+    pub fn get(&self, path: &Path) -> Option<TIFF> {
+        self.headers.lock().unwrap().get(path).cloned()
+    }
+
+    /// Cache the header of the file at `path`.
+    pub fn insert(&self, path: Path, tiff: TIFF) {
+        self.headers.lock().unwrap().insert(path, tiff);
+    }
+
+    /// The number of cached headers.
+    pub fn len(&self) -> usize {
+        self.headers.lock().unwrap().len()
+    }
+
+    /// Whether no headers are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all cached headers.
+    pub fn clear(&self) {
+        self.headers.lock().unwrap().clear();
+    }
+}
+
+/// Options for [`open_many`].
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    concurrency: usize,
+    prefetch: u64,
+    prefetch_budget: Option<u64>,
+    limits: MetadataLimits,
+    header_cache: HeaderCache,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 16,
+            prefetch: 32 * 1024,
+            prefetch_budget: None,
+            limits: MetadataLimits::default(),
+            header_cache: HeaderCache::default(),
+        }
+    }
+}
+
+impl OpenOptions {
+    /// Create the default options: 16 files opened at once, prefetching the first 32 KiB of each.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of files opened at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the number of bytes prefetched from the start of each file with a [`PrefetchBuffer`].
+    pub fn with_prefetch(mut self, prefetch: u64) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// Set the maximum number of prefetched bytes held at once across all files being opened.
+    ///
+    /// This lowers the concurrency to fit, but at least one file is always opened at a time.
+    /// Only the prefetch buffers are counted: the parsed headers, which are kept in the
+    /// [`HeaderCache`], are not. Limit the metadata of each file with
+    /// [`with_limits`](Self::with_limits), and measure the headers with
+    /// [`ImageFileDirectory::estimated_heap_size`](crate::ImageFileDirectory::estimated_heap_size).
+    pub fn with_prefetch_budget(mut self, prefetch_budget: u64) -> Self {
+        self.prefetch_budget = Some(prefetch_budget);
+        self
+    }
+
+    /// Set the limits on the metadata read from each file.
+    pub fn with_limits(mut self, limits: MetadataLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Share `header_cache` with other calls to [`open_many`].
+    pub fn with_header_cache(mut self, header_cache: HeaderCache) -> Self {
+        self.header_cache = header_cache;
+        self
+    }
+
+    /// The maximum number of files opened at once.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// The number of bytes prefetched from the start of each file.
+    pub fn prefetch(&self) -> u64 {
+        self.prefetch
+    }
+
+    /// The maximum number of prefetched bytes held at once, if any.
+    pub fn prefetch_budget(&self) -> Option<u64> {
+        self.prefetch_budget
+    }
+
+    /// The limits on the metadata read from each file.
+    pub fn limits(&self) -> &MetadataLimits {
+        &self.limits
+    }
+
+    /// The cache of parsed headers.
+    pub fn header_cache(&self) -> &HeaderCache {
+        &self.header_cache
+    }
+
+    /// The number of files opened at once, within the prefetch budget.
+    fn effective_concurrency(&self) -> usize {
+        let concurrency = match self.prefetch_budget {
+            Some(budget) => {
+                let fits = budget / self.prefetch.max(1);
+                self.concurrency.min(fits.try_into().unwrap_or(usize::MAX))
+            }
+            None => self.concurrency,
+        };
+        concurrency.max(1)
+    }
+}
+
+/// Open the TIFFs at `paths` in `store` concurrently, reading all of their IFDs.
+///
+/// The result of each file is returned in the order of `paths`, so that files that fail to open
+/// don't prevent using the others. Headers found in the [`HeaderCache`] of `options` aren't
+/// fetched again, and the headers of files opened successfully are added to it.
+pub async fn open_many(
+    store: Arc<dyn ObjectStore>,
+    paths: impl IntoIterator<Item = Path>,
+    options: &OpenOptions,
+) -> Vec<AsyncTiffResult<TIFF>> {
+    stream::iter(paths)
+        .map(|path| open_one(store.clone(), path, options))
+        .buffered(options.effective_concurrency())
+        .collect()
+        .await
+}
+
+async fn open_one(
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    options: &OpenOptions,
+) -> AsyncTiffResult<TIFF> {
+    if let Some(tiff) = options.header_cache.get(&path) {
+        return Ok(tiff);
+    }
+    let reader = ObjectReader::new(store, path.clone());
+    let prefetch = PrefetchBuffer::new(reader, options.prefetch).await?;
+    let mut metadata = TiffMetadataReader::try_open(&prefetch)
+        .await?
+        .with_limits(options.limits);
+    let tiff = TIFF::new(metadata.read_all_ifds(&prefetch).await?);
+    options.header_cache.insert(path, tiff.clone());
+    Ok(tiff)
+}
+
+#[cfg(test)]
+mod test {
+    use std::env::current_dir;
+
+    use object_store::local::LocalFileSystem;
+
+    use super::*;
+
+    #[test]
+    fn test_effective_concurrency() {
+        let options = OpenOptions::new().with_concurrency(8).with_prefetch(1024);
+        assert_eq!(options.effective_concurrency(), 8);
+        assert_eq!(
+            options
+                .clone()
+                .with_prefetch_budget(4096)
+                .effective_concurrency(),
+            4
+        );
+        assert_eq!(options.with_prefetch_budget(10).effective_concurrency(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_open_many() {
+        let store = Arc::new(LocalFileSystem::new_with_prefix(current_dir().unwrap()).unwrap());
+        let paths = [
+            "tests/image_tiff/images/tiled-jpeg-rgb-u8.tif",
+            "tests/image_tiff/images/missing.tif",
+            "tests/image_tiff/images/int16.tif",
+        ]
+        .map(Path::from);
+        let options = OpenOptions::new().with_prefetch_budget(64 * 1024);
+
+        let results = open_many(store.clone(), paths.clone(), &options).await;
+        assert_eq!(results.len(), 3);
+        assert!(!results[0].as_ref().unwrap().ifds().is_empty());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+        assert_eq!(options.header_cache().len(), 2);
+
+        // Cached headers are used without fetching
+        let cached = results[0].as_ref().unwrap().clone();
+        options.header_cache().insert(paths[1].clone(), cached);
+        let results = open_many(store, paths, &options).await;
+        assert!(results.iter().all(|result| result.is_ok()));
+    }
+}
@@ -0,0 +1,237 @@
+//! Validation of overview IFDs against the full-resolution image.
+
+use std::fmt;
+
+use crate::cog::TIFF;
+use crate::ifd::ImageFileDirectory;
+use crate::tiff::tags::SampleFormat;
+
+/// A way in which an overview IFD disagrees with the full-resolution image.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum OverviewIssue {
+    /// The overview has a different number of samples per pixel.
+    BandCount {
+        /// The index of the overview IFD.
+        ifd_index: usize,
+        /// The samples per pixel of the full-resolution image.
+        expected: u16,
+        /// The samples per pixel of the overview.
+        found: u16,
+    },
+    /// The overview stores samples with a different bit depth or sample format.
+    DataType {
+        /// The index of the overview IFD.
+        ifd_index: usize,
+        /// The bits per sample and sample format of the full-resolution image.
+        expected: (Vec<u16>, Vec<SampleFormat>),
+        /// The bits per sample and sample format of the overview.
+        found: (Vec<u16>, Vec<SampleFormat>),
+    },
+    /// The overview has a different nodata value.
+    Nodata {
+        /// The index of the overview IFD.
+        ifd_index: usize,
+        /// The nodata value of the full-resolution image.
+        expected: Option<f64>,
+        /// The nodata value of the overview.
+        found: Option<f64>,
+    },
+    /// The overview's dimensions are not the full-resolution dimensions reduced by a single
+    /// integer factor, so its extent does not line up with the full-resolution image.
+    Extent {
+        /// The index of the overview IFD.
+        ifd_index: usize,
+        /// The width and height of the overview.
+        size: (u32, u32),
+        /// The width and height of the full-resolution image.
+        full_size: (u32, u32),
+    },
+    /// The overview is not smaller than the level preceding it.
+    NotDecreasing {
+        /// The index of the overview IFD.
+        ifd_index: usize,
+        /// The index of the preceding level's IFD.
+        previous_ifd_index: usize,
+    },
+}
+
+impl fmt::Display for OverviewIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BandCount {
+                ifd_index,
+                expected,
+                found,
+            } => write!(f, "IFD {ifd_index} has {found} bands, expected {expected}"),
+            Self::DataType { ifd_index, .. } => {
+                write!(f, "IFD {ifd_index} has a different data type")
+            }
+            Self::Nodata {
+                ifd_index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "IFD {ifd_index} has nodata {found:?}, expected {expected:?}"
+            ),
+            Self::Extent {
+                ifd_index, size, ..
+            } => write!(
+                f,
+                "IFD {ifd_index} size {size:?} is not an integer reduction"
+            ),
+            Self::NotDecreasing { ifd_index, .. } => {
+                write!(
+                    f,
+                    "IFD {ifd_index} is not smaller than the previous overview"
+                )
+            }
+        }
+    }
+}
+
+/// The result of [`TIFF::check_overviews`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OverviewReport {
+    overview_indices: Vec<usize>,
+    issues: Vec<OverviewIssue>,
+}
+
+impl OverviewReport {
+    /// The indices of the IFDs that were checked as overviews, in file order.
+    pub fn overview_indices(&self) -> &[usize] {
+        &self.overview_indices
+    }
+
+    /// Every inconsistency found.
+    pub fn issues(&self) -> &[OverviewIssue] {
+        &self.issues
+    }
+
+    /// Returns `true` if no inconsistencies were found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl TIFF {
+    /// Check that the overview IFDs agree with the full-resolution image in the first IFD.
+    ///
+    /// Overviews are the IFDs flagged as reduced-resolution images in their
+    /// [NewSubfileType](ImageFileDirectory::new_subfile_type) that are not masks. Each is checked
+    /// for matching band count, data type and nodata value, for dimensions that are the full
+    /// dimensions reduced by a single integer factor, and for decreasing size.
+    pub fn check_overviews(&self) -> OverviewReport {
+        let mut report = OverviewReport::default();
+        let Some(full) = self.ifds().first() else {
+            return report;
+        };
+
+        let mut previous = (0, full);
+        for (ifd_index, overview) in self.ifds().iter().enumerate().skip(1) {
+            if !is_overview(overview) {
+                continue;
+            }
+            report.overview_indices.push(ifd_index);
+            check_overview(full, ifd_index, overview, &mut report.issues);
+
+            let (previous_ifd_index, previous_ifd) = previous;
+            if overview.image_width() >= previous_ifd.image_width()
+                || overview.image_height() >= previous_ifd.image_height()
+            {
+                report.issues.push(OverviewIssue::NotDecreasing {
+                    ifd_index,
+                    previous_ifd_index,
+                });
+            }
+            previous = (ifd_index, overview);
+        }
+        report
+    }
+}
+
+pub(crate) fn is_overview(ifd: &ImageFileDirectory) -> bool {
+    ifd.new_subfile_type().is_some_and(|t| t & 1 != 0) && !ifd.is_mask()
+}
+
+fn check_overview(
+    full: &ImageFileDirectory,
+    ifd_index: usize,
+    overview: &ImageFileDirectory,
+    issues: &mut Vec<OverviewIssue>,
+) {
+    if overview.samples_per_pixel() != full.samples_per_pixel() {
+        issues.push(OverviewIssue::BandCount {
+            ifd_index,
+            expected: full.samples_per_pixel(),
+            found: overview.samples_per_pixel(),
+        });
+    }
+    if overview.bits_per_sample() != full.bits_per_sample()
+        || overview.sample_format() != full.sample_format()
+    {
+        issues.push(OverviewIssue::DataType {
+            ifd_index,
+            expected: (
+                full.bits_per_sample().to_vec(),
+                full.sample_format().to_vec(),
+            ),
+            found: (
+                overview.bits_per_sample().to_vec(),
+                overview.sample_format().to_vec(),
+            ),
+        });
+    }
+    let (expected, found) = (full.nodata(), overview.nodata());
+    let nodata_matches = match (expected, found) {
+        (Some(a), Some(b)) => a == b || (a.is_nan() && b.is_nan()),
+        (None, None) => true,
+        _ => false,
+    };
+    if !nodata_matches {
+        issues.push(OverviewIssue::Nodata {
+            ifd_index,
+            expected,
+            found,
+        });
+    }
+
+    let size = (overview.image_width(), overview.image_height());
+    let full_size = (full.image_width(), full.image_height());
+    if !is_aligned(full_size, size) {
+        issues.push(OverviewIssue::Extent {
+            ifd_index,
+            size,
+            full_size,
+        });
+    }
+}
+
+/// Whether `size` is `full_size` reduced by the same integer factor along both axes, allowing for
+/// either rounding direction.
+fn is_aligned(full_size: (u32, u32), size: (u32, u32)) -> bool {
+    if size.0 == 0 || size.1 == 0 {
+        return false;
+    }
+    let factor = (full_size.0 as f64 / size.0 as f64).round().max(1.0) as u32;
+    let reduces =
+        |full: u32, reduced: u32| reduced == full / factor || reduced == full.div_ceil(factor);
+    reduces(full_size.0, size.0) && reduces(full_size.1, size.1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_aligned() {
+        assert!(is_aligned((1000, 500), (500, 250)));
+        assert!(is_aligned((1001, 501), (501, 251)));
+        assert!(is_aligned((1001, 501), (500, 250)));
+        assert!(is_aligned((1001, 501), (126, 63)));
+        // Different factors along each axis
+        assert!(!is_aligned((1000, 500), (500, 125)));
+        assert!(!is_aligned((1000, 500), (0, 0)));
+    }
+}
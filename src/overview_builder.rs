@@ -0,0 +1,196 @@
+//! Generation of overviews by repeatedly halving an image.
+
+use bytes::Bytes;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::tiff::tags::SampleFormat;
+use crate::window::{f64_to_sample, sample_to_f64};
+use crate::WindowData;
+
+/// The resampling method used to generate overviews.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverviewResampling {
+    /// Take the top-left pixel of each 2x2 block.
+    Nearest,
+    /// Average each 2x2 block, ignoring samples equal to the nodata value.
+    #[default]
+    Average,
+}
+
+/// An image of interleaved, native-endian samples.
+#[derive(Debug, Clone)]
+pub(crate) struct Raster {
+    width: usize,
+    height: usize,
+    samples_per_pixel: usize,
+    bytes_per_sample: usize,
+    sample_format: SampleFormat,
+    data: Vec<u8>,
+}
+
+impl Raster {
+    /// Wrap interleaved window data.
+    pub(crate) fn from_window(window: WindowData) -> Self {
+        Self {
+            width: window.window().width() as usize,
+            height: window.window().height() as usize,
+            samples_per_pixel: window.samples_per_pixel() as usize,
+            bytes_per_sample: window.bytes_per_sample(),
+            sample_format: window.sample_format(),
+            data: window.into_data(),
+        }
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    pub(crate) fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    pub(crate) fn bits_per_sample(&self) -> u16 {
+        (self.bytes_per_sample * 8) as u16
+    }
+
+    pub(crate) fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// Downsample by a factor of two along each axis, rounding the size up.
+    pub(crate) fn halve(
+        &self,
+        resampling: OverviewResampling,
+        nodata: Option<f64>,
+    ) -> AsyncTiffResult<Self> {
+        let width = self.width.div_ceil(2);
+        let height = self.height.div_ceil(2);
+        let pixel_bytes = self.samples_per_pixel * self.bytes_per_sample;
+        let sample_at = |col: usize, row: usize, sample: usize| {
+            let start = (row * self.width + col) * pixel_bytes + sample * self.bytes_per_sample;
+            &self.data[start..start + self.bytes_per_sample]
+        };
+
+        let mut data = Vec::with_capacity(width * height * pixel_bytes);
+        for row in 0..height {
+            for col in 0..width {
+                for sample in 0..self.samples_per_pixel {
+                    if resampling == OverviewResampling::Nearest {
+                        data.extend_from_slice(sample_at(col * 2, row * 2, sample));
+                        continue;
+                    }
+                    let (mut sum, mut count) = (0.0, 0);
+                    for src_row in row * 2..(row * 2 + 2).min(self.height) {
+                        for src_col in col * 2..(col * 2 + 2).min(self.width) {
+                            let value = sample_to_f64(
+                                sample_at(src_col, src_row, sample),
+                                self.sample_format,
+                            )
+                            .ok_or_else(|| {
+                                AsyncTiffError::General(format!(
+                                    "Cannot resample {}-byte {:?} samples",
+                                    self.bytes_per_sample, self.sample_format
+                                ))
+                            })?;
+                            if nodata != Some(value) && !value.is_nan() {
+                                sum += value;
+                                count += 1;
+                            }
+                        }
+                    }
+                    let value = match (count, self.sample_format) {
+                        (0, _) => nodata.unwrap_or(f64::NAN),
+                        (_, SampleFormat::IEEEFP) => sum / count as f64,
+                        _ => (sum / count as f64).round(),
+                    };
+                    data.extend(f64_to_sample(
+                        value,
+                        self.sample_format,
+                        self.bytes_per_sample,
+                    )?);
+                }
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            data,
+            ..*self
+        })
+    }
+
+    /// Split into tiles in row-major order, padding the edge tiles with zeros.
+    pub(crate) fn tiles(&self, tile_width: usize, tile_height: usize) -> Vec<Bytes> {
+        let pixel_bytes = self.samples_per_pixel * self.bytes_per_sample;
+        let mut tiles = vec![];
+        for y in 0..self.height.div_ceil(tile_height) {
+            for x in 0..self.width.div_ceil(tile_width) {
+                let mut tile = vec![0; tile_width * tile_height * pixel_bytes];
+                let cols = tile_width.min(self.width - x * tile_width);
+                for (i, row) in
+                    (y * tile_height..self.height.min((y + 1) * tile_height)).enumerate()
+                {
+                    let start = (row * self.width + x * tile_width) * pixel_bytes;
+                    tile[i * tile_width * pixel_bytes..][..cols * pixel_bytes]
+                        .copy_from_slice(&self.data[start..start + cols * pixel_bytes]);
+                }
+                tiles.push(tile.into());
+            }
+        }
+        tiles
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn raster(width: usize, height: usize, data: Vec<u8>) -> Raster {
+        Raster {
+            width,
+            height,
+            samples_per_pixel: 1,
+            bytes_per_sample: 1,
+            sample_format: SampleFormat::Uint,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_halve() {
+        #[rustfmt::skip]
+        let image = raster(3, 3, vec![
+            1, 2, 10,
+            3, 5, 20,
+            7, 9, 255,
+        ]);
+        let average = image.halve(OverviewResampling::Average, None).unwrap();
+        assert_eq!((average.width(), average.height()), (2, 2));
+        assert_eq!(average.data, [3, 15, 8, 255]);
+
+        let nearest = image.halve(OverviewResampling::Nearest, None).unwrap();
+        assert_eq!(nearest.data, [1, 10, 7, 255]);
+
+        // Nodata samples are ignored, and blocks of only nodata stay nodata
+        let masked = image
+            .halve(OverviewResampling::Average, Some(255.0))
+            .unwrap();
+        assert_eq!(masked.data, [3, 15, 8, 255]);
+        let masked = image.halve(OverviewResampling::Average, Some(5.0)).unwrap();
+        assert_eq!(masked.data, [2, 15, 8, 255]);
+    }
+
+    #[test]
+    fn test_tiles() {
+        let image = raster(3, 3, (1..=9).collect());
+        let tiles = image.tiles(2, 2);
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(tiles[0].as_ref(), [1, 2, 4, 5]);
+        assert_eq!(tiles[1].as_ref(), [3, 0, 6, 0]);
+        assert_eq!(tiles[3].as_ref(), [9, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,228 @@
+//! A composable fetch → decode → post-process pipeline for batch jobs.
+//!
+//! Each stage runs concurrently with the others and hands its output to the next stage through a
+//! bounded channel, so that network requests for later tiles overlap with decoding of earlier
+//! ones without buffering an unbounded number of tiles in memory.
+//!
+//! The pipeline doesn't spawn tasks on any async runtime: all stages are driven by polling the
+//! stream returned from [`Pipeline::run`].
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::stream::{self, BoxStream};
+use futures::{FutureExt, SinkExt, StreamExt};
+
+use crate::decoder::{DecodePool, DecoderRegistry};
+use crate::error::AsyncTiffResult;
+use crate::ifd::ImageFileDirectory;
+use crate::reader::AsyncFileReader;
+use crate::tile::Tile;
+
+/// A decoded tile produced by a [`Pipeline`].
+#[derive(Debug, Clone)]
+pub struct DecodedTile {
+    /// The column index of this tile.
+    pub x: usize,
+    /// The row index of this tile.
+    pub y: usize,
+    /// The decoded (and post-processed) bytes of this tile.
+    pub data: Bytes,
+}
+
+type PostProcess = Arc<dyn Fn(DecodedTile) -> AsyncTiffResult<DecodedTile> + Send + Sync>;
+
+/// A builder for a [`Pipeline`].
+pub struct PipelineBuilder {
+    ifd: Arc<ImageFileDirectory>,
+    reader: Arc<dyn AsyncFileReader>,
+    decoder_registry: Arc<DecoderRegistry>,
+    pool: Option<DecodePool>,
+    post_process: Vec<PostProcess>,
+    fetch_batch_size: usize,
+    fetch_concurrency: usize,
+    decode_concurrency: usize,
+    channel_capacity: usize,
+}
+
+impl PipelineBuilder {
+    /// Set the registry used to decode tiles.
+    pub fn with_decoder_registry(mut self, decoder_registry: Arc<DecoderRegistry>) -> Self {
+        self.decoder_registry = decoder_registry;
+        self
+    }
+
+    /// Decode and post-process tiles on `pool` instead of on the polling thread.
+    pub fn with_pool(mut self, pool: DecodePool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Append a post-processing stage, such as a type conversion or masking step.
+    ///
+    /// Stages run in the order they were added, after decoding and on the same thread.
+    pub fn with_post_process<F>(mut self, f: F) -> Self
+    where
+        F: Fn(DecodedTile) -> AsyncTiffResult<DecodedTile> + Send + Sync + 'static,
+    {
+        self.post_process.push(Arc::new(f));
+        self
+    }
+
+    /// Set how many tiles are fetched with each call to
+    /// [`get_byte_ranges`](AsyncFileReader::get_byte_ranges), letting the reader coalesce them.
+    pub fn with_fetch_batch_size(mut self, fetch_batch_size: usize) -> Self {
+        self.fetch_batch_size = fetch_batch_size.max(1);
+        self
+    }
+
+    /// Set how many fetch batches may be in flight at once.
+    pub fn with_fetch_concurrency(mut self, fetch_concurrency: usize) -> Self {
+        self.fetch_concurrency = fetch_concurrency.max(1);
+        self
+    }
+
+    /// Set how many tiles may be decoding at once.
+    pub fn with_decode_concurrency(mut self, decode_concurrency: usize) -> Self {
+        self.decode_concurrency = decode_concurrency.max(1);
+        self
+    }
+
+    /// Set the capacity of the channels between stages.
+    pub fn with_channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity;
+        self
+    }
+
+    /// Build the pipeline.
+    pub fn build(self) -> Pipeline {
+        Pipeline {
+            inner: Arc::new(self),
+        }
+    }
+}
+
+impl Debug for PipelineBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipelineBuilder")
+            .field("pool", &self.pool)
+            .field("post_process", &self.post_process.len())
+            .field("fetch_batch_size", &self.fetch_batch_size)
+            .field("fetch_concurrency", &self.fetch_concurrency)
+            .field("decode_concurrency", &self.decode_concurrency)
+            .field("channel_capacity", &self.channel_capacity)
+            .finish()
+    }
+}
+
+/// A fetch → decode → post-process pipeline over the tiles of one IFD.
+///
+/// Cloning a `Pipeline` is cheap, and a pipeline can be [run](Self::run) any number of times.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    inner: Arc<PipelineBuilder>,
+}
+
+impl Pipeline {
+    /// Start building a pipeline reading tiles of `ifd` from `reader`.
+    pub fn builder(ifd: ImageFileDirectory, reader: Arc<dyn AsyncFileReader>) -> PipelineBuilder {
+        PipelineBuilder {
+            ifd: Arc::new(ifd),
+            reader,
+            decoder_registry: Default::default(),
+            pool: None,
+            post_process: vec![],
+            fetch_batch_size: 16,
+            fetch_concurrency: 4,
+            decode_concurrency: 8,
+            channel_capacity: 4,
+        }
+    }
+
+    /// Process the tiles at `tiles`, given as `(x, y)` indices.
+    ///
+    /// Tiles are yielded in completion order, which may differ from the input order. A failure
+    /// is yielded as an error item, and processing continues with the remaining tiles; a failed
+    /// fetch yields a single error for its whole batch.
+    pub fn run(
+        &self,
+        tiles: Vec<(usize, usize)>,
+    ) -> BoxStream<'static, AsyncTiffResult<DecodedTile>> {
+        let inner = self.inner.clone();
+        let (fetched_tx, fetched_rx) =
+            mpsc::channel::<AsyncTiffResult<Vec<Tile>>>(inner.channel_capacity);
+        let (out_tx, out_rx) = mpsc::channel(inner.channel_capacity);
+
+        let fetch_stage = {
+            let inner = inner.clone();
+            let batches = tiles
+                .chunks(inner.fetch_batch_size)
+                .map(<[_]>::to_vec)
+                .collect::<Vec<_>>();
+            async move {
+                let mut fetched = stream::iter(batches)
+                    .map(|batch| {
+                        let inner = inner.clone();
+                        async move {
+                            let (xs, ys): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+                            inner.ifd.fetch_tiles(&xs, &ys, inner.reader.as_ref()).await
+                        }
+                    })
+                    .buffer_unordered(inner.fetch_concurrency)
+                    .map(Ok);
+                let mut fetched_tx = fetched_tx;
+                // An error means the consumer dropped the output, so there's nothing left to do.
+                let _ = fetched_tx.send_all(&mut fetched).await;
+            }
+        };
+
+        let decode_stage = {
+            let inner = inner.clone();
+            async move {
+                let mut decoded = fetched_rx
+                    .flat_map(|batch| match batch {
+                        Ok(tiles) => stream::iter(tiles).map(Ok).left_stream(),
+                        Err(err) => stream::iter([Err(err)]).right_stream(),
+                    })
+                    .map(|tile| {
+                        let inner = inner.clone();
+                        async move { process(&inner, tile?).await }
+                    })
+                    .buffer_unordered(inner.decode_concurrency)
+                    .map(Ok);
+                let mut out_tx = out_tx;
+                let _ = out_tx.send_all(&mut decoded).await;
+            }
+        };
+
+        // Drive both stages from the output stream. The driver never yields an item; it only
+        // makes progress whenever the consumer polls.
+        let driver = futures::future::join(fetch_stage, decode_stage)
+            .map(|_| None)
+            .into_stream();
+        stream::select(out_rx.map(Some), driver)
+            .filter_map(|item| async move { item })
+            .boxed()
+    }
+}
+
+/// Decode and post-process a single tile.
+async fn process(inner: &Arc<PipelineBuilder>, tile: Tile) -> AsyncTiffResult<DecodedTile> {
+    let job = {
+        let inner = inner.clone();
+        move || {
+            let (x, y) = (tile.x(), tile.y());
+            let data = tile.decode(&inner.decoder_registry)?;
+            inner
+                .post_process
+                .iter()
+                .try_fold(DecodedTile { x, y, data }, |tile, f| f(tile))
+        }
+    };
+    match &inner.pool {
+        Some(pool) => pool.spawn(job).await?,
+        None => job(),
+    }
+}
@@ -3,8 +3,9 @@ use std::fmt::Debug;
 
 use bytes::{Bytes, BytesMut};
 
+use crate::decoder::CancellationToken;
 use crate::error::AsyncTiffError;
-use crate::tiff::tags::PlanarConfiguration;
+use crate::tiff::tags::{PlanarConfiguration, SampleFormat};
 use crate::ImageFileDirectory;
 use crate::{error::AsyncTiffResult, reader::Endianness};
 
@@ -37,6 +38,12 @@ pub(crate) struct PredictorInfo {
     samples_per_pixel: u16,
 
     planar_configuration: PlanarConfiguration,
+
+    /// the format of the first sample
+    sample_format: SampleFormat,
+
+    /// whether chunks are tiles rather than strips
+    tiled: bool,
 }
 
 impl PredictorInfo {
@@ -48,6 +55,14 @@ impl PredictorInfo {
         self.bits_per_sample
     }
 
+    pub(crate) fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    pub(crate) fn planar_configuration(&self) -> PlanarConfiguration {
+        self.planar_configuration
+    }
+
     pub(crate) fn from_ifd(ifd: &ImageFileDirectory) -> Self {
         if !ifd.bits_per_sample.windows(2).all(|w| w[0] == w[1]) {
             panic!("bits_per_sample should be the same for all channels");
@@ -61,8 +76,7 @@ impl PredictorInfo {
         let chunk_height = if let Some(tile_height) = ifd.tile_height {
             tile_height
         } else {
-            ifd.rows_per_strip
-                .expect("no tile height and no rows_per_strip")
+            ifd.strip_height().unwrap_or(ifd.image_height)
         };
 
         PredictorInfo {
@@ -74,6 +88,85 @@ impl PredictorInfo {
             planar_configuration: ifd.planar_configuration,
             bits_per_sample: ifd.bits_per_sample[0],
             samples_per_pixel: ifd.samples_per_pixel,
+            sample_format: ifd.sample_format[0],
+            tiled: ifd.tile_width.is_some(),
+        }
+    }
+
+    /// The number of samples per pixel within a chunk.
+    ///
+    /// With [`PlanarConfiguration::Planar`], every chunk holds a single sample plane, so this is
+    /// 1 regardless of the number of samples per pixel of the image.
+    pub(crate) fn chunk_samples_per_pixel(&self) -> usize {
+        match self.planar_configuration {
+            PlanarConfiguration::Chunky => self.samples_per_pixel as usize,
+            PlanarConfiguration::Planar => 1,
+        }
+    }
+
+    /// The number of bytes in each decoded row of a chunk, including any padding columns.
+    ///
+    /// With [`PlanarConfiguration::Planar`], this is the row stride of a single sample plane.
+    pub(crate) fn chunk_row_bytes(&self) -> usize {
+        (self.chunk_width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    /// The number of rows of chunk row `y`, not counting padding rows.
+    pub(crate) fn chunk_rows(&self, y: u32) -> AsyncTiffResult<u32> {
+        self.chunk_height_pixels(y)
+    }
+
+    /// The minimum number of decoded bytes of chunk row `y`.
+    ///
+    /// Edge tiles may or may not include their padding rows, so only the rows within the image
+    /// are required.
+    pub(crate) fn required_chunk_bytes(&self, y: u32) -> AsyncTiffResult<usize> {
+        Ok(self.chunk_rows(y)? as usize * self.chunk_row_bytes())
+    }
+
+    /// The number of bytes in each row of chunk column `x`, not counting padding columns.
+    pub(crate) fn valid_row_bytes(&self, x: u32) -> AsyncTiffResult<usize> {
+        Ok((self.chunk_width_pixels(x)? as usize * self.bits_per_pixel()).div_ceil(8))
+    }
+
+    /// The number of columns of chunk column `x`, not counting padding columns.
+    pub(crate) fn chunk_columns(&self, x: u32) -> AsyncTiffResult<u32> {
+        self.chunk_width_pixels(x)
+    }
+
+    /// The number of rows of chunk row `y`, including padding rows.
+    ///
+    /// Strips aren't padded, so for strips this is the same as [`chunk_rows`](Self::chunk_rows).
+    pub(crate) fn padded_chunk_rows(&self, y: u32) -> AsyncTiffResult<u32> {
+        if self.tiled {
+            Ok(self.chunk_height)
+        } else {
+            self.chunk_rows(y)
+        }
+    }
+
+    /// The number of columns of a full chunk, including padding columns.
+    pub(crate) fn padded_chunk_columns(&self) -> u32 {
+        self.chunk_width
+    }
+
+    /// The number of pixels of a full chunk, including any padding rows and columns.
+    pub(crate) fn padded_chunk_pixels(&self) -> u64 {
+        self.chunk_width as u64 * self.chunk_height as u64
+    }
+
+    /// The number of decoded bytes of a full chunk, including any padding rows and columns.
+    pub(crate) fn padded_chunk_bytes(&self) -> usize {
+        self.chunk_height as usize * self.chunk_row_bytes()
+    }
+
+    /// The info for `rows` full-width rows of a chunk, treated as a chunk of their own.
+    pub(crate) fn for_rows(&self, rows: u32) -> Self {
+        Self {
+            image_width: self.chunk_width,
+            image_height: rows,
+            chunk_height: rows,
+            ..*self
         }
     }
 
@@ -112,24 +205,16 @@ impl PredictorInfo {
         Ok((self.chunk_width_pixels(x)? as usize).saturating_mul(self.bits_per_pixel()) / 8)
     }
 
-    /// the number of rows the output has, taking padding and PlanarConfiguration into account.
+    /// the number of rows the output has, taking padding into account.
+    ///
+    /// A chunk of a planar image holds a single sample plane, so this doesn't depend on
+    /// PlanarConfiguration.
     fn output_rows(&self, y: u32) -> AsyncTiffResult<usize> {
-        match self.planar_configuration {
-            PlanarConfiguration::Chunky => Ok(self.chunk_height_pixels(y)? as usize),
-            PlanarConfiguration::Planar => {
-                Ok((self.chunk_height_pixels(y)? as usize)
-                    .saturating_mul(self.samples_per_pixel as _))
-            }
-        }
+        Ok(self.chunk_height_pixels(y)? as usize)
     }
 
-    fn bits_per_pixel(&self) -> usize {
-        match self.planar_configuration {
-            PlanarConfiguration::Chunky => {
-                self.bits_per_sample as usize * self.samples_per_pixel as usize
-            }
-            PlanarConfiguration::Planar => self.bits_per_sample as usize,
-        }
+    pub(crate) fn bits_per_pixel(&self) -> usize {
+        self.bits_per_sample as usize * self.chunk_samples_per_pixel()
     }
 
     /// The number of chunks in the horizontal (x) direction
@@ -150,19 +235,66 @@ pub(crate) fn unpredict_hdiff(
     buffer: Bytes,
     predictor_info: &PredictorInfo,
     tile_x: u32,
+    cancellation: &CancellationToken,
 ) -> AsyncTiffResult<Bytes> {
-    let output_row_stride = predictor_info.output_row_stride(tile_x)?;
-    let samples = predictor_info.samples_per_pixel as usize;
+    let samples = predictor_info.chunk_samples_per_pixel();
     let bit_depth = predictor_info.bits_per_sample;
+    let row_stride = hdiff_row_stride(buffer.len(), predictor_info, tile_x)?;
 
     let buffer = fix_endianness(buffer, predictor_info.endianness, bit_depth);
     let mut res = BytesMut::from(buffer);
-    for buf in res.chunks_mut(output_row_stride) {
+    for buf in res.chunks_mut(row_stride) {
+        cancellation.check()?;
         rev_hpredict_nsamp(buf, bit_depth, samples);
     }
     Ok(res.into())
 }
 
+/// reverse horizontal predictor applied to each byte of a sample independently
+///
+/// Some writers (incorrectly) apply horizontal differencing to floating point data byte by byte
+/// in file byte order. GDAL reads such files by reversing the differencing the same way, before
+/// fixing byte order.
+pub(crate) fn unpredict_hdiff_bytewise(
+    buffer: Bytes,
+    predictor_info: &PredictorInfo,
+    tile_x: u32,
+    cancellation: &CancellationToken,
+) -> AsyncTiffResult<Bytes> {
+    let bytes_per_sample = (predictor_info.bits_per_sample as usize).div_ceil(8);
+    let bytes_per_pixel = predictor_info.chunk_samples_per_pixel() * bytes_per_sample;
+    let row_stride = hdiff_row_stride(buffer.len(), predictor_info, tile_x)?;
+
+    let mut res = BytesMut::from(buffer);
+    for buf in res.chunks_mut(row_stride) {
+        cancellation.check()?;
+        rev_hpredict_nsamp(buf, 8, bytes_per_pixel);
+    }
+    Ok(fix_endianness(
+        res.freeze(),
+        predictor_info.endianness,
+        predictor_info.bits_per_sample,
+    ))
+}
+
+/// The number of bytes in each row of a decoded chunk.
+///
+/// Edge tiles are normally padded to the full chunk width, in which case rows must be split at
+/// the padded width.
+fn hdiff_row_stride(
+    buffer_len: usize,
+    predictor_info: &PredictorInfo,
+    tile_x: u32,
+) -> AsyncTiffResult<usize> {
+    let padded_row_stride =
+        predictor_info.chunk_width as usize * predictor_info.bits_per_pixel() / 8;
+    if buffer_len == padded_row_stride * predictor_info.chunk_height as usize {
+        Ok(padded_row_stride)
+    } else {
+        predictor_info.output_row_stride(tile_x)
+    }
+}
+
 /// Reverse predictor convenience function for horizontal differencing
 ///
 // From image-tiff
@@ -202,16 +334,59 @@ pub fn rev_hpredict_nsamp(buf: &mut [u8], bit_depth: u16, samples: usize) {
     }
 }
 
-/// Fix endianness. If `byte_order` matches the host, then conversion is a no-op.
+/// Horizontal differencing, the inverse of [`rev_hpredict_nsamp`]
+///
+/// This should be used _before_ converting to the byte order of the file
+pub fn hpredict_nsamp(buf: &mut [u8], bit_depth: u16, samples: usize) {
+    match bit_depth {
+        0..=8 => {
+            for i in (samples..buf.len()).rev() {
+                buf[i] = buf[i].wrapping_sub(buf[i - samples]);
+            }
+        }
+        9..=16 => {
+            for i in (samples * 2..buf.len()).step_by(2).rev() {
+                let v = u16::from_ne_bytes(buf[i..][..2].try_into().unwrap());
+                let p = u16::from_ne_bytes(buf[i - 2 * samples..][..2].try_into().unwrap());
+                buf[i..][..2].copy_from_slice(&(v.wrapping_sub(p)).to_ne_bytes());
+            }
+        }
+        17..=32 => {
+            for i in (samples * 4..buf.len()).step_by(4).rev() {
+                let v = u32::from_ne_bytes(buf[i..][..4].try_into().unwrap());
+                let p = u32::from_ne_bytes(buf[i - 4 * samples..][..4].try_into().unwrap());
+                buf[i..][..4].copy_from_slice(&(v.wrapping_sub(p)).to_ne_bytes());
+            }
+        }
+        33..=64 => {
+            for i in (samples * 8..buf.len()).step_by(8).rev() {
+                let v = u64::from_ne_bytes(buf[i..][..8].try_into().unwrap());
+                let p = u64::from_ne_bytes(buf[i - 8 * samples..][..8].try_into().unwrap());
+                buf[i..][..8].copy_from_slice(&(v.wrapping_sub(p)).to_ne_bytes());
+            }
+        }
+        _ => {
+            unreachable!("Caller should have validated arguments. Please file a bug.")
+        }
+    }
+}
+
+/// Whether samples of `bit_depth` bits stored in `byte_order` differ from their native-endian
+/// representation.
+pub(crate) fn needs_byte_swap(byte_order: Endianness, bit_depth: u16) -> bool {
+    let native = match byte_order {
+        Endianness::LittleEndian => cfg!(target_endian = "little"),
+        Endianness::BigEndian => cfg!(target_endian = "big"),
+    };
+    bit_depth > 8 && !native
+}
+
+/// Fix endianness. If `byte_order` matches the host or samples are at most 8 bits, then
+/// conversion is a no-op and `buffer` is returned without copying.
 ///
 // from image-tiff
 pub fn fix_endianness(buffer: Bytes, byte_order: Endianness, bit_depth: u16) -> Bytes {
-    #[cfg(target_endian = "little")]
-    if let Endianness::LittleEndian = byte_order {
-        return buffer;
-    }
-    #[cfg(target_endian = "big")]
-    if let Endianness::BigEndian = byte_order {
+    if !needs_byte_swap(byte_order, bit_depth) {
         return buffer;
     }
 
@@ -262,11 +437,13 @@ pub(crate) fn unpredict_float(
     predictor_info: &PredictorInfo,
     tile_x: u32,
     tile_y: u32,
+    cancellation: &CancellationToken,
 ) -> AsyncTiffResult<Bytes> {
     let output_row_stride = predictor_info.output_row_stride(tile_x)?;
     let mut res: BytesMut =
         BytesMut::zeroed(output_row_stride * predictor_info.output_rows(tile_y)?);
     let bit_depth = predictor_info.bits_per_sample;
+    let samples = predictor_info.chunk_samples_per_pixel();
     if predictor_info.chunk_width_pixels(tile_x)? == predictor_info.chunk_width {
         // no special padding handling
         let mut input = BytesMut::from(buffer);
@@ -274,10 +451,11 @@ pub(crate) fn unpredict_float(
             .chunks_mut(output_row_stride)
             .zip(res.chunks_mut(output_row_stride))
         {
+            cancellation.check()?;
             match bit_depth {
-                16 => rev_predict_f16(in_buf, out_buf, predictor_info.samples_per_pixel as _),
-                32 => rev_predict_f32(in_buf, out_buf, predictor_info.samples_per_pixel as _),
-                64 => rev_predict_f64(in_buf, out_buf, predictor_info.samples_per_pixel as _),
+                16 => rev_predict_f16(in_buf, out_buf, samples),
+                32 => rev_predict_f32(in_buf, out_buf, samples),
+                64 => rev_predict_f64(in_buf, out_buf, samples),
                 _ => {
                     return Err(AsyncTiffError::General(format!(
                         "No predictor support for f{bit_depth:?}"
@@ -290,17 +468,17 @@ pub(crate) fn unpredict_float(
         // create a buffer for the full width
         let mut input = BytesMut::from(buffer);
 
-        let input_row_stride =
-            predictor_info.chunk_width as usize * predictor_info.bits_per_sample as usize / 8;
+        let input_row_stride = predictor_info.chunk_row_bytes();
         for (in_buf, out_buf) in input
             .chunks_mut(input_row_stride)
             .zip(res.chunks_mut(output_row_stride))
         {
+            cancellation.check()?;
             let mut out_row = BytesMut::zeroed(input_row_stride);
             match bit_depth {
-                16 => rev_predict_f16(in_buf, &mut out_row, predictor_info.samples_per_pixel as _),
-                32 => rev_predict_f32(in_buf, &mut out_row, predictor_info.samples_per_pixel as _),
-                64 => rev_predict_f64(in_buf, &mut out_row, predictor_info.samples_per_pixel as _),
+                16 => rev_predict_f16(in_buf, &mut out_row, samples),
+                32 => rev_predict_f32(in_buf, &mut out_row, samples),
+                64 => rev_predict_f64(in_buf, &mut out_row, samples),
                 _ => {
                     return Err(AsyncTiffError::General(format!(
                         "No predictor support for f{bit_depth:?}"
@@ -391,6 +569,30 @@ pub fn rev_predict_f64(input: &mut [u8], output: &mut [u8], samples: usize) {
     }
 }
 
+/// Floating point prediction of a row, the inverse of [`rev_predict_f16`], [`rev_predict_f32`]
+/// and [`rev_predict_f64`]
+///
+/// Shuffles the bytes of the native-endian samples of `input` into big-endian byte planes in
+/// `output`, and then applies horizontal differencing to the bytes.
+pub fn predict_float(input: &[u8], output: &mut [u8], bit_depth: u16, samples: usize) {
+    let bytes_per_sample = bit_depth as usize / 8;
+    let plane_len = input.len() / bytes_per_sample;
+    for (i, sample) in input.chunks_exact(bytes_per_sample).enumerate() {
+        for byte in 0..bytes_per_sample {
+            // floating predictor is be-like
+            let be_byte = if cfg!(target_endian = "little") {
+                sample[bytes_per_sample - 1 - byte]
+            } else {
+                sample[byte]
+            };
+            output[byte * plane_len + i] = be_byte;
+        }
+    }
+    for i in (samples..output.len()).rev() {
+        output[i] = output[i].wrapping_sub(output[i - samples]);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::vec;
@@ -413,6 +615,8 @@ mod test {
         bits_per_sample: 8,
         samples_per_pixel: 1,
         planar_configuration: PlanarConfiguration::Chunky,
+        sample_format: SampleFormat::Uint,
+        tiled: true,
     };
     #[rustfmt::skip]
     const RES: [u8;16] = [
@@ -482,9 +686,32 @@ mod test {
         assert_eq!(info.output_rows(0).unwrap(), 4);
         assert_eq!(info.output_rows(1).unwrap(), 3);
         info.output_rows(2).unwrap_err();
+        // Each chunk of a planar image holds a single sample plane
+        info.planar_configuration = PlanarConfiguration::Planar;
+        assert_eq!(info.output_rows(0).unwrap(), 4);
+        assert_eq!(info.output_rows(1).unwrap(), 3);
+        assert_eq!(info.chunk_samples_per_pixel(), 1);
+        assert_eq!(info.chunk_row_bytes(), 4);
+    }
+
+    #[test]
+    fn test_hdiff_unpredict_planar() {
+        // A plane of 3-sample image only differences neighbouring values of the same sample
+        let mut info = PRED_INFO;
+        info.image_width = 4;
+        info.image_height = 1;
+        info.chunk_height = 1;
+        info.samples_per_pixel = 3;
         info.planar_configuration = PlanarConfiguration::Planar;
-        assert_eq!(info.output_rows(0).unwrap(), 8);
-        assert_eq!(info.output_rows(1).unwrap(), 6);
+        let buffer = Bytes::from_static(&[1, 1, 1, 1]);
+        assert_eq!(
+            &unpredict_hdiff(buffer.clone(), &info, 0, &Default::default()).unwrap()[..],
+            [1, 2, 3, 4]
+        );
+        assert_eq!(
+            &unpredict_hdiff_bytewise(buffer, &info, 0, &Default::default()).unwrap()[..],
+            [1, 2, 3, 4]
+        );
     }
 
     // #[rustfmt::skip]
@@ -501,6 +728,37 @@ mod test {
     //     }
     // }
 
+    #[test]
+    fn test_fix_endianness() {
+        let buffer = Bytes::from(vec![1u8, 2, 3, 4]);
+        // No copy is made of shared 8-bit or native-endian buffers
+        for (byte_order, bit_depth) in [
+            (Endianness::BigEndian, 8),
+            (Endianness::LittleEndian, 8),
+            (native_endianness(), 16),
+        ] {
+            let fixed = fix_endianness(buffer.clone(), byte_order, bit_depth);
+            assert_eq!(fixed.as_ptr(), buffer.as_ptr());
+        }
+        let swapped = match native_endianness() {
+            Endianness::LittleEndian => Endianness::BigEndian,
+            Endianness::BigEndian => Endianness::LittleEndian,
+        };
+        assert_eq!(
+            fix_endianness(buffer.clone(), swapped, 16).as_ref(),
+            [2, 1, 4, 3]
+        );
+        assert_eq!(fix_endianness(buffer, swapped, 32).as_ref(), [4, 3, 2, 1]);
+    }
+
+    fn native_endianness() -> Endianness {
+        if cfg!(target_endian = "little") {
+            Endianness::LittleEndian
+        } else {
+            Endianness::BigEndian
+        }
+    }
+
     #[rustfmt::skip]
     #[test]
     fn test_hdiff_unpredict() {
@@ -537,25 +795,25 @@ mod test {
             println!("testing u8");
             let buffer = Bytes::from(input.iter().map(|v| *v as u8).collect::<Vec<_>>());
             let res = Bytes::from(expected.clone());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u16, u16::MAX);
             println!("testing u16");
             predictor_info.bits_per_sample = 16;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as u16).to_le_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as u16).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u32, u32::MAX);
             println!("testing u32");
             predictor_info.bits_per_sample = 32;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as u32).to_le_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as u32).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u64, u64::MAX);
             println!("testing u64");
             predictor_info.bits_per_sample = 64;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as u64).to_le_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as u64).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
 
             println!("ints littleendian");
             predictor_info.bits_per_sample = 8;
@@ -563,22 +821,22 @@ mod test {
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as i8).to_le_bytes()).collect::<Vec<_>>());
             println!("{:?}", &buffer[..]);
             let res = Bytes::from(expected.clone());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap()[..], res[..]);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap()[..], res[..]);
             println!("testing i16");
             predictor_info.bits_per_sample = 16;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as i16).to_le_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as i16).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             println!("testing i32");
             predictor_info.bits_per_sample = 32;
             let buffer = Bytes::from(input.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as i32).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             println!("testing i64");
             predictor_info.bits_per_sample = 64;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as i64).to_le_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as i64).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
 
             println!("uints bigendian");
             predictor_info.endianness = Endianness::BigEndian;
@@ -587,26 +845,26 @@ mod test {
             println!("testing u8");
             let buffer = Bytes::from(input.iter().map(|v| *v as u8).collect::<Vec<_>>());
             let res = Bytes::from(expected.clone());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u16, u16::MAX);
             println!("testing u16");
             predictor_info.bits_per_sample = 16;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as u16).to_be_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as u16).to_ne_bytes()).collect::<Vec<_>>());
             println!("buffer: {:?}", &buffer[..]);
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap()[..], res[..]);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap()[..], res[..]);
             assert_eq!(-1i32 as u32, u32::MAX);
             println!("testing u32");
             predictor_info.bits_per_sample = 32;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as u32).to_be_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as u32).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u64, u64::MAX);
             println!("testing u64");
             predictor_info.bits_per_sample = 64;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as u64).to_be_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as u64).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
 
             println!("ints bigendian");
             predictor_info.bits_per_sample = 8;
@@ -614,28 +872,70 @@ mod test {
             println!("testing i8");
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as i8).to_be_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.clone());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u16, u16::MAX);
             println!("testing i16");
             predictor_info.bits_per_sample = 16;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as i16).to_be_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as i16).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u32, u32::MAX);
             println!("testing i32");
             predictor_info.bits_per_sample = 32;
             let buffer = Bytes::from(input.iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as i32).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
             assert_eq!(-1i32 as u64, u64::MAX);
             println!("testing i64");
             predictor_info.bits_per_sample = 64;
             let buffer = Bytes::from(input.iter().flat_map(|v| (*v as i64).to_be_bytes()).collect::<Vec<_>>());
             let res = Bytes::from(expected.iter().flat_map(|v| (*v as i64).to_ne_bytes()).collect::<Vec<_>>());
-            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x).unwrap(), res);
+            assert_eq!(unpredict_hdiff(buffer, &predictor_info, x, &Default::default()).unwrap(), res);
         }
     }
 
+    #[test]
+    fn test_hdiff_unpredict_bytewise() {
+        let mut predictor_info = PRED_INFO;
+        predictor_info.bits_per_sample = 32;
+        predictor_info.chunk_width = 3;
+        predictor_info.chunk_height = 1;
+        predictor_info.image_width = 3;
+        predictor_info.image_height = 1;
+        predictor_info.sample_format = SampleFormat::IEEEFP;
+
+        let expected = [1.5f32, -2.25, 1e10];
+        let bytes = expected
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        // Difference each byte against the same byte of the previous pixel
+        let mut diffed = bytes.clone();
+        for i in (4..bytes.len()).rev() {
+            diffed[i] = bytes[i].wrapping_sub(bytes[i - 4]);
+        }
+
+        let res =
+            unpredict_hdiff_bytewise(Bytes::from(diffed), &predictor_info, 0, &Default::default())
+                .unwrap();
+        let res = res
+            .chunks_exact(4)
+            .map(|v| f32::from_ne_bytes(v.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn test_hdiff_unpredict_cancelled() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let buffer = Bytes::from(vec![0u8; 16]);
+        assert!(matches!(
+            unpredict_hdiff(buffer, &PRED_INFO, 0, &cancellation),
+            Err(AsyncTiffError::Cancelled)
+        ));
+    }
+
     #[rustfmt::skip]
     #[test]
     fn test_predict_f16() {
@@ -657,10 +957,12 @@ mod test {
             bits_per_sample: 16,
             samples_per_pixel: 1,
             planar_configuration: PlanarConfiguration::Chunky,
+            sample_format: SampleFormat::IEEEFP,
+            tiled: true,
         };
         let input = Bytes::from_owner(diffed);
         assert_eq!(
-            &unpredict_float(input, &info, 1, 1).unwrap()[..],
+            &unpredict_float(input, &info, 1, 1, &Default::default()).unwrap()[..],
             &expect_le[..]
         )
     }
@@ -686,10 +988,12 @@ mod test {
             bits_per_sample: 16,
             samples_per_pixel: 1,
             planar_configuration: PlanarConfiguration::Chunky,
+            sample_format: SampleFormat::IEEEFP,
+            tiled: true,
         };
         let input = Bytes::from_owner(diffed);
         assert_eq!(
-            &unpredict_float(input, &info, 1, 1).unwrap()[..],
+            &unpredict_float(input, &info, 1, 1, &Default::default()).unwrap()[..],
             &expect_le[..]
         )
     }
@@ -714,10 +1018,23 @@ mod test {
             bits_per_sample: 32,
             samples_per_pixel: 1,
             planar_configuration: PlanarConfiguration::Chunky,
+            sample_format: SampleFormat::IEEEFP,
+            tiled: true,
         };
         let input = Bytes::from_owner(diffed);
         assert_eq!(
-            &unpredict_float(input.clone(), &info, 0, 1).unwrap()[..],
+            &unpredict_float(input.clone(), &info, 0, 1, &Default::default()).unwrap()[..],
+            &expect_le
+        );
+
+        // A plane of a 3-sample planar image is predicted like a single-sample image
+        let planar = PredictorInfo {
+            samples_per_pixel: 3,
+            planar_configuration: PlanarConfiguration::Planar,
+            ..info
+        };
+        assert_eq!(
+            &unpredict_float(input, &planar, 0, 1, &Default::default()).unwrap()[..],
             &expect_le
         );
     }
@@ -742,12 +1059,41 @@ mod test {
             bits_per_sample: 64,
             samples_per_pixel: 1,
             planar_configuration: PlanarConfiguration::Chunky,
+            sample_format: SampleFormat::IEEEFP,
+            tiled: true,
         };
         let input = Bytes::from_owner(diffed);
         assert_eq!(
-            &unpredict_float(input, &info, 0, 1)
+            &unpredict_float(input, &info, 0, 1, &Default::default())
                 .unwrap()[..],
             &expect_be[..]
         );
     }
+
+    #[test]
+    fn test_hpredict_roundtrip() {
+        for bit_depth in [8, 16, 32, 64] {
+            let original = (0..48u8).map(|v| v.wrapping_mul(37)).collect::<Vec<_>>();
+            let mut buf = original.clone();
+            hpredict_nsamp(&mut buf, bit_depth, 3);
+            assert_ne!(buf, original);
+            rev_hpredict_nsamp(&mut buf, bit_depth, 3);
+            assert_eq!(buf, original);
+        }
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_predict_float() {
+        // the inverse of test_fpredict_f32
+        let input = [0x00010203u32, 0x04050607].map(u32::to_ne_bytes).concat();
+        let mut output = [0; 8];
+        predict_float(&input, &mut output, 32, 1);
+        assert_eq!(output, [0,4,253,4,253,4,253,4u8]);
+
+        let input = [0x0001020304050607u64, 0x08090a0b0c0d0e0f].map(u64::to_ne_bytes).concat();
+        let mut output = [0; 16];
+        predict_float(&input, &mut output, 64, 1);
+        assert_eq!(output, [0,8,249,8,249,8,249,8,249,8,249,8,249,8,249,8u8]);
+    }
 }
@@ -0,0 +1,201 @@
+//! Reading the basic properties of a TIFF with as few bytes as possible.
+
+#[cfg(feature = "object_store")]
+use std::sync::Arc;
+
+#[cfg(feature = "object_store")]
+use object_store::path::Path;
+#[cfg(feature = "object_store")]
+use object_store::ObjectStore;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::geo::GeoKeyDirectory;
+#[cfg(feature = "object_store")]
+use crate::metadata::PrefetchBuffer;
+use crate::metadata::{ImageFileDirectoryReader, MetadataFetch, TiffMetadataReader};
+#[cfg(feature = "object_store")]
+use crate::reader::ObjectReader;
+use crate::tiff::tags::{CompressionMethod, SampleFormat, Tag};
+
+/// The number of bytes prefetched by [`probe`], which holds the first IFD of most files.
+#[cfg(feature = "object_store")]
+const PROBE_PREFETCH: u64 = 16 * 1024;
+
+/// Tags whose values are skipped when probing, as they hold an entry per tile or strip.
+const CHUNK_LOCATION_TAGS: [Tag; 4] = [
+    Tag::TileOffsets,
+    Tag::TileByteCounts,
+    Tag::StripOffsets,
+    Tag::StripByteCounts,
+];
+
+/// The basic properties of the full resolution image of a TIFF, as read by [`probe`] or
+/// [`ProbeInfo::read`].
+#[derive(Debug, Clone)]
+pub struct ProbeInfo {
+    width: u32,
+    height: u32,
+    samples_per_pixel: u16,
+    sample_format: SampleFormat,
+    bits_per_sample: u16,
+    compression: CompressionMethod,
+    tile_size: Option<(u32, u32)>,
+    geo_key_directory: Option<GeoKeyDirectory>,
+    bigtiff: bool,
+}
+
+impl ProbeInfo {
+    /// Read the properties of the first IFD of the file behind `fetch`.
+    ///
+    /// Only the file header, the IFD entries and the tag values they point to are fetched. The
+    /// tile and strip offsets and byte counts are skipped, as they can take up most of the
+    /// metadata of large images. Wrap `fetch` in a
+    /// [`PrefetchBuffer`](crate::metadata::PrefetchBuffer) to read the IFD with a single request.
+    pub async fn read<F: MetadataFetch>(fetch: &F) -> AsyncTiffResult<Self> {
+        let metadata = TiffMetadataReader::try_open(fetch).await?;
+        let ifd_offset = metadata
+            .next_ifd_offset()
+            .ok_or_else(|| AsyncTiffError::General("File has no IFDs".to_string()))?;
+        let ifd = ImageFileDirectoryReader::open(
+            fetch,
+            ifd_offset,
+            metadata.bigtiff(),
+            metadata.endianness(),
+        )
+        .await?
+        .read_skipping(fetch, &CHUNK_LOCATION_TAGS)
+        .await?;
+
+        Ok(Self {
+            width: ifd.image_width(),
+            height: ifd.image_height(),
+            samples_per_pixel: ifd.samples_per_pixel(),
+            sample_format: ifd
+                .sample_format()
+                .first()
+                .copied()
+                .unwrap_or(SampleFormat::Uint),
+            bits_per_sample: ifd.bits_per_sample().first().copied().unwrap_or(1),
+            compression: ifd.compression(),
+            tile_size: ifd.tile_width().zip(ifd.tile_height()),
+            geo_key_directory: ifd.geo_key_directory().cloned(),
+            bigtiff: metadata.bigtiff(),
+        })
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of bands, i.e. samples per pixel.
+    pub fn band_count(&self) -> u16 {
+        self.samples_per_pixel
+    }
+
+    /// The format of the samples of the first band.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The number of bits per sample of the first band.
+    pub fn bits_per_sample(&self) -> u16 {
+        self.bits_per_sample
+    }
+
+    /// The compression of the image data.
+    pub fn compression(&self) -> CompressionMethod {
+        self.compression
+    }
+
+    /// The width and height of the tiles, or `None` if the image is stored in strips.
+    pub fn tile_size(&self) -> Option<(u32, u32)> {
+        self.tile_size
+    }
+
+    /// The GeoTIFF keys describing the CRS, if any.
+    pub fn geo_key_directory(&self) -> Option<&GeoKeyDirectory> {
+        self.geo_key_directory.as_ref()
+    }
+
+    /// The EPSG code of the CRS, if it is one.
+    pub fn epsg(&self) -> Option<u16> {
+        self.geo_key_directory.as_ref()?.epsg_code()
+    }
+
+    /// Whether the file is a BigTIFF.
+    pub fn bigtiff(&self) -> bool {
+        self.bigtiff
+    }
+}
+
+/// Read the basic properties of the TIFF at `path` in `store`, such as its dimensions, data
+/// type and CRS, without reading its tile offsets.
+///
+/// This prefetches the first 16 KiB of the file, so that a file whose first IFD is in its header,
+/// like a Cloud Optimized GeoTIFF, is probed with a single request. It is suited to cataloging
+/// many files; see [`ProbeInfo::read`] for other sources.
+#[cfg(feature = "object_store")]
+pub async fn probe(store: Arc<dyn ObjectStore>, path: Path) -> AsyncTiffResult<ProbeInfo> {
+    let reader = ObjectReader::new(store, path);
+    let prefetch = PrefetchBuffer::new(reader, PROBE_PREFETCH).await?;
+    ProbeInfo::read(&prefetch).await
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::test_util::{write_cog, CountingReader};
+    use crate::{CogImage, IfdBuilder};
+
+    #[tokio::test]
+    async fn test_probe_skips_tile_offsets() {
+        // 4096 tiles, whose offsets and byte counts take up 32 KiB
+        let ifd = IfdBuilder::new(1024, 1024)
+            .with_data_type(SampleFormat::Int, 16)
+            .with_samples_per_pixel(2)
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::Deflate)
+            .with_geo_key_directory(GeoKeyDirectory {
+                projected_type: Some(32633),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let tiles = vec![Bytes::new(); 4096];
+        let file = write_cog(vec![CogImage::from_encoded(ifd, tiles)]).await;
+        let fetch = CountingReader::new(file);
+
+        let info = ProbeInfo::read(&fetch).await.unwrap();
+        assert_eq!((info.width(), info.height()), (1024, 1024));
+        assert_eq!(info.band_count(), 2);
+        assert_eq!(info.sample_format(), SampleFormat::Int);
+        assert_eq!(info.bits_per_sample(), 16);
+        assert_eq!(info.compression(), CompressionMethod::Deflate);
+        assert_eq!(info.tile_size(), Some((16, 16)));
+        assert_eq!(info.epsg(), Some(32633));
+        assert!(!info.bigtiff());
+        assert!(fetch.fetched() < 1024);
+    }
+
+    #[cfg(feature = "object_store")]
+    #[tokio::test]
+    async fn test_probe() {
+        use object_store::local::LocalFileSystem;
+
+        let store =
+            Arc::new(LocalFileSystem::new_with_prefix(std::env::current_dir().unwrap()).unwrap());
+        let path = Path::from("tests/image_tiff/images/tiled-rgb-u8.tif");
+        let info = probe(store, path).await.unwrap();
+        assert_eq!(info.band_count(), 3);
+        assert_eq!(info.bits_per_sample(), 8);
+        assert!(info.tile_size().is_some());
+    }
+}
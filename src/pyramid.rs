@@ -0,0 +1,242 @@
+//! Grouping of a full-resolution image with its overviews and masks.
+
+use crate::cog::TIFF;
+use crate::decoder::DecoderRegistry;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::ifd::ImageFileDirectory;
+use crate::overview::is_overview;
+use crate::reader::AsyncFileReader;
+use crate::window::{MaskedWindowData, Window, WindowData};
+
+/// One resolution level of a [`Pyramid`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PyramidLevel {
+    ifd_index: usize,
+    mask_ifd_index: Option<usize>,
+    decimation: (f64, f64),
+}
+
+impl PyramidLevel {
+    /// The index of this level's IFD within the TIFF.
+    pub fn ifd_index(&self) -> usize {
+        self.ifd_index
+    }
+
+    /// The index of this level's internal mask IFD within the TIFF, if any.
+    pub fn mask_ifd_index(&self) -> Option<usize> {
+        self.mask_ifd_index
+    }
+
+    /// How many full-resolution pixels each pixel of this level covers along x and y.
+    ///
+    /// This is `(1.0, 1.0)` for the full-resolution level.
+    pub fn decimation(&self) -> (f64, f64) {
+        self.decimation
+    }
+}
+
+/// A full-resolution image together with its overviews and masks.
+///
+/// Levels are ordered from the full-resolution image to the coarsest overview, so that callers
+/// don't need to know how IFDs are laid out in the file.
+#[derive(Debug, Clone)]
+pub struct Pyramid {
+    tiff: TIFF,
+    levels: Vec<PyramidLevel>,
+}
+
+impl Pyramid {
+    /// Group the IFDs of `tiff`, treating the first IFD as the full-resolution image.
+    pub fn try_new(tiff: TIFF) -> AsyncTiffResult<Self> {
+        let full = tiff
+            .ifds()
+            .first()
+            .ok_or(AsyncTiffError::General("TIFF has no IFDs".to_string()))?;
+        let (full_width, full_height) = (full.image_width() as f64, full.image_height() as f64);
+
+        let mut indices = vec![0];
+        indices.extend(
+            tiff.ifds()
+                .iter()
+                .enumerate()
+                .skip(1)
+                .filter(|(_, ifd)| is_overview(ifd))
+                .map(|(idx, _)| idx),
+        );
+        indices.sort_by_key(|idx| std::cmp::Reverse(tiff.ifds()[*idx].image_width()));
+
+        let levels = indices
+            .into_iter()
+            .map(|ifd_index| {
+                let ifd = &tiff.ifds()[ifd_index];
+                PyramidLevel {
+                    ifd_index,
+                    mask_ifd_index: tiff.mask_ifd_index(ifd_index),
+                    decimation: (
+                        full_width / ifd.image_width() as f64,
+                        full_height / ifd.image_height() as f64,
+                    ),
+                }
+            })
+            .collect();
+
+        Ok(Self { tiff, levels })
+    }
+
+    /// Access the underlying TIFF.
+    pub fn tiff(&self) -> &TIFF {
+        &self.tiff
+    }
+
+    /// The levels of this pyramid, from full resolution to the coarsest overview.
+    pub fn levels(&self) -> &[PyramidLevel] {
+        &self.levels
+    }
+
+    /// The IFD of the level at `level`.
+    pub fn ifd(&self, level: usize) -> Option<&ImageFileDirectory> {
+        let level = self.levels.get(level)?;
+        Some(&self.tiff.ifds()[level.ifd_index])
+    }
+
+    /// The internal mask IFD of the level at `level`, if any.
+    pub fn mask(&self, level: usize) -> Option<&ImageFileDirectory> {
+        let level = self.levels.get(level)?;
+        Some(&self.tiff.ifds()[level.mask_ifd_index?])
+    }
+
+    /// Choose the coarsest level that still has at least the requested resolution.
+    ///
+    /// `decimation` is the number of full-resolution pixels per output pixel.
+    pub fn select_level(&self, decimation: f64) -> usize {
+        self.levels
+            .iter()
+            .rposition(|level| level.decimation.0.max(level.decimation.1) <= decimation)
+            .unwrap_or(0)
+    }
+
+    /// Read `window`, given in full-resolution pixels, from the coarsest level that can provide
+    /// at least `out_width` by `out_height` pixels for it.
+    ///
+    /// Returns the chosen level index and the data read from it. The returned data's window is in
+    /// the chosen level's pixel space and is expanded outwards to whole pixels of that level; it
+    /// is not resampled to exactly `out_width` by `out_height`.
+    pub async fn read_window(
+        &self,
+        window: Window,
+        out_width: u32,
+        out_height: u32,
+        reader: &dyn AsyncFileReader,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<(usize, WindowData)> {
+        let level = self.select_level(decimation(window, out_width, out_height));
+        let ifd = self.ifd(level).unwrap();
+        let level_window = self.level_window(level, window);
+        let data = ifd
+            .read_window(level_window, reader, decoder_registry)
+            .await?;
+        Ok((level, data))
+    }
+
+    /// Read `window` as in [`read_window`](Self::read_window), applying the chosen level's mask
+    /// or nodata value as in [`ImageFileDirectory::read_window_masked`].
+    pub async fn read_window_masked(
+        &self,
+        window: Window,
+        out_width: u32,
+        out_height: u32,
+        reader: &dyn AsyncFileReader,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<(usize, MaskedWindowData)> {
+        let level = self.select_level(decimation(window, out_width, out_height));
+        let ifd = self.ifd(level).unwrap();
+        let level_window = self.level_window(level, window);
+        let data = ifd
+            .read_window_masked(self.mask(level), level_window, reader, decoder_registry)
+            .await?;
+        Ok((level, data))
+    }
+
+    /// Convert a full-resolution window to the pixel space of `level`.
+    fn level_window(&self, level: usize, window: Window) -> Window {
+        let ifd = self.ifd(level).unwrap();
+        let (dx, dy) = self.levels[level].decimation;
+        let col_off = (window.col_off() as f64 / dx).floor() as u32;
+        let row_off = (window.row_off() as f64 / dy).floor() as u32;
+        let col_end = ((window.col_end() as f64 / dx).ceil() as u32).min(ifd.image_width());
+        let row_end = ((window.row_end() as f64 / dy).ceil() as u32).min(ifd.image_height());
+        Window::new(
+            col_off.min(col_end),
+            row_off.min(row_end),
+            col_end.saturating_sub(col_off),
+            row_end.saturating_sub(row_off),
+        )
+    }
+}
+
+/// The number of window pixels per output pixel, along the axis needing the most detail.
+fn decimation(window: Window, out_width: u32, out_height: u32) -> f64 {
+    let x = window.width() as f64 / out_width.max(1) as f64;
+    let y = window.height() as f64 / out_height.max(1) as f64;
+    x.min(y)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::reader::Endianness;
+    use crate::tiff::tags::Tag;
+    use crate::tiff::Value;
+
+    use super::*;
+
+    fn ifd(new_subfile_type: u32, width: u32, height: u32) -> ImageFileDirectory {
+        let tags = HashMap::from([
+            (Tag::NewSubfileType, Value::Unsigned(new_subfile_type)),
+            (Tag::ImageWidth, Value::Unsigned(width)),
+            (Tag::ImageLength, Value::Unsigned(height)),
+            (Tag::BitsPerSample, Value::Short(8)),
+            (Tag::PhotometricInterpretation, Value::Short(1)),
+            (Tag::SamplesPerPixel, Value::Short(1)),
+            (Tag::TileWidth, Value::Short(256)),
+            (Tag::TileLength, Value::Short(256)),
+        ]);
+        ImageFileDirectory::from_tags(tags, Endianness::LittleEndian).unwrap()
+    }
+
+    #[test]
+    fn test_pyramid_levels() {
+        // GDAL writes overviews before the masks
+        let tiff = TIFF::new(vec![
+            ifd(0, 1000, 800),
+            ifd(1, 250, 200),
+            ifd(1, 500, 400),
+            ifd(4, 1000, 800),
+            ifd(5, 500, 400),
+        ]);
+        let pyramid = Pyramid::try_new(tiff).unwrap();
+        let levels = pyramid.levels();
+        assert_eq!(
+            levels.iter().map(|l| l.ifd_index()).collect::<Vec<_>>(),
+            [0, 2, 1]
+        );
+        assert_eq!(
+            levels
+                .iter()
+                .map(|l| l.mask_ifd_index())
+                .collect::<Vec<_>>(),
+            [Some(3), Some(4), None]
+        );
+        assert_eq!(levels[2].decimation(), (4.0, 4.0));
+
+        assert_eq!(pyramid.select_level(1.0), 0);
+        assert_eq!(pyramid.select_level(3.0), 1);
+        assert_eq!(pyramid.select_level(100.0), 2);
+
+        assert_eq!(
+            pyramid.level_window(1, Window::new(101, 99, 200, 101)),
+            Window::new(50, 49, 101, 51)
+        );
+    }
+}
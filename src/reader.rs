@@ -1,17 +1,21 @@
 //! Abstractions for network reading.
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::Debug;
 use std::io::Read;
 use std::ops::Range;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use bytes::buf::Reader;
-use bytes::{Buf, Bytes};
-use futures::future::{BoxFuture, FutureExt};
+use bytes::{Buf, Bytes, BytesMut};
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, FutureExt, Shared};
+#[cfg(feature = "object_store")]
 use futures::TryFutureExt;
 
-use crate::error::AsyncTiffResult;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
 
 /// The asynchronous interface used to read COG files
 ///
@@ -26,6 +30,8 @@ use crate::error::AsyncTiffResult;
 /// 2. You can use [`TokioReader`] to implement [`AsyncFileReader`] for types that implement
 ///    [`tokio::io::AsyncRead`] and [`tokio::io::AsyncSeek`], for example [`tokio::fs::File`].
 ///
+/// 3. [`MemoryReader`] reads a file held in memory.
+///
 /// [`ObjectStore`]: object_store::ObjectStore
 ///
 /// [`tokio::fs::File`]: https://docs.rs/tokio/latest/tokio/fs/struct.File.html
@@ -83,6 +89,81 @@ impl AsyncFileReader for Arc<dyn AsyncFileReader + '_> {
     }
 }
 
+/// An [`AsyncFileReader`] that reads a file held in memory, e.g. one written with
+/// [`MemoryWriter`](crate::writer::MemoryWriter).
+///
+/// Ranges past the end of the file are cut short at its end, as [`ObjectReader`] does.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryReader(Bytes);
+
+impl MemoryReader {
+    /// Create a reader of `data`.
+    pub fn new(data: impl Into<Bytes>) -> Self {
+        Self(data.into())
+    }
+
+    /// The bytes of the file.
+    pub fn data(&self) -> &Bytes {
+        &self.0
+    }
+
+    /// Consume this reader, returning the bytes of the file.
+    pub fn into_inner(self) -> Bytes {
+        self.0
+    }
+}
+
+impl AsyncFileReader for MemoryReader {
+    fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        let len = self.0.len() as u64;
+        let start = range.start.min(len);
+        let end = range.end.clamp(start, len);
+        let bytes = self.0.slice(start as usize..end as usize);
+        async move { Ok(bytes) }.boxed()
+    }
+}
+
+/// Opaque context attached to read requests, such as a trace id or tenant id.
+///
+/// Readers that issue HTTP requests forward each entry as a request header, so that requests can
+/// be correlated end-to-end in object storage access logs. Since readers are cheap to clone,
+/// attach a context per operation by cloning the reader with a new context.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    headers: Vec<(String, String)>,
+}
+
+impl RequestContext {
+    /// Construct an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry to forward as the header `name`, e.g. `traceparent` or `x-request-id`.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The entries of this context, in the order they were added.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns `true` if this context has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    #[cfg(feature = "reqwest")]
+    fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request
+    }
+}
+
 /// A wrapper for things that implement [AsyncRead] and [AsyncSeek] to also implement
 /// [AsyncFileReader].
 ///
@@ -142,6 +223,9 @@ impl<T: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin + Send + Debug> Asyn
 pub struct ObjectReader {
     store: Arc<dyn object_store::ObjectStore>,
     path: object_store::path::Path,
+    get_options: Option<object_store::GetOptions>,
+    coalesce_gap: Option<u64>,
+    coalesce_stats: Option<Arc<CoalesceStats>>,
 }
 
 #[cfg(feature = "object_store")]
@@ -150,14 +234,103 @@ impl ObjectReader {
     ///
     /// [`ObjectMeta`] can be obtained using [`ObjectStore::list`] or [`ObjectStore::head`]
     pub fn new(store: Arc<dyn object_store::ObjectStore>, path: object_store::path::Path) -> Self {
-        Self { store, path }
+        Self {
+            store,
+            path,
+            get_options: None,
+            coalesce_gap: None,
+            coalesce_stats: None,
+        }
+    }
+
+    /// Send `get_options` with every request, for example to set a version id or an `If-Match`
+    /// precondition so that all reads see the same version of the object.
+    ///
+    /// The `range` of `get_options` is ignored and replaced by the range being read. Note that
+    /// [`get_byte_ranges`](AsyncFileReader::get_byte_ranges) then coalesces ranges itself rather
+    /// than delegating to [`ObjectStore::get_ranges`], since that doesn't accept options.
+    ///
+    /// [`ObjectStore::get_ranges`]: object_store::ObjectStore::get_ranges
+    pub fn with_get_options(mut self, get_options: object_store::GetOptions) -> Self {
+        self.get_options = Some(get_options);
+        self
+    }
+
+    /// The options sent with every request, if any.
+    pub fn get_options(&self) -> Option<&object_store::GetOptions> {
+        self.get_options.as_ref()
+    }
+
+    /// Attach `context` to every request.
+    ///
+    /// The backends provided by `object_store` have no way to set per-request headers, so the
+    /// context is stored in the [`extensions`](object_store::GetOptions::extensions) of the
+    /// request's options, where a custom [`ObjectStore`](object_store::ObjectStore) implementation
+    /// can read it and forward it.
+    pub fn with_context(mut self, context: RequestContext) -> Self {
+        self.get_options
+            .get_or_insert_with(Default::default)
+            .extensions
+            .insert(context);
+        self
+    }
+
+    /// Merge ranges in [`get_byte_ranges`](AsyncFileReader::get_byte_ranges) that are at most
+    /// `gap` bytes apart into a single request.
+    ///
+    /// A larger gap issues fewer requests at the cost of fetching bytes that aren't needed. The
+    /// right trade-off depends on the latency and per-request cost of the storage backend, which
+    /// can be measured with [`with_coalesce_stats`](Self::with_coalesce_stats). Defaults to
+    /// [`OBJECT_STORE_COALESCE_DEFAULT`](object_store::OBJECT_STORE_COALESCE_DEFAULT).
+    pub fn with_coalesce_gap(mut self, gap: u64) -> Self {
+        self.coalesce_gap = Some(gap);
+        self
+    }
+
+    /// Record how effective coalescing is in `stats`.
+    ///
+    /// The same counters can be shared between several readers to aggregate over a workload.
+    /// Like [`with_get_options`](Self::with_get_options), this makes
+    /// [`get_byte_ranges`](AsyncFileReader::get_byte_ranges) coalesce ranges itself rather than
+    /// delegating to [`ObjectStore::get_ranges`].
+    ///
+    /// [`ObjectStore::get_ranges`]: object_store::ObjectStore::get_ranges
+    pub fn with_coalesce_stats(mut self, stats: Arc<CoalesceStats>) -> Self {
+        self.coalesce_stats = Some(stats);
+        self
+    }
+
+    /// The coalescing counters attached to this reader, if any.
+    pub fn coalesce_stats(&self) -> Option<&Arc<CoalesceStats>> {
+        self.coalesce_stats.as_ref()
     }
 
     async fn make_range_request(&self, range: Range<u64>) -> AsyncTiffResult<Bytes> {
         let range = range.start as _..range.end as _;
+        match &self.get_options {
+            Some(get_options) => Ok(self.get_range_with_options(get_options, range).await?),
+            None => {
+                self.store
+                    .get_range(&self.path, range)
+                    .map_err(|e| e.into())
+                    .await
+            }
+        }
+    }
+
+    async fn get_range_with_options(
+        &self,
+        get_options: &object_store::GetOptions,
+        range: Range<u64>,
+    ) -> object_store::Result<Bytes> {
+        let options = object_store::GetOptions {
+            range: Some(range.into()),
+            ..get_options.clone()
+        };
         self.store
-            .get_range(&self.path, range)
-            .map_err(|e| e.into())
+            .get_opts(&self.path, options)
+            .await?
+            .bytes()
             .await
     }
 }
@@ -177,42 +350,592 @@ impl AsyncFileReader for ObjectReader {
             .map(|r| r.start as _..r.end as _)
             .collect::<Vec<_>>();
         async move {
-            self.store
-                .get_ranges(&self.path, &ranges)
-                .await
-                .map_err(|e| e.into())
+            if self.get_options.is_none()
+                && self.coalesce_gap.is_none()
+                && self.coalesce_stats.is_none()
+            {
+                return self
+                    .store
+                    .get_ranges(&self.path, &ranges)
+                    .await
+                    .map_err(|e| e.into());
+            }
+
+            if let Some(stats) = &self.coalesce_stats {
+                stats.record_ranges(&ranges);
+            }
+            object_store::coalesce_ranges(
+                &ranges,
+                |range| {
+                    if let Some(stats) = &self.coalesce_stats {
+                        stats.record_request(&range);
+                    }
+                    self.make_range_request(range)
+                },
+                self.coalesce_gap
+                    .unwrap_or(object_store::OBJECT_STORE_COALESCE_DEFAULT),
+            )
+            .await
+        }
+        .boxed()
+    }
+}
+
+/// An [`AsyncFileReader`] that merges nearby byte ranges into fewer requests to the reader it
+/// wraps.
+///
+/// Every call to [`get_byte_ranges`](AsyncFileReader::get_byte_ranges) merges the ranges that are
+/// at most [`gap`](Self::with_gap) bytes apart into requests of at most
+/// [`max_request_bytes`](Self::with_max_request_bytes), fetches them with a single call to the
+/// inner reader, and slices the fetched bytes back into the ranges asked for. This helps when
+/// fetching many adjacent tiles with [`fetch_tiles`](crate::ImageFileDirectory::fetch_tiles) from
+/// a reader that issues one request per range, such as [`ReqwestReader`]. [`ObjectReader`]
+/// coalesces ranges itself, see [`ObjectReader::with_coalesce_gap`].
+#[derive(Debug)]
+pub struct CoalescingReader<R> {
+    inner: R,
+    gap: u64,
+    max_request_bytes: u64,
+    stats: Option<Arc<CoalesceStats>>,
+}
+
+impl<R: AsyncFileReader> CoalescingReader<R> {
+    /// Wrap `inner`, merging ranges at most 1 MiB apart into requests of at most 32 MiB.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            gap: 1024 * 1024,
+            max_request_bytes: 32 * 1024 * 1024,
+            stats: None,
+        }
+    }
+
+    /// Merge ranges separated by at most `gap` bytes, fetching the bytes between them.
+    pub fn with_gap(mut self, gap: u64) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Don't merge ranges into requests longer than `max_request_bytes`.
+    ///
+    /// A single range that is longer is still requested on its own.
+    pub fn with_max_request_bytes(mut self, max_request_bytes: u64) -> Self {
+        self.max_request_bytes = max_request_bytes;
+        self
+    }
+
+    /// Record the ranges asked for and the requests issued in `stats`.
+    pub fn with_coalesce_stats(mut self, stats: Arc<CoalesceStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// The largest gap between merged ranges.
+    pub fn gap(&self) -> u64 {
+        self.gap
+    }
+
+    /// The largest request merged ranges are fetched with.
+    pub fn max_request_bytes(&self) -> u64 {
+        self.max_request_bytes
+    }
+
+    /// The counters attached with [`with_coalesce_stats`](Self::with_coalesce_stats), if any.
+    pub fn coalesce_stats(&self) -> Option<&Arc<CoalesceStats>> {
+        self.stats.as_ref()
+    }
+
+    /// The wrapped reader.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Merge the non-empty `ranges` into requests, returning the requests and the index of the
+    /// request holding each range.
+    fn merge(&self, ranges: &[Range<u64>]) -> (Vec<Range<u64>>, Vec<Option<usize>>) {
+        let mut order = (0..ranges.len())
+            .filter(|&index| !ranges[index].is_empty())
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&index| ranges[index].start);
+
+        let mut requests: Vec<Range<u64>> = vec![];
+        let mut request_of = vec![None; ranges.len()];
+        for index in order {
+            let range = &ranges[index];
+            match requests.last_mut() {
+                Some(last)
+                    if range.start <= last.end.saturating_add(self.gap)
+                        && range.end.max(last.end) - last.start <= self.max_request_bytes =>
+                {
+                    last.end = last.end.max(range.end);
+                }
+                _ => requests.push(range.clone()),
+            }
+            request_of[index] = Some(requests.len() - 1);
+        }
+        (requests, request_of)
+    }
+}
+
+impl<R: AsyncFileReader> AsyncFileReader for CoalescingReader<R> {
+    fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        self.inner.get_bytes(range)
+    }
+
+    fn get_byte_ranges(
+        &self,
+        ranges: Vec<Range<u64>>,
+    ) -> BoxFuture<'_, AsyncTiffResult<Vec<Bytes>>> {
+        async move {
+            let (requests, request_of) = self.merge(&ranges);
+            if let Some(stats) = &self.stats {
+                stats.record_ranges(&ranges);
+                requests
+                    .iter()
+                    .for_each(|request| stats.record_request(request));
+            }
+            let buffers = self.inner.get_byte_ranges(requests.clone()).await?;
+            ranges
+                .iter()
+                .zip(request_of)
+                .map(|(range, request)| {
+                    let Some(request) = request else {
+                        return Ok(Bytes::new());
+                    };
+                    let buffer = buffers.get(request).ok_or_else(|| {
+                        AsyncTiffError::General("Reader returned too few byte ranges".to_string())
+                    })?;
+                    let start = (range.start - requests[request].start) as usize;
+                    let end = (range.end - requests[request].start) as usize;
+                    if end > buffer.len() {
+                        return Err(AsyncTiffError::General(format!(
+                            "Reader returned {} bytes for range {:?}",
+                            buffer.len(),
+                            requests[request]
+                        )));
+                    }
+                    Ok(buffer.slice(start..end))
+                })
+                .collect()
         }
         .boxed()
     }
 }
 
+/// An [`AsyncFileReader`] that caches the bytes read from the reader it wraps, so that repeated
+/// reads of the same tiles, e.g. by a tile server, don't hit object storage again.
+///
+/// Bytes are read and cached in aligned blocks of [`block_size`](Self::with_block_size) bytes,
+/// and the least recently used blocks are evicted once more than
+/// [`capacity`](Self::with_capacity) bytes are cached. The blocks missing for a call to
+/// [`get_byte_ranges`](AsyncFileReader::get_byte_ranges) are fetched with a single call to the
+/// inner reader, with adjacent blocks merged into one range. Blocks already being fetched for
+/// another call aren't fetched again, and the call waits for them instead.
+///
+/// Since blocks are aligned, the last block of a file is requested past its end. The inner reader
+/// must then return the bytes up to the end of the file, as [`ObjectReader`] and [`ReqwestReader`]
+/// do.
+#[derive(Debug)]
+pub struct CachingReader<R> {
+    inner: R,
+    block_size: u64,
+    capacity: u64,
+    cache: Mutex<BlockCache>,
+    /// The blocks being fetched, resolving once they are cached.
+    in_flight: Mutex<HashMap<u64, Shared<oneshot::Receiver<Bytes>>>>,
+}
+
+impl<R: AsyncFileReader> CachingReader<R> {
+    /// Wrap `inner`, caching up to 64 MiB in blocks of 64 KiB.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            block_size: 64 * 1024,
+            capacity: 64 * 1024 * 1024,
+            cache: Mutex::new(BlockCache::default()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Read and cache blocks of `block_size` bytes.
+    ///
+    /// This clears the cache.
+    ///
+    /// # Panics
+    ///
+    /// If `block_size` is zero.
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        assert!(block_size > 0, "Block size must be positive");
+        self.block_size = block_size;
+        self.clear();
+        self
+    }
+
+    /// Cache at most `capacity` bytes.
+    pub fn with_capacity(mut self, capacity: u64) -> Self {
+        self.capacity = capacity;
+        self.cache.get_mut().unwrap().evict(capacity);
+        self
+    }
+
+    /// The size of the cached blocks.
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// The largest number of bytes cached.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// The number of bytes currently cached.
+    pub fn cached_bytes(&self) -> u64 {
+        self.cache.lock().unwrap().bytes
+    }
+
+    /// Remove all cached blocks.
+    pub fn clear(&self) {
+        *self.cache.lock().unwrap() = BlockCache::default();
+    }
+
+    /// The wrapped reader.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// The indices of the blocks holding `range`.
+    fn blocks(&self, range: &Range<u64>) -> Range<u64> {
+        range.start / self.block_size..range.end.div_ceil(self.block_size)
+    }
+
+    /// Copy `range` out of the `blocks` holding it.
+    fn assemble(&self, range: &Range<u64>, blocks: &HashMap<u64, Bytes>) -> AsyncTiffResult<Bytes> {
+        let mut parts = vec![];
+        let mut read = 0;
+        for block in self.blocks(range) {
+            let data = &blocks[&block];
+            let block_start = block * self.block_size;
+            let start = (range.start.max(block_start) - block_start) as usize;
+            let end = (range.end.min(block_start + self.block_size) - block_start) as usize;
+            if end > data.len() {
+                read += data.len().saturating_sub(start) as u64;
+                return Err(AsyncTiffError::EndOfFile(range.end - range.start, read));
+            }
+            read += (end - start) as u64;
+            parts.push(data.slice(start..end));
+        }
+        match parts.as_slice() {
+            [] => Ok(Bytes::new()),
+            [part] => Ok(part.clone()),
+            parts => {
+                let mut bytes = BytesMut::with_capacity(read as usize);
+                parts.iter().for_each(|part| bytes.extend_from_slice(part));
+                Ok(bytes.freeze())
+            }
+        }
+    }
+}
+
+impl<R: AsyncFileReader> AsyncFileReader for CachingReader<R> {
+    fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        async move {
+            let mut buffers = self.get_byte_ranges(vec![range]).await?;
+            Ok(buffers.pop().unwrap_or_default())
+        }
+        .boxed()
+    }
+
+    fn get_byte_ranges(
+        &self,
+        ranges: Vec<Range<u64>>,
+    ) -> BoxFuture<'_, AsyncTiffResult<Vec<Bytes>>> {
+        async move {
+            // Look up every block up front, so that blocks evicted while fetching the missing
+            // ones are still at hand
+            let mut blocks = HashMap::new();
+            let mut missing = BTreeSet::new();
+            let mut pending = HashMap::new();
+            let mut senders = HashMap::new();
+            {
+                let mut cache = self.cache.lock().unwrap();
+                let mut in_flight = self.in_flight.lock().unwrap();
+                for block in ranges.iter().flat_map(|range| self.blocks(range)) {
+                    if blocks.contains_key(&block)
+                        || missing.contains(&block)
+                        || pending.contains_key(&block)
+                    {
+                        continue;
+                    }
+                    if let Some(data) = cache.get(block) {
+                        blocks.insert(block, data);
+                    } else if let Some(fetching) = in_flight.get(&block) {
+                        pending.insert(block, fetching.clone());
+                    } else {
+                        let (sender, receiver) = oneshot::channel();
+                        in_flight.insert(block, receiver.shared());
+                        senders.insert(block, sender);
+                        missing.insert(block);
+                    }
+                }
+            }
+            // Whether or not they are fetched, the blocks stop being in flight with this call
+            let _in_flight = InFlightBlocks {
+                in_flight: &self.in_flight,
+                blocks: missing.iter().copied().collect(),
+            };
+
+            let mut requests: Vec<Range<u64>> = vec![];
+            for block in missing {
+                match requests.last_mut() {
+                    Some(last) if last.end == block => last.end += 1,
+                    _ => requests.push(block..block + 1),
+                }
+            }
+            if !requests.is_empty() {
+                let byte_ranges = requests
+                    .iter()
+                    .map(|request| request.start * self.block_size..request.end * self.block_size)
+                    .collect();
+                let buffers = self.inner.get_byte_ranges(byte_ranges).await?;
+                if buffers.len() != requests.len() {
+                    return Err(AsyncTiffError::General(format!(
+                        "Reader returned {} byte ranges, expected {}",
+                        buffers.len(),
+                        requests.len()
+                    )));
+                }
+
+                let mut cache = self.cache.lock().unwrap();
+                for (request, buffer) in requests.into_iter().zip(buffers) {
+                    for (i, block) in request.enumerate() {
+                        let start = (i as u64 * self.block_size).min(buffer.len() as u64);
+                        let end = (start + self.block_size).min(buffer.len() as u64);
+                        let data = buffer.slice(start as usize..end as usize);
+                        cache.insert(block, data.clone(), self.capacity);
+                        if let Some(sender) = senders.remove(&block) {
+                            // Waiting calls may have been dropped
+                            let _ = sender.send(data.clone());
+                        }
+                        blocks.insert(block, data);
+                    }
+                }
+            }
+
+            for (block, fetching) in pending {
+                let data = match fetching.await {
+                    Ok(data) => data,
+                    // The call fetching the block failed or was dropped, so fetch it here
+                    Err(_) => {
+                        let start = block * self.block_size;
+                        let data = self.inner.get_bytes(start..start + self.block_size).await?;
+                        let mut cache = self.cache.lock().unwrap();
+                        cache.insert(block, data.clone(), self.capacity);
+                        data
+                    }
+                };
+                blocks.insert(block, data);
+            }
+
+            ranges
+                .iter()
+                .map(|range| self.assemble(range, &blocks))
+                .collect()
+        }
+        .boxed()
+    }
+}
+
+/// The blocks a call to [`CachingReader::get_byte_ranges`] fetches, which are no longer in flight
+/// once it returns or is dropped.
+struct InFlightBlocks<'a> {
+    in_flight: &'a Mutex<HashMap<u64, Shared<oneshot::Receiver<Bytes>>>>,
+    blocks: Vec<u64>,
+}
+
+impl Drop for InFlightBlocks<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        for block in &self.blocks {
+            in_flight.remove(block);
+        }
+    }
+}
+
+/// Blocks of a [`CachingReader`], keyed by index, evicted least recently used first.
+#[derive(Debug, Default)]
+struct BlockCache {
+    /// The bytes of each block and when it was last used.
+    blocks: HashMap<u64, (Bytes, u64)>,
+    /// The block last used at each time.
+    used: BTreeMap<u64, u64>,
+    time: u64,
+    bytes: u64,
+}
+
+impl BlockCache {
+    fn get(&mut self, block: u64) -> Option<Bytes> {
+        let (data, used) = self.blocks.get_mut(&block)?;
+        self.used.remove(used);
+        self.time += 1;
+        *used = self.time;
+        self.used.insert(self.time, block);
+        Some(data.clone())
+    }
+
+    fn insert(&mut self, block: u64, data: Bytes, capacity: u64) {
+        self.time += 1;
+        self.bytes += data.len() as u64;
+        if let Some((old, used)) = self.blocks.insert(block, (data, self.time)) {
+            self.bytes -= old.len() as u64;
+            self.used.remove(&used);
+        }
+        self.used.insert(self.time, block);
+        self.evict(capacity);
+    }
+
+    /// Evict the least recently used blocks until at most `capacity` bytes are cached.
+    fn evict(&mut self, capacity: u64) {
+        while self.bytes > capacity {
+            let Some((_, block)) = self.used.pop_first() else {
+                break;
+            };
+            if let Some((data, _)) = self.blocks.remove(&block) {
+                self.bytes -= data.len() as u64;
+            }
+        }
+    }
+}
+
+/// Counters describing how effectively a reader coalesces byte ranges.
+///
+/// Attach this to an [`ObjectReader`] with
+/// [`with_coalesce_stats`](ObjectReader::with_coalesce_stats), or to a [`CoalescingReader`].
+/// Every call to [`get_byte_ranges`](AsyncFileReader::get_byte_ranges) then records the ranges
+/// that were asked for and the requests that were actually issued, so that the coalescing gap can be tuned for a
+/// storage backend: a high [`wasted_bytes`](Self::wasted_bytes) suggests a smaller gap, while few
+/// [`saved_requests`](Self::saved_requests) on a high-latency store suggests a larger one.
+#[derive(Debug, Default)]
+pub struct CoalesceStats {
+    ranges: AtomicU64,
+    range_bytes: AtomicU64,
+    requests: AtomicU64,
+    request_bytes: AtomicU64,
+}
+
+impl CoalesceStats {
+    /// Create a new, empty set of counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of byte ranges that were asked for.
+    pub fn ranges(&self) -> u64 {
+        self.ranges.load(Ordering::Relaxed)
+    }
+
+    /// The total length of the byte ranges that were asked for.
+    pub fn range_bytes(&self) -> u64 {
+        self.range_bytes.load(Ordering::Relaxed)
+    }
+
+    /// The number of requests that were issued after merging.
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// The total length of the requests that were issued after merging.
+    pub fn request_bytes(&self) -> u64 {
+        self.request_bytes.load(Ordering::Relaxed)
+    }
+
+    /// The number of requests avoided by merging ranges.
+    pub fn saved_requests(&self) -> u64 {
+        self.ranges().saturating_sub(self.requests())
+    }
+
+    /// The number of bytes fetched only to fill the gaps between merged ranges.
+    ///
+    /// Overlapping ranges are fetched once, so this can be less than the gap bytes alone.
+    pub fn wasted_bytes(&self) -> u64 {
+        self.request_bytes().saturating_sub(self.range_bytes())
+    }
+
+    /// The fraction of fetched bytes that were asked for, between 0 and 1 unless ranges overlap.
+    ///
+    /// Returns `None` if nothing has been fetched.
+    pub fn efficiency(&self) -> Option<f64> {
+        let request_bytes = self.request_bytes();
+        (request_bytes > 0).then(|| self.range_bytes() as f64 / request_bytes as f64)
+    }
+
+    /// Reset all counters to zero.
+    pub fn reset(&self) {
+        self.ranges.store(0, Ordering::Relaxed);
+        self.range_bytes.store(0, Ordering::Relaxed);
+        self.requests.store(0, Ordering::Relaxed);
+        self.request_bytes.store(0, Ordering::Relaxed);
+    }
+
+    fn record_ranges(&self, ranges: &[Range<u64>]) {
+        let bytes = ranges.iter().map(|range| range.end - range.start).sum();
+        self.ranges
+            .fetch_add(ranges.len() as u64, Ordering::Relaxed);
+        self.range_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn record_request(&self, range: &Range<u64>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.request_bytes
+            .fetch_add(range.end - range.start, Ordering::Relaxed);
+    }
+}
+
 /// An AsyncFileReader that reads from a URL using reqwest.
 #[cfg(feature = "reqwest")]
 #[derive(Debug, Clone)]
 pub struct ReqwestReader {
     client: reqwest::Client,
     url: reqwest::Url,
+    context: RequestContext,
 }
 
 #[cfg(feature = "reqwest")]
 impl ReqwestReader {
     /// Construct a new ReqwestReader from a reqwest client and URL.
     pub fn new(client: reqwest::Client, url: reqwest::Url) -> Self {
-        Self { client, url }
+        Self {
+            client,
+            url,
+            context: Default::default(),
+        }
     }
 
-    fn make_range_request(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
-        let url = self.url.clone();
-        let client = self.client.clone();
+    /// Forward `context` as headers on every request.
+    pub fn with_context(mut self, context: RequestContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// The context forwarded on every request.
+    pub fn context(&self) -> &RequestContext {
+        &self.context
+    }
+
+    fn range_request(&self, range: Range<u64>) -> reqwest::RequestBuilder {
         // HTTP range is inclusive, so we need to subtract 1 from the end
         let range = format!("bytes={}-{}", range.start, range.end - 1);
+        let request = self.client.get(self.url.clone()).header("Range", range);
+        self.context.apply(request)
+    }
+
+    fn make_range_request(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        // Empty ranges, such as those of sparse tiles, can't be expressed as an HTTP range
+        if range.is_empty() {
+            return async { Ok(Bytes::new()) }.boxed();
+        }
+        let request = self.range_request(range);
         async move {
-            let response = client
-                .get(url)
-                .header("Range", range)
-                .send()
-                .await?
-                .error_for_status()?;
+            let response = request.send().await?.error_for_status()?;
             let bytes = response.bytes().await?;
             Ok(bytes)
         }
@@ -227,8 +950,240 @@ impl AsyncFileReader for ReqwestReader {
     }
 }
 
+/// An AsyncFileReader that reads from a URL using reqwest, fetching all of the ranges passed to
+/// [`get_byte_ranges`](AsyncFileReader::get_byte_ranges) with a single multi-range request.
+///
+/// Servers that support multiple ranges answer with a `multipart/byteranges` body, which is split
+/// back into the requested ranges. Servers that don't are also handled: some merge the ranges and
+/// return a single `206` part, and others ignore the `Range` header and return the whole file.
+/// The latter is correct but expensive, so prefer [`ReqwestReader`] for servers that are known not
+/// to support multiple ranges.
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone)]
+pub struct ReqwestMultiRangeReader {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    max_ranges_per_request: usize,
+    context: RequestContext,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestMultiRangeReader {
+    /// Construct a new ReqwestMultiRangeReader from a reqwest client and URL.
+    pub fn new(client: reqwest::Client, url: reqwest::Url) -> Self {
+        Self {
+            client,
+            url,
+            max_ranges_per_request: 64,
+            context: Default::default(),
+        }
+    }
+
+    /// Forward `context` as headers on every request.
+    pub fn with_context(mut self, context: RequestContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// The context forwarded on every request.
+    pub fn context(&self) -> &RequestContext {
+        &self.context
+    }
+
+    /// Set the maximum number of ranges sent in a single request, since servers limit the size of
+    /// the `Range` header. Larger batches are split across several requests. Defaults to 64.
+    pub fn with_max_ranges_per_request(mut self, max_ranges_per_request: usize) -> Self {
+        self.max_ranges_per_request = max_ranges_per_request.max(1);
+        self
+    }
+
+    async fn make_multi_range_request(&self, ranges: &[Range<u64>]) -> AsyncTiffResult<Vec<Bytes>> {
+        use reqwest::header::{CONTENT_RANGE, CONTENT_TYPE, RANGE};
+        use reqwest::StatusCode;
+
+        // Empty ranges, such as those of sparse tiles, can't be expressed as an HTTP range and
+        // would make the server ignore the whole header, so they are answered without a request
+        if ranges.iter().all(|range| range.is_empty()) {
+            return Ok(vec![Bytes::new(); ranges.len()]);
+        }
+
+        // HTTP range is inclusive, so we need to subtract 1 from the end
+        let header = ranges
+            .iter()
+            .filter(|range| !range.is_empty())
+            .map(|range| format!("{}-{}", range.start, range.end - 1))
+            .collect::<Vec<_>>()
+            .join(",");
+        let request = self
+            .client
+            .get(self.url.clone())
+            .header(RANGE, format!("bytes={header}"));
+        let response = self
+            .context
+            .apply(request)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+
+        let parts = if status == StatusCode::PARTIAL_CONTENT {
+            let content_type = headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            if let Some(boundary) = multipart_boundary(content_type) {
+                parse_multipart_byteranges(&body, boundary)?
+            } else {
+                let content_range = headers
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or(AsyncTiffError::General(
+                        "Missing Content-Range header in partial response".to_string(),
+                    ))?;
+                vec![(parse_content_range(content_range)?, body)]
+            }
+        } else {
+            // The server ignored the Range header and sent the whole file
+            vec![(0..body.len() as u64, body)]
+        };
+
+        ranges
+            .iter()
+            .map(|range| {
+                if range.is_empty() {
+                    Ok(Bytes::new())
+                } else {
+                    extract_range(&parts, range)
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl AsyncFileReader for ReqwestMultiRangeReader {
+    fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        async move {
+            let mut result = self.make_multi_range_request(&[range]).await?;
+            Ok(result.remove(0))
+        }
+        .boxed()
+    }
+
+    fn get_byte_ranges(
+        &self,
+        ranges: Vec<Range<u64>>,
+    ) -> BoxFuture<'_, AsyncTiffResult<Vec<Bytes>>> {
+        async move {
+            let mut result = Vec::with_capacity(ranges.len());
+            for chunk in ranges.chunks(self.max_ranges_per_request) {
+                result.extend(self.make_multi_range_request(chunk).await?);
+            }
+            Ok(result)
+        }
+        .boxed()
+    }
+}
+
+/// Extract the boundary from a `multipart/byteranges` content type.
+#[cfg(feature = "reqwest")]
+fn multipart_boundary(content_type: &str) -> Option<&str> {
+    let mut params = content_type.split(';').map(str::trim);
+    if !params.next()?.eq_ignore_ascii_case("multipart/byteranges") {
+        return None;
+    }
+    params
+        .find_map(|param| param.strip_prefix("boundary="))
+        .map(|boundary| boundary.trim_matches('"'))
+}
+
+/// Parse a `Content-Range` value such as `bytes 200-1000/67589` into an exclusive range.
+#[cfg(feature = "reqwest")]
+fn parse_content_range(value: &str) -> AsyncTiffResult<Range<u64>> {
+    let invalid = || AsyncTiffError::General(format!("Invalid Content-Range: {value}"));
+    let (start, end) = value
+        .trim()
+        .strip_prefix("bytes ")
+        .and_then(|value| value.split('/').next())
+        .and_then(|value| value.split_once('-'))
+        .ok_or_else(invalid)?;
+    let start = start.trim().parse::<u64>().map_err(|_| invalid())?;
+    let end = end.trim().parse::<u64>().map_err(|_| invalid())?;
+    if end < start {
+        return Err(invalid());
+    }
+    Ok(start..end.checked_add(1).ok_or_else(invalid)?)
+}
+
+/// Split a `multipart/byteranges` body into its parts and their ranges.
+///
+/// The length of each part is taken from its `Content-Range` header rather than by searching for
+/// the next boundary, since the boundary could also appear in binary data.
+#[cfg(feature = "reqwest")]
+fn parse_multipart_byteranges(
+    body: &Bytes,
+    boundary: &str,
+) -> AsyncTiffResult<Vec<(Range<u64>, Bytes)>> {
+    let delimiter = format!("--{boundary}");
+    let malformed =
+        |reason: &str| AsyncTiffError::General(format!("Malformed multipart response: {reason}"));
+    let find = |haystack: &[u8], needle: &[u8]| {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    };
+
+    let mut parts = vec![];
+    let mut pos = 0;
+    loop {
+        let start =
+            find(&body[pos..], delimiter.as_bytes()).ok_or(malformed("missing boundary"))?;
+        pos += start + delimiter.len();
+        if body[pos..].starts_with(b"--") {
+            return Ok(parts);
+        }
+
+        let headers_len = find(&body[pos..], b"\r\n\r\n").ok_or(malformed("missing headers"))?;
+        let headers = std::str::from_utf8(&body[pos..pos + headers_len])
+            .map_err(|_| malformed("headers are not valid UTF-8"))?;
+        pos += headers_len + 4;
+
+        let content_range = headers
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-range"))
+            .map(|(_, value)| value)
+            .ok_or(malformed("part without Content-Range"))?;
+        let range = parse_content_range(content_range)?;
+        let end = usize::try_from(range.end - range.start)
+            .ok()
+            .and_then(|len| pos.checked_add(len))
+            .filter(|&end| end <= body.len())
+            .ok_or(malformed("part is truncated"))?;
+        parts.push((range, body.slice(pos..end)));
+        pos = end;
+    }
+}
+
+/// Find `range` within the ranges returned by the server, which may have merged adjacent ranges.
+#[cfg(feature = "reqwest")]
+fn extract_range(parts: &[(Range<u64>, Bytes)], range: &Range<u64>) -> AsyncTiffResult<Bytes> {
+    parts
+        .iter()
+        .find(|(part, _)| part.start <= range.start && range.end <= part.end)
+        .map(|(part, data)| {
+            data.slice((range.start - part.start) as usize..(range.end - part.start) as usize)
+        })
+        .ok_or(AsyncTiffError::General(format!(
+            "Response did not contain the requested range {range:?}"
+        )))
+}
+
 /// Endianness
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Endianness {
     /// Little Endian
     LittleEndian,
@@ -249,6 +1204,11 @@ impl EndianAwareReader {
         }
     }
 
+    /// The number of bytes left to read.
+    pub(crate) fn remaining(&self) -> usize {
+        self.reader.get_ref().remaining()
+    }
+
     /// Read a u8 from the cursor, advancing the internal state by 1 byte.
     pub(crate) fn read_u8(&mut self) -> AsyncTiffResult<u8> {
         Ok(self.reader.read_u8()?)
@@ -332,3 +1292,327 @@ impl Read for EndianAwareReader {
         self.reader.read(buf)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::CountingReader;
+
+    #[tokio::test]
+    async fn test_memory_reader() {
+        let reader = MemoryReader::new(&b"0123456789"[..]);
+        let buffers = reader
+            .get_byte_ranges(vec![2..5, 8..12, 12..14])
+            .await
+            .unwrap();
+        assert_eq!(buffers, [&b"234"[..], b"89", b""]);
+        assert_eq!(reader.into_inner().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_coalescing_reader() {
+        let inner = CountingReader::new(Bytes::from_static(b"0123456789abcdefghij"));
+        let stats = Arc::new(CoalesceStats::new());
+        let reader = CoalescingReader::new(inner)
+            .with_gap(3)
+            .with_max_request_bytes(8)
+            .with_coalesce_stats(stats.clone());
+        let buffers = reader
+            .get_byte_ranges(vec![8..10, 0..2, 3..4, 5..5, 1..3, 13..20])
+            .await
+            .unwrap();
+        assert_eq!(buffers, [&b"89"[..], b"01", b"3", b"", b"12", b"defghij"]);
+        // 0..2, 1..3 and 3..4 are merged, 8..10 is too far from them, and merging 13..20 with it
+        // would exceed 8 bytes
+        assert_eq!(reader.inner().requests(), 3);
+        assert_eq!(stats.requests(), 3);
+        assert_eq!(stats.ranges(), 6);
+        assert_eq!(stats.request_bytes(), 4 + 2 + 7);
+    }
+
+    #[tokio::test]
+    async fn test_caching_reader() {
+        let inner = CountingReader::new(Bytes::from_static(b"0123456789abcdefgh"));
+        let reader = CachingReader::new(inner)
+            .with_block_size(4)
+            .with_capacity(8);
+        let requests = || reader.inner().requests();
+
+        assert_eq!(reader.get_bytes(1..6).await.unwrap(), &b"12345"[..]);
+        assert_eq!(requests(), 1);
+        assert_eq!(reader.cached_bytes(), 8);
+        assert_eq!(
+            reader
+                .get_byte_ranges(vec![2..3, 4..8, 6..6])
+                .await
+                .unwrap(),
+            [&b"2"[..], b"4567", b""]
+        );
+        assert_eq!(requests(), 1);
+
+        // The last block is short, and evicts the least recently used block 0..4
+        assert_eq!(reader.get_bytes(16..18).await.unwrap(), &b"gh"[..]);
+        assert_eq!(requests(), 2);
+        assert_eq!(reader.cached_bytes(), 6);
+        assert_eq!(reader.get_bytes(5..6).await.unwrap(), &b"5"[..]);
+        assert_eq!(requests(), 2);
+        assert_eq!(reader.get_bytes(0..1).await.unwrap(), &b"0"[..]);
+        assert_eq!(requests(), 3);
+
+        assert!(matches!(
+            reader.get_bytes(16..20).await,
+            Err(AsyncTiffError::EndOfFile(4, 2))
+        ));
+        reader.clear();
+        assert_eq!(reader.cached_bytes(), 0);
+    }
+
+    /// Yields to the executor before reading, so that concurrent reads overlap.
+    #[derive(Debug, Default)]
+    struct YieldingReader(CountingReader);
+
+    impl AsyncFileReader for YieldingReader {
+        fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+            async move {
+                tokio::task::yield_now().await;
+                self.0.get_bytes(range).await
+            }
+            .boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caching_reader_in_flight() {
+        let inner = YieldingReader(CountingReader::new(Bytes::from_static(
+            b"0123456789abcdefgh",
+        )));
+        let reader = CachingReader::new(inner).with_block_size(4);
+        let requests = || reader.inner().0.requests();
+
+        // The second and third reads wait for the blocks the first one is fetching
+        let (a, b, c) = futures::join!(
+            reader.get_bytes(0..6),
+            reader.get_bytes(1..3),
+            reader.get_byte_ranges(vec![5..7, 9..10]),
+        );
+        assert_eq!(a.unwrap(), &b"012345"[..]);
+        assert_eq!(b.unwrap(), &b"12"[..]);
+        assert_eq!(c.unwrap(), [&b"56"[..], b"9"]);
+        assert_eq!(requests(), 2);
+        assert!(reader.in_flight.lock().unwrap().is_empty());
+
+        // A dropped read doesn't leave its blocks in flight
+        reader.clear();
+        let mut read = reader.get_bytes(0..2);
+        assert!(futures::poll!(&mut read).is_pending());
+        assert_eq!(reader.in_flight.lock().unwrap().len(), 1);
+        let mut waiting = reader.get_bytes(0..1);
+        assert!(futures::poll!(&mut waiting).is_pending());
+        drop(read);
+        assert!(reader.in_flight.lock().unwrap().is_empty());
+        // Instead, the read waiting for them fetches them
+        assert_eq!(waiting.await.unwrap(), &b"0"[..]);
+        assert_eq!(requests(), 3);
+        assert_eq!(reader.cached_bytes(), 4);
+    }
+
+    #[cfg(feature = "object_store")]
+    #[tokio::test]
+    async fn test_object_reader_get_options() {
+        use object_store::memory::InMemory;
+        use object_store::{GetOptions, ObjectStore};
+
+        let store = Arc::new(InMemory::new());
+        let path = object_store::path::Path::from("image.tif");
+        let result = store
+            .put(&path, Bytes::from_static(b"0123456789").into())
+            .await
+            .unwrap();
+
+        let matching =
+            ObjectReader::new(store.clone(), path.clone()).with_get_options(GetOptions {
+                if_match: result.e_tag,
+                ..Default::default()
+            });
+        assert_eq!(matching.get_bytes(2..4).await.unwrap(), &b"23"[..]);
+        assert_eq!(
+            matching.get_byte_ranges(vec![0..1, 8..10]).await.unwrap(),
+            [&b"0"[..], &b"89"[..]]
+        );
+
+        let stale = ObjectReader::new(store, path).with_get_options(GetOptions {
+            if_match: Some("stale".to_string()),
+            ..Default::default()
+        });
+        assert!(stale.get_bytes(2..4).await.is_err());
+        assert!(stale.get_byte_ranges(vec![0..1, 8..10]).await.is_err());
+    }
+
+    #[cfg(feature = "object_store")]
+    #[tokio::test]
+    async fn test_object_reader_coalesce_stats() {
+        use object_store::memory::InMemory;
+        use object_store::ObjectStore;
+
+        let store = Arc::new(InMemory::new());
+        let path = object_store::path::Path::from("image.tif");
+        store
+            .put(&path, Bytes::from_static(b"0123456789").into())
+            .await
+            .unwrap();
+
+        let stats = Arc::new(CoalesceStats::new());
+        let reader = ObjectReader::new(store, path)
+            .with_coalesce_gap(2)
+            .with_coalesce_stats(stats.clone());
+        assert_eq!(
+            reader
+                .get_byte_ranges(vec![0..1, 2..4, 8..10])
+                .await
+                .unwrap(),
+            [&b"0"[..], &b"23"[..], &b"89"[..]]
+        );
+        assert_eq!(stats.ranges(), 3);
+        assert_eq!(stats.range_bytes(), 5);
+        assert_eq!(stats.requests(), 2);
+        assert_eq!(stats.request_bytes(), 6);
+        assert_eq!(stats.saved_requests(), 1);
+        assert_eq!(stats.wasted_bytes(), 1);
+        assert_eq!(stats.efficiency(), Some(5.0 / 6.0));
+
+        stats.reset();
+        assert_eq!(stats.requests(), 0);
+        assert_eq!(stats.efficiency(), None);
+    }
+
+    #[cfg(feature = "object_store")]
+    #[test]
+    fn test_object_reader_context() {
+        use object_store::memory::InMemory;
+
+        let context = RequestContext::new().with_header("x-request-id", "abc");
+        let reader = ObjectReader::new(Arc::new(InMemory::new()), "image.tif".into())
+            .with_context(context.clone());
+        let extensions = &reader.get_options().unwrap().extensions;
+        assert_eq!(extensions.get::<RequestContext>(), Some(&context));
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_reqwest_reader_context() {
+        let context = RequestContext::new()
+            .with_header(
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            )
+            .with_header("x-tenant-id", "tenant-a");
+        let url = "https://example.com/image.tif".parse().unwrap();
+        let reader = ReqwestReader::new(reqwest::Client::new(), url).with_context(context);
+        let request = reader.range_request(0..10).build().unwrap();
+        assert_eq!(request.headers()["range"], "bytes=0-9");
+        assert_eq!(request.headers()["x-tenant-id"], "tenant-a");
+        assert!(request.headers().contains_key("traceparent"));
+    }
+
+    /// Serve `file` over HTTP, ignoring any `Range` header, and record the `Range` headers of the
+    /// requests.
+    #[cfg(feature = "reqwest")]
+    fn serve_whole_file(file: &'static [u8]) -> (reqwest::Url, Arc<Mutex<Vec<String>>>) {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image.tif", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(vec![]));
+        let requested = ranges.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut lines = BufReader::new(&stream).lines();
+                while let Some(Ok(line)) = lines.next() {
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: ") {
+                        requested.lock().unwrap().push(range.to_string());
+                    }
+                }
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    file.len()
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(file).unwrap();
+            }
+        });
+        (url.parse().unwrap(), ranges)
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn test_reqwest_empty_ranges() {
+        let (url, requested) = serve_whole_file(b"0123456789");
+        let reader = ReqwestMultiRangeReader::new(reqwest::Client::new(), url.clone());
+        let buffers = reader
+            .get_byte_ranges(vec![0..0, 2..4, 5..5, 7..10])
+            .await
+            .unwrap();
+        assert_eq!(buffers, [&b""[..], b"23", b"", b"789"]);
+        assert_eq!(*requested.lock().unwrap(), ["bytes=2-3,7-9"]);
+
+        // Sparse tiles alone don't make a request
+        let buffers = reader.get_byte_ranges(vec![0..0, 5..5]).await.unwrap();
+        assert_eq!(buffers, [Bytes::new(), Bytes::new()]);
+        assert_eq!(reader.get_bytes(0..0).await.unwrap(), Bytes::new());
+        let reader = ReqwestReader::new(reqwest::Client::new(), url);
+        assert_eq!(reader.get_bytes(0..0).await.unwrap(), Bytes::new());
+        assert_eq!(requested.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_parse_multipart_byteranges() {
+        let content_type = "multipart/byteranges; boundary=THIS_STRING_SEPARATES";
+        let boundary = multipart_boundary(content_type).unwrap();
+        assert_eq!(boundary, "THIS_STRING_SEPARATES");
+        assert_eq!(multipart_boundary("image/tiff"), None);
+
+        // The second part's data contains the boundary itself
+        let body = Bytes::from_static(
+            b"\r\n--THIS_STRING_SEPARATES\r\n\
+Content-Type: image/tiff\r\n\
+Content-Range: bytes 10-14/1000\r\n\r\n\
+abcde\r\n--THIS_STRING_SEPARATES\r\n\
+Content-Type: image/tiff\r\n\
+Content-Range: bytes 500-524/1000\r\n\r\n\
+--THIS_STRING_SEPARATES--\r\n--THIS_STRING_SEPARATES--\r\n",
+        );
+        let parts = parse_multipart_byteranges(&body, boundary).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0], (10..15, Bytes::from_static(b"abcde")));
+        assert_eq!(parts[1].0, 500..525);
+        assert_eq!(parts[1].1, &b"--THIS_STRING_SEPARATES--"[..]);
+
+        assert_eq!(
+            extract_range(&parts, &(11..13)).unwrap(),
+            Bytes::from_static(b"bc")
+        );
+        assert!(extract_range(&parts, &(14..16)).is_err());
+
+        // Reversed, overflowing and oversized ranges are errors rather than panics
+        for content_range in [
+            "bytes 10-5/100",
+            "bytes 0-18446744073709551615/*",
+            "bytes 0-18446744073709551614/*",
+            "bytes 0-1000/*",
+        ] {
+            let body = Bytes::from(format!(
+                "--THIS_STRING_SEPARATES\r\nContent-Range: {content_range}\r\n\r\nabc\r\n\
+                 --THIS_STRING_SEPARATES--\r\n"
+            ));
+            assert!(parse_multipart_byteranges(&body, boundary).is_err());
+        }
+        assert!(parse_content_range("bytes 10-5/100").is_err());
+        assert!(parse_content_range("bytes 0-18446744073709551615/*").is_err());
+        assert_eq!(parse_content_range("bytes 5-5/100").unwrap(), 5..6);
+    }
+}
@@ -0,0 +1,372 @@
+//! Reading the same window from many images on an identical grid, such as a time series.
+
+use std::sync::Arc;
+
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+use crate::decoder::{DecoderRegistry, DecodingResult};
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::geo::AffineTransform;
+use crate::ifd::ImageFileDirectory;
+use crate::reader::AsyncFileReader;
+use crate::tiff::tags::SampleFormat;
+use crate::window::{ReadWindowOptions, Window};
+use crate::ReadEstimate;
+
+/// Single-band images on an identical grid, such as daily observations of the same area, that are
+/// read together as a (time, y, x) cube.
+///
+/// The window is read from every layer concurrently, sharing a single [`DecoderRegistry`] and an
+/// optional memory budget across all of them.
+#[derive(Debug, Clone)]
+pub struct Stack {
+    layers: Vec<(ImageFileDirectory, Arc<dyn AsyncFileReader>)>,
+    concurrency: usize,
+    memory_budget: Option<u64>,
+}
+
+impl Stack {
+    /// Stack the images of `layers`, each read with its own reader, in time order.
+    ///
+    /// Fails if there are no layers, or unless every layer has a single band and the same
+    /// dimensions, data type and geotransform as the first.
+    pub fn try_new(
+        layers: impl IntoIterator<Item = (ImageFileDirectory, Arc<dyn AsyncFileReader>)>,
+    ) -> AsyncTiffResult<Self> {
+        let layers = layers.into_iter().collect::<Vec<_>>();
+        let (first, _) = layers
+            .first()
+            .ok_or_else(|| AsyncTiffError::General("Stack has no layers".to_string()))?;
+        for (index, (ifd, _)) in layers.iter().enumerate() {
+            if ifd.samples_per_pixel() != 1 {
+                return Err(AsyncTiffError::General(format!(
+                    "Stack layer {index} has {} bands, expected 1",
+                    ifd.samples_per_pixel()
+                )));
+            }
+            let mismatch = if (ifd.image_width(), ifd.image_height())
+                != (first.image_width(), first.image_height())
+            {
+                Some("dimensions")
+            } else if (ifd.sample_format(), ifd.bits_per_sample())
+                != (first.sample_format(), first.bits_per_sample())
+            {
+                Some("data type")
+            } else if AffineTransform::from_ifd(ifd) != AffineTransform::from_ifd(first) {
+                Some("geotransform")
+            } else {
+                None
+            };
+            if let Some(mismatch) = mismatch {
+                return Err(AsyncTiffError::General(format!(
+                    "Stack layer {index} has different {mismatch} than the first layer"
+                )));
+            }
+        }
+        Ok(Self {
+            layers,
+            concurrency: 8,
+            memory_budget: None,
+        })
+    }
+
+    /// Set the maximum number of layers read at once. Defaults to 8.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the maximum number of compressed and decoded tile bytes held at once across all layers
+    /// being read.
+    ///
+    /// This lowers the concurrency to fit the [estimated](Self::estimate_read) cost of reading a
+    /// layer, but at least one layer is always read at a time.
+    pub fn with_memory_budget(mut self, memory_budget: u64) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// The maximum number of layers read at once.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// The maximum number of tile bytes held at once, if any.
+    pub fn memory_budget(&self) -> Option<u64> {
+        self.memory_budget
+    }
+
+    /// The number of layers, i.e. the length of the time axis.
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The IFDs of the layers, in time order.
+    pub fn ifds(&self) -> impl Iterator<Item = &ImageFileDirectory> {
+        self.layers.iter().map(|(ifd, _)| ifd)
+    }
+
+    /// Estimate the cost of reading `window` from every layer, summed across layers.
+    pub fn estimate_read(
+        &self,
+        window: Window,
+        options: &ReadWindowOptions,
+    ) -> AsyncTiffResult<ReadEstimate> {
+        self.ifds()
+            .map(|ifd| ifd.estimate_read(window, options))
+            .sum()
+    }
+
+    /// Read the pixels inside `window` from every layer.
+    pub async fn read_window(
+        &self,
+        window: Window,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<StackData> {
+        self.read_window_with_options(window, &Default::default(), decoder_registry)
+            .await
+    }
+
+    /// Read the pixels inside `window` from every layer, as
+    /// [`ImageFileDirectory::read_window_with_options`] does for each of them.
+    pub async fn read_window_with_options(
+        &self,
+        window: Window,
+        options: &ReadWindowOptions,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<StackData> {
+        let concurrency = self.effective_concurrency(window, options)?;
+        let layers = stream::iter(&self.layers)
+            .map(|(ifd, reader)| {
+                ifd.read_window_with_options(window, options, reader.as_ref(), decoder_registry)
+            })
+            .buffered(concurrency)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let first = &layers[0];
+        let mut data = Vec::with_capacity(first.data().len() * layers.len());
+        for layer in &layers {
+            data.extend_from_slice(layer.data());
+        }
+        Ok(StackData {
+            window,
+            layer_count: layers.len(),
+            transform: first.transform().copied(),
+            bytes_per_sample: first.bytes_per_sample(),
+            sample_format: first.sample_format(),
+            data,
+        })
+    }
+
+    /// The number of layers read at once, within the memory budget.
+    fn effective_concurrency(
+        &self,
+        window: Window,
+        options: &ReadWindowOptions,
+    ) -> AsyncTiffResult<usize> {
+        let concurrency = match self.memory_budget {
+            Some(budget) => {
+                let mut layer_bytes = 0;
+                for ifd in self.ifds() {
+                    let estimate = ifd.estimate_read(window, options)?;
+                    layer_bytes =
+                        layer_bytes.max(estimate.compressed_bytes() + estimate.decoded_bytes());
+                }
+                let fits = budget / layer_bytes.max(1);
+                self.concurrency.min(fits.try_into().unwrap_or(usize::MAX))
+            }
+            None => self.concurrency,
+        };
+        Ok(concurrency.max(1))
+    }
+}
+
+/// Pixel data read from a [`Window`] of every layer of a [`Stack`].
+///
+/// Samples are stored as a (time, y, x) cube: the rows of the window of each layer one after the
+/// other, in native byte order.
+#[derive(Debug, Clone)]
+pub struct StackData {
+    window: Window,
+    layer_count: usize,
+    transform: Option<AffineTransform>,
+    bytes_per_sample: usize,
+    sample_format: SampleFormat,
+    data: Vec<u8>,
+}
+
+impl StackData {
+    /// The window that was read.
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// The number of layers, i.e. the length of the time axis.
+    pub fn layer_count(&self) -> usize {
+        self.layer_count
+    }
+
+    /// The affine transform of the window, shared by every layer, if it was requested with
+    /// [`ReadWindowOptions::with_transform`].
+    pub fn transform(&self) -> Option<&AffineTransform> {
+        self.transform.as_ref()
+    }
+
+    /// The number of bytes of each sample.
+    pub fn bytes_per_sample(&self) -> usize {
+        self.bytes_per_sample
+    }
+
+    /// The format of the samples.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// The samples of every layer.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consume this data, returning the samples of every layer.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The samples of layer `index`, if it exists.
+    pub fn layer(&self, index: usize) -> Option<&[u8]> {
+        let layer_len = self.data.len() / self.layer_count;
+        (index < self.layer_count).then(|| &self.data[index * layer_len..(index + 1) * layer_len])
+    }
+
+    /// Interpret the samples as their format and bit depth, as one flat (time, y, x) array.
+    pub fn to_typed(&self) -> AsyncTiffResult<DecodingResult> {
+        DecodingResult::from_bytes(
+            &self.data,
+            self.sample_format,
+            (self.bytes_per_sample * 8) as u16,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::reader::MemoryReader;
+    use crate::test_util::{read_ifds, write_cog};
+    use crate::tiff::tags::CompressionMethod;
+    use crate::{CogImage, IfdBuilder};
+
+    /// A 40x30 uint16 layer of 16x16 tiles in which every sample is `value`.
+    async fn layer(
+        value: u16,
+        builder: IfdBuilder,
+    ) -> (ImageFileDirectory, Arc<dyn AsyncFileReader>) {
+        let ifd = builder
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::None)
+            .build()
+            .unwrap();
+        let tile = value
+            .to_ne_bytes()
+            .repeat(16 * 16 * ifd.samples_per_pixel() as usize);
+        let tiles = vec![Bytes::from(tile); 6];
+        let file = write_cog(vec![CogImage::from_encoded(ifd, tiles)]).await;
+        let reader: Arc<dyn AsyncFileReader> = Arc::new(MemoryReader::new(file));
+        let ifd = read_ifds(&reader).await.remove(0);
+        (ifd, reader)
+    }
+
+    fn builder() -> IfdBuilder {
+        IfdBuilder::new(40, 30).with_data_type(SampleFormat::Uint, 16)
+    }
+
+    #[tokio::test]
+    async fn test_read_stack() {
+        let mut layers = vec![];
+        for value in [10, 20, 30] {
+            layers.push(layer(value, builder()).await);
+        }
+        let stack = Stack::try_new(layers).unwrap();
+        assert_eq!(stack.layer_count(), 3);
+
+        let window = Window::new(10, 5, 20, 15);
+        let data = stack
+            .read_window(window, &DecoderRegistry::default())
+            .await
+            .unwrap();
+        assert_eq!(data.layer_count(), 3);
+        assert_eq!(data.window(), window);
+        assert_eq!(data.data().len(), 3 * 20 * 15 * 2);
+        let DecodingResult::U16(samples) = data.to_typed().unwrap() else {
+            panic!("Expected u16 samples");
+        };
+        for (t, layer) in samples.chunks_exact(20 * 15).enumerate() {
+            assert!(layer.iter().all(|v| *v as usize == (t + 1) * 10));
+        }
+        assert_eq!(data.layer(2), Some(&data.data()[2 * 600..]));
+        assert_eq!(data.layer(3), None);
+
+        let estimate = stack.estimate_read(window, &Default::default()).unwrap();
+        assert_eq!(estimate.chunk_count(), 3 * 4);
+    }
+
+    #[tokio::test]
+    async fn test_read_empty_window() {
+        let layers = vec![layer(1, builder()).await, layer(2, builder()).await];
+        let stack = Stack::try_new(layers).unwrap();
+        let data = stack
+            .read_window(Window::new(0, 0, 0, 0), &DecoderRegistry::default())
+            .await
+            .unwrap();
+        assert!(data.data().is_empty());
+        assert_eq!(data.layer(1), Some(&[][..]));
+        assert_eq!(data.layer(2), None);
+    }
+
+    #[tokio::test]
+    async fn test_stack_memory_budget() {
+        let layers = vec![layer(1, builder()).await, layer(2, builder()).await];
+        let stack = Stack::try_new(layers).unwrap();
+        let window = Window::new(0, 0, 16, 16);
+        let options = ReadWindowOptions::default();
+        // A single tile, compressed and decoded
+        let tile_bytes = 2 * 16 * 16 * 2;
+        assert_eq!(stack.effective_concurrency(window, &options).unwrap(), 8);
+        let stack = stack.with_memory_budget(tile_bytes * 2);
+        assert_eq!(stack.effective_concurrency(window, &options).unwrap(), 2);
+        let stack = stack.with_memory_budget(1);
+        assert_eq!(stack.effective_concurrency(window, &options).unwrap(), 1);
+        let data = stack
+            .read_window(window, &DecoderRegistry::default())
+            .await
+            .unwrap();
+        assert_eq!(data.layer_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stack_mismatch() {
+        assert!(Stack::try_new([]).is_err());
+
+        let other = [
+            (IfdBuilder::new(40, 31), "dimensions"),
+            (IfdBuilder::new(40, 30), "data type"),
+            (
+                builder()
+                    .with_model_pixel_scale([10.0, 10.0, 0.0])
+                    .with_model_tiepoint([0.0, 0.0, 0.0, 500.0, 800.0, 0.0]),
+                "geotransform",
+            ),
+        ];
+        for (builder, mismatch) in other {
+            let layers = vec![layer(1, self::builder()).await, layer(2, builder).await];
+            let err = Stack::try_new(layers).unwrap_err();
+            assert!(err.to_string().contains(mismatch), "{err}");
+        }
+
+        let layers = vec![layer(1, builder().with_samples_per_pixel(2)).await];
+        assert!(Stack::try_new(layers).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Band statistics and histograms, embedded in written files as GDAL metadata.
+
+use std::fmt::Write;
+
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::tiff::tags::SampleFormat;
+use crate::window::sample_to_f64;
+
+/// The number of buckets of computed histograms.
+const HISTOGRAM_BUCKETS: usize = 256;
+
+/// The statistics of the valid samples of a band, i.e. those that are neither NaN nor equal to
+/// the nodata value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandStatistics {
+    min: f64,
+    max: f64,
+    mean: f64,
+    std_dev: f64,
+    valid_percent: f64,
+    histogram: Option<Histogram>,
+}
+
+impl BandStatistics {
+    /// Create statistics, e.g. computed by another tool.
+    pub fn new(min: f64, max: f64, mean: f64, std_dev: f64, valid_percent: f64) -> Self {
+        Self {
+            min,
+            max,
+            mean,
+            std_dev,
+            valid_percent,
+            histogram: None,
+        }
+    }
+
+    /// Attach a histogram of the band.
+    pub fn with_histogram(mut self, histogram: Histogram) -> Self {
+        self.histogram = Some(histogram);
+        self
+    }
+
+    /// The minimum valid sample.
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// The maximum valid sample.
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// The mean of the valid samples.
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// The population standard deviation of the valid samples.
+    pub fn std_dev(&self) -> f64 {
+        self.std_dev
+    }
+
+    /// The percentage of samples that are valid.
+    pub fn valid_percent(&self) -> f64 {
+        self.valid_percent
+    }
+
+    /// The histogram of the band, if known.
+    pub fn histogram(&self) -> Option<&Histogram> {
+        self.histogram.as_ref()
+    }
+
+    /// The statistics and histograms of each band as a GDAL PAM `.aux.xml` sidecar.
+    ///
+    /// GDAL reads histograms from the sidecar rather than from the GDAL_METADATA tag, so this
+    /// complements the statistics embedded by
+    /// [`CogWriter::with_statistics`](crate::CogWriter::with_statistics).
+    pub fn to_aux_xml(bands: &[Self]) -> String {
+        let mut xml = "<PAMDataset>\n".to_string();
+        // Writing to a String can't fail
+        for (i, band) in bands.iter().enumerate() {
+            let _ = writeln!(xml, r#"  <PAMRasterBand band="{}">"#, i + 1);
+            if let Some(histogram) = &band.histogram {
+                let counts = histogram.counts.iter().map(u64::to_string);
+                xml.push_str("    <Histograms>\n      <HistItem>\n");
+                let _ = writeln!(xml, "        <HistMin>{}</HistMin>", histogram.min);
+                let _ = writeln!(xml, "        <HistMax>{}</HistMax>", histogram.max);
+                let _ = writeln!(
+                    xml,
+                    "        <BucketCount>{}</BucketCount>",
+                    histogram.counts.len()
+                );
+                xml.push_str("        <IncludeOutOfRange>0</IncludeOutOfRange>\n");
+                xml.push_str("        <Approximate>0</Approximate>\n");
+                let _ = writeln!(
+                    xml,
+                    "        <HistCounts>{}</HistCounts>",
+                    counts.collect::<Vec<_>>().join("|")
+                );
+                xml.push_str("      </HistItem>\n    </Histograms>\n");
+            }
+            xml.push_str("    <Metadata>\n");
+            for (name, value) in band.items() {
+                let _ = writeln!(xml, r#"      <MDI key="{name}">{value}</MDI>"#);
+            }
+            xml.push_str("    </Metadata>\n  </PAMRasterBand>\n");
+        }
+        xml.push_str("</PAMDataset>\n");
+        xml
+    }
+
+    /// The GDAL metadata items of these statistics.
+    fn items(&self) -> [(&'static str, f64); 5] {
+        [
+            ("STATISTICS_MAXIMUM", self.max),
+            ("STATISTICS_MEAN", self.mean),
+            ("STATISTICS_MINIMUM", self.min),
+            ("STATISTICS_STDDEV", self.std_dev),
+            ("STATISTICS_VALID_PERCENT", self.valid_percent),
+        ]
+    }
+}
+
+/// A histogram of equally sized buckets between a minimum and a maximum value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    min: f64,
+    max: f64,
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// Create a histogram from the number of samples in each bucket between `min` and `max`.
+    pub fn new(min: f64, max: f64, counts: Vec<u64>) -> Self {
+        Self { min, max, counts }
+    }
+
+    /// The lower bound of the first bucket.
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// The upper bound of the last bucket.
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// The number of samples in each bucket.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// An empty histogram of samples between `min` and `max`. Integer samples get a bucket per
+    /// value if they fit, centered on the value as GDAL does.
+    fn empty(min: f64, max: f64, sample_format: SampleFormat) -> Self {
+        let (min, max, buckets) =
+            if sample_format != SampleFormat::IEEEFP && max - min < HISTOGRAM_BUCKETS as f64 {
+                (min - 0.5, max + 0.5, (max - min) as usize + 1)
+            } else if min == max {
+                (min - 0.5, max + 0.5, 1)
+            } else {
+                (min, max, HISTOGRAM_BUCKETS)
+            };
+        Self {
+            min,
+            max,
+            counts: vec![0; buckets],
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        let buckets = self.counts.len();
+        let bucket = ((value - self.min) / (self.max - self.min) * buckets as f64) as usize;
+        self.counts[bucket.min(buckets - 1)] += 1;
+    }
+}
+
+/// Accumulates the statistics of the bands of an image from its samples.
+///
+/// Samples are passed twice: first to [`add`](Self::add), and then, once the range of each band
+/// is known, to [`add_to_histograms`](Self::add_to_histograms).
+pub(crate) struct StatisticsBuilder {
+    sample_format: SampleFormat,
+    nodata: Option<f64>,
+    total: u64,
+    bands: Vec<BandAccumulator>,
+}
+
+#[derive(Clone)]
+struct BandAccumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    sum_squares: f64,
+    count: u64,
+    histogram: Option<Histogram>,
+}
+
+impl StatisticsBuilder {
+    pub(crate) fn new(bands: usize, sample_format: SampleFormat, nodata: Option<f64>) -> Self {
+        let band = BandAccumulator {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            sum_squares: 0.0,
+            count: 0,
+            histogram: None,
+        };
+        Self {
+            sample_format,
+            nodata,
+            total: 0,
+            bands: vec![band; bands],
+        }
+    }
+
+    /// The valid samples of `data`, with the index of their band.
+    ///
+    /// `data` holds native-endian samples interleaved by pixel, or only the samples of `band`.
+    fn valid_samples<'a>(
+        &self,
+        data: &'a [u8],
+        band: Option<usize>,
+        bytes_per_sample: usize,
+    ) -> impl Iterator<Item = (usize, f64)> + 'a {
+        let (first, bands) = match band {
+            Some(band) => (band, 1),
+            None => (0, self.bands.len()),
+        };
+        let (sample_format, nodata) = (self.sample_format, self.nodata);
+        data.chunks_exact(bytes_per_sample)
+            .enumerate()
+            .filter_map(move |(i, sample)| {
+                let value = sample_to_f64(sample, sample_format)?;
+                (!value.is_nan() && nodata != Some(value)).then_some((first + i % bands, value))
+            })
+    }
+
+    /// Add samples to the minimum, maximum, mean and standard deviation.
+    pub(crate) fn add(&mut self, data: &[u8], band: Option<usize>, bytes_per_sample: usize) {
+        let samples = self
+            .valid_samples(data, band, bytes_per_sample)
+            .collect::<Vec<_>>();
+        for (i, value) in samples {
+            let band = &mut self.bands[i];
+            band.min = band.min.min(value);
+            band.max = band.max.max(value);
+            band.sum += value;
+            band.sum_squares += value * value;
+            band.count += 1;
+        }
+        if band.is_none_or(|band| band == 0) {
+            let bands = if band.is_some() { 1 } else { self.bands.len() };
+            self.total += (data.len() / bytes_per_sample / bands) as u64;
+        }
+    }
+
+    /// Add samples to the histograms, after every sample was passed to [`add`](Self::add).
+    pub(crate) fn add_to_histograms(
+        &mut self,
+        data: &[u8],
+        band: Option<usize>,
+        bytes_per_sample: usize,
+    ) {
+        let samples = self
+            .valid_samples(data, band, bytes_per_sample)
+            .collect::<Vec<_>>();
+        for (i, value) in samples {
+            let sample_format = self.sample_format;
+            let band = &mut self.bands[i];
+            band.histogram
+                .get_or_insert_with(|| Histogram::empty(band.min, band.max, sample_format))
+                .add(value);
+        }
+    }
+
+    /// The statistics of each band, or an error if a band has no valid samples.
+    pub(crate) fn finish(self) -> AsyncTiffResult<Vec<BandStatistics>> {
+        self.bands
+            .into_iter()
+            .enumerate()
+            .map(|(i, band)| {
+                if band.count == 0 {
+                    return Err(AsyncTiffError::General(format!(
+                        "Band {i} has no valid samples"
+                    )));
+                }
+                let count = band.count as f64;
+                let mean = band.sum / count;
+                let variance = (band.sum_squares / count - mean * mean).max(0.0);
+                Ok(BandStatistics {
+                    min: band.min,
+                    max: band.max,
+                    mean,
+                    std_dev: variance.sqrt(),
+                    valid_percent: count / self.total as f64 * 100.0,
+                    histogram: band.histogram,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The GDAL_METADATA XML holding the statistics of each band, merged into `existing` XML if set.
+pub(crate) fn gdal_metadata(bands: &[BandStatistics], existing: Option<&str>) -> String {
+    let mut items = String::new();
+    for (sample, band) in bands.iter().enumerate() {
+        for (name, value) in band.items() {
+            // Writing to a String can't fail
+            let _ = writeln!(
+                items,
+                r#"  <Item name="{name}" sample="{sample}">{value}</Item>"#
+            );
+        }
+    }
+    match existing.and_then(|xml| xml.rfind("</GDALMetadata>").map(|end| (xml, end))) {
+        Some((xml, end)) => format!("{}{items}{}", &xml[..end], &xml[end..]),
+        None => format!("<GDALMetadata>\n{items}</GDALMetadata>\n"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_statistics() {
+        let mut builder = StatisticsBuilder::new(2, SampleFormat::Uint, Some(0.0));
+        let data = [1, 10, 3, 10, 0, 20, 0, 20];
+        builder.add(&data, None, 1);
+        builder.add_to_histograms(&data, None, 1);
+        let bands = builder.finish().unwrap();
+        assert_eq!(
+            (bands[0].min(), bands[0].max(), bands[0].mean()),
+            (1.0, 3.0, 2.0)
+        );
+        assert_eq!(bands[0].std_dev(), 1.0);
+        assert_eq!(bands[0].valid_percent(), 50.0);
+        assert_eq!(bands[1].mean(), 15.0);
+        assert_eq!(bands[1].valid_percent(), 100.0);
+
+        let histogram = bands[0].histogram().unwrap();
+        assert_eq!((histogram.min(), histogram.max()), (0.5, 3.5));
+        assert_eq!(histogram.counts(), [1, 0, 1]);
+
+        let builder = StatisticsBuilder::new(1, SampleFormat::Uint, Some(0.0));
+        assert!(builder.finish().is_err());
+    }
+
+    #[test]
+    fn test_gdal_metadata() {
+        let bands = [BandStatistics::new(1.0, 3.0, 2.0, 1.0, 50.0)];
+        let xml = gdal_metadata(&bands, None);
+        assert!(xml.starts_with("<GDALMetadata>\n"));
+        assert!(xml.contains(r#"<Item name="STATISTICS_MEAN" sample="0">2</Item>"#));
+
+        let existing = r#"<GDALMetadata><Item name="OFFSET" sample="0">0</Item></GDALMetadata>"#;
+        let merged = gdal_metadata(&bands, Some(existing));
+        assert!(merged.starts_with(r#"<GDALMetadata><Item name="OFFSET""#));
+        assert!(merged.contains("STATISTICS_MINIMUM"));
+        assert!(merged.ends_with("</GDALMetadata>"));
+
+        let aux = BandStatistics::to_aux_xml(&[bands[0].clone().with_histogram(Histogram::new(
+            0.5,
+            3.5,
+            vec![1, 0, 1],
+        ))]);
+        assert!(aux.contains("<HistCounts>1|0|1</HistCounts>"));
+        assert!(aux.contains(r#"<MDI key="STATISTICS_STDDEV">1</MDI>"#));
+    }
+}
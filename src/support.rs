@@ -0,0 +1,224 @@
+//! Checks of whether the image data of an IFD can be decoded, without fetching any of it.
+
+use std::fmt;
+
+use crate::decoder::DecoderRegistry;
+use crate::ifd::ImageFileDirectory;
+use crate::tiff::tags::{
+    CompressionMethod, PhotometricInterpretation, PlanarConfiguration, Predictor, SampleFormat,
+};
+
+/// A feature of an IFD that prevents its image data from being decoded.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum UnsupportedFeature {
+    /// No decoder is registered for the compression method.
+    Compression(CompressionMethod),
+    /// The samples of a pixel have different bit depths.
+    MixedBitsPerSample(Vec<u16>),
+    /// Samples of this bit depth and format can't be represented as decoded data.
+    BitsPerSample {
+        /// The bit depth of each sample.
+        bits_per_sample: u16,
+        /// The format of each sample.
+        sample_format: SampleFormat,
+    },
+    /// The sample format isn't one defined by the TIFF specification.
+    SampleFormat(SampleFormat),
+    /// The compression method can't decode this color space.
+    Photometric {
+        /// The color space of the image data.
+        photometric_interpretation: PhotometricInterpretation,
+        /// The compression method of the image data.
+        compression: CompressionMethod,
+    },
+    /// The converter registered for the color space can't convert planar samples.
+    PlanarConversion(PhotometricInterpretation),
+    /// The predictor can't be reversed for samples of this bit depth.
+    Predictor {
+        /// The predictor applied to the image data.
+        predictor: Predictor,
+        /// The bit depth of each sample.
+        bits_per_sample: u16,
+    },
+}
+
+impl fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compression(compression) => {
+                write!(f, "no decoder for compression {compression:?}")
+            }
+            Self::MixedBitsPerSample(bits_per_sample) => {
+                write!(f, "mixed bits per sample {bits_per_sample:?}")
+            }
+            Self::BitsPerSample {
+                bits_per_sample,
+                sample_format,
+            } => write!(f, "{bits_per_sample}-bit {sample_format:?} samples"),
+            Self::SampleFormat(sample_format) => write!(f, "sample format {sample_format:?}"),
+            Self::Photometric {
+                photometric_interpretation,
+                compression,
+            } => write!(
+                f,
+                "photometric interpretation {photometric_interpretation:?} with {compression:?} compression"
+            ),
+            Self::PlanarConversion(photometric_interpretation) => write!(
+                f,
+                "photometric interpretation {photometric_interpretation:?} with planar samples"
+            ),
+            Self::Predictor {
+                predictor,
+                bits_per_sample,
+            } => write!(f, "{predictor:?} predictor on {bits_per_sample}-bit samples"),
+        }
+    }
+}
+
+impl ImageFileDirectory {
+    /// List the features of this IFD that prevent `registry` from decoding its tiles.
+    ///
+    /// This only looks at the tags already read, so files can be triaged before fetching any
+    /// image data. An empty list means every tile is expected to decode, barring corrupt data.
+    pub fn check_supported(&self, registry: &DecoderRegistry) -> Vec<UnsupportedFeature> {
+        let mut unsupported = vec![];
+        if !registry.as_ref().contains_key(&self.compression) {
+            unsupported.push(UnsupportedFeature::Compression(self.compression));
+        }
+
+        if let Some(format) = self
+            .sample_format
+            .iter()
+            .find(|format| matches!(format, SampleFormat::Unknown(_)))
+        {
+            unsupported.push(UnsupportedFeature::SampleFormat(*format));
+        }
+
+        if !self.bits_per_sample.windows(2).all(|w| w[0] == w[1]) {
+            unsupported.push(UnsupportedFeature::MixedBitsPerSample(
+                self.bits_per_sample.clone(),
+            ));
+        } else if let (Some(&bits_per_sample), Some(&sample_format)) =
+            (self.bits_per_sample.first(), self.sample_format.first())
+        {
+            let representable = match sample_format {
+                SampleFormat::IEEEFP => matches!(bits_per_sample, 16 | 32 | 64),
+                SampleFormat::Int => matches!(bits_per_sample, 8 | 16 | 32 | 64),
+                _ => matches!(bits_per_sample, 1..=8 | 16 | 32 | 64),
+            };
+            if !representable {
+                unsupported.push(UnsupportedFeature::BitsPerSample {
+                    bits_per_sample,
+                    sample_format,
+                });
+            }
+
+            let predictable = match self.predictor.unwrap_or(Predictor::None) {
+                Predictor::None => true,
+                Predictor::Horizontal => matches!(bits_per_sample, 8 | 16 | 32 | 64),
+                Predictor::FloatingPoint => matches!(bits_per_sample, 16 | 32 | 64),
+                Predictor::Unknown(_) => false,
+            };
+            if !predictable {
+                unsupported.push(UnsupportedFeature::Predictor {
+                    predictor: self.predictor.unwrap_or(Predictor::None),
+                    bits_per_sample,
+                });
+            }
+        }
+
+        let jpeg_photometric = matches!(
+            self.photometric_interpretation,
+            PhotometricInterpretation::RGB
+                | PhotometricInterpretation::WhiteIsZero
+                | PhotometricInterpretation::BlackIsZero
+                | PhotometricInterpretation::TransparencyMask
+                | PhotometricInterpretation::CMYK
+                | PhotometricInterpretation::YCbCr
+        );
+        if self.compression == CompressionMethod::ModernJPEG && !jpeg_photometric {
+            unsupported.push(UnsupportedFeature::Photometric {
+                photometric_interpretation: self.photometric_interpretation,
+                compression: self.compression,
+            });
+        }
+
+        let converted = registry
+            .photometric_registry()
+            .as_ref()
+            .contains_key(&self.photometric_interpretation);
+        if converted && self.planar_configuration != PlanarConfiguration::Chunky {
+            unsupported.push(UnsupportedFeature::PlanarConversion(
+                self.photometric_interpretation,
+            ));
+        }
+
+        unsupported
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IfdBuilder;
+
+    #[test]
+    fn test_check_supported() {
+        let registry = DecoderRegistry::default();
+        let ifd = IfdBuilder::new(10, 10)
+            .with_data_type(SampleFormat::IEEEFP, 32)
+            .with_compression(CompressionMethod::Deflate)
+            .with_predictor(Predictor::FloatingPoint)
+            .build()
+            .unwrap();
+        assert!(ifd.check_supported(&registry).is_empty());
+
+        let ifd = IfdBuilder::new(10, 10)
+            .with_data_type(SampleFormat::Uint, 12)
+            .with_compression(CompressionMethod::Deflate)
+            .with_predictor(Predictor::Horizontal)
+            .build()
+            .unwrap();
+        assert_eq!(
+            ifd.check_supported(&registry),
+            [
+                UnsupportedFeature::BitsPerSample {
+                    bits_per_sample: 12,
+                    sample_format: SampleFormat::Uint
+                },
+                UnsupportedFeature::Predictor {
+                    predictor: Predictor::Horizontal,
+                    bits_per_sample: 12
+                },
+            ]
+        );
+        assert!(ifd
+            .check_supported(&DecoderRegistry::new())
+            .contains(&UnsupportedFeature::Compression(CompressionMethod::Deflate)));
+
+        let ifd = IfdBuilder::new(10, 10)
+            .with_samples_per_pixel(3)
+            .with_compression(CompressionMethod::ModernJPEG)
+            .with_photometric_interpretation(PhotometricInterpretation::CIELab)
+            .build()
+            .unwrap();
+        assert_eq!(
+            ifd.check_supported(&registry),
+            [UnsupportedFeature::Photometric {
+                photometric_interpretation: PhotometricInterpretation::CIELab,
+                compression: CompressionMethod::ModernJPEG,
+            }]
+        );
+
+        let mut ifd = IfdBuilder::new(10, 10)
+            .with_samples_per_pixel(2)
+            .build()
+            .unwrap();
+        ifd.bits_per_sample = vec![8, 16];
+        assert_eq!(
+            ifd.check_supported(&registry),
+            [UnsupportedFeature::MixedBitsPerSample(vec![8, 16])]
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! TIFF tags, the enums of their values and the [`Value`] of any tag.
+//!
+//! These types started out vendored from the `tiff` crate, under [`tiff`](crate::tiff), and have
+//! since diverged from it. This module is their stable path: the types re-exported here follow
+//! the semver of this crate, and new variants are added in minor releases as the enums are
+//! `#[non_exhaustive]`.
+
+pub use crate::tiff::tags::*;
+pub use crate::tiff::Value;
@@ -0,0 +1,80 @@
+//! Fixtures shared by the unit tests.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+
+use crate::error::AsyncTiffResult;
+use crate::metadata::{MetadataFetch, TiffMetadataReader};
+use crate::reader::{AsyncFileReader, MemoryReader};
+use crate::writer::MemoryWriter;
+use crate::{CogImage, CogWriter, ImageFileDirectory};
+
+/// Counts the requests made to, and the bytes fetched from, a file in memory.
+#[derive(Debug, Default)]
+pub(crate) struct CountingReader {
+    file: MemoryReader,
+    requests: AtomicU64,
+    fetched: AtomicU64,
+}
+
+impl CountingReader {
+    pub(crate) fn new(file: impl Into<Bytes>) -> Self {
+        Self {
+            file: MemoryReader::new(file),
+            ..Default::default()
+        }
+    }
+
+    /// The bytes of the file.
+    pub(crate) fn file(&self) -> &Bytes {
+        self.file.data()
+    }
+
+    /// The number of requests made so far.
+    pub(crate) fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// The number of bytes fetched so far.
+    pub(crate) fn fetched(&self) -> u64 {
+        self.fetched.load(Ordering::Relaxed)
+    }
+
+    /// The number of requests made and bytes fetched since the last call, resetting both.
+    pub(crate) fn take_counts(&self) -> (usize, u64) {
+        (
+            self.requests.swap(0, Ordering::Relaxed) as usize,
+            self.fetched.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+impl AsyncFileReader for CountingReader {
+    fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.fetched
+            .fetch_add(range.end.saturating_sub(range.start), Ordering::Relaxed);
+        self.file.get_bytes(range)
+    }
+}
+
+/// Write `images` as a COG with the default options.
+pub(crate) async fn write_cog(images: Vec<CogImage>) -> Bytes {
+    write_cog_with(&CogWriter::new(), images).await
+}
+
+/// Write `images` as a COG with `writer`.
+pub(crate) async fn write_cog_with(writer: &CogWriter, images: Vec<CogImage>) -> Bytes {
+    let mut file = MemoryWriter::new();
+    writer.write(images, &mut file).await.unwrap();
+    file.into_inner()
+}
+
+/// Read all IFDs of the file read by `fetch`.
+pub(crate) async fn read_ifds<F: MetadataFetch>(fetch: &F) -> Vec<ImageFileDirectory> {
+    let mut metadata = TiffMetadataReader::try_open(fetch).await.unwrap();
+    metadata.read_all_ifds(fetch).await.unwrap()
+}
@@ -0,0 +1,444 @@
+//! Synthesize small TIFFs in memory for tests.
+//!
+//! [`TestImage`] describes a tiled image whose pixels follow a deterministic pattern, and writes
+//! it as a TIFF or BigTIFF with [`CogWriter`], so that tests can cover combinations of tiling,
+//! compression, predictor, data type and geo keys without committing binary fixtures.
+//!
+//! ```
+//! # tokio_test::block_on(async {
+//! use async_tiff::testgen::TestImage;
+//! use async_tiff::tags::{CompressionMethod, Predictor, SampleFormat};
+//!
+//! let image = TestImage::new(100, 60)
+//!     .with_data_type(SampleFormat::Int, 16)
+//!     .with_tiling(32, 32)
+//!     .with_compression(CompressionMethod::Deflate)
+//!     .with_predictor(Predictor::Horizontal)
+//!     .with_geo(32633, [500_000.0, 4_000_000.0], 10.0);
+//! let file = image.encode().await.unwrap();
+//! assert_eq!(&file[..2], b"II");
+//! assert_eq!(image.pixels().len(), 100 * 60 * 2);
+//! # })
+//! ```
+
+use bytes::Bytes;
+
+use crate::decoder::DecoderRegistry;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::geo::GeoKeyDirectory;
+use crate::metadata::TiffMetadataReader;
+use crate::reader::{AsyncFileReader, MemoryReader};
+use crate::tiff::tags::{CompressionMethod, PlanarConfiguration, Predictor, SampleFormat};
+use crate::writer::MemoryWriter;
+use crate::{CogImage, CogWriter, IfdBuilder, ImageFileDirectory};
+
+/// The largest sample value of the pattern, so that it is exact in an `f64` and fits every
+/// integer type once reduced to its range.
+const PATTERN_BITS: u16 = 24;
+
+/// A tiled test image with deterministic pixel values.
+///
+/// The default image holds single 8-bit unsigned samples in uncompressed 16x16 tiles, and is
+/// written as a little-endian TIFF.
+#[derive(Debug, Clone)]
+pub struct TestImage {
+    width: u32,
+    height: u32,
+    tile_size: (u32, u32),
+    sample_format: SampleFormat,
+    bits_per_sample: u16,
+    samples_per_pixel: u16,
+    planar_configuration: PlanarConfiguration,
+    compression: CompressionMethod,
+    predictor: Predictor,
+    bigtiff: bool,
+    geo: Option<(u16, [f64; 2], f64)>,
+}
+
+impl TestImage {
+    /// Describe a `width` by `height` image.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tile_size: (16, 16),
+            sample_format: SampleFormat::Uint,
+            bits_per_sample: 8,
+            samples_per_pixel: 1,
+            planar_configuration: PlanarConfiguration::Chunky,
+            compression: CompressionMethod::None,
+            predictor: Predictor::None,
+            bigtiff: false,
+            geo: None,
+        }
+    }
+
+    /// Set the size of the tiles, which must be multiples of 16.
+    pub fn with_tiling(mut self, tile_width: u32, tile_height: u32) -> Self {
+        self.tile_size = (tile_width, tile_height);
+        self
+    }
+
+    /// Set the data type of the samples: 8, 16, 32 or 64-bit integers, or 32 or 64-bit floats.
+    pub fn with_data_type(mut self, sample_format: SampleFormat, bits_per_sample: u16) -> Self {
+        self.sample_format = sample_format;
+        self.bits_per_sample = bits_per_sample;
+        self
+    }
+
+    /// Set the number of samples of each pixel.
+    pub fn with_samples_per_pixel(mut self, samples_per_pixel: u16) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Set whether the samples of each pixel are stored together or in separate planes.
+    pub fn with_planar_configuration(mut self, planar_configuration: PlanarConfiguration) -> Self {
+        self.planar_configuration = planar_configuration;
+        self
+    }
+
+    /// Set the compression of the tiles, encoded with the default
+    /// [`EncoderRegistry`](crate::encoder::EncoderRegistry).
+    pub fn with_compression(mut self, compression: CompressionMethod) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the predictor applied before compression.
+    pub fn with_predictor(mut self, predictor: Predictor) -> Self {
+        self.predictor = predictor;
+        self
+    }
+
+    /// Set whether to write a BigTIFF.
+    pub fn with_bigtiff(mut self, bigtiff: bool) -> Self {
+        self.bigtiff = bigtiff;
+        self
+    }
+
+    /// Georeference the image in the CRS with the EPSG code `epsg`, with its top left corner at
+    /// `origin` and square pixels of `pixel_size` CRS units.
+    ///
+    /// Codes from 4000 to 4999 are written as geographic CRSs, and others as projected CRSs.
+    pub fn with_geo(mut self, epsg: u16, origin: [f64; 2], pixel_size: f64) -> Self {
+        self.geo = Some((epsg, origin, pixel_size));
+        self
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The value of sample `sample` of the pixel at column `x` and row `y`.
+    ///
+    /// Values vary along both axes and between samples, cover negative values for signed and
+    /// fractional values for floating point types, and are exact in an `f64`. Pixels past the
+    /// edges of the image, in the padding of edge tiles, follow the same pattern.
+    pub fn sample(&self, x: u32, y: u32, sample: u16) -> f64 {
+        let v = (x as u64 * 3 + y as u64 * 5 + sample as u64 * 7) * 37;
+        let bits = self.bits_per_sample.min(PATTERN_BITS);
+        match self.sample_format {
+            SampleFormat::Int => (v % (1 << bits)) as f64 - (1u64 << (bits - 1)) as f64,
+            SampleFormat::IEEEFP => v as f64 * 0.25 - 1000.0,
+            _ => (v % (1 << bits)) as f64,
+        }
+    }
+
+    /// The native-endian samples of the whole image, with the samples of each pixel stored
+    /// together, as returned by [`read_window`](crate::ImageFileDirectory::read_window).
+    pub fn pixels(&self) -> Vec<u8> {
+        let mut data = vec![];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                for sample in 0..self.samples_per_pixel {
+                    self.push_sample(self.sample(x, y, sample), &mut data);
+                }
+            }
+        }
+        data
+    }
+
+    /// Build the IFD and decoded tiles of the image, e.g. to write it along with overviews.
+    pub fn cog_image(&self) -> AsyncTiffResult<CogImage> {
+        self.check_data_type()?;
+        let (tile_width, tile_height) = self.tile_size;
+        let mut builder = IfdBuilder::new(self.width, self.height)
+            .with_data_type(self.sample_format, self.bits_per_sample)
+            .with_samples_per_pixel(self.samples_per_pixel)
+            .with_planar_configuration(self.planar_configuration)
+            .with_tiling(tile_width, tile_height)
+            .with_compression(self.compression)
+            .with_predictor(self.predictor);
+        if let Some((epsg, [x, y], pixel_size)) = self.geo {
+            let geographic = (4000..5000).contains(&epsg);
+            let mut geo_key_directory = GeoKeyDirectory {
+                model_type: Some(if geographic { 2 } else { 1 }),
+                raster_type: Some(1),
+                ..Default::default()
+            };
+            if geographic {
+                geo_key_directory.geographic_type = Some(epsg);
+            } else {
+                geo_key_directory.projected_type = Some(epsg);
+            }
+            builder = builder
+                .with_geo_key_directory(geo_key_directory)
+                .with_model_pixel_scale([pixel_size, pixel_size, 0.0])
+                .with_model_tiepoint([0.0, 0.0, 0.0, x, y, 0.0]);
+        }
+        let ifd = builder.build()?;
+
+        let (x_count, y_count) = ifd.tile_count().expect("the image is tiled");
+        // Each tile of a planar image holds a single sample of its pixels.
+        let planes = match self.planar_configuration {
+            PlanarConfiguration::Chunky => vec![(0, self.samples_per_pixel)],
+            PlanarConfiguration::Planar => {
+                (0..self.samples_per_pixel).map(|s| (s, s + 1)).collect()
+            }
+        };
+        let mut tiles = vec![];
+        for (first_sample, end_sample) in planes {
+            for tile_y in 0..y_count as u32 {
+                for tile_x in 0..x_count as u32 {
+                    let mut tile = vec![];
+                    for y in tile_y * tile_height..(tile_y + 1) * tile_height {
+                        for x in tile_x * tile_width..(tile_x + 1) * tile_width {
+                            for sample in first_sample..end_sample {
+                                self.push_sample(self.sample(x, y, sample), &mut tile);
+                            }
+                        }
+                    }
+                    tiles.push(Bytes::from(tile));
+                }
+            }
+        }
+        Ok(CogImage::new(ifd, tiles))
+    }
+
+    /// Write the image to a new in-memory file.
+    pub async fn encode(&self) -> AsyncTiffResult<Bytes> {
+        let mut writer = MemoryWriter::new();
+        self.writer()
+            .write(vec![self.cog_image()?], &mut writer)
+            .await?;
+        Ok(writer.into_inner())
+    }
+
+    /// Encode the image and decode it again with [`roundtrip_with`], returning the IFD that was
+    /// read back.
+    pub async fn roundtrip(&self) -> AsyncTiffResult<ImageFileDirectory> {
+        let image = self.cog_image()?;
+        roundtrip_with(&self.writer(), image.ifd().clone(), image.tiles().to_vec()).await
+    }
+
+    /// The writer of the image, which applies the predictor as set even if it is none.
+    fn writer(&self) -> CogWriter {
+        CogWriter::new()
+            .with_bigtiff(self.bigtiff)
+            .with_predictor(self.predictor)
+    }
+
+    fn check_data_type(&self) -> AsyncTiffResult<()> {
+        match (self.sample_format, self.bits_per_sample) {
+            (SampleFormat::Uint | SampleFormat::Int, 8 | 16 | 32 | 64)
+            | (SampleFormat::IEEEFP, 32 | 64) => Ok(()),
+            (sample_format, bits) => Err(AsyncTiffError::General(format!(
+                "Unsupported test image data type: {sample_format:?} with {bits} bits"
+            ))),
+        }
+    }
+
+    fn push_sample(&self, value: f64, data: &mut Vec<u8>) {
+        match (self.sample_format, self.bits_per_sample) {
+            (SampleFormat::Int, 8) => data.extend((value as i8).to_ne_bytes()),
+            (SampleFormat::Int, 16) => data.extend((value as i16).to_ne_bytes()),
+            (SampleFormat::Int, 32) => data.extend((value as i32).to_ne_bytes()),
+            (SampleFormat::Int, _) => data.extend((value as i64).to_ne_bytes()),
+            (SampleFormat::IEEEFP, 32) => data.extend((value as f32).to_ne_bytes()),
+            (SampleFormat::IEEEFP, _) => data.extend(value.to_ne_bytes()),
+            (_, 8) => data.push(value as u8),
+            (_, 16) => data.extend((value as u16).to_ne_bytes()),
+            (_, 32) => data.extend((value as u32).to_ne_bytes()),
+            (_, _) => data.extend((value as u64).to_ne_bytes()),
+        }
+    }
+}
+
+/// Encode the decoded `tiles` of `ifd` with a default [`CogWriter`], then read the file back and
+/// check that every tile decodes to the same bytes.
+///
+/// See [`roundtrip_with`].
+pub async fn roundtrip(
+    ifd: ImageFileDirectory,
+    tiles: Vec<Bytes>,
+) -> AsyncTiffResult<ImageFileDirectory> {
+    roundtrip_with(&CogWriter::new(), ifd, tiles).await
+}
+
+/// Encode the decoded `tiles` of `ifd` with `writer`, then read the file back and check that every
+/// tile decodes to the same bytes.
+///
+/// The tiles are as taken by [`CogImage::new`]. This exercises the encoding path of the
+/// combination of data type, compression and predictor described by `ifd` against the decoding
+/// path, so that it can be checked for every combination a test or downstream crate cares about.
+///
+/// Returns the IFD read back from the file, e.g. to check its tags, or an error if writing or
+/// reading fails or a tile differs.
+pub async fn roundtrip_with(
+    writer: &CogWriter,
+    ifd: ImageFileDirectory,
+    tiles: Vec<Bytes>,
+) -> AsyncTiffResult<ImageFileDirectory> {
+    let mut file = MemoryWriter::new();
+    writer
+        .write(vec![CogImage::new(ifd, tiles.clone())], &mut file)
+        .await?;
+    let reader = MemoryReader::new(file.into_inner());
+    let mut metadata = TiffMetadataReader::try_open(&reader).await?;
+    let ifd = metadata
+        .read_next_ifd(&reader)
+        .await?
+        .ok_or_else(|| AsyncTiffError::General("No IFD was written".to_string()))?;
+
+    let (x_count, y_count) = ifd
+        .tile_count()
+        .ok_or_else(|| AsyncTiffError::General("Not a tiled TIFF".to_string()))?;
+    let registry = DecoderRegistry::default();
+    for (index, expected) in tiles.iter().enumerate() {
+        let position = index % (x_count * y_count);
+        let range = ifd
+            .chunk_byte_range(index)
+            .ok_or(AsyncTiffError::TileIndexError(index as u32, 0))?;
+        let compressed_bytes = reader.get_bytes(range.clone()).await?;
+        let decoded = ifd
+            .new_tile(
+                position % x_count,
+                position / x_count,
+                range.start,
+                compressed_bytes,
+            )?
+            .decode(&registry)?;
+        if decoded != expected {
+            let first = decoded
+                .iter()
+                .zip(expected)
+                .position(|(a, b)| a != b)
+                .unwrap_or(decoded.len().min(expected.len()));
+            return Err(AsyncTiffError::General(format!(
+                "Tile {index} of {:?} compressed data with {:?} predictor decoded to {} bytes \
+                 that differ from the {} encoded bytes, starting at byte {first}",
+                ifd.compression,
+                ifd.predictor.unwrap_or(Predictor::None),
+                decoded.len(),
+                expected.len(),
+            )));
+        }
+    }
+    Ok(ifd)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Window;
+
+    async fn read_back(image: &TestImage) -> (ImageFileDirectory, Vec<u8>) {
+        let reader = MemoryReader::new(image.encode().await.unwrap());
+        let mut metadata = TiffMetadataReader::try_open(&reader).await.unwrap();
+        let ifd = metadata.read_all_ifds(&reader).await.unwrap().remove(0);
+        let window = Window::new(0, 0, image.width(), image.height());
+        let data = ifd
+            .read_window(window, &reader, &DecoderRegistry::default())
+            .await
+            .unwrap();
+        (ifd, data.into_data())
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_matrix() {
+        let data_types = [
+            (SampleFormat::Uint, 8, Predictor::Horizontal),
+            (SampleFormat::Uint, 16, Predictor::Horizontal),
+            (SampleFormat::Int, 32, Predictor::Horizontal),
+            (SampleFormat::Int, 64, Predictor::None),
+            (SampleFormat::IEEEFP, 32, Predictor::FloatingPoint),
+            (SampleFormat::IEEEFP, 64, Predictor::FloatingPoint),
+        ];
+        for (sample_format, bits, predictor) in data_types {
+            for compression in [CompressionMethod::Deflate, CompressionMethod::LZW] {
+                for planar in [PlanarConfiguration::Chunky, PlanarConfiguration::Planar] {
+                    let image = TestImage::new(40, 20)
+                        .with_data_type(sample_format, bits)
+                        .with_samples_per_pixel(2)
+                        .with_planar_configuration(planar)
+                        .with_tiling(16, 16)
+                        .with_compression(compression)
+                        .with_predictor(predictor)
+                        .with_bigtiff(planar == PlanarConfiguration::Planar);
+                    let (ifd, data) = read_back(&image).await;
+                    assert_eq!(ifd.compression, compression);
+                    assert_eq!(ifd.predictor.unwrap_or(Predictor::None), predictor);
+                    assert_eq!(data, image.pixels(), "{sample_format:?} {bits} {planar:?}");
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        for compression in [
+            CompressionMethod::None,
+            CompressionMethod::Deflate,
+            CompressionMethod::LZW,
+        ] {
+            for predictor in [Predictor::None, Predictor::Horizontal] {
+                let image = TestImage::new(20, 20)
+                    .with_data_type(SampleFormat::Uint, 16)
+                    .with_samples_per_pixel(3)
+                    .with_compression(compression)
+                    .with_predictor(predictor);
+                let ifd = image.roundtrip().await.unwrap();
+                assert_eq!(ifd.compression, compression);
+            }
+        }
+
+        let ifd = IfdBuilder::new(16, 16)
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::Deflate)
+            .build()
+            .unwrap();
+        let tile = Bytes::from((0..=255).collect::<Vec<u8>>());
+        assert!(roundtrip(ifd.clone(), vec![tile]).await.is_ok());
+        assert!(roundtrip(ifd, vec![Bytes::from_static(&[0; 4])])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_geo() {
+        let image = TestImage::new(16, 16).with_geo(4326, [10.0, 50.0], 0.5);
+        let (ifd, _) = read_back(&image).await;
+        let geo_key_directory = ifd.geo_key_directory().unwrap();
+        assert_eq!(geo_key_directory.geographic_type, Some(4326));
+        assert_eq!(ifd.model_tiepoint().unwrap()[3..5], [10.0, 50.0]);
+    }
+
+    #[test]
+    fn test_sample_pattern() {
+        let image = TestImage::new(4, 4).with_data_type(SampleFormat::Int, 8);
+        assert!((-128.0..128.0).contains(&image.sample(3, 3, 0)));
+        assert_ne!(image.sample(0, 0, 0), image.sample(1, 0, 0));
+        assert_ne!(image.sample(0, 0, 0), image.sample(0, 1, 0));
+        assert!(TestImage::new(4, 4)
+            .with_data_type(SampleFormat::IEEEFP, 16)
+            .cog_image()
+            .is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Generation of RGBA previews from the overviews of a [`Pyramid`].
+
+use crate::decoder::DecoderRegistry;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::ifd::ImageFileDirectory;
+use crate::reader::AsyncFileReader;
+use crate::tiff::tags::{PhotometricInterpretation, SampleFormat};
+use crate::window::{sample_to_f64, SampleLayout, Window, WindowData};
+use crate::Pyramid;
+
+/// An image of interleaved 8-bit RGBA samples, as returned by [`Pyramid::thumbnail`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// The width of the thumbnail in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the thumbnail in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The RGBA samples of each pixel, in row-major order.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consume this and return the RGBA samples.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl Pyramid {
+    /// Render the whole image as an RGBA thumbnail whose larger side is at most `max_size`
+    /// pixels, keeping its aspect ratio and never upscaling.
+    ///
+    /// The thumbnail is read from the coarsest level with enough resolution, as in
+    /// [`read_window_masked`](Self::read_window_masked), and resampled to fit with nearest
+    /// neighbour. Palette images are looked up in their color map, grayscale images are expanded
+    /// to RGB and WhiteIsZero images inverted. Samples other than 8-bit unsigned integers are
+    /// stretched from the minimum to the maximum valid sample. Pixels masked by a mask IFD or
+    /// nodata value are transparent, and an alpha extra sample, if any, sets the opacity of the
+    /// others.
+    pub async fn thumbnail(
+        &self,
+        max_size: u32,
+        reader: &dyn AsyncFileReader,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<Thumbnail> {
+        if max_size == 0 {
+            return Err(AsyncTiffError::General(
+                "Thumbnails must be at least 1 pixel".to_string(),
+            ));
+        }
+        let full = self.ifd(0).unwrap();
+        let (full_width, full_height) = (full.image_width(), full.image_height());
+        let scale = (max_size as f64 / full_width.max(full_height) as f64).min(1.0);
+        let width = ((full_width as f64 * scale).round() as u32).clamp(1, max_size);
+        let height = ((full_height as f64 * scale).round() as u32).clamp(1, max_size);
+
+        let window = Window::new(0, 0, full_width, full_height);
+        let (level, masked) = self
+            .read_window_masked(window, width, height, reader, decoder_registry)
+            .await?;
+        let (data, mask) = masked.into_parts();
+        let colors = Colors::new(self.ifd(level).unwrap(), &data, &mask);
+
+        let (level_width, level_height) = (data.window().width(), data.window().height());
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let level_y = ((y as f64 + 0.5) * level_height as f64 / height as f64) as usize;
+            for x in 0..width {
+                let level_x = ((x as f64 + 0.5) * level_width as f64 / width as f64) as usize;
+                let idx = level_y * level_width as usize + level_x;
+                rgba.extend(colors.rgba(idx, mask[idx]));
+            }
+        }
+        Ok(Thumbnail {
+            width,
+            height,
+            data: rgba,
+        })
+    }
+}
+
+/// Converts the pixels of decoded window data to RGBA.
+struct Colors<'a> {
+    data: &'a WindowData,
+    pixels: usize,
+    photometric_interpretation: PhotometricInterpretation,
+    color_samples: usize,
+    alpha_sample: Option<usize>,
+    palette: Option<Vec<[u8; 3]>>,
+    /// The range stretched to 0-255, if samples aren't already 8-bit.
+    range: Option<(f64, f64)>,
+}
+
+impl<'a> Colors<'a> {
+    fn new(ifd: &ImageFileDirectory, data: &'a WindowData, mask: &[bool]) -> Self {
+        let samples_per_pixel = data.samples_per_pixel() as usize;
+        let photometric_interpretation = ifd.photometric_interpretation();
+        let palette = (photometric_interpretation == PhotometricInterpretation::RGBPalette
+            && samples_per_pixel == 1)
+            .then(|| ifd.colormap())
+            .flatten()
+            .map(|colormap| {
+                (0..colormap.len())
+                    .map(|idx| colormap.get(&idx).copied().unwrap_or_default())
+                    .collect()
+            });
+        let color_samples = match photometric_interpretation {
+            PhotometricInterpretation::CMYK if samples_per_pixel >= 4 => 4,
+            PhotometricInterpretation::WhiteIsZero
+            | PhotometricInterpretation::BlackIsZero
+            | PhotometricInterpretation::RGBPalette => 1,
+            _ if samples_per_pixel >= 3 => 3,
+            _ => 1,
+        };
+        // Associated or unassociated alpha
+        let alpha_sample = ifd
+            .extra_samples()
+            .and_then(|extra| extra.iter().position(|v| matches!(v, 1 | 2)))
+            .map(|idx| color_samples + idx)
+            .filter(|idx| *idx < samples_per_pixel);
+
+        let mut colors = Self {
+            data,
+            pixels: mask.len(),
+            photometric_interpretation,
+            color_samples,
+            alpha_sample,
+            palette,
+            range: None,
+        };
+        let is_u8 = data.sample_format() == SampleFormat::Uint && data.bytes_per_sample() == 1;
+        if !is_u8 && colors.palette.is_none() {
+            let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+            for (idx, _) in mask.iter().enumerate().filter(|(_, valid)| **valid) {
+                for sample in 0..color_samples {
+                    let value = colors.sample(idx, sample);
+                    if !value.is_nan() {
+                        min = min.min(value);
+                        max = max.max(value);
+                    }
+                }
+            }
+            colors.range = Some((min, max));
+        }
+        colors
+    }
+
+    /// The value of `sample` of the pixel at `idx`, or NaN if it can't be represented.
+    fn sample(&self, idx: usize, sample: usize) -> f64 {
+        let bytes_per_sample = self.data.bytes_per_sample();
+        let offset = match self.data.layout() {
+            SampleLayout::Interleaved => {
+                (idx * self.data.samples_per_pixel() as usize + sample) * bytes_per_sample
+            }
+            SampleLayout::Planar => (sample * self.pixels + idx) * bytes_per_sample,
+        };
+        let bytes = &self.data.data()[offset..offset + bytes_per_sample];
+        sample_to_f64(bytes, self.data.sample_format()).unwrap_or(f64::NAN)
+    }
+
+    /// A color sample scaled to 0-255.
+    fn scaled(&self, idx: usize, sample: usize) -> u8 {
+        let value = self.sample(idx, sample);
+        let value = match self.range {
+            Some((min, max)) if max > min => (value - min) / (max - min) * 255.0,
+            Some(_) => 0.0,
+            None => value,
+        };
+        value.round().clamp(0.0, 255.0) as u8
+    }
+
+    fn rgba(&self, idx: usize, valid: bool) -> [u8; 4] {
+        let [r, g, b] = if let Some(palette) = &self.palette {
+            let index = self.sample(idx, 0);
+            palette.get(index as usize).copied().unwrap_or_default()
+        } else {
+            match (self.color_samples, self.photometric_interpretation) {
+                (1, PhotometricInterpretation::WhiteIsZero) => [255 - self.scaled(idx, 0); 3],
+                (1, _) => [self.scaled(idx, 0); 3],
+                (4, _) => {
+                    let k = 255 - self.scaled(idx, 3) as u32;
+                    [0, 1, 2].map(|c| ((255 - self.scaled(idx, c) as u32) * k / 255) as u8)
+                }
+                _ => [0, 1, 2].map(|c| self.scaled(idx, c)),
+            }
+        };
+        let alpha = match self.alpha_sample {
+            _ if !valid => 0,
+            Some(sample) => self.alpha(idx, sample),
+            None => 255,
+        };
+        [r, g, b, alpha]
+    }
+
+    /// An alpha sample scaled from the full range of its type, or from 0-1 for floats.
+    fn alpha(&self, idx: usize, sample: usize) -> u8 {
+        let value = self.sample(idx, sample);
+        let max = match self.data.sample_format() {
+            SampleFormat::IEEEFP => 1.0,
+            SampleFormat::Int => 2f64.powi(8 * self.data.bytes_per_sample() as i32 - 1) - 1.0,
+            _ => 2f64.powi(8 * self.data.bytes_per_sample() as i32) - 1.0,
+        };
+        (value / max * 255.0).round().clamp(0.0, 255.0) as u8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::reader::MemoryReader;
+    use crate::test_util::{read_ifds, write_cog};
+    use crate::tiff::tags::{CompressionMethod, Tag};
+    use crate::tiff::Value;
+    use crate::{CogImage, IfdBuilder, TIFF};
+
+    /// Write a COG of the given images, each filled with one pixel value.
+    async fn pyramid(images: Vec<(IfdBuilder, Vec<u8>)>) -> (Pyramid, MemoryReader) {
+        let images = images
+            .into_iter()
+            .map(|(builder, pixel)| {
+                let ifd = builder
+                    .with_tiling(16, 16)
+                    .with_compression(CompressionMethod::Deflate)
+                    .build()
+                    .unwrap();
+                let (x_count, y_count) = ifd.tile_count().unwrap();
+                let tile = Bytes::from(pixel.repeat(256));
+                CogImage::new(ifd, vec![tile; x_count * y_count])
+            })
+            .collect();
+        let reader = MemoryReader::new(write_cog(images).await);
+        let ifds = read_ifds(&reader).await;
+        (Pyramid::try_new(TIFF::new(ifds)).unwrap(), reader)
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_levels() {
+        let rgb = |size: u32| IfdBuilder::new(size * 2, size).with_samples_per_pixel(3);
+        let (pyramid, reader) = pyramid(vec![
+            (rgb(64), vec![10, 20, 30]),
+            (rgb(32), vec![40, 50, 60]),
+            (rgb(16), vec![70, 80, 90]),
+        ])
+        .await;
+        let registry = DecoderRegistry::default();
+
+        let thumbnail = pyramid.thumbnail(40, &reader, &registry).await.unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (40, 20));
+        assert_eq!(thumbnail.data().len(), 40 * 20 * 4);
+        assert_eq!(thumbnail.data()[..4], [40, 50, 60, 255]);
+
+        let thumbnail = pyramid.thumbnail(20, &reader, &registry).await.unwrap();
+        assert_eq!(thumbnail.data()[..4], [70, 80, 90, 255]);
+
+        // Thumbnails are never upscaled
+        let thumbnail = pyramid.thumbnail(1000, &reader, &registry).await.unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (128, 64));
+        assert_eq!(thumbnail.data()[..4], [10, 20, 30, 255]);
+
+        assert!(pyramid.thumbnail(0, &reader, &registry).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_colors() {
+        let registry = DecoderRegistry::default();
+
+        let color_map = (0..768).map(|v| if v == 256 { 65535 } else { 0 }).collect();
+        let palette = IfdBuilder::new(16, 16)
+            .with_photometric_interpretation(PhotometricInterpretation::RGBPalette)
+            .with_color_map(color_map);
+        let (pyramid, reader) = pyramid(vec![(palette, vec![0])]).await;
+        let thumbnail = pyramid.thumbnail(8, &reader, &registry).await.unwrap();
+        assert_eq!(thumbnail.data()[..4], [0, 255, 0, 255]);
+
+        // 16-bit samples are stretched, and nodata pixels are transparent
+        let ifd = IfdBuilder::new(48, 16)
+            .with_data_type(SampleFormat::Uint, 16)
+            .with_tag(Tag::GdalNodata, Value::Ascii("0".to_string()))
+            .with_tiling(16, 16)
+            .with_compression(CompressionMethod::Deflate)
+            .build()
+            .unwrap();
+        let tiles = [0u16, 1000, 3000]
+            .map(|v| Bytes::from(v.to_ne_bytes().repeat(256)))
+            .to_vec();
+        let reader = MemoryReader::new(write_cog(vec![CogImage::new(ifd, tiles)]).await);
+        let ifds = read_ifds(&reader).await;
+        let pyramid = Pyramid::try_new(TIFF::new(ifds)).unwrap();
+        let thumbnail = pyramid.thumbnail(48, &reader, &registry).await.unwrap();
+        assert_eq!(thumbnail.data()[..4], [0, 0, 0, 0]);
+        assert_eq!(thumbnail.data()[16 * 4..17 * 4], [0, 0, 0, 255]);
+        assert_eq!(thumbnail.data()[32 * 4..33 * 4], [255, 255, 255, 255]);
+    }
+}
@@ -35,6 +35,18 @@ pub enum Value {
 }
 
 impl Value {
+    /// The heap memory held by this value, in bytes.
+    pub(crate) fn heap_size(&self) -> usize {
+        match self {
+            List(values) => {
+                values.capacity() * std::mem::size_of::<Value>()
+                    + values.iter().map(Value::heap_size).sum::<usize>()
+            }
+            Ascii(s) => s.capacity(),
+            _ => 0,
+        }
+    }
+
     pub fn into_u8(self) -> TiffResult<u8> {
         match self {
             Byte(val) => Ok(val),
@@ -142,9 +154,34 @@ impl Value {
         }
     }
 
+    /// Convert to a string, returning only the first string if the value holds several
+    /// NUL-separated strings.
     pub fn into_string(self) -> TiffResult<String> {
         match self {
-            Ascii(val) => Ok(val),
+            Ascii(mut val) => {
+                if let Some(first) = val.find('\0') {
+                    val.truncate(first);
+                }
+                Ok(val)
+            }
+            val => Err(TiffError::FormatError(
+                TiffFormatError::SignedIntegerExpected(val),
+            )),
+        }
+    }
+
+    /// Convert to a list of strings, splitting on the NUL bytes that separate multiple strings
+    /// stored in a single ASCII tag.
+    pub fn into_string_vec(self) -> TiffResult<Vec<String>> {
+        match self {
+            Ascii(val) => Ok(val.split('\0').map(String::from).collect()),
+            List(vec) => {
+                let mut new_vec = Vec::with_capacity(vec.len());
+                for v in vec {
+                    new_vec.extend(v.into_string_vec()?)
+                }
+                Ok(new_vec)
+            }
             val => Err(TiffError::FormatError(
                 TiffFormatError::SignedIntegerExpected(val),
             )),
@@ -1,4 +1,6 @@
 //! Vendored content from tiff crate
+//!
+//! Prefer the stable [`tags`](crate::tags) module for the tag types and [`Value`].
 
 mod error;
 mod ifd;
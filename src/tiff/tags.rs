@@ -87,7 +87,7 @@ pub enum Tag(u16) unknown("A private or extension tag") {
     Copyright = 33_432,
     DateTime = 306,
     ExtraSamples = 338, // TODO add support
-    FillOrder = 266, // TODO add support
+    FillOrder = 266,
     FreeByteCounts = 289, // TODO add support
     FreeOffsets = 288, // TODO add support
     GrayResponseCurve = 291, // TODO add support
@@ -120,12 +120,24 @@ pub enum Tag(u16) unknown("A private or extension tag") {
     TileLength = 323,
     TileOffsets = 324,
     TileByteCounts = 325,
+    // CCITT fax
+    T4Options = 292,
+    T6Options = 293,
     // Data Sample Format
     SampleFormat = 339,
     SMinSampleValue = 340, // TODO add support
     SMaxSampleValue = 341, // TODO add support
     // JPEG
     JPEGTables = 347,
+    JPEGProc = 512,
+    // YCbCr
+    YCbCrCoefficients = 529,
+    YCbCrSubSampling = 530, // TODO add support
+    YCbCrPositioning = 531, // TODO add support
+    ReferenceBlackWhite = 532,
+    // EXIF
+    ExifIfd = 34665,
+    DateTimeOriginal = 36867,
     // GeoTIFF
     ModelPixelScaleTag = 33550, // (SoftDesk)
     ModelTransformationTag = 34264, // (JPL Carto Group)
@@ -133,6 +145,7 @@ pub enum Tag(u16) unknown("A private or extension tag") {
     GeoKeyDirectoryTag = 34735, // (SPOT)
     GeoDoubleParamsTag = 34736, // (SPOT)
     GeoAsciiParamsTag = 34737, // (SPOT)
+    GdalMetadata = 42112, // XML metadata, such as band statistics
     GdalNodata = 42113, // Contains areas with missing data
 }
 }
@@ -190,14 +203,17 @@ pub enum CompressionMethod(u16) unknown("A custom compression method") {
     Deflate = 8,
     OldDeflate = 0x80B2,
     PackBits = 0x8005,
+    // Limited Error Raster Compression, by Esri
+    LERC = 34887,
 
     // Self-assigned by libtiff
     ZSTD = 0xC350,
+    WebP = 0xC351,
 }
 }
 
 tags! {
-pub enum PhotometricInterpretation(u16) {
+pub enum PhotometricInterpretation(u16) unknown("An unknown or custom photometric interpretation") {
     WhiteIsZero = 0,
     BlackIsZero = 1,
     RGB = 2,
@@ -206,6 +222,29 @@ pub enum PhotometricInterpretation(u16) {
     CMYK = 5,
     YCbCr = 6,
     CIELab = 8,
+    ICCLab = 9,
+}
+}
+
+tags! {
+/// The meaning of a sample beyond those of the color space, as stored in the ExtraSamples tag.
+pub enum ExtraSample(u16) {
+    /// Unspecified data, such as an additional band
+    Unspecified = 0,
+    /// Opacity that the color samples are premultiplied with
+    AssociatedAlpha = 1,
+    /// Opacity independent of the color samples
+    UnassociatedAlpha = 2,
+}
+}
+
+tags! {
+/// The logical order of bits within a byte.
+pub enum FillOrder(u16) {
+    /// Lower column values are stored in the higher-order bits of the byte. This is the default.
+    MsbToLsb = 1,
+    /// Lower column values are stored in the lower-order bits of the byte.
+    LsbToMsb = 2,
 }
 }
 
@@ -217,7 +256,7 @@ pub enum PlanarConfiguration(u16) {
 }
 
 tags! {
-pub enum Predictor(u16) {
+pub enum Predictor(u16) unknown("An unknown or custom predictor") {
     /// No changes were made to the data
     None = 1,
     /// The images' rows were processed to contain the difference of each pixel from the previous one.
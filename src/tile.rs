@@ -1,10 +1,56 @@
-use bytes::Bytes;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
-use crate::decoder::DecoderRegistry;
-use crate::error::AsyncTiffResult;
-use crate::predictor::{fix_endianness, unpredict_float, unpredict_hdiff, PredictorInfo};
-use crate::tiff::tags::{CompressionMethod, PhotometricInterpretation, Predictor};
+use bytes::{Bytes, BytesMut};
+
+use crate::decoder::{
+    is_uniform, CancellationToken, Decoder, DecoderRegistry, DecodingResult, StreamingDecompressor,
+    TileInfo, YCbCrConversion,
+};
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::jpeg_tables::merge_jpeg_tables;
+use crate::predictor::{
+    fix_endianness, needs_byte_swap, unpredict_float, unpredict_hdiff, unpredict_hdiff_bytewise,
+    PredictorInfo,
+};
+use crate::tiff::tags::{
+    CompressionMethod, FillOrder, PhotometricInterpretation, PlanarConfiguration, Predictor,
+    SampleFormat,
+};
 use crate::tiff::{TiffError, TiffUnsupportedError};
+use crate::window::f64_to_sample;
+
+/// The request that fetched a [`Tile`], for logging which reads produced which tiles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchInfo {
+    pub(crate) byte_range: Range<u64>,
+    pub(crate) duration: Duration,
+    pub(crate) batch_len: usize,
+}
+
+impl FetchInfo {
+    /// The range of bytes of the file that were read for the tile.
+    pub fn byte_range(&self) -> Range<u64> {
+        self.byte_range.clone()
+    }
+
+    /// The number of bytes that were read for the tile.
+    pub fn bytes(&self) -> u64 {
+        self.byte_range.end - self.byte_range.start
+    }
+
+    /// The time taken by the reader call that returned the tile's bytes.
+    ///
+    /// Tiles fetched together share the duration of the whole call.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The number of byte ranges requested in the same reader call, including this tile's.
+    pub fn batch_len(&self) -> usize {
+        self.batch_len
+    }
+}
 
 /// A TIFF Tile response.
 ///
@@ -24,6 +70,11 @@ pub struct Tile {
     pub(crate) compression_method: CompressionMethod,
     pub(crate) photometric_interpretation: PhotometricInterpretation,
     pub(crate) jpeg_tables: Option<Bytes>,
+    pub(crate) ycbcr_conversion: Option<YCbCrConversion>,
+    pub(crate) fill_order: FillOrder,
+    pub(crate) fax_options: u32,
+    pub(crate) truncated: Option<Truncated>,
+    pub(crate) fetch_info: Option<FetchInfo>,
 }
 
 impl Tile {
@@ -54,6 +105,22 @@ impl Tile {
         self.photometric_interpretation
     }
 
+    /// Returns `true` if this tile's byte range extends past the end of the file, so that its
+    /// compressed bytes are incomplete.
+    ///
+    /// Such tiles are only produced by
+    /// [`fetch_tiles_with_file_size`](crate::ImageFileDirectory::fetch_tiles_with_file_size).
+    pub fn is_truncated(&self) -> bool {
+        self.truncated.is_some()
+    }
+
+    /// Where and how long it took to fetch this tile, if the IFD it was fetched from records it.
+    ///
+    /// See [`ImageFileDirectory::with_fetch_info`](crate::ImageFileDirectory::with_fetch_info).
+    pub fn fetch_info(&self) -> Option<&FetchInfo> {
+        self.fetch_info.as_ref()
+    }
+
     /// Access the JPEG Tables, if any, from the IFD producing this tile.
     ///
     /// Note that [`Bytes`] is reference-counted, so it is very cheap to clone if needed.
@@ -66,6 +133,53 @@ impl Tile {
     /// Decoding is separate from fetching so that sync and async operations do not block the same
     /// runtime.
     pub fn decode(self, decoder_registry: &DecoderRegistry) -> AsyncTiffResult<Bytes> {
+        self.decode_cancellable(decoder_registry, &CancellationToken::new())
+    }
+
+    /// Decode this tile into samples typed by the image's sample format and bit depth.
+    ///
+    /// This is [`decode`](Self::decode) followed by reinterpreting the native-endian bytes.
+    /// Samples narrower than a byte, such as those of bilevel images, are expanded to one byte
+    /// each.
+    pub fn decode_typed(
+        self,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<DecodingResult> {
+        let predictor_info = self.predictor_info;
+        let (columns, _) = self.decoded_dimensions(decoder_registry)?;
+        let decoded = self.decode(decoder_registry)?;
+        DecodingResult::from_predictor_info(
+            decoded,
+            &predictor_info,
+            columns,
+            decoder_registry.limits(),
+        )
+    }
+
+    /// Decode this tile, abandoning the work early if `cancellation` is cancelled or its deadline
+    /// passes.
+    ///
+    /// The token is checked before and after decompression and between rows while reversing the
+    /// predictor. If it fires, [`AsyncTiffError::Cancelled`] is returned.
+    ///
+    /// [`AsyncTiffError::Cancelled`]: crate::error::AsyncTiffError::Cancelled
+    pub fn decode_cancellable(
+        self,
+        decoder_registry: &DecoderRegistry,
+        cancellation: &CancellationToken,
+    ) -> AsyncTiffResult<Bytes> {
+        cancellation.check()?;
+        decoder_registry
+            .limits()
+            .check_tile_pixels(self.predictor_info.padded_chunk_pixels())?;
+
+        let edge_tiles = decoder_registry.edge_tiles();
+        if let Some(Truncated::Fill(value)) = self.truncated {
+            let filled = self.fill(value)?;
+            let converted = self.convert_photometric(filled, decoder_registry)?;
+            return self.shape_edge(converted, false, edge_tiles);
+        }
+
         let decoder = decoder_registry
             .as_ref()
             .get(&self.compression_method)
@@ -73,24 +187,907 @@ impl Tile {
                 TiffUnsupportedError::UnsupportedCompressionMethod(self.compression_method),
             ))?;
 
-        let decoded_tile = decoder.decode_tile(
-            self.compressed_bytes.clone(),
-            self.photometric_interpretation,
-            self.jpeg_tables.as_deref(),
+        let stats = decoder_registry.stats();
+        let uniform_tiles = decoder_registry.uniform_tiles();
+
+        // FillOrder applies to the bytes as stored, so like libtiff, reverse the bits of the raw
+        // data before decompressing it.
+        let compressed_bytes = match self.fill_order {
+            FillOrder::LsbToMsb => reverse_bits(&self.compressed_bytes),
+            FillOrder::MsbToLsb => self.compressed_bytes.clone(),
+        };
+
+        // Decoders convert YCbCr with the default coefficients, so when the image specifies its
+        // own, ask for the raw samples instead and convert them below.
+        let photometric_interpretation = if self.ycbcr_conversion.is_some() {
+            PhotometricInterpretation::RGB
+        } else {
+            self.photometric_interpretation
+        };
+
+        // JPEG tables take part in decoding, so payloads sharing them can't be keyed on alone.
+        let cacheable = self.jpeg_tables.is_none() && self.truncated.is_none();
+        let info = self.tile_info()?;
+        let cached = if cacheable {
+            uniform_tiles.get(
+                self.compression_method,
+                photometric_interpretation,
+                &info,
+                &compressed_bytes,
+            )
+        } else {
+            None
+        };
+        let decoded_tile = if let Some(cached) = cached {
+            cached
+        } else if self.truncated.is_some() {
+            self.decode_truncated(
+                decoder.as_ref(),
+                compressed_bytes,
+                photometric_interpretation,
+            )?
+        } else {
+            let start = stats.map(|_| Instant::now());
+            let decoded_tile = decoder.decode_tile_with_info(
+                compressed_bytes.clone(),
+                photometric_interpretation,
+                self.jpeg_tables.as_deref(),
+                &info,
+            )?;
+            if let (Some(stats), Some(start)) = (stats, start) {
+                stats.record_decompression(
+                    self.compression_method,
+                    compressed_bytes.len(),
+                    decoded_tile.len(),
+                    start.elapsed(),
+                );
+            }
+            if cacheable {
+                uniform_tiles.insert(
+                    self.compression_method,
+                    photometric_interpretation,
+                    &info,
+                    &compressed_bytes,
+                    &decoded_tile,
+                );
+            }
+            decoded_tile
+        };
+        self.validate_decoded_len(decoded_tile.len())?;
+        let decoded_tile = match &self.ycbcr_conversion {
+            Some(conversion) => conversion.convert(decoded_tile),
+            None => decoded_tile,
+        };
+
+        cancellation.check()?;
+
+        let unpredicted = self.unpredict(
+            decoded_tile,
+            &self.predictor_info,
+            self.x as _,
+            self.y as _,
+            decoder_registry,
+            cancellation,
         )?;
+        let converted = self.convert_photometric(unpredicted, decoder_registry)?;
+        // Reversing the floating point predictor already drops the padding.
+        let trimmed = self.predictor == Predictor::FloatingPoint;
+        self.shape_edge(converted, trimmed, edge_tiles)
+    }
 
-        match self.predictor {
+    /// The width and height in pixels of this tile once decoded with `decoder_registry`.
+    ///
+    /// This depends on the registry's [`EdgeTiles`] setting for tiles on the right and bottom
+    /// edges of the image.
+    pub fn decoded_dimensions(
+        &self,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<(u32, u32)> {
+        let info = &self.predictor_info;
+        match decoder_registry.edge_tiles() {
+            EdgeTiles::Padded => Ok((
+                info.padded_chunk_columns(),
+                info.padded_chunk_rows(self.y as _)?,
+            )),
+            EdgeTiles::Trimmed => Ok((
+                info.chunk_columns(self.x as _)?,
+                info.chunk_rows(self.y as _)?,
+            )),
+        }
+    }
+
+    /// Decode this tile or strip a group of rows at a time, passing each group to `f` as soon as
+    /// it is decoded.
+    ///
+    /// This drives [`Tile::row_groups`], stopping at the first error returned by decoding or by
+    /// `f`. Such streams can't be split, so decompression still runs on a single thread, but `f`
+    /// can hand each group off, e.g. to a [`DecodePool`](crate::decoder::DecodePool), while the
+    /// next one is decompressed.
+    pub fn decode_row_groups<F>(
+        self,
+        decoder_registry: &DecoderRegistry,
+        rows_per_group: u32,
+        mut f: F,
+    ) -> AsyncTiffResult<()>
+    where
+        F: FnMut(RowGroup) -> AsyncTiffResult<()>,
+    {
+        for group in self.row_groups(decoder_registry, rows_per_group)? {
+            f(group?)?;
+        }
+        Ok(())
+    }
+
+    /// Decode this tile or strip lazily, as an iterator over groups of `rows_per_group` rows.
+    ///
+    /// For uncompressed, Deflate and LZW data, the chunk is decompressed incrementally as the
+    /// iterator is advanced using the built-in decompressors, so only one group of decoded rows is
+    /// held in memory at once, even for an 80,000-pixel-wide single-strip image. Other
+    /// compression methods are decoded in full using `decoder_registry` on the first call to
+    /// [`next`](Iterator::next) and then split.
+    ///
+    /// Rows are as decoded, so the rows of a padded edge tile include its padding columns. The
+    /// iterator ends after the first error. Since decoding is CPU-bound, wrap the iterator with
+    /// [`futures::stream::iter`] only when it is polled off the async runtime.
+    pub fn row_groups(
+        self,
+        decoder_registry: &DecoderRegistry,
+        rows_per_group: u32,
+    ) -> AsyncTiffResult<RowGroups<'_>> {
+        let rows = self.predictor_info.chunk_rows(self.y as _)?;
+        let source = match StreamingDecompressor::new(self.compression_method)
+            .filter(|_| self.ycbcr_conversion.is_none() && self.jpeg_tables.is_none())
+        {
+            Some(decompressor) => {
+                let input = match self.fill_order {
+                    FillOrder::LsbToMsb => reverse_bits(&self.compressed_bytes),
+                    FillOrder::MsbToLsb => self.compressed_bytes.clone(),
+                };
+                RowSource::Streaming {
+                    decompressor,
+                    input,
+                }
+            }
+            None => RowSource::Pending,
+        };
+        Ok(RowGroups {
+            tile: self,
+            decoder_registry,
+            rows_per_group: rows_per_group.max(1),
+            rows,
+            next_row: 0,
+            source,
+        })
+    }
+
+    /// Decompress as much of the incomplete compressed bytes of a truncated tile as possible,
+    /// filling the rows that couldn't be decoded with zeros.
+    ///
+    /// Only tiles are ever truncated, so the output is padded to the full tile size.
+    fn decode_truncated(
+        &self,
+        decoder: &dyn Decoder,
+        compressed_bytes: Bytes,
+        photometric_interpretation: PhotometricInterpretation,
+    ) -> AsyncTiffResult<Bytes> {
+        let required_bytes = self.predictor_info.padded_chunk_bytes();
+        let streaming = StreamingDecompressor::new(self.compression_method)
+            .filter(|_| self.ycbcr_conversion.is_none() && self.jpeg_tables.is_none());
+        let mut decoded = match streaming {
+            Some(mut decompressor) => {
+                let mut buffer = Vec::with_capacity(required_bytes);
+                let mut input = &compressed_bytes[..];
+                // The stream ends abruptly, so stop at the first error and keep what was decoded.
+                while let Ok((consumed, done)) =
+                    decompressor.decompress(input, &mut buffer, required_bytes)
+                {
+                    input = &input[consumed..];
+                    if done || consumed == 0 || buffer.len() >= required_bytes {
+                        break;
+                    }
+                }
+                buffer
+            }
+            None => Vec::from(decoder.decode_tile_with_info(
+                compressed_bytes,
+                photometric_interpretation,
+                self.jpeg_tables.as_deref(),
+                &self.tile_info()?,
+            )?),
+        };
+        if decoded.len() < required_bytes {
+            decoded.resize(required_bytes, 0);
+        }
+        Ok(decoded.into())
+    }
+
+    /// The layout of this tile as passed to decoders.
+    fn tile_info(&self) -> AsyncTiffResult<TileInfo> {
+        let info = &self.predictor_info;
+        Ok(TileInfo {
+            width: info.padded_chunk_columns(),
+            height: info.padded_chunk_rows(self.y as _)?,
+            bits_per_pixel: info.bits_per_pixel() as u32,
+            fax_options: self.fax_options,
+            endianness: info.endianness(),
+        })
+    }
+
+    /// Convert decoded samples with the converter registered for this tile's photometric
+    /// interpretation, if any.
+    fn convert_photometric(
+        &self,
+        data: Bytes,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<Bytes> {
+        let Some(converter) = decoder_registry
+            .photometric_registry()
+            .as_ref()
+            .get(&self.photometric_interpretation)
+        else {
+            return Ok(data);
+        };
+        // Each chunk of a planar image only holds one sample of every pixel.
+        let planar_configuration = self.predictor_info.planar_configuration();
+        if planar_configuration != PlanarConfiguration::Chunky {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedPlanarConfig(Some(planar_configuration)),
+            )
+            .into());
+        }
+        converter.convert(
+            data,
+            self.predictor_info.chunk_samples_per_pixel() as u16,
+            self.predictor_info.bits_per_sample(),
+            self.predictor_info.sample_format(),
+        )
+    }
+
+    /// The decoded bytes of a truncated tile in which every sample is `value`.
+    ///
+    /// Samples narrower than a byte are filled with zeros.
+    fn fill(&self, value: f64) -> AsyncTiffResult<Bytes> {
+        let len = self.predictor_info.padded_chunk_bytes();
+        let bits_per_sample = self.predictor_info.bits_per_sample() as usize;
+        if !bits_per_sample.is_multiple_of(8) {
+            return Ok(vec![0; len].into());
+        }
+        let sample = f64_to_sample(
+            value,
+            self.predictor_info.sample_format(),
+            bits_per_sample / 8,
+        )?;
+        Ok(sample
+            .iter()
+            .copied()
+            .cycle()
+            .take(len)
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    /// Pad or trim decoded rows to the layout selected by `edge_tiles`.
+    ///
+    /// `trimmed` is whether the rows of `data` already exclude padding columns.
+    fn shape_edge(
+        &self,
+        data: Bytes,
+        trimmed: bool,
+        edge_tiles: EdgeTiles,
+    ) -> AsyncTiffResult<Bytes> {
+        let info = &self.predictor_info;
+        let padded_stride = info.chunk_row_bytes();
+        let valid_stride = info.valid_row_bytes(self.x as _)?;
+        let in_stride = if trimmed { valid_stride } else { padded_stride };
+        let (out_stride, out_rows) = match edge_tiles {
+            EdgeTiles::Padded => (padded_stride, info.padded_chunk_rows(self.y as _)?),
+            EdgeTiles::Trimmed => (valid_stride, info.chunk_rows(self.y as _)?),
+        };
+        let out_len = out_stride * out_rows as usize;
+
+        if in_stride == out_stride {
+            return Ok(match data.len().cmp(&out_len) {
+                std::cmp::Ordering::Equal => data,
+                std::cmp::Ordering::Greater => data.slice(..out_len),
+                std::cmp::Ordering::Less => {
+                    let mut padded = BytesMut::from(data);
+                    padded.resize(out_len, 0);
+                    padded.freeze()
+                }
+            });
+        }
+
+        let row_len = in_stride.min(out_stride);
+        let mut output = BytesMut::zeroed(out_len);
+        for (src, dst) in data.chunks(in_stride).zip(output.chunks_mut(out_stride)) {
+            let len = row_len.min(src.len());
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+        Ok(output.freeze())
+    }
+
+    /// Check that decompression produced at least the rows of this tile within the image, so
+    /// that a short read from a corrupt tile isn't silently passed on.
+    fn validate_decoded_len(&self, actual_bytes: usize) -> AsyncTiffResult<()> {
+        let required_bytes = self.predictor_info.required_chunk_bytes(self.y as _)?;
+        if actual_bytes < required_bytes {
+            return Err(AsyncTiffError::UnexpectedCompressedData {
+                x: self.x,
+                y: self.y,
+                actual_bytes,
+                required_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reverse the predictor of decoded data described by `predictor_info`.
+    fn unpredict(
+        &self,
+        decoded_tile: Bytes,
+        predictor_info: &PredictorInfo,
+        x: u32,
+        y: u32,
+        decoder_registry: &DecoderRegistry,
+        cancellation: &CancellationToken,
+    ) -> AsyncTiffResult<Bytes> {
+        let stats = decoder_registry.stats();
+
+        // Native-endian and uniform buffers are unchanged by byte swapping, and all-zero buffers
+        // are unchanged by reversing horizontal differencing, so they can skip the predictor
+        // entirely.
+        let passthrough = match self.predictor {
+            Predictor::None => {
+                !needs_byte_swap(
+                    predictor_info.endianness(),
+                    predictor_info.bits_per_sample(),
+                ) || is_uniform(&decoded_tile)
+            }
+            Predictor::Horizontal => decoded_tile.iter().all(|b| *b == 0),
+            Predictor::FloatingPoint | Predictor::Unknown(_) => false,
+        };
+        if passthrough {
+            return Ok(decoded_tile);
+        }
+
+        let start = stats.map(|_| Instant::now());
+        let decoded_len = decoded_tile.len();
+        let result = match self.predictor {
             Predictor::None => Ok(fix_endianness(
                 decoded_tile,
-                self.predictor_info.endianness(),
-                self.predictor_info.bits_per_sample(),
+                predictor_info.endianness(),
+                predictor_info.bits_per_sample(),
             )),
-            Predictor::Horizontal => {
-                unpredict_hdiff(decoded_tile, &self.predictor_info, self.x as _)
+            Predictor::Horizontal
+                if decoder_registry.lenient_float_predictor()
+                    && predictor_info.sample_format() == SampleFormat::IEEEFP =>
+            {
+                unpredict_hdiff_bytewise(decoded_tile, predictor_info, x, cancellation)
             }
+            Predictor::Horizontal => unpredict_hdiff(decoded_tile, predictor_info, x, cancellation),
             Predictor::FloatingPoint => {
-                unpredict_float(decoded_tile, &self.predictor_info, self.x as _, self.y as _)
+                unpredict_float(decoded_tile, predictor_info, x, y, cancellation)
+            }
+            Predictor::Unknown(_) => Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedPredictor(self.predictor),
+            )
+            .into()),
+        }?;
+        if let (Some(stats), Some(start)) = (stats, start) {
+            stats.record_predictor(self.predictor, decoded_len, result.len(), start.elapsed());
+        }
+
+        Ok(result)
+    }
+}
+
+/// The compressed bytes of a tile, as fetched by
+/// [`fetch_tile_raw`](crate::ImageFileDirectory::fetch_tile_raw).
+///
+/// Unlike a [`Tile`], this carries only what is needed to re-serve the compressed payload as is,
+/// not to decode it.
+#[derive(Debug, Clone)]
+pub struct RawTile {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) compressed_bytes: Bytes,
+    pub(crate) compression_method: CompressionMethod,
+    pub(crate) jpeg_tables: Option<Bytes>,
+    pub(crate) fetch_info: Option<FetchInfo>,
+}
+
+impl RawTile {
+    /// The column index of this tile.
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    /// The row index of this tile.
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    /// Access the compressed bytes of this tile.
+    ///
+    /// Note that [`Bytes`] is reference-counted, so it is very cheap to clone if needed.
+    pub fn compressed_bytes(&self) -> &Bytes {
+        &self.compressed_bytes
+    }
+
+    /// Consume this tile, returning its compressed bytes.
+    pub fn into_compressed_bytes(self) -> Bytes {
+        self.compressed_bytes
+    }
+
+    /// Access the compression tag representing this tile.
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.compression_method
+    }
+
+    /// Access the JPEG Tables, if any, from the IFD producing this tile.
+    ///
+    /// Note that [`Bytes`] is reference-counted, so it is very cheap to clone if needed.
+    pub fn jpeg_tables(&self) -> Option<&Bytes> {
+        self.jpeg_tables.as_ref()
+    }
+
+    /// Where and how long it took to fetch this tile, if the IFD it was fetched from records it.
+    ///
+    /// See [`ImageFileDirectory::with_fetch_info`](crate::ImageFileDirectory::with_fetch_info).
+    pub fn fetch_info(&self) -> Option<&FetchInfo> {
+        self.fetch_info.as_ref()
+    }
+
+    /// Whether the compressed bytes of this tile can be served as an image as is.
+    pub fn payload(&self) -> TilePayload {
+        TilePayload::new(self.compression_method, self.jpeg_tables.as_deref())
+    }
+
+    /// The compressed bytes of this tile as a complete image stream of the MIME type of its
+    /// [`payload`](Self::payload), merging in the shared JPEGTables if needed.
+    ///
+    /// Fails if the tile holds compressed samples rather than an image stream.
+    pub fn image_bytes(&self) -> AsyncTiffResult<Bytes> {
+        match (self.payload(), &self.jpeg_tables) {
+            (TilePayload::JpegWithSharedTables, Some(jpeg_tables)) => {
+                merge_jpeg_tables(jpeg_tables, &self.compressed_bytes)
             }
+            (TilePayload::Standalone(_), _) => Ok(self.compressed_bytes.clone()),
+            _ => Err(AsyncTiffError::General(format!(
+                "Tiles compressed with {:?} are not image streams",
+                self.compression_method
+            ))),
+        }
+    }
+}
+
+/// Whether the compressed payload of a tile is an image stream that can be served as is, e.g. as
+/// the body of an HTTP response, rather than decoded and re-encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TilePayload {
+    /// A complete image stream of this MIME type.
+    Standalone(&'static str),
+    /// A JPEG stream whose quantization and Huffman tables are stored in the JPEGTables of the
+    /// IFD, which must be merged in with [`merge_jpeg_tables`] to make it a complete JPEG.
+    JpegWithSharedTables,
+    /// Compressed samples rather than an image stream.
+    Samples,
+}
+
+impl TilePayload {
+    pub(crate) fn new(compression: CompressionMethod, jpeg_tables: Option<&[u8]>) -> Self {
+        match compression {
+            CompressionMethod::ModernJPEG if jpeg_tables.is_some() => Self::JpegWithSharedTables,
+            CompressionMethod::ModernJPEG => Self::Standalone("image/jpeg"),
+            CompressionMethod::WebP => Self::Standalone("image/webp"),
+            _ => Self::Samples,
+        }
+    }
+
+    /// The MIME type of the payload once it is a complete image stream, or `None` if it holds
+    /// compressed samples.
+    pub fn mime_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Standalone(mime_type) => Some(mime_type),
+            Self::JpegWithSharedTables => Some("image/jpeg"),
+            Self::Samples => None,
+        }
+    }
+
+    /// Returns `true` if the payload is a complete image stream as is.
+    pub fn is_standalone(&self) -> bool {
+        matches!(self, Self::Standalone(_))
+    }
+}
+
+/// How to handle tiles whose byte range extends past the end of the file, e.g. because an upload
+/// was truncated.
+///
+/// This is used by
+/// [`fetch_tiles_with_file_size`](crate::ImageFileDirectory::fetch_tiles_with_file_size).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncatedTiles {
+    /// Fail with [`AsyncTiffError::TruncatedTile`] before fetching anything.
+    #[default]
+    Error,
+    /// Decode as much of the tile as is present, filling the remaining rows with zeros.
+    ///
+    /// Uncompressed, Deflate and LZW tiles are decompressed up to the point where their data
+    /// ends. Tiles with other compression methods are passed to their decoder as is, which
+    /// usually fails.
+    Partial,
+    /// Decode the tile as if every sample held the IFD's [nodata
+    /// value](crate::ImageFileDirectory::nodata), or zero if it has none.
+    FillNodata,
+}
+
+/// The layout of decoded tiles on the right and bottom edges of an image.
+///
+/// Tiles on these edges extend past the image, and are stored with padding columns and rows to
+/// fill the full tile size. Select the layout with
+/// [`DecoderRegistry::with_edge_tiles`](crate::decoder::DecoderRegistry::with_edge_tiles), and
+/// look up the resulting size with [`Tile::decoded_dimensions`].
+///
+/// This applies to every compression method and predictor alike. Strips aren't padded, so the
+/// last strip of an image only ever holds the rows within the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeTiles {
+    /// Keep the padding, so that every tile decodes to the full tile width and height.
+    ///
+    /// Padding rows missing from the compressed data are filled with zeros. The contents of the
+    /// padding are otherwise unspecified.
+    #[default]
+    Padded,
+    /// Trim the padding, so that edge tiles only hold the pixels within the image.
+    Trimmed,
+}
+
+/// How a truncated tile is decoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Truncated {
+    Partial,
+    Fill(f64),
+}
+
+/// A group of consecutive decoded rows of a tile or strip.
+///
+/// This is produced by [`Tile::row_groups`] and [`Tile::decode_row_groups`].
+#[derive(Debug, Clone)]
+pub struct RowGroup {
+    row: u32,
+    num_rows: u32,
+    data: Bytes,
+}
+
+impl RowGroup {
+    /// The index of the first row of this group within its tile or strip.
+    pub fn row(&self) -> u32 {
+        self.row
+    }
+
+    /// The number of rows in this group.
+    pub fn num_rows(&self) -> u32 {
+        self.num_rows
+    }
+
+    /// The decoded bytes of these rows.
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Consume this group, returning its decoded bytes.
+    pub fn into_data(self) -> Bytes {
+        self.data
+    }
+}
+
+/// An iterator decoding a tile or strip a group of rows at a time.
+///
+/// This is returned by [`Tile::row_groups`].
+pub struct RowGroups<'a> {
+    tile: Tile,
+    decoder_registry: &'a DecoderRegistry,
+    rows_per_group: u32,
+    rows: u32,
+    next_row: u32,
+    source: RowSource,
+}
+
+enum RowSource {
+    /// Compressed data not yet decompressed, consumed from the front as groups are produced.
+    Streaming {
+        decompressor: StreamingDecompressor,
+        input: Bytes,
+    },
+    /// A compression method without a streaming decompressor, not yet decoded.
+    Pending,
+    /// The fully decoded chunk, split into groups as they are produced.
+    Decoded(Bytes),
+}
+
+impl RowGroups<'_> {
+    /// The total number of rows this iterator produces.
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    fn next_group(&mut self, row: u32, num_rows: u32) -> AsyncTiffResult<RowGroup> {
+        if let RowSource::Pending = self.source {
+            let tile = Tile {
+                compressed_bytes: self.tile.compressed_bytes.clone(),
+                jpeg_tables: self.tile.jpeg_tables.clone(),
+                fetch_info: self.tile.fetch_info.clone(),
+                ..self.tile
+            };
+            self.source = RowSource::Decoded(tile.decode(self.decoder_registry)?);
+        }
+
+        let data = match &mut self.source {
+            RowSource::Decoded(data) => {
+                let row_bytes = data.len() / self.rows.max(1) as usize;
+                let start = row as usize * row_bytes;
+                data.slice(start..start + num_rows as usize * row_bytes)
+            }
+            RowSource::Streaming {
+                decompressor,
+                input,
+            } => {
+                let row_bytes = self.tile.predictor_info.chunk_row_bytes();
+                let limit = num_rows as usize * row_bytes;
+                let mut buffer = Vec::with_capacity(limit);
+                while buffer.len() < limit {
+                    let before = buffer.len();
+                    let (consumed, done) = decompressor.decompress(input, &mut buffer, limit)?;
+                    bytes::Buf::advance(input, consumed);
+                    if buffer.len() < limit && (done || (consumed == 0 && buffer.len() == before)) {
+                        return Err(AsyncTiffError::General(format!(
+                            "Compressed data ended after {} of {} rows",
+                            row as usize + buffer.len() / row_bytes,
+                            self.rows
+                        )));
+                    }
+                }
+
+                let predictor_info = self.tile.predictor_info.for_rows(num_rows);
+                let unpredicted = self.tile.unpredict(
+                    buffer.into(),
+                    &predictor_info,
+                    0,
+                    0,
+                    self.decoder_registry,
+                    &CancellationToken::new(),
+                )?;
+                self.tile
+                    .convert_photometric(unpredicted, self.decoder_registry)?
+            }
+            RowSource::Pending => unreachable!(),
+        };
+        Ok(RowGroup {
+            row,
+            num_rows,
+            data,
+        })
+    }
+}
+
+impl Iterator for RowGroups<'_> {
+    type Item = AsyncTiffResult<RowGroup>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.next_row;
+        if row >= self.rows {
+            return None;
+        }
+        let num_rows = self.rows_per_group.min(self.rows - row);
+        let group = self.next_group(row, num_rows);
+        // Stop after an error, as the decompressor state can't be resumed.
+        self.next_row = if group.is_ok() {
+            row + num_rows
+        } else {
+            self.rows
+        };
+        Some(group)
+    }
+}
+
+/// Reverse the order of the bits within each byte.
+fn reverse_bits(data: &[u8]) -> Bytes {
+    data.iter()
+        .map(|b| b.reverse_bits())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::decoder::{PhotometricConverter, PhotometricRegistry};
+    use crate::tiff::tags::Tag;
+    use crate::tiff::Value;
+    use crate::IfdBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_fill_order() {
+        // A 16x1 bilevel image, stored least significant bit first
+        let ifd = IfdBuilder::new(16, 1)
+            .with_data_type(SampleFormat::Uint, 1)
+            .with_tag(Tag::FillOrder, Value::Short(2))
+            .build()
+            .unwrap();
+        let bytes = Bytes::from_static(&[0b0000_0001, 0b1100_0000]);
+        let tile = ifd.new_tile(0, 0, 0, bytes).unwrap();
+        let decoded = tile.decode(&Default::default()).unwrap();
+        assert_eq!(decoded.as_ref(), [0b1000_0000, 0b0000_0011]);
+    }
+
+    #[test]
+    fn test_decode_uniform_fax() {
+        // Every row of an all-white image is coded as a single vertical mode code, so the same
+        // payload is a uniform tile of any width and must not be served from the cache for both.
+        let registry = DecoderRegistry::default();
+        for width in [8, 16] {
+            let ifd = IfdBuilder::new(width, 1)
+                .with_data_type(SampleFormat::Uint, 1)
+                .with_photometric_interpretation(PhotometricInterpretation::WhiteIsZero)
+                .with_compression(CompressionMethod::Fax4)
+                .build()
+                .unwrap();
+            let tile = ifd.new_tile(0, 0, 0, Bytes::from_static(&[0xff])).unwrap();
+            let decoded = tile.decode(&registry).unwrap();
+            assert_eq!(decoded.len(), width as usize / 8);
+            assert!(is_uniform(&decoded));
+        }
+    }
+
+    #[test]
+    fn test_decode_uncompressed_zero_copy() {
+        let ifd = IfdBuilder::new(4, 4).with_tiling(16, 16).build().unwrap();
+        let file = Bytes::from((0..=255).collect::<Vec<u8>>());
+        let tile = ifd.new_tile(0, 0, 0, file.clone()).unwrap();
+        let DecodingResult::U8(samples) = tile.decode_typed(&Default::default()).unwrap() else {
+            panic!("Expected u8 samples");
+        };
+        // The samples are the fetched bytes themselves
+        assert_eq!(samples.as_ptr(), file.as_ptr());
+        assert_eq!(samples, file);
+    }
+
+    #[test]
+    fn test_decode_short_tile() {
+        // A 4x4 8-bit strip with only 10 of its 16 bytes present
+        let ifd = IfdBuilder::new(4, 4).build().unwrap();
+        let tile = ifd.new_tile(0, 0, 0, Bytes::from_static(&[1; 10])).unwrap();
+        let err = tile.decode(&Default::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            AsyncTiffError::UnexpectedCompressedData {
+                x: 0,
+                y: 0,
+                actual_bytes: 10,
+                required_bytes: 16,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decode_photometric_converter() {
+        #[derive(Debug)]
+        struct Invert;
+
+        impl PhotometricConverter for Invert {
+            fn convert(
+                &self,
+                buffer: Bytes,
+                _samples_per_pixel: u16,
+                _bits_per_sample: u16,
+                _sample_format: SampleFormat,
+            ) -> AsyncTiffResult<Bytes> {
+                Ok(buffer.iter().map(|b| !b).collect::<Vec<_>>().into())
+            }
+        }
+
+        // A 2x1 8-bit WhiteIsZero image
+        let tile = |planar_configuration: PlanarConfiguration| {
+            let ifd = IfdBuilder::new(2, 1)
+                .with_photometric_interpretation(PhotometricInterpretation::WhiteIsZero)
+                .with_planar_configuration(planar_configuration)
+                .build()
+                .unwrap();
+            ifd.new_tile(0, 0, 0, Bytes::from_static(&[0, 200]))
+                .unwrap()
+        };
+
+        let registry = DecoderRegistry::default();
+        assert_eq!(
+            tile(PlanarConfiguration::Chunky)
+                .decode(&registry)
+                .unwrap()
+                .as_ref(),
+            [0, 200]
+        );
+
+        let mut photometric = PhotometricRegistry::new();
+        photometric
+            .as_mut()
+            .insert(PhotometricInterpretation::WhiteIsZero, Box::new(Invert));
+        let registry = DecoderRegistry::default().with_photometric_registry(photometric);
+        assert_eq!(
+            tile(PlanarConfiguration::Chunky)
+                .decode(&registry)
+                .unwrap()
+                .as_ref(),
+            [255, 55]
+        );
+        assert!(tile(PlanarConfiguration::Planar)
+            .decode(&registry)
+            .unwrap_err()
+            .is_unsupported());
+    }
+
+    #[test]
+    fn test_decode_edge_tiles() {
+        // A 18x18 image of 16x16 tiles, whose bottom-right tile holds 2x2 pixels
+        let tile = |bits: u16, predictor: Predictor| {
+            let sample_format = if bits == 8 {
+                SampleFormat::Uint
+            } else {
+                SampleFormat::IEEEFP
+            };
+            let ifd = IfdBuilder::new(18, 18)
+                .with_data_type(sample_format, bits)
+                .with_tiling(16, 16)
+                .with_predictor(predictor)
+                .build()
+                .unwrap();
+            let bytes = (0..256 * bits as usize / 8)
+                .map(|i| i as u8)
+                .collect::<Vec<_>>();
+            ifd.new_tile(1, 1, 0, bytes.into()).unwrap()
+        };
+        let padded = DecoderRegistry::default();
+        let trimmed = DecoderRegistry::default().with_edge_tiles(EdgeTiles::Trimmed);
+
+        let u8_tile = tile(8, Predictor::None);
+        assert_eq!(u8_tile.decoded_dimensions(&padded).unwrap(), (16, 16));
+        assert_eq!(u8_tile.decoded_dimensions(&trimmed).unwrap(), (2, 2));
+        assert_eq!(
+            tile(8, Predictor::None).decode(&padded).unwrap().as_ref(),
+            (0..=255).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            tile(8, Predictor::None).decode(&trimmed).unwrap().as_ref(),
+            [0, 1, 16, 17]
+        );
+
+        // The floating point predictor used to always drop the padding
+        let f32_padded = tile(32, Predictor::FloatingPoint).decode(&padded).unwrap();
+        assert_eq!(f32_padded.len(), 16 * 16 * 4);
+        let f32_trimmed = tile(32, Predictor::FloatingPoint).decode(&trimmed).unwrap();
+        assert_eq!(f32_trimmed.len(), 2 * 2 * 4);
+        assert_eq!(f32_trimmed[..8], f32_padded[..8]);
+        assert_eq!(f32_trimmed[8..], f32_padded[64..72]);
+    }
+
+    #[test]
+    fn test_tile_payload() {
+        let tables = Some(&[0xFF, 0xD8, 0xFF, 0xD9][..]);
+        let payload = TilePayload::new(CompressionMethod::ModernJPEG, tables);
+        assert_eq!(payload, TilePayload::JpegWithSharedTables);
+        assert_eq!(payload.mime_type(), Some("image/jpeg"));
+        assert!(!payload.is_standalone());
+
+        let payload = TilePayload::new(CompressionMethod::ModernJPEG, None);
+        assert_eq!(payload, TilePayload::Standalone("image/jpeg"));
+        assert!(payload.is_standalone());
+        let payload = TilePayload::new(CompressionMethod::WebP, None);
+        assert_eq!(payload.mime_type(), Some("image/webp"));
+
+        for compression in [CompressionMethod::Deflate, CompressionMethod::JPEG] {
+            let payload = TilePayload::new(compression, tables);
+            assert_eq!(payload, TilePayload::Samples);
+            assert_eq!(payload.mime_type(), None);
         }
     }
 }
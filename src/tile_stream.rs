@@ -0,0 +1,61 @@
+//! Streaming every tile of an image with bounded concurrency.
+
+use futures::stream::{self, Stream};
+use futures::StreamExt;
+
+use crate::error::AsyncTiffResult;
+use crate::ifd::ImageFileDirectory;
+use crate::reader::AsyncFileReader;
+use crate::tile::Tile;
+
+/// The number of tiles fetched at once by [`ImageFileDirectory::tile_stream`].
+const DEFAULT_CONCURRENCY: usize = 8;
+
+impl ImageFileDirectory {
+    /// Stream every tile of this IFD, row by row, fetching up to 8 tiles at once.
+    ///
+    /// The strips of stripped images are streamed as image-width tiles, as by
+    /// [`fetch_strip`](Self::fetch_strip). See
+    /// [`tile_stream_with_concurrency`](Self::tile_stream_with_concurrency).
+    pub fn tile_stream<'a>(
+        &'a self,
+        reader: &'a dyn AsyncFileReader,
+    ) -> impl Stream<Item = AsyncTiffResult<Tile>> + 'a {
+        self.tile_stream_with_concurrency(reader, DEFAULT_CONCURRENCY)
+    }
+
+    /// Stream every tile of this IFD, row by row, fetching up to `concurrency` tiles at once.
+    ///
+    /// Tiles are yielded in order as they are fetched, and at most `concurrency` fetched tiles
+    /// are held before the consumer polls for them, so that all tiles of a large image can be
+    /// processed in bounded memory. A failed fetch is yielded as an error item, and the stream
+    /// continues with the next tile.
+    pub fn tile_stream_with_concurrency<'a>(
+        &'a self,
+        reader: &'a dyn AsyncFileReader,
+        concurrency: usize,
+    ) -> impl Stream<Item = AsyncTiffResult<Tile>> + 'a {
+        let (x_count, count) = match (self.tile_count(), self.strip_count()) {
+            (Some((x_count, y_count)), _) => (Some(x_count), x_count * y_count),
+            (None, Some(strip_count)) => (None, strip_count),
+            (None, None) => {
+                return stream::iter([Err(self.not_tiled_error())]).left_stream();
+            }
+        };
+        stream::iter(0..count)
+            .map(move |index| async move {
+                match x_count {
+                    Some(x_count) => {
+                        // Load whole pages of lazy tile offsets, rather than the entries of
+                        // each tile, as all of them are needed.
+                        self.load_lazy_tile_offsets([index], reader).await?;
+                        self.fetch_tile(index % x_count, index / x_count, reader)
+                            .await
+                    }
+                    None => self.fetch_strip(index, reader).await,
+                }
+            })
+            .buffered(concurrency.max(1))
+            .right_stream()
+    }
+}
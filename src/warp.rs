@@ -0,0 +1,406 @@
+//! Reprojection of GeoTIFF data onto a target grid while reading.
+//!
+//! Callers provide a [`CoordinateTransform`] from the target CRS to the file's CRS, and
+//! [`ImageFileDirectory::warp`] uses it together with the file's affine transform to resample the
+//! image. [`ProjTransform`] transforms between CRSs identified by EPSG codes using `proj4rs`, and
+//! any closure can be used for other transforms.
+
+use proj4rs::Proj;
+
+use crate::decoder::DecoderRegistry;
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::geo::AffineTransform;
+use crate::ifd::ImageFileDirectory;
+use crate::reader::AsyncFileReader;
+use crate::tiff::tags::SampleFormat;
+use crate::window::{f64_to_sample, sample_to_f64, Window, WindowData};
+
+/// Convert coordinates from the target CRS to the CRS of the file being warped.
+pub trait CoordinateTransform {
+    /// Transform the point `(x, y)`, returning `None` if it has no equivalent in the file's CRS.
+    fn transform(&self, x: f64, y: f64) -> Option<(f64, f64)>;
+}
+
+impl<F> CoordinateTransform for F
+where
+    F: Fn(f64, f64) -> Option<(f64, f64)>,
+{
+    fn transform(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        self(x, y)
+    }
+}
+
+/// A [`CoordinateTransform`] between two coordinate reference systems identified by EPSG codes,
+/// backed by `proj4rs`.
+///
+/// Geographic coordinates are in degrees, and projected coordinates in the units of their CRS.
+#[derive(Debug)]
+pub struct ProjTransform {
+    target: Proj,
+    source: Proj,
+}
+
+impl ProjTransform {
+    /// Transform from the CRS with EPSG code `target_epsg` to the CRS with EPSG code
+    /// `source_epsg`.
+    pub fn new(target_epsg: u16, source_epsg: u16) -> AsyncTiffResult<Self> {
+        let proj = |epsg| {
+            Proj::from_epsg_code(epsg).map_err(|err| {
+                AsyncTiffError::General(format!("Unsupported CRS EPSG:{epsg}: {err}"))
+            })
+        };
+        Ok(Self {
+            target: proj(target_epsg)?,
+            source: proj(source_epsg)?,
+        })
+    }
+
+    /// Transform from the CRS with EPSG code `target_epsg` to the CRS of `ifd`, as given by its
+    /// [GeoKeys](ImageFileDirectory::geo_key_directory).
+    pub fn to_ifd(target_epsg: u16, ifd: &ImageFileDirectory) -> AsyncTiffResult<Self> {
+        let source_epsg = ifd
+            .geo_key_directory()
+            .and_then(|geo_keys| geo_keys.epsg_code())
+            .ok_or(AsyncTiffError::General(
+                "Cannot transform to an image without an EPSG code".to_string(),
+            ))?;
+        Self::new(target_epsg, source_epsg)
+    }
+}
+
+impl CoordinateTransform for ProjTransform {
+    fn transform(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        // proj4rs takes and returns geographic coordinates in radians
+        let mut point = if self.target.is_latlong() {
+            (x.to_radians(), y.to_radians())
+        } else {
+            (x, y)
+        };
+        proj4rs::transform::transform(&self.target, &self.source, &mut point).ok()?;
+        if self.source.is_latlong() {
+            point = (point.0.to_degrees(), point.1.to_degrees());
+        }
+        (point.0.is_finite() && point.1.is_finite()).then_some(point)
+    }
+}
+
+/// The resampling method used when warping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Resampling {
+    /// Use the value of the nearest source pixel.
+    #[default]
+    Nearest,
+    /// Interpolate linearly between the four nearest source pixels.
+    Bilinear,
+}
+
+/// A north-up grid of pixels in the target CRS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetGrid {
+    transform: AffineTransform,
+    width: u32,
+    height: u32,
+}
+
+impl TargetGrid {
+    /// Construct a grid of `width` by `height` pixels whose affine transform maps pixel
+    /// positions to target CRS coordinates.
+    pub fn new(transform: AffineTransform, width: u32, height: u32) -> Self {
+        Self {
+            transform,
+            width,
+            height,
+        }
+    }
+
+    /// The affine transform of the grid.
+    pub fn transform(&self) -> &AffineTransform {
+        &self.transform
+    }
+
+    /// The number of columns in the grid.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The number of rows in the grid.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl ImageFileDirectory {
+    /// Read this image resampled onto `grid`.
+    ///
+    /// Each target pixel center is mapped through `coordinate_transform` into the file's CRS and
+    /// then into source pixel space using the file's affine transform. Only the source window
+    /// covering the grid is fetched. Target pixels that fall outside the source image are set to
+    /// `nodata`.
+    ///
+    /// The returned data is interleaved, its window spans the whole grid, and its
+    /// [transform](WindowData::transform) is the grid's transform.
+    pub async fn warp(
+        &self,
+        grid: &TargetGrid,
+        coordinate_transform: &dyn CoordinateTransform,
+        resampling: Resampling,
+        nodata: f64,
+        reader: &dyn AsyncFileReader,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<WindowData> {
+        let to_pixel = AffineTransform::from_ifd(self)
+            .and_then(|transform| transform.inverse())
+            .ok_or(AsyncTiffError::General(
+                "Cannot warp an image without a valid affine transform".to_string(),
+            ))?;
+        let positions = source_positions(grid, coordinate_transform, &to_pixel);
+        let window = source_window(
+            &positions,
+            resampling,
+            self.image_width(),
+            self.image_height(),
+        );
+        let source = match window {
+            Some(window) => Some(self.read_window(window, reader, decoder_registry).await?),
+            None => None,
+        };
+        resample(grid, &positions, source.as_ref(), resampling, nodata)
+    }
+}
+
+/// The source pixel position of each target pixel center, in row-major order.
+fn source_positions(
+    grid: &TargetGrid,
+    coordinate_transform: &dyn CoordinateTransform,
+    to_pixel: &AffineTransform,
+) -> Vec<Option<(f64, f64)>> {
+    let mut positions = Vec::with_capacity(grid.width as usize * grid.height as usize);
+    for row in 0..grid.height {
+        for col in 0..grid.width {
+            let (x, y) = grid.transform.apply(col as f64 + 0.5, row as f64 + 0.5);
+            positions.push(
+                coordinate_transform
+                    .transform(x, y)
+                    .map(|(x, y)| to_pixel.apply(x, y)),
+            );
+        }
+    }
+    positions
+}
+
+/// The smallest window of the source image containing every pixel needed for `positions`.
+fn source_window(
+    positions: &[Option<(f64, f64)>],
+    resampling: Resampling,
+    image_width: u32,
+    image_height: u32,
+) -> Option<Window> {
+    // Bilinear sampling also touches the neighbouring pixel on each side.
+    let margin = match resampling {
+        Resampling::Nearest => 0.0,
+        Resampling::Bilinear => 1.0,
+    };
+    let (mut col_min, mut row_min) = (f64::INFINITY, f64::INFINITY);
+    let (mut col_max, mut row_max) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for (col, row) in positions.iter().flatten() {
+        if *col < 0.0 || *row < 0.0 || *col >= image_width as f64 || *row >= image_height as f64 {
+            continue;
+        }
+        col_min = col_min.min(*col - margin);
+        row_min = row_min.min(*row - margin);
+        col_max = col_max.max(*col + margin);
+        row_max = row_max.max(*row + margin);
+    }
+    if col_min > col_max {
+        return None;
+    }
+    let col_off = col_min.floor().max(0.0) as u32;
+    let row_off = row_min.floor().max(0.0) as u32;
+    let col_end = (col_max.floor() as u32 + 1).min(image_width);
+    let row_end = (row_max.floor() as u32 + 1).min(image_height);
+    Some(Window::new(
+        col_off,
+        row_off,
+        col_end - col_off,
+        row_end - row_off,
+    ))
+}
+
+/// Sample `source` at each of `positions`.
+fn resample(
+    grid: &TargetGrid,
+    positions: &[Option<(f64, f64)>],
+    source: Option<&WindowData>,
+    resampling: Resampling,
+    nodata: f64,
+) -> AsyncTiffResult<WindowData> {
+    let output_window = Window::new(0, 0, grid.width, grid.height);
+    let Some(source) = source else {
+        // Nothing in the grid overlaps the image, so we don't know its sample type.
+        return Err(AsyncTiffError::General(
+            "Target grid does not overlap the image".to_string(),
+        ));
+    };
+
+    let samples_per_pixel = source.samples_per_pixel() as usize;
+    let bytes_per_sample = source.bytes_per_sample();
+    let sample_format = source.sample_format();
+    let nodata_sample = f64_to_sample(nodata, sample_format, bytes_per_sample)?;
+    let window = source.window();
+    let bytes_per_pixel = source.bytes_per_pixel();
+
+    let read = |col: u32, row: u32, sample: usize| -> f64 {
+        let idx = ((row - window.row_off()) as usize * window.width() as usize
+            + (col - window.col_off()) as usize)
+            * bytes_per_pixel
+            + sample * bytes_per_sample;
+        sample_to_f64(&source.data()[idx..idx + bytes_per_sample], sample_format)
+            .unwrap_or(f64::NAN)
+    };
+    let contains = |col: f64, row: f64| {
+        col >= window.col_off() as f64
+            && row >= window.row_off() as f64
+            && col < (window.col_off() + window.width()) as f64
+            && row < (window.row_off() + window.height()) as f64
+    };
+
+    let mut data = Vec::with_capacity(positions.len() * bytes_per_pixel);
+    for position in positions {
+        let position = position.filter(|(col, row)| contains(*col, *row));
+        let Some((col, row)) = position else {
+            for _ in 0..samples_per_pixel {
+                data.extend_from_slice(&nodata_sample);
+            }
+            continue;
+        };
+        match resampling {
+            Resampling::Nearest => {
+                let idx = ((row as u32 - window.row_off()) as usize * window.width() as usize
+                    + (col as u32 - window.col_off()) as usize)
+                    * bytes_per_pixel;
+                data.extend_from_slice(&source.data()[idx..idx + bytes_per_pixel]);
+            }
+            Resampling::Bilinear => {
+                // Interpolate between pixel centers, clamping at the edges of the window.
+                let clamp_col = |c: f64| {
+                    (c.max(window.col_off() as f64) as u32)
+                        .min(window.col_off() + window.width() - 1)
+                };
+                let clamp_row = |r: f64| {
+                    (r.max(window.row_off() as f64) as u32)
+                        .min(window.row_off() + window.height() - 1)
+                };
+                let (x, y) = (col - 0.5, row - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (c0, c1) = (clamp_col(x0), clamp_col(x0 + 1.0));
+                let (r0, r1) = (clamp_row(y0), clamp_row(y0 + 1.0));
+                for sample in 0..samples_per_pixel {
+                    let top = read(c0, r0, sample) * (1.0 - fx) + read(c1, r0, sample) * fx;
+                    let bottom = read(c0, r1, sample) * (1.0 - fx) + read(c1, r1, sample) * fx;
+                    let mut value = top * (1.0 - fy) + bottom * fy;
+                    if sample_format != SampleFormat::IEEEFP {
+                        value = value.round();
+                    }
+                    data.extend_from_slice(&f64_to_sample(value, sample_format, bytes_per_sample)?);
+                }
+            }
+        }
+    }
+
+    Ok(WindowData::new_interleaved(
+        output_window,
+        Some(grid.transform),
+        source.samples_per_pixel(),
+        bytes_per_sample,
+        sample_format,
+        data,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A 4x4 single-band source with values `row * 10 + col`.
+    fn source() -> WindowData {
+        let data = (0..4)
+            .flat_map(|row| (0..4).map(move |col| row * 10 + col))
+            .collect();
+        WindowData::new_interleaved(
+            Window::new(0, 0, 4, 4),
+            None,
+            1,
+            1,
+            SampleFormat::Uint,
+            data,
+        )
+    }
+
+    #[test]
+    fn test_proj_transform() {
+        // WGS 84 to UTM zone 33N, whose central meridian is 15°E
+        let transform = ProjTransform::new(4326, 32633).unwrap();
+        let (x, y) = transform.transform(15.0, 0.0).unwrap();
+        assert!((x - 500000.0).abs() < 1e-6, "{x}");
+        assert!(y.abs() < 1e-6, "{y}");
+
+        let inverse = ProjTransform::new(32633, 4326).unwrap();
+        let (lon, lat) = inverse.transform(500000.0, 1000000.0).unwrap();
+        assert!((lon - 15.0).abs() < 1e-9, "{lon}");
+        assert!((lat - 9.04).abs() < 0.01, "{lat}");
+
+        assert!(ProjTransform::new(4326, 1).is_err());
+        let ifd = crate::IfdBuilder::new(16, 16).build().unwrap();
+        assert!(ProjTransform::to_ifd(4326, &ifd).is_err());
+    }
+
+    #[test]
+    fn test_affine_inverse() {
+        let transform = AffineTransform::new(10.0, 0.0, 1000.0, 0.0, -10.0, 5000.0);
+        let inverse = transform.inverse().unwrap();
+        let (x, y) = transform.apply(3.0, 4.0);
+        assert_eq!(inverse.apply(x, y), (3.0, 4.0));
+    }
+
+    #[test]
+    fn test_resample_nearest() {
+        // Identity CRS transform, and a grid at half the source resolution offset by one pixel.
+        let to_pixel = AffineTransform::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0);
+        let grid = TargetGrid::new(AffineTransform::new(2.0, 0.0, 1.0, 0.0, 2.0, 0.0), 2, 2);
+        let identity = |x: f64, y: f64| Some((x, y));
+        let positions = source_positions(&grid, &identity, &to_pixel);
+        assert_eq!(
+            source_window(&positions, Resampling::Nearest, 4, 4),
+            Some(Window::new(2, 1, 1, 3))
+        );
+
+        let warped = resample(
+            &grid,
+            &positions,
+            Some(&source()),
+            Resampling::Nearest,
+            255.0,
+        );
+        // The right column falls outside the source image.
+        assert_eq!(warped.unwrap().data(), [12, 255, 32, 255]);
+    }
+
+    #[test]
+    fn test_resample_bilinear() {
+        let to_pixel = AffineTransform::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0);
+        // A single pixel centered on the corner shared by source pixels (1, 1) to (2, 2).
+        let grid = TargetGrid::new(AffineTransform::new(1.0, 0.0, 1.5, 0.0, 1.0, 1.5), 1, 1);
+        let identity = |x: f64, y: f64| Some((x, y));
+        let positions = source_positions(&grid, &identity, &to_pixel);
+        let warped = resample(
+            &grid,
+            &positions,
+            Some(&source()),
+            Resampling::Bilinear,
+            0.0,
+        );
+        // The mean of 11, 12, 21, 22
+        assert_eq!(warped.unwrap().data(), [17]);
+    }
+}
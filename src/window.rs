@@ -0,0 +1,1161 @@
+//! Reading rectangular pixel windows that may span several tiles.
+
+use std::ops::Range;
+
+use bytes::Bytes;
+
+use crate::decoder::{DecoderRegistry, DecodingResult, F16};
+use crate::error::{AsyncTiffError, AsyncTiffResult};
+use crate::geo::AffineTransform;
+use crate::ifd::ImageFileDirectory;
+use crate::reader::AsyncFileReader;
+use crate::tiff::tags::{PlanarConfiguration, SampleFormat};
+use crate::tile::EdgeTiles;
+
+/// A rectangular region of an image, in pixel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    col_off: u32,
+    row_off: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Window {
+    /// Construct a new window from its top-left corner and its size in pixels.
+    pub fn new(col_off: u32, row_off: u32, width: u32, height: u32) -> Self {
+        Self {
+            col_off,
+            row_off,
+            width,
+            height,
+        }
+    }
+
+    /// The column of the window's left edge.
+    pub fn col_off(&self) -> u32 {
+        self.col_off
+    }
+
+    /// The row of the window's top edge.
+    pub fn row_off(&self) -> u32 {
+        self.row_off
+    }
+
+    /// The number of columns in the window.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The number of rows in the window.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The column after the window's right edge.
+    ///
+    /// This saturates, and is only exact for windows that passed
+    /// [`check_window_bounds`](ImageFileDirectory::check_window_bounds).
+    pub(crate) fn col_end(&self) -> u32 {
+        self.col_off.saturating_add(self.width)
+    }
+
+    /// The row after the window's bottom edge, saturating as [`col_end`](Self::col_end) does.
+    pub(crate) fn row_end(&self) -> u32 {
+        self.row_off.saturating_add(self.height)
+    }
+
+    fn num_pixels(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+}
+
+/// How the samples of [`WindowData`] are arranged in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleLayout {
+    /// The samples of each pixel are stored together, in `(rows, cols, bands)` order.
+    #[default]
+    Interleaved,
+    /// Each band is stored contiguously, in `(bands, rows, cols)` order.
+    ///
+    /// This is what most numeric pipelines expect, and is available regardless of the file's
+    /// [`PlanarConfiguration`].
+    Planar,
+}
+
+/// Which corner of the window the first row of [`WindowData`] starts at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RowOrigin {
+    /// Rows run from the top of the window to the bottom, as stored in the file.
+    #[default]
+    TopLeft,
+    /// Rows run from the bottom of the window to the top.
+    BottomLeft,
+}
+
+/// Options for [`ImageFileDirectory::read_window_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct ReadWindowOptions {
+    layout: SampleLayout,
+    origin: RowOrigin,
+    include_transform: bool,
+    sequential_reads: Option<u64>,
+}
+
+impl ReadWindowOptions {
+    /// Create options with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the arrangement of samples in the returned data.
+    pub fn with_layout(mut self, layout: SampleLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// The arrangement of samples in the returned data.
+    pub fn layout(&self) -> SampleLayout {
+        self.layout
+    }
+
+    /// Set which corner the first row of the returned data starts at.
+    pub fn with_origin(mut self, origin: RowOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Which corner the first row of the returned data starts at.
+    pub fn origin(&self) -> RowOrigin {
+        self.origin
+    }
+
+    /// Set whether to compute the affine transform of the returned data.
+    ///
+    /// The transform is only available for GeoTIFFs with a pixel scale and tiepoint.
+    pub fn with_transform(mut self, include_transform: bool) -> Self {
+        self.include_transform = include_transform;
+        self
+    }
+
+    /// Whether to compute the affine transform of the returned data.
+    pub fn include_transform(&self) -> bool {
+        self.include_transform
+    }
+
+    /// Fetch the intersecting tiles with a few linear requests of at most `max_request_bytes`
+    /// each when they are stored in order, rather than with a request per tile.
+    ///
+    /// This suits reading whole images or large windows of files whose tiles are stored in
+    /// row-major order, such as Cloud Optimized GeoTIFFs (see
+    /// [`ImageFileDirectory::has_sequential_chunks`]), from readers that don't coalesce requests
+    /// themselves. Gaps of up to 64 KiB between tiles, such as GDAL's tile leaders and
+    /// interleaved mask tiles, are fetched and discarded; larger gaps start a new request. Tiles
+    /// that aren't stored in order are fetched one by one as usual.
+    pub fn with_sequential_reads(mut self, max_request_bytes: u64) -> Self {
+        self.sequential_reads = Some(max_request_bytes);
+        self
+    }
+
+    /// The maximum size of the linear requests of sequential reads, if enabled.
+    pub fn sequential_reads(&self) -> Option<u64> {
+        self.sequential_reads
+    }
+}
+
+/// Pixel data read from a [`Window`] of an image.
+///
+/// Samples are stored in row-major order, arranged according to [`layout`](Self::layout), and in
+/// native byte order. Samples narrower than a byte, such as those of 1-bit masks, are expanded to
+/// one byte each.
+#[derive(Debug, Clone)]
+pub struct WindowData {
+    window: Window,
+    layout: SampleLayout,
+    origin: RowOrigin,
+    transform: Option<AffineTransform>,
+    samples_per_pixel: u16,
+    bytes_per_sample: usize,
+    sample_format: SampleFormat,
+    data: Vec<u8>,
+}
+
+impl WindowData {
+    /// Construct interleaved, top-down data covering `window`.
+    #[cfg(feature = "warp")]
+    pub(crate) fn new_interleaved(
+        window: Window,
+        transform: Option<AffineTransform>,
+        samples_per_pixel: u16,
+        bytes_per_sample: usize,
+        sample_format: SampleFormat,
+        data: Vec<u8>,
+    ) -> Self {
+        Self {
+            window,
+            layout: SampleLayout::Interleaved,
+            origin: RowOrigin::TopLeft,
+            transform,
+            samples_per_pixel,
+            bytes_per_sample,
+            sample_format,
+            data,
+        }
+    }
+
+    /// The window this data was read from.
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// How samples are arranged in [`data`](Self::data).
+    pub fn layout(&self) -> SampleLayout {
+        self.layout
+    }
+
+    /// Which corner of the window the first row of [`data`](Self::data) starts at.
+    pub fn origin(&self) -> RowOrigin {
+        self.origin
+    }
+
+    /// The affine transform mapping (column, row) positions in [`data`](Self::data) to model
+    /// coordinates, if it was requested with [`ReadWindowOptions::with_transform`].
+    ///
+    /// This accounts for both the window offset and the row [origin](Self::origin).
+    pub fn transform(&self) -> Option<&AffineTransform> {
+        self.transform.as_ref()
+    }
+
+    /// The number of samples stored for each pixel.
+    pub fn samples_per_pixel(&self) -> u16 {
+        self.samples_per_pixel
+    }
+
+    /// The number of bytes used to store each sample.
+    pub fn bytes_per_sample(&self) -> usize {
+        self.bytes_per_sample
+    }
+
+    /// The format of each sample.
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    /// Access the raw pixel bytes.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consume this and return the raw pixel bytes.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Interpret the pixel bytes as samples of their format and bit depth, e.g.
+    /// [`DecodingResult::F16`] for 16-bit floating point data.
+    pub fn to_typed(&self) -> AsyncTiffResult<DecodingResult> {
+        DecodingResult::from_bytes(
+            &self.data,
+            self.sample_format,
+            (self.bytes_per_sample * 8) as u16,
+        )
+    }
+
+    /// Access the samples of band `band`, if this data uses [`SampleLayout::Planar`].
+    pub fn band(&self, band: usize) -> Option<&[u8]> {
+        if self.layout != SampleLayout::Planar || band >= self.samples_per_pixel as usize {
+            return None;
+        }
+        let band_len = self.window.num_pixels() * self.bytes_per_sample;
+        Some(&self.data[band * band_len..(band + 1) * band_len])
+    }
+
+    pub(crate) fn bytes_per_pixel(&self) -> usize {
+        self.bytes_per_sample * self.samples_per_pixel as usize
+    }
+
+    /// Reverse the order of rows, within each band for planar data.
+    fn flip_rows(&mut self) {
+        let row_len = match self.layout {
+            SampleLayout::Interleaved => self.window.width as usize * self.bytes_per_pixel(),
+            SampleLayout::Planar => self.window.width as usize * self.bytes_per_sample,
+        };
+        let plane_len = row_len * self.window.height as usize;
+        if plane_len == 0 {
+            return;
+        }
+        for plane in self.data.chunks_exact_mut(plane_len) {
+            let height = self.window.height as usize;
+            for row in 0..height / 2 {
+                let (top, bottom) = plane.split_at_mut((height - 1 - row) * row_len);
+                top[row * row_len..(row + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+            }
+        }
+    }
+
+    /// The byte offset of sample `sample` of the pixel at `idx`.
+    fn sample_offset(&self, idx: usize, sample: usize) -> usize {
+        match self.layout {
+            SampleLayout::Interleaved => {
+                idx * self.bytes_per_pixel() + sample * self.bytes_per_sample
+            }
+            SampleLayout::Planar => {
+                (sample * self.window.num_pixels() + idx) * self.bytes_per_sample
+            }
+        }
+    }
+
+    /// Returns `true` if every sample of the pixel at `idx` equals `value`.
+    fn pixel_equals(&self, idx: usize, value: f64) -> bool {
+        (0..self.samples_per_pixel as usize).all(|sample| {
+            let offset = self.sample_offset(idx, sample);
+            let sample = &self.data[offset..offset + self.bytes_per_sample];
+            match sample_to_f64(sample, self.sample_format) {
+                Some(v) if value.is_nan() => v.is_nan(),
+                Some(v) => v == value,
+                None => false,
+            }
+        })
+    }
+}
+
+/// Pixel data read from a [`Window`] together with a per-pixel validity mask.
+///
+/// This is returned by [`ImageFileDirectory::read_window_masked`].
+#[derive(Debug, Clone)]
+pub struct MaskedWindowData {
+    data: WindowData,
+    mask: Vec<bool>,
+}
+
+impl MaskedWindowData {
+    /// Access the pixel data.
+    pub fn data(&self) -> &WindowData {
+        &self.data
+    }
+
+    /// Access the validity mask.
+    ///
+    /// This holds one entry per pixel of the window in row-major order; `true` means the pixel
+    /// holds valid data.
+    pub fn mask(&self) -> &[bool] {
+        &self.mask
+    }
+
+    /// Consume this and return the pixel data and the validity mask.
+    pub fn into_parts(self) -> (WindowData, Vec<bool>) {
+        (self.data, self.mask)
+    }
+
+    /// Overwrite every sample of each masked-out pixel with `value`.
+    ///
+    /// This is typically used to write the IFD's [nodata
+    /// value](ImageFileDirectory::nodata) into pixels that were masked by a mask IFD.
+    pub fn fill_masked(&mut self, value: f64) -> AsyncTiffResult<()> {
+        let sample = f64_to_sample(value, self.data.sample_format, self.data.bytes_per_sample)?;
+        for (idx, valid) in self.mask.iter().enumerate() {
+            if !valid {
+                for s in 0..self.data.samples_per_pixel as usize {
+                    let offset = self.data.sample_offset(idx, s);
+                    self.data.data[offset..offset + sample.len()].copy_from_slice(&sample);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ImageFileDirectory {
+    /// Read the pixels inside `window`, fetching and decoding every tile or strip it intersects.
+    ///
+    /// Strips are read as image-width tiles. Only byte-aligned samples, or 1-bit samples with a
+    /// single sample per pixel, are currently supported. For planar images, the chunks of every
+    /// sample plane are fetched and assembled into the requested [`SampleLayout`].
+    pub async fn read_window(
+        &self,
+        window: Window,
+        reader: &dyn AsyncFileReader,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<WindowData> {
+        self.read_window_with_options(window, &Default::default(), reader, decoder_registry)
+            .await
+    }
+
+    /// Read the pixels inside `window`, as [`read_window`](Self::read_window), with additional
+    /// options controlling the output.
+    pub async fn read_window_with_options(
+        &self,
+        window: Window,
+        options: &ReadWindowOptions,
+        reader: &dyn AsyncFileReader,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<WindowData> {
+        let (tile_width, tile_height) = self.window_chunk_size()?;
+        let bits_per_sample = self.bits_per_sample[0];
+        let samples_per_pixel = self.samples_per_pixel;
+        let planes = match self.planar_configuration {
+            PlanarConfiguration::Chunky => 1,
+            PlanarConfiguration::Planar => samples_per_pixel as usize,
+        };
+        if !(bits_per_sample.is_multiple_of(8) || (bits_per_sample == 1 && samples_per_pixel == 1))
+        {
+            return Err(AsyncTiffError::General(format!(
+                "Windowed reads of {bits_per_sample}-bit samples are not supported"
+            )));
+        }
+        self.check_window_bounds(window)?;
+
+        let bytes_per_sample = (bits_per_sample as usize).div_ceil(8);
+        let bytes_per_pixel = bytes_per_sample * samples_per_pixel as usize;
+        decoder_registry
+            .limits()
+            .check_output_bytes(window.num_pixels() as u64 * bytes_per_pixel as u64)?;
+        let mut output = WindowData {
+            window,
+            layout: options.layout,
+            origin: options.origin,
+            transform: options
+                .include_transform
+                .then(|| AffineTransform::from_ifd(self))
+                .flatten()
+                .map(|transform| window_transform(&transform, window, options.origin)),
+            samples_per_pixel,
+            bytes_per_sample,
+            sample_format: self
+                .sample_format
+                .first()
+                .copied()
+                .unwrap_or(SampleFormat::Uint),
+            data: vec![0; window.num_pixels() * bytes_per_pixel],
+        };
+        if window.num_pixels() == 0 {
+            return Ok(output);
+        }
+
+        if self.has_lazy_tile_offsets() {
+            let indices = self.window_chunk_positions(window)?.into_iter();
+            let indices = indices.filter_map(|(x, y, band)| self.chunk_index(x, y, band));
+            self.load_lazy_tile_offsets(indices, reader).await?;
+        }
+        let (chunks, byte_ranges) = self.window_chunks(window)?;
+        let offsets = byte_ranges
+            .iter()
+            .map(|range| range.start)
+            .collect::<Vec<_>>();
+        let buffers = match options
+            .sequential_reads
+            .and_then(|max| sequential_requests(&byte_ranges, max))
+        {
+            Some(requests) => {
+                let buffers = reader.get_byte_ranges(requests.clone()).await?;
+                split_requests(&byte_ranges, &requests, &buffers)?
+            }
+            None => reader.get_byte_ranges(byte_ranges).await?,
+        };
+
+        let output_row_stride = window.width as usize * bytes_per_pixel;
+        let band_len = window.num_pixels() * bytes_per_sample;
+        let chunk_bytes_per_pixel = bytes_per_pixel / planes;
+        for ((compressed_bytes, offset), (x, y, band)) in
+            buffers.into_iter().zip(offsets).zip(chunks)
+        {
+            let tile = self.new_tile(x, y, offset, compressed_bytes)?;
+            let tile_col = x as u32 * tile_width;
+            let tile_row = y as u32 * tile_height;
+            let decoded = tile.decode(decoder_registry)?;
+
+            let valid_width = tile_width.min(self.image_width - tile_col);
+            let valid_height = tile_height.min(self.image_height - tile_row);
+            let stride_width = match decoder_registry.edge_tiles() {
+                EdgeTiles::Padded => tile_width,
+                EdgeTiles::Trimmed => valid_width,
+            };
+            let tile_row_stride = if bits_per_sample == 1 {
+                (stride_width as usize).div_ceil(8)
+            } else {
+                stride_width as usize * chunk_bytes_per_pixel
+            };
+
+            let col_start = window.col_off.max(tile_col);
+            let col_end = window.col_end().min(tile_col + valid_width);
+            let row_start = window.row_off.max(tile_row);
+            let row_end = window.row_end().min(tile_row + valid_height);
+            let needed = (row_end - tile_row) as usize * tile_row_stride;
+            if decoded.len() < needed {
+                return Err(AsyncTiffError::General(format!(
+                    "Decoded tile ({x}, {y}) has {} bytes, expected at least {needed}",
+                    decoded.len(),
+                )));
+            }
+
+            for row in row_start..row_end {
+                let src_row = &decoded[(row - tile_row) as usize * tile_row_stride..];
+                let out_row = (row - window.row_off) as usize;
+                let out_col = (col_start - window.col_off) as usize;
+                let num_cols = (col_end - col_start) as usize;
+                if bits_per_sample == 1 {
+                    // There is a single sample per pixel, so both layouts are identical.
+                    let dst = &mut output.data[out_row * output_row_stride + out_col..];
+                    for (i, col) in (col_start - tile_col..col_end - tile_col).enumerate() {
+                        let col = col as usize;
+                        dst[i] = (src_row[col / 8] >> (7 - col % 8)) & 1;
+                    }
+                    continue;
+                }
+
+                let src_start = (col_start - tile_col) as usize * chunk_bytes_per_pixel;
+                let src = &src_row[src_start..src_start + num_cols * chunk_bytes_per_pixel];
+                let pixel_start = out_row * window.width as usize + out_col;
+                match (options.layout, planes > 1) {
+                    (SampleLayout::Interleaved, false) => {
+                        let dst_start = pixel_start * bytes_per_pixel;
+                        output.data[dst_start..dst_start + src.len()].copy_from_slice(src);
+                    }
+                    (SampleLayout::Planar, true) => {
+                        let dst_start = band * band_len + pixel_start * bytes_per_sample;
+                        output.data[dst_start..dst_start + src.len()].copy_from_slice(src);
+                    }
+                    (SampleLayout::Interleaved, true) => {
+                        for (i, sample) in src.chunks_exact(bytes_per_sample).enumerate() {
+                            let dst_start =
+                                (pixel_start + i) * bytes_per_pixel + band * bytes_per_sample;
+                            output.data[dst_start..dst_start + bytes_per_sample]
+                                .copy_from_slice(sample);
+                        }
+                    }
+                    (SampleLayout::Planar, false) => {
+                        for (i, pixel) in src.chunks_exact(bytes_per_pixel).enumerate() {
+                            for (band, sample) in pixel.chunks_exact(bytes_per_sample).enumerate() {
+                                let dst_start =
+                                    band * band_len + (pixel_start + i) * bytes_per_sample;
+                                output.data[dst_start..dst_start + bytes_per_sample]
+                                    .copy_from_slice(sample);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if options.origin == RowOrigin::BottomLeft {
+            output.flip_rows();
+        }
+
+        Ok(output)
+    }
+
+    pub(crate) fn check_window_bounds(&self, window: Window) -> AsyncTiffResult<()> {
+        let in_bounds =
+            |off: u32, len: u32, size: u32| off.checked_add(len).is_some_and(|end| end <= size);
+        if !in_bounds(window.col_off, window.width, self.image_width)
+            || !in_bounds(window.row_off, window.height, self.image_height)
+        {
+            return Err(AsyncTiffError::General(format!(
+                "{window:?} is out of bounds for a {}x{} image",
+                self.image_width, self.image_height
+            )));
+        }
+        Ok(())
+    }
+
+    /// The width and height of the tiles, or of the strips as image-width tiles.
+    fn window_chunk_size(&self) -> AsyncTiffResult<(u32, u32)> {
+        match (self.tile_width, self.tile_height, self.strip_count()) {
+            (Some(width), Some(height), _) => Ok((width, height)),
+            (None, _, Some(_)) => Ok((
+                self.image_width,
+                self.strip_height().unwrap_or(self.image_height).max(1),
+            )),
+            _ => Err(AsyncTiffError::General(
+                "Not a tiled or stripped TIFF".to_string(),
+            )),
+        }
+    }
+
+    /// The `(x, y, band)` position and byte range of every tile intersecting `window`, in the
+    /// order they are fetched by [`read_window`](Self::read_window).
+    pub(crate) fn window_chunks(
+        &self,
+        window: Window,
+    ) -> AsyncTiffResult<(Vec<ChunkPosition>, Vec<Range<u64>>)> {
+        let chunks = self.window_chunk_positions(window)?;
+        let byte_ranges = chunks
+            .iter()
+            .map(|&(x, y, band)| {
+                self.chunk_index(x, y, band)
+                    .and_then(|index| self.chunk_byte_range(index))
+                    .ok_or(AsyncTiffError::TileIndexError(x as u32, y as u32))
+            })
+            .collect::<AsyncTiffResult<Vec<_>>>()?;
+        Ok((chunks, byte_ranges))
+    }
+
+    /// The `(x, y, band)` position of every tile intersecting `window`.
+    fn window_chunk_positions(&self, window: Window) -> AsyncTiffResult<Vec<ChunkPosition>> {
+        let (tile_width, tile_height) = self.window_chunk_size()?;
+        if window.num_pixels() == 0 {
+            return Ok(vec![]);
+        }
+
+        // Every sample plane of a planar image is stored in its own chunks, so fetch each
+        // intersecting tile once per plane.
+        let planes = match self.planar_configuration {
+            PlanarConfiguration::Chunky => 1,
+            PlanarConfiguration::Planar => self.samples_per_pixel as usize,
+        };
+        let mut chunks = vec![];
+        for band in 0..planes {
+            for y in window.row_off / tile_height..=(window.row_end() - 1) / tile_height {
+                for x in window.col_off / tile_width..=(window.col_end() - 1) / tile_width {
+                    chunks.push((x as usize, y as usize, band));
+                }
+            }
+        }
+        Ok(chunks)
+    }
+
+    /// Read the pixels inside `window` along with a mask of which pixels hold valid data.
+    ///
+    /// If `mask` is provided, it is used as this image's internal mask (see
+    /// [`TIFF::mask_ifd`](crate::TIFF::mask_ifd)): pixels are valid where the mask is non-zero.
+    /// The mask may use a different tiling from this image, and if its dimensions differ it is
+    /// resampled with nearest-neighbour lookup. Otherwise, if this IFD has a
+    /// [nodata value](Self::nodata), pixels whose samples all equal it are masked out. If
+    /// neither is available, every pixel is valid.
+    pub async fn read_window_masked(
+        &self,
+        mask: Option<&ImageFileDirectory>,
+        window: Window,
+        reader: &dyn AsyncFileReader,
+        decoder_registry: &DecoderRegistry,
+    ) -> AsyncTiffResult<MaskedWindowData> {
+        let data = self.read_window(window, reader, decoder_registry).await?;
+
+        let mask = if let Some(mask_ifd) = mask {
+            read_mask(self, mask_ifd, window, reader, decoder_registry).await?
+        } else if let Some(nodata) = self.nodata() {
+            (0..window.num_pixels())
+                .map(|idx| !data.pixel_equals(idx, nodata))
+                .collect()
+        } else {
+            vec![true; window.num_pixels()]
+        };
+
+        Ok(MaskedWindowData { data, mask })
+    }
+
+    /// Split the image into windows aligned to its tiles or strips, each of which decodes to at
+    /// most `target_bytes`, in the order the chunks are stored.
+    ///
+    /// When a whole row of tiles fits in `target_bytes`, windows span the full width of the image
+    /// and as many tile rows as fit. Otherwise they span as many tiles of a single tile row as fit.
+    /// Either way, no tile is decoded by more than one window, and a window covers at least one
+    /// tile even if that alone decodes to more than `target_bytes`. Windows on the right and
+    /// bottom edges are clipped to the image.
+    pub fn suggest_windows(&self, target_bytes: u64) -> impl Iterator<Item = Window> {
+        let (image_width, image_height) = (self.image_width, self.image_height);
+        let (chunk_width, chunk_height) = match (self.tile_width, self.tile_height) {
+            (Some(tile_width), Some(tile_height)) => (tile_width, tile_height),
+            _ => (image_width, self.strip_height().unwrap_or(image_height)),
+        };
+        let (chunk_width, chunk_height) = (chunk_width.max(1), chunk_height.max(1));
+        let planes = match self.planar_configuration {
+            PlanarConfiguration::Chunky => 1,
+            PlanarConfiguration::Planar => self.samples_per_pixel as u64,
+        };
+        let chunk_bytes = self.chunk_row_bytes() as u64 * chunk_height as u64 * planes;
+
+        let chunks = (target_bytes / chunk_bytes.max(1)).max(1);
+        let chunks_across = image_width.div_ceil(chunk_width).max(1) as u64;
+        let (across, down) = if chunks >= chunks_across {
+            (chunks_across, chunks / chunks_across)
+        } else {
+            (chunks, 1)
+        };
+        let window_width = (across * chunk_width as u64).min(image_width.max(1) as u64) as u32;
+        let window_height = (down * chunk_height as u64).min(image_height.max(1) as u64) as u32;
+
+        (0..image_height)
+            .step_by(window_height as usize)
+            .flat_map(move |row_off| {
+                (0..image_width)
+                    .step_by(window_width as usize)
+                    .map(move |col_off| {
+                        Window::new(
+                            col_off,
+                            row_off,
+                            window_width.min(image_width - col_off),
+                            window_height.min(image_height - row_off),
+                        )
+                    })
+            })
+    }
+}
+
+/// The transform of `window` within an image with the given `transform`.
+fn window_transform(
+    transform: &AffineTransform,
+    window: Window,
+    origin: RowOrigin,
+) -> AffineTransform {
+    let col = window.col_off as f64;
+    let (row, row_sign) = match origin {
+        RowOrigin::TopLeft => (window.row_off as f64, 1.0),
+        RowOrigin::BottomLeft => (window.row_end() as f64, -1.0),
+    };
+    AffineTransform::new(
+        transform.a(),
+        transform.b() * row_sign,
+        transform.c() + transform.a() * col + transform.b() * row,
+        transform.d(),
+        transform.e() * row_sign,
+        transform.f() + transform.d() * col + transform.e() * row,
+    )
+}
+
+/// Read `mask_ifd` over the area covered by `window` in `ifd`'s pixel space.
+async fn read_mask(
+    ifd: &ImageFileDirectory,
+    mask_ifd: &ImageFileDirectory,
+    window: Window,
+    reader: &dyn AsyncFileReader,
+    decoder_registry: &DecoderRegistry,
+) -> AsyncTiffResult<Vec<bool>> {
+    if window.num_pixels() == 0 {
+        return Ok(vec![]);
+    }
+    let cols = (window.col_off..window.col_end())
+        .map(|c| nearest(c, ifd.image_width, mask_ifd.image_width))
+        .collect::<Vec<_>>();
+    let rows = (window.row_off..window.row_end())
+        .map(|r| nearest(r, ifd.image_height, mask_ifd.image_height))
+        .collect::<Vec<_>>();
+
+    // Indices are monotonic, so the first and last entries bound the mask window.
+    let mask_window = Window::new(
+        cols[0],
+        rows[0],
+        cols[cols.len() - 1] - cols[0] + 1,
+        rows[rows.len() - 1] - rows[0] + 1,
+    );
+    let mask_data = mask_ifd
+        .read_window(mask_window, reader, decoder_registry)
+        .await?;
+    let bytes_per_pixel = mask_data.bytes_per_pixel();
+    let mask_row_stride = mask_window.width as usize * bytes_per_pixel;
+
+    let mut mask = Vec::with_capacity(window.num_pixels());
+    for row in &rows {
+        let mask_row = &mask_data.data[(row - mask_window.row_off) as usize * mask_row_stride..];
+        for col in &cols {
+            let start = (col - mask_window.col_off) as usize * bytes_per_pixel;
+            mask.push(
+                mask_row[start..start + bytes_per_pixel]
+                    .iter()
+                    .any(|b| *b != 0),
+            );
+        }
+    }
+    Ok(mask)
+}
+
+/// Map pixel `i` of a `src_len`-pixel axis to the nearest pixel of a `dst_len`-pixel axis.
+fn nearest(i: u32, src_len: u32, dst_len: u32) -> u32 {
+    if src_len == dst_len {
+        return i;
+    }
+    let mapped = (2 * i as u64 + 1) * dst_len as u64 / (2 * src_len as u64);
+    (mapped as u32).min(dst_len.saturating_sub(1))
+}
+
+pub(crate) fn sample_to_f64(sample: &[u8], sample_format: SampleFormat) -> Option<f64> {
+    let v = match (sample_format, sample.len()) {
+        (SampleFormat::Int, 1) => i8::from_ne_bytes(sample.try_into().ok()?) as f64,
+        (SampleFormat::Int, 2) => i16::from_ne_bytes(sample.try_into().ok()?) as f64,
+        (SampleFormat::Int, 4) => i32::from_ne_bytes(sample.try_into().ok()?) as f64,
+        (SampleFormat::Int, 8) => i64::from_ne_bytes(sample.try_into().ok()?) as f64,
+        (SampleFormat::IEEEFP, 2) => {
+            F16::from_bits(u16::from_ne_bytes(sample.try_into().ok()?)).to_f64()
+        }
+        (SampleFormat::IEEEFP, 4) => f32::from_ne_bytes(sample.try_into().ok()?) as f64,
+        (SampleFormat::IEEEFP, 8) => f64::from_ne_bytes(sample.try_into().ok()?),
+        (SampleFormat::IEEEFP, _) => return None,
+        (_, 1) => sample[0] as f64,
+        (_, 2) => u16::from_ne_bytes(sample.try_into().ok()?) as f64,
+        (_, 4) => u32::from_ne_bytes(sample.try_into().ok()?) as f64,
+        (_, 8) => u64::from_ne_bytes(sample.try_into().ok()?) as f64,
+        _ => return None,
+    };
+    Some(v)
+}
+
+pub(crate) fn f64_to_sample(
+    value: f64,
+    sample_format: SampleFormat,
+    bytes_per_sample: usize,
+) -> AsyncTiffResult<Vec<u8>> {
+    let sample = match (sample_format, bytes_per_sample) {
+        (SampleFormat::Int, 1) => (value as i8).to_ne_bytes().to_vec(),
+        (SampleFormat::Int, 2) => (value as i16).to_ne_bytes().to_vec(),
+        (SampleFormat::Int, 4) => (value as i32).to_ne_bytes().to_vec(),
+        (SampleFormat::Int, 8) => (value as i64).to_ne_bytes().to_vec(),
+        (SampleFormat::IEEEFP, 2) => F16::from_f32(value as f32).to_bits().to_ne_bytes().to_vec(),
+        (SampleFormat::IEEEFP, 4) => (value as f32).to_ne_bytes().to_vec(),
+        (SampleFormat::IEEEFP, 8) => value.to_ne_bytes().to_vec(),
+        (SampleFormat::IEEEFP, _) => {
+            return Err(AsyncTiffError::General(format!(
+                "Cannot write {}-bit floating point samples",
+                bytes_per_sample * 8
+            )))
+        }
+        (_, 1) => (value as u8).to_ne_bytes().to_vec(),
+        (_, 2) => (value as u16).to_ne_bytes().to_vec(),
+        (_, 4) => (value as u32).to_ne_bytes().to_vec(),
+        (_, 8) => (value as u64).to_ne_bytes().to_vec(),
+        _ => {
+            return Err(AsyncTiffError::General(format!(
+                "Cannot write {bytes_per_sample}-byte samples"
+            )))
+        }
+    };
+    Ok(sample)
+}
+
+/// The `(x, y, band)` position of a tile of a sample plane.
+type ChunkPosition = (usize, usize, usize);
+
+/// The largest gap between chunks that sequential reads fetch rather than skip.
+const SEQUENTIAL_MAX_GAP: u64 = 64 * 1024;
+
+/// Merge `ranges` into linear requests of at most `max_request_bytes`, or `None` if the non-empty
+/// ranges aren't in increasing order.
+///
+/// A single range larger than `max_request_bytes` is requested on its own.
+pub(crate) fn sequential_requests(
+    ranges: &[Range<u64>],
+    max_request_bytes: u64,
+) -> Option<Vec<Range<u64>>> {
+    let mut requests: Vec<Range<u64>> = vec![];
+    for range in ranges.iter().filter(|range| !range.is_empty()) {
+        match requests.last_mut() {
+            Some(last) if range.start < last.end => return None,
+            Some(last)
+                if range.start - last.end <= SEQUENTIAL_MAX_GAP
+                    && range.end - last.start <= max_request_bytes =>
+            {
+                last.end = range.end;
+            }
+            _ => requests.push(range.clone()),
+        }
+    }
+    Some(requests)
+}
+
+/// Slice the bytes of each of `ranges` out of the `buffers` fetched for the `requests` returned
+/// by [`sequential_requests`].
+fn split_requests(
+    ranges: &[Range<u64>],
+    requests: &[Range<u64>],
+    buffers: &[Bytes],
+) -> AsyncTiffResult<Vec<Bytes>> {
+    let mut requests = requests.iter().zip(buffers).peekable();
+    ranges
+        .iter()
+        .map(|range| {
+            if range.is_empty() {
+                return Ok(Bytes::new());
+            }
+            while requests
+                .peek()
+                .is_some_and(|(request, _)| request.end < range.end)
+            {
+                requests.next();
+            }
+            let (request, buffer) = requests.peek().ok_or_else(|| {
+                AsyncTiffError::General("Reader returned too few byte ranges".to_string())
+            })?;
+            let start = (range.start - request.start) as usize;
+            let end = (range.end - request.start) as usize;
+            if end > buffer.len() {
+                return Err(AsyncTiffError::General(format!(
+                    "Reader returned {} bytes for range {request:?}",
+                    buffer.len()
+                )));
+            }
+            Ok(buffer.slice(start..end))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::reader::MemoryReader;
+    use crate::test_util::CountingReader;
+    use crate::IfdBuilder;
+
+    #[test]
+    fn test_suggest_windows() {
+        // 4x3 tiles of 32x32 RGB pixels, each decoding to 3072 bytes
+        let ifd = IfdBuilder::new(100, 70)
+            .with_samples_per_pixel(3)
+            .with_tiling(32, 32)
+            .build()
+            .unwrap();
+        let windows = |target_bytes| ifd.suggest_windows(target_bytes).collect::<Vec<_>>();
+
+        // Two full tile rows fit, with room to spare
+        assert_eq!(
+            windows(3072 * 9),
+            [Window::new(0, 0, 100, 64), Window::new(0, 64, 100, 6)]
+        );
+        // Runs of three tiles within each tile row
+        assert_eq!(
+            windows(3072 * 3)[..3],
+            [
+                Window::new(0, 0, 96, 32),
+                Window::new(96, 0, 4, 32),
+                Window::new(0, 32, 96, 32),
+            ]
+        );
+        // At least one tile per window, covering the image once
+        let single = windows(0);
+        assert_eq!(single.len(), 12);
+        let area = single.iter().map(Window::num_pixels).sum::<usize>();
+        assert_eq!(area, 100 * 70);
+
+        // Strips of 10 rows, two at a time
+        let ifd = IfdBuilder::new(100, 70).with_strips(10).build().unwrap();
+        let windows = ifd.suggest_windows(2500).collect::<Vec<_>>();
+        assert_eq!(windows.len(), 4);
+        assert_eq!(windows[0], Window::new(0, 0, 100, 20));
+        assert_eq!(windows[3], Window::new(0, 60, 100, 10));
+    }
+
+    #[test]
+    fn test_nearest() {
+        assert_eq!(nearest(5, 10, 10), 5);
+        // A mask at half resolution
+        assert_eq!(
+            (0..6).map(|i| nearest(i, 6, 3)).collect::<Vec<_>>(),
+            [0, 0, 1, 1, 2, 2]
+        );
+        // A mask at double resolution
+        assert_eq!(
+            (0..3).map(|i| nearest(i, 3, 6)).collect::<Vec<_>>(),
+            [1, 3, 5]
+        );
+    }
+
+    #[test]
+    fn test_window_transform() {
+        let transform = AffineTransform::new(10.0, 0.0, 1000.0, 0.0, -10.0, 5000.0);
+        let window = Window::new(2, 3, 4, 5);
+        assert_eq!(
+            window_transform(&transform, window, RowOrigin::TopLeft),
+            AffineTransform::new(10.0, 0.0, 1020.0, 0.0, -10.0, 4970.0)
+        );
+        assert_eq!(
+            window_transform(&transform, window, RowOrigin::BottomLeft),
+            AffineTransform::new(10.0, 0.0, 1020.0, 0.0, 10.0, 4920.0)
+        );
+    }
+
+    #[test]
+    fn test_flip_rows() {
+        let mut data = WindowData {
+            window: Window::new(0, 0, 2, 3),
+            layout: SampleLayout::Planar,
+            origin: RowOrigin::TopLeft,
+            transform: None,
+            samples_per_pixel: 2,
+            bytes_per_sample: 1,
+            sample_format: SampleFormat::Uint,
+            data: vec![1, 2, 3, 4, 5, 6, 11, 12, 13, 14, 15, 16],
+        };
+        data.flip_rows();
+        assert_eq!(data.data(), [5, 6, 3, 4, 1, 2, 15, 16, 13, 14, 11, 12]);
+    }
+
+    #[test]
+    fn test_fill_masked() {
+        let data = WindowData {
+            window: Window::new(0, 0, 3, 1),
+            layout: SampleLayout::Interleaved,
+            origin: RowOrigin::TopLeft,
+            transform: None,
+            samples_per_pixel: 2,
+            bytes_per_sample: 2,
+            sample_format: SampleFormat::Int,
+            data: [1i16, 2, 3, 4, 5, 6]
+                .iter()
+                .flat_map(|v| v.to_ne_bytes())
+                .collect(),
+        };
+        assert!(!data.pixel_equals(1, 3.0));
+        let mut masked = MaskedWindowData {
+            data,
+            mask: vec![true, false, true],
+        };
+        masked.fill_masked(-9999.0).unwrap();
+        let values = masked
+            .data()
+            .data()
+            .chunks_exact(2)
+            .map(|s| i16::from_ne_bytes(s.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(values, [1, 2, -9999, -9999, 5, 6]);
+        assert!(masked.data().pixel_equals(1, -9999.0));
+    }
+
+    #[test]
+    fn test_f16_window() {
+        let mut masked = MaskedWindowData {
+            data: WindowData {
+                window: Window::new(0, 0, 3, 1),
+                layout: SampleLayout::Interleaved,
+                origin: RowOrigin::TopLeft,
+                transform: None,
+                samples_per_pixel: 1,
+                bytes_per_sample: 2,
+                sample_format: SampleFormat::IEEEFP,
+                data: [1.0, 0.5, -2.0]
+                    .iter()
+                    .flat_map(|v| F16::from_f32(*v).to_bits().to_ne_bytes())
+                    .collect(),
+            },
+            mask: vec![true, false, true],
+        };
+        assert!(masked.data().pixel_equals(1, 0.5));
+        masked.fill_masked(-32768.0).unwrap();
+        assert_eq!(
+            masked.data().to_typed().unwrap(),
+            DecodingResult::F16(
+                [1.0, -32768.0, -2.0]
+                    .into_iter()
+                    .map(F16::from_f32)
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_window_planar() {
+        // A 20x20 planar RGB image of 16x16 tiles, stored band by band
+        let value = |band: usize, row: usize, col: usize| (band * 100 + row * 3 + col) as u8;
+        let ifd = IfdBuilder::new(20, 20)
+            .with_samples_per_pixel(3)
+            .with_planar_configuration(PlanarConfiguration::Planar)
+            .with_tiling(16, 16)
+            .with_chunk_locations((0..12).map(|i| i * 256).collect(), vec![256; 12])
+            .build()
+            .unwrap();
+        let mut file = vec![];
+        for band in 0..3 {
+            for y in 0..2 {
+                for x in 0..2 {
+                    assert_eq!(ifd.chunk_index(x, y, band), Some(file.len() / 256));
+                    for row in y * 16..y * 16 + 16 {
+                        file.extend((x * 16..x * 16 + 16).map(|col| value(band, row, col)));
+                    }
+                }
+            }
+        }
+        assert_eq!(ifd.chunk_index(2, 0, 0), None);
+        assert_eq!(ifd.chunk_index(0, 0, 3), None);
+        let reader = MemoryReader::new(file);
+
+        let window = Window::new(10, 12, 8, 6);
+        let read = |layout: SampleLayout| {
+            let options = ReadWindowOptions::new().with_layout(layout);
+            ifd.read_window_with_options(window, &options, &reader, &Default::default())
+                .now_or_never()
+                .unwrap()
+                .unwrap()
+        };
+
+        let interleaved = read(SampleLayout::Interleaved);
+        let expected = (12..18)
+            .flat_map(|row| (10..18).flat_map(move |col| (0..3).map(move |b| value(b, row, col))))
+            .collect::<Vec<_>>();
+        assert_eq!(interleaved.data(), expected);
+
+        let planar = read(SampleLayout::Planar);
+        for band in 0..3 {
+            let expected = (12..18)
+                .flat_map(|row| (10..18).map(move |col| value(band, row, col)))
+                .collect::<Vec<_>>();
+            assert_eq!(planar.band(band), Some(&expected[..]));
+        }
+    }
+
+    #[test]
+    fn test_sequential_requests() {
+        assert_eq!(
+            sequential_requests(&[0..10, 10..20, 0..0, 24..30, 100_000..100_010], 25),
+            Some(vec![0..20, 24..30, 100_000..100_010])
+        );
+        // A single range larger than the maximum is requested on its own
+        assert_eq!(
+            sequential_requests(&[0..10, 10..50, 50..60], 20),
+            Some(vec![0..10, 10..50, 50..60])
+        );
+        assert_eq!(sequential_requests(&[10..20, 0..10], 100), None);
+
+        let requests = [0..20, 24..30];
+        let buffers = [Bytes::from_static(&[1; 20]), Bytes::from_static(&[2; 6])];
+        let split = split_requests(&[0..10, 0..0, 10..20, 24..30], &requests, &buffers).unwrap();
+        assert_eq!(
+            split.iter().map(Bytes::len).collect::<Vec<_>>(),
+            [10, 0, 10, 6]
+        );
+        assert_eq!(split[3][0], 2);
+    }
+
+    #[test]
+    fn test_read_window_sequential() {
+        let file: Vec<u8> = (0..12 * 256)
+            .map(|i| (i / 256 * 10 + i % 7) as u8)
+            .collect();
+        let reader = CountingReader::new(file);
+        let window = Window::new(0, 0, 20, 20);
+        let read = |offsets: Vec<u64>, options: ReadWindowOptions| {
+            let ifd = IfdBuilder::new(20, 20)
+                .with_samples_per_pixel(3)
+                .with_planar_configuration(PlanarConfiguration::Planar)
+                .with_tiling(16, 16)
+                .with_chunk_locations(offsets, vec![256; 12])
+                .build()
+                .unwrap();
+            reader.take_counts();
+            let data = ifd
+                .read_window_with_options(window, &options, &reader, &Default::default())
+                .now_or_never()
+                .unwrap()
+                .unwrap();
+            (
+                ifd.has_sequential_chunks().unwrap(),
+                data,
+                reader.requests(),
+            )
+        };
+
+        let in_order = (0..12).map(|i| i * 256).collect::<Vec<_>>();
+        let (sequential, expected, requests) = read(in_order.clone(), ReadWindowOptions::new());
+        assert!(sequential);
+        assert_eq!(requests, 12);
+        let options = ReadWindowOptions::new().with_sequential_reads(1024);
+        let (_, data, requests) = read(in_order, options.clone());
+        assert_eq!(requests, 3);
+        assert_eq!(data.data(), expected.data());
+
+        // Tiles out of order are fetched one by one
+        let shuffled = (0..12).map(|i| (11 - i) * 256).collect::<Vec<_>>();
+        let (sequential, _, requests) = read(shuffled, options);
+        assert!(!sequential);
+        assert_eq!(requests, 12);
+    }
+}
@@ -0,0 +1,575 @@
+//! Abstractions for writing files.
+
+use std::fmt::Debug;
+#[cfg(feature = "object_store")]
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::future::{BoxFuture, FutureExt};
+
+use crate::error::AsyncTiffResult;
+
+/// The asynchronous interface used to write TIFF files.
+///
+/// Files are written sequentially from start to end, so that they can be streamed to sinks that
+/// don't support seeking, such as multipart uploads to object storage.
+///
+/// Notes:
+///
+/// 1. [`ObjectWriter`], available when the `object_store` crate feature is enabled, implements
+///    this interface for [`ObjectStore`], switching to a multipart upload for large files.
+///
+/// 2. [`TokioWriter`], available when the `tokio` crate feature is enabled, implements this
+///    interface for types that implement [`tokio::io::AsyncWrite`], for example
+///    [`tokio::fs::File`].
+///
+/// 3. [`MemoryWriter`] collects the file in memory.
+///
+/// [`ObjectStore`]: object_store::ObjectStore
+///
+/// [`tokio::fs::File`]: https://docs.rs/tokio/latest/tokio/fs/struct.File.html
+pub trait AsyncFileWriter: Debug + Send {
+    /// Append `data` to the file.
+    fn write(&mut self, data: Bytes) -> BoxFuture<'_, AsyncTiffResult<()>>;
+
+    /// Flush any buffered data and complete the file. Nothing may be written afterwards.
+    fn finish(&mut self) -> BoxFuture<'_, AsyncTiffResult<()>>;
+}
+
+impl AsyncFileWriter for Box<dyn AsyncFileWriter + '_> {
+    fn write(&mut self, data: Bytes) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        self.as_mut().write(data)
+    }
+
+    fn finish(&mut self) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        self.as_mut().finish()
+    }
+}
+
+/// An [`AsyncFileWriter`] that collects the file in memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryWriter {
+    data: Vec<u8>,
+}
+
+impl MemoryWriter {
+    /// Create a new, empty writer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The bytes written so far.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consume this writer, returning the bytes written.
+    pub fn into_inner(self) -> Bytes {
+        self.data.into()
+    }
+}
+
+impl AsyncFileWriter for MemoryWriter {
+    fn write(&mut self, data: Bytes) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        self.data.extend_from_slice(&data);
+        async { Ok(()) }.boxed()
+    }
+
+    fn finish(&mut self) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        async { Ok(()) }.boxed()
+    }
+}
+
+/// A wrapper for things that implement [AsyncWrite] to implement [`AsyncFileWriter`].
+///
+/// [AsyncWrite]: tokio::io::AsyncWrite
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioWriter<T: tokio::io::AsyncWrite + Unpin + Send + Debug>(T);
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncWrite + Unpin + Send + Debug> TokioWriter<T> {
+    /// Create a new TokioWriter from a writer.
+    pub fn new(inner: T) -> Self {
+        Self(inner)
+    }
+
+    /// Consume this writer, returning the inner writer.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncWrite + Unpin + Send + Debug> AsyncFileWriter for TokioWriter<T> {
+    fn write(&mut self, data: Bytes) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        use tokio::io::AsyncWriteExt;
+
+        async move { Ok(self.0.write_all(&data).await?) }.boxed()
+    }
+
+    fn finish(&mut self) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        use tokio::io::AsyncWriteExt;
+
+        async move { Ok(self.0.shutdown().await?) }.boxed()
+    }
+}
+
+/// The default size of each part of a multipart upload, the minimum most object stores accept.
+#[cfg(feature = "object_store")]
+pub const DEFAULT_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// An [`AsyncFileWriter`] that writes to an [`ObjectStore`] instance.
+///
+/// Files smaller than the part size are written with a single `put`. Larger files are streamed
+/// with a multipart upload as they are written, with at most
+/// [`max_concurrency`](Self::max_concurrency) parts in flight at a time, so that writing doesn't
+/// need to buffer the whole file. If a part fails to upload, the multipart upload is aborted.
+///
+/// [`ObjectStore`]: object_store::ObjectStore
+#[cfg(feature = "object_store")]
+#[derive(Debug)]
+pub struct ObjectWriter {
+    store: Arc<dyn object_store::ObjectStore>,
+    path: object_store::path::Path,
+    part_size: usize,
+    max_concurrency: usize,
+    state: UploadState,
+}
+
+#[cfg(feature = "object_store")]
+#[derive(Debug)]
+enum UploadState {
+    /// Less than a part has been written.
+    Buffering(Vec<u8>),
+    Multipart(object_store::WriteMultipart),
+    Finished,
+}
+
+#[cfg(feature = "object_store")]
+impl ObjectWriter {
+    /// Creates a new [`ObjectWriter`] for the provided [`ObjectStore`] and path.
+    ///
+    /// [`ObjectStore`]: object_store::ObjectStore
+    pub fn new(store: Arc<dyn object_store::ObjectStore>, path: object_store::path::Path) -> Self {
+        Self {
+            store,
+            path,
+            part_size: DEFAULT_PART_SIZE,
+            max_concurrency: 8,
+            state: UploadState::Buffering(vec![]),
+        }
+    }
+
+    /// Set the size of each part of a multipart upload. Defaults to [`DEFAULT_PART_SIZE`].
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size.max(1);
+        self
+    }
+
+    /// The size of each part of a multipart upload.
+    pub fn part_size(&self) -> usize {
+        self.part_size
+    }
+
+    /// Set the maximum number of parts uploaded concurrently. Defaults to 8.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// The maximum number of parts uploaded concurrently.
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    async fn write_bytes(&mut self, data: Bytes) -> AsyncTiffResult<()> {
+        if let UploadState::Buffering(buffer) = &mut self.state {
+            if buffer.len() + data.len() < self.part_size {
+                buffer.extend_from_slice(&data);
+                return Ok(());
+            }
+            let buffer = std::mem::take(buffer);
+            let upload = self.store.put_multipart(&self.path).await?;
+            let mut multipart =
+                object_store::WriteMultipart::new_with_chunk_size(upload, self.part_size);
+            multipart.put(buffer.into());
+            self.state = UploadState::Multipart(multipart);
+        }
+
+        match &mut self.state {
+            UploadState::Multipart(multipart) => {
+                if let Err(err) = multipart.wait_for_capacity(self.max_concurrency).await {
+                    self.abort().await;
+                    return Err(err.into());
+                }
+                multipart.put(data);
+                Ok(())
+            }
+            UploadState::Buffering(_) => unreachable!(),
+            UploadState::Finished => Err(crate::error::AsyncTiffError::General(
+                "Cannot write to a finished ObjectWriter".to_string(),
+            )),
+        }
+    }
+
+    async fn finish_upload(&mut self) -> AsyncTiffResult<()> {
+        match std::mem::replace(&mut self.state, UploadState::Finished) {
+            UploadState::Buffering(buffer) => {
+                self.store.put(&self.path, buffer.into()).await?;
+            }
+            UploadState::Multipart(multipart) => {
+                multipart.finish().await?;
+            }
+            UploadState::Finished => {}
+        }
+        Ok(())
+    }
+
+    async fn abort(&mut self) {
+        if let UploadState::Multipart(multipart) =
+            std::mem::replace(&mut self.state, UploadState::Finished)
+        {
+            // The upload already failed, so report that error rather than any from cleaning up.
+            let _ = multipart.abort().await;
+        }
+    }
+}
+
+#[cfg(feature = "object_store")]
+impl AsyncFileWriter for ObjectWriter {
+    fn write(&mut self, data: Bytes) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        self.write_bytes(data).boxed()
+    }
+
+    fn finish(&mut self) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        self.finish_upload().boxed()
+    }
+}
+
+/// The progress of a [`MultipartWriter`], from which an interrupted upload can be resumed.
+///
+/// Persist the [`multipart_id`](Self::multipart_id), [`part_size`](Self::part_size) and the
+/// content IDs of the [`parts`](Self::parts) after a failure, and rebuild the checkpoint with
+/// [`new`](Self::new) to [resume](MultipartWriter::resume) the upload, e.g. from another process.
+#[cfg(feature = "object_store")]
+#[derive(Debug, Clone)]
+pub struct UploadCheckpoint {
+    multipart_id: Option<object_store::MultipartId>,
+    part_size: usize,
+    parts: Vec<object_store::multipart::PartId>,
+}
+
+#[cfg(feature = "object_store")]
+impl UploadCheckpoint {
+    /// Create a checkpoint of a multipart upload whose first `parts` have been uploaded.
+    pub fn new(
+        multipart_id: object_store::MultipartId,
+        part_size: usize,
+        parts: Vec<object_store::multipart::PartId>,
+    ) -> Self {
+        Self {
+            multipart_id: Some(multipart_id),
+            part_size: part_size.max(1),
+            parts,
+        }
+    }
+
+    /// The ID of the multipart upload, or `None` if no part has been uploaded yet.
+    pub fn multipart_id(&self) -> Option<&object_store::MultipartId> {
+        self.multipart_id.as_ref()
+    }
+
+    /// The size of each part but the last.
+    pub fn part_size(&self) -> usize {
+        self.part_size
+    }
+
+    /// The parts uploaded so far, in order.
+    pub fn parts(&self) -> &[object_store::multipart::PartId] {
+        &self.parts
+    }
+
+    /// The number of bytes of the file uploaded so far.
+    pub fn bytes_uploaded(&self) -> u64 {
+        (self.parts.len() * self.part_size) as u64
+    }
+}
+
+/// An [`AsyncFileWriter`] that uploads a file with an explicit multipart upload, which can be
+/// resumed after a failure.
+///
+/// Each full part is uploaded as soon as it's written, retrying up to
+/// [`max_retries`](Self::max_retries) times. If a part still fails, the upload is left open and
+/// its [`checkpoint`](Self::checkpoint) records the parts uploaded so far. To resume, create a
+/// writer with [`resume`](Self::resume) and write the same file again from the start: the bytes
+/// of the uploaded parts are skipped. This requires the file to be written identically, as
+/// [`CogWriter`](crate::CogWriter) does.
+///
+/// Unlike [`ObjectWriter`], this always uses a multipart upload, whose parts other than the last
+/// must usually be at least 5 MiB.
+#[cfg(feature = "object_store")]
+pub struct MultipartWriter {
+    store: Arc<dyn object_store::multipart::MultipartStore>,
+    path: object_store::path::Path,
+    max_retries: usize,
+    checkpoint: UploadCheckpoint,
+    /// The number of bytes still to skip, uploaded before resuming.
+    skip: u64,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+#[cfg(feature = "object_store")]
+impl Debug for MultipartWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultipartWriter")
+            .field("path", &self.path)
+            .field("max_retries", &self.max_retries)
+            .field("checkpoint", &self.checkpoint)
+            .field("skip", &self.skip)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "object_store")]
+impl MultipartWriter {
+    /// Creates a new [`MultipartWriter`] for the provided [`MultipartStore`] and path.
+    ///
+    /// [`MultipartStore`]: object_store::multipart::MultipartStore
+    pub fn new(
+        store: Arc<dyn object_store::multipart::MultipartStore>,
+        path: object_store::path::Path,
+    ) -> Self {
+        Self::resume(
+            store,
+            path,
+            UploadCheckpoint {
+                multipart_id: None,
+                part_size: DEFAULT_PART_SIZE,
+                parts: vec![],
+            },
+        )
+    }
+
+    /// Resume the upload recorded by `checkpoint`, skipping the bytes of its uploaded parts.
+    pub fn resume(
+        store: Arc<dyn object_store::multipart::MultipartStore>,
+        path: object_store::path::Path,
+        checkpoint: UploadCheckpoint,
+    ) -> Self {
+        Self {
+            store,
+            path,
+            max_retries: 3,
+            skip: checkpoint.bytes_uploaded(),
+            checkpoint,
+            buffer: vec![],
+            finished: false,
+        }
+    }
+
+    /// Set the size of each part. Defaults to [`DEFAULT_PART_SIZE`].
+    ///
+    /// This has no effect once a part has been uploaded, as the parts of an upload must have
+    /// the same size.
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        if self.checkpoint.parts.is_empty() {
+            self.checkpoint.part_size = part_size.max(1);
+        }
+        self
+    }
+
+    /// The size of each part but the last.
+    pub fn part_size(&self) -> usize {
+        self.checkpoint.part_size
+    }
+
+    /// Set the number of times the upload of a part is retried. Defaults to 3.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The number of times the upload of a part is retried.
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// The progress of the upload, from which it can be [resumed](Self::resume).
+    pub fn checkpoint(&self) -> &UploadCheckpoint {
+        &self.checkpoint
+    }
+
+    /// Abort the upload, deleting its uploaded parts.
+    pub async fn abort(&mut self) -> AsyncTiffResult<()> {
+        self.finished = true;
+        if let Some(id) = self.checkpoint.multipart_id.take() {
+            self.checkpoint.parts.clear();
+            self.store.abort_multipart(&self.path, &id).await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_part(&mut self, data: Bytes) -> AsyncTiffResult<()> {
+        let id = match &self.checkpoint.multipart_id {
+            Some(id) => id.clone(),
+            None => {
+                let id = self.store.create_multipart(&self.path).await?;
+                self.checkpoint.multipart_id.insert(id).clone()
+            }
+        };
+        let part_idx = self.checkpoint.parts.len();
+        let mut attempt = 0;
+        let part = loop {
+            match self
+                .store
+                .put_part(&self.path, &id, part_idx, data.clone().into())
+                .await
+            {
+                Ok(part) => break part,
+                Err(_) if attempt < self.max_retries => attempt += 1,
+                Err(err) => return Err(err.into()),
+            }
+        };
+        self.checkpoint.parts.push(part);
+        Ok(())
+    }
+
+    async fn write_bytes(&mut self, mut data: Bytes) -> AsyncTiffResult<()> {
+        if self.finished {
+            return Err(crate::error::AsyncTiffError::General(
+                "Cannot write to a finished MultipartWriter".to_string(),
+            ));
+        }
+        let skipped = self.skip.min(data.len() as u64);
+        self.skip -= skipped;
+        bytes::Buf::advance(&mut data, skipped as usize);
+
+        let part_size = self.checkpoint.part_size;
+        while !data.is_empty() {
+            if self.buffer.is_empty() && data.len() >= part_size {
+                let part = data.split_to(part_size);
+                self.upload_part(part).await?;
+                continue;
+            }
+            let len = (part_size - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data.split_to(len));
+            if self.buffer.len() == part_size {
+                let part = std::mem::take(&mut self.buffer);
+                if let Err(err) = self.upload_part(part.clone().into()).await {
+                    self.buffer = part;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish_upload(&mut self) -> AsyncTiffResult<()> {
+        if self.finished {
+            return Ok(());
+        }
+        if !self.buffer.is_empty() || self.checkpoint.parts.is_empty() {
+            let part = std::mem::take(&mut self.buffer);
+            self.upload_part(part.into()).await?;
+        }
+        let id = self.checkpoint.multipart_id.clone().unwrap_or_default();
+        self.store
+            .complete_multipart(&self.path, &id, self.checkpoint.parts.clone())
+            .await?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "object_store")]
+impl AsyncFileWriter for MultipartWriter {
+    fn write(&mut self, data: Bytes) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        self.write_bytes(data).boxed()
+    }
+
+    fn finish(&mut self) -> BoxFuture<'_, AsyncTiffResult<()>> {
+        self.finish_upload().boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_writer() {
+        let mut writer = MemoryWriter::new();
+        writer.write(Bytes::from_static(b"II")).await.unwrap();
+        writer.write(Bytes::from_static(&[42, 0])).await.unwrap();
+        writer.finish().await.unwrap();
+        assert_eq!(writer.into_inner().as_ref(), b"II*\0");
+    }
+
+    #[cfg(feature = "object_store")]
+    #[tokio::test]
+    async fn test_object_writer() {
+        use object_store::memory::InMemory;
+        use object_store::ObjectStore;
+
+        let store = Arc::new(InMemory::new());
+        let data = (0..100u8).collect::<Vec<_>>();
+        for (name, part_size) in [("single.tif", 1000), ("multipart.tif", 16)] {
+            let path = object_store::path::Path::from(name);
+            let mut writer = ObjectWriter::new(store.clone(), path.clone())
+                .with_part_size(part_size)
+                .with_max_concurrency(2);
+            for chunk in data.chunks(30) {
+                writer.write(Bytes::copy_from_slice(chunk)).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+            assert!(writer.write(Bytes::new()).await.is_err());
+
+            let written = store.get(&path).await.unwrap().bytes().await.unwrap();
+            assert_eq!(written.as_ref(), data, "{name}");
+        }
+    }
+
+    #[cfg(feature = "object_store")]
+    #[tokio::test]
+    async fn test_multipart_writer_resume() {
+        use object_store::memory::InMemory;
+        use object_store::ObjectStore;
+
+        let store = Arc::new(InMemory::new());
+        let path = object_store::path::Path::from("resumed.tif");
+        let data = (0..100u8).collect::<Vec<_>>();
+
+        // Interrupt the upload after 40 bytes, i.e. two full parts
+        let mut writer = MultipartWriter::new(store.clone(), path.clone()).with_part_size(16);
+        writer
+            .write(Bytes::copy_from_slice(&data[..40]))
+            .await
+            .unwrap();
+        let checkpoint = writer.checkpoint().clone();
+        assert_eq!(checkpoint.parts().len(), 2);
+        assert_eq!(checkpoint.bytes_uploaded(), 32);
+        drop(writer);
+        assert!(store.get(&path).await.is_err());
+
+        let checkpoint = UploadCheckpoint::new(
+            checkpoint.multipart_id().unwrap().clone(),
+            checkpoint.part_size(),
+            checkpoint.parts().to_vec(),
+        );
+        let mut writer = MultipartWriter::resume(store.clone(), path.clone(), checkpoint)
+            // The part size of the checkpoint is kept
+            .with_part_size(1000);
+        assert_eq!(writer.part_size(), 16);
+        for chunk in data.chunks(30) {
+            writer.write(Bytes::copy_from_slice(chunk)).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+        assert_eq!(writer.checkpoint().parts().len(), 7);
+
+        let written = store.get(&path).await.unwrap().bytes().await.unwrap();
+        assert_eq!(written.as_ref(), data);
+    }
+}
@@ -0,0 +1,66 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use async_tiff::decoder::DecoderRegistry;
+use async_tiff::error::AsyncTiffResult;
+use async_tiff::reader::AsyncFileReader;
+use async_tiff::ByteTransform;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+const KEY: u8 = 0x5a;
+
+fn xor(bytes: &[u8]) -> Bytes {
+    bytes.iter().map(|b| b ^ KEY).collect()
+}
+
+/// Serves every byte XOR'd with [`KEY`], as an obfuscated archive would store its tiles.
+#[derive(Debug)]
+struct XorReader(Arc<dyn AsyncFileReader>);
+
+impl AsyncFileReader for XorReader {
+    fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        async move { Ok(xor(&self.0.get_bytes(range).await?)) }.boxed()
+    }
+}
+
+#[derive(Debug)]
+struct Xor;
+
+impl ByteTransform for Xor {
+    fn transform(
+        &self,
+        _x: usize,
+        _y: usize,
+        _offset: u64,
+        bytes: Bytes,
+    ) -> AsyncTiffResult<Bytes> {
+        Ok(xor(&bytes))
+    }
+}
+
+#[tokio::test]
+async fn test_byte_transform() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let xor_reader = XorReader(reader.clone());
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let registry = DecoderRegistry::default();
+
+    let expected = ifd.fetch_tile(0, 0, reader.as_ref()).await.unwrap();
+    let transformed = ifd
+        .clone()
+        .with_byte_transform(Arc::new(Xor))
+        .fetch_tile(0, 0, &xor_reader)
+        .await
+        .unwrap();
+    assert_eq!(transformed.compressed_bytes(), expected.compressed_bytes());
+    assert_eq!(
+        transformed.decode(&registry).unwrap(),
+        expected.decode(&registry).unwrap()
+    );
+}
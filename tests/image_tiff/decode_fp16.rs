@@ -0,0 +1,50 @@
+use async_tiff::decoder::{DecodingResult, F16};
+use async_tiff::tiff::tags::{Predictor, SampleFormat};
+
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+async fn read_f16(filename: &str) -> Vec<F16> {
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    assert_eq!(ifd.sample_format(), [SampleFormat::IEEEFP]);
+    assert_eq!(ifd.bits_per_sample(), [16]);
+
+    let indices = (0..ifd.strip_count().unwrap()).collect::<Vec<_>>();
+    let strips = ifd.fetch_strips(&indices, reader.as_ref()).await.unwrap();
+    let mut samples = vec![];
+    for strip in strips {
+        let DecodingResult::F16(data) = strip.decode_typed(&Default::default()).unwrap() else {
+            panic!("Expected f16 samples");
+        };
+        samples.extend(data);
+    }
+    assert_eq!(
+        samples.len(),
+        (ifd.image_width() * ifd.image_height()) as usize
+    );
+    samples
+}
+
+#[tokio::test]
+async fn test_decode_f16() {
+    let white = read_f16("white-fp16.tiff").await;
+    assert!(white.iter().all(|v| *v == F16::from_f32(1.0)));
+
+    let black = read_f16("single-black-fp16.tiff").await;
+    assert!(black[0].to_f32() < 0.001);
+    assert!(black[1..].iter().all(|v| v.to_f32() == 1.0));
+}
+
+#[tokio::test]
+async fn test_decode_f16_predictors() {
+    let expected = read_f16("random-fp16.tiff").await;
+    for (filename, predictor) in [
+        ("random-fp16-pred2.tiff", Predictor::Horizontal),
+        ("random-fp16-pred3.tiff", Predictor::FloatingPoint),
+    ] {
+        let tiff = open_tiff(filename).await;
+        assert_eq!(tiff.ifds()[0].predictor(), Some(predictor));
+        assert_eq!(read_f16(filename).await, expected, "{filename}");
+    }
+}
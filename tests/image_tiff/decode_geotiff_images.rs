@@ -1,5 +1,7 @@
 extern crate tiff;
 
+use async_tiff::tiff::tags::Tag;
+
 use crate::image_tiff::util::open_tiff;
 
 #[tokio::test]
@@ -25,6 +27,12 @@ async fn test_geo_tiff() {
             ifd.model_pixel_scale().expect("Cannot get pixel scale"),
             vec![60.0, 60.0, 0.0]
         );
+        assert_eq!(ifd.tag_u32(Tag::RowsPerStrip), Some(10));
+        assert_eq!(
+            ifd.tag_f64_vec(Tag::ModelPixelScaleTag),
+            Some(vec![60.0, 60.0, 0.0])
+        );
+        assert_eq!(ifd.tag_str(Tag::GdalNodata), None);
 
         // We don't currently support reading strip images
         // let DecodingResult::I16(data) = decoder.read_image().unwrap() else {
@@ -1,8 +1,12 @@
 extern crate tiff;
 
-use async_tiff::tiff::tags::PhotometricInterpretation;
+use std::sync::Arc;
 
-use crate::image_tiff::util::open_tiff;
+use async_tiff::decoder::{DecodeStats, DecoderRegistry, DecodingResult};
+use async_tiff::tiff::tags::{CompressionMethod, PhotometricInterpretation, Predictor};
+use async_tiff::Window;
+
+use crate::image_tiff::util::{open_reader, open_tiff};
 
 #[tokio::test]
 async fn cmyk_u8() {
@@ -57,6 +61,8 @@ async fn test_gray_u12() {
         PhotometricInterpretation::BlackIsZero
     ));
     assert!(ifd.bits_per_sample().iter().all(|x| *x == 12));
+    assert_eq!(ifd.x_resolution_rational(), Some((72, 1)));
+    assert_eq!(ifd.y_resolution(), Some(72.0));
 }
 
 #[tokio::test]
@@ -240,6 +246,38 @@ async fn test_string_tags() {
     }
 }
 
+#[tokio::test]
+async fn test_check_overviews() {
+    // A file without overviews is trivially consistent
+    let tiff = open_tiff("tiled-rgb-u8.tif").await;
+    let report = tiff.check_overviews();
+    assert!(report.overview_indices().is_empty());
+    assert!(report.is_valid());
+}
+
+#[tokio::test]
+async fn test_decode_stats() {
+    let filename = "tiled-rgb-u8.tif";
+    let tiff = open_tiff(filename).await;
+    let reader = open_reader(filename);
+    let ifd = &tiff.ifds()[0];
+
+    let stats = Arc::new(DecodeStats::new());
+    let registry = DecoderRegistry::default().with_stats(stats.clone());
+    let tile = ifd.fetch_tile(0, 0, &reader).await.unwrap();
+    let compressed_len = tile.compressed_bytes().len() as u64;
+    let decoded = tile.decode(&registry).unwrap();
+
+    let decompression = stats.decompression()[&ifd.compression()];
+    assert_eq!(decompression.count, 1);
+    assert_eq!(decompression.input_bytes, compressed_len);
+    assert_eq!(decompression.output_bytes, decoded.len() as u64);
+    assert_eq!(stats.predictor().values().map(|s| s.count).sum::<u64>(), 1);
+
+    stats.reset();
+    assert!(stats.decompression().is_empty());
+}
+
 // #[test]
 // fn test_decode_data() {
 //     let mut image_data = Vec::new();
@@ -623,9 +661,101 @@ async fn test_string_tags() {
 //     test_image_sum_f32("predictor-3-gray-f32.tif", ColorType::Gray(32), 20008.275);
 // }
 
-// #[test]
-// #[cfg(feature = "zstd")]
-// fn test_zstd_compression() {
-//     // gdal_translate -co COMPRESS=ZSTD -co ZSTD_LEVEL=20 int16.tif int16_zstd.tif
-//     test_image_sum_i16("int16_zstd.tif", ColorType::Gray(16), 354396);
-// }
+async fn decode_first_strip(filename: &str) -> Vec<u8> {
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    assert_eq!(ifd.strip_count(), Some(1));
+    let strip = ifd.fetch_strip(0, reader.as_ref()).await.unwrap();
+    strip.decode(&DecoderRegistry::default()).unwrap().to_vec()
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn test_zstd_compression() {
+    // gdal_translate -co COMPRESS=ZSTD -co ZSTD_LEVEL=20 int16.tif int16_zstd.tif
+    let tiff = open_tiff("int16_zstd.tif").await;
+    assert_eq!(tiff.ifds()[0].compression(), CompressionMethod::ZSTD);
+    let expected = decode_first_strip("int16.tif").await;
+    assert_eq!(decode_first_strip("int16_zstd.tif").await, expected);
+
+    // The strips of these were recompressed with `zstd -19`, keeping their predictors
+    let tiff = open_tiff("int16_zstd_pred2.tif").await;
+    assert_eq!(tiff.ifds()[0].predictor(), Some(Predictor::Horizontal));
+    assert_eq!(decode_first_strip("int16_zstd_pred2.tif").await, expected);
+
+    let tiff = open_tiff("predictor-3-gray-f32-zstd.tif").await;
+    assert_eq!(tiff.ifds()[0].predictor(), Some(Predictor::FloatingPoint));
+    assert_eq!(
+        decode_first_strip("predictor-3-gray-f32-zstd.tif").await,
+        decode_first_strip("predictor-3-gray-f32.tif").await
+    );
+}
+
+/// Decode the whole bilevel image of `filename` with one byte per pixel.
+async fn decode_bilevel(filename: &str) -> Vec<u8> {
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let registry = DecoderRegistry::default();
+    if ifd.tile_count().is_some() {
+        let window = Window::new(0, 0, ifd.image_width(), ifd.image_height());
+        let data = ifd.read_window(window, &reader, &registry).await.unwrap();
+        return data.into_data();
+    }
+
+    let mut pixels = vec![];
+    for index in 0..ifd.strip_count().unwrap() {
+        let strip = ifd.fetch_strip(index, reader.as_ref()).await.unwrap();
+        let DecodingResult::U8(samples) = strip.decode_typed(&registry).unwrap() else {
+            panic!("Expected u8 samples");
+        };
+        pixels.extend_from_slice(&samples);
+    }
+    pixels
+}
+
+#[tokio::test]
+async fn test_ccitt_compression() {
+    // A 203x157 bilevel image written by libtiff with each compression
+    let expected = decode_bilevel("bilevel-none.tif").await;
+    assert_eq!(expected.len(), 203 * 157);
+    assert!(expected.iter().all(|v| *v <= 1));
+    for (filename, compression) in [
+        ("bilevel-ccittrle.tif", CompressionMethod::Huffman),
+        ("bilevel-fax3-1d.tif", CompressionMethod::Fax3),
+        ("bilevel-fax3-2d-lsb.tif", CompressionMethod::Fax3),
+        ("bilevel-fax4.tif", CompressionMethod::Fax4),
+    ] {
+        let tiff = open_tiff(filename).await;
+        assert_eq!(tiff.ifds()[0].compression(), compression);
+        assert_eq!(decode_bilevel(filename).await, expected, "{filename}");
+    }
+
+    // Tiled, with inverted pixels stored as BlackIsZero
+    let filename = "bilevel-fax4-tiled-minisblack.tif";
+    let tiff = open_tiff(filename).await;
+    assert_eq!(
+        tiff.ifds()[0].photometric_interpretation(),
+        PhotometricInterpretation::BlackIsZero
+    );
+    let inverted = expected.iter().map(|v| 1 - v).collect::<Vec<_>>();
+    assert_eq!(decode_bilevel(filename).await, inverted);
+}
+
+#[tokio::test]
+async fn test_tiled_jpeg_tables_consistent() {
+    let filename = "tiled-jpeg-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let (tiles_across, tiles_down) = ifd.tile_count().unwrap();
+    let (x, y): (Vec<_>, Vec<_>) = (0..tiles_down)
+        .flat_map(|y| (0..tiles_across).map(move |x| (x, y)))
+        .unzip();
+    let tiles = ifd.fetch_tiles(&x, &y, reader.as_ref()).await.unwrap();
+
+    let report = ifd.check_jpeg_tables(&tiles);
+    assert_eq!(report.tiles_checked(), tiles.len());
+    assert!(report.is_valid(), "{:?}", report.issues());
+}
@@ -0,0 +1,120 @@
+extern crate tiff;
+
+use std::fs::File;
+
+use async_tiff::decoder::DecoderRegistry;
+use tiff::decoder::{Decoder, DecodingResult};
+
+use crate::image_tiff::util::{image_path, open_reader, open_tiff};
+
+#[tokio::test]
+async fn test_decode_single_strip_row_groups() {
+    let filename = "predictor-3-gray-f32.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    assert_eq!(ifd.strip_count(), Some(1));
+
+    let file = File::open(image_path(filename)).unwrap();
+    let expected = match Decoder::new(file).unwrap().read_image().unwrap() {
+        DecodingResult::F32(data) => data,
+        _ => panic!("Expected f32 data"),
+    };
+
+    let registry = DecoderRegistry::default();
+    let strip = ifd.fetch_strip(0, reader.as_ref()).await.unwrap();
+    let mut next_row = 0;
+    let mut decoded = vec![];
+    strip
+        .decode_row_groups(&registry, 7, |group| {
+            assert_eq!(group.row(), next_row);
+            assert!(group.num_rows() <= 7);
+            next_row += group.num_rows();
+            decoded.extend_from_slice(group.data());
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(next_row, ifd.image_height());
+
+    let decoded = decoded
+        .chunks_exact(4)
+        .map(|v| f32::from_ne_bytes(v.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(decoded, expected);
+}
+
+#[tokio::test]
+async fn test_decode_strips_row_groups_match_decode() {
+    let filename = "planar-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let strip_count = ifd.strip_count().unwrap();
+
+    let registry = DecoderRegistry::default();
+    let indices = (0..strip_count).collect::<Vec<_>>();
+    let strips = ifd.fetch_strips(&indices, reader.as_ref()).await.unwrap();
+    for (strip, &index) in strips.into_iter().zip(&indices) {
+        let expected = ifd
+            .fetch_strip(index, reader.as_ref())
+            .await
+            .unwrap()
+            .decode(&registry)
+            .unwrap();
+        let mut decoded = vec![];
+        strip
+            .decode_row_groups(&registry, 4, |group| {
+                decoded.extend_from_slice(group.data());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(decoded, expected);
+    }
+
+    assert!(ifd.fetch_strip(strip_count, reader.as_ref()).await.is_err());
+    let err = ifd.fetch_tile(0, 0, reader.as_ref()).await.unwrap_err();
+    assert!(err.to_string().contains("fetch_strip"), "{err}");
+}
+
+#[tokio::test]
+async fn test_row_groups_iterator() {
+    let filename = "issue_69_lzw.tiff";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let registry = DecoderRegistry::default();
+    let expected = ifd
+        .fetch_strip(0, reader.as_ref())
+        .await
+        .unwrap()
+        .decode(&registry)
+        .unwrap();
+
+    let strip = ifd.fetch_strip(0, reader.as_ref()).await.unwrap();
+    let groups = strip.row_groups(&registry, 3).unwrap();
+    let rows = groups.rows();
+    let groups = groups.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(groups.len(), rows.div_ceil(3) as usize);
+    let decoded = groups
+        .iter()
+        .flat_map(|group| group.data().to_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(decoded, expected);
+}
+
+#[tokio::test]
+async fn test_row_groups_stop_after_error() {
+    // PackBits has no streaming decompressor, so it's decoded in full on the first group, which
+    // fails as the default registry has no PackBits decoder.
+    let filename = "issue_69_packbits.tiff";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let registry = DecoderRegistry::default();
+    let strip = ifd.fetch_strip(0, reader.as_ref()).await.unwrap();
+    let mut groups = strip.row_groups(&registry, 3).unwrap();
+    assert!(groups.next().unwrap().is_err());
+    assert!(groups.next().is_none());
+}
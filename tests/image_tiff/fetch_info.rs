@@ -0,0 +1,33 @@
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+#[tokio::test]
+async fn test_fetch_info() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    // Not recorded unless enabled
+    let tile = ifd.fetch_tile(0, 0, reader.as_ref()).await.unwrap();
+    assert!(tile.fetch_info().is_none());
+
+    let ifd = ifd.clone().with_fetch_info(true);
+    let tile = ifd.fetch_tile(1, 0, reader.as_ref()).await.unwrap();
+    let info = tile.fetch_info().unwrap();
+    let offset = ifd.tile_offsets().unwrap()[1];
+    let byte_count = ifd.tile_byte_counts().unwrap()[1];
+    assert_eq!(info.byte_range(), offset..offset + byte_count);
+    assert_eq!(info.bytes(), tile.compressed_bytes().len() as u64);
+    assert_eq!(info.batch_len(), 1);
+
+    let tiles = ifd
+        .fetch_tiles(&[0, 1], &[0, 0], reader.as_ref())
+        .await
+        .unwrap();
+    for tile in &tiles {
+        let info = tile.fetch_info().unwrap();
+        assert_eq!(info.bytes(), tile.compressed_bytes().len() as u64);
+        assert_eq!(info.batch_len(), 2);
+        assert_eq!(info.duration(), tiles[0].fetch_info().unwrap().duration());
+    }
+}
@@ -0,0 +1,90 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use async_tiff::decoder::{DecodePool, DecoderRegistry};
+use async_tiff::error::{AsyncTiffError, AsyncTiffResult};
+use async_tiff::reader::AsyncFileReader;
+use async_tiff::ByteTransform;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+/// Fails every read that starts at `offset`, as if that object range were missing.
+#[derive(Debug)]
+struct FailAt {
+    inner: Arc<dyn AsyncFileReader>,
+    offset: u64,
+}
+
+impl AsyncFileReader for FailAt {
+    fn get_bytes(&self, range: Range<u64>) -> BoxFuture<'_, AsyncTiffResult<Bytes>> {
+        async move {
+            if range.start == self.offset {
+                return Err(AsyncTiffError::General("missing range".to_string()));
+            }
+            self.inner.get_bytes(range).await
+        }
+        .boxed()
+    }
+}
+
+/// Replaces the compressed bytes of tile (1, 0) with garbage.
+#[derive(Debug)]
+struct Corrupt;
+
+impl ByteTransform for Corrupt {
+    fn transform(&self, x: usize, y: usize, _offset: u64, bytes: Bytes) -> AsyncTiffResult<Bytes> {
+        if (x, y) == (1, 0) {
+            Ok(Bytes::from_static(&[0xff; 4]))
+        } else {
+            Ok(bytes)
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_fetch_tiles_isolated() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let failing = FailAt {
+        inner: reader.clone(),
+        offset: ifd.tile_offsets().unwrap()[1],
+    };
+    let (x, y) = (vec![0, 1, 99], vec![0, 0, 0]);
+    assert!(ifd.fetch_tiles(&x, &y, &failing).await.is_err());
+
+    let tiles = ifd.fetch_tiles_isolated(&x, &y, &failing).await;
+    assert_eq!(tiles.len(), 3);
+    let expected = ifd.fetch_tile(0, 0, reader.as_ref()).await.unwrap();
+    assert_eq!(
+        tiles[0].as_ref().unwrap().compressed_bytes(),
+        expected.compressed_bytes()
+    );
+    assert!(tiles[1].is_err());
+    assert!(matches!(tiles[2], Err(AsyncTiffError::TileIndexError(..))));
+}
+
+#[tokio::test]
+async fn test_decode_tiles_isolated() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = tiff.ifds()[0]
+        .clone()
+        .with_byte_transform(Arc::new(Corrupt));
+    let registry = Arc::new(DecoderRegistry::default());
+    let pool = DecodePool::new(2).unwrap();
+
+    let tiles = ifd
+        .fetch_tiles(&[0, 1], &[0, 0], reader.as_ref())
+        .await
+        .unwrap();
+    let decoded = pool.decode_tiles_isolated(tiles, registry).await;
+    assert!(decoded[0].is_ok());
+    assert!(decoded[1].is_err());
+}
@@ -1,4 +1,14 @@
+mod byte_transform;
 mod decode_bigtiff_images;
+mod decode_fp16;
 mod decode_geotiff_images;
 mod decode_images;
+mod decode_strips;
+mod fetch_info;
+mod isolated_tiles;
+mod pipeline;
+mod raw_tiles;
+mod read_window;
+mod tile_stream;
+mod truncated_tiles;
 mod util;
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use async_tiff::decoder::{DecodePool, DecoderRegistry};
+use async_tiff::pipeline::Pipeline;
+use futures::TryStreamExt;
+
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+#[tokio::test]
+async fn test_pipeline_matches_fetch_and_decode() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = tiff.ifds()[0].clone();
+    let registry = DecoderRegistry::default();
+
+    let (tiles_x, tiles_y) = ifd.tile_count().unwrap();
+    let tiles = (0..tiles_y)
+        .flat_map(|y| (0..tiles_x).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+
+    let mut expected = HashMap::new();
+    for &(x, y) in &tiles {
+        let tile = ifd.fetch_tile(x, y, reader.as_ref()).await.unwrap();
+        expected.insert((x, y), tile.decode(&registry).unwrap());
+    }
+
+    let pipeline = Pipeline::builder(ifd, reader)
+        .with_fetch_batch_size(2)
+        .with_channel_capacity(1)
+        .with_pool(DecodePool::new(2).unwrap())
+        .with_post_process(|mut tile| {
+            tile.data = tile.data.iter().map(|v| 255 - v).collect::<Vec<_>>().into();
+            Ok(tile)
+        })
+        .build();
+    let decoded = pipeline
+        .run(tiles.clone())
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+    assert_eq!(decoded.len(), tiles.len());
+    for tile in decoded {
+        let inverted = expected[&(tile.x, tile.y)]
+            .iter()
+            .map(|v| 255 - v)
+            .collect::<Vec<_>>();
+        assert_eq!(tile.data, inverted);
+    }
+}
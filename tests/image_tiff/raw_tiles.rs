@@ -0,0 +1,67 @@
+use async_tiff::decoder::{Decoder, DecoderRegistry, JPEGDecoder};
+use async_tiff::error::AsyncTiffError;
+use async_tiff::tiff::tags::CompressionMethod;
+use async_tiff::TilePayload;
+
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+#[tokio::test]
+async fn test_fetch_tile_raw() {
+    let filename = "tiled-jpeg-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let raw = ifd.fetch_tile_raw(1, 0, reader.as_ref()).await.unwrap();
+    let tile = ifd.fetch_tile(1, 0, reader.as_ref()).await.unwrap();
+    assert_eq!((raw.x(), raw.y()), (1, 0));
+    assert_eq!(raw.compressed_bytes(), tile.compressed_bytes());
+    assert_eq!(raw.compression_method(), CompressionMethod::ModernJPEG);
+    assert_eq!(raw.jpeg_tables(), tile.jpeg_tables());
+    assert!(raw.fetch_info().is_none());
+    assert_eq!(raw.payload(), TilePayload::JpegWithSharedTables);
+    assert_eq!(ifd.tile_payload(), raw.payload());
+
+    let (x_count, _) = ifd.tile_count().unwrap();
+    assert!(matches!(
+        ifd.fetch_tile_raw(x_count, 0, reader.as_ref()).await,
+        Err(AsyncTiffError::TileIndexError(..))
+    ));
+}
+
+#[tokio::test]
+async fn test_tile_payload() {
+    let tiff = open_tiff("tiled-rgb-u8.tif").await;
+    let payload = tiff.ifds()[0].tile_payload();
+    assert_eq!(payload, TilePayload::Samples);
+    assert_eq!(payload.mime_type(), None);
+}
+
+#[tokio::test]
+async fn test_image_bytes() {
+    let filename = "tiled-jpeg-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let registry = DecoderRegistry::default();
+
+    let raw = ifd.fetch_tile_raw(0, 0, reader.as_ref()).await.unwrap();
+    let image = raw.image_bytes().unwrap();
+    assert_eq!(image[..2], [0xFF, 0xD8]);
+    assert!(image.len() > raw.compressed_bytes().len());
+
+    // The merged JPEG decodes on its own to the same pixels as the tile
+    let decoded = JPEGDecoder
+        .decode_tile(image, ifd.photometric_interpretation(), None)
+        .unwrap();
+    let tile = ifd.fetch_tile(0, 0, reader.as_ref()).await.unwrap();
+    assert_eq!(decoded, tile.decode(&registry).unwrap());
+
+    let tiff = open_tiff("tiled-rgb-u8.tif").await;
+    let reader = open_reader("tiled-rgb-u8.tif");
+    let raw = tiff.ifds()[0]
+        .fetch_tile_raw(0, 0, reader.as_ref())
+        .await
+        .unwrap();
+    assert!(raw.image_bytes().is_err());
+}
@@ -0,0 +1,260 @@
+extern crate tiff;
+
+use std::fs::File;
+
+use async_tiff::decoder::{DecodeLimits, DecoderRegistry};
+use async_tiff::error::AsyncTiffError;
+use async_tiff::{ReadWindowOptions, RowOrigin, SampleLayout, Window};
+use tiff::decoder::{Decoder, DecodingResult};
+
+use crate::image_tiff::util::{image_path, open_reader, open_tiff};
+
+fn decode_with_image_tiff(filename: &str) -> Vec<u8> {
+    let file = File::open(image_path(filename)).unwrap();
+    let mut decoder = Decoder::new(file).unwrap();
+    match decoder.read_image().unwrap() {
+        DecodingResult::U8(data) => data,
+        _ => panic!("Expected u8 data"),
+    }
+}
+
+#[tokio::test]
+async fn test_read_window_spanning_tiles() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let expected = decode_with_image_tiff(filename);
+    let image_width = ifd.image_width() as usize;
+
+    // Spans several tiles, including the padded edge tiles
+    let window = Window::new(20, 470, 354, 29);
+    let data = ifd
+        .read_window(window, &reader, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(data.samples_per_pixel(), 3);
+    assert_eq!(data.bytes_per_sample(), 1);
+
+    let row_len = window.width() as usize * 3;
+    for (i, row) in data.data().chunks_exact(row_len).enumerate() {
+        let start = ((window.row_off() as usize + i) * image_width + window.col_off() as usize) * 3;
+        assert_eq!(row, &expected[start..start + row_len]);
+    }
+
+    // Including windows whose far edge overflows a u32
+    for out_of_bounds in [
+        Window::new(300, 0, 100, 10),
+        Window::new(u32::MAX - 5, 0, 10, 10),
+        Window::new(0, 10, 10, u32::MAX),
+    ] {
+        assert!(ifd
+            .read_window(out_of_bounds, &reader, &Default::default())
+            .await
+            .is_err());
+    }
+}
+
+#[tokio::test]
+async fn test_read_window_spanning_strips() {
+    let filename = "rgb-3c-8b.tiff";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    assert!(ifd.strip_count().unwrap() > 1);
+    let expected = decode_with_image_tiff(filename);
+    let image_width = ifd.image_width() as usize;
+
+    // Spans several strips, including the last
+    let strip_height = ifd.strip_height().unwrap();
+    let row_off = strip_height - 1;
+    let window = Window::new(3, row_off, 20, ifd.image_height() - row_off);
+    let data = ifd
+        .read_window(window, &reader, &Default::default())
+        .await
+        .unwrap();
+
+    let row_len = window.width() as usize * 3;
+    for (i, row) in data.data().chunks_exact(row_len).enumerate() {
+        let start = ((window.row_off() as usize + i) * image_width + window.col_off() as usize) * 3;
+        assert_eq!(row, &expected[start..start + row_len]);
+    }
+}
+
+#[tokio::test]
+async fn test_read_window_planar() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let window = Window::new(30, 40, 50, 20);
+    let interleaved = ifd
+        .read_window(window, &reader, &Default::default())
+        .await
+        .unwrap();
+    let options = ReadWindowOptions::new().with_layout(SampleLayout::Planar);
+    let planar = ifd
+        .read_window_with_options(window, &options, &reader, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(planar.layout(), SampleLayout::Planar);
+    assert_eq!(planar.data().len(), interleaved.data().len());
+    assert!(interleaved.band(0).is_none());
+    for band in 0..3 {
+        let expected = interleaved
+            .data()
+            .iter()
+            .skip(band)
+            .step_by(3)
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(planar.band(band).unwrap(), expected);
+    }
+    assert!(planar.band(3).is_none());
+}
+
+#[tokio::test]
+async fn test_read_window_bottom_left_origin() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let window = Window::new(30, 40, 50, 20);
+    let top_down = ifd
+        .read_window(window, &reader, &Default::default())
+        .await
+        .unwrap();
+    let options = ReadWindowOptions::new()
+        .with_origin(RowOrigin::BottomLeft)
+        .with_transform(true);
+    let bottom_up = ifd
+        .read_window_with_options(window, &options, &reader, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(bottom_up.origin(), RowOrigin::BottomLeft);
+    // Not a GeoTIFF
+    assert!(bottom_up.transform().is_none());
+
+    let row_len = 50 * 3;
+    let flipped = top_down
+        .data()
+        .chunks_exact(row_len)
+        .rev()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(bottom_up.data(), flipped);
+}
+
+#[tokio::test]
+async fn test_read_window_bilevel() {
+    let filename = "tiled-gray-i1.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let image_width = ifd.image_width() as usize;
+
+    let full = ifd
+        .read_window(
+            Window::new(0, 0, ifd.image_width(), ifd.image_height()),
+            &reader,
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+    assert!(full.data().iter().all(|v| *v <= 1));
+
+    // Compare the first tile against its unpacked bits
+    let tile_width = ifd.tile_width().unwrap() as usize;
+    let tile = ifd.fetch_tile(0, 0, &reader).await.unwrap();
+    let packed = tile.decode(&Default::default()).unwrap();
+    let packed_row_len = tile_width.div_ceil(8);
+    for row in 0..ifd.tile_height().unwrap() as usize {
+        for col in 0..tile_width {
+            let bit = (packed[row * packed_row_len + col / 8] >> (7 - col % 8)) & 1;
+            assert_eq!(full.data()[row * image_width + col], bit);
+        }
+    }
+
+    // Unaligned windows agree with the full read
+    let window = Window::new(3, 7, 30, 40);
+    let data = ifd
+        .read_window(window, &reader, &Default::default())
+        .await
+        .unwrap();
+    for (i, row) in data.data().chunks_exact(30).enumerate() {
+        let start = (7 + i) * image_width + 3;
+        assert_eq!(row, &full.data()[start..start + 30]);
+    }
+}
+
+#[tokio::test]
+async fn test_read_window_masked() {
+    let reader = open_reader("tiled-rgb-u8.tif");
+    let tiff = open_tiff("tiled-rgb-u8.tif").await;
+    let ifd = &tiff.ifds()[0];
+    let window = Window::new(10, 20, 100, 50);
+
+    // Without a mask or nodata value every pixel is valid
+    let masked = ifd
+        .read_window_masked(None, window, &reader, &Default::default())
+        .await
+        .unwrap();
+    assert!(masked.mask().iter().all(|valid| *valid));
+
+    // A bilevel image used as its own mask
+    let reader = open_reader("tiled-gray-i1.tif");
+    let tiff = open_tiff("tiled-gray-i1.tif").await;
+    let ifd = &tiff.ifds()[0];
+    let window = Window::new(5, 10, 30, 40);
+    let mut masked = ifd
+        .read_window_masked(Some(ifd), window, &reader, &Default::default())
+        .await
+        .unwrap();
+    assert!(masked.mask().iter().any(|valid| !valid));
+    for (value, valid) in masked.data().data().iter().zip(masked.mask()) {
+        assert_eq!(*valid, *value != 0);
+    }
+
+    masked.fill_masked(7.0).unwrap();
+    for (value, valid) in masked.data().data().iter().zip(masked.mask()) {
+        assert_eq!(*value, if *valid { 1 } else { 7 });
+    }
+}
+
+#[tokio::test]
+async fn test_decode_limits() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let window = Window::new(0, 0, 10, 10);
+
+    let registry = DecoderRegistry::default()
+        .with_limits(DecodeLimits::default().with_max_output_bytes(10 * 10 * 3));
+    assert!(ifd.read_window(window, &reader, &registry).await.is_ok());
+    let err = ifd
+        .read_window(Window::new(0, 0, 10, 11), &reader, &registry)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        AsyncTiffError::LimitExceeded {
+            what: "Output size",
+            size: 330,
+            limit: 300,
+        }
+    ));
+
+    let tile_pixels = ifd.tile_width().unwrap() as u64 * ifd.tile_height().unwrap() as u64;
+    let registry = DecoderRegistry::default()
+        .with_limits(DecodeLimits::default().with_max_tile_pixels(tile_pixels - 1));
+    assert!(ifd.read_window(window, &reader, &registry).await.is_err());
+    let tile = ifd.fetch_tile(0, 0, reader.as_ref()).await.unwrap();
+    assert!(matches!(
+        tile.decode_typed(&registry),
+        Err(AsyncTiffError::LimitExceeded { .. })
+    ));
+}
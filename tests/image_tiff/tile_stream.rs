@@ -0,0 +1,49 @@
+use futures::TryStreamExt;
+
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+#[tokio::test]
+async fn test_tile_stream() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let (x_count, y_count) = ifd.tile_count().unwrap();
+    let (x, y): (Vec<_>, Vec<_>) = (0..y_count)
+        .flat_map(|y| (0..x_count).map(move |x| (x, y)))
+        .unzip();
+    let expected = ifd.fetch_tiles(&x, &y, reader.as_ref()).await.unwrap();
+    for concurrency in [0, 1, 3] {
+        let tiles = ifd
+            .tile_stream_with_concurrency(reader.as_ref(), concurrency)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(tiles.len(), expected.len());
+        for (tile, expected) in tiles.iter().zip(&expected) {
+            assert_eq!((tile.x(), tile.y()), (expected.x(), expected.y()));
+            assert_eq!(tile.compressed_bytes(), expected.compressed_bytes());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tile_stream_strips() {
+    let filename = "rgb-3c-8b.tiff";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+
+    let strips = ifd
+        .tile_stream(reader.as_ref())
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(strips.len(), ifd.strip_count().unwrap());
+    for (index, strip) in strips.iter().enumerate() {
+        let expected = ifd.fetch_strip(index, reader.as_ref()).await.unwrap();
+        assert_eq!(strip.y(), index);
+        assert_eq!(strip.compressed_bytes(), expected.compressed_bytes());
+    }
+}
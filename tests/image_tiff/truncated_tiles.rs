@@ -0,0 +1,55 @@
+use async_tiff::decoder::DecoderRegistry;
+use async_tiff::error::AsyncTiffError;
+use async_tiff::TruncatedTiles;
+
+use crate::image_tiff::util::{open_reader, open_tiff};
+
+#[tokio::test]
+async fn test_fetch_truncated_tiles() {
+    let filename = "tiled-rgb-u8.tif";
+    let reader = open_reader(filename);
+    let tiff = open_tiff(filename).await;
+    let ifd = &tiff.ifds()[0];
+    let registry = DecoderRegistry::default();
+
+    // Pretend the upload stopped halfway through the tile stored last in the file.
+    let offsets = ifd.tile_offsets().unwrap();
+    let byte_counts = ifd.tile_byte_counts().unwrap();
+    let last = (0..offsets.len()).max_by_key(|i| offsets[*i]).unwrap();
+    let file_size = offsets[last] + byte_counts[last] / 2;
+    let (tiles_across, _) = ifd.tile_count().unwrap();
+    let (x, y) = (vec![0, last % tiles_across], vec![0, last / tiles_across]);
+
+    let expected = ifd
+        .fetch_tiles(&x, &y, reader.as_ref())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tile| tile.decode(&registry).unwrap())
+        .collect::<Vec<_>>();
+
+    let err = ifd
+        .fetch_tiles_with_file_size(&x, &y, reader.as_ref(), file_size, TruncatedTiles::Error)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AsyncTiffError::TruncatedTile { .. }));
+
+    for truncated in [TruncatedTiles::Partial, TruncatedTiles::FillNodata] {
+        let tiles = ifd
+            .fetch_tiles_with_file_size(&x, &y, reader.as_ref(), file_size, truncated)
+            .await
+            .unwrap();
+        assert!(!tiles[0].is_truncated());
+        assert!(tiles[1].is_truncated());
+
+        let decoded = tiles
+            .into_iter()
+            .map(|tile| tile.decode(&registry).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(decoded[0], expected[0]);
+        assert_eq!(decoded[1].len(), expected[1].len());
+        if truncated == TruncatedTiles::FillNodata {
+            assert!(decoded[1].iter().all(|b| *b == 0));
+        }
+    }
+}
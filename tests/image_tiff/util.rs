@@ -6,13 +6,21 @@ use async_tiff::reader::{AsyncFileReader, ObjectReader};
 use async_tiff::TIFF;
 use object_store::local::LocalFileSystem;
 
-const TEST_IMAGE_DIR: &str = "tests/image_tiff/images/";
+pub(crate) const TEST_IMAGE_DIR: &str = "tests/image_tiff/images/";
 
-pub(crate) async fn open_tiff(filename: &str) -> TIFF {
+/// The path of a test image, relative to the crate root.
+pub(crate) fn image_path(filename: &str) -> String {
+    format!("{TEST_IMAGE_DIR}/{filename}")
+}
+
+pub(crate) fn open_reader(filename: &str) -> Arc<dyn AsyncFileReader> {
     let store = Arc::new(LocalFileSystem::new_with_prefix(current_dir().unwrap()).unwrap());
-    let path = format!("{TEST_IMAGE_DIR}/{filename}");
-    let reader = Arc::new(ObjectReader::new(store.clone(), path.as_str().into()))
-        as Arc<dyn AsyncFileReader>;
+    let path = image_path(filename);
+    Arc::new(ObjectReader::new(store.clone(), path.as_str().into()))
+}
+
+pub(crate) async fn open_tiff(filename: &str) -> TIFF {
+    let reader = open_reader(filename);
     let mut metadata_reader = TiffMetadataReader::try_open(&reader).await.unwrap();
     let ifds = metadata_reader.read_all_ifds(&reader).await.unwrap();
     TIFF::new(ifds)
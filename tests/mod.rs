@@ -1 +1,4 @@
+// The fixtures are read with `ObjectReader`.
+#![cfg(feature = "object_store")]
+
 mod image_tiff;
@@ -1,3 +1,6 @@
+// The fixtures are fetched with `ObjectReader` over HTTP.
+#![cfg(all(feature = "object_store", feature = "reqwest"))]
+
 /// Integration tests on OME-TIFF files.
 use async_tiff::tiff::tags::PhotometricInterpretation;
 
@@ -0,0 +1,34 @@
+//! The stable `tags` module names the same types as the vendored `tiff` module.
+
+use std::any::TypeId;
+
+use async_tiff::tags::{
+    CompressionMethod, PhotometricInterpretation, Predictor, SampleFormat, Tag, Type, Value,
+};
+use async_tiff::tiff;
+
+#[test]
+fn test_tags_reexport_tiff_types() {
+    assert_eq!(TypeId::of::<Tag>(), TypeId::of::<tiff::tags::Tag>());
+    assert_eq!(TypeId::of::<Type>(), TypeId::of::<tiff::tags::Type>());
+    assert_eq!(
+        TypeId::of::<CompressionMethod>(),
+        TypeId::of::<tiff::tags::CompressionMethod>()
+    );
+    assert_eq!(
+        TypeId::of::<PhotometricInterpretation>(),
+        TypeId::of::<tiff::tags::PhotometricInterpretation>()
+    );
+    assert_eq!(
+        TypeId::of::<Predictor>(),
+        TypeId::of::<tiff::tags::Predictor>()
+    );
+    assert_eq!(
+        TypeId::of::<SampleFormat>(),
+        TypeId::of::<tiff::tags::SampleFormat>()
+    );
+    assert_eq!(TypeId::of::<Value>(), TypeId::of::<tiff::Value>());
+
+    let tag: tiff::tags::Tag = Tag::ImageWidth;
+    assert_eq!(tag, tiff::tags::Tag::ImageWidth);
+}